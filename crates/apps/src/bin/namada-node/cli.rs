@@ -11,9 +11,13 @@ pub fn main() -> Result<()> {
     match cmd {
         cmds::NamadaNode::Ledger(sub) => match sub {
             cmds::Ledger::Run(cmds::LedgerRun(args)) => {
-                let chain_ctx = ctx.take_chain_or_exit();
+                let mut chain_ctx = ctx.take_chain_or_exit();
                 let wasm_dir = chain_ctx.wasm_dir();
                 sleep_until(args.start_time);
+                chain_ctx.config.ledger.shell.storage_stats |=
+                    args.storage_stats;
+                chain_ctx.config.ledger.shell.gas_trace |= args.gas_trace;
+                chain_ctx.config.ledger.shell.audit_ibc |= args.audit_ibc;
                 ledger::run(chain_ctx.config.ledger, wasm_dir);
             }
             cmds::Ledger::RunUntil(cmds::LedgerRunUntil(args)) => {
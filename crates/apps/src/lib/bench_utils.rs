@@ -62,13 +62,13 @@ use namada::ibc::primitives::proto::{Any, Protobuf};
 use namada::ibc::primitives::{Msg, Timestamp as IbcTimestamp};
 use namada::ibc::storage::port_key;
 use namada::io::StdIo;
-use namada::ledger::dry_run_tx;
 use namada::ledger::gas::TxGasMeter;
 use namada::ledger::ibc::storage::{channel_key, connection_key};
 use namada::ledger::native_vp::ibc::get_dummy_header;
 use namada::ledger::queries::{
     Client, EncodedResponseQuery, RequestCtx, RequestQuery, Router, RPC,
 };
+use namada::ledger::{dry_run_tx, dry_run_vps};
 use namada::state::StorageRead;
 use namada::tx::data::pos::Bond;
 use namada::tx::data::{TxResult, VpsResult};
@@ -752,6 +752,8 @@ impl Client for BenchShell {
 
         if request.path == "/shell/dry_run_tx" {
             dry_run_tx(ctx, &request)
+        } else if request.path == "/shell/dry_run_vps" {
+            dry_run_vps(ctx, &request)
         } else {
             RPC.handle(ctx, &request)
         }
@@ -860,7 +862,8 @@ impl Client for BenchShell {
                             changed_keys: changed_keys.to_owned(),
                             vps_result: VpsResult::default(),
                             initialized_accounts: vec![],
-                            ibc_events: BTreeSet::default(),
+                            ibc_events: Vec::new(),
+                            events: Vec::new(),
                             eth_bridge_events: BTreeSet::default(),
                         };
                         namada::tendermint::abci::Event {
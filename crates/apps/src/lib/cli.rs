@@ -859,6 +859,9 @@ pub mod cmds {
                     // The `run` command is the default if no sub-command given
                     .or(Some(Self::Run(LedgerRun(args::LedgerRun {
                         start_time: None,
+                        storage_stats: false,
+                        gas_trace: false,
+                        audit_ibc: false,
                     }))))
             })
         }
@@ -2974,6 +2977,7 @@ pub mod args {
     pub const ALLOW_DUPLICATE_IP: ArgFlag = flag("allow-duplicate-ip");
     pub const AMOUNT: Arg<token::DenominatedAmount> = arg("amount");
     pub const ARCHIVE_DIR: ArgOpt<PathBuf> = arg_opt("archive-dir");
+    pub const AUDIT_IBC: ArgFlag = flag("audit-ibc");
     pub const AVATAR_OPT: ArgOpt<String> = arg_opt("avatar");
     pub const BALANCE_OWNER: ArgOpt<WalletBalanceOwner> = arg_opt("owner");
     pub const BASE_DIR: ArgDefault<PathBuf> = arg_default(
@@ -3027,6 +3031,8 @@ pub mod args {
     pub const DATA_PATH_OPT: ArgOpt<PathBuf> = arg_opt("data-path");
     pub const DATA_PATH: Arg<PathBuf> = arg("data-path");
     pub const DECRYPT: ArgFlag = flag("decrypt");
+    pub const DELEGATIONS: ArgMulti<WalletAddress, GlobStar> =
+        arg_multi("delegations");
     pub const DESCRIPTION_OPT: ArgOpt<String> = arg_opt("description");
     pub const DISPOSABLE_SIGNING_KEY: ArgFlag = flag("disposable-gas-payer");
     pub const DESTINATION_VALIDATOR: Arg<WalletAddress> =
@@ -3062,6 +3068,7 @@ pub mod args {
     pub const FORCE: ArgFlag = flag("force");
     pub const GAS_LIMIT: ArgDefault<GasLimit> =
         arg_default("gas-limit", DefaultFn(|| GasLimit::from(25_000)));
+    pub const GAS_TRACE: ArgFlag = flag("gas-trace");
     pub const FEE_TOKEN: ArgDefaultFromCtx<WalletAddrOrNativeToken> =
         arg_default_from_ctx("gas-token", DefaultFn(|| "".parse().unwrap()));
     pub const FEE_PAYER: Arg<WalletAddress> = arg("fee-payer");
@@ -3107,6 +3114,8 @@ pub mod args {
     pub const MAX_COMMISSION_RATE_CHANGE: Arg<Dec> =
         arg("max-commission-rate-change");
     pub const MAX_ETH_GAS: ArgOpt<u64> = arg_opt("max_eth-gas");
+    pub const MAX_TOTAL_DELEGATED_OPT: ArgOpt<token::DenominatedAmount> =
+        arg_opt("max-total-delegated");
     pub const MEMO_OPT: ArgOpt<String> = arg_opt("memo");
     pub const MODE: ArgOpt<String> = arg_opt("mode");
     pub const NET_ADDRESS: Arg<SocketAddr> = arg("net-address");
@@ -3173,6 +3182,7 @@ pub mod args {
     pub const STEWARD: Arg<WalletAddress> = arg("steward");
     pub const SOURCE_VALIDATOR: Arg<WalletAddress> = arg("source-validator");
     pub const STORAGE_KEY: Arg<storage::Key> = arg("storage-key");
+    pub const STORAGE_STATS: ArgFlag = flag("storage-stats");
     pub const SUSPEND_ACTION: ArgFlag = flag("suspend");
     pub const TEMPLATES_PATH: Arg<PathBuf> = arg("templates-path");
     pub const TIMEOUT_HEIGHT: ArgOpt<u64> = arg_opt("timeout-height");
@@ -3204,6 +3214,7 @@ pub mod args {
     pub const VALIDATOR_ETH_HOT_KEY: ArgOpt<WalletPublicKey> =
         arg_opt("eth-hot-key");
     pub const VALUE: Arg<String> = arg("value");
+    pub const VOTE_MEMO_OPT: ArgOpt<String> = arg_opt("vote-memo");
     pub const VOTER_OPT: ArgOpt<WalletAddress> = arg_opt("voter");
     pub const VIEWING_KEY: Arg<WalletViewingKey> = arg("key");
     pub const VIEWING_KEYS: ArgMulti<WalletViewingKey, GlobStar> =
@@ -3272,12 +3283,23 @@ pub mod args {
     #[derive(Clone, Debug)]
     pub struct LedgerRun {
         pub start_time: Option<DateTimeUtc>,
+        pub storage_stats: bool,
+        pub gas_trace: bool,
+        pub audit_ibc: bool,
     }
 
     impl Args for LedgerRun {
         fn parse(matches: &ArgMatches) -> Self {
             let start_time = NAMADA_START_TIME.parse(matches);
-            Self { start_time }
+            let storage_stats = STORAGE_STATS.parse(matches);
+            let gas_trace = GAS_TRACE.parse(matches);
+            let audit_ibc = AUDIT_IBC.parse(matches);
+            Self {
+                start_time,
+                storage_stats,
+                gas_trace,
+                audit_ibc,
+            }
         }
 
         fn def(app: App) -> App {
@@ -3289,6 +3311,18 @@ pub mod args {
                  equivalent:\n2023-01-20T12:12:12Z\n2023-01-20 \
                  12:12:12Z\n2023-  01-20T12:  12:12Z",
             ))
+            .arg(STORAGE_STATS.def().help(
+                "Log the statistics of the DB column families on every block \
+                 commit.",
+            ))
+            .arg(GAS_TRACE.def().help(
+                "Break down the gas consumed by the validity predicates of \
+                 each transaction in its event attributes.",
+            ))
+            .arg(AUDIT_IBC.def().help(
+                "Audit the tokens escrowed by IBC against the packet \
+                 accounting on startup and log the discrepancies.",
+            ))
         }
     }
 
@@ -4831,11 +4865,19 @@ pub mod args {
 
     impl CliToSdk<VoteProposal<SdkTypes>> for VoteProposal<CliTypes> {
         fn to_sdk(self, ctx: &mut Context) -> VoteProposal<SdkTypes> {
+            let tx = self.tx.to_sdk(ctx);
+            let chain_ctx = ctx.borrow_chain_or_exit();
             VoteProposal::<SdkTypes> {
-                tx: self.tx.to_sdk(ctx),
+                tx,
                 proposal_id: self.proposal_id,
                 vote: self.vote,
-                voter: ctx.borrow_chain_or_exit().get(&self.voter),
+                memo: self.memo,
+                voter: chain_ctx.get(&self.voter),
+                delegations: self
+                    .delegations
+                    .iter()
+                    .map(|delegation| chain_ctx.get(delegation))
+                    .collect(),
                 is_offline: self.is_offline,
                 proposal_data: self.proposal_data.map(|path| {
                     std::fs::read(path)
@@ -4851,7 +4893,9 @@ pub mod args {
             let tx = Tx::parse(matches);
             let proposal_id = PROPOSAL_ID_OPT.parse(matches);
             let vote = PROPOSAL_VOTE.parse(matches);
+            let memo = VOTE_MEMO_OPT.parse(matches);
             let voter = ADDRESS.parse(matches);
+            let delegations = DELEGATIONS.parse(matches);
             let is_offline = PROPOSAL_OFFLINE.parse(matches);
             let proposal_data = DATA_PATH_OPT.parse(matches);
             let tx_code_path = PathBuf::from(TX_VOTE_PROPOSAL);
@@ -4860,8 +4904,10 @@ pub mod args {
                 tx,
                 proposal_id,
                 vote,
+                memo,
                 is_offline,
                 voter,
+                delegations,
                 proposal_data,
                 tx_code_path,
             }
@@ -4881,6 +4927,15 @@ pub mod args {
                 .arg(PROPOSAL_VOTE.def().help(
                     "The vote for the proposal. Either yay, nay, or abstain.",
                 ))
+                .arg(
+                    VOTE_MEMO_OPT
+                        .def()
+                        .help(
+                            "An optional memo justifying the vote, bounded by \
+                             the governance parameter max_vote_memo_length.",
+                        )
+                        .conflicts_with(PROPOSAL_OFFLINE.name),
+                )
                 .arg(
                     PROPOSAL_OFFLINE
                         .def()
@@ -4898,6 +4953,15 @@ pub mod args {
                         .conflicts_with(PROPOSAL_ID.name),
                 )
                 .arg(ADDRESS.def().help("The address of the voter."))
+                .arg(
+                    DELEGATIONS
+                        .def()
+                        .help(
+                            "The validators of the delegations to vote with. \
+                             Defaults to all the delegations of the voter.",
+                        )
+                        .conflicts_with(PROPOSAL_OFFLINE.name),
+                )
         }
     }
 
@@ -5524,6 +5588,7 @@ pub mod args {
                 discord_handle: self.discord_handle,
                 avatar: self.avatar,
                 commission_rate: self.commission_rate,
+                max_total_delegated: self.max_total_delegated,
                 tx_code_path: self.tx_code_path.to_path_buf(),
             }
         }
@@ -5539,6 +5604,21 @@ pub mod args {
             let discord_handle = DISCORD_OPT.parse(matches);
             let avatar = AVATAR_OPT.parse(matches);
             let commission_rate = COMMISSION_RATE_OPT.parse(matches);
+            let max_total_delegated =
+                MAX_TOTAL_DELEGATED_OPT.parse(matches).map(|amount| {
+                    amount
+                        .canonical()
+                        .increase_precision(NATIVE_MAX_DECIMAL_PLACES.into())
+                        .unwrap_or_else(|e| {
+                            println!(
+                                "Could not parse max total delegated amount: \
+                                 {:?}",
+                                e
+                            );
+                            safe_exit(1);
+                        })
+                        .amount()
+                });
             let tx_code_path = PathBuf::from(TX_CHANGE_METADATA_WASM);
             Self {
                 tx,
@@ -5549,6 +5629,7 @@ pub mod args {
                 discord_handle,
                 avatar,
                 commission_rate,
+                max_total_delegated,
                 tx_code_path,
             }
         }
@@ -5585,6 +5666,11 @@ pub mod args {
                         .def()
                         .help("The desired new commission rate."),
                 )
+                .arg(MAX_TOTAL_DELEGATED_OPT.def().help(
+                    "The desired new cap on the total amount of tokens \
+                     delegated to the validator. To remove the existing cap, \
+                     pass zero to this argument.",
+                ))
         }
     }
 
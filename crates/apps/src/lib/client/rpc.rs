@@ -1365,10 +1365,13 @@ pub async fn query_proposal_result(
         )
         .await;
 
+        let governance_parameters =
+            query_governance_parameters(context.client()).await;
         let proposal_result = compute_proposal_result(
             proposal_votes,
             total_voting_power,
             TallyType::TwoThirds,
+            governance_parameters.threshold_default,
         );
 
         display_line!(
@@ -1491,6 +1494,44 @@ pub async fn query_protocol_parameters(
         "",
         governance_parameters.min_proposal_grace_epochs
     );
+    display_line!(
+        context.io(),
+        "{:4}Max. concurrent proposals per author: {}",
+        "",
+        governance_parameters.max_concurrent_proposals_per_author
+    );
+    display_line!(
+        context.io(),
+        "{:4}Proposal content required fields: {}",
+        "",
+        governance_parameters
+            .proposal_content_required_fields
+            .join(", ")
+    );
+    display_line!(
+        context.io(),
+        "{:4}Max. vote memo length: {}",
+        "",
+        governance_parameters.max_vote_memo_length
+    );
+    display_line!(
+        context.io(),
+        "{:4}Default proposal threshold: {}",
+        "",
+        governance_parameters.threshold_default
+    );
+    display_line!(
+        context.io(),
+        "{:4}PGF steward proposal threshold: {}",
+        "",
+        governance_parameters.threshold_pgf_steward
+    );
+    display_line!(
+        context.io(),
+        "{:4}PGF payment proposal threshold: {}",
+        "",
+        governance_parameters.threshold_pgf_payment
+    );
 
     let pgf_parameters = query_pgf_parameters(context.client()).await;
     display_line!(context.io(), "\nPublic Goods Funding Parameters");
@@ -1626,6 +1667,18 @@ pub async fn query_protocol_parameters(
         "",
         pos_params.validator_stake_threshold
     );
+    display_line!(
+        context.io(),
+        "{:4}Min. delegation amount: {}",
+        "",
+        pos_params.min_delegation_amount
+    );
+    display_line!(
+        context.io(),
+        "{:4}Min. validator self-bond: {}",
+        "",
+        pos_params.min_validator_self_bond
+    );
     display_line!(
         context.io(),
         "{:4}Duplicate vote minimum slash rate: {}",
@@ -1723,9 +1776,10 @@ pub async fn query_unbond_with_slashing<
 pub async fn query_pos_parameters<C: namada::ledger::queries::Client + Sync>(
     client: &C,
 ) -> PosParams {
-    unwrap_client_response::<C, PosParams>(
-        RPC.vp().pos().pos_params(client).await,
-    )
+    rpc::get_pos_params(client).await.unwrap_or_else(|err| {
+        eprintln!("Error in the query: {:?}", err);
+        cli::safe_exit(1)
+    })
 }
 
 pub async fn query_consensus_keys<C: namada::ledger::queries::Client + Sync>(
@@ -2903,7 +2957,8 @@ pub async fn compute_offline_proposal_votes(
     let mut validators_vote: HashMap<Address, TallyVote> = HashMap::default();
     let mut validator_voting_power: HashMap<Address, VotePower> =
         HashMap::default();
-    let mut delegators_vote: HashMap<Address, TallyVote> = HashMap::default();
+    let mut delegators_vote: HashMap<Address, HashMap<Address, TallyVote>> =
+        HashMap::default();
     let mut delegator_voting_power: HashMap<
         Address,
         HashMap<Address, VotePower>,
@@ -2935,7 +2990,9 @@ pub async fn compute_offline_proposal_votes(
                     validators.get(&validator).cloned().unwrap_or_default();
 
                 delegators_vote
-                    .insert(vote.address.clone(), vote.clone().into());
+                    .entry(vote.address.clone())
+                    .or_default()
+                    .insert(validator.clone(), vote.clone().into());
                 delegator_voting_power
                     .entry(vote.address.clone())
                     .or_default()
@@ -2972,7 +3029,8 @@ pub async fn compute_proposal_votes<
     let mut validators_vote: HashMap<Address, TallyVote> = HashMap::default();
     let mut validator_voting_power: HashMap<Address, VotePower> =
         HashMap::default();
-    let mut delegators_vote: HashMap<Address, TallyVote> = HashMap::default();
+    let mut delegators_vote: HashMap<Address, HashMap<Address, TallyVote>> =
+        HashMap::default();
     let mut delegator_voting_power: HashMap<
         Address,
         HashMap<Address, VotePower>,
@@ -2998,7 +3056,9 @@ pub async fn compute_proposal_votes<
 
             if let Some(stake) = delegator_stake {
                 delegators_vote
-                    .insert(vote.delegator.clone(), vote.data.into());
+                    .entry(vote.delegator.clone())
+                    .or_default()
+                    .insert(vote.validator.clone(), vote.data.into());
                 delegator_voting_power
                     .entry(vote.delegator.clone())
                     .or_default()
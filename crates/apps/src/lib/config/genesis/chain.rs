@@ -368,6 +368,8 @@ impl Finalized {
             liveness_threshold,
             rewards_gain_p,
             rewards_gain_d,
+            min_delegation_amount,
+            min_validator_self_bond,
         } = self.parameters.pos_params.clone();
 
         namada::proof_of_stake::parameters::PosParams {
@@ -388,6 +390,8 @@ impl Finalized {
                 liveness_threshold,
                 rewards_gain_p,
                 rewards_gain_d,
+                min_delegation_amount,
+                min_validator_self_bond,
             },
             max_proposal_period: self.parameters.gov_params.max_proposal_period,
         }
@@ -403,6 +407,12 @@ impl Finalized {
             max_proposal_period,
             max_proposal_content_size,
             min_proposal_grace_epochs,
+            max_concurrent_proposals_per_author,
+            proposal_content_required_fields,
+            max_vote_memo_length,
+            threshold_default,
+            threshold_pgf_steward,
+            threshold_pgf_payment,
         } = self.parameters.gov_params.clone();
         namada::governance::parameters::GovernanceParameters {
             min_proposal_fund: Amount::native_whole(min_proposal_fund),
@@ -411,6 +421,12 @@ impl Finalized {
             max_proposal_content_size,
             min_proposal_grace_epochs,
             min_proposal_voting_period,
+            max_concurrent_proposals_per_author,
+            proposal_content_required_fields,
+            max_vote_memo_length,
+            threshold_default,
+            threshold_pgf_steward,
+            threshold_pgf_payment,
         }
     }
 
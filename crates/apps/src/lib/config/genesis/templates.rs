@@ -15,6 +15,7 @@ use namada::core::token::{
 use namada::eth_bridge::storage::parameters::{
     Contracts, Erc20WhitelistEntry, MinimumConfirmations,
 };
+use namada::governance::utils::is_valid_threshold;
 use namada::token;
 use serde::{Deserialize, Serialize};
 
@@ -409,6 +410,10 @@ pub struct PosParams {
     pub rewards_gain_p: Dec,
     /// PoS gain d (read only)
     pub rewards_gain_d: Dec,
+    /// The minimum amount of tokens of a delegation
+    pub min_delegation_amount: token::Amount,
+    /// The minimum self-bond of an active validator
+    pub min_validator_self_bond: token::Amount,
 }
 
 #[derive(
@@ -434,6 +439,21 @@ pub struct GovernanceParams {
     pub max_proposal_content_size: u64,
     /// Minimum number of epoch between end and grace epoch
     pub min_proposal_grace_epochs: u64,
+    /// Maximum number of proposals of an author which haven't been activated
+    pub max_concurrent_proposals_per_author: u64,
+    /// Fields which must be set in the proposal content
+    pub proposal_content_required_fields: Vec<String>,
+    /// Maximum number of bytes of the memo attached to a vote
+    pub max_vote_memo_length: u64,
+    /// Minimum fraction of the total voting power that must vote on a
+    /// default proposal
+    pub threshold_default: Dec,
+    /// Minimum fraction of the total voting power that must vote on a PGF
+    /// steward proposal
+    pub threshold_pgf_steward: Dec,
+    /// Minimum fraction of the total voting power that must vote on a PGF
+    /// payment proposal
+    pub threshold_pgf_payment: Dec,
 }
 
 #[derive(
@@ -851,6 +871,21 @@ pub fn validate_parameters(
             );
         }
     }
+    // check that the tally thresholds are fractions of the voting power
+    let gov = &parameters.gov_params;
+    for (name, threshold) in [
+        ("threshold_default", &gov.threshold_default),
+        ("threshold_pgf_steward", &gov.threshold_pgf_steward),
+        ("threshold_pgf_payment", &gov.threshold_pgf_payment),
+    ] {
+        if !is_valid_threshold(threshold) {
+            eprintln!(
+                "The governance parameter {name} {threshold} must be between \
+                 0 and 1"
+            );
+            is_valid = false;
+        }
+    }
     let Parameters {
         parameters,
         pos_params,
@@ -8,6 +8,7 @@ pub mod utils;
 use std::collections::HashMap;
 use std::fs::{create_dir_all, File};
 use std::io::Write;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 
 use directories::ProjectDirs;
@@ -111,12 +112,46 @@ pub struct Shell {
     /// When set, will limit the how many block heights in the past can the
     /// storage be queried for reading values.
     pub storage_read_past_height_limit: Option<u64>,
+    /// When set, the number of past epochs for which the merkle tree stores
+    /// of the IBC subtree are retained to prove the IBC state at past
+    /// heights. Otherwise, they're retained for the
+    /// `storage_read_past_height_limit`.
+    pub ibc_merkle_tree_retention_epochs: Option<u64>,
+    /// When set, the number of past epochs for which the other provable
+    /// merkle tree stores are retained. Otherwise, they're retained for the
+    /// `storage_read_past_height_limit`.
+    pub merkle_tree_retention_epochs: Option<u64>,
+    /// When set, the number of past blocks for which the diffs are retained
+    /// to read the state at past heights. The diffs since the start of the
+    /// current epoch are always retained. Otherwise, they're never pruned.
+    pub diff_retention_blocks: Option<u64>,
     /// Use the [`Ledger::db_dir()`] method to read the value.
     db_dir: PathBuf,
     /// Use the [`Ledger::cometbft_dir()`] method to read the value.
     cometbft_dir: PathBuf,
     /// An optional action to take when a given blockheight is reached.
     pub action_at_height: Option<ActionAtHeight>,
+    /// When set, the storage migrations up to the binary's storage version
+    /// are applied in the block of this height. It must be the upgrade
+    /// height coordinated by all the nodes of the chain.
+    #[serde(default)]
+    pub storage_migration_height: Option<BlockHeight>,
+    /// When set, the statistics of the DB column families are logged on
+    /// every block commit.
+    #[serde(default)]
+    pub storage_stats: bool,
+    /// When set, the gas consumed by the VPs of a tx is broken down per
+    /// label in the tx event attributes. This has no consensus impact.
+    #[serde(default)]
+    pub gas_trace: bool,
+    /// When set, the tokens escrowed by IBC are audited against the packet
+    /// accounting on startup and the discrepancies are logged.
+    #[serde(default)]
+    pub audit_ibc: bool,
+    /// When set, the ledger metrics are served for Prometheus under
+    /// `/metrics` on this address. Requires the `metrics` feature.
+    #[serde(default)]
+    pub metrics_listen_addr: Option<SocketAddr>,
     /// Specify if tendermint is started as validator, fullnode or seednode
     pub tendermint_mode: TendermintMode,
 }
@@ -143,9 +178,17 @@ impl Ledger {
                 tx_wasm_compilation_cache_bytes: None,
                 // Default corresponds to 1 hour of past blocks at 1 block/sec
                 storage_read_past_height_limit: Some(3600),
+                ibc_merkle_tree_retention_epochs: None,
+                merkle_tree_retention_epochs: None,
+                diff_retention_blocks: None,
                 db_dir: DB_DIR.into(),
                 cometbft_dir: COMETBFT_DIR.into(),
                 action_at_height: None,
+                storage_migration_height: None,
+                storage_stats: false,
+                gas_trace: false,
+                audit_ibc: false,
+                metrics_listen_addr: None,
                 tendermint_mode: mode,
             },
             cometbft: tendermint_config,
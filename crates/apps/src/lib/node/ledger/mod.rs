@@ -9,15 +9,13 @@ pub mod tendermint_node;
 use std::convert::TryInto;
 use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::str::FromStr;
 use std::thread;
 
 use byte_unit::Byte;
 use futures::future::TryFutureExt;
-use namada::core::storage::Key;
 use namada::core::time::DateTimeUtc;
 use namada::eth_bridge::ethers::providers::{Http, Provider};
-use namada::governance::storage::keys as governance_storage;
+use namada::ledger::governance::finalize as governance;
 use namada::tendermint::abci::request::CheckTxKind;
 use namada_sdk::state::StateRead;
 use once_cell::unsync::Lazy;
@@ -66,31 +64,11 @@ const ENV_VAR_RAYON_THREADS: &str = "NAMADA_RAYON_THREADS";
 //```
 impl Shell {
     fn load_proposals(&mut self) {
-        let proposals_key = governance_storage::get_commiting_proposals_prefix(
-            self.state.in_mem().last_epoch.0,
+        let proposals = governance::load_proposals(
+            &self.state,
+            self.state.in_mem().last_epoch,
         );
-
-        let (proposal_iter, _) = self.state.db_iter_prefix(&proposals_key);
-        for (key, _, _) in proposal_iter {
-            let key =
-                Key::from_str(key.as_str()).expect("Key should be parsable");
-            if governance_storage::get_commit_proposal_epoch(&key).unwrap()
-                != self.state.in_mem().last_epoch.0
-            {
-                // NOTE: `iter_prefix` iterate over the matching prefix. In this
-                // case  a proposal with grace_epoch 110 will be
-                // matched by prefixes  1, 11 and 110. Thus we
-                // have to skip to the next iteration of
-                //  the cycle for all the prefixes that don't actually match
-                //  the desired epoch.
-                continue;
-            }
-
-            let proposal_id = governance_storage::get_commit_proposal_id(&key);
-            if let Some(id) = proposal_id {
-                self.proposal_data.insert(id);
-            }
-        }
+        self.proposal_data.extend(proposals);
     }
 
     fn call(&mut self, req: Request) -> Result<Response, Error> {
@@ -220,7 +198,7 @@ pub fn dump_db(
     let chain_id = config.chain_id;
     let db_path = config.shell.db_dir(&chain_id);
 
-    let db = storage::PersistentDB::open(db_path, None);
+    let db = storage::PersistentDB::open(db_path, None, None);
     db.dump_block(out_file_path, historic, block_height);
 }
 
@@ -264,6 +242,12 @@ async fn run_aux(config: config::Ledger, wasm_dir: PathBuf) {
     let _ = namada_sdk::masp::preload_verifying_keys();
     tracing::info!("Done loading MASP verifying keys.");
 
+    // Start the server of the ledger metrics if enabled
+    #[cfg(feature = "metrics")]
+    let metrics_server = maybe_start_metrics_server(&mut spawner, &config);
+    #[cfg(not(feature = "metrics"))]
+    let metrics_server = spawn_dummy_task(());
+
     // Start ABCI server and broadcaster (the latter only if we are a validator
     // node)
     let (abci, broadcaster, shell_handler) = start_abci_broadcaster_shell(
@@ -278,10 +262,16 @@ async fn run_aux(config: config::Ledger, wasm_dir: PathBuf) {
     let aborted = spawner.wait_for_abort().await.child_terminated();
 
     // Wait for all managed tasks to finish.
-    let res = tokio::try_join!(tendermint_node, abci, eth_oracle, broadcaster);
+    let res = tokio::try_join!(
+        tendermint_node,
+        abci,
+        eth_oracle,
+        broadcaster,
+        metrics_server
+    );
 
     match res {
-        Ok((tendermint_res, abci_res, _, _)) => {
+        Ok((tendermint_res, abci_res, _, _, _)) => {
             // we ignore errors on user-initiated shutdown
             if aborted {
                 if let Err(err) = tendermint_res {
@@ -632,6 +622,43 @@ fn start_tendermint(
         })
 }
 
+/// Potentially starts a [`warp::Server`] serving the ledger metrics for
+/// Prometheus under `/metrics`, if a listen address is configured.
+#[cfg(feature = "metrics")]
+fn maybe_start_metrics_server(
+    spawner: &mut AbortableSpawner,
+    config: &config::Ledger,
+) -> task::JoinHandle<()> {
+    use warp::Filter;
+
+    let Some(listen_addr) = config.shell.metrics_listen_addr else {
+        return spawn_dummy_task(());
+    };
+    let (abort_send, abort_recv) = tokio::sync::oneshot::channel::<()>();
+
+    spawner
+        .spawn_abortable("Metrics", move |aborter| async move {
+            let metrics = warp::get()
+                .and(warp::path("metrics"))
+                .and(warp::path::end())
+                .map(namada::ledger::metrics::render);
+            let (_, server) = warp::serve(metrics).bind_with_graceful_shutdown(
+                listen_addr,
+                async move {
+                    let _ = abort_recv.await;
+                },
+            );
+            tracing::info!(?listen_addr, "Serving the ledger metrics");
+            server.await;
+            tracing::info!("Metrics server is no longer running.");
+
+            drop(aborter);
+        })
+        .with_cleanup(async move {
+            let _ = abort_send.send(());
+        })
+}
+
 /// Represents a [`tokio::task`] in which an Ethereum oracle may be running, and
 /// if so, channels for communicating with it.
 enum EthereumOracleTask {
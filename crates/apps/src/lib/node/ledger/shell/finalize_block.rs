@@ -1,20 +1,13 @@
 //! Implementation of the `FinalizeBlock` ABCI++ method for the Shell
 
 use data_encoding::HEXUPPER;
-use masp_primitives::merkle_tree::CommitmentTree;
-use masp_primitives::sapling::Node;
-use namada::core::storage::{BlockHash, BlockResults, Epoch, Header};
-use namada::governance::pgf::inflation as pgf_inflation;
+use namada::core::storage::{BlockHash, BlockResults, Header};
 use namada::ledger::events::EventType;
 use namada::ledger::gas::GasMetering;
 use namada::ledger::pos::namada_proof_of_stake;
 use namada::ledger::protocol::WrapperArgs;
-use namada::proof_of_stake;
-use namada::proof_of_stake::storage::{
-    find_validator_by_raw_hash, write_last_block_proposer_address,
-};
-use namada::state::write_log::StorageModification;
-use namada::state::{ResultExt, StorageWrite, EPOCH_SWITCH_BLOCKS_DELAY};
+use namada::proof_of_stake::storage::find_validator_by_raw_hash;
+use namada::state::EPOCH_SWITCH_BLOCKS_DELAY;
 use namada::tx::data::protocol::ProtocolTxType;
 use namada::vote_ext::ethereum_events::MultiSignedEthEvent;
 use namada::vote_ext::ethereum_tx_data_variants;
@@ -82,6 +75,19 @@ where
             self.state.in_mem().update_epoch_blocks_delay
         );
 
+        // Apply the storage migrations at the coordinated upgrade height,
+        // before any of the block's txs
+        if self.storage_migration_height == Some(height) {
+            if let Some(applied) = migrations::migrate(&mut self.state)? {
+                tracing::info!(
+                    "Migrated the storage from the version {} to {} at \
+                     height {height}",
+                    applied.from_version,
+                    applied.to_version,
+                );
+            }
+        }
+
         // Finalize the transactions' hashes from the previous block
         let (write_log, _in_mem, db) = self.state.split_borrow();
         for (raw_key, _, _) in db.iter_replay_protection() {
@@ -94,40 +100,32 @@ where
         let emit_events = &mut response.events;
         // Get the actual votes from cometBFT in the preferred format
         let votes = pos_votes_from_abci(&self.state, &req.votes);
-        let validator_set_update_epoch =
-            self.get_validator_set_update_epoch(current_epoch);
 
-        // Sub-system updates:
+        // Sub-system updates, each one rolled back on failure:
         // - Governance - applied first in case a proposal changes any of the
         //   other syb-systems
-        governance::finalize_block(self, emit_events, new_epoch)?;
-        // - Token
-        token::finalize_block(&mut self.state, emit_events, new_epoch)?;
-        // - PoS
-        //    - Must be applied after governance in case it changes PoS params
-        proof_of_stake::finalize_block(
+        let proposals = if new_epoch {
+            std::mem::take(&mut self.proposal_data)
+        } else {
+            BTreeSet::new()
+        };
+        engine::finalize_governance(
+            &mut self.state,
+            emit_events,
+            new_epoch,
+            proposals,
+            &mut self.vp_wasm_cache,
+            &mut self.tx_wasm_cache,
+        )?;
+        // - Token, IBC, PoS and the inflation
+        engine::finalize_block(
             &mut self.state,
             emit_events,
             new_epoch,
-            validator_set_update_epoch,
             votes,
             req.byzantine_validators,
         )?;
 
-        // Take IBC events that may be emitted from PGF
-        for ibc_event in self.state.write_log_mut().take_ibc_events() {
-            let mut event = Event::from(ibc_event.clone());
-            // Add the height for IBC event query
-            let height = self.state.in_mem().get_last_block_height() + 1;
-            event["height"] = height.to_string();
-            response.events.push(event);
-        }
-
-        if new_epoch {
-            // Apply PoS and PGF inflation
-            self.apply_inflation(current_epoch)?;
-        }
-
         let mut stats = InternalStats::default();
 
         let native_block_proposer_address = {
@@ -274,7 +272,8 @@ where
                     (
                         event,
                         Some(tx_in_queue.tx),
-                        TxGasMeter::new_from_sub_limit(tx_in_queue.gas),
+                        TxGasMeter::new_from_sub_limit(tx_in_queue.gas)
+                            .with_gas_trace(self.gas_trace),
                         None,
                         None,
                     )
@@ -354,24 +353,21 @@ where
                 },
             };
             let tx_gas_meter = RefCell::new(tx_gas_meter);
-            let tx_result = protocol::check_tx_allowed(&tx, &self.state)
-                .and_then(|()| {
-                    protocol::dispatch_tx(
-                        tx,
-                        processed_tx.tx.as_ref(),
-                        TxIndex(
-                            tx_index
-                                .try_into()
-                                .expect("transaction index out of bounds"),
-                        ),
-                        &tx_gas_meter,
-                        &mut self.state,
-                        &mut self.vp_wasm_cache,
-                        &mut self.tx_wasm_cache,
-                        wrapper_args.as_mut(),
-                    )
-                })
-                .map_err(Error::TxApply);
+            let tx_result = engine::execute_tx(
+                tx,
+                processed_tx.tx.as_ref(),
+                TxIndex(
+                    tx_index
+                        .try_into()
+                        .expect("transaction index out of bounds"),
+                ),
+                &tx_gas_meter,
+                &mut self.state,
+                &mut self.vp_wasm_cache,
+                &mut self.tx_wasm_cache,
+                wrapper_args.as_mut(),
+            )
+            .map_err(Error::TxApply);
             let tx_gas_meter = tx_gas_meter.into_inner();
             match tx_result {
                 Ok(result) => {
@@ -415,36 +411,6 @@ where
                                 self.commit_inner_tx_hash(wrapper);
                             }
                         }
-                        self.state.commit_tx();
-                        if !tx_event.contains_key("code") {
-                            tx_event["code"] = ResultCode::Ok.into();
-                            self.state
-                                .in_mem_mut()
-                                .block
-                                .results
-                                .accept(tx_index);
-                        }
-                        // events from other sources
-                        response.events.extend(
-                            // ibc events
-                            result
-                                .ibc_events
-                                .iter()
-                                .cloned()
-                                .map(|ibc_event| {
-                                    // Add the IBC event besides the tx_event
-                                    let mut event = Event::from(ibc_event);
-                                    event["height"] = height.to_string();
-                                    event
-                                })
-                                // eth bridge events
-                                .chain(
-                                    result
-                                        .eth_bridge_events
-                                        .iter()
-                                        .map(Event::from),
-                                ),
-                        );
                     } else {
                         tracing::trace!(
                             "some VPs rejected transaction {} storage \
@@ -463,12 +429,17 @@ where
                         }
 
                         stats.increment_rejected_txs();
-                        self.state.drop_tx();
-                        tx_event["code"] = ResultCode::InvalidTx.into();
+                        #[cfg(feature = "metrics")]
+                        namada::ledger::metrics::record_vp_rejections(
+                            &result.vps_result,
+                        );
                     }
-                    tx_event["gas_used"] = result.gas_used.to_string();
-                    tx_event["info"] = "Check inner_tx for result.".to_string();
-                    tx_event["inner_tx"] = result.to_string();
+                    response.events.extend(engine::finalize_tx_result(
+                        &mut self.state,
+                        &mut tx_event,
+                        tx_index,
+                        &result,
+                    ));
                 }
                 Err(msg) => {
                     tracing::info!(
@@ -509,14 +480,9 @@ where
                     }
 
                     stats.increment_errored_txs();
-                    self.state.drop_tx();
 
-                    tx_event["gas_used"] =
-                        tx_gas_meter.get_tx_consumed_gas().to_string();
-                    tx_event["info"] = msg.to_string();
-                    if let EventType::Accepted = tx_event.event_type {
-                        // If wrapper, invalid tx error code
-                        tx_event["code"] = ResultCode::InvalidTx.into();
+                    let code = if let EventType::Accepted = tx_event.event_type
+                    {
                         // The fee unshield operation could still have been
                         // committed
                         if wrapper_args
@@ -526,9 +492,19 @@ where
                             tx_event["is_valid_masp_tx"] =
                                 format!("{}", tx_index);
                         }
+                        // If wrapper, invalid tx error code
+                        ResultCode::InvalidTx
                     } else {
-                        tx_event["code"] = ResultCode::WasmRuntimeError.into();
-                    }
+                        ResultCode::WasmRuntimeError
+                    };
+                    // The full message is logged above
+                    engine::finalize_tx_error(
+                        &mut self.state,
+                        &mut tx_event,
+                        tx_gas_meter.get_tx_consumed_gas(),
+                        code,
+                        msg,
+                    )?;
                 }
             }
             response.events.push(tx_event);
@@ -546,19 +522,6 @@ where
         tracing::info!("{}", stats);
         tracing::info!("{}", stats.format_tx_executed());
 
-        // Update the MASP commitment tree anchor if the tree was updated
-        let tree_key = token::storage_key::masp_commitment_tree_key();
-        if let Some(StorageModification::Write { value }) =
-            self.state.write_log().read(&tree_key).0
-        {
-            let updated_tree = CommitmentTree::<Node>::try_from_slice(value)
-                .into_storage_result()?;
-            let anchor_key = token::storage_key::masp_commitment_anchor_key(
-                updated_tree.root(),
-            );
-            self.state.write(&anchor_key, ())?;
-        }
-
         if update_for_tendermint {
             self.update_epoch(&mut response);
             // send the latest oracle configs. These may have changed due to
@@ -566,9 +529,10 @@ where
             self.update_eth_oracle(&changed_keys);
         }
 
-        write_last_block_proposer_address(
+        // The MASP anchor and the block proposer
+        engine::end_block(
             &mut self.state,
-            native_block_proposer_address,
+            Some(&native_block_proposer_address),
         )?;
 
         self.event_log_mut().log_events(response.events.clone());
@@ -587,24 +551,8 @@ where
         header: Header,
         hash: BlockHash,
     ) -> (BlockHeight, bool) {
-        let height = self.state.in_mem().get_last_block_height() + 1;
-
-        self.state
-            .in_mem_mut()
-            .begin_block(hash, height)
-            .expect("Beginning a block shouldn't fail");
-
-        let header_time = header.time;
-        self.state
-            .in_mem_mut()
-            .set_header(header)
-            .expect("Setting a header shouldn't fail");
-
-        let new_epoch = self
-            .state
-            .update_epoch(height, header_time)
-            .expect("Must be able to update epoch");
-        (height, new_epoch)
+        engine::begin_block(&mut self.state, header, hash)
+            .expect("Beginning a block shouldn't fail")
     }
 
     /// If a new epoch begins, we update the response to include
@@ -626,37 +574,6 @@ where
             .expect("Must be able to update validator set");
     }
 
-    /// Calculate the new inflation rate, mint the new tokens to the PoS
-    /// account, then update the reward products of the validators. This is
-    /// executed while finalizing the first block of a new epoch and is applied
-    /// with respect to the previous epoch.
-    fn apply_inflation(&mut self, current_epoch: Epoch) -> Result<()> {
-        let last_epoch = current_epoch.prev();
-
-        // Get the number of blocks in the last epoch
-        let first_block_of_last_epoch =
-            self.state.in_mem().block.pred_epochs.first_block_heights
-                [last_epoch.0 as usize]
-                .0;
-        let num_blocks_in_last_epoch =
-            self.state.in_mem().block.height.0 - first_block_of_last_epoch;
-
-        // PoS inflation
-        namada_proof_of_stake::rewards::apply_inflation(
-            &mut self.state,
-            last_epoch,
-            num_blocks_in_last_epoch,
-        )?;
-
-        // Pgf inflation
-        pgf_inflation::apply_inflation(
-            self.state.restrict_writes_to_write_log(),
-            namada::ibc::transfer_over_ibc,
-        )?;
-
-        Ok(())
-    }
-
     // Write the inner tx hash to storage and remove the corresponding wrapper
     // hash since it's redundant (we check the inner tx hash too when validating
     // the wrapper). Requires the wrapper transaction as argument to recover
@@ -746,7 +663,7 @@ mod test_finalize_block {
     use namada::core::hash::Hash;
     use namada::core::keccak::KeccakHash;
     use namada::core::key::testing::common_sk_from_simple_seed;
-    use namada::core::storage::KeySeg;
+    use namada::core::storage::{Epoch, KeySeg};
     use namada::core::time::DurationSecs;
     use namada::core::uint::Uint;
     use namada::eth_bridge::storage::bridge_pool::{
@@ -754,17 +671,31 @@ mod test_finalize_block {
     };
     use namada::eth_bridge::storage::min_confirmations_key;
     use namada::ethereum_bridge::storage::wrapped_erc20s;
-    use namada::governance::storage::keys::get_proposal_execution_key;
+    use namada::governance::storage::keys::{
+        get_max_proposal_period_key, get_proposal_execution_key,
+        get_threshold_default_key, get_voting_power_snapshot_key,
+    };
     use namada::governance::storage::proposal::ProposalType;
+    use namada::governance::storage::{
+        get_proposal_result_record, read_voting_power_snapshot,
+        VotingPowerSnapshot,
+    };
+    use namada::governance::utils::{
+        compute_proposal_result, ProposalResultRecord, TallyType,
+    };
     use namada::governance::{InitProposalData, VoteProposalData};
+    use namada::ibc::core::host::types::identifiers::{ChannelId, PortId};
     use namada::ledger::gas::VpGasMeter;
+    use namada::ledger::governance::finalize::{
+        compute_proposal_votes, load_proposals,
+    };
     use namada::ledger::native_vp::parameters::ParametersVp;
     use namada::ledger::native_vp::NativeVp;
     use namada::ledger::parameters::EpochDuration;
     use namada::proof_of_stake::storage::{
         enqueued_slashes_handle, get_num_consensus_validators,
-        read_consensus_validator_set_addresses_with_stake, read_total_stake,
-        read_validator_stake, rewards_accumulator_handle,
+        read_consensus_validator_set_addresses_with_stake, read_pos_params,
+        read_total_stake, read_validator_stake, rewards_accumulator_handle,
         validator_consensus_key_handle, validator_rewards_products_handle,
         validator_slashes_handle, validator_state_handle, write_pos_params,
     };
@@ -776,6 +707,7 @@ mod test_finalize_block {
     };
     use namada::proof_of_stake::{unjail_validator, ADDRESS as pos_address};
     use namada::replay_protection;
+    use namada::state::StorageWrite;
     use namada::tendermint::abci::types::{Misbehavior, MisbehaviorKind};
     use namada::token::{Amount, DenominatedAmount, NATIVE_MAX_DECIMAL_PLACES};
     use namada::tx::data::Fee;
@@ -1175,6 +1107,35 @@ mod test_finalize_block {
         assert_eq!(code, &String::from(ResultCode::InvalidTx));
     }
 
+    /// Test that the storage migrations are only applied in the block of the
+    /// coordinated upgrade height
+    #[test]
+    fn test_storage_migration_at_upgrade_height() {
+        let (mut shell, _, _, _) = setup_at_height(3u64);
+        let upgrade_height = shell.state.in_mem().get_last_block_height() + 2;
+        shell.storage_migration_height = Some(upgrade_height);
+        // a new chain starts with the binary's version
+        assert_eq!(
+            migrations::read_storage_version(&shell.state).unwrap(),
+            migrations::STORAGE_VERSION
+        );
+        // a chain predating the versioning
+        migrations::write_storage_version(&mut shell.state, 0).unwrap();
+
+        shell.finalize_and_commit(None);
+        assert_eq!(migrations::read_storage_version(&shell.state).unwrap(), 0);
+
+        shell.finalize_and_commit(None);
+        assert_eq!(
+            shell.state.in_mem().get_last_block_height(),
+            upgrade_height
+        );
+        assert_eq!(
+            migrations::read_storage_version(&shell.state).unwrap(),
+            migrations::STORAGE_VERSION
+        );
+    }
+
     /// Test that once a validator's vote for an Ethereum event lands
     /// on-chain from a vote extension digest, it dequeues from the
     /// list of events to vote on.
@@ -1536,6 +1497,7 @@ mod test_finalize_block {
                 vote,
                 voter: validator,
                 delegations: vec![],
+                memo: None,
             };
             // Vote to accept the proposal (there's only one validator, so its
             // vote decides)
@@ -1642,6 +1604,272 @@ mod test_finalize_block {
         }
     }
 
+    /// Test that the result of a proposal is recorded in storage at its
+    /// activation epoch
+    #[test]
+    fn test_proposal_result_record() {
+        let (mut shell, _broadcaster, _, _eth_control) = setup();
+        let validator = shell.mode.get_validator_address().unwrap().clone();
+        let proposal_id = 0;
+        let proposal = InitProposalData {
+            id: proposal_id,
+            content: Hash::default(),
+            author: validator.clone(),
+            voting_start_epoch: Epoch::default(),
+            voting_end_epoch: Epoch::default().next(),
+            grace_epoch: Epoch::default().next(),
+            r#type: ProposalType::Default(None),
+        };
+        namada::governance::init_proposal(
+            &mut shell.state,
+            proposal,
+            vec![],
+            None,
+        )
+        .unwrap();
+        let vote = VoteProposalData {
+            id: proposal_id,
+            vote: ProposalVote::Yay,
+            voter: validator.clone(),
+            delegations: vec![],
+            memo: None,
+        };
+        namada::governance::vote_proposal(&mut shell.state, vote).unwrap();
+        shell.proposal_data.insert(proposal_id);
+        assert!(
+            get_proposal_result_record(&shell.state, proposal_id)
+                .unwrap()
+                .is_none()
+        );
+
+        // The only validator votes yay with its whole stake
+        let pos_params =
+            namada_proof_of_stake::storage::read_pos_params(&shell.state)
+                .unwrap();
+        let stake = read_validator_stake(
+            &shell.state,
+            &pos_params,
+            &validator,
+            Epoch::default().next(),
+        )
+        .unwrap();
+        let expected = ProposalResultRecord {
+            passed: true,
+            yay: stake,
+            nay: Amount::zero(),
+            abstain: Amount::zero(),
+            total_voting_power: stake,
+            turnout: Dec::one(),
+        };
+
+        shell.start_new_epoch(None);
+
+        let record = get_proposal_result_record(&shell.state, proposal_id)
+            .unwrap()
+            .expect("The proposal result should be recorded");
+        assert_eq!(record, expected);
+    }
+
+    /// Test that a proposal is tallied against the threshold of the
+    /// governance parameters at its creation, even when the threshold is
+    /// changed during its voting period
+    #[test]
+    fn test_proposal_tallied_with_params_snapshot() {
+        let (mut shell, _recv, _, _) = setup_with_cfg(SetupCfg {
+            last_height: 0,
+            num_validators: 4,
+            ..Default::default()
+        });
+        let validator = read_consensus_validator_set_addresses_with_stake(
+            &shell.state,
+            Epoch::default(),
+        )
+        .unwrap()
+        .into_iter()
+        .next()
+        .unwrap()
+        .address;
+
+        // The proposal is created while any turnout is enough
+        shell
+            .state
+            .write(&get_threshold_default_key(), Dec::zero())
+            .unwrap();
+        let proposal_id = 0;
+        let proposal = InitProposalData {
+            id: proposal_id,
+            content: Hash::default(),
+            author: validator.clone(),
+            voting_start_epoch: Epoch::default(),
+            voting_end_epoch: Epoch::default().next(),
+            grace_epoch: Epoch::default().next(),
+            r#type: ProposalType::Default(None),
+        };
+        namada::governance::init_proposal(
+            &mut shell.state,
+            proposal,
+            vec![],
+            None,
+        )
+        .unwrap();
+
+        // The whole voting power must vote on the proposals created from now
+        // on, which a single validator out of four doesn't have
+        shell
+            .state
+            .write(&get_threshold_default_key(), Dec::one())
+            .unwrap();
+        let vote = VoteProposalData {
+            id: proposal_id,
+            vote: ProposalVote::Yay,
+            voter: validator.clone(),
+            delegations: vec![validator],
+            memo: None,
+        };
+        namada::governance::vote_proposal(&mut shell.state, vote).unwrap();
+        shell.proposal_data.insert(proposal_id);
+
+        shell.start_new_epoch(None);
+
+        let record = get_proposal_result_record(&shell.state, proposal_id)
+            .unwrap()
+            .expect("The proposal result should be recorded");
+        assert!(record.passed);
+        assert!(record.turnout < Dec::one());
+    }
+
+    /// Test that the stakes of the consensus validators are snapshotted at the
+    /// beginning of an epoch and that the tally from the snapshot matches the
+    /// tally from the PoS state
+    #[test]
+    fn test_voting_power_snapshot() {
+        let (mut shell, _recv, _, _) = setup_with_cfg(SetupCfg {
+            last_height: 0,
+            num_validators: 4,
+            ..Default::default()
+        });
+        let epoch = shell.start_new_epoch(None);
+
+        let consensus_set = read_consensus_validator_set_addresses_with_stake(
+            &shell.state,
+            epoch,
+        )
+        .unwrap();
+        let expected: VotingPowerSnapshot = consensus_set
+            .iter()
+            .map(|validator| {
+                (validator.address.clone(), validator.bonded_stake)
+            })
+            .collect();
+        let snapshot = read_voting_power_snapshot(&shell.state, epoch)
+            .unwrap()
+            .expect("The snapshot should have been taken");
+        assert_eq!(snapshot, expected);
+
+        // Two of the validators vote for a proposal ending in the epoch
+        let proposal_id = 0;
+        let validators: Vec<_> = consensus_set
+            .into_iter()
+            .map(|validator| validator.address)
+            .collect();
+        let proposal = InitProposalData {
+            id: proposal_id,
+            content: Hash::default(),
+            author: validators[0].clone(),
+            voting_start_epoch: Epoch::default(),
+            voting_end_epoch: epoch,
+            grace_epoch: epoch.next(),
+            r#type: ProposalType::Default(None),
+        };
+        namada::governance::init_proposal(
+            &mut shell.state,
+            proposal,
+            vec![],
+            None,
+        )
+        .unwrap();
+        for (validator, vote) in validators
+            .iter()
+            .zip([ProposalVote::Yay, ProposalVote::Nay])
+        {
+            let vote = VoteProposalData {
+                id: proposal_id,
+                vote,
+                voter: validator.clone(),
+                delegations: vec![validator.clone()],
+                memo: None,
+            };
+            namada::governance::vote_proposal(&mut shell.state, vote).unwrap();
+        }
+
+        let params = read_pos_params(&shell.state).unwrap();
+        let total_voting_power =
+            read_total_stake(&shell.state, &params, epoch).unwrap();
+        let tally = |shell: &TestShell| {
+            let votes = compute_proposal_votes(
+                &shell.state,
+                &params,
+                proposal_id,
+                epoch,
+            )
+            .unwrap();
+            let voting_power = votes.validator_voting_power.clone();
+            let result = compute_proposal_result(
+                votes,
+                total_voting_power,
+                TallyType::OneHalfOverOneThird,
+                TallyType::OneHalfOverOneThird.default_threshold(),
+            );
+            (
+                voting_power,
+                result.total_yay_power,
+                result.total_nay_power,
+                result.total_abstain_power,
+            )
+        };
+        let from_snapshot = tally(&shell);
+        assert_eq!(from_snapshot.0.len(), 2);
+
+        // Without the snapshot, the stakes are read from the PoS state
+        shell
+            .state
+            .delete(&get_voting_power_snapshot_key(epoch))
+            .unwrap();
+        assert!(
+            read_voting_power_snapshot(&shell.state, epoch)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(tally(&shell), from_snapshot);
+    }
+
+    /// Test that the voting power snapshots older than the maximum proposal
+    /// period are pruned
+    #[test]
+    fn test_voting_power_snapshot_pruning() {
+        let (mut shell, _recv, _, _) = setup();
+        let max_proposal_period = 2;
+        shell
+            .state
+            .write(&get_max_proposal_period_key(), max_proposal_period)
+            .unwrap();
+
+        let mut epoch = Epoch::default();
+        for _ in 0..5 {
+            epoch = shell.start_new_epoch(None);
+        }
+        for snapshot_epoch in 1..=epoch.0 {
+            let snapshot =
+                read_voting_power_snapshot(&shell.state, Epoch(snapshot_epoch))
+                    .unwrap();
+            assert_eq!(
+                snapshot.is_some(),
+                snapshot_epoch + max_proposal_period >= epoch.0,
+                "Unexpected snapshot at epoch {snapshot_epoch}"
+            );
+        }
+    }
+
     /// A unit test for PoS inflationary rewards
     #[test]
     fn test_inflation_accounting() {
@@ -4974,4 +5202,162 @@ mod test_finalize_block {
             control_receiver.recv().await.expect("Test failed");
         assert_eq!(u64::from(cmd.min_confirmations), 42);
     }
+
+    /// Test that the IBC throughputs of an epoch are deleted from the storage
+    /// on a new epoch
+    #[test]
+    fn test_ibc_throughputs_cleared_on_new_epoch() {
+        let (mut shell, _, _, _) = setup();
+        let token = shell.state.in_mem().native_token.clone();
+        let port_id = PortId::transfer();
+        let channel_id = ChannelId::new(0);
+        let keys = [
+            ibc::storage::deposit_key(&token),
+            ibc::storage::withdraw_key(&token),
+            ibc::storage::deposit_key_for_channel(
+                &token,
+                &port_id,
+                &channel_id,
+            ),
+            ibc::storage::withdraw_key_for_channel(
+                &token,
+                &port_id,
+                &channel_id,
+            ),
+        ];
+
+        // the throughputs recorded by transfers in the current epoch
+        let epoch = shell.state.in_mem().get_current_epoch().0;
+        for key in &keys {
+            shell
+                .state
+                .write(key, Amount::native_whole(100))
+                .expect("Test failed");
+        }
+        shell.state.commit_block().expect("Test failed");
+        for key in &keys {
+            assert!(shell.state.has_key(key).expect("Test failed"));
+        }
+
+        assert_eq!(shell.start_new_epoch(None), epoch.next());
+        for key in &keys {
+            assert!(!shell.state.has_key(key).expect("Test failed"));
+        }
+        let usage =
+            ibc::read_limits_usage(&shell.state, &token).expect("Test failed");
+        assert_eq!(usage.deposit, Amount::zero());
+        assert_eq!(usage.withdraw, Amount::zero());
+    }
+
+    /// Test that the engine applies the block-level updates like the shell,
+    /// with the same merkle roots over several epochs in which a proposal is
+    /// executed and the PoS rewards and the inflation are applied
+    #[test]
+    fn test_engine_matches_shell_over_epochs() {
+        use namada::ledger::engine::Engine;
+        use namada::state::mockdb::MockDB;
+
+        type TestState = FullAccessState<MockDB, Sha256Hasher>;
+
+        let (mut shell, _, _, _) = setup();
+        let (mut other, _, _, _) = setup();
+        assert_eq!(
+            shell.state.in_mem().merkle_root(),
+            other.state.in_mem().merkle_root()
+        );
+
+        // A proposal executed at the beginning of the epoch 2
+        let validator = shell.mode.get_validator_address().unwrap().clone();
+        let init_proposal = |state: &mut TestState| {
+            let proposal = InitProposalData {
+                id: 0,
+                content: Hash::default(),
+                author: validator.clone(),
+                voting_start_epoch: Epoch::default(),
+                voting_end_epoch: Epoch::default().next(),
+                grace_epoch: Epoch::default().next(),
+                r#type: ProposalType::Default(None),
+            };
+            namada::governance::init_proposal(state, proposal, vec![], None)
+                .unwrap();
+            let vote = VoteProposalData {
+                id: 0,
+                vote: ProposalVote::Yay,
+                voter: validator.clone(),
+                delegations: vec![],
+                memo: None,
+            };
+            namada::governance::vote_proposal(state, vote).unwrap();
+            state.commit_tx();
+        };
+        init_proposal(&mut shell.state);
+        init_proposal(&mut other.state);
+
+        let state = std::mem::replace(
+            &mut other.state,
+            TestState::open(
+                "",
+                None,
+                ChainId::default(),
+                address::testing::nam(),
+                None,
+                |_key| true,
+            ),
+        );
+        let mut engine = Engine::new(
+            state,
+            other.vp_wasm_cache.clone(),
+            other.tx_wasm_cache.clone(),
+        );
+
+        // Start a new epoch every few blocks
+        let force_new_epoch = |state: &mut TestState| {
+            let height = state.in_mem().get_last_block_height() + 1;
+            state.in_mem_mut().next_epoch_min_start_height = height;
+            state.in_mem_mut().next_epoch_min_start_time = DateTimeUtc::now();
+        };
+        let blocks_per_epoch = u64::from(EPOCH_SWITCH_BLOCKS_DELAY) + 1;
+        for block in 0..4 * blocks_per_epoch {
+            if block % blocks_per_epoch == 0 {
+                force_new_epoch(&mut shell.state);
+                force_new_epoch(engine.state_mut());
+            }
+            let votes = get_default_true_votes(
+                &shell.state,
+                shell.state.in_mem().block.epoch,
+            );
+            let mut req = FinalizeBlock {
+                votes: votes.clone(),
+                ..Default::default()
+            };
+            req.header.time = DateTimeUtc::now();
+
+            let (header, hash) = (req.header.clone(), req.hash.clone());
+            // The proposals are loaded before each block like the node does
+            let proposals =
+                load_proposals(&shell.state, shell.state.in_mem().last_epoch);
+            shell.proposal_data.extend(proposals);
+            shell.finalize_block(req).expect("Test failed");
+            shell.commit();
+
+            let votes = pos_votes_from_abci(engine.state(), &votes);
+            engine.begin_block(header, hash, votes, vec![]).unwrap();
+            engine.end_block(Some(&validator)).unwrap();
+            let root = engine.commit().unwrap();
+
+            assert_eq!(root, shell.state.in_mem().merkle_root());
+        }
+
+        assert!(shell.state.in_mem().block.epoch >= Epoch(3));
+        // The proposal has been executed by both
+        assert!(
+            get_proposal_result_record(engine.state(), 0)
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(
+            get_proposal_result_record(&shell.state, 0).unwrap(),
+            get_proposal_result_record(engine.state(), 0).unwrap()
+        );
+    }
 }
@@ -157,6 +157,12 @@ where
             ),
         )?;
 
+        // The new chain starts with the storage layout of this binary
+        migrations::write_storage_version(
+            &mut self.state,
+            migrations::STORAGE_VERSION,
+        )?;
+
         // Set the initial validator set
         response.validators = self
             .get_abci_validator_updates(true, |pk, power| {
@@ -435,9 +441,15 @@ where
             )
             .unwrap();
             if masp_params.is_some() {
-                // add token addresses to the masp reward conversions lookup
-                // table.
+                // add token addresses to the masp reward tokens and to the
+                // conversions lookup table.
                 let alias = alias.to_string();
+                namada::token::write_reward_token(
+                    &mut self.state,
+                    &alias,
+                    address,
+                )
+                .unwrap();
                 self.state
                     .in_mem_mut()
                     .conversion_state
@@ -7,7 +7,6 @@
 //! More info in <https://github.com/anoma/namada/issues/362>.
 pub mod block_alloc;
 mod finalize_block;
-mod governance;
 mod init_chain;
 pub use init_chain::InitChainValidation;
 use namada_sdk::state::StateRead;
@@ -20,7 +19,6 @@ mod stats;
 #[cfg(any(test, feature = "testing"))]
 #[allow(dead_code)]
 pub mod testing;
-pub mod utils;
 mod vote_extensions;
 
 use std::cell::RefCell;
@@ -31,6 +29,7 @@ use std::rc::Rc;
 
 use borsh::BorshDeserialize;
 use borsh_ext::BorshSerializeExt;
+use itertools::Itertools;
 use masp_primitives::transaction::Transaction;
 use namada::core::address::Address;
 use namada::core::chain::ChainId;
@@ -52,13 +51,12 @@ use namada::ledger::protocol::{
     apply_wasm_tx, get_fee_unshielding_transaction,
     get_transfer_hash_from_storage, ShellParams,
 };
-use namada::ledger::{parameters, protocol};
+use namada::ledger::{engine, ibc, parameters, protocol};
 use namada::parameters::validate_tx_bytes;
-use namada::proof_of_stake::storage::read_pos_params;
 use namada::state::tx_queue::{ExpiredTx, TxInQueue};
 use namada::state::{
-    DBIter, FullAccessState, Sha256Hasher, StorageHasher, StorageRead,
-    TempWlState, WlState, DB, EPOCH_SWITCH_BLOCKS_DELAY,
+    DBIter, FullAccessState, PruningConfig, Sha256Hasher, StorageHasher,
+    StorageRead, TempWlState, WlState, DB,
 };
 use namada::token;
 pub use namada::tx::data::ResultCode;
@@ -79,6 +77,7 @@ use crate::facade::tendermint::{self, validator};
 use crate::facade::tendermint_proto::v0_37::crypto::public_key;
 use crate::node::ledger::shims::abcipp_shim_types::shim;
 use crate::node::ledger::shims::abcipp_shim_types::shim::response::TxResult;
+use crate::node::ledger::storage::migrations;
 use crate::node::ledger::{storage, tendermint_node};
 use crate::wallet::{ValidatorData, ValidatorKeys};
 
@@ -123,6 +122,8 @@ pub enum Error {
     Storage(#[from] namada::state::StorageError),
     #[error("Transaction replay attempt: {0}")]
     ReplayAttempt(String),
+    #[error("Failed to migrate the storage: {0}")]
+    StorageMigration(#[from] migrations::MigrationError),
 }
 
 impl From<Error> for TxResult {
@@ -157,7 +158,7 @@ pub fn rollback(config: config::Ledger) -> Result<()> {
 
     // Rollback Namada state
     let db_path = config.shell.db_dir(&config.chain_id);
-    let mut db = storage::PersistentDB::open(db_path, None);
+    let mut db = storage::PersistentDB::open(db_path, None, None);
     tracing::info!("Rollback Namada state");
 
     db.rollback(tendermint_block_height)
@@ -348,10 +349,19 @@ where
     /// limit the how many block heights in the past can the storage be
     /// queried for reading values.
     storage_read_past_height_limit: Option<u64>,
+    /// Taken from config `storage_stats`. When set, the statistics of the DB
+    /// are logged on every block commit.
+    storage_stats: bool,
+    /// Taken from config `gas_trace`. When set, the gas consumed by the VPs
+    /// of a tx is broken down per label in the tx event attributes.
+    gas_trace: bool,
     /// Proposal execution tracking
     pub proposal_data: BTreeSet<u64>,
     /// Log of events emitted by `FinalizeBlock` ABCI calls.
     event_log: EventLog,
+    /// The coordinated upgrade height at which the storage migrations are
+    /// applied
+    storage_migration_height: Option<BlockHeight>,
 }
 
 /// Merkle tree storage key filter. Return `false` for keys that shouldn't be
@@ -406,6 +416,9 @@ where
         let mode = config.shell.tendermint_mode;
         let storage_read_past_height_limit =
             config.shell.storage_read_past_height_limit;
+        let storage_stats = config.shell.storage_stats;
+        let gas_trace = config.shell.gas_trace;
+        let audit_ibc = config.shell.audit_ibc;
         if !Path::new(&base_dir).is_dir() {
             std::fs::create_dir(&base_dir)
                 .expect("Creating directory for Namada should not fail");
@@ -431,14 +444,26 @@ where
         };
 
         // load last state from storage
-        let state = FullAccessState::open(
+        let merkle_tree_pruning = PruningConfig {
+            ibc_retention_epochs: config.shell.ibc_merkle_tree_retention_epochs,
+            general_retention_epochs: config.shell.merkle_tree_retention_epochs,
+            diff_retention_blocks: config.shell.diff_retention_blocks,
+        };
+        let state = FullAccessState::open_with_pruning(
             db_path,
             db_cache,
             chain_id.clone(),
             native_token,
             config.shell.storage_read_past_height_limit,
+            merkle_tree_pruning,
             is_merklized_storage_key,
-        );
+        )
+        // reserve the storage keys owned by the protocol subsystems
+        .with_reserved_prefixes(protocol::reserved_prefixes());
+        // refuse to start if the storage layout is ahead of the binary's
+        // version
+        migrations::check_storage_version(&state)
+            .unwrap_or_else(|err| panic!("Incompatible storage layout: {err}"));
         let vp_wasm_cache_dir =
             base_dir.join(chain_id.as_str()).join("vp_wasm_cache");
         let tx_wasm_cache_dir =
@@ -522,11 +547,17 @@ where
                 tx_wasm_compilation_cache as usize,
             ),
             storage_read_past_height_limit,
+            storage_stats,
+            gas_trace,
             proposal_data: BTreeSet::new(),
             // TODO: config event log params
             event_log: EventLog::default(),
+            storage_migration_height: config.shell.storage_migration_height,
         };
         shell.update_eth_oracle(&Default::default());
+        if audit_ibc {
+            shell.log_ibc_escrow_audit();
+        }
         shell
     }
 
@@ -614,21 +645,7 @@ where
         &self,
         current_epoch: namada_sdk::storage::Epoch,
     ) -> namada_sdk::storage::Epoch {
-        if let Some(delay) = self.state.in_mem().update_epoch_blocks_delay {
-            if delay == EPOCH_SWITCH_BLOCKS_DELAY {
-                // If we're about to update validator sets for the
-                // upcoming epoch, we can still remove the validator
-                current_epoch.next()
-            } else {
-                // If we're waiting to switch to a new epoch, it's too
-                // late to update validator sets
-                // on the next epoch, so we need to
-                // wait for the one after.
-                current_epoch.next().next()
-            }
-        } else {
-            current_epoch.next()
-        }
+        engine::validator_set_update_epoch(&self.state, current_epoch)
     }
 
     /// Commit a block. Persist the application state and return the Merkle root
@@ -636,15 +653,15 @@ where
     pub fn commit(&mut self) -> response::Commit {
         self.bump_last_processed_eth_block();
 
-        self.state
-            .commit_block()
+        let merkle_root = engine::commit_block(&mut self.state)
             .expect("Encountered a storage error while committing a block");
-
-        let merkle_root = self.state.in_mem().merkle_root();
         let committed_height = self.state.in_mem().get_last_block_height();
         tracing::info!(
             "Committed block hash: {merkle_root}, height: {committed_height}",
         );
+        if self.storage_stats {
+            self.log_storage_stats();
+        }
 
         self.broadcast_queued_txs();
 
@@ -657,6 +674,65 @@ where
         }
     }
 
+    /// Log the statistics of the DB column families
+    fn log_storage_stats(&self) {
+        match self.state.db().db_stats() {
+            Ok(stats) => {
+                for cf in stats.column_families {
+                    tracing::info!(
+                        column_family = %cf.name,
+                        estimated_keys = cf.estimated_keys,
+                        live_sst_bytes = cf.live_sst_bytes,
+                        pending_compaction_bytes = cf.pending_compaction_bytes,
+                        "Storage stats"
+                    );
+                }
+            }
+            Err(err) => {
+                tracing::error!("Failed to read the storage stats: {err}")
+            }
+        }
+    }
+
+    /// Audit the tokens escrowed by IBC and log the discrepancies
+    fn log_ibc_escrow_audit(&self) {
+        let tokens = match ibc::escrowed_tokens(&self.state) {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                tracing::error!(
+                    "Failed to read the IBC escrowed tokens: {err}"
+                );
+                return;
+            }
+        };
+        for token in tokens {
+            let report = match ibc::audit_ibc_escrow(&self.state, &token) {
+                Ok(report) => report,
+                Err(err) => {
+                    tracing::error!(
+                        "Failed to audit the IBC escrow of {token}: {err}"
+                    );
+                    continue;
+                }
+            };
+            if report.is_consistent() {
+                tracing::info!(%token, "The IBC escrow is consistent");
+                continue;
+            }
+            tracing::warn!(
+                %token,
+                balance = %report.balance.to_string_native(),
+                expected = %report.escrowed.to_string_native(),
+                fee_balance = %report.fee_balance.to_string_native(),
+                packet_fees = %report.packet_fees.to_string_native(),
+                in_flight = %report.in_flight.to_string_native(),
+                stale_packets = %report.stale_packets.iter().join(", "),
+                uncovered_packets = %report.uncovered_packets.iter().join(", "),
+                "The IBC escrow is inconsistent"
+            );
+        }
+    }
+
     /// Updates the Ethereum oracle's last processed block.
     #[inline]
     fn bump_last_processed_eth_block(&mut self) {
@@ -1412,7 +1488,7 @@ mod test_utils {
     use namada::proof_of_stake::parameters::PosParams;
     use namada::proof_of_stake::storage::validator_consensus_key_handle;
     use namada::state::mockdb::MockDB;
-    use namada::state::{LastBlock, StorageWrite};
+    use namada::state::{LastBlock, StorageWrite, EPOCH_SWITCH_BLOCKS_DELAY};
     use namada::tendermint::abci::types::VoteInfo;
     use namada::token::conversion::update_allowed_conversions;
     use namada::tx::data::Fee;
@@ -2022,6 +2098,104 @@ mod test_utils {
         assert!(!shell.state.in_mem().tx_queue.is_empty());
     }
 
+    /// Start a shell over the RocksDB storage of the given config
+    fn new_persistent_shell(
+        config: config::Ledger,
+    ) -> Shell<PersistentDB, PersistentStorageHasher> {
+        let (sender, _) = tokio::sync::mpsc::unbounded_channel();
+        let (_, eth_receiver) =
+            tokio::sync::mpsc::channel(ORACLE_CHANNEL_BUFFER_SIZE);
+        let (control_sender, _) = oracle::control::channel();
+        let (_, last_processed_block_receiver) =
+            last_processed_block::channel();
+        let eth_oracle = EthereumOracleChannels::new(
+            eth_receiver,
+            control_sender,
+            last_processed_block_receiver,
+        );
+        let vp_wasm_compilation_cache = 50 * 1024 * 1024; // 50 kiB
+        let tx_wasm_compilation_cache = 50 * 1024 * 1024; // 50 kiB
+        Shell::<PersistentDB, PersistentStorageHasher>::new(
+            config,
+            top_level_directory().join("wasm"),
+            sender,
+            Some(eth_oracle),
+            None,
+            vp_wasm_compilation_cache,
+            tx_wasm_compilation_cache,
+        )
+    }
+
+    /// Write the fixture of a storage in the DB directory of the given
+    /// config. The fixture has the layout predating the storage versioning,
+    /// with an IBC denom without its lookup by the IBC token, and the given
+    /// storage version, if any.
+    fn write_storage_fixture(
+        config: &config::Ledger,
+        storage_version: Option<u64>,
+    ) -> (Key, String) {
+        use namada::ibc::storage::ibc_denom_key;
+
+        use crate::node::ledger::storage::benches::{
+            begin_next_block, open_state,
+        };
+
+        let mut state = open_state(config.db_dir());
+        begin_next_block(&mut state).expect("begin_block failed");
+        let denom_key = ibc_denom_key("uatom", "hash");
+        let denom = "transfer/channel-0/uatom".to_string();
+        state.write(&denom_key, &denom).expect("write failed");
+        if let Some(version) = storage_version {
+            migrations::write_storage_version(&mut state, version)
+                .expect("write failed");
+        }
+        state.commit_block().expect("commit failed");
+        (denom_key, denom)
+    }
+
+    /// Test that the shell starts on a storage with the layout predating the
+    /// storage versioning, which can then be migrated
+    #[test]
+    fn test_open_old_storage_layout() {
+        let base_dir = tempdir().unwrap().as_ref().canonicalize().unwrap();
+        let config = config::Ledger::new(
+            base_dir,
+            Default::default(),
+            TendermintMode::Validator,
+        );
+        let (denom_key, denom) = write_storage_fixture(&config, None);
+
+        let mut shell = new_persistent_shell(config);
+        assert_eq!(migrations::read_storage_version(&shell.state).unwrap(), 0);
+        let stored: Option<String> = shell.state.read(&denom_key).unwrap();
+        assert_eq!(stored, Some(denom));
+
+        let applied = migrations::migrate(&mut shell.state).unwrap();
+        assert_eq!(
+            applied,
+            Some(migrations::AppliedMigrations {
+                from_version: 0,
+                to_version: migrations::STORAGE_VERSION,
+            })
+        );
+    }
+
+    /// Test that the shell refuses to start on a storage whose version is
+    /// ahead of the binary's version
+    #[test]
+    #[should_panic(expected = "Incompatible storage layout")]
+    fn test_open_storage_version_ahead() {
+        let base_dir = tempdir().unwrap().as_ref().canonicalize().unwrap();
+        let config = config::Ledger::new(
+            base_dir,
+            Default::default(),
+            TendermintMode::Validator,
+        );
+        write_storage_fixture(&config, Some(migrations::STORAGE_VERSION + 1));
+
+        new_persistent_shell(config);
+    }
+
     pub(super) fn get_pkh_from_address<S>(
         storage: &S,
         params: &PosParams,
@@ -1,7 +1,7 @@
 //! Shell methods for querying state
 
-use namada::ledger::dry_run_tx;
 use namada::ledger::queries::{RequestCtx, ResponseQuery};
+use namada::ledger::{dry_run_tx, dry_run_vps};
 
 use super::*;
 
@@ -26,6 +26,8 @@ where
         // Invoke the root RPC handler - returns borsh-encoded data on success
         let result = if query.path == "/shell/dry_run_tx" {
             dry_run_tx(ctx, &query)
+        } else if query.path == "/shell/dry_run_vps" {
+            dry_run_vps(ctx, &query)
         } else {
             namada::ledger::queries::handle_path(ctx, &query)
         };
@@ -67,6 +69,7 @@ mod test_queries {
     use namada::ledger::pos::PosQueries;
     use namada::proof_of_stake::storage::read_consensus_validator_set_addresses_with_stake;
     use namada::proof_of_stake::types::WeightedValidator;
+    use namada::state::EPOCH_SWITCH_BLOCKS_DELAY;
     use namada::tendermint::abci::types::VoteInfo;
     use namada_sdk::eth_bridge::SendValsetUpd;
 
@@ -0,0 +1,486 @@
+//! Migrations of the storage layout, applied in the block of the upgrade
+//! height coordinated by the nodes of a chain when the binary's storage
+//! version is ahead of the stored version.
+//!
+//! The migrations are written in the block write log, together with the bump
+//! of the stored version, so that they're committed and merkleized with the
+//! block on every node. A new chain starts with the binary's version, written
+//! on the chain's initialization, so the migrations only apply to the chains
+//! created with an older layout, which are at the version `0` if they predate
+//! the versioning. A node refuses to start on a storage whose version is
+//! ahead of its binary's version.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use namada::core::address::{Address, InternalAddress, IBC_FEE_ESCROW};
+use namada::core::ibc::PacketFee;
+use namada::core::storage::{Key, KeySeg};
+use namada::ibc::storage::{
+    escrow_amount_key, fee_escrow_amount_key, ibc_denom_by_token_key,
+    ibc_denom_key_prefix, ibc_token, is_ibc_denom_key, packet_fee_prefix,
+};
+use namada::proof_of_stake::storage::validator_delegators_handle;
+use namada::proof_of_stake::storage_key::{
+    bonds_prefix, is_bond_key, is_unbond_key, unbonds_prefix,
+};
+use namada::proof_of_stake::types::BondId;
+use namada::state::{
+    self, DBIter, StorageError, StorageHasher, StorageRead, StorageResult,
+    StorageWrite, DB,
+};
+use namada::token::storage_key::is_any_token_balance_key;
+use namada::token::{read_balance, transfer, Amount};
+use namada_sdk::state::FullAccessState;
+use thiserror::Error;
+
+/// The version of the storage layout of this binary
+pub const STORAGE_VERSION: u64 = 3;
+
+/// The sub-key of the parameters holding the storage version
+const STORAGE_VERSION_KEY: &str = "storage_version";
+
+/// Errors of the storage migrations
+#[derive(Error, Debug)]
+pub enum MigrationError {
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+    #[error(
+        "The storage version {stored} is ahead of the binary's version \
+         {STORAGE_VERSION}"
+    )]
+    VersionAhead { stored: u64 },
+    #[error("No migration from the storage version {0}")]
+    MissingMigration(u64),
+    #[error(
+        "The migration from the storage version {from_version} to \
+         {to_version} failed: {error}"
+    )]
+    Failed {
+        from_version: u64,
+        to_version: u64,
+        error: StorageError,
+    },
+}
+
+/// A migration of the storage layout from a version to the next one
+pub struct Migration<D, H>
+where
+    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
+    H: StorageHasher + Sync + 'static,
+{
+    /// The storage version the migration applies to
+    pub from_version: u64,
+    /// The storage version after the migration
+    pub to_version: u64,
+    /// Write the changes of the layout to the write log
+    pub run: fn(&mut FullAccessState<D, H>) -> StorageResult<()>,
+}
+
+/// The storage migrations applied in a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppliedMigrations {
+    /// The stored version before the migrations
+    pub from_version: u64,
+    /// The stored version after the migrations
+    pub to_version: u64,
+}
+
+/// The registry of the storage migrations, in the order of the versions
+pub fn registry<D, H>() -> Vec<Migration<D, H>>
+where
+    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
+    H: StorageHasher + Sync + 'static,
+{
+    vec![
+        Migration {
+            from_version: 0,
+            to_version: 1,
+            run: write_ibc_denom_by_token_index,
+        },
+        Migration {
+            from_version: 1,
+            to_version: 2,
+            run: write_ibc_escrow_amounts,
+        },
+        Migration {
+            from_version: 2,
+            to_version: 3,
+            run: write_validator_delegators_index,
+        },
+    ]
+}
+
+/// The storage key of the storage version
+pub fn storage_version_key() -> Key {
+    Key::from(Address::Internal(InternalAddress::Parameters).to_db_key())
+        .push(&STORAGE_VERSION_KEY.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Read the stored storage version. The layout before the versioning was
+/// introduced is the version `0`.
+pub fn read_storage_version<S>(storage: &S) -> StorageResult<u64>
+where
+    S: StorageRead,
+{
+    Ok(storage.read(&storage_version_key())?.unwrap_or_default())
+}
+
+/// Write the storage version
+pub fn write_storage_version<S>(
+    storage: &mut S,
+    version: u64,
+) -> StorageResult<()>
+where
+    S: StorageWrite,
+{
+    storage.write(&storage_version_key(), version)
+}
+
+/// Check that the stored storage version can be handled by this binary, i.e.
+/// that it isn't ahead of the binary's version. Returns the stored version.
+pub fn check_storage_version<S>(storage: &S) -> Result<u64, MigrationError>
+where
+    S: StorageRead,
+{
+    let stored = read_storage_version(storage)?;
+    if stored > STORAGE_VERSION {
+        return Err(MigrationError::VersionAhead { stored });
+    }
+    Ok(stored)
+}
+
+/// Write the migrations from the stored storage version to the binary's
+/// version in the block write log. Returns `None` if the storage is already
+/// up to date or if the chain hasn't been initialized yet.
+pub fn migrate<D, H>(
+    state: &mut FullAccessState<D, H>,
+) -> Result<Option<AppliedMigrations>, MigrationError>
+where
+    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
+    H: StorageHasher + Sync + 'static,
+{
+    if state.in_mem().last_block.is_none() {
+        return Ok(None);
+    }
+    let stored = check_storage_version(state)?;
+    if stored == STORAGE_VERSION {
+        return Ok(None);
+    }
+    apply_migrations(state, stored, STORAGE_VERSION, &registry()).map(Some)
+}
+
+/// Run the registered migrations from the given version up to the target
+/// version and write the target version
+fn apply_migrations<D, H>(
+    state: &mut FullAccessState<D, H>,
+    from_version: u64,
+    to_version: u64,
+    migrations: &[Migration<D, H>],
+) -> Result<AppliedMigrations, MigrationError>
+where
+    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
+    H: StorageHasher + Sync + 'static,
+{
+    let mut version = from_version;
+    while version < to_version {
+        let migration = migrations
+            .iter()
+            .find(|migration| migration.from_version == version)
+            .ok_or(MigrationError::MissingMigration(version))?;
+        tracing::info!(
+            "Migrating the storage from the version {} to {}",
+            migration.from_version,
+            migration.to_version
+        );
+        (migration.run)(state).map_err(|error| MigrationError::Failed {
+            from_version: migration.from_version,
+            to_version: migration.to_version,
+            error,
+        })?;
+        version = migration.to_version;
+    }
+    write_storage_version(state, version)?;
+    Ok(AppliedMigrations {
+        from_version,
+        to_version: version,
+    })
+}
+
+/// Write the lookup of the IBC denoms by the IBC tokens derived from them for
+/// the denoms received before the lookup was introduced
+fn write_ibc_denom_by_token_index<D, H>(
+    state: &mut FullAccessState<D, H>,
+) -> StorageResult<()>
+where
+    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
+    H: StorageHasher + Sync + 'static,
+{
+    let denoms =
+        state::iter_prefix::<String>(state, &ibc_denom_key_prefix(None))?
+            .filter_map(|entry| match entry {
+                Ok((key, denom)) => is_ibc_denom_key(&key).map(|_| Ok(denom)),
+                Err(err) => Some(Err(err)),
+            })
+            .collect::<StorageResult<Vec<_>>>()?;
+    for denom in denoms {
+        let key = ibc_denom_by_token_key(&ibc_token(&denom));
+        if !state.has_key(&key)? {
+            state.write(&key, denom)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the amounts of the tokens escrowed by IBC transfers and for the
+/// relayer fees, which are recorded from the transfers since the audit was
+/// introduced. The relayer fees are the sum of the fees recorded for the
+/// in-flight packets. The part of them still held by the IBC account is moved
+/// to the relayer fee escrow account and the rest of the balance of the IBC
+/// account has been escrowed by the transfers. The escrow of each in-flight
+/// packet can't be recovered from its commitment, so the packets sent before
+/// aren't recorded.
+fn write_ibc_escrow_amounts<D, H>(
+    state: &mut FullAccessState<D, H>,
+) -> StorageResult<()>
+where
+    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
+    H: StorageHasher + Sync + 'static,
+{
+    let ibc = Address::Internal(InternalAddress::Ibc);
+    // The balances aren't indexed by the owner
+    let multitoken =
+        Key::from(Address::Internal(InternalAddress::Multitoken).to_db_key());
+    let mut tokens: BTreeSet<Address> = state
+        .db()
+        .iter_prefix(Some(&multitoken))
+        .filter_map(|(key, _, _)| {
+            let key = Key::parse(key).ok()?;
+            match is_any_token_balance_key(&key) {
+                Some([token, owner]) if *owner == ibc => Some(token.clone()),
+                _ => None,
+            }
+        })
+        .collect();
+
+    let mut fees: BTreeMap<Address, Amount> = BTreeMap::new();
+    for entry in state::iter_prefix::<PacketFee>(state, &packet_fee_prefix())? {
+        let (_, fee) = entry?;
+        let total = fees.entry(fee.token).or_default();
+        for amount in [fee.recv_fee, fee.ack_fee, fee.timeout_fee] {
+            *total = total.checked_add(amount.amount()).ok_or_else(|| {
+                StorageError::new_const("The relayer fees overflow")
+            })?;
+        }
+    }
+    tokens.extend(fees.keys().cloned());
+
+    for token in tokens {
+        let fee = fees.remove(&token).unwrap_or_default();
+        // The fees escrowed before the fee escrow account was introduced are
+        // held by the IBC account
+        let fee_balance = read_balance(state, &token, &IBC_FEE_ESCROW)?;
+        let unmoved = fee.checked_sub(fee_balance).unwrap_or_default();
+        transfer(state, &token, &ibc, &IBC_FEE_ESCROW, unmoved).map_err(
+            |_| {
+                StorageError::new(format!(
+                    "The IBC balance of {token} doesn't cover the relayer fees"
+                ))
+            },
+        )?;
+        let escrowed = read_balance(state, &token, &ibc)?;
+        state.write(&escrow_amount_key(&token), escrowed)?;
+        state.write(&fee_escrow_amount_key(&token), fee)?;
+    }
+    Ok(())
+}
+
+/// Write the index of the sources of the bonds to each validator for the
+/// bonds and unbonds made before the index was introduced
+fn write_validator_delegators_index<D, H>(
+    state: &mut FullAccessState<D, H>,
+) -> StorageResult<()>
+where
+    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
+    H: StorageHasher + Sync + 'static,
+{
+    let mut bond_ids = BTreeSet::new();
+    for prefix in [bonds_prefix(), unbonds_prefix()] {
+        for entry in state::iter_prefix_bytes(state, &prefix)? {
+            let (key, _) = entry?;
+            let bond_id = is_bond_key(&key)
+                .map(|(bond_id, _)| bond_id)
+                .or_else(|| is_unbond_key(&key).map(|(bond_id, _, _)| bond_id));
+            bond_ids.extend(bond_id);
+        }
+    }
+    for BondId { source, validator } in bond_ids {
+        validator_delegators_handle(&validator).insert(state, source)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use namada::core::address;
+    use namada::core::chain::ChainId;
+    use namada::core::ibc::core::host::types::identifiers::{
+        ChannelId, PortId, Sequence,
+    };
+    use namada::core::storage::{BlockHash, BlockHeight, Epoch};
+    use namada::core::token::DenominatedAmount;
+    use namada::ibc::storage::{ibc_denom_key, packet_fee_key};
+    use namada::ledger::ibc::{audit_ibc_escrow, read_ibc_denom};
+    use namada::proof_of_stake::storage::bond_handle;
+    use namada::token::storage_key::balance_key;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::node::ledger::storage::benches::{begin_next_block, open_state};
+    use crate::node::ledger::storage::{export_snapshot, import_snapshot};
+
+    /// Test that the migrations are applied to a snapshot of the state with
+    /// the layout before the storage versioning and that re-running them
+    /// after the commit is a no-op
+    #[test]
+    fn test_migrate_old_layout_snapshot() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let mut state = open_state(db_path.path());
+
+        // The old layout only has the denoms by the trace hashes
+        let denom_a = "transfer/channel-0/uatom";
+        let denom_b = "transfer/channel-1/transfer/channel-0/uosmo";
+        let owner = address::testing::established_address_1();
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(1))
+            .expect("begin_block failed");
+        state
+            .in_mem_mut()
+            .block
+            .pred_epochs
+            .new_epoch(BlockHeight(1));
+        state
+            .write(&ibc_denom_key(owner.to_string(), "hash_a"), denom_a)
+            .expect("write failed");
+        state
+            .write(&ibc_denom_key("uatom", "hash_a"), denom_a)
+            .expect("write failed");
+        state
+            .write(&ibc_denom_key(owner.to_string(), "hash_b"), denom_b)
+            .expect("write failed");
+        // The IBC account holds the token escrowed by the transfers and for
+        // the relayer fees of an in-flight packet, which are moved to the fee
+        // escrow account
+        let token = address::testing::nam();
+        let ibc = Address::Internal(InternalAddress::Ibc);
+        state
+            .write(&balance_key(&token, &ibc), Amount::native_whole(10))
+            .expect("write failed");
+        let fee = PacketFee {
+            payer: owner.clone(),
+            token: token.clone(),
+            recv_fee: DenominatedAmount::native(Amount::native_whole(1)),
+            ack_fee: DenominatedAmount::native(Amount::native_whole(1)),
+            timeout_fee: DenominatedAmount::native(Amount::zero()),
+        };
+        let fee_key = packet_fee_key(
+            &PortId::transfer(),
+            &ChannelId::new(0),
+            Sequence::from(1),
+        );
+        state.write(&fee_key, fee).expect("write failed");
+        // The old layout has no index of the sources of the bonds
+        let validator = address::testing::established_address_2();
+        bond_handle(&owner, &validator)
+            .get_data_handler()
+            .insert(&mut state, Epoch(0), Amount::native_whole(1))
+            .expect("write failed");
+        state.commit_block().expect("commit failed");
+
+        let snapshot_dir = TempDir::new()
+            .expect("Unable to create a temporary snapshot directory");
+        let snapshot_path = snapshot_dir.path().join("snapshot");
+        export_snapshot(&state, &snapshot_path, BlockHeight(1)).unwrap();
+        let imported_path = snapshot_dir.path().join("imported");
+        let mut imported = import_snapshot(
+            &snapshot_path,
+            &imported_path,
+            None,
+            ChainId::default(),
+            address::testing::nam(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(read_storage_version(&imported).unwrap(), 0);
+
+        let applied = migrate(&mut imported).unwrap();
+        assert_eq!(
+            applied,
+            Some(AppliedMigrations {
+                from_version: 0,
+                to_version: STORAGE_VERSION,
+            })
+        );
+        begin_next_block(&mut imported).unwrap();
+        imported.commit_block().expect("commit failed");
+
+        for denom in [denom_a, denom_b] {
+            assert_eq!(
+                read_ibc_denom(&imported, &ibc_token(denom)).unwrap(),
+                Some(denom.to_string())
+            );
+        }
+        let report = audit_ibc_escrow(&imported, &token).unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(report.escrowed, Amount::native_whole(8));
+        assert_eq!(report.packet_fees, Amount::native_whole(2));
+        assert_eq!(report.balance, Amount::native_whole(8));
+        assert_eq!(report.fee_balance, Amount::native_whole(2));
+        assert!(
+            validator_delegators_handle(&validator)
+                .contains(&imported, &owner)
+                .unwrap()
+        );
+        assert_eq!(read_storage_version(&imported).unwrap(), STORAGE_VERSION);
+
+        // Re-running the migrations once migrated doesn't write anything
+        drop(imported);
+        let mut restarted = open_state(&imported_path);
+        assert_eq!(migrate(&mut restarted).unwrap(), None);
+        assert!(
+            restarted
+                .write_log()
+                .read(&storage_version_key())
+                .0
+                .is_none()
+        );
+    }
+
+    /// Test that the migrations are refused if the stored version is ahead
+    /// of the binary's version or if a migration is missing
+    #[test]
+    fn test_migrate_refused() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let mut state = open_state(db_path.path());
+        let ahead = STORAGE_VERSION + 1;
+        begin_next_block(&mut state).unwrap();
+        write_storage_version(&mut state, ahead).unwrap();
+        state.commit_block().expect("commit failed");
+
+        let result = migrate(&mut state);
+        assert!(matches!(
+            result,
+            Err(MigrationError::VersionAhead { stored }) if stored == ahead
+        ));
+
+        let result = apply_migrations(&mut state, 0, ahead, &registry());
+        assert!(matches!(
+            result,
+            Err(MigrationError::MissingMigration(version))
+                if version == STORAGE_VERSION
+        ));
+    }
+}
@@ -1,16 +1,42 @@
 //! The storage module handles both the current state in-memory and the stored
 //! state in DB.
 
+pub mod migrations;
 mod rocksdb;
 
 use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 
 use arse_merkle_tree::blake2b::Blake2bHasher;
 use arse_merkle_tree::traits::Hasher;
 use arse_merkle_tree::H256;
 use blake2b_rs::{Blake2b, Blake2bBuilder};
-use namada::state::StorageHasher;
+use borsh::{BorshDeserialize, BorshSerialize};
+use borsh_ext::BorshSerializeExt;
+use namada::core::address::Address;
+use namada::core::chain::ChainId;
+use namada::core::hash::Hash;
+use namada::core::storage::BlockHeight;
+use namada::state::{DbError, StateRead, StorageHasher, DB};
 use namada_sdk::state::FullAccessState;
+use thiserror::Error;
+
+use self::rocksdb::{SnapshotColumn, SnapshotEntry};
+use crate::node::ledger::shell::is_merklized_storage_key;
+
+/// The magic bytes at the start of a storage snapshot
+const SNAPSHOT_MAGIC: &[u8; 8] = b"NAMSNAP\0";
+
+/// The version of the storage snapshot format
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// The target size in bytes of the key-vals in a snapshot chunk
+const SNAPSHOT_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// The max length in bytes of a snapshot frame
+const MAX_SNAPSHOT_FRAME_LEN: u64 = 1024 * 1024 * 1024;
 
 #[derive(Default)]
 pub struct PersistentStorageHasher(Blake2bHasher);
@@ -50,6 +76,540 @@ fn new_blake2b() -> Blake2b {
     Blake2bBuilder::new(32).personal(b"namada storage").build()
 }
 
+/// Errors of the storage snapshots
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("Snapshot I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("DB error: {0}")]
+    Db(#[from] DbError),
+    #[error("Storage error: {0}")]
+    Storage(#[from] namada::state::Error),
+    #[error(
+        "Only the last committed height {last_height} can be exported, got \
+         {height}"
+    )]
+    HeightNotLast {
+        height: BlockHeight,
+        last_height: BlockHeight,
+    },
+    #[error("The file isn't a storage snapshot")]
+    InvalidMagic,
+    #[error(
+        "Unsupported snapshot format version {0}, expected \
+         {SNAPSHOT_FORMAT_VERSION}"
+    )]
+    UnsupportedVersion(u32),
+    #[error("The snapshot is truncated")]
+    Truncated,
+    #[error("The snapshot frame {index} is too long: {len} bytes")]
+    FrameTooLong { index: u64, len: u64 },
+    #[error("Checksum mismatch of the snapshot frame {index}")]
+    ChecksumMismatch { index: u64 },
+    #[error("Error decoding the snapshot frame {index}: {error}")]
+    Decoding { index: u64, error: io::Error },
+    #[error("Unexpected snapshot frame {index}")]
+    UnexpectedFrame { index: u64 },
+    #[error(
+        "The snapshot has {num_chunks} chunks with {num_entries} key-vals, \
+         expected {expected_chunks} chunks with {expected_entries} key-vals"
+    )]
+    MissingChunks {
+        num_chunks: u64,
+        num_entries: u64,
+        expected_chunks: u64,
+        expected_entries: u64,
+    },
+    #[error("The snapshot is for the chain {snapshot}, expected {expected}")]
+    ChainIdMismatch {
+        snapshot: ChainId,
+        expected: ChainId,
+    },
+    #[error("The DB directory {} already exists", .0.to_string_lossy())]
+    DbExists(PathBuf),
+    #[error(
+        "The imported state at the height {height} doesn't match the snapshot \
+         at the height {expected_height}"
+    )]
+    HeightMismatch {
+        height: BlockHeight,
+        expected_height: BlockHeight,
+    },
+    #[error(
+        "The imported merkle root {root} doesn't match the snapshot's root \
+         {expected_root}"
+    )]
+    MerkleRootMismatch { root: Hash, expected_root: Hash },
+}
+
+/// The header of a storage snapshot
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct SnapshotHeader {
+    /// The chain of the snapshot
+    pub chain_id: ChainId,
+    /// The height of the snapshot's block
+    pub height: BlockHeight,
+    /// The merkle root at the height
+    pub merkle_root: Hash,
+}
+
+/// A frame of a storage snapshot
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+enum SnapshotFrame {
+    Header(SnapshotHeader),
+    Chunk(Vec<SnapshotEntry>),
+    End { num_chunks: u64, num_entries: u64 },
+}
+
+/// Export a snapshot of the state at the given height, which must be the last
+/// committed height, to a new file at the given path. A new node can be
+/// bootstrapped from it with [`import_snapshot`] instead of replaying the
+/// chain.
+///
+/// The snapshot starts with magic bytes and the format version, followed by
+/// frames. Each frame is its length, the borsh-encoded frame and its SHA-256
+/// checksum. The first frame is the header with the chain ID, the height and
+/// the merkle root, followed by the chunks of the DB's key-vals and the end
+/// frame with their counts.
+pub fn export_snapshot(
+    state: &PersistentState,
+    path: &Path,
+    height: BlockHeight,
+) -> Result<SnapshotHeader, SnapshotError> {
+    write_snapshot(state, path, height, SNAPSHOT_CHUNK_SIZE)
+}
+
+fn write_snapshot(
+    state: &PersistentState,
+    path: &Path,
+    height: BlockHeight,
+    chunk_size: usize,
+) -> Result<SnapshotHeader, SnapshotError> {
+    let last_height = state.in_mem().get_last_block_height();
+    if height.0 == 0 || height != last_height {
+        return Err(SnapshotError::HeightNotLast {
+            height,
+            last_height,
+        });
+    }
+    let pred_epochs = &state.in_mem().block.pred_epochs;
+    let epoch = pred_epochs.get_epoch(height).unwrap_or_default();
+    let epoch_start_height = match pred_epochs.get_start_height_of_epoch(epoch)
+    {
+        Some(BlockHeight(0)) | None => BlockHeight(1),
+        Some(height) => height,
+    };
+    let header = SnapshotHeader {
+        chain_id: state.in_mem().chain_id.clone(),
+        height,
+        merkle_root: Hash(state.get_merkle_tree(height, None)?.root().0),
+    };
+
+    let file = File::options().write(true).create_new(true).open(path)?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(SNAPSHOT_MAGIC)?;
+    writer.write_all(&SNAPSHOT_FORMAT_VERSION.to_le_bytes())?;
+    write_frame(&mut writer, &SnapshotFrame::Header(header.clone()))?;
+
+    let mut chunk = vec![];
+    let mut chunk_len = 0;
+    let mut num_chunks = 0;
+    let mut num_entries = 0;
+    state.db().snapshot_entries(
+        height,
+        epoch,
+        epoch_start_height,
+        |entry| -> Result<(), SnapshotError> {
+            chunk_len += entry.key.len() + entry.value.len();
+            chunk.push(entry);
+            num_entries += 1;
+            if chunk_len >= chunk_size {
+                let entries = std::mem::take(&mut chunk);
+                write_frame(&mut writer, &SnapshotFrame::Chunk(entries))?;
+                chunk_len = 0;
+                num_chunks += 1;
+            }
+            Ok(())
+        },
+    )?;
+    if !chunk.is_empty() {
+        write_frame(&mut writer, &SnapshotFrame::Chunk(chunk))?;
+        num_chunks += 1;
+    }
+    write_frame(
+        &mut writer,
+        &SnapshotFrame::End {
+            num_chunks,
+            num_entries,
+        },
+    )?;
+    writer
+        .into_inner()
+        .map_err(|err| err.into_error())?
+        .sync_all()?;
+    Ok(header)
+}
+
+/// Import a snapshot exported with [`export_snapshot`] into a new DB at the
+/// given path and open the state from it. The snapshot is refused if it's for
+/// another chain, if it's corrupted or if the merkle root of the imported
+/// state doesn't match the snapshot's root, in which case the new DB is
+/// removed.
+pub fn import_snapshot(
+    snapshot_path: &Path,
+    db_path: &Path,
+    cache: Option<&::rocksdb::Cache>,
+    chain_id: ChainId,
+    native_token: Address,
+    storage_read_past_height_limit: Option<u64>,
+) -> Result<PersistentState, SnapshotError> {
+    let mut reader = BufReader::new(File::open(snapshot_path)?);
+    let mut magic = [0; SNAPSHOT_MAGIC.len()];
+    read_snapshot_bytes(&mut reader, &mut magic)?;
+    if &magic != SNAPSHOT_MAGIC {
+        return Err(SnapshotError::InvalidMagic);
+    }
+    let mut version = [0; 4];
+    read_snapshot_bytes(&mut reader, &mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != SNAPSHOT_FORMAT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+    let header = match read_frame(&mut reader, 0)? {
+        SnapshotFrame::Header(header) => header,
+        _ => return Err(SnapshotError::UnexpectedFrame { index: 0 }),
+    };
+    if header.chain_id != chain_id {
+        return Err(SnapshotError::ChainIdMismatch {
+            snapshot: header.chain_id,
+            expected: chain_id,
+        });
+    }
+    if db_path.exists() {
+        return Err(SnapshotError::DbExists(db_path.to_owned()));
+    }
+
+    let state = write_snapshot_db(&mut reader, db_path, cache).and_then(|()| {
+        open_snapshot_state(
+            &header,
+            db_path,
+            cache,
+            native_token,
+            storage_read_past_height_limit,
+        )
+    });
+    if state.is_err() {
+        // Don't leave an inconsistent DB behind
+        if let Err(err) = std::fs::remove_dir_all(db_path) {
+            tracing::error!(
+                "Failed to remove the DB {}: {err}",
+                db_path.to_string_lossy()
+            );
+        }
+    }
+    state
+}
+
+/// Write the key-vals of the snapshot's chunks into a new DB
+fn write_snapshot_db(
+    reader: &mut impl Read,
+    db_path: &Path,
+    cache: Option<&::rocksdb::Cache>,
+) -> Result<(), SnapshotError> {
+    let mut db = PersistentDB::open(db_path, cache, None);
+    let mut num_chunks = 0;
+    let mut num_entries = 0;
+    let mut index = 1;
+    loop {
+        match read_frame(reader, index)? {
+            SnapshotFrame::Chunk(entries) => {
+                num_chunks += 1;
+                num_entries += entries.len() as u64;
+                db.write_snapshot_entries(entries)?;
+            }
+            SnapshotFrame::End {
+                num_chunks: expected_chunks,
+                num_entries: expected_entries,
+            } => {
+                if (num_chunks, num_entries)
+                    != (expected_chunks, expected_entries)
+                {
+                    return Err(SnapshotError::MissingChunks {
+                        num_chunks,
+                        num_entries,
+                        expected_chunks,
+                        expected_entries,
+                    });
+                }
+                return Ok(());
+            }
+            SnapshotFrame::Header(_) => {
+                return Err(SnapshotError::UnexpectedFrame { index });
+            }
+        }
+        index += 1;
+    }
+}
+
+/// Open the state from the imported DB and check it against the snapshot's
+/// header
+fn open_snapshot_state(
+    header: &SnapshotHeader,
+    db_path: &Path,
+    cache: Option<&::rocksdb::Cache>,
+    native_token: Address,
+    storage_read_past_height_limit: Option<u64>,
+) -> Result<PersistentState, SnapshotError> {
+    let state = PersistentState::open(
+        db_path,
+        cache,
+        header.chain_id.clone(),
+        native_token,
+        storage_read_past_height_limit,
+        is_merklized_storage_key,
+    );
+    let height = state.in_mem().get_last_block_height();
+    if height != header.height {
+        return Err(SnapshotError::HeightMismatch {
+            height,
+            expected_height: header.height,
+        });
+    }
+    // Check both the loaded tree and the tree rebuilt from the epoch's stores
+    // and diffs
+    let roots = [
+        state.in_mem().merkle_root(),
+        state.get_merkle_tree(height, None)?.root(),
+    ];
+    for root in roots {
+        let root = Hash(root.0);
+        if root != header.merkle_root {
+            return Err(SnapshotError::MerkleRootMismatch {
+                root,
+                expected_root: header.merkle_root,
+            });
+        }
+    }
+    Ok(state)
+}
+
+fn write_frame(
+    writer: &mut impl Write,
+    frame: &SnapshotFrame,
+) -> io::Result<()> {
+    let bytes = frame.serialize_to_vec();
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(&bytes)?;
+    writer.write_all(&Hash::sha256(&bytes).0)
+}
+
+fn read_frame(
+    reader: &mut impl Read,
+    index: u64,
+) -> Result<SnapshotFrame, SnapshotError> {
+    let mut len = [0; 8];
+    read_snapshot_bytes(reader, &mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len > MAX_SNAPSHOT_FRAME_LEN {
+        return Err(SnapshotError::FrameTooLong { index, len });
+    }
+    let mut bytes = vec![0; len as usize];
+    read_snapshot_bytes(reader, &mut bytes)?;
+    let mut checksum = [0; 32];
+    read_snapshot_bytes(reader, &mut checksum)?;
+    if Hash::sha256(&bytes).0 != checksum {
+        return Err(SnapshotError::ChecksumMismatch { index });
+    }
+    SnapshotFrame::try_from_slice(&bytes)
+        .map_err(|error| SnapshotError::Decoding { index, error })
+}
+
+fn read_snapshot_bytes(
+    reader: &mut impl Read,
+    buf: &mut [u8],
+) -> Result<(), SnapshotError> {
+    reader.read_exact(buf).map_err(|err| {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            SnapshotError::Truncated
+        } else {
+            SnapshotError::Io(err)
+        }
+    })
+}
+
+/// Generators of block write logs and helpers to measure the storage
+/// operations on them, shared by the storage benchmarks and the regression
+/// tests.
+#[cfg(any(test, feature = "benches"))]
+pub mod benches {
+    use std::path::Path;
+
+    use namada::core::storage::{BlockHash, Header, Key};
+    use namada::core::time::DateTimeUtc;
+    use namada::state::{ResultExt, StorageResult};
+
+    use super::*;
+    use crate::node::ledger::shell::is_merklized_storage_key;
+
+    /// The shape of a generated block write log
+    #[derive(Clone, Copy, Debug)]
+    pub struct WriteLogSpec {
+        /// The number of the written or deleted keys
+        pub num_keys: usize,
+        /// The length in bytes of the written values
+        pub value_len: usize,
+        /// The percentage of the keys that are deleted rather than written
+        pub delete_percent: usize,
+        /// The number of the prefixes that the keys are spread across
+        pub num_prefixes: usize,
+    }
+
+    impl WriteLogSpec {
+        /// The prefix of the `index`-th prefix of the keys
+        pub fn prefix(index: usize) -> Key {
+            Key::parse(format!("bench_{index}"))
+                .expect("cannot parse the key string")
+        }
+
+        /// The generated keys, spread across the prefixes
+        pub fn keys(&self) -> impl Iterator<Item = Key> + '_ {
+            (0..self.num_keys).map(|i| {
+                Self::prefix(i % self.num_prefixes.max(1))
+                    .push(&format!("{i}"))
+                    .expect("cannot push the key segment")
+            })
+        }
+
+        /// Whether the `index`-th key is deleted
+        fn is_deleted(&self, index: usize) -> bool {
+            index % 100 < self.delete_percent
+        }
+
+        /// The value written to the `index`-th key, depending on the `seed`
+        fn value(&self, index: usize, seed: u64) -> Vec<u8> {
+            let byte = (index as u64).wrapping_add(seed) as u8;
+            vec![byte; self.value_len]
+        }
+    }
+
+    /// Open a persistent state in the given directory
+    pub fn open_state(db_path: impl AsRef<Path>) -> PersistentState {
+        PersistentState::open(
+            db_path,
+            None,
+            ChainId::default(),
+            namada::core::address::testing::nam(),
+            None,
+            is_merklized_storage_key,
+        )
+    }
+
+    /// Begin the next block of the state
+    pub fn begin_next_block(state: &mut PersistentState) -> StorageResult<()> {
+        let height = state.in_mem().get_last_block_height().next_height();
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), height)
+            .into_storage_result()?;
+        state
+            .in_mem_mut()
+            .set_header(Header {
+                hash: Hash::default(),
+                time: DateTimeUtc::now(),
+                next_validators_hash: Hash::default(),
+            })
+            .into_storage_result()
+    }
+
+    /// Begin a new block and write all the keys of the spec to its write log,
+    /// without committing the block
+    pub fn prepare_write_block(
+        state: &mut PersistentState,
+        spec: &WriteLogSpec,
+        seed: u64,
+    ) -> StorageResult<()> {
+        begin_next_block(state)?;
+        for (i, key) in spec.keys().enumerate() {
+            state
+                .write_log_mut()
+                .write(&key, spec.value(i, seed))
+                .into_storage_result()?;
+        }
+        state.write_log_mut().commit_tx();
+        Ok(())
+    }
+
+    /// Begin a new block and write or delete the keys of the spec in its
+    /// write log as specified, without committing the block. The keys are
+    /// expected to have been written in a previous block.
+    pub fn prepare_update_block(
+        state: &mut PersistentState,
+        spec: &WriteLogSpec,
+        seed: u64,
+    ) -> StorageResult<()> {
+        begin_next_block(state)?;
+        for (i, key) in spec.keys().enumerate() {
+            if spec.is_deleted(i) {
+                state.write_log_mut().delete(&key).into_storage_result()?;
+            } else {
+                state
+                    .write_log_mut()
+                    .write(&key, spec.value(i, seed))
+                    .into_storage_result()?;
+            }
+        }
+        state.write_log_mut().commit_tx();
+        Ok(())
+    }
+
+    /// Commit a block writing all the keys of the spec, followed by a block
+    /// writing or deleting them as specified. Returns the height of the first
+    /// block.
+    pub fn populate(
+        state: &mut PersistentState,
+        spec: &WriteLogSpec,
+    ) -> StorageResult<BlockHeight> {
+        prepare_write_block(state, spec, 0)?;
+        let height = state.in_mem().block.height;
+        state.commit_block()?;
+        prepare_update_block(state, spec, 1)?;
+        state.commit_block()?;
+        Ok(height)
+    }
+
+    /// Read all the keys of the spec at the given height. Returns the number
+    /// of the found values.
+    pub fn read_with_height(
+        state: &PersistentState,
+        spec: &WriteLogSpec,
+        height: BlockHeight,
+    ) -> StorageResult<usize> {
+        let mut found = 0;
+        for key in spec.keys() {
+            let (value, _gas) = state
+                .db_read_with_height(&key, height)
+                .into_storage_result()?;
+            found += usize::from(value.is_some());
+        }
+        Ok(found)
+    }
+
+    /// Iterate all the prefixes of the spec. Returns the number of the
+    /// iterated key-vals.
+    pub fn iter_prefixes(
+        state: &PersistentState,
+        spec: &WriteLogSpec,
+    ) -> usize {
+        (0..spec.num_prefixes.max(1))
+            .map(|index| {
+                let (iter, _gas) =
+                    state.db_iter_prefix(&WriteLogSpec::prefix(index));
+                iter.count()
+            })
+            .sum()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -61,14 +621,16 @@ mod tests {
     use namada::core::hash::Hash;
     use namada::core::keccak::KeccakHash;
     use namada::core::storage::{BlockHash, BlockHeight, Key};
-    use namada::core::time::DurationSecs;
+    use namada::core::time::{DateTimeUtc, DurationSecs};
     use namada::core::{address, storage};
     use namada::eth_bridge::storage::proof::BridgePoolRootProof;
     use namada::ledger::eth_bridge::storage::bridge_pool;
     use namada::ledger::gas::STORAGE_ACCESS_GAS_PER_BYTE;
     use namada::ledger::ibc::storage::ibc_key;
     use namada::ledger::parameters::{EpochDuration, Parameters};
-    use namada::state::{self, StorageRead, StorageWrite, StoreType, DB};
+    use namada::state::{
+        self, DBIter, DiffOp, StorageRead, StorageWrite, StoreType, DB,
+    };
     use namada::token::conversion::update_allowed_conversions;
     use namada::{decode, encode, parameters};
     use namada_sdk::state::StateRead;
@@ -551,16 +1113,28 @@ mod tests {
     /// Test the restore of the merkle tree
     #[test]
     fn test_prune_merkle_tree_stores() {
+        test_prune_merkle_tree_stores_aux(None);
+        // The IBC subtree stores retained longer than the others
+        test_prune_merkle_tree_stores_aux(Some(10));
+    }
+
+    fn test_prune_merkle_tree_stores_aux(ibc_retention_epochs: Option<u64>) {
         let db_path =
             TempDir::new().expect("Unable to create a temporary DB directory");
-        let mut state = PersistentState::open(
+        let mut state = PersistentState::open_with_pruning(
             db_path.path(),
             None,
             ChainId::default(),
             address::testing::nam(),
             Some(5),
+            state::PruningConfig {
+                ibc_retention_epochs,
+                general_retention_epochs: None,
+                diff_retention_blocks: None,
+            },
             is_merklized_storage_key,
         );
+        let ibc_retained = ibc_retention_epochs.is_some();
         let new_epoch_start = BlockHeight(1);
         let signed_root_key = bridge_pool::get_signed_root_key();
         // the first nonce isn't written for a test skipping pruning
@@ -571,9 +1145,11 @@ mod tests {
             .begin_block(BlockHash::default(), new_epoch_start)
             .expect("begin_block failed");
 
-        let key = ibc_key("key").unwrap();
+        let first_key = ibc_key("key").unwrap();
         let value: u64 = 1;
-        state.db_write(&key, encode(&value)).expect("write failed");
+        state
+            .db_write(&first_key, encode(&value))
+            .expect("write failed");
 
         state
             .in_mem_mut()
@@ -629,13 +1205,29 @@ mod tests {
 
         state.commit_block().expect("commit failed");
 
-        let result = state.get_merkle_tree(1.into(), Some(StoreType::Ibc));
-        assert!(result.is_err(), "The tree at Height 1 should be pruned");
-        let result = state.get_merkle_tree(5.into(), Some(StoreType::Ibc));
-        assert!(
-            result.is_err(),
-            "The tree at Height 5 shouldn't be able to be restored"
-        );
+        if ibc_retained {
+            let tree = state
+                .get_merkle_tree(1.into(), Some(StoreType::Ibc))
+                .expect("The ibc tree at Height 1 should be retained");
+            assert!(tree.has_key(&first_key).unwrap());
+            let result = state.get_merkle_tree(5.into(), Some(StoreType::Ibc));
+            assert!(
+                result.is_ok(),
+                "The ibc tree at Height 5 should be retained"
+            );
+        } else {
+            let result = state.get_merkle_tree(1.into(), Some(StoreType::Ibc));
+            assert!(result.is_err(), "The tree at Height 1 should be pruned");
+            let result = state.get_merkle_tree(5.into(), Some(StoreType::Ibc));
+            assert!(
+                result.is_err(),
+                "The tree at Height 5 shouldn't be able to be restored"
+            );
+        }
+        // The other subtrees are pruned regardless of the IBC retention
+        let result =
+            state.get_merkle_tree(1.into(), Some(StoreType::BridgePool));
+        assert!(result.is_err(), "The bridge pool tree should be pruned");
         let result = state.get_merkle_tree(6.into(), Some(StoreType::Ibc));
         assert!(result.is_ok(), "The ibc tree should be restored");
         let result =
@@ -670,6 +1262,131 @@ mod tests {
         assert!(result.is_err(), "The bridge pool tree should be pruned");
     }
 
+    /// Test reading and proving the state at the heights whose diffs have been
+    /// pruned
+    #[test]
+    fn test_diff_retention() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let mut state = PersistentState::open_with_pruning(
+            db_path.path(),
+            None,
+            ChainId::default(),
+            address::testing::nam(),
+            None,
+            state::PruningConfig {
+                diff_retention_blocks: Some(2),
+                ..Default::default()
+            },
+            is_merklized_storage_key,
+        );
+
+        // The epochs start at the heights 1 and 6
+        let key = ibc_key("key").unwrap();
+        for height in 1..=10_u64 {
+            let height = BlockHeight(height);
+            state
+                .in_mem_mut()
+                .begin_block(BlockHash::default(), height)
+                .expect("begin_block failed");
+            state.db_write(&key, encode(&height)).expect("write failed");
+            if height == BlockHeight(1) || height == BlockHeight(6) {
+                state.in_mem_mut().block.epoch =
+                    state.in_mem().block.epoch.next();
+                state.in_mem_mut().block.pred_epochs.new_epoch(height);
+            }
+            state.commit_block().expect("commit failed");
+        }
+
+        // The diffs since the start of the last epoch are retained
+        let height = BlockHeight(7);
+        let (value, _gas) = state.db_read_at_height(&key, height).unwrap();
+        assert_eq!(value, Some(encode(&height)));
+        state
+            .get_existence_proof(&key, &encode(&height), height)
+            .expect("The state at the height 7 should be proven");
+        state
+            .get_existence_commitment_proof(&key, height)
+            .expect("The state at the height 7 should be proven");
+
+        // The diffs of the previous epoch have been pruned
+        assert_eq!(state.in_mem().get_oldest_height(), BlockHeight(6));
+        let height = BlockHeight(3);
+        let result = state.db_read_at_height(&key, height);
+        assert!(matches!(
+            result,
+            Err(state::Error::BlockHeightTooOld {
+                height: BlockHeight(3),
+                oldest_height: BlockHeight(6),
+            })
+        ));
+        let result = state.get_existence_proof(&key, &encode(&height), height);
+        assert!(matches!(
+            result,
+            Err(state::Error::DbError(state::DbError::PrunedHeight { .. }))
+        ));
+        let result = state.get_existence_commitment_proof(&key, height);
+        assert!(matches!(
+            result,
+            Err(state::Error::DbError(state::DbError::PrunedHeight { .. }))
+        ));
+        let non_key = ibc_key("non_key").unwrap();
+        let result = state.get_non_existence_commitment_proof(&non_key, height);
+        assert!(matches!(
+            result,
+            Err(state::Error::DbError(state::DbError::PrunedHeight { .. }))
+        ));
+    }
+
+    /// Test that the diffs are retained at least for the heights that can be
+    /// read
+    #[test]
+    fn test_diff_retention_clamped_to_read_limit() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let mut state = PersistentState::open_with_pruning(
+            db_path.path(),
+            None,
+            ChainId::default(),
+            address::testing::nam(),
+            Some(5),
+            state::PruningConfig {
+                diff_retention_blocks: Some(2),
+                ..Default::default()
+            },
+            is_merklized_storage_key,
+        );
+        assert_eq!(
+            state.in_mem().merkle_tree_pruning.diff_retention_blocks,
+            Some(5)
+        );
+
+        // The epochs start at the heights 1 and 9
+        let key = ibc_key("key").unwrap();
+        for height in 1..=10_u64 {
+            let height = BlockHeight(height);
+            state
+                .in_mem_mut()
+                .begin_block(BlockHash::default(), height)
+                .expect("begin_block failed");
+            state.db_write(&key, encode(&height)).expect("write failed");
+            if height == BlockHeight(1) || height == BlockHeight(9) {
+                state.in_mem_mut().block.epoch =
+                    state.in_mem().block.epoch.next();
+                state.in_mem_mut().block.pred_epochs.new_epoch(height);
+            }
+            state.commit_block().expect("commit failed");
+        }
+
+        // All the heights within the read limit can still be read
+        let oldest_height = BlockHeight(5);
+        assert_eq!(state.in_mem().get_oldest_height(), oldest_height);
+        assert_eq!(state.in_mem().diffs_oldest_height, Some(oldest_height));
+        let (value, _gas) =
+            state.db_read_at_height(&key, oldest_height).unwrap();
+        assert_eq!(value, Some(encode(&oldest_height)));
+    }
+
     /// Test the prefix iterator with RocksDB.
     #[test]
     fn test_persistent_storage_prefix_iter() {
@@ -770,6 +1487,499 @@ mod tests {
         itertools::assert_equal(iter, expected);
     }
 
+    /// Test the deletion of a prefix with RocksDB.
+    #[test]
+    fn test_persistent_storage_delete_prefix() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let mut state = PersistentState::open(
+            db_path.path(),
+            None,
+            ChainId::default(),
+            address::testing::nam(),
+            None,
+            is_merklized_storage_key,
+        );
+
+        let prefix = storage::Key::parse("prefix/sub").unwrap();
+        // The neighbouring prefixes share a part of the prefix string
+        let neighbours = [
+            storage::Key::parse("prefix/sub0").unwrap(),
+            storage::Key::parse("prefix/su").unwrap(),
+            storage::Key::parse("prefix/other").unwrap(),
+        ];
+        for i in 0..300_u64 {
+            state.write(&prefix.push(&i).unwrap(), i).unwrap();
+            for neighbour in &neighbours {
+                state.write(&neighbour.push(&i).unwrap(), i).unwrap();
+            }
+        }
+        state.commit_block().unwrap();
+        assert!(
+            state
+                .in_mem()
+                .block
+                .tree
+                .has_key(&prefix.push(&0_u64).unwrap())
+                .unwrap()
+        );
+
+        state.delete_prefix(&prefix).unwrap();
+        // A key written after the deletion is kept
+        let rewritten_key = prefix.push(&1_u64).unwrap();
+        state.write(&rewritten_key, 1_u64).unwrap();
+
+        // The deleted keys are not read before the commit
+        let iter = state::iter_prefix::<u64>(&state, &prefix)
+            .unwrap()
+            .map(Result::unwrap);
+        itertools::assert_equal(iter, [(rewritten_key.clone(), 1)]);
+        assert!(!state.has_key(&prefix.push(&0_u64).unwrap()).unwrap());
+
+        state.commit_block().unwrap();
+
+        let iter = state::iter_prefix::<u64>(&state, &prefix)
+            .unwrap()
+            .map(Result::unwrap);
+        itertools::assert_equal(iter, [(rewritten_key.clone(), 1)]);
+        let db_keys: Vec<_> = state
+            .db()
+            .iter_prefix(Some(&prefix))
+            .map(|(key, _, _)| key)
+            .collect();
+        assert_eq!(db_keys, vec![rewritten_key.to_string()]);
+        for i in 0..300_u64 {
+            let key = prefix.push(&i).unwrap();
+            assert_eq!(
+                state.in_mem().block.tree.has_key(&key).unwrap(),
+                key == rewritten_key
+            );
+        }
+
+        // The neighbouring prefixes are untouched
+        for neighbour in &neighbours {
+            let iter = state::iter_prefix::<u64>(&state, neighbour)
+                .unwrap()
+                .map(Result::unwrap);
+            assert_eq!(iter.count(), 300);
+            for i in 0..300_u64 {
+                let key = neighbour.push(&i).unwrap();
+                assert!(state.in_mem().block.tree.has_key(&key).unwrap());
+            }
+        }
+    }
+
+    /// Test reading a range of block headers with RocksDB
+    #[test]
+    fn test_get_block_headers() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let past_height_limit = 3;
+        let mut state = PersistentState::open(
+            db_path.path(),
+            None,
+            ChainId::default(),
+            address::testing::nam(),
+            Some(past_height_limit),
+            is_merklized_storage_key,
+        );
+
+        // Commit the blocks with distinct headers
+        let header_hash = |height: u64| Hash::sha256(height.to_le_bytes());
+        for height in 1..=6_u64 {
+            state
+                .in_mem_mut()
+                .begin_block(BlockHash::default(), BlockHeight(height))
+                .expect("begin_block failed");
+            state
+                .in_mem_mut()
+                .set_header(storage::Header {
+                    hash: header_hash(height),
+                    time: DateTimeUtc::from_unix_timestamp(height as i64)
+                        .unwrap(),
+                    next_validators_hash: Hash::default(),
+                })
+                .expect("set_header failed");
+            state.commit_block().expect("commit failed");
+        }
+
+        // The oldest readable height is at the pruning boundary
+        let oldest_height = state.in_mem().get_oldest_height();
+        assert_eq!(oldest_height, BlockHeight(6 - past_height_limit));
+        let (headers, gas) = state
+            .get_block_headers(oldest_height, BlockHeight(6))
+            .unwrap();
+        assert!(gas > 0);
+        let heights: Vec<_> =
+            headers.iter().map(|(height, _)| *height).collect();
+        assert_eq!(heights, (3..=6).map(BlockHeight).collect::<Vec<_>>());
+        for (height, header) in &headers {
+            assert_eq!(header.hash, header_hash(height.0));
+        }
+        let (headers, _gas) = state
+            .get_block_headers(BlockHeight(5), BlockHeight(5))
+            .unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].1.hash, header_hash(5));
+
+        // Past the pruning boundary
+        let result = state
+            .get_block_headers(oldest_height.prev_height(), BlockHeight(6));
+        assert!(matches!(
+            result,
+            Err(state::Error::BlockHeightTooOld { .. })
+        ));
+        // Past the last committed height
+        let result = state.get_block_headers(BlockHeight(4), BlockHeight(7));
+        assert!(matches!(
+            result,
+            Err(state::Error::BlockHeightPastLast { .. })
+        ));
+        // An empty range
+        let result = state.get_block_headers(BlockHeight(5), BlockHeight(4));
+        assert!(matches!(
+            result,
+            Err(state::Error::InvalidBlockHeightRange { .. })
+        ));
+        // A too long range
+        let result = state.get_block_headers(
+            BlockHeight(1),
+            BlockHeight(state::MAX_BLOCK_HEADERS_RANGE + 1),
+        );
+        assert!(matches!(
+            result,
+            Err(state::Error::BlockHeightRangeTooLong { .. })
+        ));
+    }
+
+    /// Test a round trip of a storage snapshot
+    #[test]
+    fn test_snapshot_export_import() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let mut state = PersistentState::open(
+            db_path.path(),
+            None,
+            ChainId::default(),
+            address::testing::nam(),
+            None,
+            is_merklized_storage_key,
+        );
+
+        // Write a few hundred keys over several blocks, updating and deleting
+        // some of the keys of the first block
+        let num_blocks = 4;
+        let num_keys = 100;
+        let key = |height: u64, index: u64| {
+            Key::parse(format!("block{height}/key{index}")).unwrap()
+        };
+        for height in 1..=num_blocks {
+            state
+                .in_mem_mut()
+                .begin_block(BlockHash::default(), BlockHeight(height))
+                .expect("begin_block failed");
+            if height == 1 {
+                state
+                    .in_mem_mut()
+                    .block
+                    .pred_epochs
+                    .new_epoch(BlockHeight(1));
+            }
+            for index in 0..num_keys {
+                state
+                    .write(&key(height, index), (height, index))
+                    .expect("write failed");
+            }
+            if height > 1 {
+                state.write(&key(1, height), height).expect("write failed");
+                state
+                    .delete(&key(1, num_keys - height))
+                    .expect("delete failed");
+            }
+            state.commit_block().expect("commit failed");
+        }
+        let last_height = BlockHeight(num_blocks);
+
+        let snapshot_dir = TempDir::new()
+            .expect("Unable to create a temporary snapshot directory");
+        let snapshot_path = snapshot_dir.path().join("snapshot");
+        // Only the last committed height can be exported
+        let result =
+            export_snapshot(&state, &snapshot_path, last_height.prev_height());
+        assert!(matches!(result, Err(SnapshotError::HeightNotLast { .. })));
+        // Export in small chunks
+        let header =
+            write_snapshot(&state, &snapshot_path, last_height, 1024).unwrap();
+        assert_eq!(header.height, last_height);
+        assert_eq!(header.merkle_root.0, state.in_mem().merkle_root().0);
+
+        // A snapshot for another chain is refused
+        let imported_path = snapshot_dir.path().join("imported");
+        let result = import_snapshot(
+            &snapshot_path,
+            &imported_path,
+            None,
+            ChainId("other-chain".to_string()),
+            address::testing::nam(),
+            None,
+        );
+        assert!(matches!(result, Err(SnapshotError::ChainIdMismatch { .. })));
+
+        let imported = import_snapshot(
+            &snapshot_path,
+            &imported_path,
+            None,
+            ChainId::default(),
+            address::testing::nam(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(imported.in_mem().get_last_block_height(), last_height);
+        assert_eq!(
+            imported.in_mem().merkle_root().0,
+            state.in_mem().merkle_root().0
+        );
+        for height in 1..=num_blocks {
+            for index in 0..num_keys {
+                let key = key(height, index);
+                assert_eq!(
+                    imported.read_bytes(&key).unwrap(),
+                    state.read_bytes(&key).unwrap()
+                );
+            }
+            let prefix = Key::parse(format!("block{height}")).unwrap();
+            let iter_prefix = |state: &PersistentState| {
+                namada::state::iter_prefix_bytes(state, &prefix)
+                    .unwrap()
+                    .map(Result::unwrap)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(iter_prefix(&imported), iter_prefix(&state));
+        }
+        for height in 1..=num_blocks {
+            let height = BlockHeight(height);
+            let tree = imported.get_merkle_tree(height, None).unwrap();
+            let expected = state.get_merkle_tree(height, None).unwrap();
+            assert_eq!(tree.root().0, expected.root().0);
+            for index in [0, num_keys - num_blocks] {
+                let key = key(1, index);
+                assert_eq!(
+                    tree.has_key(&key).unwrap(),
+                    expected.has_key(&key).unwrap()
+                );
+            }
+        }
+
+        // A corrupted snapshot is refused and the new DB is removed
+        let imported_path = snapshot_dir.path().join("corrupted");
+        let mut bytes = std::fs::read(&snapshot_path).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        let corrupted_path = snapshot_dir.path().join("corrupted_snapshot");
+        std::fs::write(&corrupted_path, &bytes).unwrap();
+        let result = import_snapshot(
+            &corrupted_path,
+            &imported_path,
+            None,
+            ChainId::default(),
+            address::testing::nam(),
+            None,
+        );
+        assert!(matches!(
+            result,
+            Err(SnapshotError::ChecksumMismatch { .. })
+        ));
+        assert!(!imported_path.exists());
+
+        // A snapshot with another merkle root is refused
+        let mut reader = &std::fs::read(&snapshot_path).unwrap()[..];
+        let mut magic_and_version = [0; 12];
+        reader.read_exact(&mut magic_and_version).unwrap();
+        let mut header = match read_frame(&mut reader, 0).unwrap() {
+            SnapshotFrame::Header(header) => header,
+            frame => panic!("Unexpected frame {frame:?}"),
+        };
+        header.merkle_root = Hash::sha256("another root");
+        let mut bytes = magic_and_version.to_vec();
+        write_frame(&mut bytes, &SnapshotFrame::Header(header)).unwrap();
+        bytes.extend_from_slice(reader);
+        let tampered_path = snapshot_dir.path().join("tampered_snapshot");
+        std::fs::write(&tampered_path, &bytes).unwrap();
+        let result = import_snapshot(
+            &tampered_path,
+            &imported_path,
+            None,
+            ChainId::default(),
+            address::testing::nam(),
+            None,
+        );
+        assert!(matches!(
+            result,
+            Err(SnapshotError::MerkleRootMismatch { .. })
+        ));
+        assert!(!imported_path.exists());
+    }
+
+    /// Test the iterator of the diffs at a height with RocksDB
+    #[test]
+    fn test_iter_diffs_at_height() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let mut state = PersistentState::open(
+            db_path.path(),
+            None,
+            ChainId::default(),
+            address::testing::nam(),
+            Some(1),
+            is_merklized_storage_key,
+        );
+        let prefix = Key::parse("diffs").unwrap();
+        let key = |name: &str| prefix.push(&name.to_owned()).unwrap();
+        let other_key = Key::parse("other/key").unwrap();
+        let diffs_at_height =
+            |state: &PersistentState, height: u64, prefix: Option<&Key>| {
+                state
+                    .iter_diffs_at_height(BlockHeight(height), prefix)
+                    .unwrap()
+                    .collect::<Vec<_>>()
+            };
+
+        // Block 1: write a, b, c and a key with another prefix
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(1))
+            .expect("begin_block failed");
+        for name in ["a", "b", "c"] {
+            state.write_bytes(&key(name), [1]).unwrap();
+        }
+        state.write_bytes(&other_key, [1]).unwrap();
+        state.commit_block().expect("commit failed");
+        assert_eq!(
+            diffs_at_height(&state, 1, Some(&prefix)),
+            vec![
+                (key("a"), DiffOp::Write(vec![1])),
+                (key("b"), DiffOp::Write(vec![1])),
+                (key("c"), DiffOp::Write(vec![1])),
+            ]
+        );
+        assert_eq!(
+            diffs_at_height(&state, 1, None),
+            vec![
+                (key("a"), DiffOp::Write(vec![1])),
+                (key("b"), DiffOp::Write(vec![1])),
+                (key("c"), DiffOp::Write(vec![1])),
+                (other_key.clone(), DiffOp::Write(vec![1])),
+            ]
+        );
+
+        // Block 2: update a, delete b and write d
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .expect("begin_block failed");
+        state.write_bytes(&key("a"), [2]).unwrap();
+        state.delete(&key("b")).unwrap();
+        state.write_bytes(&key("d"), [2]).unwrap();
+        state.commit_block().expect("commit failed");
+        let expected_diffs_2 = vec![
+            (key("a"), DiffOp::Write(vec![2])),
+            (key("b"), DiffOp::Delete),
+            (key("d"), DiffOp::Write(vec![2])),
+        ];
+        assert_eq!(diffs_at_height(&state, 2, Some(&prefix)), expected_diffs_2);
+        assert_eq!(diffs_at_height(&state, 2, None), expected_diffs_2);
+
+        // Block 3: delete a and c, write b again and delete the other key
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(3))
+            .expect("begin_block failed");
+        state.delete(&key("a")).unwrap();
+        state.write_bytes(&key("b"), [3]).unwrap();
+        state.delete(&key("c")).unwrap();
+        state.delete(&other_key).unwrap();
+        state.commit_block().expect("commit failed");
+        assert_eq!(
+            diffs_at_height(&state, 3, Some(&prefix)),
+            vec![
+                (key("a"), DiffOp::Delete),
+                (key("b"), DiffOp::Write(vec![3])),
+                (key("c"), DiffOp::Delete),
+            ]
+        );
+        assert_eq!(
+            diffs_at_height(&state, 3, None).last(),
+            Some(&(other_key, DiffOp::Delete))
+        );
+        // The diffs at the previous height are still retained
+        assert_eq!(diffs_at_height(&state, 2, Some(&prefix)), expected_diffs_2);
+
+        // The heights before the oldest height and past the last height
+        let result = state.iter_diffs_at_height(BlockHeight(1), Some(&prefix));
+        assert!(matches!(
+            result,
+            Err(state::Error::BlockHeightTooOld { .. })
+        ));
+        let result = state.iter_diffs_at_height(BlockHeight(4), Some(&prefix));
+        assert!(matches!(
+            result,
+            Err(state::Error::BlockHeightPastLast { .. })
+        ));
+    }
+
+    /// Test that a block with a large write log is committed within a
+    /// generous time bound, to catch order-of-magnitude regressions
+    #[test]
+    fn test_commit_block_with_large_write_log() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let mut state = benches::open_state(db_path.path());
+        let spec = benches::WriteLogSpec {
+            num_keys: 50_000,
+            value_len: 32,
+            delete_percent: 0,
+            num_prefixes: 10,
+        };
+        benches::prepare_write_block(&mut state, &spec, 0)
+            .expect("Test failed");
+
+        let start = std::time::Instant::now();
+        state.commit_block().expect("commit failed");
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed < std::time::Duration::from_secs(120),
+            "Committing {} keys took {elapsed:?}",
+            spec.num_keys
+        );
+
+        assert_eq!(benches::iter_prefixes(&state, &spec), spec.num_keys);
+    }
+
+    /// Test the generated write logs with deletions
+    #[test]
+    fn test_bench_write_logs() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let mut state = benches::open_state(db_path.path());
+        let spec = benches::WriteLogSpec {
+            num_keys: 1_000,
+            value_len: 8,
+            delete_percent: 10,
+            num_prefixes: 3,
+        };
+        let height = benches::populate(&mut state, &spec).expect("Test failed");
+
+        // all the keys were written at the first block
+        let found = benches::read_with_height(&state, &spec, height)
+            .expect("Test failed");
+        assert_eq!(found, spec.num_keys);
+        // some of them have been deleted at the next block
+        let found =
+            benches::read_with_height(&state, &spec, height.next_height())
+                .expect("Test failed");
+        assert_eq!(found, 900);
+        assert_eq!(benches::iter_prefixes(&state, &spec), 900);
+    }
+
     fn test_key_1() -> Key {
         Key::parse("testing1").unwrap()
     }
@@ -17,7 +17,18 @@
 //!     - `tx_queue`
 //!     - `next_epoch_min_start_height`
 //!     - `next_epoch_min_start_time`
+//!     - `conversion_state`: the predecessor values of the conversion state
+//!       keys changed in the last epoch update
 //!   - `conversion_state`: MASP conversion state
+//!     - `header`: the normed inflation, the tree size and the tokens
+//!     - `tree`: the conversion tree
+//!     - `assets/{asset_type}`: the conversion of an asset
+//!   - `batch_journal`: the sub-batches of a batch split for exceeding the max
+//!     batch size, kept until all of them are written
+//!     - `{i}`: the data of the `i`-th sub-batch
+//!     - `len`: the number of the sub-batches, written with the last one
+//!   - `diffs_oldest_height`: the oldest height whose state can be read from
+//!     the diffs, written when the older diffs are pruned
 //! - `subspace`: accounts sub-spaces
 //!   - `{address}/{dyn}`: any byte data associated with accounts
 //! - `diffs`: diffs in account subspaces' key-vals
@@ -38,13 +49,14 @@
 //!     - `all`: the hashes included up to the last block
 //!     - `last`: the hashes included in the last block
 
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 use std::str::FromStr;
 use std::sync::Mutex;
 
-use borsh::BorshDeserialize;
+use borsh::{BorshDeserialize, BorshSerialize};
 use borsh_ext::BorshSerializeExt;
 use data_encoding::HEXLOWER;
 use itertools::Either;
@@ -59,12 +71,15 @@ use namada::ledger::eth_bridge::storage::bridge_pool;
 use namada::ledger::storage::tx_queue::TxQueue;
 use namada::replay_protection;
 use namada::state::merkle_tree::{base_tree_key_prefix, subtree_key_prefix};
+use namada::state::storage::conversion_state::{self, diff_split};
 use namada::state::{
-    BlockStateRead, BlockStateWrite, DBIter, DBWriteBatch, DbError as Error,
-    DbResult as Result, MerkleTreeStoresRead, PrefixIterator, StoreType, DB,
+    BlockStateRead, BlockStateWrite, ColumnFamilyStats, DBIter, DBStats,
+    DBWriteBatch, DbError as Error, DbResult as Result, IterOpts,
+    MerkleTreeStoresRead, PrefixIterator, StoreType, DB,
 };
 use namada::token::ConversionState;
 use rayon::prelude::*;
+use rocksdb::properties::{self, PropName};
 use rocksdb::{
     BlockBasedOptions, ColumnFamily, ColumnFamilyDescriptor, DBCompactionStyle,
     DBCompressionType, Direction, FlushOptions, IteratorMode, Options,
@@ -79,6 +94,13 @@ use crate::config::utils::num_of_threads;
 const ENV_VAR_ROCKSDB_COMPACTION_THREADS: &str =
     "NAMADA_ROCKSDB_COMPACTION_THREADS";
 
+/// Env. var to set the max size of a write batch in bytes. A larger batch is
+/// split into sub-batches.
+const ENV_VAR_ROCKSDB_MAX_BATCH_BYTES: &str = "NAMADA_ROCKSDB_MAX_BATCH_BYTES";
+
+/// The default max size of a write batch in bytes
+const DEFAULT_MAX_BATCH_BYTES: usize = 64 * 1024 * 1024;
+
 /// Column family names
 const SUBSPACE_CF: &str = "subspace";
 const DIFFS_CF: &str = "diffs";
@@ -89,18 +111,123 @@ const REPLAY_PROTECTION_CF: &str = "replay_protection";
 const OLD_DIFF_PREFIX: &str = "old";
 const NEW_DIFF_PREFIX: &str = "new";
 
+const BATCH_JOURNAL_PREFIX: &str = "batch_journal";
+const BATCH_JOURNAL_LEN_KEY: &str = "batch_journal/len";
+
+const DIFFS_OLDEST_HEIGHT_KEY: &str = "diffs_oldest_height";
+
 /// RocksDB handle
 #[derive(Debug)]
-pub struct RocksDB(rocksdb::DB);
+pub struct RocksDB {
+    inner: rocksdb::DB,
+    /// The number of past blocks for which the diffs are retained. The diffs
+    /// are never pruned when it isn't set.
+    diff_retention: Option<u64>,
+}
+
+/// The column families of the key-vals in a snapshot
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize,
+)]
+pub enum SnapshotColumn {
+    Subspace,
+    Diffs,
+    State,
+    Block,
+    ReplayProtection,
+}
+
+impl SnapshotColumn {
+    fn cf_name(&self) -> &'static str {
+        match self {
+            Self::Subspace => SUBSPACE_CF,
+            Self::Diffs => DIFFS_CF,
+            Self::State => STATE_CF,
+            Self::Block => BLOCK_CF,
+            Self::ReplayProtection => REPLAY_PROTECTION_CF,
+        }
+    }
+}
+
+/// A key-val of a snapshot
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct SnapshotEntry {
+    pub column: SnapshotColumn,
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+/// DB Handle for batch writes. The writes are split into sub-batches once the
+/// max batch size is reached, so that a large batch is journaled and written
+/// one sub-batch at a time.
+pub struct RocksDBWriteBatch {
+    /// The sub-batches in order, the writes go to the last one
+    sub_batches: Vec<WriteBatch>,
+    /// The max size of a sub-batch in bytes, after which a new one is started
+    max_batch_bytes: usize,
+}
+
+impl Default for RocksDBWriteBatch {
+    fn default() -> Self {
+        Self {
+            sub_batches: vec![],
+            // An invalid value has been rejected when opening the DB
+            max_batch_bytes: read_max_batch_bytes()
+                .unwrap_or(DEFAULT_MAX_BATCH_BYTES),
+        }
+    }
+}
+
+impl RocksDBWriteBatch {
+    /// Put the key-val in the column family
+    fn put_cf<K, V>(&mut self, cf: &ColumnFamily, key: K, value: V)
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.current().put_cf(cf, key, value)
+    }
+
+    /// Delete the key in the column family
+    fn delete_cf<K: AsRef<[u8]>>(&mut self, cf: &ColumnFamily, key: K) {
+        self.current().delete_cf(cf, key)
+    }
 
-/// DB Handle for batch writes.
-#[derive(Default)]
-pub struct RocksDBWriteBatch(WriteBatch);
+    /// The sub-batch to write to, starting a new one if the last one has
+    /// reached the max size
+    fn current(&mut self) -> &mut WriteBatch {
+        let is_full = self.sub_batches.last().map_or(true, |batch| {
+            batch.size_in_bytes() >= self.max_batch_bytes
+        });
+        if is_full {
+            self.sub_batches.push(WriteBatch::default());
+        }
+        self.sub_batches.last_mut().expect("A sub-batch must exist")
+    }
+}
+
+/// Read the max size of a write batch in bytes from its env. var, if set
+fn read_max_batch_bytes() -> Result<usize> {
+    match std::env::var(ENV_VAR_ROCKSDB_MAX_BATCH_BYTES) {
+        Ok(num_str) => usize::from_str(&num_str)
+            .ok()
+            .filter(|num| *num > 0)
+            .ok_or_else(|| {
+                Error::DBError(format!(
+                    "Invalid env. var {ENV_VAR_ROCKSDB_MAX_BATCH_BYTES} \
+                     value: {num_str}. Expecting a positive number."
+                ))
+            }),
+        Err(_) => Ok(DEFAULT_MAX_BATCH_BYTES),
+    }
+}
 
-/// Open RocksDB for the DB
+/// Open RocksDB for the DB. The diffs older than the `diff_retention` in
+/// blocks, if any, are pruned on a block commit.
 pub fn open(
     path: impl AsRef<Path>,
     cache: Option<&rocksdb::Cache>,
+    diff_retention: Option<u64>,
 ) -> Result<RocksDB> {
     let logical_cores = num_cpus::get();
     let compaction_threads = num_of_threads(
@@ -188,9 +315,20 @@ pub fn open(
         replay_protection_cf_opts,
     ));
 
-    rocksdb::DB::open_cf_descriptors(&db_opts, path, cfs)
-        .map(RocksDB)
-        .map_err(|e| Error::DBError(e.into_string()))
+    // Fail early on an invalid max batch size
+    read_max_batch_bytes()?;
+
+    let db = rocksdb::DB::open_cf_descriptors(&db_opts, path, cfs)
+        .map(|inner| RocksDB {
+            inner,
+            diff_retention,
+        })
+        .map_err(|e| Error::DBError(e.into_string()))?;
+    // The node must not start silently after a crash in the middle of a batch
+    if let Some(num_sub_batches) = db.recover_batch()? {
+        return Err(Error::PartialBatchRecovered { num_sub_batches });
+    }
+    Ok(db)
 }
 
 impl Drop for RocksDB {
@@ -201,13 +339,26 @@ impl Drop for RocksDB {
 
 impl RocksDB {
     fn get_column_family(&self, cf_name: &str) -> Result<&ColumnFamily> {
-        self.0
+        self.inner
             .cf_handle(cf_name)
             .ok_or(Error::DBError("No {cf_name} column family".to_string()))
     }
 
     /// Persist the diff of an account subspace key-val under the height where
     /// it was changed.
+    /// Read an integer property of a column family. A missing property is
+    /// read as zero.
+    fn read_cf_int_property(
+        &self,
+        cf: &ColumnFamily,
+        name: &PropName,
+    ) -> Result<u64> {
+        self.inner
+            .property_int_value_cf(cf, name)
+            .map(Option::unwrap_or_default)
+            .map_err(|e| Error::DBError(e.into_string()))
+    }
+
     fn write_subspace_diff(
         &self,
         height: BlockHeight,
@@ -220,13 +371,13 @@ impl RocksDB {
         let (old_val_key, new_val_key) = old_and_new_diff_key(key, height)?;
 
         if let Some(old_value) = old_value {
-            self.0
+            self.inner
                 .put_cf(cf, old_val_key, old_value)
                 .map_err(|e| Error::DBError(e.into_string()))?;
         }
 
         if let Some(new_value) = new_value {
-            self.0
+            self.inner
                 .put_cf(cf, new_val_key, new_value)
                 .map_err(|e| Error::DBError(e.into_string()))?;
         }
@@ -238,22 +389,22 @@ impl RocksDB {
                 let (old_diff_key, new_diff_key) =
                     old_and_new_diff_key(key, height)?;
                 let has_old_diff = self
-                    .0
+                    .inner
                     .get_cf(cf, &old_diff_key)
                     .map_err(|e| Error::DBError(e.into_string()))?
                     .is_some();
                 let has_new_diff = self
-                    .0
+                    .inner
                     .get_cf(cf, &new_diff_key)
                     .map_err(|e| Error::DBError(e.into_string()))?
                     .is_some();
                 if has_old_diff {
-                    self.0
+                    self.inner
                         .delete_cf(cf, old_diff_key)
                         .map_err(|e| Error::DBError(e.into_string()))?;
                 }
                 if has_new_diff {
-                    self.0
+                    self.inner
                         .delete_cf(cf, new_diff_key)
                         .map_err(|e| Error::DBError(e.into_string()))?;
                 }
@@ -281,11 +432,11 @@ impl RocksDB {
         let (old_val_key, new_val_key) = old_and_new_diff_key(key, height)?;
 
         if let Some(old_value) = old_value {
-            batch.0.put_cf(cf, old_val_key, old_value);
+            batch.put_cf(cf, old_val_key, old_value);
         }
 
         if let Some(new_value) = new_value {
-            batch.0.put_cf(cf, new_val_key, new_value);
+            batch.put_cf(cf, new_val_key, new_value);
         }
 
         // If not persisting the diffs, remove the last diffs.
@@ -295,20 +446,20 @@ impl RocksDB {
                 let (old_diff_key, new_diff_key) =
                     old_and_new_diff_key(key, height)?;
                 let has_old_diff = self
-                    .0
+                    .inner
                     .get_cf(cf, &old_diff_key)
                     .map_err(|e| Error::DBError(e.into_string()))?
                     .is_some();
                 let has_new_diff = self
-                    .0
+                    .inner
                     .get_cf(cf, &new_diff_key)
                     .map_err(|e| Error::DBError(e.into_string()))?
                     .is_some();
                 if has_old_diff {
-                    batch.0.delete_cf(cf, old_diff_key);
+                    batch.delete_cf(cf, old_diff_key);
                 }
                 if has_new_diff {
-                    batch.0.delete_cf(cf, new_diff_key);
+                    batch.delete_cf(cf, new_diff_key);
                 }
                 if has_old_diff || has_new_diff {
                     break;
@@ -319,12 +470,206 @@ impl RocksDB {
         Ok(())
     }
 
-    fn exec_batch(&mut self, batch: WriteBatch) -> Result<()> {
-        self.0
+    /// Prune the diffs older than the retention, if any, in a batch write.
+    /// The diffs after the given epoch start height are kept to restore the
+    /// merkle tree.
+    fn batch_prune_diffs(
+        &self,
+        batch: &mut RocksDBWriteBatch,
+        height: BlockHeight,
+        epoch_start_height: BlockHeight,
+    ) -> Result<()> {
+        let Some(retention) = self.diff_retention else {
+            return Ok(());
+        };
+        // The diffs of the last height are needed for a rollback
+        let retention = retention.max(1);
+        let oldest_height = BlockHeight(
+            height.0.saturating_sub(retention).min(epoch_start_height.0),
+        );
+        let pruned_height =
+            self.read_diffs_oldest_height()?.unwrap_or_default();
+        if oldest_height <= pruned_height {
+            return Ok(());
+        }
+
+        // The keys are prefixed with the fixed-length height, so the range
+        // covers all the diffs from the last pruned height up to the oldest
+        // height
+        let diffs_cf = self.get_column_family(DIFFS_CF)?;
+        batch.delete_range_cf(
+            diffs_cf,
+            pruned_height.raw(),
+            oldest_height.next_height().raw(),
+        );
+        let state_cf = self.get_column_family(STATE_CF)?;
+        batch.put_cf(state_cf, DIFFS_OLDEST_HEIGHT_KEY, encode(&oldest_height));
+        Ok(())
+    }
+
+    fn exec_batch(&mut self, batch: RocksDBWriteBatch) -> Result<()> {
+        let mut sub_batches = batch.sub_batches;
+        if sub_batches.len() <= 1 {
+            return match sub_batches.pop() {
+                Some(batch) => self.write_batch(batch),
+                None => Ok(()),
+            };
+        }
+        // The sub-batches are journaled before any of them is written so
+        // that the batch can be rolled forward if the node crashes in between
+        tracing::debug!(
+            "Writing a write batch in {} sub-batches",
+            sub_batches.len()
+        );
+        self.journal_sub_batches(&sub_batches)?;
+        self.write_sub_batches(sub_batches)
+    }
+
+    fn write_batch(&self, batch: WriteBatch) -> Result<()> {
+        self.inner
             .write(batch)
             .map_err(|e| Error::DBError(e.into_string()))
     }
 
+    /// Write the sub-batches to the journal. The number of the sub-batches
+    /// is written with the last one to mark the journal complete.
+    fn journal_sub_batches(&self, sub_batches: &[WriteBatch]) -> Result<()> {
+        let state_cf = self.get_column_family(STATE_CF)?;
+        for (i, sub_batch) in sub_batches.iter().enumerate() {
+            let mut batch = WriteBatch::default();
+            batch.put_cf(state_cf, batch_journal_key(i), sub_batch.data());
+            if i + 1 == sub_batches.len() {
+                batch.put_cf(
+                    state_cf,
+                    BATCH_JOURNAL_LEN_KEY,
+                    encode(&(sub_batches.len() as u64)),
+                );
+            }
+            self.write_batch(batch)?;
+        }
+        Ok(())
+    }
+
+    /// Write the sub-batches in order. The journal is cleared atomically with
+    /// the last one.
+    fn write_sub_batches(&self, sub_batches: Vec<WriteBatch>) -> Result<()> {
+        let state_cf = self.get_column_family(STATE_CF)?;
+        let num_sub_batches = sub_batches.len();
+        for (i, mut batch) in sub_batches.into_iter().enumerate() {
+            if i + 1 == num_sub_batches {
+                for j in 0..num_sub_batches {
+                    batch.delete_cf(state_cf, batch_journal_key(j));
+                }
+                batch.delete_cf(state_cf, BATCH_JOURNAL_LEN_KEY);
+            }
+            self.write_batch(batch)?;
+        }
+        Ok(())
+    }
+
+    /// Roll forward the batch whose sub-batches have been journaled but
+    /// maybe not all written, e.g. when the node crashed in between. A journal
+    /// that isn't complete is discarded since none of its sub-batches has
+    /// been written. Returns the number of the sub-batches if a batch has
+    /// been rolled forward.
+    fn recover_batch(&self) -> Result<Option<u64>> {
+        let state_cf = self.get_column_family(STATE_CF)?;
+        let num_sub_batches = match self
+            .inner
+            .get_cf(state_cf, BATCH_JOURNAL_LEN_KEY)
+            .map_err(|e| Error::DBError(e.into_string()))?
+        {
+            Some(bytes) => decode::<u64>(bytes).map_err(Error::CodingError)?,
+            None => {
+                let read_opts = make_iter_read_opts(Some(format!(
+                    "{BATCH_JOURNAL_PREFIX}/"
+                )));
+                let iter = self.inner.iterator_cf_opt(
+                    state_cf,
+                    read_opts,
+                    IteratorMode::Start,
+                );
+                let mut batch = WriteBatch::default();
+                for result in iter {
+                    let (key, _) =
+                        result.map_err(|e| Error::DBError(e.into_string()))?;
+                    batch.delete_cf(state_cf, key);
+                }
+                if !batch.is_empty() {
+                    tracing::warn!("Discarding an incomplete batch journal");
+                    self.write_batch(batch)?;
+                }
+                return Ok(None);
+            }
+        };
+
+        let mut sub_batches = Vec::with_capacity(num_sub_batches as usize);
+        for i in 0..num_sub_batches as usize {
+            let data = self
+                .inner
+                .get_cf(state_cf, batch_journal_key(i))
+                .map_err(|e| Error::DBError(e.into_string()))?
+                .ok_or_else(|| {
+                    Error::DBError(format!(
+                        "The sub-batch {i} of {num_sub_batches} is missing in \
+                         the batch journal"
+                    ))
+                })?;
+            sub_batches.push(WriteBatch::from_data(&data));
+        }
+        // Writing the sub-batches again is idempotent since they are written
+        // in the same order
+        self.write_sub_batches(sub_batches)?;
+        Ok(Some(num_sub_batches))
+    }
+
+    /// Read the key-vals of the persisted conversion state, including the
+    /// legacy single value if it's present
+    fn read_conversion_state_split(&self) -> Result<BTreeMap<String, Vec<u8>>> {
+        let state_cf = self.get_column_family(STATE_CF)?;
+        let mut split = BTreeMap::new();
+        if let Some(bytes) = self
+            .inner
+            .get_cf(state_cf, conversion_state::LEGACY_KEY)
+            .map_err(|e| Error::DBError(e.into_string()))?
+        {
+            split.insert(conversion_state::LEGACY_KEY.to_string(), bytes);
+        }
+        let read_opts = make_iter_read_opts(Some(format!(
+            "{}/",
+            conversion_state::LEGACY_KEY
+        )));
+        let iter = self.inner.iterator_cf_opt(
+            state_cf,
+            read_opts,
+            IteratorMode::Start,
+        );
+        for result in iter {
+            let (key, value) =
+                result.map_err(|e| Error::DBError(e.into_string()))?;
+            let key = String::from_utf8(key.to_vec())
+                .map_err(|e| Error::DBError(e.to_string()))?;
+            split.insert(key, value.to_vec());
+        }
+        Ok(split)
+    }
+
+    /// Read the persisted conversion state. Falls back to the legacy single
+    /// value for the chains that haven't updated the split keys yet.
+    fn read_conversion_state(&self) -> Result<Option<ConversionState>> {
+        let split = self.read_conversion_state_split()?;
+        if let Some(conversion_state) =
+            ConversionState::decode_split(&split).map_err(Error::CodingError)?
+        {
+            return Ok(Some(conversion_state));
+        }
+        split
+            .get(conversion_state::LEGACY_KEY)
+            .map(decode)
+            .transpose()
+            .map_err(Error::CodingError)
+    }
+
     /// Dump last known block
     pub fn dump_block(
         &self,
@@ -338,7 +683,7 @@ impl RocksDB {
             .expect("State column family should exist");
 
         let last_height: BlockHeight = decode(
-            self.0
+            self.inner
                 .get_cf(state_cf, "height")
                 .expect("Unable to read DB")
                 .expect("No block height found"),
@@ -452,17 +797,18 @@ impl RocksDB {
     ) {
         let read_opts = make_iter_read_opts(prefix.clone());
         let iter = if let Some(prefix) = prefix {
-            self.0.iterator_cf_opt(
+            self.inner.iterator_cf_opt(
                 cf,
                 read_opts,
                 IteratorMode::From(prefix.as_bytes(), Direction::Forward),
             )
         } else {
-            self.0.iterator_cf_opt(cf, read_opts, IteratorMode::Start)
+            self.inner
+                .iterator_cf_opt(cf, read_opts, IteratorMode::Start)
         };
 
         let mut buf = BufWriter::new(file);
-        for (key, raw_val, _gas) in PersistentPrefixIterator(
+        for (key, raw_val, _gas) in PersistentPrefixIterator::new(
             PrefixIterator::new(iter, String::default()),
             // Empty string to prevent prefix stripping, the prefix is
             // already in the enclosed iterator
@@ -475,6 +821,112 @@ impl RocksDB {
         buf.flush().expect("Unable to write to output file");
     }
 
+    /// Visit the key-vals of a snapshot of the last committed block, which
+    /// must be at the given height and epoch. Besides the whole subspace, the
+    /// ledger state and the replay protection entries, the snapshot contains
+    /// the blocks since the start of the epoch with their merkle tree stores
+    /// and diffs, from which the merkle tree at the height is rebuilt. The
+    /// key-vals are read from a consistent view of the DB.
+    pub fn snapshot_entries<E: From<Error>>(
+        &self,
+        height: BlockHeight,
+        epoch: Epoch,
+        epoch_start_height: BlockHeight,
+        mut visit: impl FnMut(SnapshotEntry) -> std::result::Result<(), E>,
+    ) -> std::result::Result<(), E> {
+        // The snapshot restores the merkle tree with the diffs since the
+        // epoch start
+        self.check_diffs_retained(epoch_start_height)?;
+
+        let snapshot = self.inner.snapshot();
+
+        let state_cf = self.get_column_family(STATE_CF)?;
+        let last_height: Option<BlockHeight> = snapshot
+            .get_cf(state_cf, "height")
+            .map_err(|e| Error::DBError(e.into_string()))?
+            .map(decode)
+            .transpose()
+            .map_err(Error::CodingError)?;
+        if last_height != Some(height) {
+            return Err(Error::DBError(format!(
+                "The height {height} isn't the last committed height"
+            ))
+            .into());
+        }
+
+        let mut prefixes = vec![
+            (SnapshotColumn::Subspace, None),
+            (SnapshotColumn::State, None),
+            (SnapshotColumn::ReplayProtection, None),
+        ];
+        let mut keys = vec![];
+        let mut block_height = epoch_start_height;
+        while block_height <= height {
+            let prefix = format!("{}/", block_height.raw());
+            prefixes.push((SnapshotColumn::Block, Some(prefix.clone())));
+            // The diffs from the base tree at the epoch start height
+            if block_height > epoch_start_height {
+                prefixes.push((SnapshotColumn::Diffs, Some(prefix)));
+            }
+            keys.push(format!("results/{}", block_height.raw()));
+            block_height = block_height.next_height();
+        }
+        // The subtree stores, unless they're already under a block's prefix
+        if !(epoch_start_height.0..=height.0).contains(&epoch.0) {
+            for st in StoreType::iter_subtrees() {
+                let prefix = format!("{}/", subtree_key_prefix(st, epoch));
+                prefixes.push((SnapshotColumn::Block, Some(prefix)));
+            }
+        }
+
+        for (column, prefix) in prefixes {
+            let cf = self.get_column_family(column.cf_name())?;
+            let read_opts = make_iter_read_opts(prefix.clone());
+            let mode = match &prefix {
+                Some(prefix) => {
+                    IteratorMode::From(prefix.as_bytes(), Direction::Forward)
+                }
+                None => IteratorMode::Start,
+            };
+            for item in snapshot.iterator_cf_opt(cf, read_opts, mode) {
+                let (key, value) =
+                    item.map_err(|e| Error::DBError(e.into_string()))?;
+                visit(SnapshotEntry {
+                    column,
+                    key: key.into_vec(),
+                    value: value.into_vec(),
+                })?;
+            }
+        }
+        let block_cf = self.get_column_family(BLOCK_CF)?;
+        for key in keys {
+            if let Some(value) = snapshot
+                .get_cf(block_cf, &key)
+                .map_err(|e| Error::DBError(e.into_string()))?
+            {
+                visit(SnapshotEntry {
+                    column: SnapshotColumn::Block,
+                    key: key.into_bytes(),
+                    value,
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the key-vals of a snapshot
+    pub fn write_snapshot_entries(
+        &mut self,
+        entries: Vec<SnapshotEntry>,
+    ) -> Result<()> {
+        let mut batch = RocksDBWriteBatch::default();
+        for SnapshotEntry { column, key, value } in entries {
+            let cf = self.get_column_family(column.cf_name())?;
+            batch.put_cf(cf, key, value);
+        }
+        self.exec_batch(batch)
+    }
+
     /// Rollback to previous block. Given the inner working of tendermint
     /// rollback and of the key structure of Namada, calling rollback more than
     /// once without restarting the chain results in a single rollback.
@@ -501,7 +953,7 @@ impl RocksDB {
             return Ok(());
         }
 
-        let mut batch = WriteBatch::default();
+        let mut batch = RocksDBWriteBatch::default();
         let previous_height =
             BlockHeight::from(u64::from(last_block.height) - 1);
 
@@ -519,7 +971,7 @@ impl RocksDB {
         ] {
             let previous_key = format!("pred/{}", metadata_key);
             let previous_value = self
-                .0
+                .inner
                 .get_cf(state_cf, previous_key.as_bytes())
                 .map_err(|e| Error::DBError(e.to_string()))?
                 .ok_or(Error::UnknownKey { key: previous_key })?;
@@ -537,11 +989,18 @@ impl RocksDB {
         {
             let previous_key = "pred/conversion_state".to_string();
             let previous_value = self
-                .0
+                .inner
                 .get_cf(state_cf, previous_key.as_bytes())
                 .map_err(|e| Error::DBError(e.to_string()))?
                 .ok_or(Error::UnknownKey { key: previous_key })?;
-            batch.put_cf(state_cf, "conversion_state", previous_value);
+            let previous: Vec<(String, Option<Vec<u8>>)> =
+                decode(previous_value).map_err(Error::CodingError)?;
+            for (key, value) in previous {
+                match value {
+                    Some(value) => batch.put_cf(state_cf, key, value),
+                    None => batch.delete_cf(state_cf, key),
+                }
+            }
         }
 
         // Delete block results for the last block
@@ -609,12 +1068,12 @@ impl RocksDB {
         let prefix = last_block.height.to_string();
         let mut delete_keys = |cf: &ColumnFamily| {
             let read_opts = make_iter_read_opts(Some(prefix.clone()));
-            let iter = self.0.iterator_cf_opt(
+            let iter = self.inner.iterator_cf_opt(
                 cf,
                 read_opts,
                 IteratorMode::From(prefix.as_bytes(), Direction::Forward),
             );
-            for (key, _value, _gas) in PersistentPrefixIterator(
+            for (key, _value, _gas) in PersistentPrefixIterator::new(
                 // Empty prefix string to prevent stripping
                 PrefixIterator::new(iter, String::default()),
             ) {
@@ -640,23 +1099,53 @@ impl DB for RocksDB {
     fn open(
         db_path: impl AsRef<std::path::Path>,
         cache: Option<&Self::Cache>,
+        diff_retention: Option<u64>,
     ) -> Self {
-        open(db_path, cache).expect("cannot open the DB")
+        open(db_path, cache, diff_retention).expect("cannot open the DB")
     }
 
     fn flush(&self, wait: bool) -> Result<()> {
         let mut flush_opts = FlushOptions::default();
         flush_opts.set_wait(wait);
-        self.0
+        self.inner
             .flush_opt(&flush_opts)
             .map_err(|e| Error::DBError(e.into_string()))
     }
 
+    fn db_stats(&self) -> Result<DBStats> {
+        let column_families = [
+            SUBSPACE_CF,
+            DIFFS_CF,
+            STATE_CF,
+            BLOCK_CF,
+            REPLAY_PROTECTION_CF,
+        ]
+        .into_iter()
+        .map(|cf_name| {
+            let cf = self.get_column_family(cf_name)?;
+            Ok(ColumnFamilyStats {
+                name: cf_name.to_string(),
+                estimated_keys: self
+                    .read_cf_int_property(cf, properties::ESTIMATE_NUM_KEYS)?,
+                live_sst_bytes: self.read_cf_int_property(
+                    cf,
+                    properties::LIVE_SST_FILES_SIZE,
+                )?,
+                pending_compaction_bytes: self.read_cf_int_property(
+                    cf,
+                    properties::ESTIMATE_PENDING_COMPACTION_BYTES,
+                )?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+        Ok(DBStats { column_families })
+    }
+
     fn read_last_block(&self) -> Result<Option<BlockStateRead>> {
         // Block height
         let state_cf = self.get_column_family(STATE_CF)?;
         let height: BlockHeight = match self
-            .0
+            .inner
             .get_cf(state_cf, "height")
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -672,7 +1161,7 @@ impl DB for RocksDB {
         let block_cf = self.get_column_family(BLOCK_CF)?;
         let results_path = format!("results/{}", height.raw());
         let results: BlockResults = match self
-            .0
+            .inner
             .get_cf(block_cf, results_path)
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -682,7 +1171,7 @@ impl DB for RocksDB {
 
         // Epoch start height and time
         let next_epoch_min_start_height: BlockHeight = match self
-            .0
+            .inner
             .get_cf(state_cf, "next_epoch_min_start_height")
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -695,7 +1184,7 @@ impl DB for RocksDB {
             }
         };
         let next_epoch_min_start_time: DateTimeUtc = match self
-            .0
+            .inner
             .get_cf(state_cf, "next_epoch_min_start_time")
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -708,7 +1197,7 @@ impl DB for RocksDB {
             }
         };
         let update_epoch_blocks_delay: Option<u32> = match self
-            .0
+            .inner
             .get_cf(state_cf, "update_epoch_blocks_delay")
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -720,19 +1209,15 @@ impl DB for RocksDB {
                 return Ok(None);
             }
         };
-        let conversion_state: ConversionState = match self
-            .0
-            .get_cf(state_cf, "conversion_state")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            Some(bytes) => decode(bytes).map_err(Error::CodingError)?,
+        let conversion_state = match self.read_conversion_state()? {
+            Some(conversion_state) => conversion_state,
             None => {
                 tracing::error!("Couldn't load conversion state from the DB");
                 return Ok(None);
             }
         };
         let tx_queue: TxQueue = match self
-            .0
+            .inner
             .get_cf(state_cf, "tx_queue")
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -744,7 +1229,7 @@ impl DB for RocksDB {
         };
 
         let ethereum_height: Option<ethereum_structs::BlockHeight> = match self
-            .0
+            .inner
             .get_cf(state_cf, "ethereum_height")
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -756,7 +1241,7 @@ impl DB for RocksDB {
         };
 
         let eth_events_queue: EthEventsQueue = match self
-            .0
+            .inner
             .get_cf(state_cf, "eth_events_queue")
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -781,7 +1266,7 @@ impl DB for RocksDB {
         let mut epoch: Option<Epoch> = None;
         let mut pred_epochs = None;
         let mut address_gen = None;
-        for value in self.0.iterator_cf_opt(
+        for value in self.inner.iterator_cf_opt(
             block_cf,
             read_opts,
             IteratorMode::From(prefix.as_bytes(), Direction::Forward),
@@ -851,7 +1336,7 @@ impl DB for RocksDB {
                 let root_key =
                     key_prefix.clone().with_segment("root".to_owned());
                 if let Some(bytes) = self
-                    .0
+                    .inner
                     .get_cf(block_cf, &root_key.to_string())
                     .map_err(|e| Error::DBError(e.into_string()))?
                 {
@@ -862,7 +1347,7 @@ impl DB for RocksDB {
                 }
                 let store_key = key_prefix.with_segment("store".to_owned());
                 if let Some(bytes) = self
-                    .0
+                    .inner
                     .get_cf(block_cf, &store_key.to_string())
                     .map_err(|e| Error::DBError(e.into_string()))?
                 {
@@ -929,89 +1414,89 @@ impl DB for RocksDB {
         // Epoch start height and time
         let state_cf = self.get_column_family(STATE_CF)?;
         if let Some(current_value) = self
-            .0
+            .inner
             .get_cf(state_cf, "next_epoch_min_start_height")
             .map_err(|e| Error::DBError(e.into_string()))?
         {
             // Write the predecessor value for rollback
-            batch.0.put_cf(
+            batch.put_cf(
                 state_cf,
                 "pred/next_epoch_min_start_height",
                 current_value,
             );
         }
-        batch.0.put_cf(
+        batch.put_cf(
             state_cf,
             "next_epoch_min_start_height",
             encode(&next_epoch_min_start_height),
         );
 
         if let Some(current_value) = self
-            .0
+            .inner
             .get_cf(state_cf, "next_epoch_min_start_time")
             .map_err(|e| Error::DBError(e.into_string()))?
         {
             // Write the predecessor value for rollback
-            batch.0.put_cf(
+            batch.put_cf(
                 state_cf,
                 "pred/next_epoch_min_start_time",
                 current_value,
             );
         }
-        batch.0.put_cf(
+        batch.put_cf(
             state_cf,
             "next_epoch_min_start_time",
             encode(&next_epoch_min_start_time),
         );
         if let Some(current_value) = self
-            .0
+            .inner
             .get_cf(state_cf, "update_epoch_blocks_delay")
             .map_err(|e| Error::DBError(e.into_string()))?
         {
             // Write the predecessor value for rollback
-            batch.0.put_cf(
+            batch.put_cf(
                 state_cf,
                 "pred/update_epoch_blocks_delay",
                 current_value,
             );
         }
-        batch.0.put_cf(
+        batch.put_cf(
             state_cf,
             "update_epoch_blocks_delay",
             encode(&update_epoch_blocks_delay),
         );
 
-        // Save the conversion state when the epoch is updated
+        // Save the changes of the conversion state when the epoch is updated
         if is_full_commit {
-            if let Some(current_value) = self
+            let current = self.read_conversion_state_split()?;
+            let changes =
+                diff_split(&current, &conversion_state.encode_split());
+            // Write the predecessor values of the changed keys for rollback
+            let pred: Vec<(String, Option<Vec<u8>>)> = changes
+                .iter()
+                .map(|(key, _)| (key.clone(), current.get(key).cloned()))
+                .collect();
+            batch
                 .0
-                .get_cf(state_cf, "conversion_state")
-                .map_err(|e| Error::DBError(e.into_string()))?
-            {
-                // Write the predecessor value for rollback
-                batch.0.put_cf(
-                    state_cf,
-                    "pred/conversion_state",
-                    current_value,
-                );
+                .put_cf(state_cf, "pred/conversion_state", encode(&pred));
+            for (key, value) in changes {
+                match value {
+                    Some(value) => batch.put_cf(state_cf, key, value),
+                    None => batch.delete_cf(state_cf, key),
+                }
             }
-            batch.0.put_cf(
-                state_cf,
-                "conversion_state",
-                encode(conversion_state),
-            );
         }
 
         // Tx queue
         if let Some(pred_tx_queue) = self
-            .0
+            .inner
             .get_cf(state_cf, "tx_queue")
             .map_err(|e| Error::DBError(e.into_string()))?
         {
             // Write the predecessor value for rollback
-            batch.0.put_cf(state_cf, "pred/tx_queue", pred_tx_queue);
+            batch.put_cf(state_cf, "pred/tx_queue", pred_tx_queue);
         }
-        batch.0.put_cf(state_cf, "tx_queue", encode(&tx_queue));
+        batch.put_cf(state_cf, "tx_queue", encode(&tx_queue));
         batch
             .0
             .put_cf(state_cf, "ethereum_height", encode(&ethereum_height));
@@ -1032,13 +1517,13 @@ impl DB for RocksDB {
                     };
                     let root_key =
                         key_prefix.clone().with_segment("root".to_owned());
-                    batch.0.put_cf(
+                    batch.put_cf(
                         block_cf,
                         root_key.to_string(),
                         encode(merkle_tree_stores.root(st)),
                     );
                     let store_key = key_prefix.with_segment("store".to_owned());
-                    batch.0.put_cf(
+                    batch.put_cf(
                         block_cf,
                         store_key.to_string(),
                         merkle_tree_stores.store(st).encode(),
@@ -1062,26 +1547,26 @@ impl DB for RocksDB {
             let key = prefix_key
                 .push(&"hash".to_owned())
                 .map_err(Error::KeyError)?;
-            batch.0.put_cf(block_cf, key.to_string(), encode(&hash));
+            batch.put_cf(block_cf, key.to_string(), encode(&hash));
         }
         // Block time
         {
             let key = prefix_key
                 .push(&"time".to_owned())
                 .map_err(Error::KeyError)?;
-            batch.0.put_cf(block_cf, key.to_string(), encode(&time));
+            batch.put_cf(block_cf, key.to_string(), encode(&time));
         }
         // Block epoch
         {
             let key = prefix_key
                 .push(&"epoch".to_owned())
                 .map_err(Error::KeyError)?;
-            batch.0.put_cf(block_cf, key.to_string(), encode(&epoch));
+            batch.put_cf(block_cf, key.to_string(), encode(&epoch));
         }
         // Block results
         {
             let results_path = format!("results/{}", height.raw());
-            batch.0.put_cf(block_cf, results_path, encode(&results));
+            batch.put_cf(block_cf, results_path, encode(&results));
         }
         // Predecessor block epochs
         {
@@ -1103,7 +1588,14 @@ impl DB for RocksDB {
         }
 
         // Block height
-        batch.0.put_cf(state_cf, "height", encode(&height));
+        batch.put_cf(state_cf, "height", encode(&height));
+
+        // The diffs of the heights before the start of the epoch aren't
+        // needed to restore the merkle tree
+        let epoch_start_height = pred_epochs
+            .get_start_height_of_epoch(epoch)
+            .unwrap_or_default();
+        self.batch_prune_diffs(batch, height, epoch_start_height)?;
 
         Ok(())
     }
@@ -1115,7 +1607,7 @@ impl DB for RocksDB {
             .push(&"header".to_owned())
             .map_err(Error::KeyError)?;
         let value = self
-            .0
+            .inner
             .get_cf(block_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))?;
         match value {
@@ -1127,6 +1619,38 @@ impl DB for RocksDB {
         }
     }
 
+    fn read_block_headers(
+        &self,
+        from: BlockHeight,
+        to: BlockHeight,
+    ) -> Result<Vec<(BlockHeight, Header)>> {
+        let block_cf = self.get_column_family(BLOCK_CF)?;
+        let heights: Vec<BlockHeight> =
+            (from.0..=to.0).map(BlockHeight).collect();
+        let keys = heights
+            .iter()
+            .map(|height| {
+                Key::from(height.to_db_key())
+                    .push(&"header".to_owned())
+                    .map(|key| (block_cf, key.to_string()))
+                    .map_err(Error::KeyError)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Read all the headers at once
+        let values = self.inner.multi_get_cf(keys);
+
+        let mut headers = vec![];
+        for (height, value) in heights.into_iter().zip(values) {
+            let value = value.map_err(|e| Error::DBError(e.into_string()))?;
+            if let Some(v) = value {
+                let header = Header::try_from_slice(&v[..])
+                    .map_err(Error::BorshCodingError)?;
+                headers.push((height, header));
+            }
+        }
+        Ok(headers)
+    }
+
     fn read_merkle_tree_stores(
         &self,
         epoch: Epoch,
@@ -1148,7 +1672,7 @@ impl DB for RocksDB {
             };
             let root_key = key_prefix.clone().with_segment("root".to_owned());
             let bytes = self
-                .0
+                .inner
                 .get_cf(block_cf, root_key.to_string())
                 .map_err(|e| Error::DBError(e.into_string()))?;
             match bytes {
@@ -1161,7 +1685,7 @@ impl DB for RocksDB {
 
             let store_key = key_prefix.with_segment("store".to_owned());
             let bytes = self
-                .0
+                .inner
                 .get_cf(block_cf, store_key.to_string())
                 .map_err(|e| Error::DBError(e.into_string()))?;
             match bytes {
@@ -1186,7 +1710,7 @@ impl DB for RocksDB {
             replay_protection::all_key(hash),
         ] {
             if self
-                .0
+                .inner
                 .get_pinned_cf(replay_protection_cf, key.to_string())
                 .map_err(|e| Error::DBError(e.into_string()))?
                 .is_some()
@@ -1197,6 +1721,28 @@ impl DB for RocksDB {
         Ok(false)
     }
 
+    fn read_diffs_oldest_height(&self) -> Result<Option<BlockHeight>> {
+        let state_cf = self.get_column_family(STATE_CF)?;
+        self.inner
+            .get_cf(state_cf, DIFFS_OLDEST_HEIGHT_KEY)
+            .map_err(|e| Error::DBError(e.into_string()))?
+            .map(decode)
+            .transpose()
+            .map_err(Error::CodingError)
+    }
+
+    fn check_diffs_retained(&self, height: BlockHeight) -> Result<()> {
+        match self.read_diffs_oldest_height()? {
+            Some(oldest_height) if height < oldest_height => {
+                Err(Error::PrunedHeight {
+                    height,
+                    oldest_height,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn read_diffs_val(
         &self,
         key: &Key,
@@ -1210,14 +1756,14 @@ impl DB for RocksDB {
             old_and_new_diff_key(key, height)?.1
         };
 
-        self.0
+        self.inner
             .get_cf(diffs_cf, key)
             .map_err(|e| Error::DBError(e.into_string()))
     }
 
     fn read_subspace_val(&self, key: &Key) -> Result<Option<Vec<u8>>> {
         let subspace_cf = self.get_column_family(SUBSPACE_CF)?;
-        self.0
+        self.inner
             .get_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))
     }
@@ -1228,13 +1774,15 @@ impl DB for RocksDB {
         height: BlockHeight,
         last_height: BlockHeight,
     ) -> Result<Option<Vec<u8>>> {
+        self.check_diffs_retained(height)?;
+
         // Check if the value changed at this height
         let diffs_cf = self.get_column_family(DIFFS_CF)?;
         let (old_val_key, new_val_key) = old_and_new_diff_key(key, height)?;
 
         // If it has a "new" val, it was written at this height
         match self
-            .0
+            .inner
             .get_cf(diffs_cf, new_val_key)
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -1243,10 +1791,10 @@ impl DB for RocksDB {
             }
             None => {
                 // If it has an "old" val, it was deleted at this height
-                if self.0.key_may_exist_cf(diffs_cf, &old_val_key) {
+                if self.inner.key_may_exist_cf(diffs_cf, &old_val_key) {
                     // check if it actually exists
                     if self
-                        .0
+                        .inner
                         .get_cf(diffs_cf, old_val_key)
                         .map_err(|e| Error::DBError(e.into_string()))?
                         .is_some()
@@ -1265,7 +1813,7 @@ impl DB for RocksDB {
             let (old_val_key, new_val_key) =
                 old_and_new_diff_key(key, BlockHeight(raw_height))?;
             let old_val = self
-                .0
+                .inner
                 .get_cf(diffs_cf, &old_val_key)
                 .map_err(|e| Error::DBError(e.into_string()))?;
             // If it has an "old" val, it's the one we're looking for
@@ -1274,10 +1822,10 @@ impl DB for RocksDB {
                 None => {
                     // Check if the value was created at this height instead,
                     // which would mean that it wasn't present before
-                    if self.0.key_may_exist_cf(diffs_cf, &new_val_key) {
+                    if self.inner.key_may_exist_cf(diffs_cf, &new_val_key) {
                         // check if it actually exists
                         if self
-                            .0
+                            .inner
                             .get_cf(diffs_cf, new_val_key)
                             .map_err(|e| Error::DBError(e.into_string()))?
                             .is_some()
@@ -1307,7 +1855,7 @@ impl DB for RocksDB {
         let subspace_cf = self.get_column_family(SUBSPACE_CF)?;
         let value = value.as_ref();
         let size_diff = match self
-            .0
+            .inner
             .get_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -1335,7 +1883,7 @@ impl DB for RocksDB {
         };
 
         // Write the new key-val
-        self.0
+        self.inner
             .put_cf(subspace_cf, key.to_string(), value)
             .map_err(|e| Error::DBError(e.into_string()))?;
 
@@ -1352,7 +1900,7 @@ impl DB for RocksDB {
 
         // Check the length of previous value, if any
         let prev_len = match self
-            .0
+            .inner
             .get_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -1371,7 +1919,7 @@ impl DB for RocksDB {
         };
 
         // Delete the key-val
-        self.0
+        self.inner
             .delete_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))?;
 
@@ -1383,7 +1931,7 @@ impl DB for RocksDB {
     }
 
     fn exec_batch(&mut self, batch: Self::WriteBatch) -> Result<()> {
-        self.exec_batch(batch.0)
+        self.exec_batch(batch)
     }
 
     fn batch_write_subspace_val(
@@ -1397,7 +1945,7 @@ impl DB for RocksDB {
         let value = value.as_ref();
         let subspace_cf = self.get_column_family(SUBSPACE_CF)?;
         let size_diff = match self
-            .0
+            .inner
             .get_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -1428,7 +1976,7 @@ impl DB for RocksDB {
         };
 
         // Write the new key-val
-        batch.0.put_cf(subspace_cf, key.to_string(), value);
+        batch.put_cf(subspace_cf, key.to_string(), value);
 
         Ok(size_diff)
     }
@@ -1444,7 +1992,7 @@ impl DB for RocksDB {
 
         // Check the length of previous value, if any
         let prev_len = match self
-            .0
+            .inner
             .get_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -1465,11 +2013,51 @@ impl DB for RocksDB {
         };
 
         // Delete the key-val
-        batch.0.delete_cf(subspace_cf, key.to_string());
+        batch.delete_cf(subspace_cf, key.to_string());
 
         Ok(prev_len)
     }
 
+    fn delete_prefix(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        prefix: &Key,
+        persist_diffs: impl Fn(&Key) -> bool,
+    ) -> Result<(Vec<Key>, i64)> {
+        let subspace_cf = self.get_column_family(SUBSPACE_CF)?;
+
+        // Persist the previous values
+        let mut keys = vec![];
+        let mut deleted_len = 0;
+        for (key, prev_value, _gas) in iter_subspace_prefix(self, Some(prefix))
+        {
+            let key = Key::parse(key).map_err(Error::KeyError)?;
+            self.batch_write_subspace_diff(
+                batch,
+                height,
+                &key,
+                Some(&prev_value),
+                None,
+                persist_diffs(&key),
+            )?;
+            deleted_len += prev_value.len() as i64;
+            keys.push(key);
+        }
+
+        // Delete the key-vals with a single range deletion. The keys are
+        // sorted, so the range from the first key until right past the last
+        // key only contains the keys with the prefix.
+        if let (Some(first), Some(last)) = (keys.first(), keys.last()) {
+            let from = first.to_string().into_bytes();
+            let mut to = last.to_string().into_bytes();
+            to.push(0);
+            batch.delete_range_cf(subspace_cf, from, to);
+        }
+
+        Ok((keys, deleted_len))
+    }
+
     fn prune_merkle_tree_store(
         &mut self,
         batch: &mut Self::WriteBatch,
@@ -1479,9 +2067,9 @@ impl DB for RocksDB {
         let block_cf = self.get_column_family(BLOCK_CF)?;
         let key_prefix = subtree_key_prefix(store_type, epoch);
         let root_key = key_prefix.clone().with_segment("root".to_owned());
-        batch.0.delete_cf(block_cf, root_key.to_string());
+        batch.delete_cf(block_cf, root_key.to_string());
         let store_key = key_prefix.with_segment("store".to_owned());
-        batch.0.delete_cf(block_cf, store_key.to_string());
+        batch.delete_cf(block_cf, store_key.to_string());
         Ok(())
     }
 
@@ -1529,7 +2117,7 @@ impl DB for RocksDB {
         let replay_protection_cf =
             self.get_column_family(REPLAY_PROTECTION_CF)?;
 
-        batch.0.delete_cf(replay_protection_cf, key.to_string());
+        batch.delete_cf(replay_protection_cf, key.to_string());
 
         Ok(())
     }
@@ -1538,11 +2126,12 @@ impl DB for RocksDB {
 impl<'iter> DBIter<'iter> for RocksDB {
     type PrefixIter = PersistentPrefixIterator<'iter>;
 
-    fn iter_prefix(
+    fn iter_prefix_opts(
         &'iter self,
         prefix: Option<&Key>,
+        opts: &IterOpts,
     ) -> PersistentPrefixIterator<'iter> {
-        iter_subspace_prefix(self, prefix)
+        iter_subspace_prefix(self, prefix, opts)
     }
 
     fn iter_results(&'iter self) -> PersistentPrefixIterator<'iter> {
@@ -1553,12 +2142,12 @@ impl<'iter> DBIter<'iter> for RocksDB {
             .get_column_family(BLOCK_CF)
             .expect("{BLOCK_CF} column family should exist");
         let read_opts = make_iter_read_opts(Some(prefix.clone()));
-        let iter = self.0.iterator_cf_opt(
+        let iter = self.inner.iterator_cf_opt(
             block_cf,
             read_opts,
             IteratorMode::From(prefix.as_bytes(), Direction::Forward),
         );
-        PersistentPrefixIterator(PrefixIterator::new(iter, db_prefix))
+        PersistentPrefixIterator::new(PrefixIterator::new(iter, db_prefix))
     }
 
     fn iter_old_diffs(
@@ -1583,19 +2172,26 @@ impl<'iter> DBIter<'iter> for RocksDB {
             .expect("{REPLAY_PROTECTION_CF} column family should exist");
 
         let stripped_prefix = Some(replay_protection::last_prefix());
-        iter_prefix(self, replay_protection_cf, stripped_prefix.as_ref(), None)
+        iter_prefix(
+            self,
+            replay_protection_cf,
+            stripped_prefix.as_ref(),
+            None,
+            &IterOpts::default(),
+        )
     }
 }
 
 fn iter_subspace_prefix<'iter>(
     db: &'iter RocksDB,
     prefix: Option<&Key>,
+    opts: &IterOpts,
 ) -> PersistentPrefixIterator<'iter> {
     let subspace_cf = db
         .get_column_family(SUBSPACE_CF)
         .expect("{SUBSPACE_CF} column family should exist");
     let stripped_prefix = None;
-    iter_prefix(db, subspace_cf, stripped_prefix, prefix)
+    iter_prefix(db, subspace_cf, stripped_prefix, prefix, opts)
 }
 
 fn iter_diffs_prefix<'a>(
@@ -1618,18 +2214,26 @@ fn iter_diffs_prefix<'a>(
             .unwrap(),
     );
     // get keys without the `stripped_prefix`
-    iter_prefix(db, diffs_cf, stripped_prefix.as_ref(), prefix)
+    iter_prefix(
+        db,
+        diffs_cf,
+        stripped_prefix.as_ref(),
+        prefix,
+        &IterOpts::default(),
+    )
 }
 
 /// Create an iterator over key-vals in the given CF matching the given
-/// prefix(es). If any, the `stripped_prefix` is matched first and will be
-/// removed from the matched keys. If any, the second `prefix` is matched
-/// against the stripped keys and remains in the matched keys.
+/// prefix(es) with the given options. If any, the `stripped_prefix` is matched
+/// first and will be removed from the matched keys. If any, the second
+/// `prefix` is matched against the stripped keys and remains in the matched
+/// keys.
 fn iter_prefix<'a>(
     db: &'a RocksDB,
     cf: &'a ColumnFamily,
     stripped_prefix: Option<&Key>,
     prefix: Option<&Key>,
+    opts: &IterOpts,
 ) -> PersistentPrefixIterator<'a> {
     let stripped_prefix = match stripped_prefix {
         Some(p) if !p.is_empty() => format!("{p}/"),
@@ -1641,40 +2245,89 @@ fn iter_prefix<'a>(
         }
         _ => stripped_prefix.clone(),
     };
-    let read_opts = make_iter_read_opts(Some(prefix.clone()));
-    let iter = db.0.iterator_cf_opt(
-        cf,
-        read_opts,
-        IteratorMode::From(prefix.as_bytes(), Direction::Forward),
-    );
-    PersistentPrefixIterator(PrefixIterator::new(iter, stripped_prefix))
+    let mut read_opts = make_iter_read_opts(Some(prefix.clone()));
+    let start_after = opts
+        .start_after
+        .as_ref()
+        .map(|key| format!("{stripped_prefix}{key}"));
+    let mode = if opts.reverse {
+        read_opts.set_iterate_lower_bound(prefix.as_bytes());
+        match &start_after {
+            Some(start_after) => {
+                IteratorMode::From(start_after.as_bytes(), Direction::Reverse)
+            }
+            // The last key is bounded by the upper bound of the prefix
+            None => IteratorMode::End,
+        }
+    } else {
+        // Start from the prefix if the start key precedes it
+        let from = match &start_after {
+            Some(start_after) if start_after.as_str() > prefix.as_str() => {
+                start_after
+            }
+            _ => &prefix,
+        };
+        IteratorMode::From(from.as_bytes(), Direction::Forward)
+    };
+    let iter = db.inner.iterator_cf_opt(cf, read_opts, mode);
+    PersistentPrefixIterator {
+        iter: PrefixIterator::new(iter, stripped_prefix),
+        start_after: opts.start_after.as_ref().map(|key| key.to_string()),
+        remaining: opts.limit,
+    }
 }
 
 #[derive(Debug)]
-pub struct PersistentPrefixIterator<'a>(
-    PrefixIterator<rocksdb::DBIterator<'a>>,
-);
+pub struct PersistentPrefixIterator<'a> {
+    iter: PrefixIterator<rocksdb::DBIterator<'a>>,
+    /// The key to be skipped, from which the iteration has started
+    start_after: Option<String>,
+    /// The number of the remaining key-vals to be iterated, if limited
+    remaining: Option<usize>,
+}
+
+impl<'a> PersistentPrefixIterator<'a> {
+    /// Iterate all the key-vals of the given iterator
+    fn new(iter: PrefixIterator<rocksdb::DBIterator<'a>>) -> Self {
+        Self {
+            iter,
+            start_after: None,
+            remaining: None,
+        }
+    }
+}
 
 impl<'a> Iterator for PersistentPrefixIterator<'a> {
     type Item = (String, Vec<u8>, u64);
 
     /// Returns the next pair and the gas cost
     fn next(&mut self) -> Option<(String, Vec<u8>, u64)> {
+        if self.remaining == Some(0) {
+            return None;
+        }
         loop {
-            match self.0.iter.next() {
+            match self.iter.iter.next() {
                 Some(result) => {
                     let (key, val) =
                         result.expect("Prefix iterator shouldn't fail");
                     let key = String::from_utf8(key.to_vec())
                         .expect("Cannot convert from bytes to key string");
-                    if let Some(k) = key.strip_prefix(&self.0.stripped_prefix) {
+                    if let Some(k) =
+                        key.strip_prefix(&self.iter.stripped_prefix)
+                    {
+                        if self.start_after.as_deref() == Some(k) {
+                            continue;
+                        }
+                        if let Some(remaining) = self.remaining.as_mut() {
+                            *remaining -= 1;
+                        }
                         let gas = k.len() + val.len();
                         return Some((k.to_owned(), val.to_vec(), gas as _));
                     } else {
                         tracing::warn!(
                             "Unmatched prefix \"{}\" in iterator's key \
                              \"{key}\"",
-                            self.0.stripped_prefix
+                            self.iter.stripped_prefix
                         );
                     }
                 }
@@ -1703,6 +2356,10 @@ fn make_iter_read_opts(prefix: Option<String>) -> ReadOptions {
 
 impl DBWriteBatch for RocksDBWriteBatch {}
 
+fn batch_journal_key(index: usize) -> String {
+    format!("{BATCH_JOURNAL_PREFIX}/{index}")
+}
+
 fn old_and_new_diff_key(
     key: &Key,
     height: BlockHeight,
@@ -1798,7 +2455,7 @@ mod test {
     #[test]
     fn test_load_state() {
         let dir = tempdir().unwrap();
-        let mut db = open(dir.path(), None).unwrap();
+        let mut db = open(dir.path(), None, None).unwrap();
 
         let mut batch = RocksDB::batch();
         let last_height = BlockHeight::default();
@@ -1820,7 +2477,7 @@ mod test {
             &ConversionState::default(),
         )
         .unwrap();
-        db.exec_batch(batch.0).unwrap();
+        db.exec_batch(batch).unwrap();
 
         let _state = db
             .read_last_block()
@@ -1828,10 +2485,159 @@ mod test {
             .expect("Block should have been written");
     }
 
+    /// Test that the DB stats grow with the written keys
+    #[test]
+    fn test_db_stats() {
+        let dir = tempdir().unwrap();
+        let mut db = open(dir.path(), None, None).unwrap();
+
+        let stats_pre = db.db_stats().unwrap();
+        let subspace_pre = stats_pre.column_family(SUBSPACE_CF).unwrap();
+        assert_eq!(stats_pre.column_families.len(), 5);
+
+        let height = BlockHeight(1);
+        for i in 0..5000 {
+            let key = Key::parse(format!("key{i}")).unwrap();
+            db.write_subspace_val(height, &key, [i as u8; 32], false)
+                .unwrap();
+        }
+        db.flush(true).unwrap();
+
+        let stats_post = db.db_stats().unwrap();
+        let subspace_post = stats_post.column_family(SUBSPACE_CF).unwrap();
+        assert!(subspace_post.estimated_keys > subspace_pre.estimated_keys);
+        assert!(subspace_post.live_sst_bytes > subspace_pre.live_sst_bytes);
+    }
+
+    /// Test that a batch exceeding the max batch size is written in
+    /// sub-batches
+    #[test]
+    fn test_exec_oversized_batch() {
+        let dir = tempdir().unwrap();
+        let mut db = open(dir.path(), None, None).unwrap();
+
+        let height = BlockHeight(1);
+        let keys: Vec<Key> = (0..100)
+            .map(|i| Key::parse(format!("key{i}")).unwrap())
+            .collect();
+        let mut batch = RocksDB::batch();
+        batch.max_batch_bytes = 1024;
+        for (i, key) in keys.iter().enumerate() {
+            db.batch_write_subspace_val(
+                &mut batch,
+                height,
+                key,
+                [i as u8; 32],
+                false,
+            )
+            .unwrap();
+        }
+        assert!(batch.sub_batches.len() > 1);
+        db.exec_batch(batch).unwrap();
+
+        for (i, key) in keys.iter().enumerate() {
+            let value = db.read_subspace_val(key).unwrap();
+            assert_eq!(value, Some(vec![i as u8; 32]));
+        }
+        // the journal has been cleared
+        let state_cf = db.get_column_family(STATE_CF).unwrap();
+        let len = db.inner.get_cf(state_cf, BATCH_JOURNAL_LEN_KEY).unwrap();
+        assert!(len.is_none());
+        let first = db.inner.get_cf(state_cf, batch_journal_key(0)).unwrap();
+        assert!(first.is_none());
+
+        // an entry exceeding the max batch size is written in its own
+        // sub-batch
+        let large_key = Key::parse("large").unwrap();
+        let mut batch = RocksDB::batch();
+        batch.max_batch_bytes = 1024;
+        for key in [&keys[0], &large_key, &keys[1]] {
+            db.batch_write_subspace_val(
+                &mut batch,
+                height,
+                key,
+                vec![0_u8; 2048],
+                false,
+            )
+            .unwrap();
+        }
+        assert_eq!(batch.sub_batches.len(), 3);
+        db.exec_batch(batch).unwrap();
+        for key in [&keys[0], &large_key, &keys[1]] {
+            let value = db.read_subspace_val(key).unwrap();
+            assert_eq!(value, Some(vec![0_u8; 2048]));
+        }
+    }
+
+    /// Test that a batch interrupted between its sub-batches is rolled
+    /// forward when the DB is reopened
+    #[test]
+    fn test_recover_partial_batch() {
+        let dir = tempdir().unwrap();
+        let db = open(dir.path(), None, None).unwrap();
+
+        let height = BlockHeight(1);
+        let keys: Vec<Key> = (0..100)
+            .map(|i| Key::parse(format!("key{i}")).unwrap())
+            .collect();
+        let mut batch = RocksDB::batch();
+        batch.max_batch_bytes = 1024;
+        for (i, key) in keys.iter().enumerate() {
+            db.batch_write_subspace_val(
+                &mut batch,
+                height,
+                key,
+                [i as u8; 32],
+                false,
+            )
+            .unwrap();
+        }
+        db.journal_sub_batches(&batch.sub_batches).unwrap();
+        // crash after writing only the first sub-batch
+        db.write_batch(WriteBatch::from_data(batch.sub_batches[0].data()))
+            .unwrap();
+        let last = db.read_subspace_val(keys.last().unwrap()).unwrap();
+        assert!(last.is_none());
+        drop(db);
+
+        // the batch is rolled forward on reopening, which fails the startup
+        let result = open(dir.path(), None, None);
+        assert!(matches!(
+            result,
+            Err(Error::PartialBatchRecovered { num_sub_batches })
+                if num_sub_batches > 1
+        ));
+        let db = open(dir.path(), None, None).unwrap();
+        for (i, key) in keys.iter().enumerate() {
+            let value = db.read_subspace_val(key).unwrap();
+            assert_eq!(value, Some(vec![i as u8; 32]));
+        }
+        let state_cf = db.get_column_family(STATE_CF).unwrap();
+        let len = db.inner.get_cf(state_cf, BATCH_JOURNAL_LEN_KEY).unwrap();
+        assert!(len.is_none());
+
+        // an incomplete journal is discarded without writing anything
+        let subspace_cf = db.get_column_family(SUBSPACE_CF).unwrap();
+        let mut batch = WriteBatch::default();
+        batch.put_cf(subspace_cf, "discarded", vec![1_u8]);
+        db.inner
+            .put_cf(state_cf, batch_journal_key(0), batch.data())
+            .unwrap();
+        drop(db);
+
+        let db = open(dir.path(), None, None).unwrap();
+        let subspace_cf = db.get_column_family(SUBSPACE_CF).unwrap();
+        let discarded = db.inner.get_cf(subspace_cf, "discarded").unwrap();
+        assert!(discarded.is_none());
+        let state_cf = db.get_column_family(STATE_CF).unwrap();
+        let first = db.inner.get_cf(state_cf, batch_journal_key(0)).unwrap();
+        assert!(first.is_none());
+    }
+
     #[test]
     fn test_read() {
         let dir = tempdir().unwrap();
-        let mut db = open(dir.path(), None).unwrap();
+        let mut db = open(dir.path(), None, None).unwrap();
 
         let key = Key::parse("test").unwrap();
         let batch_key = Key::parse("batch").unwrap();
@@ -1846,7 +2652,7 @@ mod test {
             true,
         )
         .unwrap();
-        db.exec_batch(batch.0).unwrap();
+        db.exec_batch(batch).unwrap();
 
         db.write_subspace_val(last_height, &key, vec![1_u8, 1, 1, 0], true)
             .unwrap();
@@ -1861,7 +2667,7 @@ mod test {
             true,
         )
         .unwrap();
-        db.exec_batch(batch.0).unwrap();
+        db.exec_batch(batch).unwrap();
 
         db.write_subspace_val(last_height, &key, vec![2_u8, 2, 2, 0], true)
             .unwrap();
@@ -1904,7 +2710,7 @@ mod test {
         let last_height = BlockHeight(222);
         db.batch_delete_subspace_val(&mut batch, last_height, &batch_key, true)
             .unwrap();
-        db.exec_batch(batch.0).unwrap();
+        db.exec_batch(batch).unwrap();
 
         db.delete_subspace_val(last_height, &key, true).unwrap();
 
@@ -1933,7 +2739,7 @@ mod test {
     #[test]
     fn test_prefix_iter() {
         let dir = tempdir().unwrap();
-        let mut db = open(dir.path(), None).unwrap();
+        let mut db = open(dir.path(), None, None).unwrap();
 
         let prefix_0 = Key::parse("0").unwrap();
         let key_0_a = prefix_0.push(&"a".to_string()).unwrap();
@@ -1958,7 +2764,7 @@ mod test {
             db.batch_write_subspace_val(&mut batch, height, key, [0_u8], true)
                 .unwrap();
         }
-        db.exec_batch(batch.0).unwrap();
+        db.exec_batch(batch).unwrap();
 
         // Prefix "0" shouldn't match prefix "01"
         let itered_keys: Vec<Key> = db
@@ -1977,13 +2783,72 @@ mod test {
             .iter_prefix(None)
             .map(|(key, _val, _)| Key::parse(key).unwrap())
             .collect();
-        itertools::assert_equal(all_keys, itered_keys);
+        itertools::assert_equal(all_keys.clone(), itered_keys);
+
+        let iter_opts = |prefix: Option<&Key>, opts: IterOpts| -> Vec<Key> {
+            db.iter_prefix_opts(prefix, &opts)
+                .map(|(key, _val, _)| Key::parse(key).unwrap())
+                .collect()
+        };
+
+        // The reverse iteration of prefix "0" shouldn't match prefix "01"
+        let itered_keys = iter_opts(
+            Some(&prefix_0),
+            IterOpts {
+                reverse: true,
+                ..Default::default()
+            },
+        );
+        itertools::assert_equal(keys_0.iter().rev().cloned(), itered_keys);
+
+        // Resume after the first page in both directions
+        let itered_keys = iter_opts(
+            Some(&prefix_1),
+            IterOpts {
+                reverse: false,
+                start_after: Some(keys_1[0].clone()),
+                limit: Some(1),
+            },
+        );
+        assert_eq!(itered_keys, vec![keys_1[1].clone()]);
+        let itered_keys = iter_opts(
+            Some(&prefix_1),
+            IterOpts {
+                reverse: true,
+                start_after: Some(keys_1[2].clone()),
+                limit: Some(5),
+            },
+        );
+        assert_eq!(itered_keys, vec![keys_1[1].clone(), keys_1[0].clone()]);
+
+        // A start key before the prefix doesn't match other prefixes
+        let itered_keys = iter_opts(
+            Some(&prefix_1),
+            IterOpts {
+                reverse: false,
+                start_after: Some(keys_0[0].clone()),
+                limit: None,
+            },
+        );
+        itertools::assert_equal(keys_1.clone(), itered_keys);
+        let itered_keys = iter_opts(
+            None,
+            IterOpts {
+                reverse: true,
+                start_after: Some(keys_1[0].clone()),
+                limit: Some(2),
+            },
+        );
+        itertools::assert_equal(
+            all_keys.iter().rev().skip(3).take(2).cloned(),
+            itered_keys,
+        );
     }
 
     #[test]
     fn test_rollback() {
         let dir = tempdir().unwrap();
-        let mut db = open(dir.path(), None).unwrap();
+        let mut db = open(dir.path(), None, None).unwrap();
 
         // A key that's gonna be added on a second block
         let add_key = Key::parse("add").unwrap();
@@ -2029,7 +2894,7 @@ mod test {
             &conversion_state_0,
         )
         .unwrap();
-        db.exec_batch(batch.0).unwrap();
+        db.exec_batch(batch).unwrap();
 
         // Write second block
         let mut batch = RocksDB::batch();
@@ -2065,7 +2930,7 @@ mod test {
             &conversion_state_1,
         )
         .unwrap();
-        db.exec_batch(batch.0).unwrap();
+        db.exec_batch(batch).unwrap();
 
         // Check that the values are as expected from second block
         let added = db.read_subspace_val(&add_key).unwrap();
@@ -2086,18 +2951,175 @@ mod test {
         let deleted = db.read_subspace_val(&delete_key).unwrap();
         assert_eq!(deleted, Some(to_delete_val));
         // Check the conversion state
+        let conversion_state = db.read_conversion_state().unwrap().unwrap();
+        assert_eq!(encode(&conversion_state), encode(&conversion_state_0));
+    }
+
+    /// Test that the conversion state persisted as a single legacy value is
+    /// loaded and replaced with the split representation on the next epoch
+    /// update
+    #[test]
+    fn test_legacy_conversion_state() {
+        let dir = tempdir().unwrap();
+        let mut db = open(dir.path(), None, None).unwrap();
+
+        let mut conversion_state = ConversionState::default();
+        conversion_state
+            .tokens
+            .insert("dummy".to_string(), gen_established_address("test"));
+        let state_cf = db.get_column_family(STATE_CF).unwrap();
+        db.inner
+            .put_cf(
+                state_cf,
+                conversion_state::LEGACY_KEY,
+                encode(&conversion_state),
+            )
+            .unwrap();
+        let loaded = db.read_conversion_state().unwrap().unwrap();
+        assert_eq!(encode(&loaded), encode(&conversion_state));
+
+        let mut batch = RocksDB::batch();
+        let height = BlockHeight(100);
+        let mut pred_epochs = Epochs::default();
+        pred_epochs.new_epoch(height);
+        add_block_to_batch(
+            &db,
+            &mut batch,
+            height,
+            Epoch(1),
+            pred_epochs,
+            &conversion_state,
+        )
+        .unwrap();
+        db.exec_batch(batch).unwrap();
+
         let state_cf = db.get_column_family(STATE_CF).unwrap();
-        let conversion_state =
-            db.0.get_cf(state_cf, "conversion_state".as_bytes())
-                .unwrap()
+        let legacy = db
+            .inner
+            .get_cf(state_cf, conversion_state::LEGACY_KEY)
+            .unwrap();
+        assert!(legacy.is_none());
+        let split = db.read_conversion_state_split().unwrap();
+        assert_eq!(split, conversion_state.encode_split());
+        let loaded = db.read_conversion_state().unwrap().unwrap();
+        assert_eq!(encode(&loaded), encode(&conversion_state));
+    }
+
+    #[test]
+    fn test_prune_diffs() {
+        let dir = tempdir().unwrap();
+        let retention = 5;
+        let mut db = open(dir.path(), None, Some(retention)).unwrap();
+        let key = Key::parse("test").unwrap();
+        let count_diffs = |db: &RocksDB| {
+            let diffs_cf = db.get_column_family(DIFFS_CF).unwrap();
+            db.inner.iterator_cf(diffs_cf, IteratorMode::Start).count()
+        };
+
+        // Write the key at each height, starting a new epoch with each block
+        let mut pred_epochs = Epochs::default();
+        for height in 1..=10_u64 {
+            let height = BlockHeight(height);
+            pred_epochs.new_epoch(height);
+            let mut batch = RocksDB::batch();
+            db.batch_write_subspace_val(
+                &mut batch,
+                height,
+                &key,
+                height.serialize_to_vec(),
+                true,
+            )
+            .unwrap();
+            add_block_to_batch(
+                &db,
+                &mut batch,
+                height,
+                Epoch(height.0),
+                pred_epochs.clone(),
+                &ConversionState::default(),
+            )
+            .unwrap();
+            db.exec_batch(batch).unwrap();
+
+            if height == BlockHeight(retention) {
+                // Nothing has been pruned yet: a new diff at the first height
+                // and an old and a new diff at the others
+                assert_eq!(count_diffs(&db), 9);
+            }
+        }
+
+        // Only the diffs of the retained heights are left
+        assert_eq!(count_diffs(&db), 2 * retention as usize);
+        let last_height = BlockHeight(10);
+        let oldest_height = BlockHeight(10 - retention);
+        assert_eq!(db.read_diffs_oldest_height().unwrap(), Some(oldest_height));
+        assert!(db.check_diffs_retained(oldest_height).is_ok());
+        for height in [oldest_height, BlockHeight(7)] {
+            let value = db
+                .read_subspace_val_with_height(&key, height, last_height)
                 .unwrap();
-        assert_eq!(conversion_state, encode(&conversion_state_0));
+            assert_eq!(value, Some(height.serialize_to_vec()));
+        }
+        let result =
+            db.read_subspace_val_with_height(&key, BlockHeight(1), last_height);
+        assert!(matches!(
+            result,
+            Err(Error::PrunedHeight {
+                height: BlockHeight(1),
+                oldest_height: BlockHeight(5),
+            })
+        ));
+    }
+
+    #[test]
+    fn test_prune_diffs_since_epoch_start() {
+        let dir = tempdir().unwrap();
+        let mut db = open(dir.path(), None, Some(1)).unwrap();
+        let key = Key::parse("test").unwrap();
+
+        // All the blocks are in the epoch starting at the height 2
+        let mut pred_epochs = Epochs::default();
+        pred_epochs.new_epoch(BlockHeight(2));
+        for height in 1..=5_u64 {
+            let height = BlockHeight(height);
+            let mut batch = RocksDB::batch();
+            db.batch_write_subspace_val(
+                &mut batch,
+                height,
+                &key,
+                height.serialize_to_vec(),
+                true,
+            )
+            .unwrap();
+            add_block_to_batch(
+                &db,
+                &mut batch,
+                height,
+                Epoch(1),
+                pred_epochs.clone(),
+                &ConversionState::default(),
+            )
+            .unwrap();
+            db.exec_batch(batch).unwrap();
+        }
+
+        // The diffs after the epoch start are retained to restore the merkle
+        // tree
+        assert!(db.check_diffs_retained(BlockHeight(2)).is_ok());
+        assert!(matches!(
+            db.check_diffs_retained(BlockHeight(1)),
+            Err(Error::PrunedHeight { .. })
+        ));
+        let value = db
+            .read_subspace_val_with_height(&key, BlockHeight(2), BlockHeight(5))
+            .unwrap();
+        assert_eq!(value, Some(BlockHeight(2).serialize_to_vec()));
     }
 
     #[test]
     fn test_diffs() {
         let dir = tempdir().unwrap();
-        let mut db = open(dir.path(), None).unwrap();
+        let mut db = open(dir.path(), None, None).unwrap();
 
         let key_with_diffs = Key::parse("with_diffs").unwrap();
         let key_without_diffs = Key::parse("without_diffs").unwrap();
@@ -2124,7 +3146,7 @@ mod test {
             false,
         )
         .unwrap();
-        db.exec_batch(batch.0).unwrap();
+        db.exec_batch(batch).unwrap();
 
         {
             let diffs_cf = db.get_column_family(DIFFS_CF).unwrap();
@@ -2132,14 +3154,14 @@ mod test {
             // Diffs new key for `key_with_diffs` at height_0 must be present
             let (old_with_h0, new_with_h0) =
                 old_and_new_diff_key(&key_with_diffs, height_0).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_with_h0).unwrap().is_none());
-            assert!(db.0.get_cf(diffs_cf, new_with_h0).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_with_h0).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, new_with_h0).unwrap().is_some());
 
             // Diffs new key for `key_without_diffs` at height_0 must be present
             let (old_wo_h0, new_wo_h0) =
                 old_and_new_diff_key(&key_without_diffs, height_0).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_wo_h0).unwrap().is_none());
-            assert!(db.0.get_cf(diffs_cf, new_wo_h0).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_wo_h0).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, new_wo_h0).unwrap().is_some());
         }
 
         // Write second block
@@ -2161,7 +3183,7 @@ mod test {
             false,
         )
         .unwrap();
-        db.exec_batch(batch.0).unwrap();
+        db.exec_batch(batch).unwrap();
 
         {
             let diffs_cf = db.get_column_family(DIFFS_CF).unwrap();
@@ -2169,26 +3191,26 @@ mod test {
             // Diffs keys for `key_with_diffs` at height_0 must be present
             let (old_with_h0, new_with_h0) =
                 old_and_new_diff_key(&key_with_diffs, height_0).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_with_h0).unwrap().is_none());
-            assert!(db.0.get_cf(diffs_cf, new_with_h0).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_with_h0).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, new_with_h0).unwrap().is_some());
 
             // Diffs keys for `key_without_diffs` at height_0 must be gone
             let (old_wo_h0, new_wo_h0) =
                 old_and_new_diff_key(&key_without_diffs, height_0).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_wo_h0).unwrap().is_none());
-            assert!(db.0.get_cf(diffs_cf, new_wo_h0).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, old_wo_h0).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, new_wo_h0).unwrap().is_none());
 
             // Diffs keys for `key_with_diffs` at height_1 must be present
             let (old_with_h1, new_with_h1) =
                 old_and_new_diff_key(&key_with_diffs, height_1).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_with_h1).unwrap().is_some());
-            assert!(db.0.get_cf(diffs_cf, new_with_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_with_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, new_with_h1).unwrap().is_some());
 
             // Diffs keys for `key_without_diffs` at height_1 must be present
             let (old_wo_h1, new_wo_h1) =
                 old_and_new_diff_key(&key_without_diffs, height_1).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_wo_h1).unwrap().is_some());
-            assert!(db.0.get_cf(diffs_cf, new_wo_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_wo_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, new_wo_h1).unwrap().is_some());
         }
 
         // Write third block
@@ -2210,7 +3232,7 @@ mod test {
             false,
         )
         .unwrap();
-        db.exec_batch(batch.0).unwrap();
+        db.exec_batch(batch).unwrap();
 
         {
             let diffs_cf = db.get_column_family(DIFFS_CF).unwrap();
@@ -2218,26 +3240,26 @@ mod test {
             // Diffs keys for `key_with_diffs` at height_1 must be present
             let (old_with_h1, new_with_h1) =
                 old_and_new_diff_key(&key_with_diffs, height_1).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_with_h1).unwrap().is_some());
-            assert!(db.0.get_cf(diffs_cf, new_with_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_with_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, new_with_h1).unwrap().is_some());
 
             // Diffs keys for `key_without_diffs` at height_1 must be gone
             let (old_wo_h1, new_wo_h1) =
                 old_and_new_diff_key(&key_without_diffs, height_1).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_wo_h1).unwrap().is_none());
-            assert!(db.0.get_cf(diffs_cf, new_wo_h1).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, old_wo_h1).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, new_wo_h1).unwrap().is_none());
 
             // Diffs keys for `key_with_diffs` at height_2 must be present
             let (old_with_h2, new_with_h2) =
                 old_and_new_diff_key(&key_with_diffs, height_2).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_with_h2).unwrap().is_some());
-            assert!(db.0.get_cf(diffs_cf, new_with_h2).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_with_h2).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, new_with_h2).unwrap().is_some());
 
             // Diffs keys for `key_without_diffs` at height_2 must be present
             let (old_wo_h2, new_wo_h2) =
                 old_and_new_diff_key(&key_without_diffs, height_2).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_wo_h2).unwrap().is_some());
-            assert!(db.0.get_cf(diffs_cf, new_wo_h2).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_wo_h2).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, new_wo_h2).unwrap().is_some());
         }
     }
 
@@ -1,8 +1,9 @@
 use std::cell::RefCell;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::rc::Rc;
 use std::str::FromStr;
 
+use borsh_ext::BorshSerializeExt;
 use criterion::{criterion_group, criterion_main, Criterion};
 use masp_primitives::sapling::Node;
 use namada::core::address::{self, Address, InternalAddress};
@@ -23,7 +24,7 @@ use namada::ibc::core::connection::types::Counterparty;
 use namada::ibc::core::host::types::identifiers::{
     ClientId, ClientType, ConnectionId, PortId,
 };
-use namada::ibc::{IbcActions, TransferModule};
+use namada::ibc::{IbcActions, PfmModule};
 use namada::ledger::eth_bridge::read_native_erc20_address;
 use namada::ledger::gas::{TxGasMeter, VpGasMeter};
 use namada::ledger::governance::GovernanceVp;
@@ -55,6 +56,34 @@ use namada_apps::bench_utils::{
 };
 use namada_apps::wallet::defaults;
 
+/// Encode a proposal content with the required fields, padded with extra
+/// details fields to reach the given size, as the length of each value is
+/// capped
+fn proposal_content(size: usize) -> Vec<u8> {
+    let mut content = BTreeMap::from([
+        ("title".to_string(), "Title".to_string()),
+        ("authors".to_string(), "test@test.com".to_string()),
+        ("details".to_string(), "d".to_string()),
+    ]);
+    let mut padding = size.saturating_sub(content.serialize_to_vec().len());
+    for index in 0.. {
+        let field = format!("details{index}");
+        // A field is encoded with the lengths of its name and value
+        let overhead = 8 + field.len();
+        if padding <= overhead {
+            content.insert("details".to_string(), "d".repeat(1 + padding));
+            break;
+        }
+        let len = std::cmp::min(
+            padding - overhead,
+            namada::ledger::governance::MAX_CONTENT_FIELD_VALUE_LENGTH,
+        );
+        content.insert(field, "d".repeat(len));
+        padding -= overhead + len;
+    }
+    content.serialize_to_vec()
+}
+
 fn governance(c: &mut Criterion) {
     let mut group = c.benchmark_group("vp_governance");
 
@@ -81,6 +110,7 @@ fn governance(c: &mut Criterion) {
                         vote: ProposalVote::Yay,
                         voter: defaults::albert_address(),
                         delegations: vec![defaults::validator_address()],
+                        memo: None,
                     },
                     None,
                     None,
@@ -97,6 +127,7 @@ fn governance(c: &mut Criterion) {
                         vote: ProposalVote::Nay,
                         voter: defaults::validator_address(),
                         delegations: vec![],
+                        memo: None,
                     },
                     None,
                     None,
@@ -105,7 +136,7 @@ fn governance(c: &mut Criterion) {
             }
             "minimal_proposal" => {
                 let content_section =
-                    Section::ExtraData(Code::new(vec![], None));
+                    Section::ExtraData(Code::new(proposal_content(0), None));
                 let params =
                     proof_of_stake::storage::read_pos_params(&shell.state)
                         .unwrap();
@@ -150,7 +181,7 @@ fn governance(c: &mut Criterion) {
                         "Missing max_proposal_content parameter in storage",
                     );
                 let content_section = Section::ExtraData(Code::new(
-                    vec![0; max_proposal_content_size as _],
+                    proposal_content(max_proposal_content_size as _),
                     None,
                 ));
                 let wasm_code_section = Section::ExtraData(Code::new(
@@ -1181,8 +1212,8 @@ fn ibc_vp_validate_action(c: &mut Criterion) {
         let mut actions = IbcActions::new(ctx.clone());
         actions.set_validation_params(ibc.validation_params().unwrap());
 
-        let module = TransferModule::new(ctx);
-        actions.add_transfer_module(module.module_id(), module);
+        let module = PfmModule::new(ctx);
+        actions.add_pfm_module(module);
 
         group.bench_function(bench_name, |b| {
             b.iter(|| actions.validate(&tx_data).unwrap())
@@ -1281,8 +1312,8 @@ fn ibc_vp_execute_action(c: &mut Criterion) {
         let mut actions = IbcActions::new(ctx.clone());
         actions.set_validation_params(ibc.validation_params().unwrap());
 
-        let module = TransferModule::new(ctx);
-        actions.add_transfer_module(module.module_id(), module);
+        let module = PfmModule::new(ctx);
+        actions.add_pfm_module(module);
 
         group.bench_function(bench_name, |b| {
             b.iter(|| actions.execute(&tx_data).unwrap())
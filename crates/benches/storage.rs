@@ -0,0 +1,96 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use namada_apps::node::ledger::storage::benches::{self, WriteLogSpec};
+
+/// The write logs to benchmark: the number of keys, the length of the values
+/// and the percentage of deletions
+const SPECS: [(usize, usize, usize); 4] = [
+    (1_000, 32, 0),
+    (10_000, 32, 0),
+    (10_000, 32, 20),
+    (10_000, 1024, 20),
+];
+
+/// The number of the prefixes that the keys are spread across
+const NUM_PREFIXES: usize = 16;
+
+fn specs() -> impl Iterator<Item = WriteLogSpec> {
+    SPECS
+        .into_iter()
+        .map(|(num_keys, value_len, delete_percent)| WriteLogSpec {
+            num_keys,
+            value_len,
+            delete_percent,
+            num_prefixes: NUM_PREFIXES,
+        })
+}
+
+fn bench_name(spec: &WriteLogSpec) -> String {
+    format!(
+        "keys: {}, value len: {}, deletes: {}%",
+        spec.num_keys, spec.value_len, spec.delete_percent
+    )
+}
+
+// Benchmarks the commit of a block updating the keys written by a previous
+// block
+fn commit_block(c: &mut Criterion) {
+    let mut group = c.benchmark_group("commit_block");
+
+    for spec in specs() {
+        group.bench_function(bench_name(&spec), |b| {
+            b.iter_batched_ref(
+                || {
+                    let tempdir = tempfile::tempdir().unwrap();
+                    let mut state = benches::open_state(tempdir.path());
+                    benches::prepare_write_block(&mut state, &spec, 0).unwrap();
+                    state.commit_block().unwrap();
+                    benches::prepare_update_block(&mut state, &spec, 1)
+                        .unwrap();
+                    (state, tempdir)
+                },
+                |(state, _tempdir)| state.commit_block().unwrap(),
+                criterion::BatchSize::PerIteration,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// Benchmarks the reads of all the keys at the height of the block that wrote
+// them, after they've been updated by the next block
+fn read_with_height(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_with_height");
+
+    for spec in specs() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let mut state = benches::open_state(tempdir.path());
+        let height = benches::populate(&mut state, &spec).unwrap();
+
+        group.bench_function(bench_name(&spec), |b| {
+            b.iter(|| benches::read_with_height(&state, &spec, height).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+// Benchmarks the iteration of all the prefixes of the keys
+fn iter_prefix(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iter_prefix");
+
+    for spec in specs() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let mut state = benches::open_state(tempdir.path());
+        benches::populate(&mut state, &spec).unwrap();
+
+        group.bench_function(bench_name(&spec), |b| {
+            b.iter(|| benches::iter_prefixes(&state, &spec))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(storage, commit_block, read_with_height, iter_prefix);
+criterion_main!(storage);
@@ -548,6 +548,7 @@ fn vote_proposal(c: &mut Criterion) {
             vote: ProposalVote::Yay,
             voter: defaults::albert_address(),
             delegations: vec![defaults::validator_address()],
+            memo: None,
         },
         None,
         None,
@@ -561,6 +562,7 @@ fn vote_proposal(c: &mut Criterion) {
             vote: ProposalVote::Nay,
             voter: defaults::validator_address(),
             delegations: vec![],
+            memo: None,
         },
         None,
         None,
@@ -720,6 +722,7 @@ fn change_validator_metadata(c: &mut Criterion) {
         discord_handle: None,
         avatar: None,
         commission_rate: None,
+        max_total_delegated: None,
     };
 
     let shell = BenchShell::default();
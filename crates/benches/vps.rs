@@ -100,6 +100,7 @@ fn vp_user(c: &mut Criterion) {
             vote: ProposalVote::Yay,
             voter: defaults::albert_address(),
             delegations: vec![defaults::validator_address()],
+            memo: None,
         },
         None,
         None,
@@ -241,6 +242,7 @@ fn vp_implicit(c: &mut Criterion) {
             voter: Address::from(&implicit_account.to_public()),
             delegations: vec![], /* NOTE: no need to bond tokens because the
                                   * implicit vp doesn't check that */
+            memo: None,
         },
         None,
         None,
@@ -397,6 +399,7 @@ fn vp_validator(c: &mut Criterion) {
             vote: ProposalVote::Yay,
             voter: defaults::validator_address(),
             delegations: vec![],
+            memo: None,
         },
         None,
         None,
@@ -59,6 +59,9 @@ pub const POS_SLASH_POOL: Address =
 pub const GOV: Address = Address::Internal(InternalAddress::Governance);
 /// Internal MASP address
 pub const MASP: Address = Address::Internal(InternalAddress::Masp);
+/// Internal IBC relayer fee escrow address
+pub const IBC_FEE_ESCROW: Address =
+    Address::Internal(InternalAddress::IbcFeeEscrow);
 /// Internal Multitoken address
 pub const MULTITOKEN: Address = Address::Internal(InternalAddress::Multitoken);
 /// Internal Eth bridge address
@@ -125,6 +128,9 @@ impl From<raw::Address<'_, raw::Validated>> for Address {
                 InternalAddress::IbcToken(IbcTokenHash(*raw_addr.data())),
             ),
             raw::Discriminant::Masp => Address::Internal(InternalAddress::Masp),
+            raw::Discriminant::IbcFeeEscrow => {
+                Address::Internal(InternalAddress::IbcFeeEscrow)
+            }
         }
     }
 }
@@ -219,6 +225,11 @@ impl<'addr> From<&'addr Address> for raw::Address<'addr, raw::Validated> {
                     .validate()
                     .expect("This raw address is valid")
             }
+            Address::Internal(InternalAddress::IbcFeeEscrow) => {
+                raw::Address::from_discriminant(raw::Discriminant::IbcFeeEscrow)
+                    .validate()
+                    .expect("This raw address is valid")
+            }
         }
     }
 }
@@ -543,6 +554,8 @@ pub enum InternalAddress {
     Pgf,
     /// Masp
     Masp,
+    /// Escrow of the IBC relayer fees
+    IbcFeeEscrow,
 }
 
 impl Display for InternalAddress {
@@ -564,6 +577,7 @@ impl Display for InternalAddress {
                 Self::Multitoken => "Multitoken".to_string(),
                 Self::Pgf => "PublicGoodFundings".to_string(),
                 Self::Masp => "MASP".to_string(),
+                Self::IbcFeeEscrow => "IbcFeeEscrow".to_string(),
             }
         )
     }
@@ -788,6 +802,7 @@ pub mod testing {
             InternalAddress::Nut(_) => {}
             InternalAddress::Pgf => {}
             InternalAddress::Masp => {}
+            InternalAddress::IbcFeeEscrow => {}
             InternalAddress::Multitoken => {} /* Add new addresses in the
                                                * `prop_oneof` below. */
         };
@@ -805,6 +820,7 @@ pub mod testing {
             Just(InternalAddress::Multitoken),
             Just(InternalAddress::Pgf),
             Just(InternalAddress::Masp),
+            Just(InternalAddress::IbcFeeEscrow),
         ]
     }
 
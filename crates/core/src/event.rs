@@ -5,11 +5,15 @@ use std::fmt::{self, Display};
 use std::ops::{Index, IndexMut};
 use std::str::FromStr;
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::address::Address;
 use crate::borsh::{BorshDeserialize, BorshSerialize};
 use crate::ethereum_structs::{BpTransferStatus, EthBridgeEvent};
 use crate::ibc::IbcEvent;
+use crate::storage::Epoch;
+use crate::token;
 
 /// Used in sub-systems that may emit events.
 pub trait EmitEvents {
@@ -25,7 +29,16 @@ impl EmitEvents for Vec<Event> {
 
 /// Indicates if an event is emitted do to
 /// an individual Tx or the nature of a finalized block
-#[derive(Clone, Debug, Eq, PartialEq, BorshSerialize, BorshDeserialize)]
+#[derive(
+    Clone,
+    Debug,
+    Eq,
+    PartialEq,
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+)]
 pub enum EventLevel {
     /// Indicates an event is to do with a finalized block.
     Block,
@@ -35,7 +48,16 @@ pub enum EventLevel {
 
 /// Custom events that can be queried from Tendermint
 /// using a websocket client
-#[derive(Clone, Debug, Eq, PartialEq, BorshSerialize, BorshDeserialize)]
+#[derive(
+    Clone,
+    Debug,
+    Eq,
+    PartialEq,
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+)]
 pub struct Event {
     /// The type of event.
     pub event_type: EventType,
@@ -47,7 +69,16 @@ pub struct Event {
 }
 
 /// The two types of custom events we currently use
-#[derive(Clone, Debug, Eq, PartialEq, BorshSerialize, BorshDeserialize)]
+#[derive(
+    Clone,
+    Debug,
+    Eq,
+    PartialEq,
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+)]
 pub enum EventType {
     /// The transaction was accepted to be included in a block
     Accepted,
@@ -61,6 +92,12 @@ pub enum EventType {
     PgfPayment,
     /// Ethereum Bridge event
     EthereumBridge,
+    /// The PoS bond applied by a tx
+    Bond,
+    /// The PoS unbond applied by a tx
+    Unbond,
+    /// The IBC throughput of a token in an ended epoch
+    IbcThroughput,
 }
 
 impl Display for EventType {
@@ -72,6 +109,9 @@ impl Display for EventType {
             EventType::Proposal => write!(f, "proposal"),
             EventType::PgfPayment => write!(f, "pgf_payment"),
             EventType::EthereumBridge => write!(f, "ethereum_bridge"),
+            EventType::Bond => write!(f, "bond"),
+            EventType::Unbond => write!(f, "unbond"),
+            EventType::IbcThroughput => write!(f, "ibc_throughput"),
         }?;
         Ok(())
     }
@@ -93,6 +133,10 @@ impl FromStr for EventType {
                 Ok(EventType::Ibc("write_acknowledgement".to_string()))
             }
             "ethereum_bridge" => Ok(EventType::EthereumBridge),
+            // PoS
+            "bond" => Ok(EventType::Bond),
+            "unbond" => Ok(EventType::Unbond),
+            "ibc_throughput" => Ok(EventType::IbcThroughput),
             _ => Err(EventError::InvalidEventType),
         }
     }
@@ -113,6 +157,14 @@ pub enum EventError {
     /// Missing value in attributes.
     #[error("Attributes missing value: {0}")]
     MissingValue(String),
+    /// Invalid value in attributes.
+    #[error("Attributes invalid value of the key {key}: {value}")]
+    InvalidValue {
+        /// The key of the invalid value
+        key: String,
+        /// The invalid value
+        value: String,
+    },
 }
 
 impl Event {
@@ -186,6 +238,164 @@ impl From<IbcEvent> for Event {
     }
 }
 
+/// The kind of a [`BondEvent`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BondEventKind {
+    /// Tokens bonded to a validator
+    Bond,
+    /// Tokens unbonded from a validator
+    Unbond,
+}
+
+/// A PoS bond or unbond applied by a tx
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BondEvent {
+    /// Whether the tokens have been bonded or unbonded
+    pub kind: BondEventKind,
+    /// The validator of the bond
+    pub validator: Address,
+    /// The owner of the bond, i.e. the delegator or the validator for a
+    /// self-bond
+    pub source: Address,
+    /// The bonded or unbonded amount of the native token
+    pub amount: token::Amount,
+    /// The epoch from which the change applies to the validator's stake,
+    /// i.e. the current epoch offset by the pipeline length
+    pub epoch: Epoch,
+}
+
+impl From<BondEvent> for Event {
+    fn from(event: BondEvent) -> Self {
+        let event_type = match event.kind {
+            BondEventKind::Bond => EventType::Bond,
+            BondEventKind::Unbond => EventType::Unbond,
+        };
+        let mut attributes = HashMap::new();
+        attributes.insert("validator".into(), event.validator.encode());
+        attributes.insert("source".into(), event.source.encode());
+        attributes.insert("amount".into(), event.amount.to_string_native());
+        attributes.insert("epoch".into(), event.epoch.to_string());
+        Self {
+            event_type,
+            level: EventLevel::Tx,
+            attributes,
+        }
+    }
+}
+
+impl TryFrom<&Event> for BondEvent {
+    type Error = EventError;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        let kind = match event.event_type {
+            EventType::Bond => BondEventKind::Bond,
+            EventType::Unbond => BondEventKind::Unbond,
+            _ => return Err(EventError::InvalidEventType),
+        };
+        let attribute = |key: &str| {
+            event
+                .get(key)
+                .ok_or_else(|| EventError::MissingKey(key.to_string()))
+        };
+        let invalid = |key: &str, value: &String| EventError::InvalidValue {
+            key: key.to_string(),
+            value: value.clone(),
+        };
+
+        let validator = attribute("validator")?;
+        let validator = Address::decode(validator)
+            .map_err(|_| invalid("validator", validator))?;
+        let source = attribute("source")?;
+        let source =
+            Address::decode(source).map_err(|_| invalid("source", source))?;
+        let amount = attribute("amount")?;
+        let amount =
+            token::Amount::from_str(amount, token::NATIVE_MAX_DECIMAL_PLACES)
+                .map_err(|_| invalid("amount", amount))?;
+        let epoch = attribute("epoch")?;
+        let epoch =
+            Epoch::from_str(epoch).map_err(|_| invalid("epoch", epoch))?;
+        Ok(Self {
+            kind,
+            validator,
+            source,
+            amount,
+            epoch,
+        })
+    }
+}
+
+/// The throughput of a token transferred by IBC in an ended epoch, emitted
+/// before the per-epoch throughputs are cleared. The amounts are raw since the
+/// denomination of an IBC token isn't known here.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IbcThroughputEvent {
+    /// The token
+    pub token: Address,
+    /// The ended epoch
+    pub epoch: Epoch,
+    /// The amount deposited in the epoch
+    pub deposit: token::Amount,
+    /// The amount withdrawn in the epoch
+    pub withdraw: token::Amount,
+    /// The amount minted by IBC at the end of the epoch
+    pub minted: token::Amount,
+}
+
+impl From<IbcThroughputEvent> for Event {
+    fn from(event: IbcThroughputEvent) -> Self {
+        let mut attributes = HashMap::new();
+        attributes.insert("token".into(), event.token.encode());
+        attributes.insert("epoch".into(), event.epoch.to_string());
+        attributes.insert("deposit".into(), event.deposit.to_string());
+        attributes.insert("withdraw".into(), event.withdraw.to_string());
+        attributes.insert("minted".into(), event.minted.to_string());
+        Self {
+            event_type: EventType::IbcThroughput,
+            level: EventLevel::Block,
+            attributes,
+        }
+    }
+}
+
+impl TryFrom<&Event> for IbcThroughputEvent {
+    type Error = EventError;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        if event.event_type != EventType::IbcThroughput {
+            return Err(EventError::InvalidEventType);
+        }
+        let attribute = |key: &str| {
+            event
+                .get(key)
+                .ok_or_else(|| EventError::MissingKey(key.to_string()))
+        };
+        let invalid = |key: &str, value: &String| EventError::InvalidValue {
+            key: key.to_string(),
+            value: value.clone(),
+        };
+        let amount = |key: &str| {
+            let value = attribute(key)?;
+            token::Amount::from_string_precise(value)
+                .map_err(|_| invalid(key, value))
+        };
+
+        let token = attribute("token")?;
+        let token =
+            Address::decode(token).map_err(|_| invalid("token", token))?;
+        let epoch = attribute("epoch")?;
+        let epoch =
+            Epoch::from_str(epoch).map_err(|_| invalid("epoch", epoch))?;
+        Ok(Self {
+            token,
+            epoch,
+            deposit: amount("deposit")?,
+            withdraw: amount("withdraw")?,
+            minted: amount("minted")?,
+        })
+    }
+}
+
 /// Convert our custom event into the necessary tendermint proto type
 impl From<Event> for crate::tendermint_proto::v0_37::abci::Event {
     fn from(event: Event) -> Self {
@@ -11,7 +11,7 @@ pub use ibc::*;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use super::address::HASH_LEN;
+use super::address::{Address, HASH_LEN};
 use crate::ibc::apps::transfer::types::msgs::transfer::MsgTransfer;
 use crate::ibc::apps::transfer::types::{Memo, PrefixedDenom, TracePath};
 use crate::ibc::core::handler::types::events::{
@@ -20,7 +20,7 @@ use crate::ibc::core::handler::types::events::{
 use crate::ibc::primitives::proto::Protobuf;
 use crate::masp::PaymentAddress;
 use crate::tendermint::abci::Event as AbciEvent;
-use crate::token::Transfer;
+use crate::token::{Amount, DenominatedAmount, Transfer};
 
 /// The event type defined in ibc-rs for receiving a token
 pub const EVENT_TYPE_PACKET: &str = "fungible_token_packet";
@@ -155,6 +155,84 @@ pub struct IbcShieldedTransfer {
     pub masp_tx: masp_primitives::transaction::Transaction,
 }
 
+/// Relayer fees attached to an outgoing IBC transfer
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    BorshSerialize,
+    BorshDeserialize,
+    BorshSchema,
+    Serialize,
+    Deserialize,
+)]
+pub struct PacketFee {
+    /// The account paying the fees and receiving the refunds
+    pub payer: Address,
+    /// The token in which the fees are paid
+    pub token: Address,
+    /// The fee paid for relaying the packet to the counterparty
+    pub recv_fee: DenominatedAmount,
+    /// The fee paid for relaying the acknowledgement back
+    pub ack_fee: DenominatedAmount,
+    /// The fee paid for relaying the timeout back
+    pub timeout_fee: DenominatedAmount,
+}
+
+/// The token escrowed by an outgoing IBC transfer until the packet is
+/// acknowledged or timed out
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    BorshSerialize,
+    BorshDeserialize,
+    BorshSchema,
+    Serialize,
+    Deserialize,
+)]
+pub struct PacketEscrow {
+    /// The escrowed token
+    pub token: Address,
+    /// The escrowed amount
+    pub amount: Amount,
+}
+
+/// IBC transfer message with relayer fees
+#[derive(Debug, Clone)]
+pub struct MsgTransferWithFee {
+    /// IBC transfer message
+    pub message: MsgTransfer,
+    /// Relayer fees to be escrowed
+    pub fee: PacketFee,
+}
+
+impl BorshSerialize for MsgTransferWithFee {
+    fn serialize<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> std::io::Result<()> {
+        let encoded_msg = self.message.clone().encode_vec();
+        let members = (encoded_msg, self.fee.clone());
+        BorshSerialize::serialize(&members, writer)
+    }
+}
+
+impl BorshDeserialize for MsgTransferWithFee {
+    fn deserialize_reader<R: std::io::Read>(
+        reader: &mut R,
+    ) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let (msg, fee): (Vec<u8>, PacketFee) =
+            BorshDeserialize::deserialize_reader(reader)?;
+        let message = MsgTransfer::decode_vec(&msg)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        Ok(Self { message, fee })
+    }
+}
+
 #[allow(missing_docs)]
 #[derive(Error, Debug)]
 pub enum Error {
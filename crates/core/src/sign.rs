@@ -93,3 +93,36 @@ impl PartialOrd for SignatureIndex {
         Some(self.cmp(other))
     }
 }
+
+/// The set of hashes that a signature section of a tx commits to
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    BorshSerialize,
+    BorshDeserialize,
+    BorshSchema,
+    Serialize,
+    Deserialize,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+)]
+pub enum SignatureLayout {
+    /// The signature targets only the hash of the raw tx header, which in
+    /// turn commits to the code, data and memo sections
+    RawHeader,
+    /// The signature targets the hashes of the tx header and its sections
+    AllSections,
+}
+
+impl std::fmt::Display for SignatureLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RawHeader => write!(f, "raw header"),
+            Self::AllSections => write!(f, "all sections"),
+        }
+    }
+}
@@ -43,6 +43,11 @@ pub enum Error {
     EmptyKey,
     #[error("They key is missing sub-key segments: {0}")]
     MissingSegments(String),
+    #[error(
+        "The reserved prefix {prefix} overlaps with the already reserved \
+         prefix {existing}"
+    )]
+    OverlappingReservedPrefix { prefix: String, existing: String },
 }
 
 /// Result for functions that may fail
@@ -773,6 +778,41 @@ impl KeyRef<'_> {
     }
 }
 
+/// A set of storage key prefixes reserved for the protocol. No two reserved
+/// prefixes may overlap, so that each reserved key belongs to a single owner.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReservedPrefixes {
+    prefixes: Vec<Key>,
+}
+
+impl ReservedPrefixes {
+    /// Reserve the given prefix. Fails if it's empty or if it's a prefix of
+    /// or is prefixed by an already reserved prefix.
+    pub fn register(&mut self, prefix: Key) -> Result<()> {
+        if prefix.is_empty() {
+            return Err(Error::EmptyKey);
+        }
+        if let Some(existing) = self.prefixes.iter().find(|existing| {
+            prefix.split_prefix(existing).is_some()
+                || existing.split_prefix(&prefix).is_some()
+        }) {
+            return Err(Error::OverlappingReservedPrefix {
+                prefix: prefix.to_string(),
+                existing: existing.to_string(),
+            });
+        }
+        self.prefixes.push(prefix);
+        Ok(())
+    }
+
+    /// Check if the key is under any of the reserved prefixes
+    pub fn is_reserved(&self, key: &Key) -> bool {
+        self.prefixes
+            .iter()
+            .any(|prefix| key.split_prefix(prefix).is_some())
+    }
+}
+
 // TODO use std::convert::{TryFrom, Into}?
 /// Represents a segment in a path that may be used as a database key
 pub trait KeySeg {
@@ -1896,6 +1936,34 @@ pub mod tests {
         let order = raw1.cmp(&raw2);
         assert_eq!(order, expected_order);
     }
+
+    #[test]
+    fn test_reserved_prefixes_reject_overlap() {
+        let addr_key =
+            Key::from(address::testing::established_address_1().to_db_key());
+        let prefix = addr_key.clone().with_segment("reserved".to_owned());
+        let sub_key = prefix.clone().with_segment("sub".to_owned());
+        let other = addr_key.clone().with_segment("other".to_owned());
+        let mut reserved = ReservedPrefixes::default();
+        reserved.register(prefix.clone()).expect("cannot reserve");
+
+        assert!(reserved.is_reserved(&prefix));
+        assert!(reserved.is_reserved(&sub_key));
+        assert!(!reserved.is_reserved(&addr_key));
+        assert!(!reserved.is_reserved(&other));
+
+        // the same prefix, a longer one and a shorter one all overlap
+        for overlapping in [prefix, sub_key, addr_key] {
+            let err = reserved
+                .register(overlapping)
+                .expect_err("overlapping prefix must be rejected");
+            assert!(matches!(err, Error::OverlappingReservedPrefix { .. }));
+        }
+        reserved
+            .register(other)
+            .expect("cannot reserve a disjoint prefix");
+        assert!(reserved.register(Key::default()).is_err());
+    }
 }
 
 /// Helpers for testing with storage types.
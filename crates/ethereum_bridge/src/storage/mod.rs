@@ -18,6 +18,20 @@ use namada_trans_token::storage_key::balance_key;
 
 use crate::ADDRESS;
 
+/// The storage prefixes of the Ethereum bridge that only the protocol may
+/// write under:
+///   - The bridge account's own storage (vote tallies of Ethereum events,
+///     bridge pool roots and validator set updates). The escrowed balances are
+///     under the multitoken address, so they aren't affected.
+///   - The signed Merkle root of the bridge pool and the batch nonce.
+pub fn reserved_prefixes() -> [Key; 3] {
+    [
+        prefix(),
+        bridge_pool::get_signed_root_key(),
+        bridge_pool::get_nonce_key(),
+    ]
+}
+
 /// Key prefix for the storage subspace
 pub fn prefix() -> Key {
     Key::from(ADDRESS.to_db_key())
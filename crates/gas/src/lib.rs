@@ -1,6 +1,7 @@
 //! Gas accounting module to track the gas usage in a block for transactions and
 //! validity predicates triggered by transactions.
 
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::ops::Div;
 
@@ -48,13 +49,24 @@ pub const VERIFY_TX_SIG_GAS: u64 = 9_793;
 /// The cost for requesting one more page in wasm (64KiB)
 pub const WASM_MEMORY_PAGE_GAS: u32 =
     MEMORY_ACCESS_GAS_PER_BYTE as u32 * 64 * 1_024;
-/// The cost to validate an Ibc action
+/// The base cost to validate an Ibc action
 pub const IBC_ACTION_VALIDATE_GAS: u64 = 7_511;
-/// The cost to execute an Ibc action
+/// The cost to validate an Ibc action, per byte of the tx data
+pub const IBC_ACTION_VALIDATE_GAS_PER_BYTE: u64 = 4;
+/// The base cost to execute an Ibc action
 pub const IBC_ACTION_EXECUTE_GAS: u64 = 47_452;
+/// The cost to execute an Ibc action, per byte of the tx data
+pub const IBC_ACTION_EXECUTE_GAS_PER_BYTE: u64 = 24;
+/// The cost to compare a changed IBC key with the result of the pseudo
+/// execution of an Ibc action
+pub const IBC_STATE_COMPARISON_GAS_PER_KEY: u64 = 1_024;
 /// The cost to execute a masp tx verification
 pub const MASP_VERIFY_SHIELDED_TX_GAS: u64 = 62_381_957;
 
+/// The label under which the gas consumed by a traced [`VpGasMeter`] outside
+/// of any [`VpGasMeter::scoped`] meter is recorded
+pub const UNSCOPED_GAS_LABEL: &str = "unscoped";
+
 /// Gas module result for functions that may fail
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -185,6 +197,9 @@ pub struct TxGasMeter {
     /// The gas limit for a transaction
     pub tx_gas_limit: Gas,
     transaction_gas: Gas,
+    /// Whether the VP gas meters derived from this meter should record a
+    /// per-label breakdown of their gas consumption
+    gas_trace: bool,
 }
 
 /// Gas metering in a validity predicate
@@ -196,6 +211,17 @@ pub struct VpGasMeter {
     initial_gas: Gas,
     /// The current gas usage in the VP
     current_gas: Gas,
+    /// The gas usage in the VP per label, only recorded if tracing was
+    /// enabled on the originating `TxGasMeter`
+    gas_breakdown: Option<BTreeMap<&'static str, Gas>>,
+}
+
+/// A view over a [`VpGasMeter`] that records the consumed gas under a
+/// specific label, if tracing is enabled
+#[derive(Debug)]
+pub struct ScopedVpGasMeter<'a> {
+    meter: &'a mut VpGasMeter,
+    label: &'static str,
 }
 
 /// Gas meter for VPs parallel runs
@@ -244,6 +270,7 @@ impl TxGasMeter {
         Self {
             tx_gas_limit: tx_gas_limit.into(),
             transaction_gas: Gas::default(),
+            gas_trace: false,
         }
     }
 
@@ -253,9 +280,22 @@ impl TxGasMeter {
         Self {
             tx_gas_limit,
             transaction_gas: Gas::default(),
+            gas_trace: false,
         }
     }
 
+    /// Enable or disable the per-label gas breakdown in the VP gas meters
+    /// derived from this meter. This has no effect on the consumed gas.
+    pub fn with_gas_trace(mut self, enabled: bool) -> Self {
+        self.gas_trace = enabled;
+        self
+    }
+
+    /// Check if the per-label gas breakdown is enabled
+    pub fn gas_trace(&self) -> bool {
+        self.gas_trace
+    }
+
     /// Add the gas required by a wrapper transaction which is comprised of:
     ///  - cost of validating the wrapper tx
     ///  - space that the transaction requires in the block
@@ -290,29 +330,29 @@ impl TxGasMeter {
 
 impl GasMetering for VpGasMeter {
     fn consume(&mut self, gas: u64) -> Result<()> {
-        self.current_gas = self
-            .current_gas
-            .checked_add(gas.into())
-            .ok_or(Error::GasOverflow)?;
+        self.consume_with_label(UNSCOPED_GAS_LABEL, gas)
+    }
 
-        let current_total = self
-            .initial_gas
-            .checked_add(self.current_gas)
-            .ok_or(Error::GasOverflow)?;
+    fn get_tx_consumed_gas(&self) -> Gas {
+        self.initial_gas
+    }
 
-        if current_total > self.tx_gas_limit {
-            return Err(Error::TransactionGasExceededError);
-        }
+    fn get_gas_limit(&self) -> Gas {
+        self.tx_gas_limit
+    }
+}
 
-        Ok(())
+impl GasMetering for ScopedVpGasMeter<'_> {
+    fn consume(&mut self, gas: u64) -> Result<()> {
+        self.meter.consume_with_label(self.label, gas)
     }
 
     fn get_tx_consumed_gas(&self) -> Gas {
-        self.initial_gas
+        self.meter.get_tx_consumed_gas()
     }
 
     fn get_gas_limit(&self) -> Gas {
-        self.tx_gas_limit
+        self.meter.get_gas_limit()
     }
 }
 
@@ -323,8 +363,51 @@ impl VpGasMeter {
             tx_gas_limit: tx_gas_meter.tx_gas_limit,
             initial_gas: tx_gas_meter.transaction_gas,
             current_gas: Gas::default(),
+            gas_breakdown: tx_gas_meter.gas_trace.then(BTreeMap::new),
         }
     }
+
+    /// Get the gas consumed by the VP alone
+    pub fn get_vp_consumed_gas(&self) -> Gas {
+        self.current_gas
+    }
+
+    /// Get a meter that records the consumed gas under the given label
+    pub fn scoped(&mut self, label: &'static str) -> ScopedVpGasMeter<'_> {
+        ScopedVpGasMeter { meter: self, label }
+    }
+
+    /// Get the per-label gas breakdown, if tracing is enabled. The sum of
+    /// the breakdown equals the gas consumed by the VP.
+    pub fn gas_breakdown(&self) -> Option<&BTreeMap<&'static str, Gas>> {
+        self.gas_breakdown.as_ref()
+    }
+
+    fn consume_with_label(
+        &mut self,
+        label: &'static str,
+        gas: u64,
+    ) -> Result<()> {
+        self.current_gas = self
+            .current_gas
+            .checked_add(gas.into())
+            .ok_or(Error::GasOverflow)?;
+        if let Some(breakdown) = self.gas_breakdown.as_mut() {
+            let entry = breakdown.entry(label).or_default();
+            *entry = entry.checked_add(gas.into()).ok_or(Error::GasOverflow)?;
+        }
+
+        let current_total = self
+            .initial_gas
+            .checked_add(self.current_gas)
+            .ok_or(Error::GasOverflow)?;
+
+        if current_total > self.tx_gas_limit {
+            return Err(Error::TransactionGasExceededError);
+        }
+
+        Ok(())
+    }
 }
 
 impl VpsGas {
@@ -397,6 +480,7 @@ mod tests {
         let tx_gas_meter = TxGasMeter {
             tx_gas_limit: BLOCK_GAS_LIMIT.into(),
             transaction_gas: Gas::default(),
+            gas_trace: false,
         };
             let mut meter = VpGasMeter::new_from_tx_meter(&tx_gas_meter);
             meter.consume(gas).expect("cannot add the gas");
@@ -409,6 +493,7 @@ mod tests {
         let tx_gas_meter = TxGasMeter {
             tx_gas_limit: BLOCK_GAS_LIMIT.into(),
             transaction_gas: (TX_GAS_LIMIT - 1).into(),
+            gas_trace: false,
         };
         let mut meter = VpGasMeter::new_from_tx_meter(&tx_gas_meter);
         assert_matches!(
@@ -422,6 +507,7 @@ mod tests {
         let tx_gas_meter = TxGasMeter {
             tx_gas_limit: TX_GAS_LIMIT.into(),
             transaction_gas: (TX_GAS_LIMIT - 1).into(),
+            gas_trace: false,
         };
         let mut meter = VpGasMeter::new_from_tx_meter(&tx_gas_meter);
         assert_matches!(
@@ -432,6 +518,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_vp_gas_breakdown() {
+        let tx_gas_meter = TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into())
+            .with_gas_trace(true);
+        let mut meter = VpGasMeter::new_from_tx_meter(&tx_gas_meter);
+        meter.consume(10).expect("cannot add the gas");
+        meter
+            .scoped("first")
+            .consume(20)
+            .expect("cannot add the gas");
+        meter
+            .scoped("second")
+            .consume(30)
+            .expect("cannot add the gas");
+        meter
+            .scoped("first")
+            .consume(40)
+            .expect("cannot add the gas");
+
+        let breakdown = meter.gas_breakdown().expect("tracing is enabled");
+        assert_eq!(breakdown.get(UNSCOPED_GAS_LABEL), Some(&Gas::from(10)));
+        assert_eq!(breakdown.get("first"), Some(&Gas::from(60)));
+        assert_eq!(breakdown.get("second"), Some(&Gas::from(30)));
+        let sum = breakdown
+            .values()
+            .try_fold(Gas::default(), |acc, gas| acc.checked_add(*gas))
+            .unwrap();
+        assert_eq!(sum, meter.get_vp_consumed_gas());
+
+        // Without tracing, no breakdown is recorded
+        let tx_gas_meter = TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into());
+        let mut meter = VpGasMeter::new_from_tx_meter(&tx_gas_meter);
+        meter
+            .scoped("first")
+            .consume(20)
+            .expect("cannot add the gas");
+        assert!(meter.gas_breakdown().is_none());
+        assert_eq!(meter.get_vp_consumed_gas(), Gas::from(20));
+    }
+
     #[test]
     fn test_tx_gas_overflow() {
         let mut meter = TxGasMeter::new_from_sub_limit(BLOCK_GAS_LIMIT.into());
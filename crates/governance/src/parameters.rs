@@ -1,4 +1,5 @@
 use namada_core::borsh::{BorshDeserialize, BorshSerialize};
+use namada_core::dec::Dec;
 use namada_core::token;
 use namada_storage::{Result, StorageRead, StorageWrite};
 
@@ -29,6 +30,68 @@ pub struct GovernanceParameters {
     pub max_proposal_content_size: u64,
     /// Minimum epochs between end and grace epochs
     pub min_proposal_grace_epochs: u64,
+    /// Maximum number of proposals of an author which haven't been activated
+    pub max_concurrent_proposals_per_author: u64,
+    /// Fields which must be set in the proposal content
+    pub proposal_content_required_fields: Vec<String>,
+    /// Maximum number of bytes of the memo attached to a vote
+    pub max_vote_memo_length: u64,
+    /// Minimum fraction of the total voting power that must vote on a
+    /// default proposal for the tally to count
+    pub threshold_default: Dec,
+    /// Minimum fraction of the total voting power that must vote on a PGF
+    /// steward proposal for the tally to count
+    pub threshold_pgf_steward: Dec,
+    /// Minimum fraction of the total voting power that must vote on a PGF
+    /// payment proposal for the tally to count
+    pub threshold_pgf_payment: Dec,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+/// The governance parameters that a proposal is validated against, as they
+/// were when the proposal was created
+pub struct ProposalParamsSnapshot {
+    /// Minimum amount of locked funds
+    pub min_proposal_fund: token::Amount,
+    /// Minimum proposal voting period in epochs
+    pub min_proposal_voting_period: u64,
+    /// Maximum proposal voting period in epochs
+    pub max_proposal_period: u64,
+    /// Minimum epochs between end and grace epochs
+    pub min_proposal_grace_epochs: u64,
+    /// Minimum fraction of the total voting power that must vote on a
+    /// default proposal for the tally to count
+    pub threshold_default: Dec,
+    /// Minimum fraction of the total voting power that must vote on a PGF
+    /// steward proposal for the tally to count
+    pub threshold_pgf_steward: Dec,
+    /// Minimum fraction of the total voting power that must vote on a PGF
+    /// payment proposal for the tally to count
+    pub threshold_pgf_payment: Dec,
+}
+
+impl From<&GovernanceParameters> for ProposalParamsSnapshot {
+    fn from(params: &GovernanceParameters) -> Self {
+        Self {
+            min_proposal_fund: params.min_proposal_fund,
+            min_proposal_voting_period: params.min_proposal_voting_period,
+            max_proposal_period: params.max_proposal_period,
+            min_proposal_grace_epochs: params.min_proposal_grace_epochs,
+            threshold_default: params.threshold_default,
+            threshold_pgf_steward: params.threshold_pgf_steward,
+            threshold_pgf_payment: params.threshold_pgf_payment,
+        }
+    }
 }
 
 impl Default for GovernanceParameters {
@@ -40,6 +103,15 @@ impl Default for GovernanceParameters {
             max_proposal_period: 27,
             max_proposal_content_size: 10_000,
             min_proposal_grace_epochs: 6,
+            max_concurrent_proposals_per_author: 5,
+            proposal_content_required_fields: ["title", "authors", "details"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            max_vote_memo_length: 500,
+            threshold_default: Dec::two() / 3,
+            threshold_pgf_steward: Dec::one() / 3,
+            threshold_pgf_payment: Dec::one() / 3,
         }
     }
 }
@@ -57,6 +129,12 @@ impl GovernanceParameters {
             max_proposal_period,
             max_proposal_content_size,
             min_proposal_grace_epochs,
+            max_concurrent_proposals_per_author,
+            proposal_content_required_fields,
+            max_vote_memo_length,
+            threshold_default,
+            threshold_pgf_steward,
+            threshold_pgf_payment,
         } = self;
 
         let min_proposal_fund_key =
@@ -88,6 +166,36 @@ impl GovernanceParameters {
         storage
             .write(&min_proposal_grace_epoch_key, min_proposal_grace_epochs)?;
 
+        let max_author_proposals_key =
+            goverance_storage::get_max_author_proposals_key();
+        storage.write(
+            &max_author_proposals_key,
+            max_concurrent_proposals_per_author,
+        )?;
+
+        let content_required_fields_key =
+            goverance_storage::get_content_required_fields_key();
+        storage.write(
+            &content_required_fields_key,
+            proposal_content_required_fields,
+        )?;
+
+        let max_vote_memo_length_key =
+            goverance_storage::get_max_vote_memo_length_key();
+        storage.write(&max_vote_memo_length_key, max_vote_memo_length)?;
+
+        let threshold_default_key =
+            goverance_storage::get_threshold_default_key();
+        storage.write(&threshold_default_key, threshold_default)?;
+
+        let threshold_pgf_steward_key =
+            goverance_storage::get_threshold_pgf_steward_key();
+        storage.write(&threshold_pgf_steward_key, threshold_pgf_steward)?;
+
+        let threshold_pgf_payment_key =
+            goverance_storage::get_threshold_pgf_payment_key();
+        storage.write(&threshold_pgf_payment_key, threshold_pgf_payment)?;
+
         let counter_key = goverance_storage::get_counter_key();
         storage.write(&counter_key, u64::MIN)
     }
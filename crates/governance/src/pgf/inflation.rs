@@ -7,7 +7,9 @@ use namada_storage::{Result, StorageRead, StorageWrite};
 use namada_trans_token::credit_tokens;
 use namada_trans_token::storage_key::minted_balance_key;
 
-use crate::pgf::storage::{get_parameters, get_payments, get_stewards};
+use crate::pgf::storage::{
+    get_parameters, get_payments, get_stewards, read_steward_commission_rate,
+};
 use crate::storage::proposal::{PGFIbcTarget, PGFTarget};
 
 /// Apply the PGF inflation.
@@ -91,8 +93,11 @@ where
         / epochs_per_year;
 
     for steward in stewards {
+        let commission_rate =
+            read_steward_commission_rate(storage, &steward.address)?;
         for (address, percentage) in steward.reward_distribution {
-            let pgf_steward_reward = percentage * pgf_steward_inflation;
+            let pgf_steward_reward =
+                (percentage * commission_rate) * pgf_steward_inflation;
 
             if credit_tokens(
                 storage,
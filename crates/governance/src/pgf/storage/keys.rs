@@ -1,4 +1,5 @@
 use namada_core::address::Address;
+use namada_core::dec::Dec;
 use namada_core::storage::{DbKeySeg, Key, KeySeg};
 use namada_macros::StorageKeys;
 use namada_storage::collections::{lazy_map, LazyCollection, LazyMap};
@@ -11,6 +12,7 @@ use crate::storage::proposal::StoragePgfFunding;
 #[derive(StorageKeys)]
 struct Keys {
     stewards: &'static str,
+    steward_commission_rates: &'static str,
     fundings: &'static str,
     pgf_inflation_rate: &'static str,
     steward_inflation_rate: &'static str,
@@ -50,6 +52,42 @@ pub fn is_stewards_key(key: &Key) -> Option<&Address> {
     }
 }
 
+/// Obtain a storage key prefix for the commission rates of the stewards
+pub fn steward_commission_rates_key_prefix() -> Key {
+    Key {
+        segments: vec![
+            DbKeySeg::AddressSeg(ADDRESS.to_owned()),
+            DbKeySeg::StringSeg(
+                Keys::VALUES.steward_commission_rates.to_string(),
+            ),
+        ],
+    }
+}
+
+/// LazyMap handler for the commission rates of the stewards
+pub fn steward_commission_rates_handle() -> LazyMap<Address, Dec> {
+    LazyMap::open(steward_commission_rates_key_prefix())
+}
+
+/// Check if the given storage key is a steward commission rate key. If it is,
+/// returns the steward address.
+pub fn is_steward_commission_rate_key(key: &Key) -> Option<&Address> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(pgf),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(data),
+            DbKeySeg::AddressSeg(steward),
+        ] if pgf.eq(&ADDRESS)
+            && prefix.as_str() == Keys::VALUES.steward_commission_rates
+            && data.as_str() == lazy_map::DATA_SUBKEY =>
+        {
+            Some(steward)
+        }
+        _ => None,
+    }
+}
+
 /// Obtain a storage key for pgf fundings.
 pub fn fundings_key_prefix() -> Key {
     Key {
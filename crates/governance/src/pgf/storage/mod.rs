@@ -57,6 +57,52 @@ where
     S: StorageRead + StorageWrite,
 {
     pgf_keys::stewards_handle().remove(storage, address)?;
+    pgf_keys::steward_commission_rates_handle().remove(storage, address)?;
+
+    Ok(())
+}
+
+/// Check if a steward commission rate is in the range [0, 1]
+pub fn is_valid_commission_rate(rate: &Dec) -> bool {
+    *rate >= Dec::zero() && *rate <= Dec::one()
+}
+
+/// Query the commission rate of a steward, if any
+pub fn get_steward_commission_rate<S>(
+    storage: &S,
+    address: &Address,
+) -> Result<Option<Dec>>
+where
+    S: StorageRead,
+{
+    pgf_keys::steward_commission_rates_handle().get(storage, address)
+}
+
+/// Read the commission rate of a steward. A steward without a valid
+/// commission rate, e.g. a genesis steward, gets the full rate.
+pub fn read_steward_commission_rate<S>(
+    storage: &S,
+    address: &Address,
+) -> Result<Dec>
+where
+    S: StorageRead,
+{
+    Ok(get_steward_commission_rate(storage, address)?
+        .filter(is_valid_commission_rate)
+        .unwrap_or_else(Dec::one))
+}
+
+/// Write the commission rate of a steward
+pub fn write_steward_commission_rate<S>(
+    storage: &mut S,
+    address: Address,
+    rate: Dec,
+) -> Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    pgf_keys::steward_commission_rates_handle()
+        .insert(storage, address, rate)?;
 
     Ok(())
 }
@@ -1,5 +1,5 @@
 use namada_core::address::Address;
-use namada_core::storage::{DbKeySeg, Key, KeySeg};
+use namada_core::storage::{DbKeySeg, Epoch, Key, KeySeg};
 use namada_macros::StorageKeys;
 
 use crate::ADDRESS;
@@ -9,6 +9,7 @@ use crate::ADDRESS;
 struct Keys {
     proposal: &'static str,
     vote: &'static str,
+    vote_memo: &'static str,
     author: &'static str,
     proposal_type: &'static str,
     content: &'static str,
@@ -24,9 +25,20 @@ struct Keys {
     max_period: &'static str,
     max_content: &'static str,
     min_grace_epoch: &'static str,
+    max_author_proposals: &'static str,
+    content_required_fields: &'static str,
+    max_vote_memo: &'static str,
+    threshold_default: &'static str,
+    threshold_pgf_steward: &'static str,
+    threshold_pgf_payment: &'static str,
     counter: &'static str,
     pending: &'static str,
     result: &'static str,
+    result_record: &'static str,
+    withdrawn: &'static str,
+    author_proposals: &'static str,
+    voting_power_snapshot: &'static str,
+    params_snapshot: &'static str,
 }
 
 /// Check if key is inside governance address space
@@ -54,6 +66,26 @@ pub fn is_vote_key(key: &Key) -> bool {
     }
 }
 
+/// Check if a key is a vote memo key
+pub fn is_vote_memo_key(key: &Key) -> bool {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(id),
+            DbKeySeg::StringSeg(vote_memo),
+            DbKeySeg::AddressSeg(_validator_address),
+            DbKeySeg::AddressSeg(_address),
+        ] if addr == &ADDRESS
+            && prefix == Keys::VALUES.proposal
+            && vote_memo == Keys::VALUES.vote_memo =>
+        {
+            id.parse::<u64>().is_ok()
+        }
+        _ => false,
+    }
+}
+
 /// Check if key is author key
 pub fn is_author_key(key: &Key) -> bool {
     match &key.segments[..] {
@@ -198,6 +230,62 @@ pub fn is_proposal_type_key(key: &Key) -> bool {
     }
 }
 
+/// Check if key is withdrawn key
+pub fn is_withdrawn_key(key: &Key) -> bool {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(id),
+            DbKeySeg::StringSeg(withdrawn),
+        ] if addr == &ADDRESS
+            && prefix == Keys::VALUES.proposal
+            && withdrawn == Keys::VALUES.withdrawn =>
+        {
+            id.parse::<u64>().is_ok()
+        }
+        _ => false,
+    }
+}
+
+/// Check if key is the key of the snapshot of the parameters of a proposal
+pub fn is_proposal_params_snapshot_key(key: &Key) -> bool {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(id),
+            DbKeySeg::StringSeg(params_snapshot),
+        ] if addr == &ADDRESS
+            && prefix == Keys::VALUES.proposal
+            && params_snapshot == Keys::VALUES.params_snapshot =>
+        {
+            id.parse::<u64>().is_ok()
+        }
+        _ => false,
+    }
+}
+
+/// Check if key is a proposal result key, either of the tally or of its
+/// record
+pub fn is_proposal_result_key(key: &Key) -> bool {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(id),
+            DbKeySeg::StringSeg(result),
+        ] if addr == &ADDRESS
+            && prefix == Keys::VALUES.proposal
+            && (result == Keys::VALUES.result
+                || result == Keys::VALUES.result_record) =>
+        {
+            id.parse::<u64>().is_ok()
+        }
+        _ => false,
+    }
+}
+
 /// Check if key is counter key
 pub fn is_counter_key(key: &Key) -> bool {
     matches!(&key.segments[..], [DbKeySeg::AddressSeg(addr), DbKeySeg::StringSeg(counter)] if addr == &ADDRESS && counter == Keys::VALUES.counter)
@@ -270,6 +358,93 @@ pub fn is_min_grace_epoch_key(key: &Key) -> bool {
                     && min_grace_epoch_param == Keys::VALUES.min_grace_epoch)
 }
 
+/// Check if key is a max concurrent proposals per author param key
+pub fn is_max_author_proposals_key(key: &Key) -> bool {
+    matches!(&key.segments[..], [
+             DbKeySeg::AddressSeg(addr),
+             DbKeySeg::StringSeg(max_author_proposals_param),
+         ] if addr == &ADDRESS
+             && max_author_proposals_param == Keys::VALUES.max_author_proposals)
+}
+
+/// Check if key is a proposal content required fields param key
+pub fn is_content_required_fields_key(key: &Key) -> bool {
+    matches!(&key.segments[..], [
+             DbKeySeg::AddressSeg(addr),
+             DbKeySeg::StringSeg(content_required_fields_param),
+         ] if addr == &ADDRESS
+             && content_required_fields_param
+                 == Keys::VALUES.content_required_fields)
+}
+
+/// Check if key is a max vote memo length param key
+pub fn is_max_vote_memo_length_key(key: &Key) -> bool {
+    matches!(&key.segments[..], [
+             DbKeySeg::AddressSeg(addr),
+             DbKeySeg::StringSeg(max_vote_memo_param),
+         ] if addr == &ADDRESS
+             && max_vote_memo_param == Keys::VALUES.max_vote_memo)
+}
+
+/// Check if key is a default proposal threshold param key
+pub fn is_threshold_default_key(key: &Key) -> bool {
+    matches!(&key.segments[..], [
+             DbKeySeg::AddressSeg(addr),
+             DbKeySeg::StringSeg(threshold_param),
+         ] if addr == &ADDRESS
+             && threshold_param == Keys::VALUES.threshold_default)
+}
+
+/// Check if key is a PGF steward proposal threshold param key
+pub fn is_threshold_pgf_steward_key(key: &Key) -> bool {
+    matches!(&key.segments[..], [
+             DbKeySeg::AddressSeg(addr),
+             DbKeySeg::StringSeg(threshold_param),
+         ] if addr == &ADDRESS
+             && threshold_param == Keys::VALUES.threshold_pgf_steward)
+}
+
+/// Check if key is a PGF payment proposal threshold param key
+pub fn is_threshold_pgf_payment_key(key: &Key) -> bool {
+    matches!(&key.segments[..], [
+             DbKeySeg::AddressSeg(addr),
+             DbKeySeg::StringSeg(threshold_param),
+         ] if addr == &ADDRESS
+             && threshold_param == Keys::VALUES.threshold_pgf_payment)
+}
+
+/// Check if key is an author proposal index key
+pub fn is_author_proposal_key(key: &Key) -> bool {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::AddressSeg(_author),
+            DbKeySeg::StringSeg(id),
+        ] if addr == &ADDRESS && prefix == Keys::VALUES.author_proposals => {
+            id.parse::<u64>().is_ok()
+        }
+        _ => false,
+    }
+}
+
+/// Check if key is a voting power snapshot key. Returns the epoch of the
+/// snapshot.
+pub fn is_voting_power_snapshot_key(key: &Key) -> Option<Epoch> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(epoch),
+        ] if addr == &ADDRESS
+            && prefix == Keys::VALUES.voting_power_snapshot =>
+        {
+            Epoch::parse(epoch.clone()).ok()
+        }
+        _ => None,
+    }
+}
+
 /// Check if key is parameter key
 pub fn is_parameter_key(key: &Key) -> bool {
     is_min_proposal_fund_key(key)
@@ -278,6 +453,12 @@ pub fn is_parameter_key(key: &Key) -> bool {
         || is_min_proposal_voting_period_key(key)
         || is_max_proposal_period_key(key)
         || is_min_grace_epoch_key(key)
+        || is_max_author_proposals_key(key)
+        || is_content_required_fields_key(key)
+        || is_max_vote_memo_length_key(key)
+        || is_threshold_default_key(key)
+        || is_threshold_pgf_steward_key(key)
+        || is_threshold_pgf_payment_key(key)
 }
 
 /// Check if key is start epoch or end epoch key
@@ -334,6 +515,48 @@ pub fn get_min_proposal_grace_epoch_key() -> Key {
         .expect("Cannot obtain a storage key")
 }
 
+/// Get max concurrent proposals per author key
+pub fn get_max_author_proposals_key() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&Keys::VALUES.max_author_proposals.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the proposal content required fields key
+pub fn get_content_required_fields_key() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&Keys::VALUES.content_required_fields.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the max vote memo length key
+pub fn get_max_vote_memo_length_key() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&Keys::VALUES.max_vote_memo.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the default proposal threshold key
+pub fn get_threshold_default_key() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&Keys::VALUES.threshold_default.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the PGF steward proposal threshold key
+pub fn get_threshold_pgf_steward_key() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&Keys::VALUES.threshold_pgf_steward.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the PGF payment proposal threshold key
+pub fn get_threshold_pgf_payment_key() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&Keys::VALUES.threshold_pgf_payment.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
 /// Get key of proposal ids counter
 pub fn get_counter_key() -> Key {
     Key::from(ADDRESS.to_db_key())
@@ -451,6 +674,29 @@ pub fn get_vote_proposal_key(
         .expect("Cannot obtain a storage key")
 }
 
+/// Get proposal vote memo prefix key
+pub fn get_proposal_vote_memo_prefix_key(id: u64) -> Key {
+    proposal_prefix()
+        .push(&id.to_string())
+        .expect("Cannot obtain a storage key")
+        .push(&Keys::VALUES.vote_memo.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the key of the memo attached to the vote of a voter with a
+/// delegation for a specific proposal id
+pub fn get_vote_memo_key(
+    id: u64,
+    voter_address: Address,
+    delegation_address: Address,
+) -> Key {
+    get_proposal_vote_memo_prefix_key(id)
+        .push(&delegation_address)
+        .expect("Cannot obtain a storage key")
+        .push(&voter_address)
+        .expect("Cannot obtain a storage key")
+}
+
 /// Get the proposal execution key
 pub fn get_proposal_execution_key(id: u64) -> Key {
     Key::from(ADDRESS.to_db_key())
@@ -469,6 +715,65 @@ pub fn get_proposal_result_key(id: u64) -> Key {
         .expect("Cannot obtain a storage key")
 }
 
+/// Get the proposal result record key
+pub fn get_proposal_result_record_key(id: u64) -> Key {
+    proposal_prefix()
+        .push(&id.to_string())
+        .expect("Cannot obtain a storage key")
+        .push(&Keys::VALUES.result_record.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the proposal withdrawn key
+pub fn get_withdrawn_key(id: u64) -> Key {
+    proposal_prefix()
+        .push(&id.to_string())
+        .expect("Cannot obtain a storage key")
+        .push(&Keys::VALUES.withdrawn.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the key of the snapshot of the parameters of a proposal taken at its
+/// creation
+pub fn get_proposal_params_snapshot_key(id: u64) -> Key {
+    proposal_prefix()
+        .push(&id.to_string())
+        .expect("Cannot obtain a storage key")
+        .push(&Keys::VALUES.params_snapshot.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the prefix of the proposals index of an author
+pub fn get_author_proposals_prefix(author: &Address) -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&Keys::VALUES.author_proposals.to_owned())
+        .expect("Cannot obtain a storage key")
+        .push(author)
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the author proposal index key
+pub fn get_author_proposal_key(author: &Address, id: u64) -> Key {
+    get_author_proposals_prefix(author)
+        .push(&id.to_string())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the prefix of the voting power snapshots
+pub fn get_voting_power_snapshot_prefix() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&Keys::VALUES.voting_power_snapshot.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Get the key of the voting power snapshot taken at the beginning of an
+/// epoch
+pub fn get_voting_power_snapshot_key(epoch: Epoch) -> Key {
+    get_voting_power_snapshot_prefix()
+        .push(&epoch)
+        .expect("Cannot obtain a storage key")
+}
+
 /// Get proposal id from key
 pub fn get_proposal_id(key: &Key) -> Option<u64> {
     match key.get_at(2) {
@@ -522,3 +827,18 @@ pub fn get_voter_address(key: &Key) -> Option<&Address> {
         None => None,
     }
 }
+
+/// Get the author and the proposal id from an author proposal index key
+pub fn get_author_proposal(key: &Key) -> Option<(&Address, u64)> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::AddressSeg(author),
+            DbKeySeg::StringSeg(id),
+        ] if addr == &ADDRESS && prefix == Keys::VALUES.author_proposals => {
+            id.parse::<u64>().ok().map(|id| (author, id))
+        }
+        _ => None,
+    }
+}
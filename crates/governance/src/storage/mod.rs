@@ -11,17 +11,22 @@ use std::collections::BTreeMap;
 
 use namada_core::address::Address;
 use namada_core::borsh::BorshDeserialize;
+use namada_core::dec::Dec;
 use namada_core::storage::Epoch;
-use namada_storage::{iter_prefix, Error, Result, StorageRead, StorageWrite};
+use namada_storage::{
+    iter_prefix, iter_prefix_bytes, Error, Result, StorageRead, StorageWrite,
+};
 use namada_trans_token as token;
 
-use crate::parameters::GovernanceParameters;
+use crate::parameters::{GovernanceParameters, ProposalParamsSnapshot};
+use crate::pgf::storage as pgf_storage;
 use crate::storage::keys as governance_keys;
 use crate::storage::proposal::{
-    InitProposalData, ProposalType, StorageProposal, VoteProposalData,
+    AddRemove, InitProposalData, ProposalType, StorageProposal,
+    VoteProposalData, VotingPeriodExtension,
 };
 use crate::storage::vote::ProposalVote;
-use crate::utils::{ProposalResult, Vote};
+use crate::utils::{ProposalResult, ProposalResultRecord, Vote};
 use crate::ADDRESS as governance_address;
 
 /// A proposal creation transaction.
@@ -34,6 +39,25 @@ pub fn init_proposal<S>(
 where
     S: StorageRead + StorageWrite,
 {
+    // a steward added by the proposal must have set its commission rate
+    if let ProposalType::PGFSteward(stewards) = &data.r#type {
+        for steward in stewards {
+            if let AddRemove::Add(address) = steward {
+                let rate =
+                    pgf_storage::get_steward_commission_rate(storage, address)?;
+                if !rate
+                    .as_ref()
+                    .is_some_and(pgf_storage::is_valid_commission_rate)
+                {
+                    return Err(Error::new(format!(
+                        "The steward {address} added by the proposal has no \
+                         valid commission rate"
+                    )));
+                }
+            }
+        }
+    }
+
     let counter_key = governance_keys::get_counter_key();
     let proposal_id = storage.read(&counter_key)?.expect(
         "Storage should have been initialized with an initial governance \
@@ -72,6 +96,11 @@ where
     let grace_epoch_key = governance_keys::get_grace_epoch_key(proposal_id);
     storage.write(&grace_epoch_key, data.grace_epoch)?;
 
+    // index the proposal by its author until it's activated
+    let author_proposal_key =
+        governance_keys::get_author_proposal_key(&data.author, proposal_id);
+    storage.write(&author_proposal_key, data.grace_epoch)?;
+
     if let ProposalType::Default(Some(_)) = data.r#type {
         let proposal_code_key =
             governance_keys::get_proposal_code_key(proposal_id);
@@ -82,9 +111,13 @@ where
 
     storage.write(&counter_key, proposal_id + 1)?;
 
-    let min_proposal_funds_key = governance_keys::get_min_proposal_fund_key();
-    let min_proposal_funds: token::Amount =
-        storage.read(&min_proposal_funds_key)?.unwrap();
+    // the proposal is validated against the parameters at its creation
+    let params = read_proposal_params(storage)?;
+    let params_snapshot_key =
+        governance_keys::get_proposal_params_snapshot_key(proposal_id);
+    storage.write(&params_snapshot_key, &params)?;
+
+    let min_proposal_funds = params.min_proposal_fund;
 
     let funds_key = governance_keys::get_funds_key(proposal_id);
     storage.write(&funds_key, min_proposal_funds)?;
@@ -115,9 +148,100 @@ where
         let vote_key = governance_keys::get_vote_proposal_key(
             data.id,
             data.voter.clone(),
-            delegation,
+            delegation.clone(),
         );
         storage.write(&vote_key, data.vote.clone())?;
+
+        // A vote without a memo clears the memo of a previous vote
+        let memo_key = governance_keys::get_vote_memo_key(
+            data.id,
+            data.voter.clone(),
+            delegation,
+        );
+        match &data.memo {
+            Some(memo) => storage.write(&memo_key, memo)?,
+            None => storage.delete(&memo_key)?,
+        }
+    }
+    Ok(())
+}
+
+/// A proposal withdrawal transaction. The proposal is flagged as withdrawn
+/// and its funds are refunded to the author.
+pub fn withdraw_proposal<S>(storage: &mut S, proposal_id: u64) -> Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let author = get_proposal_author(storage, proposal_id)?
+        .ok_or(Error::new_const("Missing proposal author"))?;
+    let funds_key = governance_keys::get_funds_key(proposal_id);
+    let funds: token::Amount = storage
+        .read(&funds_key)?
+        .ok_or(Error::new_const("Missing proposal funds"))?;
+
+    let withdrawn_key = governance_keys::get_withdrawn_key(proposal_id);
+    storage.write(&withdrawn_key, ())?;
+
+    token::transfer(
+        storage,
+        &storage.get_native_token()?,
+        &governance_address,
+        &author,
+        funds,
+    )
+}
+
+/// Check if a proposal has been withdrawn by its author
+pub fn is_proposal_withdrawn<S>(storage: &S, proposal_id: u64) -> Result<bool>
+where
+    S: StorageRead,
+{
+    let withdrawn_key = governance_keys::get_withdrawn_key(proposal_id);
+    storage.has_key(&withdrawn_key)
+}
+
+/// Count the proposals of an author which haven't been activated, i.e. whose
+/// grace epoch is after the given epoch, and haven't been withdrawn.
+pub fn count_active_author_proposals<S>(
+    storage: &S,
+    author: &Address,
+    current_epoch: Epoch,
+) -> Result<u64>
+where
+    S: StorageRead,
+{
+    let prefix = governance_keys::get_author_proposals_prefix(author);
+    let mut count = 0;
+    for entry in iter_prefix::<Epoch>(storage, &prefix)? {
+        let (key, activation_epoch) = entry?;
+        if activation_epoch <= current_epoch {
+            continue;
+        }
+        match governance_keys::get_author_proposal(&key) {
+            Some((_, proposal_id))
+                if !is_proposal_withdrawn(storage, proposal_id)? =>
+            {
+                count += 1;
+            }
+            _ => {}
+        }
+    }
+    Ok(count)
+}
+
+/// Delete the entry of a finalized proposal from the proposals index of its
+/// author.
+pub fn delete_author_proposal<S>(
+    storage: &mut S,
+    proposal_id: u64,
+) -> Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    if let Some(author) = get_proposal_author(storage, proposal_id)? {
+        let author_proposal_key =
+            governance_keys::get_author_proposal_key(&author, proposal_id);
+        storage.delete(&author_proposal_key)?;
     }
     Ok(())
 }
@@ -136,6 +260,19 @@ where
     storage.write(&proposal_result_key, proposal_result)
 }
 
+/// Write the record of the proposal result to storage.
+pub fn write_proposal_result_record<S>(
+    storage: &mut S,
+    proposal_id: u64,
+    record: ProposalResultRecord,
+) -> Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let key = governance_keys::get_proposal_result_record_key(proposal_id);
+    storage.write(&key, record)
+}
+
 /// Read a proposal by id from storage
 pub fn get_proposal_by_id<S>(
     storage: &S,
@@ -207,6 +344,24 @@ where
     Ok(votes)
 }
 
+/// Get the memo attached to the vote of a voter with a delegation
+pub fn get_vote_memo<S>(
+    storage: &S,
+    proposal_id: u64,
+    voter: &Address,
+    delegation: &Address,
+) -> Result<Option<String>>
+where
+    S: StorageRead,
+{
+    let memo_key = governance_keys::get_vote_memo_key(
+        proposal_id,
+        voter.clone(),
+        delegation.clone(),
+    );
+    storage.read(&memo_key)
+}
+
 /// Check if an accepted proposal is being executed
 pub fn is_proposal_accepted<S>(storage: &S, tx_data: &[u8]) -> Result<bool>
 where
@@ -246,6 +401,58 @@ where
     storage.read::<Address>(&proposal_author_key)
 }
 
+/// Postpone the voting end epoch of the proposal extended by an accepted
+/// voting period extension proposal. The extension is skipped if the extended
+/// proposal has been withdrawn or its voting period has ended, or if its
+/// postponed end epoch isn't within the max proposal period and the min grace
+/// epochs of its parameters anymore, e.g. after another extension. Returns
+/// `true` if the extension has been applied.
+pub fn apply_voting_period_extension<S>(
+    storage: &mut S,
+    extension: &VotingPeriodExtension,
+    current_epoch: Epoch,
+) -> Result<bool>
+where
+    S: StorageRead + StorageWrite,
+{
+    let extended_id = extension.proposal_id;
+    if is_proposal_withdrawn(storage, extended_id)? {
+        return Ok(false);
+    }
+    let start_epoch_key =
+        governance_keys::get_voting_start_epoch_key(extended_id);
+    let end_epoch_key = governance_keys::get_voting_end_epoch_key(extended_id);
+    let grace_epoch_key = governance_keys::get_grace_epoch_key(extended_id);
+    let (Some(start_epoch), Some(end_epoch), Some(grace_epoch)) = (
+        storage.read::<Epoch>(&start_epoch_key)?,
+        storage.read::<Epoch>(&end_epoch_key)?,
+        storage.read::<Epoch>(&grace_epoch_key)?,
+    ) else {
+        return Ok(false);
+    };
+    if current_epoch > end_epoch {
+        return Ok(false);
+    }
+    let Some(extended_end_epoch) = end_epoch.0.checked_add(extension.epochs)
+    else {
+        return Ok(false);
+    };
+    let extended_end_epoch = Epoch(extended_end_epoch);
+
+    let params = get_proposal_params_snapshot(storage, extended_id)?;
+    let is_valid_period =
+        (extended_end_epoch - start_epoch).0 <= params.max_proposal_period;
+    let is_valid_grace_epoch = extended_end_epoch < grace_epoch
+        && (grace_epoch - extended_end_epoch).0
+            >= params.min_proposal_grace_epochs;
+    if !is_valid_period || !is_valid_grace_epoch {
+        return Ok(false);
+    }
+
+    storage.write(&end_epoch_key, extended_end_epoch)?;
+    Ok(true)
+}
+
 /// Get governance parameters
 pub fn get_parameters<S>(storage: &S) -> Result<GovernanceParameters>
 where
@@ -271,6 +478,36 @@ where
     let min_proposal_voting_period: u64 =
         storage.read(&key)?.expect("Parameter should be defined.");
 
+    let key = governance_keys::get_max_author_proposals_key();
+    let max_concurrent_proposals_per_author: u64 =
+        storage.read(&key)?.expect("Parameter should be defined.");
+
+    let key = governance_keys::get_content_required_fields_key();
+    let proposal_content_required_fields: Vec<String> =
+        storage.read(&key)?.expect("Parameter should be defined.");
+
+    let key = governance_keys::get_max_vote_memo_length_key();
+    let max_vote_memo_length: u64 =
+        storage.read(&key)?.expect("Parameter should be defined.");
+
+    // The thresholds aren't written on the chains initialized before they
+    // were introduced
+    let defaults = GovernanceParameters::default();
+
+    let key = governance_keys::get_threshold_default_key();
+    let threshold_default: Dec =
+        storage.read(&key)?.unwrap_or(defaults.threshold_default);
+
+    let key = governance_keys::get_threshold_pgf_steward_key();
+    let threshold_pgf_steward: Dec = storage
+        .read(&key)?
+        .unwrap_or(defaults.threshold_pgf_steward);
+
+    let key = governance_keys::get_threshold_pgf_payment_key();
+    let threshold_pgf_payment: Dec = storage
+        .read(&key)?
+        .unwrap_or(defaults.threshold_pgf_payment);
+
     let max_proposal_period: u64 = get_max_proposal_period(storage)?;
 
     Ok(GovernanceParameters {
@@ -280,9 +517,79 @@ where
         max_proposal_period,
         max_proposal_content_size,
         min_proposal_grace_epochs,
+        max_concurrent_proposals_per_author,
+        proposal_content_required_fields,
+        max_vote_memo_length,
+        threshold_default,
+        threshold_pgf_steward,
+        threshold_pgf_payment,
+    })
+}
+
+/// Read the current values of the governance parameters that a proposal is
+/// validated against
+pub fn read_proposal_params<S>(storage: &S) -> Result<ProposalParamsSnapshot>
+where
+    S: StorageRead,
+{
+    let key = governance_keys::get_min_proposal_fund_key();
+    let min_proposal_fund: token::Amount =
+        storage.read(&key)?.expect("Parameter should be defined.");
+
+    let key = governance_keys::get_min_proposal_voting_period_key();
+    let min_proposal_voting_period: u64 =
+        storage.read(&key)?.expect("Parameter should be defined.");
+
+    let key = governance_keys::get_min_proposal_grace_epoch_key();
+    let min_proposal_grace_epochs: u64 =
+        storage.read(&key)?.expect("Parameter should be defined.");
+
+    let defaults = GovernanceParameters::default();
+
+    let key = governance_keys::get_threshold_default_key();
+    let threshold_default: Dec =
+        storage.read(&key)?.unwrap_or(defaults.threshold_default);
+
+    let key = governance_keys::get_threshold_pgf_steward_key();
+    let threshold_pgf_steward: Dec = storage
+        .read(&key)?
+        .unwrap_or(defaults.threshold_pgf_steward);
+
+    let key = governance_keys::get_threshold_pgf_payment_key();
+    let threshold_pgf_payment: Dec = storage
+        .read(&key)?
+        .unwrap_or(defaults.threshold_pgf_payment);
+
+    let max_proposal_period: u64 = get_max_proposal_period(storage)?;
+
+    Ok(ProposalParamsSnapshot {
+        min_proposal_fund,
+        min_proposal_voting_period,
+        max_proposal_period,
+        min_proposal_grace_epochs,
+        threshold_default,
+        threshold_pgf_steward,
+        threshold_pgf_payment,
     })
 }
 
+/// Get the governance parameters that a proposal is validated against, from
+/// the snapshot taken at its creation. The proposals created before the
+/// snapshots were introduced fall back on the current parameters.
+pub fn get_proposal_params_snapshot<S>(
+    storage: &S,
+    proposal_id: u64,
+) -> Result<ProposalParamsSnapshot>
+where
+    S: StorageRead,
+{
+    let key = governance_keys::get_proposal_params_snapshot_key(proposal_id);
+    match storage.read(&key)? {
+        Some(params) => Ok(params),
+        None => read_proposal_params(storage),
+    }
+}
+
 /// Get governance "max_proposal_period" parameter
 pub fn get_max_proposal_period<S>(storage: &S) -> Result<u64>
 where
@@ -306,3 +613,76 @@ where
     let proposal_result: Option<ProposalResult> = storage.read(&key)?;
     Ok(proposal_result)
 }
+
+/// Get the record of a proposal result stored in storage at the activation
+/// epoch of the proposal
+pub fn get_proposal_result_record<S>(
+    storage: &S,
+    proposal_id: u64,
+) -> Result<Option<ProposalResultRecord>>
+where
+    S: StorageRead,
+{
+    let key = governance_keys::get_proposal_result_record_key(proposal_id);
+    storage.read(&key)
+}
+
+/// The stakes of the consensus validators at the beginning of an epoch
+pub type VotingPowerSnapshot = BTreeMap<Address, token::Amount>;
+
+/// Write the snapshot of the stakes of the consensus validators taken at the
+/// beginning of the epoch
+pub fn write_voting_power_snapshot<S>(
+    storage: &mut S,
+    epoch: Epoch,
+    snapshot: &VotingPowerSnapshot,
+) -> Result<()>
+where
+    S: StorageWrite,
+{
+    let key = governance_keys::get_voting_power_snapshot_key(epoch);
+    storage.write(&key, snapshot)
+}
+
+/// Read the snapshot of the stakes of the consensus validators taken at the
+/// beginning of the epoch. Returns `None` if it hasn't been taken or it has
+/// been pruned.
+pub fn read_voting_power_snapshot<S>(
+    storage: &S,
+    epoch: Epoch,
+) -> Result<Option<VotingPowerSnapshot>>
+where
+    S: StorageRead,
+{
+    let key = governance_keys::get_voting_power_snapshot_key(epoch);
+    storage.read(&key)
+}
+
+/// Delete the voting power snapshots which can't be used by a proposal
+/// anymore, i.e. taken more than `max_proposal_period` epochs before the
+/// current epoch
+pub fn prune_voting_power_snapshots<S>(
+    storage: &mut S,
+    current_epoch: Epoch,
+    max_proposal_period: u64,
+) -> Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let oldest_epoch = current_epoch
+        .checked_sub(max_proposal_period)
+        .unwrap_or_default();
+    let prefix = governance_keys::get_voting_power_snapshot_prefix();
+    let mut pruned_keys = vec![];
+    for result in iter_prefix_bytes(storage, &prefix)? {
+        let (key, _) = result?;
+        match governance_keys::is_voting_power_snapshot_key(&key) {
+            Some(epoch) if epoch < oldest_epoch => pruned_keys.push(key),
+            _ => {}
+        }
+    }
+    for key in pruned_keys {
+        storage.delete(&key)?;
+    }
+    Ok(())
+}
@@ -81,6 +81,8 @@ pub struct VoteProposalData {
     pub voter: Address,
     /// Validators to who the voter has delegations to
     pub delegations: Vec<Address>,
+    /// The optional memo justifying the vote
+    pub memo: Option<String>,
 }
 
 impl TryFrom<DefaultProposal> for InitProposalData {
@@ -201,6 +203,27 @@ pub enum ProposalType {
     PGFSteward(BTreeSet<AddRemove<Address>>),
     /// PGF funding proposal
     PGFPayment(BTreeSet<PGFAction>),
+    /// Extension of the voting period of an ongoing proposal
+    ExtendVotingPeriod(VotingPeriodExtension),
+}
+
+/// The extension of the voting period of an ongoing proposal. The voting end
+/// epoch of the extended proposal is postponed when the extension proposal is
+/// accepted.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+)]
+pub struct VotingPeriodExtension {
+    /// The ID of the extended proposal
+    pub proposal_id: u64,
+    /// The number of epochs by which the voting end epoch is postponed
+    pub epochs: u64,
 }
 
 /// An add or remove action for PGF
@@ -430,6 +453,10 @@ impl ProposalType {
                     .map(|action| format!("\n  {}", &action))
                     .join("")
             ),
+            ProposalType::ExtendVotingPeriod(extension) => format!(
+                "Proposal ID: {}, Epochs: {}",
+                extension.proposal_id, extension.epochs
+            ),
         }
     }
 }
@@ -440,6 +467,9 @@ impl Display for ProposalType {
             ProposalType::Default(_) => write!(f, "Default"),
             ProposalType::PGFSteward(_) => write!(f, "PGF steward"),
             ProposalType::PGFPayment(_) => write!(f, "PGF funding"),
+            ProposalType::ExtendVotingPeriod(_) => {
+                write!(f, "Voting period extension")
+            }
         }
     }
 }
@@ -714,6 +744,12 @@ pub mod testing {
             .prop_map(ProposalType::PGFSteward),
             collection::btree_set(arb_pgf_action(), 0..10)
                 .prop_map(ProposalType::PGFPayment),
+            (any::<u64>(), any::<u64>()).prop_map(|(proposal_id, epochs)| {
+                ProposalType::ExtendVotingPeriod(VotingPeriodExtension {
+                    proposal_id,
+                    epochs,
+                })
+            }),
         ]
     }
 
@@ -747,12 +783,14 @@ pub mod testing {
             vote in arb_proposal_vote(),
             voter in arb_non_internal_address(),
             delegations in collection::vec(arb_non_internal_address(), 0..10),
+            memo in option::of("[a-zA-Z0-9 ]{0,64}"),
         ) -> VoteProposalData {
             VoteProposalData {
                 id,
                 vote,
                 voter,
                 delegations,
+                memo,
             }
         }
     }
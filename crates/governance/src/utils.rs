@@ -1,15 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
 
+use itertools::Itertools;
 use namada_core::address::Address;
 use namada_core::borsh::{BorshDeserialize, BorshSerialize};
 use namada_core::dec::Dec;
-use namada_core::storage::Epoch;
+use namada_core::storage::{Epoch, Key};
 use namada_core::token;
+use namada_storage::{Result, StorageRead};
+use thiserror::Error;
 
 use super::cli::offline::OfflineVote;
+use super::parameters::ProposalParamsSnapshot;
+use super::storage::keys as governance_keys;
 use super::storage::proposal::ProposalType;
 use super::storage::vote::ProposalVote;
+use super::storage::{get_proposal_result_record, get_proposal_votes};
 
 /// Proposal status
 pub enum ProposalStatus {
@@ -85,10 +91,50 @@ impl TallyType {
             (ProposalType::PGFPayment(_), false) => {
                 TallyType::OneHalfOverOneThird
             }
+            (ProposalType::ExtendVotingPeriod(_), _) => {
+                TallyType::OneHalfOverOneThird
+            }
+        }
+    }
+
+    /// The fraction of the total voting power that must vote for the tally
+    /// type when no governance parameter applies
+    pub fn default_threshold(&self) -> Dec {
+        match self {
+            TallyType::TwoThirds => Dec::two() / 3,
+            TallyType::OneHalfOverOneThird
+            | TallyType::LessOneHalfOverOneThirdNay => Dec::one() / 3,
         }
     }
 }
 
+/// Select the fraction of the total voting power that must vote on a proposal
+/// from the governance parameter of its type, as it was when the proposal was
+/// created. The parameters only apply to
+/// the tallies of the yay votes, so the proposals extending a voting period
+/// and the payments of a steward, which pass unless enough voting power votes
+/// nay, keep the default threshold of their tally type.
+pub fn tally_threshold(
+    proposal_type: &ProposalType,
+    tally_type: &TallyType,
+    params: &ProposalParamsSnapshot,
+) -> Dec {
+    match (proposal_type, tally_type) {
+        (_, TallyType::LessOneHalfOverOneThirdNay)
+        | (ProposalType::ExtendVotingPeriod(_), _) => {
+            tally_type.default_threshold()
+        }
+        (ProposalType::Default(_), _) => params.threshold_default,
+        (ProposalType::PGFSteward(_), _) => params.threshold_pgf_steward,
+        (ProposalType::PGFPayment(_), _) => params.threshold_pgf_payment,
+    }
+}
+
+/// Check if the fraction of the total voting power is within `[0, 1]`
+pub fn is_valid_threshold(threshold: &Dec) -> bool {
+    !threshold.is_negative() && *threshold <= Dec::one()
+}
+
 /// The result of a proposal
 #[derive(Copy, Clone, Debug, BorshSerialize, BorshDeserialize)]
 pub enum TallyResult {
@@ -108,9 +154,11 @@ impl Display for TallyResult {
 }
 
 impl TallyResult {
-    /// Create a new tally result
+    /// Create a new tally result. The threshold is the fraction of the total
+    /// voting power that must vote for the votes to decide the proposal.
     pub fn new(
         tally_type: &TallyType,
+        threshold: Dec,
         yay_voting_power: VotePower,
         nay_voting_power: VotePower,
         abstain_voting_power: VotePower,
@@ -118,45 +166,45 @@ impl TallyResult {
     ) -> Self {
         let passed = match tally_type {
             TallyType::TwoThirds => {
-                let at_least_two_third_voted = Self::get_total_voted_power(
+                let at_least_threshold_voted = Self::get_total_voted_power(
                     yay_voting_power,
                     nay_voting_power,
                     abstain_voting_power,
                 ) >= total_voting_power
-                    .mul_ceil(Dec::two() / 3);
+                    .mul_ceil(threshold);
 
                 let at_least_two_third_voted_yay = yay_voting_power
                     >= (nay_voting_power + yay_voting_power)
                         .mul_ceil(Dec::two() / 3);
 
-                at_least_two_third_voted && at_least_two_third_voted_yay
+                at_least_threshold_voted && at_least_two_third_voted_yay
             }
             TallyType::OneHalfOverOneThird => {
-                let at_least_one_third_voted = Self::get_total_voted_power(
+                let at_least_threshold_voted = Self::get_total_voted_power(
                     yay_voting_power,
                     nay_voting_power,
                     abstain_voting_power,
                 ) >= total_voting_power
-                    .mul_ceil(Dec::one() / 3);
+                    .mul_ceil(threshold);
 
                 // Yay votes must be more than half of the total votes
                 let more_than_half_voted_yay =
                     yay_voting_power > nay_voting_power;
-                at_least_one_third_voted && more_than_half_voted_yay
+                at_least_threshold_voted && more_than_half_voted_yay
             }
             TallyType::LessOneHalfOverOneThirdNay => {
-                let less_than_one_third = Self::get_total_voted_power(
+                let less_than_threshold = Self::get_total_voted_power(
                     yay_voting_power,
                     nay_voting_power,
                     abstain_voting_power,
                 ) < total_voting_power
-                    .mul_ceil(Dec::one() / 3);
+                    .mul_ceil(threshold);
 
                 // Nay votes must be less than half of the total votes
                 let more_than_half_voted_yay =
                     yay_voting_power > nay_voting_power;
 
-                less_than_one_third || more_than_half_voted_yay
+                less_than_threshold || more_than_half_voted_yay
             }
         };
 
@@ -179,6 +227,8 @@ pub struct ProposalResult {
     pub result: TallyResult,
     /// The type of tally required for this proposal
     pub tally_type: TallyType,
+    /// The fraction of the total voting power that had to vote
+    pub threshold: Dec,
     /// The total voting power during the proposal tally
     pub total_voting_power: VotePower,
     /// The total voting power from yay votes
@@ -208,15 +258,7 @@ impl ProposalResult {
 
 impl Display for ProposalResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let threshold = match self.tally_type {
-            TallyType::TwoThirds => {
-                self.total_voting_power.mul_ceil(Dec::two() / 3)
-            }
-            _ => self.total_voting_power.mul_ceil(Dec::one() / 3),
-        };
-
-        let thresh_frac =
-            Dec::from(threshold) / Dec::from(self.total_voting_power);
+        let threshold = self.total_voting_power.mul_ceil(self.threshold);
 
         write!(
             f,
@@ -229,7 +271,61 @@ impl Display for ProposalResult {
             self.total_abstain_power.to_string_native(),
             self.total_voting_power.to_string_native(),
             threshold.to_string_native(),
-            thresh_frac
+            self.threshold
+        )
+    }
+}
+
+/// The outcome of a proposal recorded in storage at its activation epoch
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize,
+)]
+pub struct ProposalResultRecord {
+    /// Whether the proposal has passed
+    pub passed: bool,
+    /// The total voting power from yay votes
+    pub yay: VotePower,
+    /// The total voting power from nay votes
+    pub nay: VotePower,
+    /// The total voting power from abstained votes
+    pub abstain: VotePower,
+    /// The total voting power during the proposal tally
+    pub total_voting_power: VotePower,
+    /// The fraction of the total voting power that voted
+    pub turnout: Dec,
+}
+
+impl From<&ProposalResult> for ProposalResultRecord {
+    fn from(result: &ProposalResult) -> Self {
+        let voted = result.total_yay_power
+            + result.total_nay_power
+            + result.total_abstain_power;
+        let turnout = Dec::from(voted)
+            .trunc_div(&Dec::from(result.total_voting_power))
+            .unwrap_or_else(Dec::zero);
+        Self {
+            passed: matches!(result.result, TallyResult::Passed),
+            yay: result.total_yay_power,
+            nay: result.total_nay_power,
+            abstain: result.total_abstain_power,
+            total_voting_power: result.total_voting_power,
+            turnout,
+        }
+    }
+}
+
+impl Display for ProposalResultRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} with {} yay votes, {} nay votes and {} abstain votes, total \
+             voting power: {}, turnout: {}",
+            if self.passed { "passed" } else { "rejected" },
+            self.yay.to_string_native(),
+            self.nay.to_string_native(),
+            self.abstain.to_string_native(),
+            self.total_voting_power.to_string_native(),
+            self.turnout
         )
     }
 }
@@ -303,8 +399,8 @@ pub struct ProposalVotes {
     pub validators_vote: HashMap<Address, TallyVote>,
     /// Map from validator to their voting power
     pub validator_voting_power: HashMap<Address, VotePower>,
-    /// Map from delegation address to their vote
-    pub delegators_vote: HashMap<Address, TallyVote>,
+    /// Map from delegator address to their vote for each delegated validator
+    pub delegators_vote: HashMap<Address, HashMap<Address, TallyVote>>,
     /// Map from delegator address to the corresponding validator voting power
     pub delegator_voting_power: HashMap<Address, HashMap<Address, VotePower>>,
 }
@@ -342,40 +438,164 @@ impl ProposalVotes {
             .entry(address.clone())
             .or_default()
             .insert(validator_address.clone(), voting_power);
-        self.delegators_vote.insert(address.clone(), vote);
+        self.delegators_vote
+            .entry(address.clone())
+            .or_default()
+            .insert(validator_address.clone(), vote);
     }
 }
 
-/// Compute the result of a proposal
+/// The vote of a validator with its stake
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct ValidatorTally {
+    /// The vote of the validator
+    pub vote: ProposalVote,
+    /// The stake of the validator
+    pub stake: VotePower,
+    /// The memo attached to the vote
+    pub memo: Option<String>,
+}
+
+/// The vote of a delegator, which overrides the votes of the validators it
+/// has delegated to for the delegated amounts
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct DelegatorTally {
+    /// The vote of the delegator
+    pub vote: ProposalVote,
+    /// Map from the validator address to the delegated amount
+    pub delegations: BTreeMap<Address, VotePower>,
+    /// The memo attached to the vote
+    pub memo: Option<String>,
+}
+
+/// The tally of a proposal with the breakdown of the votes
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct DetailedTallyResult {
+    /// The total voting power at the tally epoch
+    pub total_voting_power: VotePower,
+    /// The total voting power from yay votes
+    pub total_yay_power: VotePower,
+    /// The total voting power from nay votes
+    pub total_nay_power: VotePower,
+    /// The total voting power from abstained votes
+    pub total_abstain_power: VotePower,
+    /// The fraction of the total voting power that must vote, selected by
+    /// the type of the proposal
+    pub threshold: Dec,
+    /// The votes of the validators
+    pub validators: BTreeMap<Address, ValidatorTally>,
+    /// The votes of the delegators
+    pub delegators: BTreeMap<Address, DelegatorTally>,
+}
+
+impl DetailedTallyResult {
+    /// Tally the votes of the validators and the delegators
+    pub fn new(
+        validators: BTreeMap<Address, ValidatorTally>,
+        delegators: BTreeMap<Address, DelegatorTally>,
+        total_voting_power: VotePower,
+        threshold: Dec,
+    ) -> Self {
+        let mut votes = ProposalVotes::default();
+        for (address, tally) in &validators {
+            votes.add_validator(
+                address,
+                tally.stake,
+                tally.vote.clone().into(),
+            );
+        }
+        for (address, tally) in &delegators {
+            for (validator, amount) in &tally.delegations {
+                votes.add_delegator(
+                    address,
+                    validator,
+                    *amount,
+                    tally.vote.clone().into(),
+                );
+            }
+        }
+        let (total_yay_power, total_nay_power, total_abstain_power) =
+            compute_voting_powers(&votes);
+
+        Self {
+            total_voting_power,
+            total_yay_power,
+            total_nay_power,
+            total_abstain_power,
+            threshold,
+            validators,
+            delegators,
+        }
+    }
+}
+
+/// Compute the result of a proposal, requiring the given fraction of the total
+/// voting power to vote
 pub fn compute_proposal_result(
     votes: ProposalVotes,
     total_voting_power: VotePower,
     tally_type: TallyType,
+    threshold: Dec,
 ) -> ProposalResult {
+    let (yay_voting_power, nay_voting_power, abstain_voting_power) =
+        compute_voting_powers(&votes);
+
+    let tally_result = TallyResult::new(
+        &tally_type,
+        threshold,
+        yay_voting_power,
+        nay_voting_power,
+        abstain_voting_power,
+        total_voting_power,
+    );
+
+    ProposalResult {
+        result: tally_result,
+        tally_type,
+        threshold,
+        total_voting_power,
+        total_yay_power: yay_voting_power,
+        total_nay_power: nay_voting_power,
+        total_abstain_power: abstain_voting_power,
+    }
+}
+
+/// Compute the yay, nay and abstain voting powers of the votes. The vote of a
+/// delegator overrides the vote of its validator for the delegated amount of
+/// each delegation it has voted with.
+fn compute_voting_powers(
+    votes: &ProposalVotes,
+) -> (VotePower, VotePower, VotePower) {
     let mut yay_voting_power = VotePower::default();
     let mut nay_voting_power = VotePower::default();
     let mut abstain_voting_power = VotePower::default();
 
-    for (address, vote_power) in votes.validator_voting_power {
-        let vote_type = votes.validators_vote.get(&address);
+    for (address, vote_power) in &votes.validator_voting_power {
+        let vote_type = votes.validators_vote.get(address);
         if let Some(vote) = vote_type {
             if vote.is_yay() {
-                yay_voting_power += vote_power;
+                yay_voting_power += *vote_power;
             } else if vote.is_nay() {
-                nay_voting_power += vote_power;
+                nay_voting_power += *vote_power;
             } else if vote.is_abstain() {
-                abstain_voting_power += vote_power;
+                abstain_voting_power += *vote_power;
             }
         }
     }
 
-    for (delegator, delegations) in votes.delegator_voting_power {
-        let delegator_vote = match votes.delegators_vote.get(&delegator) {
-            Some(vote) => vote,
-            None => continue,
-        };
+    for (delegator, delegations) in &votes.delegator_voting_power {
         for (validator, voting_power) in delegations {
-            let validator_vote = votes.validators_vote.get(&validator);
+            // A delegator only overrides the delegations it has voted with
+            let delegator_vote = match votes
+                .delegators_vote
+                .get(delegator)
+                .and_then(|delegator_votes| delegator_votes.get(validator))
+            {
+                Some(vote) => vote,
+                None => continue,
+            };
+            let voting_power = *voting_power;
+            let validator_vote = votes.validators_vote.get(validator);
             if let Some(validator_vote) = validator_vote {
                 let validator_vote_is_same_side =
                     validator_vote.is_same_side(delegator_vote);
@@ -414,22 +634,7 @@ pub fn compute_proposal_result(
         }
     }
 
-    let tally_result = TallyResult::new(
-        &tally_type,
-        yay_voting_power,
-        nay_voting_power,
-        abstain_voting_power,
-        total_voting_power,
-    );
-
-    ProposalResult {
-        result: tally_result,
-        tally_type,
-        total_voting_power,
-        total_yay_power: yay_voting_power,
-        total_nay_power: nay_voting_power,
-        total_abstain_power: abstain_voting_power,
-    }
+    (yay_voting_power, nay_voting_power, abstain_voting_power)
 }
 
 /// Calculate the valid voting window for validator given a proposal epoch
@@ -448,6 +653,149 @@ pub fn is_valid_validator_voting_period(
     }
 }
 
+/// A proposal with all its data read from storage by [`query_proposal`]
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ProposalFull {
+    /// The proposal id
+    pub id: u64,
+    /// The encoded proposal content
+    pub content: Vec<u8>,
+    /// The proposal author address
+    pub author: Address,
+    /// The proposal type
+    pub r#type: ProposalType,
+    /// The funds locked by the author
+    pub funds: token::Amount,
+    /// The epoch from which voting is allowed
+    pub voting_start_epoch: Epoch,
+    /// The epoch from which voting is stopped
+    pub voting_end_epoch: Epoch,
+    /// The epoch from which the proposal is activated
+    pub grace_epoch: Epoch,
+    /// The number of the votes cast so far
+    pub vote_count: u64,
+    /// The record of the proposal result, once the proposal is activated
+    pub result: Option<ProposalResultRecord>,
+}
+
+/// A proposal whose data is only partially written in storage
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(
+    "The proposal {id} is incomplete, missing the keys: {}",
+    .missing_keys.iter().join(", ")
+)]
+pub struct IncompleteProposalError {
+    /// The proposal id
+    pub id: u64,
+    /// The keys of the proposal missing from storage
+    pub missing_keys: Vec<Key>,
+}
+
+/// Read all the data of a proposal from storage. Returns `None` if the
+/// proposal doesn't exist and an [`IncompleteProposalError`] if only some of
+/// its keys are written.
+pub fn query_proposal<S>(storage: &S, id: u64) -> Result<Option<ProposalFull>>
+where
+    S: StorageRead,
+{
+    /// The number of the keys written for every proposal
+    const PROPOSAL_KEYS: usize = 7;
+
+    let mut missing_keys = vec![];
+    let content_key = governance_keys::get_content_key(id);
+    let content = storage.read_bytes(&content_key)?;
+    if content.is_none() {
+        missing_keys.push(content_key);
+    }
+    let author: Option<Address> = read_proposal_key(
+        storage,
+        governance_keys::get_author_key(id),
+        &mut missing_keys,
+    )?;
+    let r#type: Option<ProposalType> = read_proposal_key(
+        storage,
+        governance_keys::get_proposal_type_key(id),
+        &mut missing_keys,
+    )?;
+    let funds: Option<token::Amount> = read_proposal_key(
+        storage,
+        governance_keys::get_funds_key(id),
+        &mut missing_keys,
+    )?;
+    let voting_start_epoch: Option<Epoch> = read_proposal_key(
+        storage,
+        governance_keys::get_voting_start_epoch_key(id),
+        &mut missing_keys,
+    )?;
+    let voting_end_epoch: Option<Epoch> = read_proposal_key(
+        storage,
+        governance_keys::get_voting_end_epoch_key(id),
+        &mut missing_keys,
+    )?;
+    let grace_epoch: Option<Epoch> = read_proposal_key(
+        storage,
+        governance_keys::get_grace_epoch_key(id),
+        &mut missing_keys,
+    )?;
+
+    match (
+        content,
+        author,
+        r#type,
+        funds,
+        voting_start_epoch,
+        voting_end_epoch,
+        grace_epoch,
+    ) {
+        (
+            Some(content),
+            Some(author),
+            Some(r#type),
+            Some(funds),
+            Some(voting_start_epoch),
+            Some(voting_end_epoch),
+            Some(grace_epoch),
+        ) => {
+            let vote_count = get_proposal_votes(storage, id)?.len() as u64;
+            let result = get_proposal_result_record(storage, id)?;
+            Ok(Some(ProposalFull {
+                id,
+                content,
+                author,
+                r#type,
+                funds,
+                voting_start_epoch,
+                voting_end_epoch,
+                grace_epoch,
+                vote_count,
+                result,
+            }))
+        }
+        _ if missing_keys.len() == PROPOSAL_KEYS => Ok(None),
+        _ => Err(namada_storage::Error::new(IncompleteProposalError {
+            id,
+            missing_keys,
+        })),
+    }
+}
+
+/// Read a key of a proposal, recording the key if it's missing
+fn read_proposal_key<S, T>(
+    storage: &S,
+    key: Key,
+    missing_keys: &mut Vec<Key>,
+) -> Result<Option<T>>
+where
+    S: StorageRead,
+    T: BorshDeserialize,
+{
+    let value = storage.read(&key)?;
+    if value.is_none() {
+        missing_keys.push(key);
+    }
+    Ok(value)
+}
+
 #[cfg(test)]
 mod test {
     use std::ops::{Add, Sub};
@@ -455,6 +803,7 @@ mod test {
     use namada_core::address;
 
     use super::*;
+    use crate::parameters::GovernanceParameters;
 
     #[test]
     fn test_proposal_result_no_votes_should_fail() {
@@ -469,6 +818,7 @@ mod test {
                 proposal_votes.clone(),
                 token::Amount::from_u64(1),
                 tally_type,
+                tally_type.default_threshold(),
             );
             let _result = if matches!(
                 tally_type,
@@ -506,6 +856,7 @@ mod test {
                 proposal_votes.clone(),
                 validator_voting_power,
                 tally_type,
+                tally_type.default_threshold(),
             );
             assert!(
                 matches!(proposal_result.result, TallyResult::Passed),
@@ -554,6 +905,7 @@ mod test {
                 proposal_votes.clone(),
                 validator_voting_power,
                 tally_type,
+                tally_type.default_threshold(),
             );
             assert!(
                 matches!(proposal_result.result, TallyResult::Passed),
@@ -602,6 +954,7 @@ mod test {
                 proposal_votes.clone(),
                 validator_voting_power,
                 tally_type,
+                tally_type.default_threshold(),
             );
             assert!(
                 matches!(proposal_result.result, TallyResult::Rejected),
@@ -662,6 +1015,7 @@ mod test {
                 proposal_votes.clone(),
                 validator_voting_power,
                 tally_type,
+                tally_type.default_threshold(),
             );
             assert!(
                 matches!(proposal_result.result, TallyResult::Rejected),
@@ -729,6 +1083,7 @@ mod test {
                 proposal_votes.clone(),
                 validator_voting_power,
                 tally_type,
+                tally_type.default_threshold(),
             );
             assert!(
                 matches!(proposal_result.result, TallyResult::Passed),
@@ -787,6 +1142,7 @@ mod test {
                 proposal_votes.clone(),
                 validator_voting_power,
                 tally_type,
+                tally_type.default_threshold(),
             );
             assert!(
                 matches!(proposal_result.result, TallyResult::Passed),
@@ -843,6 +1199,7 @@ mod test {
                 proposal_votes.clone(),
                 validator_voting_power.add(validator_voting_power_two),
                 tally_type,
+                tally_type.default_threshold(),
             );
             let _result = if matches!(
                 tally_type,
@@ -915,6 +1272,7 @@ mod test {
                 proposal_votes.clone(),
                 validator_voting_power.add(validator_voting_power_two),
                 tally_type,
+                tally_type.default_threshold(),
             );
             let _result =
                 if matches!(tally_type, TallyType::OneHalfOverOneThird) {
@@ -982,6 +1340,7 @@ mod test {
             proposal_votes.clone(),
             validator_voting_power.add(validator_voting_power_two),
             TallyType::TwoThirds,
+            TallyType::TwoThirds.default_threshold(),
         );
 
         assert!(matches!(proposal_result.result, TallyResult::Passed));
@@ -1050,6 +1409,7 @@ mod test {
             proposal_votes.clone(),
             validator_voting_power.add(validator_voting_power_two),
             TallyType::TwoThirds,
+            TallyType::TwoThirds.default_threshold(),
         );
 
         assert!(matches!(proposal_result.result, TallyResult::Rejected));
@@ -1105,6 +1465,7 @@ mod test {
             proposal_votes.clone(),
             delegator_voting_power_two.add(delegator_voting_power),
             TallyType::TwoThirds,
+            TallyType::TwoThirds.default_threshold(),
         );
 
         assert!(matches!(proposal_result.result, TallyResult::Rejected));
@@ -1157,6 +1518,7 @@ mod test {
             proposal_votes.clone(),
             token::Amount::from(200),
             TallyType::TwoThirds,
+            TallyType::TwoThirds.default_threshold(),
         );
 
         assert!(matches!(proposal_result.result, TallyResult::Passed));
@@ -1211,6 +1573,7 @@ mod test {
             proposal_votes.clone(),
             token::Amount::from(403),
             TallyType::OneHalfOverOneThird,
+            TallyType::OneHalfOverOneThird.default_threshold(),
         );
 
         assert!(matches!(proposal_result.result, TallyResult::Rejected));
@@ -1265,6 +1628,7 @@ mod test {
             proposal_votes.clone(),
             token::Amount::from(402),
             TallyType::OneHalfOverOneThird,
+            TallyType::OneHalfOverOneThird.default_threshold(),
         );
 
         assert!(matches!(proposal_result.result, TallyResult::Passed));
@@ -1319,6 +1683,7 @@ mod test {
             proposal_votes.clone(),
             token::Amount::from(100),
             TallyType::LessOneHalfOverOneThirdNay,
+            TallyType::LessOneHalfOverOneThirdNay.default_threshold(),
         );
 
         assert!(matches!(proposal_result.result, TallyResult::Rejected));
@@ -1375,6 +1740,7 @@ mod test {
             proposal_votes.clone(),
             token::Amount::from(271),
             TallyType::LessOneHalfOverOneThirdNay,
+            TallyType::LessOneHalfOverOneThirdNay.default_threshold(),
         );
 
         assert!(matches!(proposal_result.result, TallyResult::Passed));
@@ -1401,4 +1767,117 @@ mod test {
 
         assert!(!proposal_result.two_thirds_nay_over_two_thirds_total())
     }
+
+    #[test]
+    fn test_proposal_threshold_per_proposal_type() {
+        let mut proposal_votes = ProposalVotes::default();
+
+        let validator_address = address::testing::established_address_1();
+        proposal_votes.add_validator(
+            &validator_address,
+            token::Amount::from_u64(70),
+            ProposalVote::Yay.into(),
+        );
+
+        let params = ProposalParamsSnapshot::from(&GovernanceParameters {
+            threshold_pgf_payment: Dec::new(75, 2).unwrap(),
+            ..Default::default()
+        });
+        let total_voting_power = token::Amount::from_u64(100);
+
+        let default_type = ProposalType::Default(None);
+        let tally_type = TallyType::from(default_type.clone(), false);
+        let default_result = compute_proposal_result(
+            proposal_votes.clone(),
+            total_voting_power,
+            tally_type,
+            tally_threshold(&default_type, &tally_type, &params),
+        );
+        assert!(matches!(default_result.result, TallyResult::Passed));
+        assert_eq!(default_result.threshold, params.threshold_default);
+
+        let payment_type = ProposalType::PGFPayment(Default::default());
+        let tally_type = TallyType::from(payment_type.clone(), false);
+        let payment_result = compute_proposal_result(
+            proposal_votes.clone(),
+            total_voting_power,
+            tally_type,
+            tally_threshold(&payment_type, &tally_type, &params),
+        );
+        assert!(matches!(payment_result.result, TallyResult::Rejected));
+        assert_eq!(payment_result.threshold, params.threshold_pgf_payment);
+
+        // The payment of a steward is vetoed by the nay votes, so the
+        // parameter of the yay tallies doesn't apply
+        let tally_type = TallyType::from(payment_type.clone(), true);
+        let steward_result = compute_proposal_result(
+            proposal_votes,
+            total_voting_power,
+            tally_type,
+            tally_threshold(&payment_type, &tally_type, &params),
+        );
+        assert!(matches!(steward_result.result, TallyResult::Passed));
+        assert_eq!(
+            steward_result.threshold,
+            TallyType::LessOneHalfOverOneThirdNay.default_threshold()
+        );
+    }
+
+    #[test]
+    fn test_is_valid_threshold() {
+        assert!(is_valid_threshold(&Dec::zero()));
+        assert!(is_valid_threshold(&(Dec::two() / 3)));
+        assert!(is_valid_threshold(&Dec::one()));
+        assert!(!is_valid_threshold(&Dec::two()));
+        assert!(!is_valid_threshold(&-Dec::one()));
+    }
+
+    #[test]
+    fn test_proposal_delegator_votes_with_subset_of_delegations() {
+        let mut proposal_votes = ProposalVotes::default();
+
+        let validator_voting_power = token::Amount::from_u64(100);
+        let validators: Vec<_> = (0..5)
+            .map(|_| address::testing::gen_established_address())
+            .collect();
+        for validator in &validators {
+            proposal_votes.add_validator(
+                validator,
+                validator_voting_power,
+                ProposalVote::Yay.into(),
+            );
+        }
+
+        // the delegator has a delegation of 10 to each of the validators and
+        // votes with 2 of them only
+        let delegator_address = address::testing::established_address_1();
+        let delegator_voting_power = token::Amount::from_u64(10);
+        proposal_votes.add_delegator(
+            &delegator_address,
+            &validators[0],
+            delegator_voting_power,
+            ProposalVote::Nay.into(),
+        );
+        proposal_votes.add_delegator(
+            &delegator_address,
+            &validators[1],
+            delegator_voting_power,
+            ProposalVote::Abstain.into(),
+        );
+
+        let total_voting_power = validator_voting_power * 5;
+        let proposal_result = compute_proposal_result(
+            proposal_votes,
+            total_voting_power,
+            TallyType::OneHalfOverOneThird,
+            TallyType::OneHalfOverOneThird.default_threshold(),
+        );
+        assert!(matches!(proposal_result.result, TallyResult::Passed));
+        assert_eq!(
+            proposal_result.total_yay_power,
+            total_voting_power - delegator_voting_power * 2
+        );
+        assert_eq!(proposal_result.total_nay_power, delegator_voting_power);
+        assert_eq!(proposal_result.total_abstain_power, delegator_voting_power);
+    }
 }
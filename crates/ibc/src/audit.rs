@@ -0,0 +1,233 @@
+//! Audit of the tokens escrowed by IBC
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use namada_core::address::{Address, InternalAddress, IBC_FEE_ESCROW};
+use namada_core::borsh::{BorshDeserialize, BorshSerialize};
+use namada_core::ibc::core::host::types::identifiers::{
+    ChannelId, PortId, Sequence,
+};
+use namada_core::ibc::{PacketEscrow, PacketFee};
+use namada_core::token::Amount;
+use namada_storage::{iter_prefix, Error, Result, StorageRead};
+use namada_token::storage_key::balance_key;
+
+use crate::storage;
+
+/// An outgoing packet for which the token has been escrowed
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+pub struct EscrowedPacket {
+    /// The source port
+    pub port_id: String,
+    /// The source channel
+    pub channel_id: String,
+    /// The sequence of the packet
+    pub sequence: u64,
+    /// The escrowed amount
+    pub amount: Amount,
+}
+
+impl Display for EscrowedPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{}/{} ({})",
+            self.port_id,
+            self.channel_id,
+            self.sequence,
+            self.amount.to_string_native()
+        )
+    }
+}
+
+/// The result of the audit of the token escrowed by IBC
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize,
+)]
+pub struct EscrowAuditReport {
+    /// The amount escrowed by the transfers and not unescrowed yet,
+    /// including the packets that have been acknowledged
+    pub escrowed: Amount,
+    /// The relayer fees in the token escrowed for the in-flight packets,
+    /// which are accounted apart from the transfers
+    pub packet_fees: Amount,
+    /// The sum of the relayer fees in the token recorded for each in-flight
+    /// packet
+    pub packet_fee_records: Amount,
+    /// The amount escrowed for the in-flight packets
+    pub in_flight: Amount,
+    /// The balance of the IBC account
+    pub balance: Amount,
+    /// The balance of the relayer fee escrow account
+    pub fee_balance: Amount,
+    /// The packets whose escrow records remain after their commitments have
+    /// been deleted
+    pub stale_packets: Vec<EscrowedPacket>,
+    /// The in-flight packets whose escrow isn't covered by the balance
+    pub uncovered_packets: Vec<EscrowedPacket>,
+}
+
+impl EscrowAuditReport {
+    /// Check if the balances match the accounting and every escrow record
+    /// is settled or covered
+    pub fn is_consistent(&self) -> bool {
+        self.balance == self.escrowed
+            && self.fee_balance == self.packet_fees
+            && self.packet_fees == self.packet_fee_records
+            && self.in_flight <= self.escrowed
+            && self.stale_packets.is_empty()
+            && self.uncovered_packets.is_empty()
+    }
+}
+
+/// Audit the balance of the token escrowed in the IBC account against the
+/// escrowed amount recorded by the transfers and the escrow records of the
+/// in-flight packets, and the balance of the relayer fee escrow account
+/// against the escrowed relayer fees. When the balance of the IBC account
+/// falls short, the latest in-flight packets that it can't cover are
+/// reported.
+pub fn audit_ibc_escrow<S: StorageRead>(
+    storage: &S,
+    token: &Address,
+) -> Result<EscrowAuditReport> {
+    let escrowed = storage
+        .read(&storage::escrow_amount_key(token))?
+        .unwrap_or_default();
+    let balance = storage
+        .read(&balance_key(
+            token,
+            &Address::Internal(InternalAddress::Ibc),
+        ))?
+        .unwrap_or_default();
+
+    let fee_balance = storage
+        .read(&balance_key(token, &IBC_FEE_ESCROW))?
+        .unwrap_or_default();
+    let packet_fees = storage
+        .read(&storage::fee_escrow_amount_key(token))?
+        .unwrap_or_default();
+    let mut packet_fee_records = Amount::zero();
+    for result in
+        iter_prefix::<PacketFee>(storage, &storage::packet_fee_prefix())?
+    {
+        let (_, fee) = result?;
+        if fee.token == *token {
+            for amount in [fee.recv_fee, fee.ack_fee, fee.timeout_fee] {
+                packet_fee_records = packet_fee_records
+                    .checked_add(amount.amount())
+                    .ok_or_else(|| Error::new_const("Packet fees overflow"))?;
+            }
+        }
+    }
+
+    let mut packets = vec![];
+    for result in
+        iter_prefix::<PacketEscrow>(storage, &storage::packet_escrow_prefix())?
+    {
+        let (key, escrow) = result?;
+        if escrow.token != *token {
+            continue;
+        }
+        if let Some((port_id, channel_id, sequence)) =
+            storage::is_packet_escrow_key(&key)
+        {
+            packets.push((port_id, channel_id, sequence, escrow.amount));
+        }
+    }
+    // The keys are sorted as strings, not by the sequence
+    packets.sort_by_key(|(port_id, channel_id, sequence, _)| {
+        (
+            port_id.to_string(),
+            channel_id.to_string(),
+            u64::from(*sequence),
+        )
+    });
+
+    let mut in_flight_packets = vec![];
+    let mut stale_packets = vec![];
+    let mut in_flight = Amount::zero();
+    for (port_id, channel_id, sequence, amount) in packets {
+        let packet = escrowed_packet(&port_id, &channel_id, sequence, amount);
+        let commitment_key =
+            storage::commitment_key(&port_id, &channel_id, sequence);
+        if storage.has_key(&commitment_key)? {
+            in_flight = in_flight
+                .checked_add(amount)
+                .ok_or_else(|| Error::new_const("In-flight amount overflow"))?;
+            in_flight_packets.push(packet);
+        } else {
+            stale_packets.push(packet);
+        }
+    }
+
+    // The balance left for the in-flight packets after the settled escrow
+    let settled = escrowed.checked_sub(in_flight).unwrap_or_default();
+    let mut remaining = balance.checked_sub(settled).unwrap_or_default();
+    let mut uncovered_packets = vec![];
+    for packet in in_flight_packets {
+        match remaining.checked_sub(packet.amount) {
+            Some(rest) => remaining = rest,
+            None => uncovered_packets.push(packet),
+        }
+    }
+
+    Ok(EscrowAuditReport {
+        escrowed,
+        packet_fees,
+        packet_fee_records,
+        in_flight,
+        balance,
+        fee_balance,
+        stale_packets,
+        uncovered_packets,
+    })
+}
+
+/// Get the tokens which have been escrowed by IBC transfers or for relayer
+/// fees
+pub fn escrowed_tokens<S: StorageRead>(
+    storage: &S,
+) -> Result<BTreeSet<Address>> {
+    let mut tokens = BTreeSet::new();
+    for result in
+        iter_prefix::<Amount>(storage, &storage::escrow_amount_prefix())?
+    {
+        let (key, _) = result?;
+        if let Some(token) = storage::is_escrow_amount_key(&key) {
+            tokens.insert(token.clone());
+        }
+    }
+    for result in
+        iter_prefix::<Amount>(storage, &storage::fee_escrow_amount_prefix())?
+    {
+        let (key, _) = result?;
+        if let Some(token) = storage::is_fee_escrow_amount_key(&key) {
+            tokens.insert(token.clone());
+        }
+    }
+    Ok(tokens)
+}
+
+fn escrowed_packet(
+    port_id: &PortId,
+    channel_id: &ChannelId,
+    sequence: Sequence,
+    amount: Amount,
+) -> EscrowedPacket {
+    EscrowedPacket {
+        port_id: port_id.to_string(),
+        channel_id: channel_id.to_string(),
+        sequence: sequence.into(),
+        amount,
+    }
+}
@@ -0,0 +1,234 @@
+//! IBC module for the ports without an application
+
+use namada_core::ibc::core::channel::types::acknowledgement::{
+    Acknowledgement, AcknowledgementStatus, StatusValue,
+};
+use namada_core::ibc::core::channel::types::channel::{Counterparty, Order};
+use namada_core::ibc::core::channel::types::error::{
+    ChannelError, PacketError,
+};
+use namada_core::ibc::core::channel::types::packet::Packet;
+use namada_core::ibc::core::channel::types::Version;
+use namada_core::ibc::core::host::types::identifiers::{
+    ChannelId, ConnectionId, PortId,
+};
+use namada_core::ibc::core::router::module::Module;
+use namada_core::ibc::core::router::types::module::{ModuleExtras, ModuleId};
+use namada_core::ibc::primitives::Signer;
+
+use super::transfer_mod::ModuleWrapper;
+
+const MODULE_ID_STR: &str = "channel";
+
+/// IBC module for the ports which aren't bound to an application module, e.g.
+/// an interchain-accounts style port. A channel on such a port can't be opened
+/// and no packet can be handled, but an existing channel can be closed.
+#[derive(Debug, Default)]
+pub struct ChannelModule;
+
+impl ChannelModule {
+    /// Get the module ID
+    pub fn module_id(&self) -> ModuleId {
+        ModuleId::new(MODULE_ID_STR.to_string())
+    }
+}
+
+impl ModuleWrapper for ChannelModule {
+    fn as_module(&self) -> &dyn Module {
+        self
+    }
+
+    fn as_module_mut(&mut self) -> &mut dyn Module {
+        self
+    }
+}
+
+impl Module for ChannelModule {
+    #[allow(clippy::too_many_arguments)]
+    fn on_chan_open_init_validate(
+        &self,
+        _order: Order,
+        _connection_hops: &[ConnectionId],
+        port_id: &PortId,
+        _channel_id: &ChannelId,
+        _counterparty: &Counterparty,
+        _version: &Version,
+    ) -> Result<Version, ChannelError> {
+        Err(unbound_port_channel_error(port_id))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn on_chan_open_init_execute(
+        &mut self,
+        _order: Order,
+        _connection_hops: &[ConnectionId],
+        port_id: &PortId,
+        _channel_id: &ChannelId,
+        _counterparty: &Counterparty,
+        _version: &Version,
+    ) -> Result<(ModuleExtras, Version), ChannelError> {
+        Err(unbound_port_channel_error(port_id))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn on_chan_open_try_validate(
+        &self,
+        _order: Order,
+        _connection_hops: &[ConnectionId],
+        port_id: &PortId,
+        _channel_id: &ChannelId,
+        _counterparty: &Counterparty,
+        _counterparty_version: &Version,
+    ) -> Result<Version, ChannelError> {
+        Err(unbound_port_channel_error(port_id))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn on_chan_open_try_execute(
+        &mut self,
+        _order: Order,
+        _connection_hops: &[ConnectionId],
+        port_id: &PortId,
+        _channel_id: &ChannelId,
+        _counterparty: &Counterparty,
+        _counterparty_version: &Version,
+    ) -> Result<(ModuleExtras, Version), ChannelError> {
+        Err(unbound_port_channel_error(port_id))
+    }
+
+    fn on_chan_open_ack_validate(
+        &self,
+        port_id: &PortId,
+        _channel_id: &ChannelId,
+        _counterparty_version: &Version,
+    ) -> Result<(), ChannelError> {
+        Err(unbound_port_channel_error(port_id))
+    }
+
+    fn on_chan_open_ack_execute(
+        &mut self,
+        port_id: &PortId,
+        _channel_id: &ChannelId,
+        _counterparty_version: &Version,
+    ) -> Result<ModuleExtras, ChannelError> {
+        Err(unbound_port_channel_error(port_id))
+    }
+
+    fn on_chan_open_confirm_validate(
+        &self,
+        port_id: &PortId,
+        _channel_id: &ChannelId,
+    ) -> Result<(), ChannelError> {
+        Err(unbound_port_channel_error(port_id))
+    }
+
+    fn on_chan_open_confirm_execute(
+        &mut self,
+        port_id: &PortId,
+        _channel_id: &ChannelId,
+    ) -> Result<ModuleExtras, ChannelError> {
+        Err(unbound_port_channel_error(port_id))
+    }
+
+    fn on_chan_close_init_validate(
+        &self,
+        _port_id: &PortId,
+        _channel_id: &ChannelId,
+    ) -> Result<(), ChannelError> {
+        Ok(())
+    }
+
+    fn on_chan_close_init_execute(
+        &mut self,
+        _port_id: &PortId,
+        _channel_id: &ChannelId,
+    ) -> Result<ModuleExtras, ChannelError> {
+        Ok(ModuleExtras::empty())
+    }
+
+    fn on_chan_close_confirm_validate(
+        &self,
+        _port_id: &PortId,
+        _channel_id: &ChannelId,
+    ) -> Result<(), ChannelError> {
+        Ok(())
+    }
+
+    fn on_chan_close_confirm_execute(
+        &mut self,
+        _port_id: &PortId,
+        _channel_id: &ChannelId,
+    ) -> Result<ModuleExtras, ChannelError> {
+        Ok(ModuleExtras::empty())
+    }
+
+    fn on_recv_packet_execute(
+        &mut self,
+        packet: &Packet,
+        _relayer: &Signer,
+    ) -> (ModuleExtras, Acknowledgement) {
+        let status =
+            StatusValue::new(unbound_port_description(&packet.port_id_on_b))
+                .expect("The status shouldn't be empty");
+        (
+            ModuleExtras::empty(),
+            AcknowledgementStatus::error(status).into(),
+        )
+    }
+
+    fn on_acknowledgement_packet_validate(
+        &self,
+        packet: &Packet,
+        _acknowledgement: &Acknowledgement,
+        _relayer: &Signer,
+    ) -> Result<(), PacketError> {
+        Err(unbound_port_packet_error(&packet.port_id_on_a))
+    }
+
+    fn on_acknowledgement_packet_execute(
+        &mut self,
+        packet: &Packet,
+        _acknowledgement: &Acknowledgement,
+        _relayer: &Signer,
+    ) -> (ModuleExtras, Result<(), PacketError>) {
+        (
+            ModuleExtras::empty(),
+            Err(unbound_port_packet_error(&packet.port_id_on_a)),
+        )
+    }
+
+    fn on_timeout_packet_validate(
+        &self,
+        packet: &Packet,
+        _relayer: &Signer,
+    ) -> Result<(), PacketError> {
+        Err(unbound_port_packet_error(&packet.port_id_on_a))
+    }
+
+    fn on_timeout_packet_execute(
+        &mut self,
+        packet: &Packet,
+        _relayer: &Signer,
+    ) -> (ModuleExtras, Result<(), PacketError>) {
+        (
+            ModuleExtras::empty(),
+            Err(unbound_port_packet_error(&packet.port_id_on_a)),
+        )
+    }
+}
+
+fn unbound_port_description(port_id: &PortId) -> String {
+    format!("No application is bound to the port: Port ID {port_id}")
+}
+
+fn unbound_port_channel_error(port_id: &PortId) -> ChannelError {
+    ChannelError::AppModule {
+        description: unbound_port_description(port_id),
+    }
+}
+
+fn unbound_port_packet_error(port_id: &PortId) -> PacketError {
+    PacketError::AppModule {
+        description: unbound_port_description(port_id),
+    }
+}
@@ -1,5 +1,7 @@
 //! AnyClientState and AnyConsensusState for IBC context
 
+use core::time::Duration;
+
 use ibc_derive::ConsensusState;
 #[cfg(feature = "testing")]
 use ibc_testkit::testapp::ibc::clients::mock::client_state::MockClientContext;
@@ -30,6 +32,11 @@ use namada_core::ibc::core::host::ExecutionContext;
 use namada_core::ibc::primitives::proto::Any;
 use prost::Message;
 
+/// The trusting period of the mock clients, which don't have it
+#[cfg(feature = "testing")]
+pub const MOCK_TRUSTING_PERIOD: Duration =
+    Duration::from_secs(14 * 24 * 60 * 60);
+
 // TODO: #[derive(ClientState)] doesn't support contexts with contexts generic
 // for now
 /// ClientState for light clients
@@ -43,6 +50,17 @@ pub enum AnyClientState {
     Mock(MockClientState),
 }
 
+impl AnyClientState {
+    /// The period for which the consensus states of the client are trusted
+    pub fn trusting_period(&self) -> Duration {
+        match self {
+            AnyClientState::Tendermint(cs) => cs.inner().trusting_period,
+            #[cfg(feature = "testing")]
+            AnyClientState::Mock(_) => MOCK_TRUSTING_PERIOD,
+        }
+    }
+}
+
 impl From<TmClientState> for AnyClientState {
     fn from(cs: TmClientState) -> Self {
         Self::Tendermint(cs)
@@ -2,6 +2,7 @@
 
 use core::time::Duration;
 
+use namada_core::address::Address;
 use namada_core::ibc::clients::tendermint::consensus_state::ConsensusState as TmConsensusState;
 use namada_core::ibc::clients::tendermint::types::ConsensusState as TmConsensusStateType;
 use namada_core::ibc::core::channel::types::channel::ChannelEnd;
@@ -11,7 +12,7 @@ use namada_core::ibc::core::channel::types::commitment::{
 use namada_core::ibc::core::channel::types::error::{
     ChannelError, PacketError,
 };
-use namada_core::ibc::core::channel::types::packet::Receipt;
+use namada_core::ibc::core::channel::types::packet::{Packet, Receipt};
 use namada_core::ibc::core::channel::types::timeout::TimeoutHeight;
 use namada_core::ibc::core::client::context::consensus_state::ConsensusState;
 use namada_core::ibc::core::client::types::error::ClientError;
@@ -24,15 +25,18 @@ use namada_core::ibc::core::host::types::identifiers::{
 };
 use namada_core::ibc::primitives::proto::{Any, Protobuf};
 use namada_core::ibc::primitives::Timestamp;
+use namada_core::ibc::{PacketEscrow, PacketFee};
 use namada_core::storage::{BlockHeight, Key};
 use namada_core::tendermint::Time as TmTime;
 use namada_core::time::DurationSecs;
+use namada_core::token::Amount;
 use namada_parameters::storage::get_max_expected_time_per_block_key;
 use prost::Message;
 use sha2::Digest;
 
 use super::client::{AnyClientState, AnyConsensusState};
 use super::storage::IbcStorageContext;
+use crate::parameters::IbcParameters;
 use crate::storage;
 
 /// Result of IBC common function call
@@ -108,6 +112,32 @@ pub trait IbcCommonContext: IbcStorageContext {
         self.delete(&key).map_err(ContextError::from)
     }
 
+    /// Delete the consensus states of the client which have expired at the
+    /// host time, i.e. older than the trusting period of the client, as
+    /// ibc-go does on a client update. The states are visited from the lowest
+    /// height and the pruning stops at the first state which hasn't expired.
+    fn prune_expired_consensus_states(
+        &mut self,
+        client_id: &ClientId,
+    ) -> Result<()> {
+        let trusting_period = self.client_state(client_id)?.trusting_period();
+        let host_timestamp = self.host_timestamp()?;
+        // The keys are ordered as strings, not by the height
+        let mut heights = self.consensus_state_heights(client_id)?;
+        heights.sort_unstable();
+        for height in heights {
+            let consensus_state = self.consensus_state(client_id, height)?;
+            let is_expired = host_timestamp
+                .duration_since(&consensus_state.timestamp())
+                .map_or(false, |elapsed| elapsed >= trusting_period);
+            if !is_expired {
+                break;
+            }
+            self.delete_consensus_state(client_id, height)?;
+        }
+        Ok(())
+    }
+
     /// Decode ConsensusState from bytes
     fn decode_consensus_state_value(
         &self,
@@ -467,16 +497,8 @@ pub trait IbcCommonContext: IbcStorageContext {
 
     /// Read a sequence
     fn read_sequence(&self, key: &Key) -> Result<Sequence> {
-        match self.read_bytes(key)? {
-            Some(value) => {
-                let value: [u8; 8] =
-                    value.try_into().map_err(|_| ChannelError::Other {
-                        description: format!(
-                            "The sequence value wasn't u64: Key {key}",
-                        ),
-                    })?;
-                Ok(u64::from_be_bytes(value).into())
-            }
+        match storage::read_sequence(self, key)? {
+            Some(sequence) => Ok(sequence),
             // when the sequence has never been used, returns the initial value
             None => Ok(1.into()),
         }
@@ -484,8 +506,7 @@ pub trait IbcCommonContext: IbcStorageContext {
 
     /// Store the sequence
     fn store_sequence(&mut self, key: &Key, sequence: Sequence) -> Result<()> {
-        let bytes = u64::from(sequence).to_be_bytes().to_vec();
-        self.write_bytes(key, bytes).map_err(ContextError::from)
+        storage::write_sequence(self, key, sequence).map_err(ContextError::from)
     }
 
     /// Calculate the hash
@@ -554,7 +575,14 @@ pub trait IbcCommonContext: IbcStorageContext {
         sequence: Sequence,
     ) -> Result<()> {
         let key = storage::commitment_key(port_id, channel_id, sequence);
-        self.delete(&key).map_err(ContextError::from)
+        self.delete(&key).map_err(ContextError::from)?;
+        // The escrow of the packet is settled by its acknowledgement or
+        // timeout
+        let key = storage::packet_escrow_key(port_id, channel_id, sequence);
+        if self.has_key(&key)? {
+            self.delete(&key).map_err(ContextError::from)?;
+        }
+        Ok(())
     }
 
     /// Get the packet receipt
@@ -627,7 +655,7 @@ pub trait IbcCommonContext: IbcStorageContext {
 
     /// Read a counter
     fn read_counter(&self, key: &Key) -> Result<u64> {
-        match self.read::<u64>(key)? {
+        match storage::read_counter(self, key)? {
             Some(counter) => Ok(counter),
             None => unreachable!("the counter should be initialized"),
         }
@@ -640,7 +668,7 @@ pub trait IbcCommonContext: IbcStorageContext {
             u64::checked_add(count, 1).ok_or_else(|| ClientError::Other {
                 description: format!("The counter overflow: Key {key}"),
             })?;
-        self.write(key, count).map_err(ContextError::from)
+        storage::write_counter(self, key, count).map_err(ContextError::from)
     }
 
     /// Write the IBC denom. The given address could be a non-Namada token.
@@ -665,4 +693,289 @@ pub trait IbcCommonContext: IbcStorageContext {
         }
         Ok(())
     }
+
+    /// Write the IBC denom with the IBC token derived from it, to look up the
+    /// denom of an IBC token
+    fn store_ibc_denom_by_token(
+        &mut self,
+        denom: impl AsRef<str>,
+    ) -> Result<()> {
+        let token = storage::ibc_token(denom.as_ref());
+        let key = storage::ibc_denom_by_token_key(&token);
+        let has_key = self.has_key(&key).map_err(|_| ChannelError::Other {
+            description: format!("Reading the IBC denom failed: Key {key}"),
+        })?;
+        if !has_key {
+            self.write(&key, denom.as_ref()).map_err(|_| {
+                ChannelError::Other {
+                    description: format!(
+                        "Writing the denom failed: Key {key}",
+                    ),
+                }
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Check if the relayer fees are enabled for the channel
+    fn is_fee_enabled(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> Result<bool> {
+        let key = storage::fee_enabled_key(port_id, channel_id);
+        Ok(self.read::<bool>(&key)?.unwrap_or_default())
+    }
+
+    /// Get the max size in bytes of the memo of a transfer packet
+    fn max_memo_size(&self) -> Result<u64> {
+        let params: Option<IbcParameters> = self.read(&storage::params_key())?;
+        Ok(params.unwrap_or_default().max_memo_size)
+    }
+
+    /// Check if the packet forwarding is enabled
+    fn pfm_enabled(&self) -> Result<bool> {
+        let params: Option<IbcParameters> = self.read(&storage::params_key())?;
+        Ok(params.unwrap_or_default().pfm_enabled)
+    }
+
+    /// Check if the inbound transfers of the denom trace are refused
+    fn is_blocked_trace(&self, trace: &str) -> Result<bool> {
+        let key = storage::blocked_trace_key(storage::calc_hash(trace));
+        Ok(self.has_key(&key)?)
+    }
+
+    /// Get the relayer fees escrowed for the packet
+    fn packet_fee(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: Sequence,
+    ) -> Result<Option<PacketFee>> {
+        let key = storage::packet_fee_key(port_id, channel_id, sequence);
+        self.read(&key).map_err(ContextError::from)
+    }
+
+    /// Store the relayer fees escrowed for the packet
+    fn store_packet_fee(
+        &mut self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: Sequence,
+        fee: PacketFee,
+    ) -> Result<()> {
+        let key = storage::packet_fee_key(port_id, channel_id, sequence);
+        self.write(&key, fee).map_err(ContextError::from)
+    }
+
+    /// Delete the relayer fees escrowed for the packet
+    fn delete_packet_fee(
+        &mut self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: Sequence,
+    ) -> Result<()> {
+        let key = storage::packet_fee_key(port_id, channel_id, sequence);
+        self.delete(&key).map_err(ContextError::from)
+    }
+
+    /// Get the payee registered by the relayer to receive the relayer fees of
+    /// the packets relayed on the channel
+    fn payee(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        relayer: &Address,
+    ) -> Result<Option<Address>> {
+        let key = storage::payee_key(port_id, channel_id, relayer);
+        self.read(&key).map_err(ContextError::from)
+    }
+
+    /// Increase the amount of the token escrowed for the relayer fees
+    fn increase_fee_escrow_amount(
+        &mut self,
+        token: &Address,
+        amount: Amount,
+    ) -> Result<()> {
+        self.add_token_amount(&storage::fee_escrow_amount_key(token), amount)
+    }
+
+    /// Decrease the amount of the token escrowed for the relayer fees
+    fn decrease_fee_escrow_amount(
+        &mut self,
+        token: &Address,
+        amount: Amount,
+    ) -> Result<()> {
+        self.sub_token_amount(&storage::fee_escrow_amount_key(token), amount)
+    }
+
+    /// Get the token escrowed for the packet
+    fn packet_escrow(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: Sequence,
+    ) -> Result<Option<PacketEscrow>> {
+        let key = storage::packet_escrow_key(port_id, channel_id, sequence);
+        self.read(&key).map_err(ContextError::from)
+    }
+
+    /// Store the token escrowed for the packet
+    fn store_packet_escrow(
+        &mut self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: Sequence,
+        escrow: PacketEscrow,
+    ) -> Result<()> {
+        let key = storage::packet_escrow_key(port_id, channel_id, sequence);
+        self.write(&key, escrow).map_err(ContextError::from)
+    }
+
+    /// Get the received packet whose token has been forwarded by the packet
+    /// sent with the sequence
+    fn forwarded_packet(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: Sequence,
+    ) -> Result<Option<Packet>> {
+        let key = storage::forwarded_packet_key(port_id, channel_id, sequence);
+        match self.read_bytes(&key)? {
+            Some(value) => Packet::decode_vec(&value).map(Some).map_err(|_| {
+                ChannelError::Other {
+                    description: format!(
+                        "Decoding the forwarded packet failed: Key {key}",
+                    ),
+                }
+                .into()
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the received packet whose token is forwarded by the packet sent
+    /// with the sequence
+    fn store_forwarded_packet(
+        &mut self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: Sequence,
+        packet: Packet,
+    ) -> Result<()> {
+        let key = storage::forwarded_packet_key(port_id, channel_id, sequence);
+        self.write_bytes(&key, packet.encode_vec())
+            .map_err(ContextError::from)
+    }
+
+    /// Delete the received packet whose token has been forwarded by the
+    /// packet sent with the sequence
+    fn delete_forwarded_packet(
+        &mut self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: Sequence,
+    ) -> Result<()> {
+        let key = storage::forwarded_packet_key(port_id, channel_id, sequence);
+        self.delete(&key).map_err(ContextError::from)
+    }
+
+    /// Read an amount of the token for the rate limits
+    fn read_token_amount(&self, key: &Key) -> Result<Amount> {
+        Ok(self.read::<Amount>(key)?.unwrap_or_default())
+    }
+
+    /// Add the amount to the one stored with the key
+    fn add_token_amount(&mut self, key: &Key, amount: Amount) -> Result<()> {
+        let current = self.read_token_amount(key)?;
+        let new_amount =
+            current
+                .checked_add(amount)
+                .ok_or_else(|| ChannelError::Other {
+                    description: format!("The amount overflow: Key {key}"),
+                })?;
+        self.write(key, new_amount).map_err(ContextError::from)
+    }
+
+    /// Subtract the amount from the one stored with the key
+    fn sub_token_amount(&mut self, key: &Key, amount: Amount) -> Result<()> {
+        let current = self.read_token_amount(key)?;
+        let new_amount =
+            current
+                .checked_sub(amount)
+                .ok_or_else(|| ChannelError::Other {
+                    description: format!("The amount underflow: Key {key}"),
+                })?;
+        self.write(key, new_amount).map_err(ContextError::from)
+    }
+
+    /// Increase the amount of the token minted by IBC
+    fn increase_mint_amount(
+        &mut self,
+        token: &Address,
+        amount: Amount,
+    ) -> Result<()> {
+        self.add_token_amount(&storage::mint_amount_key(token), amount)
+    }
+
+    /// Decrease the amount of the token minted by IBC
+    fn decrease_mint_amount(
+        &mut self,
+        token: &Address,
+        amount: Amount,
+    ) -> Result<()> {
+        self.sub_token_amount(&storage::mint_amount_key(token), amount)
+    }
+
+    /// Increase the amount of the token escrowed by IBC transfers
+    fn increase_escrow_amount(
+        &mut self,
+        token: &Address,
+        amount: Amount,
+    ) -> Result<()> {
+        self.add_token_amount(&storage::escrow_amount_key(token), amount)
+    }
+
+    /// Decrease the amount of the token escrowed by IBC transfers
+    fn decrease_escrow_amount(
+        &mut self,
+        token: &Address,
+        amount: Amount,
+    ) -> Result<()> {
+        self.sub_token_amount(&storage::escrow_amount_key(token), amount)
+    }
+
+    /// Add the amount of the token deposited by IBC in the epoch
+    fn add_deposit(&mut self, token: &Address, amount: Amount) -> Result<()> {
+        self.add_token_amount(&storage::deposit_key(token), amount)
+    }
+
+    /// Add the amount of the token withdrawn by IBC in the epoch
+    fn add_withdraw(&mut self, token: &Address, amount: Amount) -> Result<()> {
+        self.add_token_amount(&storage::withdraw_key(token), amount)
+    }
+
+    /// Add the amount of the token deposited through the channel in the epoch
+    fn add_deposit_for_channel(
+        &mut self,
+        token: &Address,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        amount: Amount,
+    ) -> Result<()> {
+        let key = storage::deposit_key_for_channel(token, port_id, channel_id);
+        self.add_token_amount(&key, amount)
+    }
+
+    /// Add the amount of the token withdrawn through the channel in the epoch
+    fn add_withdraw_for_channel(
+        &mut self,
+        token: &Address,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        amount: Amount,
+    ) -> Result<()> {
+        let key = storage::withdraw_key_for_channel(token, port_id, channel_id);
+        self.add_token_amount(&key, amount)
+    }
 }
@@ -193,6 +193,10 @@ where
         path: &AckPath,
         ack_commitment: AcknowledgementCommitment,
     ) -> Result<(), ContextError> {
+        // The acknowledgement of the forwarded packet is pending
+        if self.is_forwarding() {
+            return Ok(());
+        }
         self.inner.borrow_mut().store_packet_ack(
             &path.port_id,
             &path.channel_id,
@@ -258,6 +262,11 @@ where
     }
 
     fn emit_ibc_event(&mut self, event: IbcEvent) -> Result<(), ContextError> {
+        if matches!(event, IbcEvent::WriteAcknowledgement(_))
+            && self.is_forwarding()
+        {
+            return Ok(());
+        }
         let event = event.try_into().expect("The event should be converted");
         self.inner
             .borrow_mut()
@@ -1,8 +1,10 @@
 //! IBC Contexts
 
+pub mod channel_mod;
 pub mod client;
 pub mod common;
 pub mod execution;
+pub mod pfm_mod;
 pub mod router;
 pub mod storage;
 pub mod token_transfer;
@@ -27,6 +29,8 @@ where
     pub inner: Rc<RefCell<C>>,
     /// Validation parameters for IBC VP
     pub validation_params: ValidationParams,
+    /// The onward transfer requested by the packet being received
+    pub(crate) forward: Option<Rc<RefCell<Option<pfm_mod::ForwardTransfer>>>>,
 }
 
 impl<C> IbcContext<C>
@@ -38,8 +42,19 @@ where
         Self {
             inner,
             validation_params: ValidationParams::default(),
+            forward: None,
         }
     }
+
+    /// Check if the token of the packet being received is forwarded. Its
+    /// acknowledgement is written when the onward transfer is acknowledged or
+    /// timed out.
+    pub(crate) fn is_forwarding(&self) -> bool {
+        self.forward
+            .as_ref()
+            .map(|forward| forward.borrow().is_some())
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Debug)]
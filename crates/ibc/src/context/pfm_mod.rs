@@ -0,0 +1,496 @@
+//! IBC module for token transfer with the packet forwarding
+
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
+
+use namada_core::address::{Address, InternalAddress};
+use namada_core::ibc::apps::transfer::types::packet::PacketData;
+use namada_core::ibc::apps::transfer::types::{
+    is_receiver_chain_source, Memo, PrefixedCoin, TracePrefix,
+};
+use namada_core::ibc::core::channel::types::acknowledgement::{
+    Acknowledgement, AcknowledgementStatus, StatusValue,
+};
+use namada_core::ibc::core::channel::types::channel::{
+    Counterparty, Order, State as ChannelState,
+};
+use namada_core::ibc::core::channel::types::error::{
+    ChannelError, PacketError,
+};
+use namada_core::ibc::core::channel::types::packet::Packet;
+use namada_core::ibc::core::channel::types::Version;
+use namada_core::ibc::core::host::types::identifiers::{
+    ChannelId, ConnectionId, PortId,
+};
+use namada_core::ibc::core::router::module::Module;
+use namada_core::ibc::core::router::types::module::{ModuleExtras, ModuleId};
+use namada_core::ibc::primitives::Signer;
+
+use super::common::IbcCommonContext;
+use super::transfer_mod::{ModuleWrapper, TransferModule};
+
+/// The default timeout of the onward transfer
+pub const DEFAULT_FORWARD_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The onward transfer of a received token requested by the forward memo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardTransfer {
+    /// The port on this chain to send the token
+    pub port_id: PortId,
+    /// The channel on this chain to send the token
+    pub channel_id: ChannelId,
+    /// The received token
+    pub token: PrefixedCoin,
+    /// The receiver on the next chain
+    pub receiver: Signer,
+    /// The memo of the onward transfer
+    pub memo: Memo,
+    /// The timeout of the onward transfer from the current block time
+    pub timeout: Duration,
+    /// The received packet, whose acknowledgement is pending until the
+    /// onward transfer is acknowledged or timed out
+    pub packet: Packet,
+}
+
+/// Get the token received on this chain by the packet and whether it has been
+/// minted, i.e. if this chain isn't the source of the token
+pub(crate) fn received_token(
+    packet: &Packet,
+    token: &PrefixedCoin,
+) -> (PrefixedCoin, bool) {
+    let mut token = token.clone();
+    if is_receiver_chain_source(
+        packet.port_id_on_a.clone(),
+        packet.chan_id_on_a.clone(),
+        &token.denom,
+    ) {
+        let prefix = TracePrefix::new(
+            packet.port_id_on_a.clone(),
+            packet.chan_id_on_a.clone(),
+        );
+        token.denom.remove_trace_prefix(&prefix);
+        (token, false)
+    } else {
+        let prefix = TracePrefix::new(
+            packet.port_id_on_b.clone(),
+            packet.chan_id_on_b.clone(),
+        );
+        token.denom.add_trace_prefix(prefix);
+        (token, true)
+    }
+}
+
+/// IBC module for token transfer with the packet forwarding.
+///
+/// When the `pfm_enabled` IBC parameter is set, a received packet with the
+/// memo `{"forward": {"receiver": .., "port": .., "channel": .., "timeout":
+/// .., "next": ..}}` is received to the IBC account, and the received token is
+/// sent from the IBC account to the receiver over the given channel in the
+/// same IBC execution. The timeout is in nanoseconds and the `next` memo is
+/// set to the memo of the onward transfer.
+///
+/// The acknowledgement of the received packet is pending until the onward
+/// transfer is acknowledged or timed out. If it succeeded, a successful
+/// acknowledgement is written. Otherwise, the refunded token is returned from
+/// the IBC account as it was received, and an error acknowledgement is written
+/// for the sender chain to refund the original sender.
+#[derive(Debug)]
+pub struct PfmModule<C>
+where
+    C: IbcCommonContext,
+{
+    transfer: TransferModule<C>,
+    forward: Rc<RefCell<Option<ForwardTransfer>>>,
+}
+
+impl<C> PfmModule<C>
+where
+    C: IbcCommonContext,
+{
+    /// Make a new module
+    pub fn new(ctx: Rc<RefCell<C>>) -> Self {
+        Self {
+            transfer: TransferModule::new(ctx),
+            forward: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Get the module ID
+    pub fn module_id(&self) -> ModuleId {
+        self.transfer.module_id()
+    }
+
+    /// Get the handle of the onward transfer requested by the last received
+    /// packet
+    pub(crate) fn forward_handle(
+        &self,
+    ) -> Rc<RefCell<Option<ForwardTransfer>>> {
+        self.forward.clone()
+    }
+
+    /// Get the onward transfer requested by the forward memo of the received
+    /// packet. Returns `None` if the packet isn't forwarded and the
+    /// description of the error if the forward memo is invalid.
+    fn forward_transfer(
+        &self,
+        packet: &Packet,
+    ) -> Result<Option<(PacketData, ForwardTransfer)>, String> {
+        // The invalid packet data is handled by the transfer module
+        let data = match serde_json::from_slice::<PacketData>(&packet.data) {
+            Ok(data) => data,
+            Err(_) => return Ok(None),
+        };
+        let memo =
+            match serde_json::from_str::<serde_json::Value>(data.memo.as_ref())
+            {
+                Ok(memo) => memo,
+                Err(_) => return Ok(None),
+            };
+        let forward = match memo.get("forward") {
+            Some(forward) => forward,
+            None => return Ok(None),
+        };
+        // The memo is ignored if the packet forwarding isn't enabled
+        match self.transfer.ctx.pfm_enabled() {
+            Ok(true) => {}
+            Ok(false) => return Ok(None),
+            Err(e) => {
+                return Err(format!(
+                    "Reading the packet forwarding parameter failed: {e}"
+                ));
+            }
+        }
+
+        let get_str = |field: &str| {
+            forward.get(field).and_then(|v| v.as_str()).ok_or_else(|| {
+                format!("The forward memo has no valid {field}: Memo {memo}")
+            })
+        };
+        let receiver = get_str("receiver")?;
+        if receiver.is_empty() {
+            return Err(format!(
+                "The forward memo has an empty receiver: Memo {memo}"
+            ));
+        }
+        let port_id = PortId::from_str(get_str("port")?).map_err(|e| {
+            format!("The forward port is invalid: Memo {memo}, Error {e}")
+        })?;
+        let channel_id =
+            ChannelId::from_str(get_str("channel")?).map_err(|e| {
+                format!(
+                    "The forward channel is invalid: Memo {memo}, Error {e}"
+                )
+            })?;
+        let timeout = match forward.get("timeout") {
+            Some(timeout) => {
+                timeout.as_u64().map(Duration::from_nanos).ok_or_else(|| {
+                    format!("The forward timeout is invalid: Memo {memo}")
+                })?
+            }
+            None => DEFAULT_FORWARD_TIMEOUT,
+        };
+        let next_memo = match forward.get("next") {
+            Some(serde_json::Value::String(next)) => next.clone(),
+            Some(next) => next.to_string(),
+            None => String::new(),
+        };
+
+        // The token can be forwarded only over an open transfer channel
+        if port_id != PortId::transfer() {
+            return Err(format!(
+                "The forward port isn't the transfer port: Port ID {port_id}"
+            ));
+        }
+        let channel_end = self
+            .transfer
+            .ctx
+            .channel_end(&port_id, &channel_id)
+            .map_err(|e| {
+                format!(
+                    "The forward channel doesn't exist: Port ID {port_id}, \
+                     Channel ID {channel_id}, Error {e}"
+                )
+            })?;
+        if !channel_end.state_matches(&ChannelState::Open) {
+            return Err(format!(
+                "The forward channel isn't open: Port ID {port_id}, Channel \
+                 ID {channel_id}"
+            ));
+        }
+
+        let (token, _is_minted) = received_token(packet, &data.token);
+        let forward = ForwardTransfer {
+            port_id,
+            channel_id,
+            token,
+            receiver: receiver.to_string().into(),
+            memo: next_memo.into(),
+            timeout,
+            packet: packet.clone(),
+        };
+        Ok(Some((data, forward)))
+    }
+}
+
+impl<C> ModuleWrapper for PfmModule<C>
+where
+    C: IbcCommonContext + Debug,
+{
+    fn as_module(&self) -> &dyn Module {
+        self
+    }
+
+    fn as_module_mut(&mut self) -> &mut dyn Module {
+        self
+    }
+}
+
+impl<C> Module for PfmModule<C>
+where
+    C: IbcCommonContext + Debug,
+{
+    #[allow(clippy::too_many_arguments)]
+    fn on_chan_open_init_validate(
+        &self,
+        order: Order,
+        connection_hops: &[ConnectionId],
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        counterparty: &Counterparty,
+        version: &Version,
+    ) -> Result<Version, ChannelError> {
+        self.transfer.on_chan_open_init_validate(
+            order,
+            connection_hops,
+            port_id,
+            channel_id,
+            counterparty,
+            version,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn on_chan_open_init_execute(
+        &mut self,
+        order: Order,
+        connection_hops: &[ConnectionId],
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        counterparty: &Counterparty,
+        version: &Version,
+    ) -> Result<(ModuleExtras, Version), ChannelError> {
+        self.transfer.on_chan_open_init_execute(
+            order,
+            connection_hops,
+            port_id,
+            channel_id,
+            counterparty,
+            version,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn on_chan_open_try_validate(
+        &self,
+        order: Order,
+        connection_hops: &[ConnectionId],
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        counterparty: &Counterparty,
+        counterparty_version: &Version,
+    ) -> Result<Version, ChannelError> {
+        self.transfer.on_chan_open_try_validate(
+            order,
+            connection_hops,
+            port_id,
+            channel_id,
+            counterparty,
+            counterparty_version,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn on_chan_open_try_execute(
+        &mut self,
+        order: Order,
+        connection_hops: &[ConnectionId],
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        counterparty: &Counterparty,
+        counterparty_version: &Version,
+    ) -> Result<(ModuleExtras, Version), ChannelError> {
+        self.transfer.on_chan_open_try_execute(
+            order,
+            connection_hops,
+            port_id,
+            channel_id,
+            counterparty,
+            counterparty_version,
+        )
+    }
+
+    fn on_chan_open_ack_validate(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        counterparty_version: &Version,
+    ) -> Result<(), ChannelError> {
+        self.transfer.on_chan_open_ack_validate(
+            port_id,
+            channel_id,
+            counterparty_version,
+        )
+    }
+
+    fn on_chan_open_ack_execute(
+        &mut self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        counterparty_version: &Version,
+    ) -> Result<ModuleExtras, ChannelError> {
+        self.transfer.on_chan_open_ack_execute(
+            port_id,
+            channel_id,
+            counterparty_version,
+        )
+    }
+
+    fn on_chan_open_confirm_validate(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> Result<(), ChannelError> {
+        self.transfer
+            .on_chan_open_confirm_validate(port_id, channel_id)
+    }
+
+    fn on_chan_open_confirm_execute(
+        &mut self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> Result<ModuleExtras, ChannelError> {
+        self.transfer
+            .on_chan_open_confirm_execute(port_id, channel_id)
+    }
+
+    fn on_chan_close_init_validate(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> Result<(), ChannelError> {
+        self.transfer
+            .on_chan_close_init_validate(port_id, channel_id)
+    }
+
+    fn on_chan_close_init_execute(
+        &mut self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> Result<ModuleExtras, ChannelError> {
+        self.transfer
+            .on_chan_close_init_execute(port_id, channel_id)
+    }
+
+    fn on_chan_close_confirm_validate(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> Result<(), ChannelError> {
+        self.transfer
+            .on_chan_close_confirm_validate(port_id, channel_id)
+    }
+
+    fn on_chan_close_confirm_execute(
+        &mut self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> Result<ModuleExtras, ChannelError> {
+        self.transfer
+            .on_chan_close_confirm_execute(port_id, channel_id)
+    }
+
+    fn on_recv_packet_execute(
+        &mut self,
+        packet: &Packet,
+        relayer: &Signer,
+    ) -> (ModuleExtras, Acknowledgement) {
+        self.forward.replace(None);
+        let (mut data, forward) = match self.forward_transfer(packet) {
+            Ok(Some(forward)) => forward,
+            Ok(None) => {
+                return self.transfer.on_recv_packet_execute(packet, relayer);
+            }
+            Err(description) => {
+                let status = StatusValue::new(description)
+                    .expect("The status shouldn't be empty");
+                return (
+                    ModuleExtras::empty(),
+                    AcknowledgementStatus::error(status).into(),
+                );
+            }
+        };
+
+        // Receive the token to the IBC account to send it onward
+        data.receiver =
+            Address::Internal(InternalAddress::Ibc).to_string().into();
+        let mut received_packet = packet.clone();
+        received_packet.data = serde_json::to_vec(&data)
+            .expect("Encoding the packet data shouldn't fail");
+        let (extras, ack) = self
+            .transfer
+            .on_recv_packet_execute(&received_packet, relayer);
+        let is_successful =
+            serde_json::from_slice::<AcknowledgementStatus>(ack.as_ref())
+                .map(|status| status.is_successful())
+                .unwrap_or_default();
+        if is_successful {
+            self.forward.replace(Some(forward));
+        }
+        (extras, ack)
+    }
+
+    fn on_acknowledgement_packet_validate(
+        &self,
+        packet: &Packet,
+        acknowledgement: &Acknowledgement,
+        relayer: &Signer,
+    ) -> Result<(), PacketError> {
+        self.transfer.on_acknowledgement_packet_validate(
+            packet,
+            acknowledgement,
+            relayer,
+        )
+    }
+
+    fn on_acknowledgement_packet_execute(
+        &mut self,
+        packet: &Packet,
+        acknowledgement: &Acknowledgement,
+        relayer: &Signer,
+    ) -> (ModuleExtras, Result<(), PacketError>) {
+        self.transfer.on_acknowledgement_packet_execute(
+            packet,
+            acknowledgement,
+            relayer,
+        )
+    }
+
+    fn on_timeout_packet_validate(
+        &self,
+        packet: &Packet,
+        relayer: &Signer,
+    ) -> Result<(), PacketError> {
+        self.transfer.on_timeout_packet_validate(packet, relayer)
+    }
+
+    fn on_timeout_packet_execute(
+        &mut self,
+        packet: &Packet,
+        relayer: &Signer,
+    ) -> (ModuleExtras, Result<(), PacketError>) {
+        self.transfer.on_timeout_packet_execute(packet, relayer)
+    }
+}
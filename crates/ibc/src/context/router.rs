@@ -8,21 +8,32 @@ use namada_core::ibc::core::router::module::Module;
 use namada_core::ibc::core::router::router::Router;
 use namada_core::ibc::core::router::types::module::ModuleId;
 
+use super::channel_mod::ChannelModule;
 use super::super::ModuleWrapper;
 
 /// IBC router
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct IbcRouter<'a> {
     modules: HashMap<ModuleId, Rc<dyn ModuleWrapper + 'a>>,
     ports: HashMap<PortId, ModuleId>,
+    /// The module for the ports without an application module
+    default_module_id: ModuleId,
 }
 
 impl<'a> IbcRouter<'a> {
-    /// Make new Router
+    /// Make new Router. The channels on the ports without an application
+    /// module are routed to the [`ChannelModule`] which only allows closing
+    /// them.
     pub fn new() -> Self {
+        let module = ChannelModule;
+        let default_module_id = module.module_id();
+        let mut modules: HashMap<ModuleId, Rc<dyn ModuleWrapper + 'a>> =
+            HashMap::new();
+        modules.insert(default_module_id.clone(), Rc::new(module));
         Self {
-            modules: HashMap::new(),
+            modules,
             ports: HashMap::new(),
+            default_module_id,
         }
     }
 
@@ -53,6 +64,17 @@ impl<'a> Router for IbcRouter<'a> {
     }
 
     fn lookup_module(&self, port_id: &PortId) -> Option<ModuleId> {
-        self.ports.get(port_id).cloned()
+        Some(
+            self.ports
+                .get(port_id)
+                .cloned()
+                .unwrap_or_else(|| self.default_module_id.clone()),
+        )
+    }
+}
+
+impl<'a> Default for IbcRouter<'a> {
+    fn default() -> Self {
+        Self::new()
     }
 }
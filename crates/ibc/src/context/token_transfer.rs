@@ -9,14 +9,18 @@ use namada_core::ibc::apps::transfer::context::{
 };
 use namada_core::ibc::apps::transfer::types::error::TokenTransferError;
 use namada_core::ibc::apps::transfer::types::{PrefixedCoin, PrefixedDenom};
+use namada_core::ibc::core::channel::types::channel::ChannelEnd;
 use namada_core::ibc::core::channel::types::error::ChannelError;
 use namada_core::ibc::core::handler::types::error::ContextError;
 use namada_core::ibc::core::host::types::identifiers::{ChannelId, PortId};
+use namada_core::ibc::PacketEscrow;
 use namada_core::token;
 use namada_core::uint::Uint;
+use namada_storage::StorageRead;
 use namada_token::read_denom;
 
 use super::common::IbcCommonContext;
+use crate::parameters::read_min_transfer_amount;
 use crate::storage;
 
 /// Token transfer context to handle tokens
@@ -26,6 +30,11 @@ where
     C: IbcCommonContext,
 {
     inner: Rc<RefCell<C>>,
+    /// The port and the channel on this chain of the current transfer
+    channel: Option<(PortId, ChannelId)>,
+    /// Whether the current transfer sends a received token onward from the
+    /// IBC account
+    is_forwarding: bool,
 }
 
 impl<C> TokenTransferContext<C>
@@ -34,42 +43,161 @@ where
 {
     /// Make new token transfer context
     pub fn new(inner: Rc<RefCell<C>>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            channel: None,
+            is_forwarding: false,
+        }
+    }
+
+    /// Set the port and the channel on this chain of the current transfer to
+    /// record the per-channel throughput
+    pub fn set_channel(&mut self, port_id: PortId, channel_id: ChannelId) {
+        self.channel = Some((port_id, channel_id));
+    }
+
+    /// Set that the current transfer sends a received token onward from the
+    /// IBC account
+    pub fn set_forwarding(&mut self) {
+        self.is_forwarding = true;
+    }
+
+    /// Get the max size in bytes of the memo of a transfer packet
+    pub fn max_memo_size(&self) -> Result<u64, ContextError> {
+        self.inner.borrow().max_memo_size()
+    }
+
+    /// Check if the packet forwarding is enabled
+    pub fn pfm_enabled(&self) -> Result<bool, ContextError> {
+        self.inner.borrow().pfm_enabled()
+    }
+
+    /// Check if the inbound transfers of the denom trace are refused
+    pub fn is_blocked_trace(&self, trace: &str) -> Result<bool, ContextError> {
+        self.inner.borrow().is_blocked_trace(trace)
+    }
+
+    /// Get the min amount of a transfer of the token
+    pub fn min_transfer_amount(
+        &self,
+        token: &Address,
+    ) -> Result<token::Amount, ContextError> {
+        read_min_transfer_amount(&*self.inner.borrow(), token)
+            .map_err(ContextError::from)
+    }
+
+    /// Get the channel end
+    pub fn channel_end(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> Result<ChannelEnd, ContextError> {
+        self.inner.borrow().channel_end(port_id, channel_id)
+    }
+
+    /// Add the deposit of the token for the rate limits
+    fn add_deposit(
+        &self,
+        token: &Address,
+        amount: token::Amount,
+    ) -> Result<(), ContextError> {
+        let mut inner = self.inner.borrow_mut();
+        inner.add_deposit(token, amount)?;
+        if let Some((port_id, channel_id)) = &self.channel {
+            inner
+                .add_deposit_for_channel(token, port_id, channel_id, amount)?;
+        }
+        Ok(())
+    }
+
+    /// Add the withdrawal of the token for the rate limits
+    fn add_withdraw(
+        &self,
+        token: &Address,
+        amount: token::Amount,
+    ) -> Result<(), ContextError> {
+        let mut inner = self.inner.borrow_mut();
+        inner.add_withdraw(token, amount)?;
+        if let Some((port_id, channel_id)) = &self.channel {
+            inner
+                .add_withdraw_for_channel(token, port_id, channel_id, amount)?;
+        }
+        Ok(())
+    }
+
+    /// Increase the escrowed amount of the token and record the escrow for
+    /// the packet to be sent with the next sequence
+    fn escrow_packet_token(
+        &self,
+        token: &Address,
+        amount: token::Amount,
+    ) -> Result<(), ContextError> {
+        self.inner
+            .borrow_mut()
+            .increase_escrow_amount(token, amount)?;
+        self.record_packet_escrow(token, amount)
+    }
+
+    /// Record the escrow for the packet to be sent with the next sequence
+    fn record_packet_escrow(
+        &self,
+        token: &Address,
+        amount: token::Amount,
+    ) -> Result<(), ContextError> {
+        let mut inner = self.inner.borrow_mut();
+        if let Some((port_id, channel_id)) = &self.channel {
+            let sequence = inner.get_next_sequence_send(port_id, channel_id)?;
+            let escrow = PacketEscrow {
+                token: token.clone(),
+                amount,
+            };
+            inner.store_packet_escrow(port_id, channel_id, sequence, escrow)?;
+        }
+        Ok(())
     }
 
     /// Get the token address and the amount from PrefixedCoin. If the base
     /// denom is not an address, it returns `IbcToken`
-    fn get_token_amount(
+    pub(crate) fn get_token_amount(
         &self,
         coin: &PrefixedCoin,
     ) -> Result<(Address, token::DenominatedAmount), TokenTransferError> {
-        let token = match Address::decode(coin.denom.base_denom.as_str()) {
-            Ok(token_addr) if coin.denom.trace_path.is_empty() => token_addr,
-            _ => storage::ibc_token(coin.denom.to_string()),
-        };
-
-        // Convert IBC amount to Namada amount for the token
-        let denom = read_denom(&*self.inner.borrow(), &token)
-            .map_err(ContextError::from)?
-            .unwrap_or(token::Denomination(0));
-        let uint_amount = Uint(primitive_types::U256::from(coin.amount).0);
-        let amount =
-            token::Amount::from_uint(uint_amount, denom).map_err(|e| {
-                TokenTransferError::ContextError(
-                    ChannelError::Other {
-                        description: format!(
-                            "The IBC amount is invalid: Coin {coin}, Error {e}",
-                        ),
-                    }
-                    .into(),
-                )
-            })?;
-        let amount = token::DenominatedAmount::new(amount, denom);
-
-        Ok((token, amount))
+        token_amount(&*self.inner.borrow(), coin)
     }
 }
 
+/// Get the token address and the amount from PrefixedCoin. If the base denom is
+/// not an address, it returns `IbcToken`. The IBC amount is converted to the
+/// amount of the token with its denomination.
+pub fn token_amount<S: StorageRead>(
+    storage: &S,
+    coin: &PrefixedCoin,
+) -> Result<(Address, token::DenominatedAmount), TokenTransferError> {
+    let token = match Address::decode(coin.denom.base_denom.as_str()) {
+        Ok(token_addr) if coin.denom.trace_path.is_empty() => token_addr,
+        _ => storage::ibc_token(coin.denom.to_string()),
+    };
+
+    // Convert IBC amount to Namada amount for the token
+    let denom = read_denom(storage, &token)
+        .map_err(ContextError::from)?
+        .unwrap_or(token::Denomination(0));
+    let uint_amount = Uint(primitive_types::U256::from(coin.amount).0);
+    let amount = token::Amount::from_uint(uint_amount, denom).map_err(|e| {
+        TokenTransferError::ContextError(
+            ChannelError::Other {
+                description: format!(
+                    "The IBC amount is invalid: Coin {coin}, Error {e}",
+                ),
+            }
+            .into(),
+        )
+    })?;
+    let amount = token::DenominatedAmount::new(amount, denom);
+
+    Ok((token, amount))
+}
+
 impl<C> TokenTransferValidationContext for TokenTransferContext<C>
 where
     C: IbcCommonContext,
@@ -143,6 +271,34 @@ where
         // has no prefix
         let (ibc_token, amount) = self.get_token_amount(coin)?;
 
+        // Record the throughput for the rate limits. A forwarded token is
+        // escrowed from the IBC account itself.
+        let escrow = Address::Internal(InternalAddress::Ibc);
+        if *from == escrow && !self.is_forwarding {
+            self.add_deposit(&ibc_token, amount.amount())?;
+        } else if *to == escrow {
+            self.add_withdraw(&ibc_token, amount.amount())?;
+        }
+
+        // The balance of the IBC account doesn't change when the forwarded
+        // token is received to or sent from it. The token sent onward has
+        // been escrowed when it was received, but its packet is recorded.
+        if from == to {
+            if *to == escrow && self.is_forwarding {
+                self.record_packet_escrow(&ibc_token, amount.amount())?;
+            }
+            return Ok(());
+        }
+
+        // Record the escrow for the audit
+        if *to == escrow {
+            self.escrow_packet_token(&ibc_token, amount.amount())?;
+        } else if *from == escrow {
+            self.inner
+                .borrow_mut()
+                .decrease_escrow_amount(&ibc_token, amount.amount())?;
+        }
+
         self.inner
             .borrow_mut()
             .transfer_token(from, to, &ibc_token, amount)
@@ -157,6 +313,17 @@ where
         // The trace path of the denom is already updated if receiving the token
         let (ibc_token, amount) = self.get_token_amount(coin)?;
 
+        self.inner
+            .borrow_mut()
+            .increase_mint_amount(&ibc_token, amount.amount())?;
+        self.add_deposit(&ibc_token, amount.amount())?;
+
+        if *account == Address::Internal(InternalAddress::Ibc) {
+            self.inner
+                .borrow_mut()
+                .increase_escrow_amount(&ibc_token, amount.amount())?;
+        }
+
         self.inner
             .borrow_mut()
             .mint_token(account, &ibc_token, amount)
@@ -170,6 +337,17 @@ where
     ) -> Result<(), TokenTransferError> {
         let (ibc_token, amount) = self.get_token_amount(coin)?;
 
+        self.inner
+            .borrow_mut()
+            .decrease_mint_amount(&ibc_token, amount.amount())?;
+        self.add_withdraw(&ibc_token, amount.amount())?;
+
+        if *account == Address::Internal(InternalAddress::Ibc) {
+            self.inner
+                .borrow_mut()
+                .decrease_escrow_amount(&ibc_token, amount.amount())?;
+        }
+
         // The burn is "unminting" from the minted balance
         self.inner
             .borrow_mut()
@@ -16,8 +16,14 @@ use namada_core::ibc::apps::transfer::module::{
     on_timeout_packet_validate,
 };
 use namada_core::ibc::apps::transfer::types::error::TokenTransferError;
-use namada_core::ibc::apps::transfer::types::MODULE_ID_STR;
-use namada_core::ibc::core::channel::types::acknowledgement::Acknowledgement;
+use namada_core::ibc::apps::transfer::types::packet::PacketData;
+use namada_core::ibc::apps::transfer::types::{
+    is_receiver_chain_source, PrefixedCoin, PrefixedDenom, TracePrefix,
+    MODULE_ID_STR,
+};
+use namada_core::ibc::core::channel::types::acknowledgement::{
+    Acknowledgement, AcknowledgementStatus, StatusValue,
+};
 use namada_core::ibc::core::channel::types::channel::{Counterparty, Order};
 use namada_core::ibc::core::channel::types::error::{
     ChannelError, PacketError,
@@ -68,6 +74,94 @@ where
     pub fn module_id(&self) -> ModuleId {
         ModuleId::new(MODULE_ID_STR.to_string())
     }
+
+    /// Returns an error acknowledgement if the memo of the received packet
+    /// exceeds the max size
+    fn check_memo_size(&self, packet: &Packet) -> Option<Acknowledgement> {
+        // The invalid packet data is handled by the transfer module
+        let data = serde_json::from_slice::<PacketData>(&packet.data).ok()?;
+        let size = data.memo.as_ref().len() as u64;
+        let description = match self.ctx.max_memo_size() {
+            Ok(max_size) if size <= max_size => return None,
+            Ok(max_size) => format!(
+                "The memo exceeds the max size: Size {size}, Max size \
+                 {max_size}"
+            ),
+            Err(e) => format!("Reading the max memo size failed: {e}"),
+        };
+        let status = StatusValue::new(description)
+            .expect("The status shouldn't be empty");
+        Some(AcknowledgementStatus::error(status).into())
+    }
+
+    /// Returns an error acknowledgement if the denom trace of the received
+    /// token on this chain is blocked
+    fn check_blocked_trace(&self, packet: &Packet) -> Option<Acknowledgement> {
+        // The invalid packet data is handled by the transfer module
+        let data = serde_json::from_slice::<PacketData>(&packet.data).ok()?;
+        let trace = received_denom(packet, data.token.denom).to_string();
+        let description = match self.ctx.is_blocked_trace(&trace) {
+            Ok(false) => return None,
+            Ok(true) => format!("The denom trace is blocked: {trace}"),
+            Err(e) => format!("Reading the blocked denom trace failed: {e}"),
+        };
+        let status = StatusValue::new(description)
+            .expect("The status shouldn't be empty");
+        Some(AcknowledgementStatus::error(status).into())
+    }
+
+    /// Returns an error acknowledgement if the received amount is below the
+    /// min transfer amount of the token on this chain
+    fn check_min_transfer_amount(
+        &self,
+        packet: &Packet,
+    ) -> Option<Acknowledgement> {
+        // The invalid packet data and amount are handled by the transfer
+        // module
+        let data = serde_json::from_slice::<PacketData>(&packet.data).ok()?;
+        let coin = PrefixedCoin {
+            denom: received_denom(packet, data.token.denom),
+            amount: data.token.amount,
+        };
+        let (token, amount) = self.ctx.get_token_amount(&coin).ok()?;
+        let amount = amount.amount();
+        let description = match self.ctx.min_transfer_amount(&token) {
+            Ok(min_amount) if amount >= min_amount => return None,
+            Ok(min_amount) => format!(
+                "The transfer amount is below the min amount: Token {token}, \
+                 Amount {amount}, Min amount {min_amount}"
+            ),
+            Err(e) => format!("Reading the min transfer amount failed: {e}"),
+        };
+        let status = StatusValue::new(description)
+            .expect("The status shouldn't be empty");
+        Some(AcknowledgementStatus::error(status).into())
+    }
+}
+
+/// Returns the denom of the received token on this chain
+fn received_denom(
+    packet: &Packet,
+    mut ibc_denom: PrefixedDenom,
+) -> PrefixedDenom {
+    if is_receiver_chain_source(
+        packet.port_id_on_a.clone(),
+        packet.chan_id_on_a.clone(),
+        &ibc_denom,
+    ) {
+        let prefix = TracePrefix::new(
+            packet.port_id_on_a.clone(),
+            packet.chan_id_on_a.clone(),
+        );
+        ibc_denom.remove_trace_prefix(&prefix);
+    } else {
+        let prefix = TracePrefix::new(
+            packet.port_id_on_b.clone(),
+            packet.chan_id_on_b.clone(),
+        );
+        ibc_denom.add_trace_prefix(prefix);
+    }
+    ibc_denom
 }
 
 impl<C> ModuleWrapper for TransferModule<C>
@@ -266,6 +360,19 @@ where
         packet: &Packet,
         _relayer: &Signer,
     ) -> (ModuleExtras, Acknowledgement) {
+        if let Some(ack) = self.check_memo_size(packet) {
+            return (ModuleExtras::empty(), ack);
+        }
+        if let Some(ack) = self.check_blocked_trace(packet) {
+            return (ModuleExtras::empty(), ack);
+        }
+        if let Some(ack) = self.check_min_transfer_amount(packet) {
+            return (ModuleExtras::empty(), ack);
+        }
+        self.ctx.set_channel(
+            packet.port_id_on_b.clone(),
+            packet.chan_id_on_b.clone(),
+        );
         on_recv_packet_execute(&mut self.ctx, packet)
     }
 
@@ -290,6 +397,10 @@ where
         acknowledgement: &Acknowledgement,
         relayer: &Signer,
     ) -> (ModuleExtras, Result<(), PacketError>) {
+        self.ctx.set_channel(
+            packet.port_id_on_a.clone(),
+            packet.chan_id_on_a.clone(),
+        );
         let (extras, result) = on_acknowledgement_packet_execute(
             &mut self.ctx,
             packet,
@@ -313,6 +424,10 @@ where
         packet: &Packet,
         relayer: &Signer,
     ) -> (ModuleExtras, Result<(), PacketError>) {
+        self.ctx.set_channel(
+            packet.port_id_on_a.clone(),
+            packet.chan_id_on_a.clone(),
+        );
         let (extras, result) =
             on_timeout_packet_execute(&mut self.ctx, packet, relayer);
         (extras, result.map_err(into_packet_error))
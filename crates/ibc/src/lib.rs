@@ -1,8 +1,11 @@
 //! IBC library code
 
 mod actions;
+pub mod audit;
 pub mod context;
+pub mod parameters;
 pub mod storage;
+pub mod tokens;
 
 use std::cell::RefCell;
 use std::fmt::Debug;
@@ -11,33 +14,53 @@ use std::str::FromStr;
 
 pub use actions::transfer_over_ibc;
 use borsh::BorshDeserialize;
+pub use context::channel_mod::ChannelModule;
 pub use context::common::IbcCommonContext;
+use context::pfm_mod::received_token;
+pub use context::pfm_mod::PfmModule;
 use context::router::IbcRouter;
 pub use context::storage::{IbcStorageContext, ProofSpec};
-pub use context::token_transfer::TokenTransferContext;
+pub use context::token_transfer::{token_amount, TokenTransferContext};
 pub use context::transfer_mod::{ModuleWrapper, TransferModule};
 use context::IbcContext;
 pub use context::ValidationParams;
-use namada_core::address::{Address, MASP};
+use namada_core::address::{Address, InternalAddress, IBC_FEE_ESCROW, MASP};
+use namada_core::ibc::apps::transfer::context::TokenTransferExecutionContext;
 use namada_core::ibc::apps::transfer::handler::{
     send_transfer_execute, send_transfer_validate,
 };
 use namada_core::ibc::apps::transfer::types::error::TokenTransferError;
 use namada_core::ibc::apps::transfer::types::msgs::transfer::MsgTransfer;
+use namada_core::ibc::apps::transfer::types::packet::PacketData;
 use namada_core::ibc::apps::transfer::types::{
-    is_receiver_chain_source, PrefixedDenom, TracePrefix,
+    ack_success_b64, is_receiver_chain_source, PrefixedDenom, TracePrefix,
 };
+use namada_core::ibc::core::channel::types::acknowledgement::{
+    Acknowledgement, AcknowledgementStatus, StatusValue,
+};
+use namada_core::ibc::core::channel::types::commitment::compute_ack_commitment;
+use namada_core::ibc::core::channel::types::events::WriteAcknowledgement;
 use namada_core::ibc::core::channel::types::msgs::PacketMsg;
+use namada_core::ibc::core::channel::types::packet::Packet;
+use namada_core::ibc::core::channel::types::timeout::TimeoutHeight;
+use namada_core::ibc::core::client::types::msgs::ClientMsg;
 use namada_core::ibc::core::entrypoint::{execute, validate};
 use namada_core::ibc::core::handler::types::error::ContextError;
+use namada_core::ibc::core::handler::types::events::{
+    IbcEvent as RawIbcEvent, MessageEvent,
+};
 use namada_core::ibc::core::handler::types::msgs::MsgEnvelope;
 use namada_core::ibc::core::host::types::error::IdentifierError;
-use namada_core::ibc::core::host::types::identifiers::{ChannelId, PortId};
+use namada_core::ibc::core::host::types::identifiers::{
+    ChannelId, PortId, Sequence,
+};
+use namada_core::ibc::core::host::ExecutionContext;
 use namada_core::ibc::core::router::types::error::RouterError;
 use namada_core::ibc::core::router::types::module::ModuleId;
 use namada_core::ibc::primitives::proto::Any;
 pub use namada_core::ibc::*;
 use namada_core::masp::PaymentAddress;
+use namada_core::token::DenominatedAmount;
 use prost::Message;
 use thiserror::Error;
 
@@ -58,8 +81,44 @@ pub enum Error {
     ChainId(IdentifierError),
     #[error("Handling MASP transaction error: {0}")]
     MaspTx(String),
+    #[error("Relayer fee error: {0}")]
+    Fee(String),
+    #[error("Packet forwarding error: {0}")]
+    Forward(String),
+    #[error(
+        "Unsupported IBC message {0}. The supported messages are: {}",
+        SUPPORTED_MESSAGE_TYPE_URLS.join(", ")
+    )]
+    UnsupportedMessage(String),
 }
 
+/// The type URLs of the IBC messages which can be handled. The messages of the
+/// other IBC modules, e.g. the channel upgrade, are refused before execution.
+pub const SUPPORTED_MESSAGE_TYPE_URLS: &[&str] = &[
+    "/ibc.applications.transfer.v1.MsgTransfer",
+    "/ibc.core.client.v1.MsgCreateClient",
+    "/ibc.core.client.v1.MsgUpdateClient",
+    "/ibc.core.client.v1.MsgUpgradeClient",
+    "/ibc.core.client.v1.MsgSubmitMisbehaviour",
+    "/ibc.core.connection.v1.MsgConnectionOpenInit",
+    "/ibc.core.connection.v1.MsgConnectionOpenTry",
+    "/ibc.core.connection.v1.MsgConnectionOpenAck",
+    "/ibc.core.connection.v1.MsgConnectionOpenConfirm",
+    "/ibc.core.channel.v1.MsgChannelOpenInit",
+    "/ibc.core.channel.v1.MsgChannelOpenTry",
+    "/ibc.core.channel.v1.MsgChannelOpenAck",
+    "/ibc.core.channel.v1.MsgChannelOpenConfirm",
+    "/ibc.core.channel.v1.MsgChannelCloseInit",
+    "/ibc.core.channel.v1.MsgChannelCloseConfirm",
+    "/ibc.core.channel.v1.MsgRecvPacket",
+    "/ibc.core.channel.v1.MsgAcknowledgement",
+    "/ibc.core.channel.v1.MsgTimeout",
+    "/ibc.core.channel.v1.MsgTimeoutOnClose",
+];
+
+/// The prefix of the type URLs of the IBC messages
+const IBC_MESSAGE_TYPE_URL_PREFIX: &str = "/ibc.";
+
 /// IBC actions to handle IBC operations
 #[derive(Debug)]
 pub struct IbcActions<'a, C>
@@ -91,6 +150,15 @@ where
         self.router.add_transfer_module(module_id, module)
     }
 
+    /// Add TokenTransfer route with the packet forwarding
+    pub fn add_pfm_module(&mut self, module: PfmModule<C>)
+    where
+        C: 'a,
+    {
+        self.ctx.forward = Some(module.forward_handle());
+        self.router.add_transfer_module(module.module_id(), module)
+    }
+
     /// Set the validation parameters
     pub fn set_validation_params(&mut self, params: ValidationParams) {
         self.ctx.validation_params = params;
@@ -103,6 +171,10 @@ where
             IbcMessage::Transfer(msg) => {
                 let mut token_transfer_ctx =
                     TokenTransferContext::new(self.ctx.inner.clone());
+                token_transfer_ctx.set_channel(
+                    msg.port_id_on_a.clone(),
+                    msg.chan_id_on_a.clone(),
+                );
                 send_transfer_execute(
                     &mut self.ctx,
                     &mut token_transfer_ctx,
@@ -113,6 +185,10 @@ where
             IbcMessage::ShieldedTransfer(msg) => {
                 let mut token_transfer_ctx =
                     TokenTransferContext::new(self.ctx.inner.clone());
+                token_transfer_ctx.set_channel(
+                    msg.message.port_id_on_a.clone(),
+                    msg.message.chan_id_on_a.clone(),
+                );
                 send_transfer_execute(
                     &mut self.ctx,
                     &mut token_transfer_ctx,
@@ -121,18 +197,68 @@ where
                 .map_err(Error::TokenTransfer)?;
                 self.handle_masp_tx(message)
             }
+            IbcMessage::TransferWithFee(msg) => {
+                let port_id = &msg.message.port_id_on_a;
+                let channel_id = &msg.message.chan_id_on_a;
+                self.check_fee(&msg.message, &msg.fee)?;
+                // The packet will be sent with the current next sequence
+                let sequence = self
+                    .ctx
+                    .inner
+                    .borrow()
+                    .get_next_sequence_send(port_id, channel_id)
+                    .map_err(|e| Error::Context(Box::new(e)))?;
+                let mut token_transfer_ctx =
+                    TokenTransferContext::new(self.ctx.inner.clone());
+                token_transfer_ctx
+                    .set_channel(port_id.clone(), channel_id.clone());
+                send_transfer_execute(
+                    &mut self.ctx,
+                    &mut token_transfer_ctx,
+                    msg.message.clone(),
+                )
+                .map_err(Error::TokenTransfer)?;
+                self.escrow_packet_fee(port_id, channel_id, sequence, &msg.fee)
+            }
             IbcMessage::Envelope(envelope) => {
                 execute(&mut self.ctx, &mut self.router, envelope.clone())
                     .map_err(|e| Error::Context(Box::new(e)))?;
+                // Prune the expired consensus states of the updated client
+                self.prune_consensus_states(envelope)?;
                 // the current ibc-rs execution doesn't store the denom for the
                 // token hash when transfer with MsgRecvPacket
                 self.store_denom(envelope)?;
+                // Send the received token onward if the packet has a forward
+                // memo
+                self.forward_transfer()?;
+                // Pay the relayer fees when the packet has been acknowledged
+                // or timed out
+                self.pay_packet_fee(envelope)?;
+                // Acknowledge the received packet whose token has been
+                // forwarded by the acknowledged or timed-out packet
+                self.resolve_forward(envelope)?;
                 // For receiving the token to a shielded address
                 self.handle_masp_tx(message)
             }
         }
     }
 
+    /// Prune the expired consensus states of the client updated by
+    /// MsgUpdateClient
+    fn prune_consensus_states(
+        &mut self,
+        envelope: &MsgEnvelope,
+    ) -> Result<(), Error> {
+        if let MsgEnvelope::Client(ClientMsg::UpdateClient(msg)) = envelope {
+            self.ctx
+                .inner
+                .borrow_mut()
+                .prune_expired_consensus_states(&msg.client_id)
+                .map_err(|e| Error::Context(Box::new(e)))?;
+        }
+        Ok(())
+    }
+
     /// Store the denom when transfer with MsgRecvPacket
     fn store_denom(&mut self, envelope: &MsgEnvelope) -> Result<(), Error> {
         if let MsgEnvelope::Packet(PacketMsg::Recv(_)) = envelope {
@@ -154,6 +280,16 @@ where
                             e
                         ))
                     })?;
+                self.ctx
+                    .inner
+                    .borrow_mut()
+                    .store_ibc_denom_by_token(&ibc_denom)
+                    .map_err(|e| {
+                        Error::Denom(format!(
+                            "Writing the IBC denom failed: {}",
+                            e
+                        ))
+                    })?;
                 if let Some((_, base_token)) = is_ibc_denom(&ibc_denom) {
                     self.ctx
                         .inner
@@ -171,6 +307,193 @@ where
         Ok(())
     }
 
+    /// Send the token received to the IBC account onward according to the
+    /// forward memo of the received packet
+    fn forward_transfer(&mut self) -> Result<(), Error> {
+        let forward = match self
+            .ctx
+            .forward
+            .as_ref()
+            .and_then(|f| f.borrow_mut().take())
+        {
+            Some(forward) => forward,
+            None => return Ok(()),
+        };
+        let host_timestamp = self
+            .ctx
+            .inner
+            .borrow()
+            .host_timestamp()
+            .map_err(|e| Error::Context(Box::new(e)))?;
+        let timeout_timestamp_on_b = (host_timestamp + forward.timeout)
+            .map_err(|e| {
+                Error::Forward(format!(
+                    "The timeout of the onward transfer overflowed: Error {e}"
+                ))
+            })?;
+        let msg = MsgTransfer {
+            port_id_on_a: forward.port_id,
+            chan_id_on_a: forward.channel_id,
+            packet_data: PacketData {
+                token: forward.token,
+                sender: Address::Internal(InternalAddress::Ibc)
+                    .to_string()
+                    .into(),
+                receiver: forward.receiver,
+                memo: forward.memo,
+            },
+            timeout_height_on_b: TimeoutHeight::Never,
+            timeout_timestamp_on_b,
+        };
+        // The received packet is acknowledged when the onward packet sent
+        // with the current next sequence is acknowledged or timed out
+        let sequence = self
+            .ctx
+            .inner
+            .borrow()
+            .get_next_sequence_send(&msg.port_id_on_a, &msg.chan_id_on_a)
+            .map_err(|e| Error::Context(Box::new(e)))?;
+        self.ctx
+            .inner
+            .borrow_mut()
+            .store_forwarded_packet(
+                &msg.port_id_on_a,
+                &msg.chan_id_on_a,
+                sequence,
+                forward.packet,
+            )
+            .map_err(|e| Error::Context(Box::new(e)))?;
+        let mut token_transfer_ctx =
+            TokenTransferContext::new(self.ctx.inner.clone());
+        token_transfer_ctx
+            .set_channel(msg.port_id_on_a.clone(), msg.chan_id_on_a.clone());
+        token_transfer_ctx.set_forwarding();
+        send_transfer_execute(&mut self.ctx, &mut token_transfer_ctx, msg)
+            .map_err(Error::TokenTransfer)
+    }
+
+    /// Write the pending acknowledgement of the received packet whose token
+    /// has been forwarded by the acknowledged or timed-out packet. If the
+    /// onward transfer failed, the refunded token is returned as it was
+    /// received and the error acknowledgement is written for the sender chain
+    /// to refund the original sender.
+    fn resolve_forward(&mut self, envelope: &MsgEnvelope) -> Result<(), Error> {
+        let (packet, is_successful) = match envelope {
+            MsgEnvelope::Packet(PacketMsg::Ack(msg)) => {
+                let is_successful =
+                    serde_json::from_slice::<AcknowledgementStatus>(
+                        msg.acknowledgement.as_ref(),
+                    )
+                    .map(|status| status.is_successful())
+                    .unwrap_or_default();
+                (&msg.packet, is_successful)
+            }
+            MsgEnvelope::Packet(PacketMsg::Timeout(msg)) => {
+                (&msg.packet, false)
+            }
+            MsgEnvelope::Packet(PacketMsg::TimeoutOnClose(msg)) => {
+                (&msg.packet, false)
+            }
+            _ => return Ok(()),
+        };
+        let received = match self
+            .ctx
+            .inner
+            .borrow()
+            .forwarded_packet(
+                &packet.port_id_on_a,
+                &packet.chan_id_on_a,
+                packet.seq_on_a,
+            )
+            .map_err(|e| Error::Context(Box::new(e)))?
+        {
+            Some(received) => received,
+            None => return Ok(()),
+        };
+        self.ctx
+            .inner
+            .borrow_mut()
+            .delete_forwarded_packet(
+                &packet.port_id_on_a,
+                &packet.chan_id_on_a,
+                packet.seq_on_a,
+            )
+            .map_err(|e| Error::Context(Box::new(e)))?;
+
+        let ack = if is_successful {
+            AcknowledgementStatus::success(ack_success_b64())
+        } else {
+            self.return_forwarded_token(&received)?;
+            let status = StatusValue::new(format!(
+                "The onward transfer failed: Port ID {}, Channel ID {}, \
+                 Sequence {}",
+                packet.port_id_on_a, packet.chan_id_on_a, packet.seq_on_a
+            ))
+            .expect("The status shouldn't be empty");
+            AcknowledgementStatus::error(status)
+        };
+        let ack: Acknowledgement = ack.into();
+        self.ctx
+            .inner
+            .borrow_mut()
+            .store_packet_ack(
+                &received.port_id_on_b,
+                &received.chan_id_on_b,
+                received.seq_on_a,
+                compute_ack_commitment(&ack),
+            )
+            .map_err(|e| Error::Context(Box::new(e)))?;
+
+        let channel_end = self
+            .ctx
+            .inner
+            .borrow()
+            .channel_end(&received.port_id_on_b, &received.chan_id_on_b)
+            .map_err(|e| Error::Context(Box::new(e)))?;
+        let connection_id = match channel_end.connection_hops().first() {
+            Some(connection_id) => connection_id.clone(),
+            None => {
+                return Err(Error::Forward(format!(
+                    "The channel has no connection: Port ID {}, Channel ID {}",
+                    received.port_id_on_b, received.chan_id_on_b
+                )));
+            }
+        };
+        let event = RawIbcEvent::WriteAcknowledgement(
+            WriteAcknowledgement::new(received, ack, connection_id),
+        );
+        self.ctx
+            .emit_ibc_event(RawIbcEvent::Message(MessageEvent::Channel))
+            .map_err(|e| Error::Context(Box::new(e)))?;
+        self.ctx
+            .emit_ibc_event(event)
+            .map_err(|e| Error::Context(Box::new(e)))
+    }
+
+    /// Return the token refunded to the IBC account as it was received by the
+    /// forwarded packet, i.e. burn it if it has been minted. The token which
+    /// has been unescrowed stays escrowed in the IBC account.
+    fn return_forwarded_token(&mut self, packet: &Packet) -> Result<(), Error> {
+        let data = serde_json::from_slice::<PacketData>(&packet.data)
+            .map_err(|_| Error::DecodingData)?;
+        let (token, is_minted) = received_token(packet, &data.token);
+        if !is_minted {
+            return Ok(());
+        }
+        let mut token_transfer_ctx =
+            TokenTransferContext::new(self.ctx.inner.clone());
+        token_transfer_ctx.set_channel(
+            packet.port_id_on_b.clone(),
+            packet.chan_id_on_b.clone(),
+        );
+        token_transfer_ctx
+            .burn_coins_execute(
+                &Address::Internal(InternalAddress::Ibc),
+                &token,
+            )
+            .map_err(Error::TokenTransfer)
+    }
+
     /// Get the minted IBC denom, the trace hash, and the receiver from IBC
     /// events
     fn get_minted_token_info(
@@ -244,6 +567,17 @@ where
                 )
                 .map_err(Error::TokenTransfer)
             }
+            IbcMessage::TransferWithFee(msg) => {
+                self.check_fee(&msg.message, &msg.fee)?;
+                let token_transfer_ctx =
+                    TokenTransferContext::new(self.ctx.inner.clone());
+                send_transfer_validate(
+                    &self.ctx,
+                    &token_transfer_ctx,
+                    msg.message,
+                )
+                .map_err(Error::TokenTransfer)
+            }
             IbcMessage::Envelope(envelope) => {
                 validate(&self.ctx, &self.router, envelope)
                     .map_err(|e| Error::Context(Box::new(e)))
@@ -251,6 +585,149 @@ where
         }
     }
 
+    /// Check that the relayer fees can be attached to the transfer
+    fn check_fee(
+        &self,
+        msg: &MsgTransfer,
+        fee: &PacketFee,
+    ) -> Result<(), Error> {
+        let is_enabled = self
+            .ctx
+            .inner
+            .borrow()
+            .is_fee_enabled(&msg.port_id_on_a, &msg.chan_id_on_a)
+            .map_err(|e| Error::Context(Box::new(e)))?;
+        if !is_enabled {
+            return Err(Error::Fee(format!(
+                "The relayer fees aren't enabled: Port ID {}, Channel ID {}",
+                msg.port_id_on_a, msg.chan_id_on_a
+            )));
+        }
+        if fee.payer.to_string() != msg.packet_data.sender.as_ref() {
+            return Err(Error::Fee(format!(
+                "The fee payer should be the sender: Payer {}, Sender {}",
+                fee.payer, msg.packet_data.sender
+            )));
+        }
+        Ok(())
+    }
+
+    /// Escrow the relayer fees for the sent packet. The fees are held by their
+    /// own escrow account, apart from the token escrowed by the transfers.
+    fn escrow_packet_fee(
+        &mut self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        sequence: Sequence,
+        fee: &PacketFee,
+    ) -> Result<(), Error> {
+        for amount in [fee.recv_fee, fee.ack_fee, fee.timeout_fee] {
+            self.transfer_fee(&fee.payer, &IBC_FEE_ESCROW, &fee.token, amount)?;
+            self.ctx
+                .inner
+                .borrow_mut()
+                .increase_fee_escrow_amount(&fee.token, amount.amount())
+                .map_err(|e| Error::Context(Box::new(e)))?;
+        }
+        self.ctx
+            .inner
+            .borrow_mut()
+            .store_packet_fee(port_id, channel_id, sequence, fee.clone())
+            .map_err(|e| Error::Context(Box::new(e)))
+    }
+
+    /// Pay the escrowed relayer fees to the relayer of the acknowledgement
+    /// or the timeout, or to the payee registered by the relayer for the
+    /// channel, and refund the unused fees to the payer
+    fn pay_packet_fee(&mut self, envelope: &MsgEnvelope) -> Result<(), Error> {
+        let (packet, signer, is_ack) = match envelope {
+            MsgEnvelope::Packet(PacketMsg::Ack(msg)) => {
+                (&msg.packet, &msg.signer, true)
+            }
+            MsgEnvelope::Packet(PacketMsg::Timeout(msg)) => {
+                (&msg.packet, &msg.signer, false)
+            }
+            MsgEnvelope::Packet(PacketMsg::TimeoutOnClose(msg)) => {
+                (&msg.packet, &msg.signer, false)
+            }
+            _ => return Ok(()),
+        };
+        let fee = match self
+            .ctx
+            .inner
+            .borrow()
+            .packet_fee(
+                &packet.port_id_on_a,
+                &packet.chan_id_on_a,
+                packet.seq_on_a,
+            )
+            .map_err(|e| Error::Context(Box::new(e)))?
+        {
+            Some(fee) => fee,
+            None => return Ok(()),
+        };
+        // The relayer is the message signer, who has to sign the tx
+        let relayer = Address::decode(signer.as_ref()).map_err(|e| {
+            Error::Fee(format!(
+                "The signer isn't a valid address: Signer {signer}, Error {e}",
+            ))
+        })?;
+        let payee = self
+            .ctx
+            .inner
+            .borrow()
+            .payee(&packet.port_id_on_a, &packet.chan_id_on_a, &relayer)
+            .map_err(|e| Error::Context(Box::new(e)))?
+            .unwrap_or(relayer);
+        let (paid, refunded) = if is_ack {
+            (vec![fee.recv_fee, fee.ack_fee], vec![fee.timeout_fee])
+        } else {
+            (vec![fee.timeout_fee], vec![fee.recv_fee, fee.ack_fee])
+        };
+        for amount in paid {
+            self.transfer_fee(&IBC_FEE_ESCROW, &payee, &fee.token, amount)?;
+        }
+        for amount in refunded {
+            self.transfer_fee(&IBC_FEE_ESCROW, &fee.payer, &fee.token, amount)?;
+        }
+        for amount in [fee.recv_fee, fee.ack_fee, fee.timeout_fee] {
+            self.ctx
+                .inner
+                .borrow_mut()
+                .decrease_fee_escrow_amount(&fee.token, amount.amount())
+                .map_err(|e| Error::Context(Box::new(e)))?;
+        }
+        self.ctx
+            .inner
+            .borrow_mut()
+            .delete_packet_fee(
+                &packet.port_id_on_a,
+                &packet.chan_id_on_a,
+                packet.seq_on_a,
+            )
+            .map_err(|e| Error::Context(Box::new(e)))
+    }
+
+    /// Transfer a part of the relayer fees
+    fn transfer_fee(
+        &mut self,
+        src: &Address,
+        dest: &Address,
+        token: &Address,
+        amount: DenominatedAmount,
+    ) -> Result<(), Error> {
+        if amount.is_zero() {
+            return Ok(());
+        }
+        self.ctx
+            .inner
+            .borrow_mut()
+            .transfer_token(src, dest, token, amount)
+            .map_err(|e| {
+                Error::Fee(format!("Transferring the fee failed: {e}"))
+            })
+    }
+
     /// Handle the MASP transaction if needed
     fn handle_masp_tx(&mut self, message: IbcMessage) -> Result<(), Error> {
         let shielded_transfer = match message {
@@ -300,12 +777,20 @@ pub enum IbcMessage {
     Transfer(MsgTransfer),
     /// Ibc shielded transfer
     ShieldedTransfer(MsgShieldedTransfer),
+    /// Ibc transparent transfer with relayer fees
+    TransferWithFee(MsgTransferWithFee),
 }
 
 /// Tries to decode transaction data to an `IbcMessage`
 pub fn decode_message(tx_data: &[u8]) -> Result<IbcMessage, Error> {
     // ibc-rs message
     if let Ok(any_msg) = Any::decode(tx_data) {
+        // Refuse an IBC message which can't be handled before decoding it
+        if any_msg.type_url.starts_with(IBC_MESSAGE_TYPE_URL_PREFIX)
+            && !SUPPORTED_MESSAGE_TYPE_URLS.contains(&any_msg.type_url.as_str())
+        {
+            return Err(Error::UnsupportedMessage(any_msg.type_url));
+        }
         if let Ok(transfer_msg) = MsgTransfer::try_from(any_msg.clone()) {
             return Ok(IbcMessage::Transfer(transfer_msg));
         }
@@ -319,6 +804,11 @@ pub fn decode_message(tx_data: &[u8]) -> Result<IbcMessage, Error> {
         return Ok(IbcMessage::ShieldedTransfer(msg));
     }
 
+    // Message with Transfer and the relayer fees
+    if let Ok(msg) = MsgTransferWithFee::try_from_slice(tx_data) {
+        return Ok(IbcMessage::TransferWithFee(msg));
+    }
+
     Err(Error::DecodingData)
 }
 
@@ -352,6 +842,16 @@ pub fn received_ibc_token(
     }
 }
 
+/// Read the IBC denom of the IBC token. Returns `None` if the token isn't an
+/// IBC token or its denom has been stored before the lookup by the token was
+/// introduced.
+pub fn read_ibc_denom<S: namada_storage::StorageRead>(
+    storage: &S,
+    token: &Address,
+) -> namada_storage::Result<Option<String>> {
+    storage.read(&storage::ibc_denom_by_token_key(token))
+}
+
 #[cfg(any(test, feature = "testing"))]
 /// Testing helpers ans strategies for IBC
 pub mod testing {
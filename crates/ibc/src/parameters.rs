@@ -0,0 +1,234 @@
+//! IBC parameters
+
+use namada_core::address::Address;
+use namada_core::borsh::{BorshDeserialize, BorshSerialize};
+use namada_core::ibc::core::host::types::identifiers::{ChannelId, PortId};
+use namada_core::storage::Epoch;
+use namada_core::token::Amount;
+use namada_storage::{iter_prefix, Result, StorageRead, StorageWrite};
+
+use crate::storage;
+
+/// The default max size in bytes of the memo of a transfer packet
+pub const DEFAULT_MAX_MEMO_SIZE: u64 = 32 * 1024;
+
+/// The default max number of the IBC keys changed by a tx
+pub const DEFAULT_MAX_IBC_KEYS_PER_TX: u64 = 1_000;
+
+/// The default max size in bytes of the data of an IBC tx
+pub const DEFAULT_MAX_TX_DATA_LEN: u64 = 1024 * 1024;
+
+/// The default number of the past epochs for which the throughput records of
+/// the tokens are retained
+pub const DEFAULT_THROUGHPUT_HISTORY_EPOCHS: u64 = 10;
+
+/// The default max number of the consensus states stored for a client
+pub const DEFAULT_MAX_CONSENSUS_STATES_PER_CLIENT: u64 = 10_000;
+
+/// IBC parameters
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct IbcParameters {
+    /// Default supply limit of each token
+    pub default_mint_limit: Amount,
+    /// Default per-epoch throughput limit of each token
+    pub default_per_epoch_throughput_limit: Amount,
+    /// The path segments under which this chain commits the upgraded client
+    /// state. A client of this chain on a counterparty chain must have the
+    /// same upgrade path, if any.
+    pub upgrade_path: Vec<String>,
+    /// The max size in bytes of the memo of a transfer packet
+    pub max_memo_size: u64,
+    /// Whether a received token can be forwarded to another chain by the
+    /// forward memo of the packet
+    pub pfm_enabled: bool,
+    /// The max number of the IBC keys changed by a tx
+    pub max_ibc_keys_per_tx: u64,
+    /// The max size in bytes of the data of an IBC tx
+    pub max_tx_data_len: u64,
+    /// The number of the past epochs for which the throughput records of the
+    /// tokens are retained. No record is kept if it's zero.
+    pub throughput_history_epochs: u64,
+    /// A fixed unbonding period in seconds for the clients of this chain. When
+    /// it isn't set, the period is computed from the PoS unbonding length and
+    /// cubic slashing window length, and the min epoch duration.
+    pub unbonding_period_secs: Option<u64>,
+    /// The max number of the consensus states stored for a client. The
+    /// expired consensus states are pruned on a client update, so a client
+    /// update exceeding it is rejected only when a relayer updates the
+    /// client too often within the trusting period.
+    pub max_consensus_states_per_client: u64,
+    /// Default min amount of a transfer of each token, below which the
+    /// outgoing transfers are rejected and the incoming ones are refused
+    /// with an error acknowledgement. No min amount is enforced if it's zero.
+    pub default_min_transfer_amount: Amount,
+}
+
+impl Default for IbcParameters {
+    fn default() -> Self {
+        Self {
+            default_mint_limit: Amount::max(),
+            default_per_epoch_throughput_limit: Amount::max(),
+            upgrade_path: Vec::new(),
+            max_memo_size: DEFAULT_MAX_MEMO_SIZE,
+            pfm_enabled: false,
+            max_ibc_keys_per_tx: DEFAULT_MAX_IBC_KEYS_PER_TX,
+            max_tx_data_len: DEFAULT_MAX_TX_DATA_LEN,
+            throughput_history_epochs: DEFAULT_THROUGHPUT_HISTORY_EPOCHS,
+            unbonding_period_secs: None,
+            max_consensus_states_per_client:
+                DEFAULT_MAX_CONSENSUS_STATES_PER_CLIENT,
+            default_min_transfer_amount: Amount::zero(),
+        }
+    }
+}
+
+impl IbcParameters {
+    /// Initialize IBC parameters into storage
+    pub fn init_storage<S>(&self, storage: &mut S) -> Result<()>
+    where
+        S: StorageRead + StorageWrite,
+    {
+        let key = storage::params_key();
+        storage.write(&key, self)
+    }
+}
+
+/// Read the IBC parameters. The limits are unbounded, the upgrade path is
+/// empty, the memo size and the size of a tx are limited by the defaults, no
+/// min transfer amount is enforced and the packet forwarding is disabled if
+/// the parameters haven't been initialized.
+pub fn read_params<S: StorageRead>(storage: &S) -> Result<IbcParameters> {
+    Ok(storage.read(&storage::params_key())?.unwrap_or_default())
+}
+
+/// Get the mint limit and the per-epoch throughput limit of the token. A
+/// per-token override set by governance takes precedence over the default.
+pub fn get_limits<S: StorageRead>(
+    storage: &S,
+    token: &Address,
+) -> Result<(Amount, Amount)> {
+    let params = read_params(storage)?;
+    let mint_limit = storage
+        .read(&storage::mint_limit_key(token))?
+        .unwrap_or(params.default_mint_limit);
+    let throughput_limit = storage
+        .read(&storage::throughput_limit_key(token))?
+        .unwrap_or(params.default_per_epoch_throughput_limit);
+    Ok((mint_limit, throughput_limit))
+}
+
+/// Get the min amount of a transfer of the token. A per-token override set by
+/// governance takes precedence over the default.
+pub fn read_min_transfer_amount<S: StorageRead>(
+    storage: &S,
+    token: &Address,
+) -> Result<Amount> {
+    match storage.read(&storage::min_transfer_amount_key(token))? {
+        Some(min_amount) => Ok(min_amount),
+        None => Ok(read_params(storage)?.default_min_transfer_amount),
+    }
+}
+
+/// Get the per-epoch throughput limit of the token for the channel, if any
+pub fn get_channel_throughput_limit<S: StorageRead>(
+    storage: &S,
+    token: &Address,
+    port_id: &PortId,
+    channel_id: &ChannelId,
+) -> Result<Option<Amount>> {
+    storage.read(&storage::throughput_limit_key_for_channel(
+        token, port_id, channel_id,
+    ))
+}
+
+/// The current usage of the IBC rate limits of a token with the configured
+/// limits
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize,
+)]
+pub struct LimitsUsage {
+    /// The amount minted by IBC
+    pub minted: Amount,
+    /// The mint limit
+    pub mint_limit: Amount,
+    /// The amount deposited in the current epoch
+    pub deposit: Amount,
+    /// The amount withdrawn in the current epoch
+    pub withdraw: Amount,
+    /// The per-epoch throughput limit
+    pub throughput_limit: Amount,
+}
+
+/// Read the amount of the token minted by IBC and the mint limit
+pub fn read_mint_usage<S: StorageRead>(
+    storage: &S,
+    token: &Address,
+) -> Result<(Amount, Amount)> {
+    let (mint_limit, _) = get_limits(storage, token)?;
+    let minted = storage
+        .read(&storage::mint_amount_key(token))?
+        .unwrap_or_default();
+    Ok((minted, mint_limit))
+}
+
+/// Read the deposit and the withdraw of the token in the current epoch and the
+/// per-epoch throughput limit
+pub fn read_throughput_usage<S: StorageRead>(
+    storage: &S,
+    token: &Address,
+) -> Result<(Amount, Amount, Amount)> {
+    let (_, throughput_limit) = get_limits(storage, token)?;
+    let deposit = storage
+        .read(&storage::deposit_key(token))?
+        .unwrap_or_default();
+    let withdraw = storage
+        .read(&storage::withdraw_key(token))?
+        .unwrap_or_default();
+    Ok((deposit, withdraw, throughput_limit))
+}
+
+/// Read the usage of the IBC rate limits of the token
+pub fn read_limits_usage<S: StorageRead>(
+    storage: &S,
+    token: &Address,
+) -> Result<LimitsUsage> {
+    let (minted, mint_limit) = read_mint_usage(storage, token)?;
+    let (deposit, withdraw, throughput_limit) =
+        read_throughput_usage(storage, token)?;
+    Ok(LimitsUsage {
+        minted,
+        mint_limit,
+        deposit,
+        withdraw,
+        throughput_limit,
+    })
+}
+
+/// The throughput of a token in an ended epoch, recorded before the per-epoch
+/// throughputs are cleared
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize,
+)]
+pub struct ThroughputRecord {
+    /// The amount deposited in the epoch
+    pub deposit: Amount,
+    /// The amount withdrawn in the epoch
+    pub withdraw: Amount,
+    /// The amount minted by IBC at the end of the epoch
+    pub minted: Amount,
+}
+
+/// Read the retained throughput records of the token, sorted by the epoch
+pub fn read_throughput_history<S: StorageRead>(
+    storage: &S,
+    token: &Address,
+) -> Result<Vec<(Epoch, ThroughputRecord)>> {
+    let prefix = storage::throughput_history_token_prefix(token);
+    iter_prefix(storage, &prefix)?
+        .filter_map(|result| match result {
+            Ok((key, record)) => storage::is_throughput_history_key(&key)
+                .map(|(_, epoch)| Ok((epoch, record))),
+            Err(e) => Some(Err(e)),
+        })
+        .collect()
+}
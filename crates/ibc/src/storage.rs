@@ -1,8 +1,10 @@
 //! Functions for IBC-related data to access the storage
 
+use std::fmt::Display;
 use std::str::FromStr;
 
 use namada_core::address::{Address, InternalAddress, HASH_LEN, SHA_HASH_LEN};
+use namada_core::borsh::{BorshDeserialize, BorshSerializeExt};
 use namada_core::ibc::core::client::types::Height;
 use namada_core::ibc::core::host::types::identifiers::{
     ChannelId, ClientId, ConnectionId, PortId, Sequence,
@@ -13,7 +15,8 @@ use namada_core::ibc::core::host::types::path::{
     ReceiptPath, SeqAckPath, SeqRecvPath, SeqSendPath,
 };
 use namada_core::ibc::IbcTokenHash;
-use namada_core::storage::{DbKeySeg, Key, KeySeg};
+use namada_core::storage::{DbKeySeg, Epoch, Key, KeySeg};
+use namada_storage::{Error as StorageError, StorageRead, StorageWrite};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
 
@@ -22,6 +25,26 @@ const CONNECTIONS_COUNTER_PREFIX: &str = "connections";
 const CHANNELS_COUNTER_PREFIX: &str = "channelEnds";
 const COUNTER_SEG: &str = "counter";
 const DENOM: &str = "ibc_denom";
+const DENOM_BY_TOKEN: &str = "ibc_denom_by_token";
+const FEE: &str = "fee";
+const FEE_ENABLED: &str = "enabled";
+const PACKET_FEE: &str = "packets";
+const PAYEE: &str = "payees";
+const FEE_ESCROW: &str = "fee_escrow_amount";
+const PARAMS: &str = "params";
+const MINT_LIMIT: &str = "mint_limit";
+const THROUGHPUT_LIMIT: &str = "throughput_limit";
+const MIN_TRANSFER_AMOUNT: &str = "min_transfer_amount";
+const BLOCKED_TRACES: &str = "blocked_traces";
+const MINT: &str = "mint_amount";
+const DEPOSIT: &str = "deposit";
+const WITHDRAW: &str = "withdraw";
+const ESCROW: &str = "escrow_amount";
+const PACKET_ESCROW: &str = "packet_escrow";
+const FORWARDED_PACKET: &str = "forwarded_packet";
+const THROUGHPUT_HISTORY: &str = "throughput_history";
+const CLIENT_UPDATE_TIMESTAMP: &str = "update_timestamp";
+const CLIENT_UPDATE_HEIGHT: &str = "update_height";
 
 #[allow(missing_docs)]
 #[derive(Error, Debug)]
@@ -41,6 +64,125 @@ pub enum Error {
 /// IBC storage functions result
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// The encoding of the u64 values in the IBC storage. The client, connection
+/// and channel counters are borsh-encoded while the sequences are encoded
+/// with big-endian as in ibc-go:
+/// https://github.com/cosmos/ibc-go/blob/89ffaafb5956a5ea606e1f1bf249c880bea802ed/modules/core/04-channel/keeper/keeper.go#L115
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum U64Codec {
+    /// Borsh encoding of the counters
+    Borsh,
+    /// Big-endian encoding of the sequences
+    BigEndian,
+}
+
+impl U64Codec {
+    /// Returns the codec of the value of the given key if it's a counter or a
+    /// sequence key
+    pub fn of_key(key: &Key) -> Option<Self> {
+        if is_ibc_counter_key(key) {
+            Some(Self::Borsh)
+        } else if is_next_sequence_key(key) {
+            Some(Self::BigEndian)
+        } else {
+            None
+        }
+    }
+
+    /// Encode the value
+    pub fn encode(&self, value: u64) -> Vec<u8> {
+        match self {
+            Self::Borsh => value.serialize_to_vec(),
+            Self::BigEndian => value.to_be_bytes().to_vec(),
+        }
+    }
+
+    /// Decode the value. Returns `None` if the bytes aren't a u64.
+    pub fn decode(&self, bytes: &[u8]) -> Option<u64> {
+        match self {
+            Self::Borsh => u64::try_from_slice(bytes).ok(),
+            Self::BigEndian => bytes.try_into().ok().map(u64::from_be_bytes),
+        }
+    }
+}
+
+impl Display for U64Codec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Borsh => write!(f, "borsh"),
+            Self::BigEndian => write!(f, "big-endian"),
+        }
+    }
+}
+
+/// Read a client, connection or channel counter
+pub fn read_counter<S: StorageRead + ?Sized>(
+    storage: &S,
+    key: &Key,
+) -> namada_storage::Result<Option<u64>> {
+    debug_assert!(
+        is_ibc_counter_key(key),
+        "A counter is read with a key which isn't a counter key: Key {key}"
+    );
+    read_u64(storage, key, U64Codec::Borsh)
+}
+
+/// Write a client, connection or channel counter
+pub fn write_counter<S: StorageWrite + ?Sized>(
+    storage: &mut S,
+    key: &Key,
+    counter: u64,
+) -> namada_storage::Result<()> {
+    debug_assert!(
+        is_ibc_counter_key(key),
+        "A counter is written with a key which isn't a counter key: Key {key}"
+    );
+    storage.write_bytes(key, U64Codec::Borsh.encode(counter))
+}
+
+/// Read the next sequence to send, receive or acknowledge
+pub fn read_sequence<S: StorageRead + ?Sized>(
+    storage: &S,
+    key: &Key,
+) -> namada_storage::Result<Option<Sequence>> {
+    debug_assert!(
+        is_next_sequence_key(key),
+        "A sequence is read with a key which isn't a sequence key: Key {key}"
+    );
+    Ok(read_u64(storage, key, U64Codec::BigEndian)?.map(Sequence::from))
+}
+
+/// Write the next sequence to send, receive or acknowledge
+pub fn write_sequence<S: StorageWrite + ?Sized>(
+    storage: &mut S,
+    key: &Key,
+    sequence: Sequence,
+) -> namada_storage::Result<()> {
+    debug_assert!(
+        is_next_sequence_key(key),
+        "A sequence is written with a key which isn't a sequence key: Key \
+         {key}"
+    );
+    storage.write_bytes(key, U64Codec::BigEndian.encode(sequence.into()))
+}
+
+fn read_u64<S: StorageRead + ?Sized>(
+    storage: &S,
+    key: &Key,
+    codec: U64Codec,
+) -> namada_storage::Result<Option<u64>> {
+    storage
+        .read_bytes(key)?
+        .map(|bytes| {
+            codec.decode(&bytes).ok_or_else(|| {
+                StorageError::new(format!(
+                    "The value isn't a {codec}-encoded u64: Key {key}"
+                ))
+            })
+        })
+        .transpose()
+}
+
 /// Returns a key of the IBC-related data
 pub fn ibc_key(path: impl AsRef<str>) -> Result<Key> {
     let path = Key::parse(path).map_err(Error::StorageKey)?;
@@ -198,13 +340,16 @@ pub fn ack_key(
 
 /// Returns a key for the timestamp for the client update
 pub fn client_update_timestamp_key(client_id: &ClientId) -> Key {
-    let path = format!("clients/{}/update_timestamp", client_id);
+    let path = format!(
+        "{CLIENTS_COUNTER_PREFIX}/{client_id}/{CLIENT_UPDATE_TIMESTAMP}"
+    );
     ibc_key(path).expect("Creating a key for the ack shouldn't fail")
 }
 
 /// Returns a key for the timestamp for the client update
 pub fn client_update_height_key(client_id: &ClientId) -> Key {
-    let path = format!("clients/{}/update_height", client_id);
+    let path =
+        format!("{CLIENTS_COUNTER_PREFIX}/{client_id}/{CLIENT_UPDATE_HEIGHT}");
     ibc_key(path).expect("Creating a key for the ack shouldn't fail")
 }
 
@@ -253,6 +398,14 @@ pub fn consensus_height(key: &Key) -> Result<Height> {
     }
 }
 
+/// Returns the client ID and the height if the given key is for a consensus
+/// state `#IBC/clients/<client_id>/consensusStates/0-<height>`
+pub fn is_consensus_state_key(key: &Key) -> Option<(ClientId, Height)> {
+    let height = consensus_height(key).ok()?;
+    let client_id = client_id(key).ok()?;
+    Some((client_id, height))
+}
+
 /// Returns a connection ID from the given connection key
 /// `#IBC/connections/<conn_id>`
 pub fn connection_id(key: &Key) -> Result<ConnectionId> {
@@ -395,6 +548,276 @@ pub fn ibc_denom_key(
         .expect("Cannot obtain a storage key")
 }
 
+/// The storage key to get the IBC denom with the IBC token address derived
+/// from it
+pub fn ibc_denom_by_token_key(token: &Address) -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&DENOM_BY_TOKEN.to_string().to_db_key())
+        .expect("Cannot obtain a storage key")
+        .push(&token.to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the switch enabling the relayer fees for the channel
+pub fn fee_enabled_key(port_id: &PortId, channel_id: &ChannelId) -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&FEE.to_string().to_db_key())
+        .and_then(|k| k.push(&FEE_ENABLED.to_string().to_db_key()))
+        .and_then(|k| k.push(&port_id.to_string().to_db_key()))
+        .and_then(|k| k.push(&channel_id.to_string().to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key prefix of the relayer fees escrowed for the packets
+pub fn packet_fee_prefix() -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&FEE.to_string().to_db_key())
+        .and_then(|k| k.push(&PACKET_FEE.to_string().to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the relayer fees escrowed for the packet
+pub fn packet_fee_key(
+    port_id: &PortId,
+    channel_id: &ChannelId,
+    sequence: Sequence,
+) -> Key {
+    packet_fee_prefix()
+        .push(&port_id.to_string().to_db_key())
+        .and_then(|k| k.push(&channel_id.to_string().to_db_key()))
+        .and_then(|k| k.push(&sequence.to_string().to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the payee registered by the relayer to receive the relayer
+/// fees of the packets relayed on the channel
+pub fn payee_key(
+    port_id: &PortId,
+    channel_id: &ChannelId,
+    relayer: &Address,
+) -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&FEE.to_string().to_db_key())
+        .and_then(|k| k.push(&PAYEE.to_string().to_db_key()))
+        .and_then(|k| k.push(&port_id.to_string().to_db_key()))
+        .and_then(|k| k.push(&channel_id.to_string().to_db_key()))
+        .and_then(|k| k.push(&relayer.to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key prefix of the relayer fees escrowed for the in-flight
+/// packets
+pub fn fee_escrow_amount_prefix() -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&FEE_ESCROW.to_string().to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the amount of the token escrowed for the relayer fees of
+/// the in-flight packets. It's accounted apart from the amount escrowed by the
+/// transfers.
+pub fn fee_escrow_amount_key(token: &Address) -> Key {
+    fee_escrow_amount_prefix()
+        .push(&token.to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the IBC parameters
+pub fn params_key() -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&PARAMS.to_string().to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the mint limit overriding the default for the token
+pub fn mint_limit_key(token: &Address) -> Key {
+    params_key()
+        .push(&MINT_LIMIT.to_string().to_db_key())
+        .and_then(|k| k.push(&token.to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the per-epoch throughput limit overriding the default for
+/// the token
+pub fn throughput_limit_key(token: &Address) -> Key {
+    params_key()
+        .push(&THROUGHPUT_LIMIT.to_string().to_db_key())
+        .and_then(|k| k.push(&token.to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the per-epoch throughput limit of the token for the
+/// channel. The channel isn't limited apart from the token's limit if the key
+/// isn't set.
+pub fn throughput_limit_key_for_channel(
+    token: &Address,
+    port_id: &PortId,
+    channel_id: &ChannelId,
+) -> Key {
+    throughput_limit_key(token)
+        .push(&port_id.to_string().to_db_key())
+        .and_then(|k| k.push(&channel_id.to_string().to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the min amount of a transfer overriding the default for the
+/// token
+pub fn min_transfer_amount_key(token: &Address) -> Key {
+    params_key()
+        .push(&MIN_TRANSFER_AMOUNT.to_string().to_db_key())
+        .and_then(|k| k.push(&token.to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key prefix of the denom traces whose inbound transfers are
+/// refused
+pub fn blocked_trace_prefix() -> Key {
+    params_key()
+        .push(&BLOCKED_TRACES.to_string().to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the denom trace whose inbound transfers are refused. The
+/// hash is the one of the trace on this chain, as in [`calc_hash`], and the
+/// value is the trace itself.
+pub fn blocked_trace_key(trace_hash: impl AsRef<str>) -> Key {
+    blocked_trace_prefix()
+        .push(&trace_hash.as_ref().to_string().to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the amount of the token minted by IBC
+pub fn mint_amount_key(token: &Address) -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&MINT.to_string().to_db_key())
+        .and_then(|k| k.push(&token.to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key prefix of the amounts escrowed by IBC transfers
+pub fn escrow_amount_prefix() -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&ESCROW.to_string().to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the amount of the token escrowed by IBC transfers
+pub fn escrow_amount_key(token: &Address) -> Key {
+    escrow_amount_prefix()
+        .push(&token.to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key prefix of the tokens escrowed for the in-flight packets
+pub fn packet_escrow_prefix() -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&PACKET_ESCROW.to_string().to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the token escrowed for the packet
+pub fn packet_escrow_key(
+    port_id: &PortId,
+    channel_id: &ChannelId,
+    sequence: Sequence,
+) -> Key {
+    packet_escrow_prefix()
+        .push(&port_id.to_string().to_db_key())
+        .and_then(|k| k.push(&channel_id.to_string().to_db_key()))
+        .and_then(|k| k.push(&sequence.to_string().to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the received packet whose token has been forwarded by the
+/// packet sent with the sequence
+pub fn forwarded_packet_key(
+    port_id: &PortId,
+    channel_id: &ChannelId,
+    sequence: Sequence,
+) -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&FORWARDED_PACKET.to_string().to_db_key())
+        .and_then(|k| k.push(&port_id.to_string().to_db_key()))
+        .and_then(|k| k.push(&channel_id.to_string().to_db_key()))
+        .and_then(|k| k.push(&sequence.to_string().to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key prefix of the amounts deposited by IBC in the epoch
+pub fn deposit_prefix() -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&DEPOSIT.to_string().to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the amount of the token deposited by IBC in the epoch
+pub fn deposit_key(token: &Address) -> Key {
+    deposit_prefix()
+        .push(&token.to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the amount of the token deposited through the channel in
+/// the epoch
+pub fn deposit_key_for_channel(
+    token: &Address,
+    port_id: &PortId,
+    channel_id: &ChannelId,
+) -> Key {
+    deposit_key(token)
+        .push(&port_id.to_string().to_db_key())
+        .and_then(|k| k.push(&channel_id.to_string().to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key prefix of the amounts withdrawn by IBC in the epoch
+pub fn withdraw_prefix() -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&WITHDRAW.to_string().to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the amount of the token withdrawn by IBC in the epoch
+pub fn withdraw_key(token: &Address) -> Key {
+    withdraw_prefix()
+        .push(&token.to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the amount of the token withdrawn through the channel in
+/// the epoch
+pub fn withdraw_key_for_channel(
+    token: &Address,
+    port_id: &PortId,
+    channel_id: &ChannelId,
+) -> Key {
+    withdraw_key(token)
+        .push(&port_id.to_string().to_db_key())
+        .and_then(|k| k.push(&channel_id.to_string().to_db_key()))
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key prefix of the throughput history of the tokens
+pub fn throughput_history_prefix() -> Key {
+    Key::from(Address::Internal(InternalAddress::Ibc).to_db_key())
+        .push(&THROUGHPUT_HISTORY.to_string().to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key prefix of the throughput history of the token
+pub fn throughput_history_token_prefix(token: &Address) -> Key {
+    throughput_history_prefix()
+        .push(&token.to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns a key of the throughput record of the token in the epoch
+pub fn throughput_history_key(token: &Address, epoch: Epoch) -> Key {
+    throughput_history_token_prefix(token)
+        .push(&epoch.to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
 /// Hash the denom
 #[inline]
 pub fn calc_hash(denom: impl AsRef<str>) -> String {
@@ -449,6 +872,23 @@ pub fn is_ibc_denom_key(key: &Key) -> Option<(String, String)> {
     }
 }
 
+/// Returns the IBC token if the given key is the key of the IBC denom by the
+/// token
+pub fn is_ibc_denom_by_token_key(key: &Key) -> Option<&Address> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::AddressSeg(token),
+        ] if *addr == Address::Internal(InternalAddress::Ibc)
+            && prefix == DENOM_BY_TOKEN =>
+        {
+            Some(token)
+        }
+        _ => None,
+    }
+}
+
 /// Returns true if the given key is for an IBC counter for clients,
 /// connections, or channelEnds
 pub fn is_ibc_counter_key(key: &Key) -> bool {
@@ -460,3 +900,300 @@ pub fn is_ibc_counter_key(key: &Key) -> bool {
                 || prefix == CHANNELS_COUNTER_PREFIX) && counter == COUNTER_SEG
             )
 }
+
+/// Returns true if the given key is for the next sequence to send, receive or
+/// acknowledge
+pub fn is_next_sequence_key(key: &Key) -> bool {
+    matches!(&key.segments[..],
+    [
+        DbKeySeg::AddressSeg(addr),
+        DbKeySeg::StringSeg(prefix),
+        DbKeySeg::StringSeg(module0),
+        _,
+        DbKeySeg::StringSeg(module1),
+        _,
+    ] if addr == &Address::Internal(InternalAddress::Ibc)
+        && (prefix == "nextSequenceSend"
+            || prefix == "nextSequenceRecv"
+            || prefix == "nextSequenceAck")
+        && module0 == "ports"
+        && module1 == "channels"
+    )
+}
+
+/// Returns the port ID and the channel ID if the given key is the switch of
+/// the relayer fees
+pub fn is_fee_enabled_key(key: &Key) -> Option<(PortId, ChannelId)> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(enabled),
+            DbKeySeg::StringSeg(port_id),
+            DbKeySeg::StringSeg(channel_id),
+        ] if addr == &Address::Internal(InternalAddress::Ibc)
+            && prefix == FEE
+            && enabled == FEE_ENABLED =>
+        {
+            let port_id = PortId::from_str(port_id).ok()?;
+            let channel_id = ChannelId::from_str(channel_id).ok()?;
+            Some((port_id, channel_id))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the port ID, the channel ID, and the sequence if the given key is
+/// for the escrowed relayer fees
+pub fn is_packet_fee_key(key: &Key) -> Option<(PortId, ChannelId, Sequence)> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(packets),
+            DbKeySeg::StringSeg(port_id),
+            DbKeySeg::StringSeg(channel_id),
+            DbKeySeg::StringSeg(sequence),
+        ] if addr == &Address::Internal(InternalAddress::Ibc)
+            && prefix == FEE
+            && packets == PACKET_FEE =>
+        {
+            let port_id = PortId::from_str(port_id).ok()?;
+            let channel_id = ChannelId::from_str(channel_id).ok()?;
+            let sequence = Sequence::from_str(sequence).ok()?;
+            Some((port_id, channel_id, sequence))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the port ID, the channel ID, and the relayer if the given key is
+/// for the registered payee
+pub fn is_payee_key(key: &Key) -> Option<(PortId, ChannelId, &Address)> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(payees),
+            DbKeySeg::StringSeg(port_id),
+            DbKeySeg::StringSeg(channel_id),
+            DbKeySeg::AddressSeg(relayer),
+        ] if addr == &Address::Internal(InternalAddress::Ibc)
+            && prefix == FEE
+            && payees == PAYEE =>
+        {
+            let port_id = PortId::from_str(port_id).ok()?;
+            let channel_id = ChannelId::from_str(channel_id).ok()?;
+            Some((port_id, channel_id, relayer))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the port ID, the channel ID, and the sequence if the given key is
+/// for the token escrowed for the packet
+pub fn is_packet_escrow_key(
+    key: &Key,
+) -> Option<(PortId, ChannelId, Sequence)> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(port_id),
+            DbKeySeg::StringSeg(channel_id),
+            DbKeySeg::StringSeg(sequence),
+        ] if addr == &Address::Internal(InternalAddress::Ibc)
+            && prefix == PACKET_ESCROW =>
+        {
+            let port_id = PortId::from_str(port_id).ok()?;
+            let channel_id = ChannelId::from_str(channel_id).ok()?;
+            let sequence = Sequence::from_str(sequence).ok()?;
+            Some((port_id, channel_id, sequence))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the client ID if the given key is for the timestamp of the client
+/// update
+pub fn is_client_update_timestamp_key(key: &Key) -> Option<ClientId> {
+    client_update_key_with(key, CLIENT_UPDATE_TIMESTAMP)
+}
+
+/// Returns the client ID if the given key is for the host height of the
+/// client update
+pub fn is_client_update_height_key(key: &Key) -> Option<ClientId> {
+    client_update_key_with(key, CLIENT_UPDATE_HEIGHT)
+}
+
+fn client_update_key_with(key: &Key, suffix: &str) -> Option<ClientId> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(client_id),
+            DbKeySeg::StringSeg(update),
+        ] if addr == &Address::Internal(InternalAddress::Ibc)
+            && prefix == CLIENTS_COUNTER_PREFIX
+            && update == suffix =>
+        {
+            ClientId::from_str(client_id).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Returns true if the given key is for the IBC parameters, including the
+/// per-token limit overrides and the blocked denom traces
+pub fn is_ibc_params_key(key: &Key) -> bool {
+    matches!(&key.segments[..],
+             [DbKeySeg::AddressSeg(addr), DbKeySeg::StringSeg(prefix), ..]
+             if addr == &Address::Internal(InternalAddress::Ibc)
+                 && prefix == PARAMS)
+}
+
+/// Returns the token address if the given key is for a per-token limit
+/// override, a per-channel throughput limit or a per-token min transfer amount
+pub fn is_limit_override_key(key: &Key) -> Option<Address> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(limit),
+            DbKeySeg::AddressSeg(token),
+        ] if addr == &Address::Internal(InternalAddress::Ibc)
+            && prefix == PARAMS
+            && (limit == MINT_LIMIT
+                || limit == THROUGHPUT_LIMIT
+                || limit == MIN_TRANSFER_AMOUNT) =>
+        {
+            Some(token.clone())
+        }
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(limit),
+            DbKeySeg::AddressSeg(token),
+            DbKeySeg::StringSeg(port_id),
+            DbKeySeg::StringSeg(channel_id),
+        ] if addr == &Address::Internal(InternalAddress::Ibc)
+            && prefix == PARAMS
+            && limit == THROUGHPUT_LIMIT
+            && PortId::from_str(port_id).is_ok()
+            && ChannelId::from_str(channel_id).is_ok() =>
+        {
+            Some(token.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Returns the trace hash if the given key is for a blocked denom trace
+pub fn is_blocked_trace_key(key: &Key) -> Option<&String> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::StringSeg(blocked),
+            DbKeySeg::StringSeg(trace_hash),
+        ] if addr == &Address::Internal(InternalAddress::Ibc)
+            && prefix == PARAMS
+            && blocked == BLOCKED_TRACES =>
+        {
+            Some(trace_hash)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the token address if the given key is for the deposit
+pub fn is_deposit_key(key: &Key) -> Option<&Address> {
+    is_token_amount_key(key, DEPOSIT)
+}
+
+/// Returns the token address if the given key is for the withdrawal
+pub fn is_withdraw_key(key: &Key) -> Option<&Address> {
+    is_token_amount_key(key, WITHDRAW)
+}
+
+/// Returns the token address if the given key is for the escrowed amount
+pub fn is_escrow_amount_key(key: &Key) -> Option<&Address> {
+    is_token_amount_key(key, ESCROW)
+}
+
+/// Returns the token address if the given key is for the amount escrowed for
+/// the relayer fees
+pub fn is_fee_escrow_amount_key(key: &Key) -> Option<&Address> {
+    is_token_amount_key(key, FEE_ESCROW)
+}
+
+fn is_token_amount_key<'a>(key: &'a Key, seg: &str) -> Option<&'a Address> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::AddressSeg(token),
+        ] if addr == &Address::Internal(InternalAddress::Ibc)
+            && prefix == seg =>
+        {
+            Some(token)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the token address and the epoch if the given key is for a
+/// throughput record
+pub fn is_throughput_history_key(key: &Key) -> Option<(&Address, Epoch)> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::AddressSeg(token),
+            DbKeySeg::StringSeg(epoch),
+        ] if addr == &Address::Internal(InternalAddress::Ibc)
+            && prefix == THROUGHPUT_HISTORY =>
+        {
+            Epoch::parse(epoch.clone()).ok().map(|epoch| (token, epoch))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the token address, the port ID and the channel ID if the given key
+/// is for the deposit through a channel
+pub fn is_deposit_key_for_channel(
+    key: &Key,
+) -> Option<(&Address, PortId, ChannelId)> {
+    is_token_amount_key_for_channel(key, DEPOSIT)
+}
+
+/// Returns the token address, the port ID and the channel ID if the given key
+/// is for the withdrawal through a channel
+pub fn is_withdraw_key_for_channel(
+    key: &Key,
+) -> Option<(&Address, PortId, ChannelId)> {
+    is_token_amount_key_for_channel(key, WITHDRAW)
+}
+
+fn is_token_amount_key_for_channel<'a>(
+    key: &'a Key,
+    seg: &str,
+) -> Option<(&'a Address, PortId, ChannelId)> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::AddressSeg(token),
+            DbKeySeg::StringSeg(port_id),
+            DbKeySeg::StringSeg(channel_id),
+        ] if addr == &Address::Internal(InternalAddress::Ibc)
+            && prefix == seg =>
+        {
+            let port_id = PortId::from_str(port_id).ok()?;
+            let channel_id = ChannelId::from_str(channel_id).ok()?;
+            Some((token, port_id, channel_id))
+        }
+        _ => None,
+    }
+}
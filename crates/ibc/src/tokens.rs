@@ -0,0 +1,74 @@
+//! Enumeration of the IBC tokens known to the chain
+
+use std::collections::BTreeMap;
+
+use namada_core::address::Address;
+use namada_core::borsh::{BorshDeserialize, BorshSerialize};
+use namada_core::token::Amount;
+use namada_storage::{iter_prefix, Result, StorageRead};
+
+use crate::storage;
+
+/// An IBC token known to the chain with its trace and its amounts
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct IbcTokenInfo {
+    /// The IBC token address
+    pub address: Address,
+    /// The denom trace the token has been derived from
+    pub trace: String,
+    /// The total minted amount
+    pub minted: Amount,
+    /// The deposit in the current epoch
+    pub deposit: Amount,
+    /// The withdraw in the current epoch
+    pub withdraw: Amount,
+}
+
+/// List a page of the IBC tokens which have been received, ordered by their
+/// addresses. The pages are numbered from `0`.
+///
+/// The tokens are enumerated from the denom traces rather than from the
+/// lookup of the denoms by the tokens, so that the tokens received before the
+/// lookup was introduced are listed too. A trace is written under every owner
+/// receiving it, so the tokens are deduplicated by their addresses. Only the
+/// amounts of the tokens of the page are read.
+pub fn list_ibc_tokens<S: StorageRead>(
+    storage: &S,
+    page: u32,
+    per_page: u8,
+) -> Result<Vec<IbcTokenInfo>> {
+    let mut traces = BTreeMap::new();
+    for result in
+        iter_prefix::<String>(storage, &storage::ibc_denom_key_prefix(None))?
+    {
+        let (key, trace) = result?;
+        if storage::is_ibc_denom_key(&key).is_some() {
+            traces.entry(storage::ibc_token(&trace)).or_insert(trace);
+        }
+    }
+
+    let per_page = usize::from(per_page);
+    traces
+        .into_iter()
+        .skip(per_page.saturating_mul(page as usize))
+        .take(per_page)
+        .map(|(address, trace)| {
+            let minted = storage
+                .read(&storage::mint_amount_key(&address))?
+                .unwrap_or_default();
+            let deposit = storage
+                .read(&storage::deposit_key(&address))?
+                .unwrap_or_default();
+            let withdraw = storage
+                .read(&storage::withdraw_key(&address))?
+                .unwrap_or_default();
+            Ok(IbcTokenInfo {
+                address,
+                trace,
+                minted,
+                deposit,
+                withdraw,
+            })
+        })
+        .collect()
+}
@@ -109,6 +109,7 @@ impl VoteProposal {
         vote: ProposalVote,
         voter: Address,
         delegations: Vec<Address>,
+        memo: Option<String>,
         args: GlobalArgs,
     ) -> Self {
         let vote_proposal = namada_sdk::governance::VoteProposalData {
@@ -116,6 +117,7 @@ impl VoteProposal {
             vote,
             voter,
             delegations,
+            memo,
         };
 
         Self(transaction::build_tx(
@@ -465,6 +465,7 @@ impl ChangeMetaData {
         discord_handle: Option<String>,
         avatar: Option<String>,
         commission_rate: Option<Dec>,
+        max_total_delegated: Option<token::Amount>,
         args: GlobalArgs,
     ) -> Self {
         let init_proposal = namada_sdk::tx::data::pos::MetaDataChange {
@@ -475,6 +476,7 @@ impl ChangeMetaData {
             discord_handle,
             avatar,
             commission_rate,
+            max_total_delegated,
         };
 
         Self(transaction::build_tx(
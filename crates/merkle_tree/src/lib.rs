@@ -20,6 +20,8 @@ use namada_core::borsh::{BorshDeserialize, BorshSerialize, BorshSerializeExt};
 use namada_core::bytes::ByteBuf;
 use namada_core::eth_bridge_pool::{is_pending_transfer_key, PendingTransfer};
 use namada_core::hash::{Hash, StorageHasher};
+use namada_core::ibc::core::commitment_types::commitment::CommitmentProofBytes;
+use namada_core::ibc::core::commitment_types::merkle::MerkleProof;
 use namada_core::keccak::KeccakHash;
 use namada_core::storage::{
     self, BlockHeight, DbKeySeg, Epoch, Error as StorageError, Key, KeySeg,
@@ -502,6 +504,32 @@ impl<H: StorageHasher + Default> MerkleTree<H> {
         Ok(())
     }
 
+    /// Delete the values corresponding to the given keys. The base tree is
+    /// only updated once per affected sub-tree.
+    pub fn delete_keys<'a>(
+        &mut self,
+        keys: impl IntoIterator<Item = &'a Key>,
+    ) -> Result<()> {
+        let mut sub_roots: Vec<(StoreType, Hash)> = vec![];
+        for key in keys {
+            let (store_type, sub_key) = StoreType::sub_key(key)?;
+            let sub_root =
+                self.tree_mut(&store_type).subtree_delete(&sub_key)?;
+            if store_type == StoreType::Base {
+                continue;
+            }
+            match sub_roots.iter_mut().find(|(st, _)| *st == store_type) {
+                Some((_, root)) => *root = sub_root,
+                None => sub_roots.push((store_type, sub_root)),
+            }
+        }
+        for (store_type, sub_root) in sub_roots {
+            let base_key = H::hash(store_type.to_string());
+            self.base.update(base_key.into(), sub_root)?;
+        }
+        Ok(())
+    }
+
     /// Get the root
     pub fn root(&self) -> MerkleRoot {
         self.base.root().into()
@@ -555,16 +583,28 @@ impl<H: StorageHasher + Default> MerkleTree<H> {
             .subtree_membership_proof(std::array::from_ref(&sub_key), values)
     }
 
-    /// Get the non-existence proof
+    /// Get the non-existence proof. The sub proof is verified against the
+    /// sub tree spec of `ibc_proof_specs` with the key given by
+    /// [`non_existence_proof_key`] because the neighbor leaves are proven
+    /// with their keys and values as stored in the sub tree.
     pub fn get_non_existence_proof(&self, key: &Key) -> Result<Proof> {
         let (store_type, sub_key) = StoreType::sub_key(key)?;
-        if store_type != StoreType::Ibc {
-            return Err(Error::NonExistenceProof(store_type.to_string()));
-        }
-
-        let string_key =
-            StringKey::try_from_bytes(sub_key.to_string().as_bytes())?;
-        let mut nep = self.ibc.non_membership_proof(&string_key)?;
+        let mut nep = match store_type {
+            StoreType::Ibc => {
+                let string_key =
+                    StringKey::try_from_bytes(sub_key.to_string().as_bytes())?;
+                self.ibc.non_membership_proof(&string_key)?
+            }
+            StoreType::Account => self
+                .account
+                .non_membership_proof(&H::hash(sub_key.to_string()).into())?,
+            StoreType::PoS => self
+                .pos
+                .non_membership_proof(&H::hash(sub_key.to_string()).into())?,
+            StoreType::Base | StoreType::BridgePool => {
+                return Err(Error::NonExistenceProof(store_type.to_string()));
+            }
+        };
         // Replace the values and the leaf op for the verification
         if let Some(ref mut nep) = nep.proof {
             match nep {
@@ -621,6 +661,22 @@ impl<H: StorageHasher + Default> MerkleTree<H> {
     }
 }
 
+/// Get the key with which the non-existence proof of the given storage key is
+/// verified against the root of its sub tree. The sub tree of IBC stores the
+/// sub key as it is, while the other sub trees store the hashed sub key.
+pub fn non_existence_proof_key<H: StorageHasher>(key: &Key) -> Result<Vec<u8>> {
+    let (store_type, sub_key) = StoreType::sub_key(key)?;
+    match store_type {
+        StoreType::Ibc => Ok(sub_key.to_string().into_bytes()),
+        StoreType::Account | StoreType::PoS => {
+            Ok(H::hash(sub_key.to_string()).as_slice().to_vec())
+        }
+        StoreType::Base | StoreType::BridgePool => {
+            Err(Error::NonExistenceProof(store_type.to_string()))
+        }
+    }
+}
+
 /// The root hash of the merkle tree as bytes
 #[derive(PartialEq)]
 pub struct MerkleRoot(pub [u8; 32]);
@@ -812,6 +868,24 @@ impl From<Proof> for namada_core::tendermint::merkle::proof::ProofOps {
     }
 }
 
+impl From<Proof> for CommitmentProofBytes {
+    fn from(
+        Proof {
+            sub_proof,
+            base_proof,
+            ..
+        }: Proof,
+    ) -> Self {
+        // The proofs are ordered from leaf to root as the proof specs
+        let merkle_proof = MerkleProof {
+            proofs: vec![sub_proof, base_proof],
+        };
+        merkle_proof
+            .try_into()
+            .expect("Encoding a non-empty proof shouldn't fail")
+    }
+}
+
 impl<'a, H: StorageHasher + Default> SubTreeRead for &'a Smt<H> {
     fn root(&self) -> MerkleRoot {
         Smt::<H>::root(self).into()
@@ -1092,6 +1166,41 @@ mod test {
         assert!(basetree_verification_res);
     }
 
+    #[test]
+    fn test_delete_keys() {
+        let ibc_prefix: Key =
+            Address::Internal(InternalAddress::Ibc).to_db_key().into();
+        let pos_prefix: Key =
+            Address::Internal(InternalAddress::PoS).to_db_key().into();
+        let keys: Vec<Key> = (0..10)
+            .flat_map(|i| {
+                [
+                    ibc_prefix.push(&format!("test{i}")).unwrap(),
+                    pos_prefix.push(&format!("test{i}")).unwrap(),
+                ]
+            })
+            .collect();
+
+        let mut tree = MerkleTree::<Sha256Hasher>::default();
+        let mut expected = MerkleTree::<Sha256Hasher>::default();
+        for key in &keys {
+            tree.update(key, [1u8; 8]).unwrap();
+            expected.update(key, [1u8; 8]).unwrap();
+        }
+
+        // delete every other key in bulk and one by one
+        let deleted: Vec<_> = keys.iter().step_by(2).collect();
+        tree.delete_keys(deleted.iter().copied()).unwrap();
+        for key in &deleted {
+            expected.delete(key).unwrap();
+        }
+
+        assert_eq!(tree.root().0, expected.root().0);
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(tree.has_key(key).unwrap(), i % 2 == 1);
+        }
+    }
+
     #[test]
     fn test_restore_tree() {
         let mut tree = MerkleTree::<Sha256Hasher>::default();
@@ -1310,4 +1419,67 @@ mod test {
             );
         assert!(basetree_verification_res);
     }
+
+    #[test]
+    fn test_non_ibc_non_existence_proof() {
+        let mut tree = MerkleTree::<Sha256Hasher>::default();
+
+        let key_prefix: Key =
+            Address::Internal(InternalAddress::PoS).to_db_key().into();
+        let pos_non_key =
+            key_prefix.push(&"test".to_string()).expect("Test failed");
+        let pos_key =
+            key_prefix.push(&"test2".to_string()).expect("Test failed");
+        let pos_val = [2u8; 8].to_vec();
+        tree.update(&pos_key, pos_val).expect("Test failed");
+
+        let nep = tree
+            .get_non_existence_proof(&pos_non_key)
+            .expect("Test failed");
+        let nep_commitment_proof = nep.sub_proof;
+        let non_existence_proof =
+            match nep_commitment_proof.clone().proof.expect("Test failed") {
+                Ics23Proof::Nonexist(nep) => nep,
+                _ => unreachable!(),
+            };
+        let subtree_root = if let Some(left) = &non_existence_proof.left {
+            ics23::calculate_existence_root::<HostFunctionsManager>(left)
+                .unwrap()
+        } else if let Some(right) = &non_existence_proof.right {
+            ics23::calculate_existence_root::<HostFunctionsManager>(right)
+                .unwrap()
+        } else {
+            unreachable!()
+        };
+        let specs = ibc_proof_specs::<Sha256Hasher>();
+
+        // The hashed key is verified because the sub tree stores the hashed
+        // keys
+        let key = non_existence_proof_key::<Sha256Hasher>(&pos_non_key)
+            .expect("Test failed");
+        assert!(ics23::verify_non_membership::<HostFunctionsManager>(
+            &nep_commitment_proof,
+            &specs[0],
+            &subtree_root,
+            &key,
+        ));
+        let basetree_ep_commitment_proof = nep.base_proof;
+        let basetree_ics23_ep =
+            match basetree_ep_commitment_proof.clone().proof.unwrap() {
+                Ics23Proof::Exist(ep) => ep,
+                _ => unreachable!(),
+            };
+        let basetree_root = ics23::calculate_existence_root::<
+            HostFunctionsManager,
+        >(&basetree_ics23_ep)
+        .unwrap();
+        assert!(ics23::verify_membership::<HostFunctionsManager>(
+            &basetree_ep_commitment_proof,
+            &specs[1],
+            &basetree_root,
+            StoreType::PoS.to_string().as_bytes(),
+            &subtree_root,
+        ));
+        assert_eq!(basetree_root, tree.root().0);
+    }
 }
@@ -0,0 +1,654 @@
+//! A facade to embed the ledger state machine in a library, without the node.
+//!
+//! The [`Engine`] applies blocks of txs to a state backed by any
+//! [`DB`] implementation, running the same wasm execution and native VPs
+//! pipeline as the node. The node's block application is built on the
+//! functions of this module, including the commit of the tx results and the
+//! events they emit, so the two cannot drift.
+//!
+//! # Stability
+//!
+//! The [`Engine`] methods, the [`Error`] variants and the signatures of the
+//! free functions of this module are only changed in a breaking release and
+//! such changes are listed in the changelog. The results of applying a tx
+//! (the [`TxResult`], the events and the merkle root) are expected to change
+//! with the protocol, as they do for the node.
+//!
+//! The [`Engine`] applies the protocol's block-level updates like the node's
+//! shell: the governance, token, IBC, PoS and inflation updates when a block
+//! begins, see [`finalize_governance`] and [`finalize_block`], and the MASP
+//! anchor and the block proposer when it ends, see [`end_block`]. The wrapper
+//! txs queue, the storage migrations and the Ethereum bridge are still driven
+//! by the shell.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+use borsh::BorshDeserialize;
+use masp_primitives::merkle_tree::CommitmentTree;
+use masp_primitives::sapling::Node;
+use namada_core::address::Address;
+use namada_core::storage::{BlockHash, BlockHeight, Epoch, Header, TxIndex};
+use namada_core::tendermint::abci::types::Misbehavior;
+use namada_gas::{Gas, GasMetering, TxGasMeter};
+use namada_proof_of_stake::storage::write_last_block_proposer_address;
+use namada_proof_of_stake::types::VoteInfo;
+use namada_state::merkle_tree::MerkleRoot;
+use namada_state::write_log::{Savepoint, StorageModification, WriteLog};
+use namada_state::{
+    DBIter, FullAccessState, ResultExt, StorageHasher, StorageResult,
+    StorageWrite, WlState, DB, EPOCH_SWITCH_BLOCKS_DELAY,
+};
+use namada_tx::data::{
+    truncate_error, DecryptedTx, ResultCode, TxResult, TxType,
+};
+use namada_tx::{new_tx_event, DecodeError, Tx, TxError};
+use thiserror::Error;
+
+use crate::ledger::events::Event;
+use crate::ledger::governance::finalize as governance;
+use crate::ledger::ibc::finalize_epoch_throughputs;
+use crate::ledger::parameters;
+use crate::ledger::protocol::{self, WrapperArgs};
+use crate::token;
+use crate::vm::wasm::{TxCache, VpCache};
+use crate::vm::WasmCacheAccess;
+
+#[allow(missing_docs)]
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Error decoding the tx: {0}")]
+    TxDecoding(DecodeError),
+    #[error("Invalid tx: {0}")]
+    InvalidTx(TxError),
+    #[error("Only decrypted txs can be applied by the engine")]
+    UnsupportedTxType,
+    #[error("A block must be begun before applying a tx")]
+    NoBlock,
+    #[error("Storage error: {0}")]
+    StorageError(namada_state::StorageError),
+    #[error("Error applying the tx: {0}")]
+    TxApply(protocol::Error),
+}
+
+/// Result of the engine's operations
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Begin a new block with the given header. Returns the height of the new
+/// block and whether a new epoch has begun.
+pub fn begin_block<D, H>(
+    state: &mut FullAccessState<D, H>,
+    header: Header,
+    hash: BlockHash,
+) -> StorageResult<(BlockHeight, bool)>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    let height = state.in_mem().get_last_block_height() + 1;
+    state.in_mem_mut().begin_block(hash, height)?;
+    let header_time = header.time;
+    state.in_mem_mut().set_header(header)?;
+    let new_epoch = state.update_epoch(height, header_time)?;
+    Ok((height, new_epoch))
+}
+
+/// Get the first epoch whose validator sets can still be updated in the
+/// current block
+pub fn validator_set_update_epoch<D, H>(
+    state: &FullAccessState<D, H>,
+    current_epoch: Epoch,
+) -> Epoch
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    match state.in_mem().update_epoch_blocks_delay {
+        // If we're waiting to switch to a new epoch, it's too late to update
+        // the validator sets of the next epoch, so we need to wait for the one
+        // after
+        Some(delay) if delay != EPOCH_SWITCH_BLOCKS_DELAY => {
+            current_epoch.next().next()
+        }
+        _ => current_epoch.next(),
+    }
+}
+
+/// Apply the governance updates of a new epoch, executing the given
+/// proposals. The updates are rolled back on failure and the error is
+/// returned to halt the block finalization. This has to be applied before
+/// [`finalize_block`], in case a proposal changes any of the other
+/// sub-systems.
+pub fn finalize_governance<D, H, CA>(
+    state: &mut FullAccessState<D, H>,
+    events: &mut Vec<Event>,
+    new_epoch: bool,
+    proposals: BTreeSet<u64>,
+    vp_wasm_cache: &mut VpCache<CA>,
+    tx_wasm_cache: &mut TxCache<CA>,
+) -> StorageResult<()>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+    CA: 'static + WasmCacheAccess + Sync,
+{
+    finalize_stage(state, "governance", |state| {
+        governance::finalize_block(
+            state,
+            events,
+            new_epoch,
+            proposals,
+            vp_wasm_cache,
+            tx_wasm_cache,
+        )
+    })
+}
+
+/// Apply the protocol's updates of the token, IBC and PoS sub-systems and the
+/// inflation of a new epoch, before the txs of the block. Each update is
+/// rolled back on failure and the error is returned to halt the block
+/// finalization. The governance updates of [`finalize_governance`] are
+/// expected to be applied before, in case a proposal changes any of the other
+/// sub-systems.
+pub fn finalize_block<D, H>(
+    state: &mut FullAccessState<D, H>,
+    events: &mut Vec<Event>,
+    new_epoch: bool,
+    votes: Vec<VoteInfo>,
+    byzantine_validators: Vec<Misbehavior>,
+) -> StorageResult<()>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    let current_epoch = state.in_mem().block.epoch;
+    let validator_set_update_epoch =
+        validator_set_update_epoch(state, current_epoch);
+
+    finalize_stage(state, "token", |state| {
+        namada_token::finalize_block(state, events, new_epoch)
+    })?;
+    if new_epoch {
+        finalize_stage(state, "IBC throughput", |state| {
+            finalize_epoch_throughputs(state, events, current_epoch.prev())
+        })?;
+    }
+    // Must be applied after governance in case it changes PoS params
+    finalize_stage(state, "PoS", |state| {
+        namada_proof_of_stake::finalize_block(
+            state,
+            events,
+            new_epoch,
+            validator_set_update_epoch,
+            votes,
+            byzantine_validators,
+        )
+    })?;
+
+    // Take IBC events that may be emitted from PGF
+    let height = state.in_mem().block.height;
+    for ibc_event in state.write_log_mut().take_ibc_events() {
+        let mut event = Event::from(ibc_event);
+        // Add the height for IBC event query
+        event["height"] = height.to_string();
+        events.push(event);
+    }
+
+    if new_epoch {
+        finalize_stage(state, "inflation", |state| {
+            apply_inflation(state, current_epoch)
+        })?;
+    }
+    Ok(())
+}
+
+/// Calculate the new inflation rate, mint the new tokens to the PoS account,
+/// then update the reward products of the validators. This is executed while
+/// finalizing the first block of a new epoch and is applied with respect to
+/// the previous epoch.
+fn apply_inflation<D, H>(
+    state: &mut FullAccessState<D, H>,
+    current_epoch: Epoch,
+) -> StorageResult<()>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    let last_epoch = current_epoch.prev();
+
+    // Get the number of blocks in the last epoch
+    let first_block_of_last_epoch =
+        state.in_mem().block.pred_epochs.first_block_heights
+            [last_epoch.0 as usize]
+            .0;
+    let num_blocks_in_last_epoch =
+        state.in_mem().block.height.0 - first_block_of_last_epoch;
+
+    // PoS inflation
+    namada_proof_of_stake::rewards::apply_inflation(
+        state,
+        last_epoch,
+        num_blocks_in_last_epoch,
+    )?;
+
+    // Pgf inflation
+    namada_governance::pgf::inflation::apply_inflation(
+        state.restrict_writes_to_write_log(),
+        namada_ibc::transfer_over_ibc,
+    )
+}
+
+/// Run a stage of the block finalization in a savepoint of the write log
+fn finalize_stage<D, H, T>(
+    state: &mut FullAccessState<D, H>,
+    stage: &str,
+    run: impl FnOnce(&mut FullAccessState<D, H>) -> StorageResult<T>,
+) -> StorageResult<T>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    let savepoint = state.write_log_mut().savepoint();
+    let result = run(state);
+    close_stage(state.write_log_mut(), stage, savepoint, &result);
+    result
+}
+
+/// Close a stage of the block finalization opened at the given savepoint.
+/// When the stage failed, its partial writes are rolled back.
+pub fn close_stage<T, E>(
+    write_log: &mut WriteLog,
+    stage: &str,
+    savepoint: Savepoint,
+    result: &std::result::Result<T, E>,
+) where
+    E: std::fmt::Display,
+{
+    let released = match result {
+        Ok(_) => write_log.release(savepoint),
+        Err(err) => {
+            tracing::error!(
+                "The {stage} stage of the block finalization failed, rolling \
+                 back its writes: {err}"
+            );
+            write_log.rollback_to(savepoint)
+        }
+    };
+    released.expect("The savepoint of the stage must be active");
+}
+
+/// Check that the tx is allowed and apply it. The changes of the tx are left
+/// in the write log, for the caller to either commit or drop them.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_tx<D, H, CA>(
+    tx: Tx,
+    tx_bytes: &[u8],
+    tx_index: TxIndex,
+    tx_gas_meter: &RefCell<TxGasMeter>,
+    state: &mut WlState<D, H>,
+    vp_wasm_cache: &mut VpCache<CA>,
+    tx_wasm_cache: &mut TxCache<CA>,
+    wrapper_args: Option<&mut WrapperArgs>,
+) -> protocol::Result<TxResult>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+    CA: 'static + WasmCacheAccess + Sync,
+{
+    protocol::check_tx_allowed(&tx, state)?;
+    protocol::dispatch_tx(
+        tx,
+        tx_bytes,
+        tx_index,
+        tx_gas_meter,
+        state,
+        vp_wasm_cache,
+        tx_wasm_cache,
+        wrapper_args,
+    )
+}
+
+/// Render the error of a failed tx for its event, truncated to the
+/// `max_tx_error_bytes` protocol parameter.
+pub fn tx_error_info<D, H>(
+    state: &WlState<D, H>,
+    err: impl std::fmt::Display,
+) -> StorageResult<String>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    let max_bytes = parameters::max_tx_error_bytes(state)?;
+    let msg = err.to_string();
+    Ok(
+        truncate_error(&msg, usize::try_from(max_bytes).unwrap_or(usize::MAX))
+            .unwrap_or(msg),
+    )
+}
+
+/// Commit the changes of an applied tx if it's accepted, otherwise drop them,
+/// and fill its event with the result. Returns the events emitted by an
+/// accepted tx. The result code already set in the event of an accepted tx is
+/// kept.
+pub fn finalize_tx_result<D, H>(
+    state: &mut FullAccessState<D, H>,
+    tx_event: &mut Event,
+    tx_index: usize,
+    result: &TxResult,
+) -> Vec<Event>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    let height = state.in_mem().block.height;
+    let mut events = vec![];
+    if result.is_accepted() {
+        state.commit_tx();
+        if !tx_event.contains_key("code") {
+            tx_event["code"] = ResultCode::Ok.into();
+            state.in_mem_mut().block.results.accept(tx_index);
+        }
+        // events from other sources
+        events.extend(
+            result
+                .ibc_events
+                .iter()
+                .cloned()
+                .map(|ibc_event| {
+                    // Add the IBC event besides the tx_event
+                    let mut event = Event::from(ibc_event);
+                    event["height"] = height.to_string();
+                    event
+                })
+                // other events emitted by the tx
+                .chain(result.events.iter().cloned().map(|mut event| {
+                    event["height"] = height.to_string();
+                    event
+                }))
+                // eth bridge events
+                .chain(result.eth_bridge_events.iter().map(Event::from)),
+        );
+    } else {
+        state.drop_tx();
+        tx_event["code"] = ResultCode::InvalidTx.into();
+    }
+    tx_event["gas_used"] = result.gas_used.to_string();
+    // Only present when gas tracing is enabled
+    for (label, gas) in &result.vps_result.gas_breakdown {
+        let key = format!("gas_breakdown.{label}");
+        tx_event[key.as_str()] = gas.to_string();
+    }
+    tx_event["info"] = "Check inner_tx for result.".to_string();
+    tx_event["inner_tx"] = result.to_string();
+    events
+}
+
+/// Drop the changes of a tx that failed to be applied and fill its event with
+/// the error and the given result code
+pub fn finalize_tx_error<D, H>(
+    state: &mut FullAccessState<D, H>,
+    tx_event: &mut Event,
+    gas_used: Gas,
+    code: ResultCode,
+    err: impl std::fmt::Display,
+) -> StorageResult<()>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    state.drop_tx();
+    tx_event["gas_used"] = gas_used.to_string();
+    tx_event["info"] = tx_error_info(state, err)?;
+    tx_event["code"] = code.into();
+    Ok(())
+}
+
+/// Apply a tx in the current block with the given function, which leaves its
+/// changes in the write log. The changes of an accepted tx are committed to
+/// the block, otherwise they are dropped. Returns the result of the tx and
+/// the events, starting with the event of the tx.
+pub fn apply_tx<D, H>(
+    state: &mut FullAccessState<D, H>,
+    mut tx_event: Event,
+    tx_index: TxIndex,
+    tx_gas_meter: &RefCell<TxGasMeter>,
+    execute: impl FnOnce(&mut FullAccessState<D, H>) -> protocol::Result<TxResult>,
+) -> StorageResult<(protocol::Result<TxResult>, Vec<Event>)>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    let tx_result = execute(state);
+    let mut events = vec![];
+    match &tx_result {
+        Ok(result) => {
+            events = finalize_tx_result(
+                state,
+                &mut tx_event,
+                tx_index.0 as usize,
+                result,
+            );
+        }
+        Err(err) => {
+            let gas_used = tx_gas_meter.borrow().get_tx_consumed_gas();
+            finalize_tx_error(
+                state,
+                &mut tx_event,
+                gas_used,
+                ResultCode::WasmRuntimeError,
+                err,
+            )?;
+        }
+    }
+    events.insert(0, tx_event);
+    Ok((tx_result, events))
+}
+
+/// Apply the protocol's updates after the txs of the block: record the anchor
+/// of the MASP commitment tree if a tx updated it, and the proposer of the
+/// block, if any, for the PoS rewards of the next block.
+pub fn end_block<D, H>(
+    state: &mut FullAccessState<D, H>,
+    proposer: Option<&Address>,
+) -> StorageResult<()>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    // Update the MASP commitment tree anchor if the tree was updated
+    let tree_key = token::storage_key::masp_commitment_tree_key();
+    if let Some(StorageModification::Write { value }) =
+        state.write_log().read(&tree_key).0
+    {
+        let updated_tree = CommitmentTree::<Node>::try_from_slice(value)
+            .into_storage_result()?;
+        let anchor_key =
+            token::storage_key::masp_commitment_anchor_key(updated_tree.root());
+        state.write(&anchor_key, ())?;
+    }
+
+    if let Some(proposer) = proposer {
+        write_last_block_proposer_address(state, proposer.clone())?;
+    }
+    Ok(())
+}
+
+/// Commit the block to the DB. Returns the new merkle root.
+pub fn commit_block<D, H>(
+    state: &mut FullAccessState<D, H>,
+) -> StorageResult<MerkleRoot>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    state.commit_block()?;
+    Ok(state.in_mem().merkle_root())
+}
+
+/// The state of the block being applied
+#[derive(Debug)]
+struct BlockInProgress {
+    height: BlockHeight,
+    next_tx_index: u32,
+    events: Vec<Event>,
+}
+
+/// The ledger state machine, applying blocks of txs to a state
+#[derive(Debug)]
+pub struct Engine<D, H, CA>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+    CA: 'static + WasmCacheAccess + Sync,
+{
+    state: FullAccessState<D, H>,
+    vp_wasm_cache: VpCache<CA>,
+    tx_wasm_cache: TxCache<CA>,
+    block: Option<BlockInProgress>,
+}
+
+impl<D, H, CA> Engine<D, H, CA>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+    CA: 'static + WasmCacheAccess + Sync,
+{
+    /// Create an engine over the given state and wasm caches
+    pub fn new(
+        state: FullAccessState<D, H>,
+        vp_wasm_cache: VpCache<CA>,
+        tx_wasm_cache: TxCache<CA>,
+    ) -> Self {
+        Self {
+            state,
+            vp_wasm_cache,
+            tx_wasm_cache,
+            block: None,
+        }
+    }
+
+    /// Read-only access to the state
+    pub fn state(&self) -> &FullAccessState<D, H> {
+        &self.state
+    }
+
+    /// Mutable access to the state, e.g. to initialize the genesis storage
+    pub fn state_mut(&mut self) -> &mut FullAccessState<D, H> {
+        &mut self.state
+    }
+
+    /// Take the state back from the engine
+    pub fn into_state(self) -> FullAccessState<D, H> {
+        self.state
+    }
+
+    /// Begin a new block and apply the protocol's updates of the governance,
+    /// token, IBC and PoS sub-systems and the inflation, like the node does
+    /// before the txs of a block. The votes and the misbehaviours are the
+    /// ones reported by the consensus for the last block. Returns the height
+    /// of the new block and whether a new epoch has begun.
+    pub fn begin_block(
+        &mut self,
+        header: Header,
+        hash: BlockHash,
+        votes: Vec<VoteInfo>,
+        byzantine_validators: Vec<Misbehavior>,
+    ) -> Result<(BlockHeight, bool)> {
+        // The proposals committed for execution at the end of the last
+        // committed epoch
+        let last_epoch = self.state.in_mem().last_epoch;
+        let (height, new_epoch) = begin_block(&mut self.state, header, hash)
+            .map_err(Error::StorageError)?;
+
+        let mut events = vec![];
+        let proposals = if new_epoch {
+            governance::load_proposals(&self.state, last_epoch)
+        } else {
+            BTreeSet::new()
+        };
+        finalize_governance(
+            &mut self.state,
+            &mut events,
+            new_epoch,
+            proposals,
+            &mut self.vp_wasm_cache,
+            &mut self.tx_wasm_cache,
+        )
+        .map_err(Error::StorageError)?;
+        finalize_block(
+            &mut self.state,
+            &mut events,
+            new_epoch,
+            votes,
+            byzantine_validators,
+        )
+        .map_err(Error::StorageError)?;
+
+        self.block = Some(BlockInProgress {
+            height,
+            next_tx_index: 0,
+            events,
+        });
+        Ok((height, new_epoch))
+    }
+
+    /// Apply a decrypted tx in the current block. The gas limit of the tx is
+    /// the block's gas limit. The changes of an accepted tx are committed to
+    /// the block, otherwise they are dropped.
+    pub fn apply_tx(&mut self, tx_bytes: &[u8]) -> Result<TxResult> {
+        let block = self.block.as_mut().ok_or(Error::NoBlock)?;
+        let tx = Tx::try_from(tx_bytes).map_err(Error::TxDecoding)?;
+        tx.validate_tx().map_err(Error::InvalidTx)?;
+        if !matches!(
+            tx.header().tx_type,
+            TxType::Decrypted(DecryptedTx::Decrypted)
+        ) {
+            return Err(Error::UnsupportedTxType);
+        }
+
+        let tx_index = TxIndex(block.next_tx_index);
+        block.next_tx_index += 1;
+        let tx_event = new_tx_event(&tx, block.height.0);
+        let max_block_gas = parameters::get_max_block_gas(&self.state)
+            .map_err(Error::StorageError)?;
+        let tx_gas_meter = RefCell::new(TxGasMeter::new(max_block_gas));
+        let (tx_result, events) = apply_tx(
+            &mut self.state,
+            tx_event,
+            tx_index,
+            &tx_gas_meter,
+            |state| {
+                execute_tx(
+                    tx,
+                    tx_bytes,
+                    tx_index,
+                    &tx_gas_meter,
+                    state,
+                    &mut self.vp_wasm_cache,
+                    &mut self.tx_wasm_cache,
+                    None,
+                )
+            },
+        )
+        .map_err(Error::StorageError)?;
+        block.events.extend(events);
+        tx_result.map_err(Error::TxApply)
+    }
+
+    /// End the current block, recording its proposer, if any. Returns the
+    /// events emitted by the protocol's updates and by the txs of the block.
+    pub fn end_block(
+        &mut self,
+        proposer: Option<&Address>,
+    ) -> Result<Vec<Event>> {
+        let block = self.block.as_mut().ok_or(Error::NoBlock)?;
+        end_block(&mut self.state, proposer).map_err(Error::StorageError)?;
+        Ok(std::mem::take(&mut block.events))
+    }
+
+    /// Commit the current block. Returns the new merkle root.
+    pub fn commit(&mut self) -> Result<MerkleRoot> {
+        self.block.take().ok_or(Error::NoBlock)?;
+        commit_block(&mut self.state).map_err(Error::StorageError)
+    }
+}
@@ -1,106 +1,199 @@
-use std::collections::HashMap;
-
-use namada::core::encode;
-use namada::core::event::EmitEvents;
-use namada::core::storage::Epoch;
-use namada::governance::pgf::storage::keys as pgf_storage;
-use namada::governance::pgf::storage::steward::StewardDetail;
-use namada::governance::pgf::{storage as pgf, ADDRESS};
-use namada::governance::storage::keys as gov_storage;
-use namada::governance::storage::proposal::{
+//! The governance updates of the block finalization, executing the proposals
+//! at the beginning of their activation epoch
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+use borsh::BorshDeserialize;
+use namada_core::event::EmitEvents;
+use namada_governance::pgf::storage::keys as pgf_storage;
+use namada_governance::pgf::storage::steward::StewardDetail;
+use namada_governance::pgf::{storage as pgf, ADDRESS as PGF_ADDRESS};
+use namada_governance::storage::keys as gov_storage;
+use namada_governance::storage::proposal::{
     AddRemove, PGFAction, PGFTarget, ProposalType, StoragePgfFunding,
 };
-use namada::governance::utils::{
-    compute_proposal_result, ProposalVotes, TallyResult, TallyType, TallyVote,
-    VotePower,
+use namada_governance::utils::{
+    compute_proposal_result, tally_threshold, ProposalVotes, TallyResult,
+    TallyType, TallyVote, VotePower,
 };
-use namada::governance::{storage as gov_api, ADDRESS as gov_address};
-use namada::ibc;
-use namada::ledger::governance::utils::ProposalEvent;
-use namada::ledger::pos::BondId;
-use namada::proof_of_stake::bond_amount;
-use namada::proof_of_stake::parameters::PosParams;
-use namada::proof_of_stake::storage::read_total_stake;
-use namada::state::StorageWrite;
-use namada::tx::{Code, Data};
-use namada_sdk::proof_of_stake::storage::read_validator_stake;
-
-use super::utils::force_read;
-use super::*;
-
-pub fn finalize_block<D, H>(
-    shell: &mut Shell<D, H>,
+use namada_governance::{storage as gov_api, ADDRESS as GOV_ADDRESS};
+use namada_proof_of_stake::bond_amount;
+use namada_proof_of_stake::parameters::PosParams;
+use namada_proof_of_stake::storage::{
+    read_consensus_validator_set_addresses_with_stake, read_pos_params,
+    read_total_stake, read_validator_stake,
+};
+use namada_proof_of_stake::types::BondId;
+use namada_state::{
+    DBIter, StateRead, StorageHasher, StorageRead, StorageResult, StorageWrite,
+    WlState, DB,
+};
+use namada_tx::data::{DecryptedTx, TxType};
+use namada_tx::{Code, Data, Tx};
+
+use crate::address::Address;
+use crate::ledger::events::Event;
+use crate::ledger::gas::TxGasMeter;
+use crate::ledger::governance::utils::{
+    finalize_proposal_result, ProposalEvent,
+};
+use crate::ledger::protocol;
+use crate::storage::{Epoch, Key, TxIndex};
+use crate::token;
+use crate::vm::wasm::{TxCache, VpCache};
+use crate::vm::WasmCacheAccess;
+
+/// Load the ids of the proposals to be executed at the beginning of the epoch
+/// following the given one
+pub fn load_proposals<S>(state: &S, epoch: Epoch) -> BTreeSet<u64>
+where
+    S: StateRead,
+{
+    let proposals_key = gov_storage::get_commiting_proposals_prefix(epoch.0);
+
+    let (proposal_iter, _) = state.db_iter_prefix(&proposals_key);
+    let mut proposals = BTreeSet::new();
+    for (key, _, _) in proposal_iter {
+        let key = Key::from_str(key.as_str()).expect("Key should be parsable");
+        if gov_storage::get_commit_proposal_epoch(&key).unwrap() != epoch.0 {
+            // NOTE: `iter_prefix` iterate over the matching prefix. In this
+            // case  a proposal with grace_epoch 110 will be
+            // matched by prefixes  1, 11 and 110. Thus we
+            // have to skip to the next iteration of
+            //  the cycle for all the prefixes that don't actually match
+            //  the desired epoch.
+            continue;
+        }
+
+        if let Some(id) = gov_storage::get_commit_proposal_id(&key) {
+            proposals.insert(id);
+        }
+    }
+    proposals
+}
+
+/// Apply the governance updates of a new epoch: snapshot the voting power of
+/// the consensus validators and execute the given proposals
+pub fn finalize_block<D, H, CA>(
+    state: &mut WlState<D, H>,
     events: &mut impl EmitEvents,
     is_new_epoch: bool,
-) -> Result<()>
+    proposals: BTreeSet<u64>,
+    vp_wasm_cache: &mut VpCache<CA>,
+    tx_wasm_cache: &mut TxCache<CA>,
+) -> StorageResult<()>
 where
     D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
     H: 'static + StorageHasher + Sync,
+    CA: 'static + WasmCacheAccess + Sync,
 {
     if is_new_epoch {
-        execute_governance_proposals(shell, events)?;
+        let current_epoch = state.in_mem().block.epoch;
+        snapshot_voting_power(state, current_epoch)?;
+        execute_governance_proposals(
+            state,
+            events,
+            proposals,
+            vp_wasm_cache,
+            tx_wasm_cache,
+        )?;
     }
     Ok(())
 }
 
-#[derive(Default)]
-pub struct ProposalsResult {
-    passed: Vec<u64>,
-    rejected: Vec<u64>,
+/// Take the snapshot of the stakes of the consensus validators at the
+/// beginning of the epoch, for the votes and the tallies to avoid recomputing
+/// them from the PoS state, and prune the snapshots which can't be used by a
+/// proposal anymore
+fn snapshot_voting_power<S>(storage: &mut S, epoch: Epoch) -> StorageResult<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let snapshot =
+        read_consensus_validator_set_addresses_with_stake(storage, epoch)?
+            .into_iter()
+            .map(|validator| (validator.address, validator.bonded_stake))
+            .collect();
+    gov_api::write_voting_power_snapshot(storage, epoch, &snapshot)?;
+
+    let max_proposal_period = gov_api::get_max_proposal_period(storage)?;
+    gov_api::prune_voting_power_snapshots(storage, epoch, max_proposal_period)
 }
 
-fn execute_governance_proposals<D, H>(
-    shell: &mut Shell<D, H>,
+fn execute_governance_proposals<D, H, CA>(
+    state: &mut WlState<D, H>,
     events: &mut impl EmitEvents,
-) -> Result<ProposalsResult>
+    proposals: BTreeSet<u64>,
+    vp_wasm_cache: &mut VpCache<CA>,
+    tx_wasm_cache: &mut TxCache<CA>,
+) -> StorageResult<()>
 where
-    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
-    H: StorageHasher + Sync + 'static,
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+    CA: 'static + WasmCacheAccess + Sync,
 {
-    let mut proposals_result = ProposalsResult::default();
+    for id in proposals {
+        // The finalized proposal doesn't count towards the concurrent
+        // proposals of its author anymore
+        gov_api::delete_author_proposal(state, id)?;
+
+        // The funds of a withdrawn proposal have already been refunded
+        if gov_api::is_proposal_withdrawn(state, id)? {
+            tracing::info!(
+                "Governance proposal {} has been withdrawn by its author.",
+                id
+            );
+            continue;
+        }
 
-    for id in std::mem::take(&mut shell.proposal_data) {
         let proposal_funds_key = gov_storage::get_funds_key(id);
         let proposal_end_epoch_key = gov_storage::get_voting_end_epoch_key(id);
         let proposal_type_key = gov_storage::get_proposal_type_key(id);
         let proposal_author_key = gov_storage::get_author_key(id);
 
-        let funds: token::Amount =
-            force_read(&shell.state, &proposal_funds_key)?;
+        let funds: token::Amount = force_read(state, &proposal_funds_key)?;
         let proposal_end_epoch: Epoch =
-            force_read(&shell.state, &proposal_end_epoch_key)?;
+            force_read(state, &proposal_end_epoch_key)?;
         let proposal_type: ProposalType =
-            force_read(&shell.state, &proposal_type_key)?;
-        let proposal_author: Address =
-            force_read(&shell.state, &proposal_author_key)?;
+            force_read(state, &proposal_type_key)?;
+        let proposal_author: Address = force_read(state, &proposal_author_key)?;
 
-        let is_steward = pgf::is_steward(&shell.state, &proposal_author)?;
+        let is_steward = pgf::is_steward(state, &proposal_author)?;
 
-        let params = read_pos_params(&shell.state)?;
+        let params = read_pos_params(state)?;
         let total_voting_power =
-            read_total_stake(&shell.state, &params, proposal_end_epoch)?;
+            read_total_stake(state, &params, proposal_end_epoch)?;
 
         let tally_type = TallyType::from(proposal_type.clone(), is_steward);
-        let votes = compute_proposal_votes(
-            &shell.state,
-            &params,
-            id,
-            proposal_end_epoch,
-        )?;
-        let proposal_result =
-            compute_proposal_result(votes, total_voting_power, tally_type);
-        gov_api::write_proposal_result(&mut shell.state, id, proposal_result)?;
+        let threshold = tally_threshold(
+            &proposal_type,
+            &tally_type,
+            &gov_api::get_proposal_params_snapshot(state, id)?,
+        );
+        let votes =
+            compute_proposal_votes(state, &params, id, proposal_end_epoch)?;
+        let proposal_result = compute_proposal_result(
+            votes,
+            total_voting_power,
+            tally_type,
+            threshold,
+        );
+        finalize_proposal_result(state, id, proposal_result)?;
 
         let transfer_address = match proposal_result.result {
             TallyResult::Passed => {
                 let proposal_event = match proposal_type {
                     ProposalType::Default(_) => {
                         let proposal_code =
-                            gov_api::get_proposal_code(&shell.state, id)?;
+                            gov_api::get_proposal_code(state, id)?;
                         let result = execute_default_proposal(
-                            shell,
+                            state,
                             id,
                             proposal_code.clone(),
+                            vp_wasm_cache,
+                            tx_wasm_cache,
                         )?;
                         tracing::info!(
                             "Governance proposal (default {} wasm) {} has \
@@ -122,10 +215,8 @@ where
                         .into()
                     }
                     ProposalType::PGFSteward(stewards) => {
-                        let result = execute_pgf_steward_proposal(
-                            &mut shell.state,
-                            stewards,
-                        )?;
+                        let result =
+                            execute_pgf_steward_proposal(state, stewards)?;
                         tracing::info!(
                             "Governance proposal (pgf stewards){} has been \
                              executed and passed.",
@@ -136,9 +227,9 @@ where
                             .into()
                     }
                     ProposalType::PGFPayment(payments) => {
-                        let native_token = &shell.state.get_native_token()?;
+                        let native_token = &state.get_native_token()?;
                         let result = execute_pgf_funding_proposal(
-                            &mut shell.state,
+                            state,
                             native_token,
                             payments,
                             id,
@@ -149,14 +240,12 @@ where
                             id
                         );
 
-                        for ibc_event in
-                            shell.state.write_log_mut().take_ibc_events()
+                        for ibc_event in state.write_log_mut().take_ibc_events()
                         {
                             let mut event = Event::from(ibc_event.clone());
                             // Add the height for IBC event query
                             let height =
-                                shell.state.in_mem().get_last_block_height()
-                                    + 1;
+                                state.in_mem().get_last_block_height() + 1;
                             event["height"] = height.to_string();
                             events.emit(event);
                         }
@@ -164,19 +253,34 @@ where
                         ProposalEvent::pgf_payments_proposal_event(id, result)
                             .into()
                     }
+                    ProposalType::ExtendVotingPeriod(extension) => {
+                        let current_epoch = state.in_mem().block.epoch;
+                        let result = gov_api::apply_voting_period_extension(
+                            state,
+                            &extension,
+                            current_epoch,
+                        )?;
+                        tracing::info!(
+                            "Governance proposal (voting period extension of \
+                             proposal {}) {} has been executed ({}) and \
+                             passed.",
+                            extension.proposal_id,
+                            id,
+                            result
+                        );
+
+                        ProposalEvent::default_proposal_event(id, false, result)
+                            .into()
+                    }
                 };
                 events.emit(proposal_event);
-                proposals_result.passed.push(id);
 
-                gov_api::get_proposal_author(&shell.state, id)?
+                gov_api::get_proposal_author(state, id)?
             }
             TallyResult::Rejected => {
                 if let ProposalType::PGFPayment(_) = proposal_type {
                     if proposal_result.two_thirds_nay_over_two_thirds_total() {
-                        pgf::remove_steward(
-                            &mut shell.state,
-                            &proposal_author,
-                        )?;
+                        pgf::remove_steward(state, &proposal_author)?;
 
                         tracing::info!(
                             "Governance proposal {} was rejected with 2/3 of \
@@ -191,7 +295,6 @@ where
                 let proposal_event =
                     ProposalEvent::rejected_proposal_event(id).into();
                 events.emit(proposal_event);
-                proposals_result.rejected.push(id);
 
                 tracing::info!(
                     "Governance proposal {} has been executed and rejected.",
@@ -202,43 +305,44 @@ where
             }
         };
 
-        let native_token = shell.state.get_native_token()?;
+        let native_token = state.get_native_token()?;
         if let Some(address) = transfer_address {
             token::transfer(
-                &mut shell.state,
+                state,
                 &native_token,
-                &gov_address,
+                &GOV_ADDRESS,
                 &address,
                 funds,
             )?;
         } else {
-            token::burn_tokens(
-                &mut shell.state,
-                &native_token,
-                &gov_address,
-                funds,
-            )?;
+            token::burn_tokens(state, &native_token, &GOV_ADDRESS, funds)?;
         }
     }
 
-    Ok(proposals_result)
+    Ok(())
 }
 
-fn compute_proposal_votes<S>(
+/// Read the votes of a proposal with the voting power of the voters at the
+/// given epoch
+pub fn compute_proposal_votes<S>(
     storage: &S,
     params: &PosParams,
     proposal_id: u64,
     epoch: Epoch,
-) -> namada::state::StorageResult<ProposalVotes>
+) -> StorageResult<ProposalVotes>
 where
     S: StorageRead,
 {
     let votes = gov_api::get_proposal_votes(storage, proposal_id)?;
+    // The stakes of the consensus validators are read from the snapshot of
+    // the epoch, if any
+    let snapshot = gov_api::read_voting_power_snapshot(storage, epoch)?;
 
     let mut validators_vote: HashMap<Address, TallyVote> = HashMap::default();
     let mut validator_voting_power: HashMap<Address, VotePower> =
         HashMap::default();
-    let mut delegators_vote: HashMap<Address, TallyVote> = HashMap::default();
+    let mut delegators_vote: HashMap<Address, HashMap<Address, TallyVote>> =
+        HashMap::default();
     let mut delegator_voting_power: HashMap<
         Address,
         HashMap<Address, VotePower>,
@@ -249,9 +353,16 @@ where
             let validator = vote.validator.clone();
             let vote_data = vote.data.clone();
 
-            let validator_stake =
-                read_validator_stake(storage, params, &validator, epoch)
-                    .unwrap_or_default();
+            let validator_stake = match snapshot
+                .as_ref()
+                .and_then(|snapshot| snapshot.get(&validator))
+            {
+                Some(stake) => *stake,
+                None => {
+                    read_validator_stake(storage, params, &validator, epoch)
+                        .unwrap_or_default()
+                }
+            };
 
             validators_vote.insert(validator.clone(), vote_data.into());
             validator_voting_power.insert(validator, validator_stake);
@@ -267,7 +378,10 @@ where
             let delegator_stake = bond_amount(storage, &bond_id, epoch);
 
             if let Ok(stake) = delegator_stake {
-                delegators_vote.insert(delegator.clone(), vote_data.into());
+                delegators_vote
+                    .entry(delegator.clone())
+                    .or_default()
+                    .insert(validator.clone(), vote_data.into());
                 delegator_voting_power
                     .entry(delegator)
                     .or_default()
@@ -286,24 +400,30 @@ where
     })
 }
 
-fn execute_default_proposal<D, H>(
-    shell: &mut Shell<D, H>,
+fn execute_default_proposal<D, H, CA>(
+    state: &mut WlState<D, H>,
     id: u64,
     proposal_code: Option<Vec<u8>>,
-) -> namada::state::StorageResult<bool>
+    vp_wasm_cache: &mut VpCache<CA>,
+    tx_wasm_cache: &mut TxCache<CA>,
+) -> StorageResult<bool>
 where
-    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
-    H: StorageHasher + Sync + 'static,
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+    CA: 'static + WasmCacheAccess + Sync,
 {
     if let Some(code) = proposal_code {
         let pending_execution_key = gov_storage::get_proposal_execution_key(id);
-        shell.state.write(&pending_execution_key, ())?;
+        state.write(&pending_execution_key, ())?;
 
         let mut tx = Tx::from_type(TxType::Decrypted(DecryptedTx::Decrypted));
-        tx.header.chain_id = shell.chain_id.clone();
-        tx.set_data(Data::new(encode(&id)));
+        tx.header.chain_id = state.in_mem().chain_id.clone();
+        tx.set_data(Data::new(namada_core::encode(&id)));
         tx.set_code(Code::new(code, None));
 
+        // The proposal code is executed by the protocol, so it may write
+        // under the reserved storage prefixes
+        state.write_log_mut().allow_reserved_keys(true);
         let tx_result = protocol::dispatch_tx(
             tx,
             &[], /*  this is used to compute the fee
@@ -311,26 +431,26 @@ where
                   * need it here. */
             TxIndex::default(),
             &RefCell::new(TxGasMeter::new_from_sub_limit(u64::MAX.into())), /* No gas limit for governance proposal */
-            &mut shell.state,
-            &mut shell.vp_wasm_cache,
-            &mut shell.tx_wasm_cache,
+            state,
+            vp_wasm_cache,
+            tx_wasm_cache,
             None,
         );
-        shell
-            .state
+        state.write_log_mut().allow_reserved_keys(false);
+        state
             .delete(&pending_execution_key)
             .expect("Should be able to delete the storage.");
         match tx_result {
             Ok(tx_result) => {
                 if tx_result.is_accepted() {
-                    shell.state.commit_tx();
+                    state.commit_tx();
                     Ok(true)
                 } else {
                     Ok(false)
                 }
             }
             Err(_) => {
-                shell.state.drop_tx();
+                state.drop_tx();
                 Ok(false)
             }
         }
@@ -346,7 +466,7 @@ where
 fn execute_pgf_steward_proposal<S>(
     storage: &mut S,
     stewards: BTreeSet<AddRemove<Address>>,
-) -> Result<bool>
+) -> StorageResult<bool>
 where
     S: StorageRead + StorageWrite,
 {
@@ -373,10 +493,10 @@ fn execute_pgf_funding_proposal<D, H>(
     token: &Address,
     fundings: BTreeSet<PGFAction>,
     proposal_id: u64,
-) -> Result<bool>
+) -> StorageResult<bool>
 where
-    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
-    H: StorageHasher + Sync + 'static,
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
 {
     for funding in fundings {
         match funding {
@@ -412,13 +532,16 @@ where
                     PGFTarget::Internal(target) => token::transfer(
                         state,
                         token,
-                        &ADDRESS,
+                        &PGF_ADDRESS,
                         &target.target,
                         target.amount,
                     ),
-                    PGFTarget::Ibc(target) => {
-                        ibc::transfer_over_ibc(state, token, &ADDRESS, target)
-                    }
+                    PGFTarget::Ibc(target) => namada_ibc::transfer_over_ibc(
+                        state,
+                        token,
+                        &PGF_ADDRESS,
+                        target,
+                    ),
                 };
                 match result {
                     Ok(()) => tracing::info!(
@@ -442,3 +565,15 @@ where
 
     Ok(true)
 }
+
+/// Read a value that must be present in storage
+fn force_read<S, T>(storage: &S, key: &Key) -> StorageResult<T>
+where
+    S: StorageRead,
+    T: BorshDeserialize,
+{
+    storage
+        .read::<T>(key)
+        .transpose()
+        .expect("Storage key must be present.")
+}
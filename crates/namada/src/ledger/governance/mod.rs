@@ -1,30 +1,50 @@
 //! Governance VP
 
+#[cfg(feature = "wasm-runtime")]
+pub mod finalize;
 pub mod utils;
 
-use std::collections::BTreeSet;
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::rc::Rc;
 
 use borsh::BorshDeserialize;
+use namada_governance::parameters::ProposalParamsSnapshot;
+use namada_governance::pgf::storage::{
+    is_steward, is_valid_commission_rate, keys as pgf_storage,
+};
 use namada_governance::storage::proposal::{
-    AddRemove, PGFAction, ProposalType,
+    AddRemove, PGFAction, PGFInternalTarget, PGFTarget, ProposalType,
+    VotingPeriodExtension,
+};
+use namada_governance::storage::{
+    count_active_author_proposals, get_proposal_params_snapshot,
+    is_proposal_accepted, is_proposal_withdrawn, keys as gov_storage,
+    read_proposal_params, read_voting_power_snapshot, VotingPowerSnapshot,
+};
+use namada_governance::utils::{
+    is_valid_threshold, is_valid_validator_voting_period,
 };
-use namada_governance::storage::{is_proposal_accepted, keys as gov_storage};
-use namada_governance::utils::is_valid_validator_voting_period;
 use namada_governance::ProposalVote;
+use namada_parameters::storage::get_tx_allowlist_storage_key;
 use namada_proof_of_stake::is_validator;
 use namada_proof_of_stake::queries::find_delegations;
 use namada_state::{StateRead, StorageRead};
-use namada_tx::Tx;
+use namada_tx::{Section, Tx};
 use namada_vp_env::VpEnv;
 use thiserror::Error;
 
 use self::utils::ReadType;
 use crate::address::{Address, InternalAddress};
+use crate::dec::Dec;
+use crate::hash::Hash;
+use crate::ledger::gas::GasMetering;
 use crate::ledger::native_vp::{Ctx, NativeVp};
 use crate::ledger::{native_vp, pos};
 use crate::storage::{Epoch, Key};
 use crate::token;
-use crate::vm::WasmCacheAccess;
+use crate::vm::{validate_untrusted_wasm, WasmCacheAccess};
 
 /// for handling Governance NativeVP errors
 pub type Result<T> = std::result::Result<T, Error>;
@@ -35,6 +55,12 @@ pub const ADDRESS: Address = Address::Internal(InternalAddress::Governance);
 /// The maximum number of item in a pgf proposal
 pub const MAX_PGF_ACTIONS: usize = 20;
 
+/// The maximum length in bytes of the name of a proposal content field
+pub const MAX_CONTENT_FIELD_NAME_LENGTH: usize = 64;
+
+/// The maximum length in bytes of the value of a proposal content field
+pub const MAX_CONTENT_FIELD_VALUE_LENGTH: usize = 4096;
+
 #[allow(missing_docs)]
 #[derive(Error, Debug)]
 pub enum Error {
@@ -44,6 +70,24 @@ pub enum Error {
     EmptyProposalField(String),
     #[error("Vote key is not valid: {0}")]
     InvalidVoteKey(String),
+    #[error("Decoding the value of {key} as {type_name} failed: {source}")]
+    Decode {
+        key: Key,
+        type_name: &'static str,
+        source: std::io::Error,
+    },
+}
+
+impl Error {
+    /// A coarse label of the error, e.g. to be used in the metrics
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::NativeVpError(_) => "native_vp",
+            Self::EmptyProposalField(_) => "proposal_field",
+            Self::InvalidVoteKey(_) => "vote",
+            Self::Decode { .. } => "decoding",
+        }
+    }
 }
 
 /// Governance VP
@@ -69,22 +113,44 @@ where
         keys_changed: &BTreeSet<Key>,
         verifiers: &BTreeSet<Address>,
     ) -> Result<bool> {
-        let (is_valid_keys_set, set_count) =
-            self.is_valid_init_proposal_key_set(keys_changed)?;
+        let Some((is_valid_keys_set, set_count)) = reject_on_decode_error(
+            self.is_valid_init_proposal_key_set(keys_changed),
+        )?
+        else {
+            return Ok(false);
+        };
         if !is_valid_keys_set {
             tracing::info!("Invalid changed governance key set");
             return Ok(false);
         };
 
         let native_token = self.ctx.pre().get_native_token()?;
+        let Some(withdrawal_refunds) =
+            reject_on_decode_error(self.withdrawal_refunds(keys_changed))?
+        else {
+            return Ok(false);
+        };
+        let Some(expected_balance_change) = reject_on_decode_error(
+            self.expected_balance_change(set_count, &withdrawal_refunds),
+        )?
+        else {
+            return Ok(false);
+        };
+        let votes_cache = VotesCache::default();
 
         Ok(keys_changed.iter().all(|key| {
             let proposal_id = gov_storage::get_proposal_id(key);
             let key_type = KeyType::from_key(key, &native_token);
 
             let result = match (key_type, proposal_id) {
-                (KeyType::VOTE, Some(proposal_id)) => {
-                    self.is_valid_vote_key(proposal_id, key, verifiers)
+                (KeyType::VOTE, Some(proposal_id)) => self.is_valid_vote_key(
+                    proposal_id,
+                    key,
+                    verifiers,
+                    &votes_cache,
+                ),
+                (KeyType::VOTE_MEMO, Some(proposal_id)) => {
+                    self.is_valid_vote_memo_key(proposal_id, key, keys_changed)
                 }
                 (KeyType::CONTENT, Some(proposal_id)) => {
                     self.is_valid_content_key(proposal_id)
@@ -98,24 +164,49 @@ where
                 (KeyType::GRACE_EPOCH, Some(proposal_id)) => {
                     self.is_valid_grace_epoch(proposal_id)
                 }
+                (KeyType::PARAMS_SNAPSHOT, Some(proposal_id)) => {
+                    self.is_valid_params_snapshot(proposal_id)
+                }
                 (KeyType::START_EPOCH, Some(proposal_id)) => {
                     self.is_valid_start_epoch(proposal_id)
                 }
                 (KeyType::END_EPOCH, Some(proposal_id)) => {
                     self.is_valid_end_epoch(proposal_id)
                 }
-                (KeyType::FUNDS, Some(proposal_id)) => {
-                    self.is_valid_funds(proposal_id, &native_token)
-                }
+                (KeyType::FUNDS, Some(proposal_id)) => self.is_valid_funds(
+                    proposal_id,
+                    &native_token,
+                    expected_balance_change.unwrap_or_default(),
+                ),
                 (KeyType::AUTHOR, Some(proposal_id)) => {
                     self.is_valid_author(proposal_id, verifiers)
                 }
+                (KeyType::AUTHOR_PROPOSAL, _) => {
+                    self.is_valid_author_proposal_key(key)
+                }
+                (KeyType::WITHDRAWN, Some(proposal_id)) => self
+                    .is_valid_withdrawal(
+                        proposal_id,
+                        verifiers,
+                        &withdrawal_refunds,
+                        &native_token,
+                    ),
                 (KeyType::COUNTER, _) => self.is_valid_counter(set_count),
                 (KeyType::PROPOSAL_COMMIT, _) => {
                     self.is_valid_proposal_commit()
                 }
-                (KeyType::PARAMETER, _) => self.is_valid_parameter(tx_data),
-                (KeyType::BALANCE, _) => self.is_valid_balance(&native_token),
+                (KeyType::PARAMETER, _) => {
+                    self.is_valid_parameter(key, tx_data)
+                }
+                (KeyType::PROPOSAL_RESULT, _) => {
+                    // The results are only written by the protocol
+                    tracing::info!(
+                        "The proposal results can't be written by a tx"
+                    );
+                    Ok(false)
+                }
+                (KeyType::BALANCE, _) => self
+                    .is_valid_balance(&native_token, expected_balance_change),
                 (KeyType::UNKNOWN_GOVERNANCE, _) => Ok(false),
                 (KeyType::UNKNOWN, _) => Ok(true),
                 _ => Ok(false),
@@ -151,18 +242,26 @@ where
         }
 
         for counter in pre_counter..post_counter {
+            let author_key = gov_storage::get_author_key(counter);
+            let author: Address = match self.ctx.post().read(&author_key)? {
+                Some(author) => author,
+                None => return Ok((false, 0)),
+            };
+
             // Construct the set of expected keys
             // NOTE: we don't check the existence of committing_epoch because
             // it's going to be checked later into the VP
             let mandatory_keys = BTreeSet::from([
                 counter_key.clone(),
                 gov_storage::get_content_key(counter),
-                gov_storage::get_author_key(counter),
+                author_key,
+                gov_storage::get_author_proposal_key(&author, counter),
                 gov_storage::get_proposal_type_key(counter),
                 gov_storage::get_funds_key(counter),
                 gov_storage::get_voting_start_epoch_key(counter),
                 gov_storage::get_voting_end_epoch_key(counter),
                 gov_storage::get_grace_epoch_key(counter),
+                gov_storage::get_proposal_params_snapshot_key(counter),
             ]);
 
             // Check that expected set is a subset of the actual one
@@ -174,11 +273,68 @@ where
         Ok((true, post_counter - pre_counter))
     }
 
+    /// Get the funds to be refunded to the authors of the proposals withdrawn
+    /// by the tx
+    fn withdrawal_refunds(
+        &self,
+        keys: &BTreeSet<Key>,
+    ) -> Result<BTreeMap<Address, token::Amount>> {
+        let mut refunds = BTreeMap::<Address, token::Amount>::new();
+        let withdrawn_proposal_ids = keys
+            .iter()
+            .filter(|key| gov_storage::is_withdrawn_key(key))
+            .filter_map(gov_storage::get_proposal_id);
+        for proposal_id in withdrawn_proposal_ids {
+            let author_key = gov_storage::get_author_key(proposal_id);
+            let funds_key = gov_storage::get_funds_key(proposal_id);
+            let author: Address =
+                self.force_read(&author_key, ReadType::Pre)?;
+            let funds: token::Amount =
+                self.force_read(&funds_key, ReadType::Pre)?;
+            *refunds.entry(author).or_default() += funds;
+        }
+        Ok(refunds)
+    }
+
+    /// Get the total funds of the proposals initialized by the tx
+    fn init_proposals_funds(&self) -> Result<token::Amount> {
+        let counter_key = gov_storage::get_counter_key();
+        let pre_counter: u64 = self.force_read(&counter_key, ReadType::Pre)?;
+        let post_counter: u64 =
+            self.force_read(&counter_key, ReadType::Post)?;
+        let mut funds = token::Amount::zero();
+        for proposal_id in pre_counter..post_counter {
+            let funds_key = gov_storage::get_funds_key(proposal_id);
+            let proposal_funds: token::Amount =
+                self.force_read(&funds_key, ReadType::Post)?;
+            funds += proposal_funds;
+        }
+        Ok(funds)
+    }
+
+    /// Get the net change of the balance expected from the proposals
+    /// initialized and withdrawn by the tx, if any: the funds of the
+    /// initialized proposals minus the refunds of the withdrawn ones
+    fn expected_balance_change(
+        &self,
+        set_count: u64,
+        withdrawal_refunds: &BTreeMap<Address, token::Amount>,
+    ) -> Result<Option<token::Change>> {
+        if set_count == 0 && withdrawal_refunds.is_empty() {
+            return Ok(None);
+        }
+        let init_proposals_funds = self.init_proposals_funds()?;
+        let refunded: token::Change =
+            withdrawal_refunds.values().map(token::Amount::change).sum();
+        Ok(Some(init_proposals_funds.change() - refunded))
+    }
+
     fn is_valid_vote_key(
         &self,
         proposal_id: u64,
         key: &Key,
         verifiers: &BTreeSet<Address>,
+        votes_cache: &VotesCache,
     ) -> Result<bool> {
         let counter_key = gov_storage::get_counter_key();
         let voting_start_epoch_key =
@@ -227,27 +383,58 @@ where
             return Err(Error::InvalidVoteKey(key.to_string()));
         }
 
-        // TODO: We should refactor this by modifying the vote proposal tx
-        let all_delegations_are_valid = if let Ok(delegations) =
-            find_delegations(&self.ctx.pre(), voter_address, &current_epoch)
-        {
-            if delegations.is_empty() {
-                return Ok(false);
-            } else {
-                delegations.iter().all(|(address, _)| {
-                    let vote_key = gov_storage::get_vote_proposal_key(
-                        proposal_id,
-                        voter_address.clone(),
-                        address.clone(),
-                    );
-                    self.ctx.post().has_key(&vote_key).unwrap_or(false)
-                })
-            }
-        } else {
+        if is_proposal_withdrawn(&self.ctx.pre(), proposal_id)? {
+            tracing::info!("The proposal {proposal_id} has been withdrawn.");
             return Ok(false);
+        }
+
+        // The delegations at the start of the voting period determine the
+        // weight of the vote. Each vote key is validated on its own, so a
+        // delegator may vote with any subset of its delegations.
+        let delegations = match votes_cache.delegations(
+            &self.ctx.pre(),
+            voter_address,
+            pre_voting_start_epoch,
+        ) {
+            Ok(delegations) if !delegations.is_empty() => delegations,
+            _ => {
+                // The votes of the PGF stewards on the PGF payment proposals
+                // are recorded even without stake
+                let is_steward_vote = self.is_steward_vote(
+                    proposal_id,
+                    verifiers,
+                    voter_address,
+                    delegation_address,
+                )?;
+                return Ok(is_steward_vote
+                    && self.is_valid_voting_window(
+                        current_epoch,
+                        pre_voting_start_epoch,
+                        pre_voting_end_epoch,
+                        false,
+                    ));
+            }
         };
-        if !all_delegations_are_valid {
-            return Ok(false);
+
+        if voter_address != delegation_address {
+            // A delegator can only vote with its delegations
+            let Some(vote_weight) = delegations.get(delegation_address) else {
+                tracing::info!(
+                    "The voter {voter_address} has no delegation to \
+                     {delegation_address} at the start epoch \
+                     {pre_voting_start_epoch}."
+                );
+                return Ok(false);
+            };
+            // Reject a vote which would have no weight
+            if vote_weight.is_zero() {
+                tracing::info!(
+                    "The voter {voter_address} has no stake delegated to \
+                     {delegation_address} at the start epoch \
+                     {pre_voting_start_epoch}."
+                );
+                return Ok(false);
+            }
         }
 
         // Voted outside of voting window. We dont check for validator because
@@ -273,6 +460,7 @@ where
                 verifiers,
                 voter_address,
                 delegation_address,
+                votes_cache,
             )
             .unwrap_or(false);
 
@@ -293,9 +481,74 @@ where
                 delegation_address,
             )
             .unwrap_or(false);
+        if !is_delegator {
+            tracing::info!(
+                "The voter {voter_address} is neither a validator nor a \
+                 delegator of {delegation_address}."
+            );
+        }
         Ok(is_delegator)
     }
 
+    /// Validate a vote memo key. The memo can only be written along with the
+    /// vote of the same voter and delegation, which is validated as a vote
+    /// key, and it must be a UTF-8 string within the maximum memo length.
+    fn is_valid_vote_memo_key(
+        &self,
+        proposal_id: u64,
+        key: &Key,
+        keys_changed: &BTreeSet<Key>,
+    ) -> Result<bool> {
+        let (voter_address, delegation_address) = match (
+            gov_storage::get_voter_address(key),
+            gov_storage::get_vote_delegation_address(key),
+        ) {
+            (Some(voter_address), Some(delegation_address)) => {
+                (voter_address, delegation_address)
+            }
+            _ => return Err(Error::InvalidVoteKey(key.to_string())),
+        };
+
+        let vote_key = gov_storage::get_vote_proposal_key(
+            proposal_id,
+            voter_address.clone(),
+            delegation_address.clone(),
+        );
+        if !keys_changed.contains(&vote_key) {
+            tracing::info!(
+                "The vote memo of {voter_address} for {delegation_address} \
+                 was written without a vote."
+            );
+            return Ok(false);
+        }
+
+        // A vote without a memo clears the memo of a previous vote
+        let post_memo = match self.ctx.read_bytes_post(key)? {
+            Some(post_memo) => post_memo,
+            None => return Ok(true),
+        };
+        let memo = match String::try_from_slice(&post_memo) {
+            Ok(memo) => memo,
+            Err(err) => {
+                tracing::info!("The vote memo is not a UTF-8 string: {err}.");
+                return Ok(false);
+            }
+        };
+
+        let max_vote_memo_length_key =
+            gov_storage::get_max_vote_memo_length_key();
+        let max_vote_memo_length: usize =
+            self.force_read(&max_vote_memo_length_key, ReadType::Pre)?;
+        let is_valid = memo.len() <= max_vote_memo_length;
+        if !is_valid {
+            tracing::info!(
+                "Max vote memo length {max_vote_memo_length}, got {}.",
+                memo.len()
+            );
+        }
+        Ok(is_valid)
+    }
+
     /// Validate a content key
     pub fn is_valid_content_key(&self, proposal_id: u64) -> Result<bool> {
         let content_key: Key = gov_storage::get_content_key(proposal_id);
@@ -318,15 +571,72 @@ where
                 "Max content length {max_content_length}, got {}.",
                 post_content.len()
             );
+            return Ok(false);
         }
-        Ok(is_valid)
+
+        // The content must be a map of strings, as in the proposal JSON
+        let content =
+            match BTreeMap::<String, String>::try_from_slice(&post_content) {
+                Ok(content) => content,
+                Err(err) => {
+                    tracing::info!("The proposal content is malformed: {err}");
+                    return Ok(false);
+                }
+            };
+        if let Some(field) = content
+            .keys()
+            .find(|field| field.len() > MAX_CONTENT_FIELD_NAME_LENGTH)
+        {
+            tracing::info!(
+                "The proposal content field {field} is longer than \
+                 {MAX_CONTENT_FIELD_NAME_LENGTH} bytes."
+            );
+            return Ok(false);
+        }
+        if let Some((field, _)) = content
+            .iter()
+            .find(|(_, value)| value.len() > MAX_CONTENT_FIELD_VALUE_LENGTH)
+        {
+            tracing::info!(
+                "The value of the proposal content field {field} is longer \
+                 than {MAX_CONTENT_FIELD_VALUE_LENGTH} bytes."
+            );
+            return Ok(false);
+        }
+
+        let required_fields_parameter_key =
+            gov_storage::get_content_required_fields_key();
+        let required_fields: Vec<String> =
+            self.force_read(&required_fields_parameter_key, ReadType::Pre)?;
+        for field in required_fields {
+            let is_set = content
+                .get(&field)
+                .map(|value| !value.trim().is_empty())
+                .unwrap_or(false);
+            if !is_set {
+                tracing::info!(
+                    "The proposal content field {field} is missing or empty."
+                );
+                return Ok(false);
+            }
+        }
+        Ok(true)
     }
 
     /// Validate the proposal type
     pub fn is_valid_proposal_type(&self, proposal_id: u64) -> Result<bool> {
         let proposal_type_key = gov_storage::get_proposal_type_key(proposal_id);
-        let proposal_type: ProposalType =
-            self.force_read(&proposal_type_key, ReadType::Post)?;
+        let proposal_type: ProposalType = self
+            .force_read(&proposal_type_key, ReadType::Post)
+            .map_err(|err| {
+                // A PGF IBC target with an invalid port ID or channel ID
+                // can't be decoded
+                tracing::info!(
+                    "The proposal type can't be decoded, e.g. a PGF IBC \
+                     target has an invalid port ID or channel ID: {err}"
+                );
+                err
+            })?;
 
         match proposal_type {
             ProposalType::PGFSteward(stewards) => {
@@ -361,12 +671,38 @@ where
                         .force_read::<Address>(&author_key, ReadType::Post)?;
                     let is_valid_author = address.eq(&author);
 
+                    // the added steward must set its commission rate
+                    let commission_rate_key =
+                        pgf_storage::steward_commission_rates_handle()
+                            .get_data_key(address);
+                    let is_valid_commission_rate = match self
+                        .ctx
+                        .post()
+                        .read::<Dec>(&commission_rate_key)?
+                    {
+                        Some(rate) if is_valid_commission_rate(&rate) => true,
+                        Some(rate) => {
+                            tracing::info!(
+                                "The commission rate {rate} of the steward \
+                                 {address} isn't in the range [0, 1]."
+                            );
+                            false
+                        }
+                        None => {
+                            tracing::info!(
+                                "The steward {address} has no commission rate."
+                            );
+                            false
+                        }
+                    };
+
                     let stewards_addresses_are_unique =
                         stewards.len() == all_pgf_action_addresses;
                     let is_valid_total_pgf_actions =
                         all_pgf_action_addresses < MAX_PGF_ACTIONS;
 
                     return Ok(is_valid_author
+                        && is_valid_commission_rate
                         && stewards_addresses_are_unique
                         && is_valid_total_pgf_actions);
                 } else {
@@ -422,12 +758,158 @@ where
 
                 Ok(is_total_fundings_valid
                     && are_continuous_fundings_unique
-                    && are_targets_unique)
+                    && are_targets_unique
+                    && self.are_valid_pgf_funding_targets(&fundings)?)
+            }
+            ProposalType::ExtendVotingPeriod(extension) => {
+                self.is_valid_voting_period_extension(proposal_id, &extension)
             }
             _ => Ok(true), // default proposal
         }
     }
 
+    /// Check that the targets of the added continuous fundings and of the
+    /// retro payments exist on this chain, for the transparent targets, and
+    /// that their amounts are non-zero
+    fn are_valid_pgf_funding_targets(
+        &self,
+        fundings: &BTreeSet<PGFAction>,
+    ) -> Result<bool> {
+        for funding in fundings {
+            let target = match funding {
+                PGFAction::Continuous(AddRemove::Add(target))
+                | PGFAction::Retro(target) => target,
+                PGFAction::Continuous(AddRemove::Remove(_)) => continue,
+            };
+            if target.amount().is_zero() {
+                tracing::info!(
+                    "PGF funding target {} has a zero amount.",
+                    target.target()
+                );
+                return Ok(false);
+            }
+            if let PGFTarget::Internal(PGFInternalTarget { target, .. }) =
+                target
+            {
+                // The internal addresses have no account
+                let target_exists = matches!(target, Address::Internal(_))
+                    || namada_account::exists(&self.ctx.pre(), target)?;
+                if !target_exists {
+                    tracing::info!(
+                        "PGF funding target {target} doesn't exist."
+                    );
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Check that the extended proposal is in its voting period and that its
+    /// voting end epoch postponed by the extension would be within the max
+    /// proposal period and the min grace epochs. The extension is only
+    /// applied when the extension proposal is accepted, so it must be
+    /// activated before the end of the voting period of the extended
+    /// proposal.
+    fn is_valid_voting_period_extension(
+        &self,
+        proposal_id: u64,
+        extension: &VotingPeriodExtension,
+    ) -> Result<bool> {
+        let extended_id = extension.proposal_id;
+        let counter_key = gov_storage::get_counter_key();
+        let pre_counter: u64 = self.force_read(&counter_key, ReadType::Pre)?;
+        if extended_id >= pre_counter {
+            tracing::info!(
+                "The extended proposal {extended_id} doesn't exist."
+            );
+            return Ok(false);
+        }
+        if extension.epochs == 0 {
+            tracing::info!("The voting period extension must be non-zero.");
+            return Ok(false);
+        }
+        if is_proposal_withdrawn(&self.ctx.pre(), extended_id)? {
+            tracing::info!("The extended proposal {extended_id} is withdrawn.");
+            return Ok(false);
+        }
+
+        let start_epoch: Epoch = self.force_read(
+            &gov_storage::get_voting_start_epoch_key(extended_id),
+            ReadType::Pre,
+        )?;
+        let pre_end_epoch: Epoch = self.force_read(
+            &gov_storage::get_voting_end_epoch_key(extended_id),
+            ReadType::Pre,
+        )?;
+        let grace_epoch: Epoch = self.force_read(
+            &gov_storage::get_grace_epoch_key(extended_id),
+            ReadType::Pre,
+        )?;
+        // The extended proposal is validated against its parameters at its
+        // creation
+        let ProposalParamsSnapshot {
+            max_proposal_period: max_period,
+            min_proposal_grace_epochs: min_grace_epoch,
+            ..
+        } = get_proposal_params_snapshot(&self.ctx.pre(), extended_id)?;
+
+        let current_epoch = self.ctx.get_block_epoch()?;
+        if !self.is_valid_voting_window(
+            current_epoch,
+            start_epoch,
+            pre_end_epoch,
+            false,
+        ) {
+            tracing::info!(
+                "The extended proposal {extended_id} isn't in its voting \
+                 period: start = {start_epoch}, end = {pre_end_epoch}, \
+                 current = {current_epoch}."
+            );
+            return Ok(false);
+        }
+
+        let activation_epoch: Epoch = self.force_read(
+            &gov_storage::get_grace_epoch_key(proposal_id),
+            ReadType::Post,
+        )?;
+        if activation_epoch > pre_end_epoch {
+            tracing::info!(
+                "The extension would be activated at {activation_epoch}, \
+                 after the end of the voting period of the extended proposal \
+                 {extended_id} at {pre_end_epoch}."
+            );
+            return Ok(false);
+        }
+
+        let extended_end_epoch =
+            match pre_end_epoch.0.checked_add(extension.epochs) {
+                Some(epoch) => Epoch(epoch),
+                None => return Ok(false),
+            };
+
+        let is_valid_period =
+            (extended_end_epoch - start_epoch).0 <= max_period;
+        if !is_valid_period {
+            tracing::info!(
+                "Expected max duration between the start and end epoch \
+                 {max_period}, but got start = {start_epoch}, end = \
+                 {extended_end_epoch}"
+            );
+        }
+        let is_valid_grace_epoch = extended_end_epoch < grace_epoch
+            && (grace_epoch - extended_end_epoch).0 >= min_grace_epoch;
+        if !is_valid_grace_epoch {
+            tracing::info!(
+                "Expected min duration between the end and grace epoch \
+                 {min_grace_epoch}, but got grace = {grace_epoch}, end = \
+                 {extended_end_epoch}"
+            );
+        }
+
+        Ok(is_valid_period && is_valid_grace_epoch)
+    }
+
     /// Validate a proposal code
     pub fn is_valid_proposal_code(&self, proposal_id: u64) -> Result<bool> {
         let proposal_type_key = gov_storage::get_proposal_type_key(proposal_id);
@@ -452,7 +934,62 @@ where
         let post_code: Vec<u8> =
             self.ctx.read_bytes_post(&code_key)?.unwrap_or_default();
 
-        Ok(post_code.len() <= max_proposal_length)
+        if post_code.len() > max_proposal_length {
+            return Ok(false);
+        }
+        if post_code.is_empty() {
+            return Ok(true);
+        }
+        self.is_valid_proposal_wasm(&post_code)
+    }
+
+    /// Validate the wasm of a proposal code. The code must be either an
+    /// allowlisted tx or attached to the tx in an extra section, in which case
+    /// it must be a valid wasm module.
+    fn is_valid_proposal_wasm(&self, code: &[u8]) -> Result<bool> {
+        let code_hash = Hash::sha256(code);
+
+        let allowlist: Vec<String> = self
+            .ctx
+            .read_pre(&get_tx_allowlist_storage_key())?
+            .unwrap_or_default();
+        if allowlist.contains(&code_hash.to_string().to_lowercase()) {
+            return Ok(true);
+        }
+
+        let is_attached = self.ctx.tx.sections.iter().any(|section| {
+            matches!(
+                section,
+                Section::ExtraData(extra) if extra.code.hash() == code_hash
+            )
+        });
+        if !is_attached {
+            tracing::info!(
+                "The proposal code {code_hash} is neither allowlisted nor \
+                 attached to the tx"
+            );
+            return Ok(false);
+        }
+
+        // The validation is charged as for the wasm of a tx
+        self.ctx
+            .gas_meter
+            .borrow_mut()
+            .add_wasm_validation_gas(code.len() as u64)
+            .map_err(|_| {
+                native_vp::Error::SimpleMessage(
+                    "Gas limit exceeded in native vp",
+                )
+            })?;
+        match validate_untrusted_wasm(code) {
+            Ok(()) => Ok(true),
+            Err(err) => {
+                tracing::info!(
+                    "The proposal code {code_hash} is not a valid wasm: {err}"
+                );
+                Ok(false)
+            }
+        }
     }
 
     /// Validate a grace_epoch key
@@ -461,9 +998,6 @@ where
             gov_storage::get_voting_start_epoch_key(proposal_id);
         let end_epoch_key = gov_storage::get_voting_end_epoch_key(proposal_id);
         let grace_epoch_key = gov_storage::get_grace_epoch_key(proposal_id);
-        let max_proposal_period = gov_storage::get_max_proposal_period_key();
-        let min_grace_epoch_key =
-            gov_storage::get_min_proposal_grace_epoch_key();
 
         let has_pre_grace_epoch = self.ctx.has_key_pre(&grace_epoch_key)?;
         if has_pre_grace_epoch {
@@ -476,10 +1010,11 @@ where
             self.force_read(&end_epoch_key, ReadType::Post)?;
         let grace_epoch: Epoch =
             self.force_read(&grace_epoch_key, ReadType::Post)?;
-        let min_grace_epoch: u64 =
-            self.force_read(&min_grace_epoch_key, ReadType::Pre)?;
-        let max_proposal_period: u64 =
-            self.force_read(&max_proposal_period, ReadType::Pre)?;
+        let ProposalParamsSnapshot {
+            max_proposal_period,
+            min_proposal_grace_epochs: min_grace_epoch,
+            ..
+        } = self.new_proposal_params(proposal_id)?;
 
         let committing_epoch_key = gov_storage::get_committing_proposals_key(
             proposal_id,
@@ -517,13 +1052,55 @@ where
             && is_valid_max_proposal_period)
     }
 
+    /// Get the parameters that a proposal initialized by the tx is validated
+    /// against, from its snapshot, which must match the current parameters
+    fn new_proposal_params(
+        &self,
+        proposal_id: u64,
+    ) -> Result<ProposalParamsSnapshot> {
+        let snapshot_key =
+            gov_storage::get_proposal_params_snapshot_key(proposal_id);
+        self.force_read(&snapshot_key, ReadType::Post)
+    }
+
+    /// Validate the snapshot of the parameters of a new proposal. It must
+    /// belong to a proposal initialized by the tx and match the current
+    /// parameters.
+    fn is_valid_params_snapshot(&self, proposal_id: u64) -> Result<bool> {
+        let counter_key = gov_storage::get_counter_key();
+        let pre_counter: u64 = self.force_read(&counter_key, ReadType::Pre)?;
+        let post_counter: u64 =
+            self.force_read(&counter_key, ReadType::Post)?;
+        if !(pre_counter..post_counter).contains(&proposal_id) {
+            tracing::info!(
+                "The parameters snapshot of the proposal {proposal_id} can \
+                 only be written at its creation. Expected an ID from \
+                 {pre_counter} to {post_counter}, exclusive."
+            );
+            return Ok(false);
+        }
+        let snapshot_key =
+            gov_storage::get_proposal_params_snapshot_key(proposal_id);
+        if self.ctx.has_key_pre(&snapshot_key)? {
+            return Ok(false);
+        }
+        let snapshot: ProposalParamsSnapshot =
+            self.force_read(&snapshot_key, ReadType::Post)?;
+        let params = read_proposal_params(&self.ctx.pre())?;
+        if snapshot != params {
+            tracing::info!(
+                "The parameters snapshot of the proposal {proposal_id} \
+                 {snapshot:?} doesn't match the current parameters {params:?}"
+            );
+        }
+        Ok(snapshot == params)
+    }
+
     /// Validate a start_epoch key
     pub fn is_valid_start_epoch(&self, proposal_id: u64) -> Result<bool> {
         let start_epoch_key =
             gov_storage::get_voting_start_epoch_key(proposal_id);
         let end_epoch_key = gov_storage::get_voting_end_epoch_key(proposal_id);
-        let min_period_parameter_key =
-            gov_storage::get_min_proposal_voting_period_key();
 
         let current_epoch = self.ctx.get_block_epoch()?;
 
@@ -538,8 +1115,9 @@ where
             self.force_read(&start_epoch_key, ReadType::Post)?;
         let end_epoch: Epoch =
             self.force_read(&end_epoch_key, ReadType::Post)?;
-        let min_period: u64 =
-            self.force_read(&min_period_parameter_key, ReadType::Pre)?;
+        let min_period = self
+            .new_proposal_params(proposal_id)?
+            .min_proposal_voting_period;
 
         if end_epoch <= start_epoch || start_epoch <= current_epoch {
             return Ok(false);
@@ -554,10 +1132,6 @@ where
         let start_epoch_key =
             gov_storage::get_voting_start_epoch_key(proposal_id);
         let end_epoch_key = gov_storage::get_voting_end_epoch_key(proposal_id);
-        let min_period_parameter_key =
-            gov_storage::get_min_proposal_voting_period_key();
-        let max_period_parameter_key =
-            gov_storage::get_max_proposal_period_key();
 
         let current_epoch = self.ctx.get_block_epoch()?;
 
@@ -572,10 +1146,11 @@ where
             self.force_read(&start_epoch_key, ReadType::Post)?;
         let end_epoch: Epoch =
             self.force_read(&end_epoch_key, ReadType::Post)?;
-        let min_period: u64 =
-            self.force_read(&min_period_parameter_key, ReadType::Pre)?;
-        let max_period: u64 =
-            self.force_read(&max_period_parameter_key, ReadType::Pre)?;
+        let ProposalParamsSnapshot {
+            min_proposal_voting_period: min_period,
+            max_proposal_period: max_period,
+            ..
+        } = self.new_proposal_params(proposal_id)?;
 
         if end_epoch <= start_epoch || start_epoch <= current_epoch {
             tracing::info!(
@@ -590,41 +1165,50 @@ where
             && (end_epoch - start_epoch).0 <= max_period)
     }
 
-    /// Validate a funds key
+    /// Validate a funds key. The balance must change by the funds of the
+    /// proposals initialized by the tx minus the refunds of the withdrawn
+    /// ones.
     pub fn is_valid_funds(
         &self,
         proposal_id: u64,
         native_token_address: &Address,
+        expected_balance_change: token::Change,
     ) -> Result<bool> {
         let funds_key = gov_storage::get_funds_key(proposal_id);
-        let balance_key = token::storage_key::balance_key(
-            native_token_address,
-            self.ctx.address,
-        );
-        let min_funds_parameter_key = gov_storage::get_min_proposal_fund_key();
+        // The funds of an existing proposal can't be changed
+        if self.ctx.has_key_pre(&funds_key)? {
+            return Ok(false);
+        }
 
-        let min_funds_parameter: token::Amount =
-            self.force_read(&min_funds_parameter_key, ReadType::Pre)?;
-        let pre_balance: Option<token::Amount> =
-            self.ctx.pre().read(&balance_key)?;
-        let post_balance: token::Amount =
-            self.force_read(&balance_key, ReadType::Post)?;
+        let min_funds_parameter =
+            self.new_proposal_params(proposal_id)?.min_proposal_fund;
         let post_funds: token::Amount =
             self.force_read(&funds_key, ReadType::Post)?;
 
-        if let Some(pre_balance) = pre_balance {
-            let is_post_funds_greater_than_minimum =
-                post_funds >= min_funds_parameter;
-            let is_valid_funds = post_balance >= pre_balance
-                && post_balance - pre_balance == post_funds;
-            Ok(is_post_funds_greater_than_minimum && is_valid_funds)
-        } else {
-            Ok(post_funds >= min_funds_parameter && post_balance == post_funds)
-        }
+        let is_post_funds_greater_than_minimum =
+            post_funds >= min_funds_parameter;
+        let is_valid_funds = self.is_valid_balance_change(
+            native_token_address,
+            expected_balance_change,
+        )?;
+        Ok(is_post_funds_greater_than_minimum && is_valid_funds)
     }
 
     /// Validate a balance key
-    fn is_valid_balance(&self, native_token_address: &Address) -> Result<bool> {
+    fn is_valid_balance(
+        &self,
+        native_token_address: &Address,
+        expected_balance_change: Option<token::Change>,
+    ) -> Result<bool> {
+        // The balance changes exactly by the funds of the proposals
+        // initialized by the tx minus the refunds of the withdrawn ones
+        if let Some(expected_balance_change) = expected_balance_change {
+            return self.is_valid_balance_change(
+                native_token_address,
+                expected_balance_change,
+            );
+        }
+
         let balance_key = token::storage_key::balance_key(
             native_token_address,
             self.ctx.address,
@@ -647,6 +1231,39 @@ where
         }
     }
 
+    /// Check that the governance balance changed by the expected amount
+    fn is_valid_balance_change(
+        &self,
+        native_token_address: &Address,
+        expected_balance_change: token::Change,
+    ) -> Result<bool> {
+        let balance_change =
+            self.balance_change(native_token_address, self.ctx.address)?;
+        let is_valid = balance_change == expected_balance_change;
+        if !is_valid {
+            tracing::info!(
+                "The balance should change by the funds of the initialized \
+                 proposals minus the refunds of the withdrawn ones. Expected \
+                 {expected_balance_change}, got {balance_change}."
+            );
+        }
+        Ok(is_valid)
+    }
+
+    /// Get the change of the balance of the owner in the tx
+    fn balance_change(
+        &self,
+        token: &Address,
+        owner: &Address,
+    ) -> Result<token::Change> {
+        let balance_key = token::storage_key::balance_key(token, owner);
+        let pre_balance: token::Amount =
+            self.ctx.pre().read(&balance_key)?.unwrap_or_default();
+        let post_balance: token::Amount =
+            self.ctx.post().read(&balance_key)?.unwrap_or_default();
+        Ok(post_balance.change() - pre_balance.change())
+    }
+
     /// Validate a author key
     pub fn is_valid_author(
         &self,
@@ -663,8 +1280,127 @@ where
         let author = self.force_read(&author_key, ReadType::Post)?;
         let author_exists =
             namada_account::exists(&self.ctx.pre(), &author).unwrap_or(false);
+        if !author_exists || !verifiers.contains(&author) {
+            return Ok(false);
+        }
 
-        Ok(author_exists && verifiers.contains(&author))
+        // The new proposals are indexed, so they're included in the count
+        let max_author_proposals_key =
+            gov_storage::get_max_author_proposals_key();
+        let max_author_proposals: u64 =
+            self.force_read(&max_author_proposals_key, ReadType::Pre)?;
+        let current_epoch = self.ctx.get_block_epoch()?;
+        let active_proposals = count_active_author_proposals(
+            &self.ctx.post(),
+            &author,
+            current_epoch,
+        )?;
+        if active_proposals > max_author_proposals {
+            tracing::info!(
+                "The author {author} has {active_proposals} proposals which \
+                 haven't been activated, the maximum is \
+                 {max_author_proposals}."
+            );
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Validate an author proposal index key
+    fn is_valid_author_proposal_key(&self, key: &Key) -> Result<bool> {
+        let (author, proposal_id) = match gov_storage::get_author_proposal(key)
+        {
+            Some(author_proposal) => author_proposal,
+            None => return Ok(false),
+        };
+        let author_key = gov_storage::get_author_key(proposal_id);
+        let grace_epoch_key = gov_storage::get_grace_epoch_key(proposal_id);
+
+        // The index can only be written with a new proposal
+        let has_pre_index = self.ctx.has_key_pre(key)?;
+        let has_pre_author = self.ctx.has_key_pre(&author_key)?;
+        if has_pre_index || has_pre_author {
+            return Ok(false);
+        }
+
+        let post_author: Address =
+            self.force_read(&author_key, ReadType::Post)?;
+        let grace_epoch: Epoch =
+            self.force_read(&grace_epoch_key, ReadType::Post)?;
+        let activation_epoch: Epoch = self.force_read(key, ReadType::Post)?;
+
+        Ok(&post_author == author && activation_epoch == grace_epoch)
+    }
+
+    /// Validate a withdrawn key
+    fn is_valid_withdrawal(
+        &self,
+        proposal_id: u64,
+        verifiers: &BTreeSet<Address>,
+        withdrawal_refunds: &BTreeMap<Address, token::Amount>,
+        native_token_address: &Address,
+    ) -> Result<bool> {
+        let withdrawn_key = gov_storage::get_withdrawn_key(proposal_id);
+        let start_epoch_key =
+            gov_storage::get_voting_start_epoch_key(proposal_id);
+        let author_key = gov_storage::get_author_key(proposal_id);
+
+        let has_pre_withdrawn = self.ctx.has_key_pre(&withdrawn_key)?;
+        let has_post_withdrawn = self.ctx.has_key_post(&withdrawn_key)?;
+        if has_pre_withdrawn || !has_post_withdrawn {
+            return Ok(false);
+        }
+
+        let current_epoch = self.ctx.get_block_epoch()?;
+        let start_epoch: Epoch =
+            self.force_read(&start_epoch_key, ReadType::Pre)?;
+        if current_epoch >= start_epoch {
+            tracing::info!(
+                "The proposal {proposal_id} can only be withdrawn before the \
+                 voting start epoch {start_epoch}. Current epoch: \
+                 {current_epoch}."
+            );
+            return Ok(false);
+        }
+
+        let author: Address = self.force_read(&author_key, ReadType::Pre)?;
+        if !verifiers.contains(&author) {
+            return Ok(false);
+        }
+
+        // The author has to receive the funds of all its withdrawn proposals,
+        // minus the funds of the proposals it initializes in the same tx
+        let refund =
+            withdrawal_refunds.get(&author).copied().unwrap_or_default();
+        let init_funds = self.author_init_proposals_funds(&author)?;
+        let balance_change =
+            self.balance_change(native_token_address, &author)?;
+        Ok(balance_change == refund.change() - init_funds.change())
+    }
+
+    /// Get the total funds of the proposals of the author initialized by the
+    /// tx
+    fn author_init_proposals_funds(
+        &self,
+        author: &Address,
+    ) -> Result<token::Amount> {
+        let counter_key = gov_storage::get_counter_key();
+        let pre_counter: u64 = self.force_read(&counter_key, ReadType::Pre)?;
+        let post_counter: u64 =
+            self.force_read(&counter_key, ReadType::Post)?;
+        let mut funds = token::Amount::zero();
+        for proposal_id in pre_counter..post_counter {
+            let author_key = gov_storage::get_author_key(proposal_id);
+            let proposal_author: Address =
+                self.force_read(&author_key, ReadType::Post)?;
+            if &proposal_author == author {
+                let funds_key = gov_storage::get_funds_key(proposal_id);
+                let proposal_funds: token::Amount =
+                    self.force_read(&funds_key, ReadType::Post)?;
+                funds += proposal_funds;
+            }
+        }
+        Ok(funds)
     }
 
     /// Validate a counter key
@@ -691,21 +1427,34 @@ where
     }
 
     /// Validate a governance parameter
-    pub fn is_valid_parameter(&self, tx: &Tx) -> Result<bool> {
-        match tx.data() {
+    pub fn is_valid_parameter(&self, key: &Key, tx: &Tx) -> Result<bool> {
+        let is_accepted = match tx.data() {
             Some(data) => is_proposal_accepted(&self.ctx.pre(), data.as_ref())
-                .map_err(Error::NativeVpError),
-            None => Ok(false),
+                .map_err(Error::NativeVpError)?,
+            None => false,
+        };
+        if !is_accepted {
+            return Ok(false);
+        }
+        if gov_storage::is_threshold_default_key(key)
+            || gov_storage::is_threshold_pgf_steward_key(key)
+            || gov_storage::is_threshold_pgf_payment_key(key)
+        {
+            let threshold: Dec = self.force_read(key, ReadType::Post)?;
+            return Ok(is_valid_threshold(&threshold));
         }
+        Ok(true)
     }
 
-    /// Check if a vote is from a validator
+    /// Check if a vote is from a validator. A consensus validator is looked up
+    /// in the voting power snapshot of the epoch, if any.
     pub fn is_validator(
         &self,
-        _epoch: Epoch,
+        epoch: Epoch,
         verifiers: &BTreeSet<Address>,
         address: &Address,
         delegation_address: &Address,
+        votes_cache: &VotesCache,
     ) -> Result<bool>
     where
         S: StateRead,
@@ -715,25 +1464,58 @@ where
             return Ok(false);
         }
 
-        let is_validator = is_validator(&self.ctx.pre(), address)?;
+        let is_validator = match votes_cache.is_in_voting_power_snapshot(
+            &self.ctx.pre(),
+            epoch,
+            address,
+        )? {
+            Some(true) => true,
+            _ => is_validator(&self.ctx.pre(), address)?,
+        };
 
         Ok(is_validator && verifiers.contains(address))
     }
 
+    /// Check if the vote is of a PGF steward on a PGF payment proposal. A
+    /// steward can only vote with its own address as the delegation.
+    fn is_steward_vote(
+        &self,
+        proposal_id: u64,
+        verifiers: &BTreeSet<Address>,
+        address: &Address,
+        delegation_address: &Address,
+    ) -> Result<bool> {
+        if address != delegation_address
+            || !verifiers.contains(address)
+            || !is_steward(&self.ctx.pre(), address)?
+        {
+            return Ok(false);
+        }
+        let proposal_type_key = gov_storage::get_proposal_type_key(proposal_id);
+        let proposal_type: ProposalType =
+            self.force_read(&proposal_type_key, ReadType::Pre)?;
+        Ok(matches!(proposal_type, ProposalType::PGFPayment(_)))
+    }
+
     /// Private method to read from storage data that are 100% in storage.
     fn force_read<T>(&self, key: &Key, read_type: ReadType) -> Result<T>
     where
         T: BorshDeserialize,
     {
         let res = match read_type {
-            ReadType::Pre => self.ctx.pre().read::<T>(key),
-            ReadType::Post => self.ctx.post().read::<T>(key),
+            ReadType::Pre => self.ctx.pre().read_bytes(key),
+            ReadType::Post => self.ctx.post().read_bytes(key),
         }?;
 
-        if let Some(data) = res {
-            Ok(data)
-        } else {
-            Err(Error::EmptyProposalField(key.to_string()))
+        match res {
+            Some(bytes) => {
+                T::try_from_slice(&bytes).map_err(|source| Error::Decode {
+                    key: key.clone(),
+                    type_name: std::any::type_name::<T>(),
+                    source,
+                })
+            }
+            None => Err(Error::EmptyProposalField(key.to_string())),
         }
     }
 
@@ -773,6 +1555,97 @@ where
     }
 }
 
+/// The voting power snapshots and the delegations of the voters read by the
+/// votes of a tx. They're read and decoded once per VP run rather than once
+/// per vote key.
+#[derive(Default)]
+struct VotesCache {
+    snapshots: RefCell<HashMap<Epoch, Option<VotingPowerSnapshot>>>,
+    delegations:
+        RefCell<HashMap<(Address, Epoch), Rc<HashMap<Address, token::Amount>>>>,
+}
+
+impl VotesCache {
+    /// Check if the address is in the voting power snapshot of the epoch.
+    /// Returns `None` if there's no snapshot for the epoch.
+    fn is_in_voting_power_snapshot<S>(
+        &self,
+        storage: &S,
+        epoch: Epoch,
+        address: &Address,
+    ) -> Result<Option<bool>>
+    where
+        S: StorageRead,
+    {
+        let mut snapshots = self.snapshots.borrow_mut();
+        let snapshot = match snapshots.entry(epoch) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(read_voting_power_snapshot(storage, epoch)?)
+            }
+        };
+        Ok(snapshot
+            .as_ref()
+            .map(|snapshot| snapshot.contains_key(address)))
+    }
+
+    /// Get the delegations of the owner at the epoch
+    fn delegations<S>(
+        &self,
+        storage: &S,
+        owner: &Address,
+        epoch: Epoch,
+    ) -> Result<Rc<HashMap<Address, token::Amount>>>
+    where
+        S: StorageRead,
+    {
+        let key = (owner.clone(), epoch);
+        if let Some(delegations) = self.delegations.borrow().get(&key) {
+            return Ok(delegations.clone());
+        }
+        let delegations = Rc::new(find_delegations(storage, owner, &epoch)?);
+        self.delegations
+            .borrow_mut()
+            .insert(key, delegations.clone());
+        Ok(delegations)
+    }
+}
+
+/// A value that can't be decoded rejects the tx instead of failing the VP.
+/// Returns `None` when the tx has to be rejected.
+fn reject_on_decode_error<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err @ Error::Decode { .. }) => {
+            tracing::info!("Rejected with error: {err}");
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// A coarse reason of a rejection of a tx by the governance VP, from the
+/// types of the governance keys changed by the tx, e.g. to be used in the
+/// metrics. The keys written by the initialization of a proposal are
+/// reported together.
+pub fn rejection_reason(
+    keys_changed: &BTreeSet<Key>,
+    native_token: &Address,
+) -> &'static str {
+    let key_types = keys_changed
+        .iter()
+        .map(|key| KeyType::from_key(key, native_token))
+        .filter(|key_type| !matches!(key_type, KeyType::UNKNOWN))
+        .collect::<Vec<_>>();
+    if key_types
+        .iter()
+        .any(|key_type| matches!(key_type, KeyType::COUNTER))
+    {
+        return "init_proposal";
+    }
+    key_types.first().map_or("unknown", KeyType::label)
+}
+
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug)]
 enum KeyType {
@@ -781,6 +1654,8 @@ enum KeyType {
     #[allow(non_camel_case_types)]
     VOTE,
     #[allow(non_camel_case_types)]
+    VOTE_MEMO,
+    #[allow(non_camel_case_types)]
     CONTENT,
     #[allow(non_camel_case_types)]
     PROPOSAL_CODE,
@@ -791,6 +1666,8 @@ enum KeyType {
     #[allow(non_camel_case_types)]
     GRACE_EPOCH,
     #[allow(non_camel_case_types)]
+    PARAMS_SNAPSHOT,
+    #[allow(non_camel_case_types)]
     START_EPOCH,
     #[allow(non_camel_case_types)]
     END_EPOCH,
@@ -803,6 +1680,12 @@ enum KeyType {
     #[allow(non_camel_case_types)]
     PARAMETER,
     #[allow(non_camel_case_types)]
+    WITHDRAWN,
+    #[allow(non_camel_case_types)]
+    AUTHOR_PROPOSAL,
+    #[allow(non_camel_case_types)]
+    PROPOSAL_RESULT,
+    #[allow(non_camel_case_types)]
     UNKNOWN_GOVERNANCE,
     #[allow(non_camel_case_types)]
     UNKNOWN,
@@ -812,6 +1695,8 @@ impl KeyType {
     fn from_key(key: &Key, native_token: &Address) -> Self {
         if gov_storage::is_vote_key(key) {
             Self::VOTE
+        } else if gov_storage::is_vote_memo_key(key) {
+            Self::VOTE_MEMO
         } else if gov_storage::is_content_key(key) {
             KeyType::CONTENT
         } else if gov_storage::is_proposal_type_key(key) {
@@ -820,6 +1705,8 @@ impl KeyType {
             Self::PROPOSAL_CODE
         } else if gov_storage::is_grace_epoch_key(key) {
             KeyType::GRACE_EPOCH
+        } else if gov_storage::is_proposal_params_snapshot_key(key) {
+            KeyType::PARAMS_SNAPSHOT
         } else if gov_storage::is_start_epoch_key(key) {
             KeyType::START_EPOCH
         } else if gov_storage::is_commit_proposal_key(key) {
@@ -834,6 +1721,12 @@ impl KeyType {
             KeyType::COUNTER
         } else if gov_storage::is_parameter_key(key) {
             KeyType::PARAMETER
+        } else if gov_storage::is_withdrawn_key(key) {
+            KeyType::WITHDRAWN
+        } else if gov_storage::is_author_proposal_key(key) {
+            KeyType::AUTHOR_PROPOSAL
+        } else if gov_storage::is_proposal_result_key(key) {
+            KeyType::PROPOSAL_RESULT
         } else if token::storage_key::is_balance_key(native_token, key)
             .is_some()
         {
@@ -844,4 +1737,1934 @@ impl KeyType {
             KeyType::UNKNOWN
         }
     }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::COUNTER => "counter",
+            Self::VOTE => "vote",
+            Self::VOTE_MEMO => "vote_memo",
+            Self::CONTENT => "content",
+            Self::PROPOSAL_CODE => "proposal_code",
+            Self::TYPE => "proposal_type",
+            Self::PROPOSAL_COMMIT => "proposal_commit",
+            Self::GRACE_EPOCH => "grace_epoch",
+            Self::PARAMS_SNAPSHOT => "params_snapshot",
+            Self::START_EPOCH => "start_epoch",
+            Self::END_EPOCH => "end_epoch",
+            Self::FUNDS => "funds",
+            Self::BALANCE => "balance",
+            Self::AUTHOR => "author",
+            Self::PARAMETER => "parameter",
+            Self::WITHDRAWN => "withdrawn",
+            Self::AUTHOR_PROPOSAL => "author_proposal",
+            Self::PROPOSAL_RESULT => "proposal_result",
+            Self::UNKNOWN_GOVERNANCE => "unknown_governance_key",
+            Self::UNKNOWN => "unknown",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::str::FromStr;
+
+    use borsh_ext::BorshSerializeExt;
+    use namada_core::validity_predicate::VpSentinel;
+    use namada_gas::TxGasMeter;
+    use namada_governance::parameters::GovernanceParameters;
+    use namada_governance::pgf::storage::steward::StewardDetail;
+    use namada_governance::pgf::storage::write_steward_commission_rate;
+    use namada_governance::storage::proposal::{
+        InitProposalData, PGFIbcTarget,
+    };
+    use namada_governance::storage::{
+        apply_voting_period_extension, init_proposal,
+        write_voting_power_snapshot,
+    };
+    use namada_governance::utils::ProposalResultRecord;
+    use namada_state::testing::TestState;
+    use namada_state::StorageWrite;
+
+    use super::*;
+    use crate::core::address::testing::{
+        established_address_1, established_address_2, established_address_3,
+        established_address_4, gen_established_address, gen_implicit_address,
+    };
+    use crate::ibc::core::host::types::identifiers::{ChannelId, PortId};
+    use crate::key::testing::{common_sk_from_simple_seed, keypair_1};
+    use crate::ledger::gas::VpGasMeter;
+    use crate::ledger::native_vp::ibc::get_dummy_genesis_validator;
+    use crate::storage::TxIndex;
+    use crate::vm::wasm;
+
+    const TX_GAS_LIMIT: u64 = 1_000_000;
+    const PROPOSAL_ID: u64 = 0;
+
+    /// Init the state with an established account
+    fn init_storage(account: &Address) -> TestState {
+        let mut state = TestState::default();
+        state
+            .write_log_mut()
+            .write(&Key::validity_predicate(account), Hash::zero().to_vec())
+            .expect("write failed");
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        state
+    }
+
+    /// Write the proposal type in the tx and validate it
+    fn validate_proposal_type_bytes(
+        state: &mut TestState,
+        proposal_type: Vec<u8>,
+    ) -> Result<bool> {
+        let type_key = gov_storage::get_proposal_type_key(PROPOSAL_ID);
+        state
+            .write_log_mut()
+            .write(&type_key, proposal_type)
+            .expect("write failed");
+        let keys_changed = BTreeSet::from([type_key]);
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(vec![])
+            .sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let verifiers = BTreeSet::new();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &*state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        gov.is_valid_proposal_type(PROPOSAL_ID)
+    }
+
+    fn validate_pgf_payment(
+        state: &mut TestState,
+        fundings: impl IntoIterator<Item = PGFAction>,
+    ) -> Result<bool> {
+        let proposal_type =
+            ProposalType::PGFPayment(fundings.into_iter().collect());
+        validate_proposal_type_bytes(state, proposal_type.serialize_to_vec())
+    }
+
+    fn internal_target(target: Address, amount: u64) -> PGFTarget {
+        PGFTarget::Internal(PGFInternalTarget {
+            target,
+            amount: token::Amount::native_whole(amount),
+        })
+    }
+
+    fn ibc_target(amount: u64) -> PGFTarget {
+        PGFTarget::Ibc(PGFIbcTarget {
+            target: "cosmos1receiver".to_string(),
+            amount: token::Amount::native_whole(amount),
+            port_id: PortId::transfer(),
+            channel_id: ChannelId::new(0),
+        })
+    }
+
+    #[test]
+    fn test_pgf_payment_target_exists() {
+        let account = established_address_1();
+        let mut state = init_storage(&account);
+
+        // an established account
+        let result = validate_pgf_payment(
+            &mut state,
+            [PGFAction::Continuous(AddRemove::Add(internal_target(
+                account, 10,
+            )))],
+        );
+        assert!(result.expect("validation failed"));
+
+        // an implicit account and an internal address
+        let result = validate_pgf_payment(
+            &mut state,
+            [
+                PGFAction::Retro(internal_target(gen_implicit_address(), 10)),
+                PGFAction::Retro(internal_target(ADDRESS, 10)),
+            ],
+        );
+        assert!(result.expect("validation failed"));
+
+        // an established account which doesn't exist
+        let result = validate_pgf_payment(
+            &mut state,
+            [PGFAction::Continuous(AddRemove::Add(internal_target(
+                established_address_2(),
+                10,
+            )))],
+        );
+        assert!(!result.expect("validation failed"));
+        let result = validate_pgf_payment(
+            &mut state,
+            [PGFAction::Retro(internal_target(
+                established_address_2(),
+                10,
+            ))],
+        );
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_pgf_payment_zero_amount() {
+        let account = established_address_1();
+        let mut state = init_storage(&account);
+
+        let result = validate_pgf_payment(
+            &mut state,
+            [
+                PGFAction::Continuous(AddRemove::Add(ibc_target(10))),
+                PGFAction::Retro(internal_target(account.clone(), 10)),
+            ],
+        );
+        assert!(result.expect("validation failed"));
+
+        // a zero continuous funding
+        let result = validate_pgf_payment(
+            &mut state,
+            [PGFAction::Continuous(AddRemove::Add(internal_target(
+                account.clone(),
+                0,
+            )))],
+        );
+        assert!(!result.expect("validation failed"));
+
+        // a zero retro payment
+        let result =
+            validate_pgf_payment(&mut state, [PGFAction::Retro(ibc_target(0))]);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_pgf_payment_invalid_ibc_target() {
+        let mut state = init_storage(&established_address_1());
+
+        let proposal_type =
+            ProposalType::PGFPayment(BTreeSet::from([PGFAction::Retro(
+                ibc_target(10),
+            )]))
+            .serialize_to_vec();
+        let result =
+            validate_proposal_type_bytes(&mut state, proposal_type.clone());
+        assert!(result.expect("validation failed"));
+
+        // replace the channel ID with an invalid one
+        let channel_id = ChannelId::new(0).to_string();
+        let invalid_channel_id = channel_id.replace('-', "!");
+        let pos = proposal_type
+            .windows(channel_id.len())
+            .position(|window| window == channel_id.as_bytes())
+            .expect("the channel ID should be encoded");
+        let mut invalid_proposal_type = proposal_type;
+        invalid_proposal_type[pos..pos + channel_id.len()]
+            .copy_from_slice(invalid_channel_id.as_bytes());
+        let result =
+            validate_proposal_type_bytes(&mut state, invalid_proposal_type);
+        assert!(result.is_err());
+    }
+
+    /// Validate a proposal adding its author as a steward with the given
+    /// commission rate
+    fn validate_steward_commission_rate(rate: Option<Dec>) -> Result<bool> {
+        let author = established_address_1();
+        let mut state = init_storage(&author);
+        state
+            .write_log_mut()
+            .write(
+                &gov_storage::get_author_key(PROPOSAL_ID),
+                author.serialize_to_vec(),
+            )
+            .expect("write failed");
+        if let Some(rate) = rate {
+            let rate_key = pgf_storage::steward_commission_rates_handle()
+                .get_data_key(&author);
+            state
+                .write_log_mut()
+                .write(&rate_key, rate.serialize_to_vec())
+                .expect("write failed");
+        }
+        let proposal_type =
+            ProposalType::PGFSteward(BTreeSet::from([AddRemove::Add(author)]));
+        validate_proposal_type_bytes(
+            &mut state,
+            proposal_type.serialize_to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_pgf_steward_commission_rate() {
+        // no commission rate
+        let result = validate_steward_commission_rate(None);
+        assert!(!result.expect("validation failed"));
+
+        // a commission rate above 1
+        let rate = Dec::from_str("1.5").unwrap();
+        let result = validate_steward_commission_rate(Some(rate));
+        assert!(!result.expect("validation failed"));
+
+        // a valid commission rate
+        let rate = Dec::from_str("0.05").unwrap();
+        let result = validate_steward_commission_rate(Some(rate));
+        assert!(result.expect("validation failed"));
+    }
+
+    /// Init a proposal adding its author as a steward, after the author has
+    /// set the given commission rate
+    fn init_steward_proposal(
+        rate: Option<Dec>,
+    ) -> (TestState, namada_state::StorageResult<()>) {
+        let author = established_address_1();
+        let mut state = init_storage(&author);
+        GovernanceParameters::default()
+            .init_storage(&mut state)
+            .expect("init failed");
+        let native_token = state.in_mem().native_token.clone();
+        token::credit_tokens(
+            &mut state,
+            &native_token,
+            &author,
+            token::Amount::native_whole(1_000),
+        )
+        .expect("credit failed");
+        if let Some(rate) = rate {
+            write_steward_commission_rate(&mut state, author.clone(), rate)
+                .expect("write failed");
+        }
+        state.commit_block().expect("commit failed");
+
+        let stewards = BTreeSet::from([AddRemove::Add(author.clone())]);
+        let data = InitProposalData {
+            id: PROPOSAL_ID,
+            content: Hash::default(),
+            author,
+            r#type: ProposalType::PGFSteward(stewards),
+            voting_start_epoch: Epoch(3),
+            voting_end_epoch: Epoch(6),
+            grace_epoch: Epoch(12),
+        };
+        let result = init_proposal(&mut state, data, vec![], None);
+        (state, result)
+    }
+
+    #[test]
+    fn test_init_steward_proposal_commission_rate() {
+        // no commission rate
+        let (_, result) = init_steward_proposal(None);
+        assert!(result.is_err());
+
+        // a commission rate above 1
+        let rate = Dec::from_str("1.5").unwrap();
+        let (_, result) = init_steward_proposal(Some(rate));
+        assert!(result.is_err());
+
+        // a valid commission rate, which isn't changed by the proposal
+        let rate = Dec::from_str("0.05").unwrap();
+        let (mut state, result) = init_steward_proposal(Some(rate));
+        result.expect("init failed");
+        let rate_key = pgf_storage::steward_commission_rates_handle()
+            .get_data_key(&established_address_1());
+        assert!(!state.write_log().get_keys().contains(&rate_key));
+        let proposal_type: ProposalType = state
+            .read(&gov_storage::get_proposal_type_key(PROPOSAL_ID))
+            .expect("read failed")
+            .expect("missing proposal type");
+        let result = validate_proposal_type_bytes(
+            &mut state,
+            proposal_type.serialize_to_vec(),
+        );
+        assert!(result.expect("validation failed"));
+    }
+
+    /// Init the state with a validator, a delegator with a delegation and a
+    /// delegator whose delegation has been fully unbonded before the voting
+    /// period of the proposal, which is in progress
+    fn init_vote_storage() -> (TestState, Address, Address, Address) {
+        let mut state = TestState::default();
+        let validator = get_dummy_genesis_validator();
+        let validator_address = validator.address.clone();
+        pos::test_utils::test_init_genesis(
+            &mut state,
+            namada_proof_of_stake::OwnedPosParams::default(),
+            vec![validator].into_iter(),
+            Epoch(0),
+        )
+        .expect("init failed");
+
+        let native_token = state.in_mem().native_token.clone();
+        let amount = token::Amount::native_whole(100);
+        let delegator = established_address_2();
+        let unbonded_delegator = established_address_3();
+        for source in [&delegator, &unbonded_delegator] {
+            token::credit_tokens(&mut state, &native_token, source, amount)
+                .expect("credit failed");
+            pos::namada_proof_of_stake::bond_tokens(
+                &mut state,
+                Some(source),
+                &validator_address,
+                amount,
+                Epoch(0),
+                None,
+            )
+            .expect("bond failed");
+        }
+        pos::namada_proof_of_stake::unbond_tokens(
+            &mut state,
+            Some(&unbonded_delegator),
+            &validator_address,
+            amount,
+            Epoch(0),
+            false,
+        )
+        .expect("unbond failed");
+        init_voting_proposal(&mut state);
+
+        (state, validator_address, delegator, unbonded_delegator)
+    }
+
+    /// Init the state with validators and a delegator with a delegation to
+    /// each of them, and the proposal in its voting period
+    fn init_multi_delegation_storage(
+        num_validators: u64,
+    ) -> (TestState, Vec<Address>, Address) {
+        let mut state = TestState::default();
+        let validators: Vec<_> = (0..num_validators)
+            .map(|i| {
+                let mut validator = get_dummy_genesis_validator();
+                validator.address = gen_established_address();
+                validator.consensus_key =
+                    common_sk_from_simple_seed(2 * i).to_public();
+                validator.protocol_key =
+                    common_sk_from_simple_seed(2 * i + 1).to_public();
+                validator
+            })
+            .collect();
+        let validator_addresses: Vec<_> = validators
+            .iter()
+            .map(|validator| validator.address.clone())
+            .collect();
+        pos::test_utils::test_init_genesis(
+            &mut state,
+            namada_proof_of_stake::OwnedPosParams::default(),
+            validators.into_iter(),
+            Epoch(0),
+        )
+        .expect("init failed");
+
+        let native_token = state.in_mem().native_token.clone();
+        let amount = token::Amount::native_whole(100);
+        let delegator = established_address_2();
+        token::credit_tokens(
+            &mut state,
+            &native_token,
+            &delegator,
+            amount * num_validators,
+        )
+        .expect("credit failed");
+        for validator in &validator_addresses {
+            pos::namada_proof_of_stake::bond_tokens(
+                &mut state,
+                Some(&delegator),
+                validator,
+                amount,
+                Epoch(0),
+                None,
+            )
+            .expect("bond failed");
+        }
+        init_voting_proposal(&mut state);
+
+        (state, validator_addresses, delegator)
+    }
+
+    /// Write the proposal whose voting period starts after the pipeline and
+    /// move to an epoch within it
+    fn init_voting_proposal(state: &mut TestState) {
+        state
+            .write(&gov_storage::get_counter_key(), 1_u64)
+            .expect("write failed");
+        state
+            .write(
+                &gov_storage::get_voting_start_epoch_key(PROPOSAL_ID),
+                Epoch(2),
+            )
+            .expect("write failed");
+        state
+            .write(
+                &gov_storage::get_voting_end_epoch_key(PROPOSAL_ID),
+                Epoch(6),
+            )
+            .expect("write failed");
+        state.commit_block().expect("commit failed");
+        state.in_mem_mut().block.epoch = Epoch(3);
+    }
+
+    /// Write the vote in the tx and validate the vote key
+    fn validate_vote(
+        state: &mut TestState,
+        voter: &Address,
+        delegation: &Address,
+    ) -> Result<bool> {
+        let vote_key = gov_storage::get_vote_proposal_key(
+            PROPOSAL_ID,
+            voter.clone(),
+            delegation.clone(),
+        );
+        state
+            .write_log_mut()
+            .write(&vote_key, ProposalVote::Yay.serialize_to_vec())
+            .expect("write failed");
+        validate_vote_keys(state, voter, BTreeSet::from([vote_key]))
+    }
+
+    /// Validate the vote and vote memo keys changed by the tx
+    fn validate_vote_keys(
+        state: &TestState,
+        voter: &Address,
+        keys_changed: BTreeSet<Key>,
+    ) -> Result<bool> {
+        let verifiers = BTreeSet::from([voter.clone()]);
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(vec![])
+            .sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &*state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        let votes_cache = VotesCache::default();
+        for key in &keys_changed {
+            let is_valid = if gov_storage::is_vote_memo_key(key) {
+                gov.is_valid_vote_memo_key(PROPOSAL_ID, key, &keys_changed)?
+            } else {
+                gov.is_valid_vote_key(
+                    PROPOSAL_ID,
+                    key,
+                    &verifiers,
+                    &votes_cache,
+                )?
+            };
+            if !is_valid {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Write the vote memo in the tx, along with the vote if `with_vote`,
+    /// and validate the keys with a maximum memo length of 16 bytes
+    fn validate_vote_memo(
+        state: &mut TestState,
+        voter: &Address,
+        delegation: &Address,
+        memo: &str,
+        with_vote: bool,
+    ) -> Result<bool> {
+        state
+            .write(&gov_storage::get_max_vote_memo_length_key(), 16_u64)
+            .expect("write failed");
+        state.commit_block().expect("commit failed");
+
+        let mut keys_changed = BTreeSet::new();
+        if with_vote {
+            let vote_key = gov_storage::get_vote_proposal_key(
+                PROPOSAL_ID,
+                voter.clone(),
+                delegation.clone(),
+            );
+            state
+                .write_log_mut()
+                .write(&vote_key, ProposalVote::Nay.serialize_to_vec())
+                .expect("write failed");
+            keys_changed.insert(vote_key);
+        }
+        let memo_key = gov_storage::get_vote_memo_key(
+            PROPOSAL_ID,
+            voter.clone(),
+            delegation.clone(),
+        );
+        state
+            .write_log_mut()
+            .write(&memo_key, memo.to_string().serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(memo_key);
+        validate_vote_keys(state, voter, keys_changed)
+    }
+
+    #[test]
+    fn test_delegator_vote() {
+        let (mut state, validator, delegator, _) = init_vote_storage();
+        let result = validate_vote(&mut state, &delegator, &validator);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_zero_stake_delegator_vote() {
+        let (mut state, validator, _, unbonded_delegator) = init_vote_storage();
+        let result = validate_vote(&mut state, &unbonded_delegator, &validator);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_vote_without_stake() {
+        let (mut state, _, _, _) = init_vote_storage();
+        // an account which is neither a validator nor a delegator
+        let voter = established_address_4();
+        let result = validate_vote(&mut state, &voter, &voter);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_votes_cache() {
+        let (mut state, validator, delegator, _) = init_vote_storage();
+        let epoch = Epoch(2);
+        let snapshot = VotingPowerSnapshot::from([(
+            validator.clone(),
+            token::Amount::native_whole(100),
+        )]);
+        write_voting_power_snapshot(&mut state, epoch, &snapshot)
+            .expect("write failed");
+        let cache = VotesCache::default();
+
+        let is_in_snapshot = cache
+            .is_in_voting_power_snapshot(&state, epoch, &validator)
+            .expect("read failed");
+        assert_eq!(is_in_snapshot, Some(true));
+        let delegations = cache
+            .delegations(&state, &delegator, epoch)
+            .expect("read failed");
+        assert!(delegations.contains_key(&validator));
+
+        // the snapshot and the delegations are only read once per VP run
+        state
+            .delete(&gov_storage::get_voting_power_snapshot_key(epoch))
+            .expect("delete failed");
+        let is_in_snapshot = cache
+            .is_in_voting_power_snapshot(&state, epoch, &validator)
+            .expect("read failed");
+        assert_eq!(is_in_snapshot, Some(true));
+        let cached_delegations = cache
+            .delegations(&state, &delegator, epoch)
+            .expect("read failed");
+        assert!(Rc::ptr_eq(&delegations, &cached_delegations));
+
+        // an epoch without a snapshot
+        let is_in_snapshot = cache
+            .is_in_voting_power_snapshot(&state, epoch.next(), &validator)
+            .expect("read failed");
+        assert_eq!(is_in_snapshot, None);
+    }
+
+    /// Init the vote storage with a steward without stake and the proposal
+    /// of the given type
+    fn init_steward_vote_storage(
+        proposal_type: ProposalType,
+    ) -> (TestState, Address) {
+        let (mut state, _, _, _) = init_vote_storage();
+        let steward = established_address_4();
+        pgf_storage::stewards_handle()
+            .insert(
+                &mut state,
+                steward.clone(),
+                StewardDetail::base(steward.clone()),
+            )
+            .expect("write failed");
+        state
+            .write(
+                &gov_storage::get_proposal_type_key(PROPOSAL_ID),
+                proposal_type,
+            )
+            .expect("write failed");
+        state.commit_block().expect("commit failed");
+        (state, steward)
+    }
+
+    #[test]
+    fn test_steward_vote() {
+        // a steward without stake can vote on a PGF payment proposal
+        let (mut state, steward) = init_steward_vote_storage(
+            ProposalType::PGFPayment(BTreeSet::new()),
+        );
+        let result = validate_vote(&mut state, &steward, &steward);
+        assert!(result.expect("validation failed"));
+
+        // but not on a default proposal
+        let (mut state, steward) =
+            init_steward_vote_storage(ProposalType::Default(None));
+        let result = validate_vote(&mut state, &steward, &steward);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_vote_for_unrelated_delegation() {
+        let (mut state, validator, delegator, _) = init_vote_storage();
+        // the vote for the actual delegation
+        let result = validate_vote(&mut state, &delegator, &validator);
+        assert!(result.expect("validation failed"));
+        // an address which the voter hasn't delegated to
+        let result =
+            validate_vote(&mut state, &delegator, &established_address_4());
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_vote_with_subset_of_delegations() {
+        let (mut state, validators, delegator) =
+            init_multi_delegation_storage(5);
+        // a single tx votes for 2 of the 5 delegations
+        let mut keys_changed = BTreeSet::new();
+        for validator in &validators[..2] {
+            let vote_key = gov_storage::get_vote_proposal_key(
+                PROPOSAL_ID,
+                delegator.clone(),
+                validator.clone(),
+            );
+            state
+                .write_log_mut()
+                .write(&vote_key, ProposalVote::Yay.serialize_to_vec())
+                .expect("write failed");
+            keys_changed.insert(vote_key);
+        }
+        let result = validate_vote_keys(&state, &delegator, keys_changed);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_vote_with_memo() {
+        let (mut state, validator, delegator, _) = init_vote_storage();
+        let result = validate_vote_memo(
+            &mut state,
+            &delegator,
+            &validator,
+            "Nay because X",
+            true,
+        );
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_vote_with_oversized_memo() {
+        let (mut state, validator, delegator, _) = init_vote_storage();
+        let result = validate_vote_memo(
+            &mut state,
+            &delegator,
+            &validator,
+            "Nay because of X, Y and Z",
+            true,
+        );
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_vote_memo_without_vote() {
+        let (mut state, validator, delegator, _) = init_vote_storage();
+        let result = validate_vote_memo(
+            &mut state,
+            &delegator,
+            &validator,
+            "Nay because X",
+            false,
+        );
+        assert!(!result.expect("validation failed"));
+    }
+
+    /// Init the state with a proposal of the author whose voting period
+    /// starts at epoch 2
+    fn init_withdrawal_storage(author: &Address) -> TestState {
+        let mut state = TestState::default();
+        let native_token = state.in_mem().native_token.clone();
+        let funds = token::Amount::native_whole(100);
+        state
+            .write(&gov_storage::get_min_proposal_fund_key(), funds)
+            .expect("write failed");
+        state
+            .write(&gov_storage::get_counter_key(), 1_u64)
+            .expect("write failed");
+        state
+            .write(&gov_storage::get_author_key(PROPOSAL_ID), author)
+            .expect("write failed");
+        state
+            .write(
+                &gov_storage::get_voting_start_epoch_key(PROPOSAL_ID),
+                Epoch(2),
+            )
+            .expect("write failed");
+        state
+            .write(&gov_storage::get_funds_key(PROPOSAL_ID), funds)
+            .expect("write failed");
+        token::credit_tokens(&mut state, &native_token, &ADDRESS, funds)
+            .expect("credit failed");
+        state.commit_block().expect("commit failed");
+        state
+    }
+
+    /// Withdraw the proposal in the tx and validate it
+    fn validate_withdrawal(
+        state: &mut TestState,
+        author: &Address,
+        verifier: &Address,
+    ) -> Result<bool> {
+        let native_token = state.in_mem().native_token.clone();
+        let funds = token::Amount::native_whole(100);
+        let withdrawn_key = gov_storage::get_withdrawn_key(PROPOSAL_ID);
+        let gov_balance_key =
+            token::storage_key::balance_key(&native_token, &ADDRESS);
+        let author_balance_key =
+            token::storage_key::balance_key(&native_token, author);
+        state
+            .write_log_mut()
+            .write(&withdrawn_key, ().serialize_to_vec())
+            .expect("write failed");
+        state
+            .write_log_mut()
+            .write(&gov_balance_key, token::Amount::zero().serialize_to_vec())
+            .expect("write failed");
+        state
+            .write_log_mut()
+            .write(&author_balance_key, funds.serialize_to_vec())
+            .expect("write failed");
+        let keys_changed = BTreeSet::from([
+            withdrawn_key,
+            gov_balance_key,
+            author_balance_key,
+        ]);
+        let verifiers = BTreeSet::from([verifier.clone()]);
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(vec![])
+            .sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &*state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        gov.validate_tx(&tx, &keys_changed, &verifiers)
+    }
+
+    #[test]
+    fn test_proposal_withdrawal() {
+        let author = established_address_1();
+        let mut state = init_withdrawal_storage(&author);
+        state.in_mem_mut().block.epoch = Epoch(1);
+
+        let result = validate_withdrawal(&mut state, &author, &author);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_proposal_withdrawal_after_voting_start() {
+        let author = established_address_1();
+        let mut state = init_withdrawal_storage(&author);
+        state.in_mem_mut().block.epoch = Epoch(2);
+
+        let result = validate_withdrawal(&mut state, &author, &author);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_proposal_withdrawal_by_non_author() {
+        let author = established_address_1();
+        let mut state = init_withdrawal_storage(&author);
+        state.in_mem_mut().block.epoch = Epoch(1);
+
+        // the funds are refunded to the author, but the author doesn't sign
+        let result =
+            validate_withdrawal(&mut state, &author, &established_address_2());
+        assert!(!result.expect("validation failed"));
+    }
+
+    /// Withdraw the proposal and initialize a new one of the same author in
+    /// the tx, with the given post balance of the governance account, and
+    /// validate the withdrawal, the funds and the balance
+    fn validate_withdrawal_and_init(
+        gov_post_balance: token::Amount,
+    ) -> Result<bool> {
+        let author = established_address_1();
+        let mut state = init_withdrawal_storage(&author);
+        GovernanceParameters::default()
+            .init_storage(&mut state)
+            .expect("init failed");
+        let native_token = state.in_mem().native_token.clone();
+        let params = read_proposal_params(&state).expect("read failed");
+        token::credit_tokens(
+            &mut state,
+            &native_token,
+            &author,
+            params.min_proposal_fund,
+        )
+        .expect("credit failed");
+        state.commit_block().expect("commit failed");
+        state.in_mem_mut().block.epoch = Epoch(1);
+
+        let new_proposal_id = PROPOSAL_ID + 1;
+        let refund = token::Amount::native_whole(100);
+        let counter_key = gov_storage::get_counter_key();
+        let withdrawn_key = gov_storage::get_withdrawn_key(PROPOSAL_ID);
+        let author_key = gov_storage::get_author_key(new_proposal_id);
+        let funds_key = gov_storage::get_funds_key(new_proposal_id);
+        let snapshot_key =
+            gov_storage::get_proposal_params_snapshot_key(new_proposal_id);
+        let gov_balance_key =
+            token::storage_key::balance_key(&native_token, &ADDRESS);
+        let author_balance_key =
+            token::storage_key::balance_key(&native_token, &author);
+        let writes = [
+            (&counter_key, (new_proposal_id + 1).serialize_to_vec()),
+            (&withdrawn_key, ().serialize_to_vec()),
+            (&author_key, author.serialize_to_vec()),
+            (&funds_key, params.min_proposal_fund.serialize_to_vec()),
+            (&snapshot_key, params.serialize_to_vec()),
+            (&gov_balance_key, gov_post_balance.serialize_to_vec()),
+            (&author_balance_key, refund.serialize_to_vec()),
+        ];
+        for (key, value) in writes.clone() {
+            state
+                .write_log_mut()
+                .write(key, value)
+                .expect("write failed");
+        }
+        let keys_changed: BTreeSet<Key> =
+            writes.into_iter().map(|(key, _)| key.clone()).collect();
+        let verifiers = BTreeSet::from([author]);
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(vec![])
+            .sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        let withdrawal_refunds = gov.withdrawal_refunds(&keys_changed)?;
+        let expected_balance_change =
+            gov.expected_balance_change(1, &withdrawal_refunds)?;
+        Ok(gov.is_valid_withdrawal(
+            PROPOSAL_ID,
+            &verifiers,
+            &withdrawal_refunds,
+            &native_token,
+        )? && gov.is_valid_funds(
+            new_proposal_id,
+            &native_token,
+            expected_balance_change.unwrap_or_default(),
+        )? && gov
+            .is_valid_balance(&native_token, expected_balance_change)?)
+    }
+
+    #[test]
+    fn test_proposal_withdrawal_and_init() {
+        let min_proposal_fund =
+            GovernanceParameters::default().min_proposal_fund;
+
+        // the refund is taken from the balance and the funds of the new
+        // proposal are deposited
+        let result = validate_withdrawal_and_init(min_proposal_fund);
+        assert!(result.expect("validation failed"));
+
+        // the funds of the new proposal are taken along with the refund
+        let result = validate_withdrawal_and_init(token::Amount::zero());
+        assert!(!result.expect("validation failed"));
+
+        // the refund is not taken from the balance
+        let result = validate_withdrawal_and_init(
+            min_proposal_fund + token::Amount::native_whole(100),
+        );
+        assert!(!result.expect("validation failed"));
+    }
+
+    /// Init the state with the author's account and the parameters allowing
+    /// 2 concurrent proposals per author
+    fn init_author_storage(author: &Address) -> TestState {
+        let mut state = init_storage(author);
+        let params = GovernanceParameters {
+            max_concurrent_proposals_per_author: 2,
+            ..Default::default()
+        };
+        params.init_storage(&mut state).expect("init failed");
+        state.commit_block().expect("commit failed");
+        state
+    }
+
+    /// Write a proposal of the author with its activation epoch
+    fn write_author_proposal(
+        state: &mut TestState,
+        author: &Address,
+        proposal_id: u64,
+        activation_epoch: Epoch,
+    ) {
+        state
+            .write(&gov_storage::get_author_key(proposal_id), author)
+            .expect("write failed");
+        state
+            .write(
+                &gov_storage::get_author_proposal_key(author, proposal_id),
+                activation_epoch,
+            )
+            .expect("write failed");
+        state.commit_block().expect("commit failed");
+    }
+
+    /// Write a new proposal of the author in the tx and validate its author
+    /// and its index key
+    fn validate_new_author_proposal(
+        state: &mut TestState,
+        author: &Address,
+        proposal_id: u64,
+    ) -> Result<bool> {
+        let grace_epoch = Epoch(10);
+        let author_key = gov_storage::get_author_key(proposal_id);
+        let grace_epoch_key = gov_storage::get_grace_epoch_key(proposal_id);
+        let index_key =
+            gov_storage::get_author_proposal_key(author, proposal_id);
+        state
+            .write_log_mut()
+            .write(&author_key, author.serialize_to_vec())
+            .expect("write failed");
+        state
+            .write_log_mut()
+            .write(&grace_epoch_key, grace_epoch.serialize_to_vec())
+            .expect("write failed");
+        state
+            .write_log_mut()
+            .write(&index_key, grace_epoch.serialize_to_vec())
+            .expect("write failed");
+        let keys_changed =
+            BTreeSet::from([author_key, grace_epoch_key, index_key.clone()]);
+        let verifiers = BTreeSet::from([author.clone()]);
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(vec![])
+            .sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &*state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        Ok(gov.is_valid_author(proposal_id, &verifiers)?
+            && gov.is_valid_author_proposal_key(&index_key)?)
+    }
+
+    #[test]
+    fn test_max_concurrent_author_proposals() {
+        let author = established_address_1();
+        let mut state = init_author_storage(&author);
+        write_author_proposal(&mut state, &author, 0, Epoch(10));
+
+        // the second proposal is within the limit
+        let result = validate_new_author_proposal(&mut state, &author, 1);
+        assert!(result.expect("validation failed"));
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+
+        // the third one exceeds it
+        let result = validate_new_author_proposal(&mut state, &author, 2);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_activated_author_proposals() {
+        let author = established_address_1();
+        let mut state = init_author_storage(&author);
+        write_author_proposal(&mut state, &author, 0, Epoch(1));
+        write_author_proposal(&mut state, &author, 1, Epoch(2));
+
+        // both proposals are active
+        let result = validate_new_author_proposal(&mut state, &author, 2);
+        assert!(!result.expect("validation failed"));
+        state.write_log_mut().drop_tx();
+
+        // the first proposal has been activated
+        state.in_mem_mut().block.epoch = Epoch(1);
+        let result = validate_new_author_proposal(&mut state, &author, 2);
+        assert!(result.expect("validation failed"));
+    }
+
+    /// Initialize two proposals with the given funds in the tx, deposit the
+    /// given amount to the governance account and validate the funds and the
+    /// balance
+    fn validate_funds(funds: [u64; 2], deposit: u64) -> Result<bool> {
+        let author = established_address_1();
+        let mut state = init_storage(&author);
+        GovernanceParameters::default()
+            .init_storage(&mut state)
+            .expect("init failed");
+        state.commit_block().expect("commit failed");
+
+        let native_token = state.in_mem().native_token.clone();
+        let counter_key = gov_storage::get_counter_key();
+        let balance_key =
+            token::storage_key::balance_key(&native_token, &ADDRESS);
+        state
+            .write_log_mut()
+            .write(&counter_key, 2u64.serialize_to_vec())
+            .expect("write failed");
+        state
+            .write_log_mut()
+            .write(
+                &balance_key,
+                token::Amount::native_whole(deposit).serialize_to_vec(),
+            )
+            .expect("write failed");
+        let mut keys_changed = BTreeSet::from([counter_key, balance_key]);
+        let params = read_proposal_params(&state).expect("read failed");
+        for (proposal_id, funds) in funds.into_iter().enumerate() {
+            let funds_key = gov_storage::get_funds_key(proposal_id as u64);
+            let snapshot_key = gov_storage::get_proposal_params_snapshot_key(
+                proposal_id as u64,
+            );
+            state
+                .write_log_mut()
+                .write(
+                    &funds_key,
+                    token::Amount::native_whole(funds).serialize_to_vec(),
+                )
+                .expect("write failed");
+            state
+                .write_log_mut()
+                .write(&snapshot_key, params.serialize_to_vec())
+                .expect("write failed");
+            keys_changed.insert(funds_key);
+            keys_changed.insert(snapshot_key);
+        }
+        let verifiers = BTreeSet::from([author]);
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(vec![])
+            .sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        let expected_balance_change = gov
+            .expected_balance_change(2, &BTreeMap::new())?
+            .unwrap_or_default();
+        Ok(
+            gov.is_valid_funds(0, &native_token, expected_balance_change)?
+                && gov.is_valid_funds(
+                    1,
+                    &native_token,
+                    expected_balance_change,
+                )?
+                && gov.is_valid_balance(
+                    &native_token,
+                    Some(expected_balance_change),
+                )?,
+        )
+    }
+
+    #[test]
+    fn test_funds_of_multiple_proposals() {
+        // the deposit matches the total funds
+        let result = validate_funds([500, 500], 1000);
+        assert!(result.expect("validation failed"));
+
+        // only the funds of a single proposal are deposited
+        let result = validate_funds([500, 500], 500);
+        assert!(!result.expect("validation failed"));
+
+        // more than the total funds are deposited
+        let result = validate_funds([500, 500], 1500);
+        assert!(!result.expect("validation failed"));
+
+        // the funds of a proposal are below the minimum
+        let result = validate_funds([400, 600], 1000);
+        assert!(!result.expect("validation failed"));
+    }
+
+    /// Write the proposal content in the tx and validate it
+    fn validate_content(content: Vec<u8>) -> Result<bool> {
+        let mut state = init_storage(&established_address_1());
+        GovernanceParameters::default()
+            .init_storage(&mut state)
+            .expect("init failed");
+        state.commit_block().expect("commit failed");
+        let content_key = gov_storage::get_content_key(PROPOSAL_ID);
+        state
+            .write_log_mut()
+            .write(&content_key, content)
+            .expect("write failed");
+        let keys_changed = BTreeSet::from([content_key]);
+        let verifiers = BTreeSet::new();
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(vec![])
+            .sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        gov.is_valid_content_key(PROPOSAL_ID)
+    }
+
+    /// Validate a tx writing the given value under the given key
+    fn validate_write(key: Key, value: Vec<u8>) -> Result<bool> {
+        validate_proposal_write(key, value, None)
+    }
+
+    /// Validate the write of the key by the given accepted proposal, if any
+    fn validate_proposal_write(
+        key: Key,
+        value: Vec<u8>,
+        proposal_id: Option<u64>,
+    ) -> Result<bool> {
+        let mut state = init_storage(&established_address_1());
+        GovernanceParameters::default()
+            .init_storage(&mut state)
+            .expect("init failed");
+        let tx_data = match proposal_id {
+            Some(id) => {
+                let execution_key = gov_storage::get_proposal_execution_key(id);
+                state.write(&execution_key, ()).expect("write failed");
+                id.serialize_to_vec()
+            }
+            None => vec![],
+        };
+        state.commit_block().expect("commit failed");
+        state
+            .write_log_mut()
+            .write(&key, value)
+            .expect("write failed");
+        let keys_changed = BTreeSet::from([key]);
+        let verifiers = BTreeSet::new();
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(tx_data)
+            .sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        gov.validate_tx(&tx, &keys_changed, &verifiers)
+    }
+
+    #[test]
+    fn test_proposal_result_written_by_tx() {
+        let record = ProposalResultRecord {
+            passed: true,
+            yay: token::Amount::native_whole(100),
+            nay: token::Amount::zero(),
+            abstain: token::Amount::zero(),
+            total_voting_power: token::Amount::native_whole(100),
+            turnout: Dec::one(),
+        };
+        let key = gov_storage::get_proposal_result_record_key(PROPOSAL_ID);
+        let result = validate_write(key, record.serialize_to_vec());
+        assert!(!result.expect("validation failed"));
+
+        let key = gov_storage::get_proposal_result_key(PROPOSAL_ID);
+        let result = validate_write(key, vec![]);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_threshold_params_written_by_tx() {
+        let threshold = Dec::new(9, 1).expect("Test failed");
+        for key in [
+            gov_storage::get_threshold_default_key(),
+            gov_storage::get_threshold_pgf_steward_key(),
+            gov_storage::get_threshold_pgf_payment_key(),
+        ] {
+            let result = validate_write(key, threshold.serialize_to_vec());
+            assert!(!result.expect("validation failed"));
+        }
+    }
+
+    #[test]
+    fn test_threshold_params_bounds() {
+        for key in [
+            gov_storage::get_threshold_default_key(),
+            gov_storage::get_threshold_pgf_steward_key(),
+            gov_storage::get_threshold_pgf_payment_key(),
+        ] {
+            for (threshold, is_valid) in [
+                (Dec::zero(), true),
+                (Dec::one(), true),
+                (Dec::new(11, 1).expect("Test failed"), false),
+                (-Dec::new(1, 1).expect("Test failed"), false),
+            ] {
+                let result = validate_proposal_write(
+                    key.clone(),
+                    threshold.serialize_to_vec(),
+                    Some(0),
+                );
+                assert_eq!(result.expect("validation failed"), is_valid);
+            }
+        }
+    }
+
+    #[test]
+    fn test_corrupt_counter_written_by_tx() {
+        // the counter isn't a u64
+        let key = gov_storage::get_counter_key();
+        let result = validate_write(key, vec![0xff; 3]);
+        assert!(!result.expect("validation failed"));
+    }
+
+    fn proposal_content() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("title".to_string(), "TheTitle".to_string()),
+            ("authors".to_string(), "test@test.com".to_string()),
+            ("details".to_string(), "The details".to_string()),
+            ("license".to_string(), "MIT".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_valid_proposal_content() {
+        let result = validate_content(proposal_content().serialize_to_vec());
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_proposal_content_missing_field() {
+        let mut content = proposal_content();
+        content.remove("title");
+        let result = validate_content(content.serialize_to_vec());
+        assert!(!result.expect("validation failed"));
+
+        // an empty required field
+        let mut content = proposal_content();
+        content.insert("authors".to_string(), " ".to_string());
+        let result = validate_content(content.serialize_to_vec());
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_proposal_content_value_too_long() {
+        let mut content = proposal_content();
+        content.insert(
+            "details".to_string(),
+            "d".repeat(MAX_CONTENT_FIELD_VALUE_LENGTH),
+        );
+        let result = validate_content(content.serialize_to_vec());
+        assert!(result.expect("validation failed"));
+
+        content.insert(
+            "details".to_string(),
+            "d".repeat(MAX_CONTENT_FIELD_VALUE_LENGTH + 1),
+        );
+        let result = validate_content(content.serialize_to_vec());
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_proposal_content_not_utf8() {
+        let mut content = proposal_content().serialize_to_vec();
+        let title = b"TheTitle";
+        let pos = content
+            .windows(title.len())
+            .position(|window| window == title)
+            .expect("the title should be encoded");
+        content[pos] = 0xff;
+        let result = validate_content(content);
+        assert!(!result.expect("validation failed"));
+
+        // arbitrary bytes
+        let result = validate_content(vec![0xff; 32]);
+        assert!(!result.expect("validation failed"));
+    }
+
+    /// The max proposal code size in the tests of the proposal code
+    const MAX_PROPOSAL_CODE_SIZE: u64 = 32;
+
+    /// A minimal valid wasm module
+    fn minimal_wasm() -> Vec<u8> {
+        b"\0asm\x01\0\0\0".to_vec()
+    }
+
+    /// Write the proposal code in the tx and validate it. The code is
+    /// attached to the tx in an extra section when `attach` is set and it's
+    /// added to the tx allowlist when `allowlist` is set.
+    fn validate_proposal_code(
+        code: Vec<u8>,
+        attach: bool,
+        allowlist: bool,
+    ) -> Result<bool> {
+        let mut state = init_storage(&established_address_1());
+        GovernanceParameters {
+            max_proposal_code_size: MAX_PROPOSAL_CODE_SIZE,
+            ..Default::default()
+        }
+        .init_storage(&mut state)
+        .expect("init failed");
+        if allowlist {
+            let code_hash = Hash::sha256(&code).to_string().to_lowercase();
+            state
+                .write(&get_tx_allowlist_storage_key(), vec![code_hash])
+                .expect("write failed");
+        }
+        state.commit_block().expect("commit failed");
+
+        let type_key = gov_storage::get_proposal_type_key(PROPOSAL_ID);
+        let code_key = gov_storage::get_proposal_code_key(PROPOSAL_ID);
+        state
+            .write_log_mut()
+            .write(&type_key, ProposalType::Default(None).serialize_to_vec())
+            .expect("write failed");
+        state
+            .write_log_mut()
+            .write(&code_key, code.clone())
+            .expect("write failed");
+        let keys_changed = BTreeSet::from([type_key, code_key]);
+        let verifiers = BTreeSet::new();
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None).add_serialized_data(vec![]);
+        if attach {
+            tx.add_extra_section(code, None);
+        }
+        tx.sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        gov.is_valid_proposal_code(PROPOSAL_ID)
+    }
+
+    #[test]
+    fn test_valid_proposal_code() {
+        let result = validate_proposal_code(minimal_wasm(), true, false);
+        assert!(result.expect("validation failed"));
+
+        // an allowlisted code doesn't have to be attached
+        let result = validate_proposal_code(minimal_wasm(), false, true);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_proposal_code_not_attached() {
+        let result = validate_proposal_code(minimal_wasm(), false, false);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_proposal_code_not_wasm() {
+        let result = validate_proposal_code(vec![0xff; 16], true, false);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_oversized_proposal_code() {
+        // a valid module with a custom section padding it over the max size
+        let mut code = minimal_wasm();
+        let payload_len = MAX_PROPOSAL_CODE_SIZE as u8;
+        code.extend([0, 5 + payload_len, 4]);
+        code.extend(b"name");
+        code.extend(vec![0; payload_len as usize]);
+        assert!(code.len() as u64 > MAX_PROPOSAL_CODE_SIZE);
+
+        let result = validate_proposal_code(code, true, false);
+        assert!(!result.expect("validation failed"));
+    }
+
+    /// Init the state with an ongoing proposal voting from epoch 2 to 8,
+    /// with the grace epoch 20
+    fn init_extension_storage() -> TestState {
+        let mut state = TestState::default();
+        GovernanceParameters::default()
+            .init_storage(&mut state)
+            .expect("init failed");
+        state
+            .write(&gov_storage::get_counter_key(), 1_u64)
+            .expect("write failed");
+        state
+            .write(
+                &gov_storage::get_voting_start_epoch_key(PROPOSAL_ID),
+                Epoch(2),
+            )
+            .expect("write failed");
+        state
+            .write(
+                &gov_storage::get_voting_end_epoch_key(PROPOSAL_ID),
+                Epoch(8),
+            )
+            .expect("write failed");
+        state
+            .write(&gov_storage::get_grace_epoch_key(PROPOSAL_ID), Epoch(20))
+            .expect("write failed");
+        state.commit_block().expect("commit failed");
+        state
+    }
+
+    /// Write a new proposal extending the voting period of the ongoing
+    /// proposal in the tx, activated in the epoch 7, and validate its type
+    fn validate_voting_period_extension(
+        state: &mut TestState,
+        epochs: u64,
+    ) -> Result<bool> {
+        validate_voting_period_extension_activated_at(state, epochs, Epoch(7))
+    }
+
+    /// Write a new proposal extending the voting period of the ongoing
+    /// proposal in the tx, activated in the given epoch, and validate its
+    /// type
+    fn validate_voting_period_extension_activated_at(
+        state: &mut TestState,
+        epochs: u64,
+        activation_epoch: Epoch,
+    ) -> Result<bool> {
+        let new_proposal_id = PROPOSAL_ID + 1;
+        let proposal_type =
+            ProposalType::ExtendVotingPeriod(VotingPeriodExtension {
+                proposal_id: PROPOSAL_ID,
+                epochs,
+            });
+        let counter_key = gov_storage::get_counter_key();
+        let type_key = gov_storage::get_proposal_type_key(new_proposal_id);
+        let grace_epoch_key = gov_storage::get_grace_epoch_key(new_proposal_id);
+        state
+            .write_log_mut()
+            .write(&counter_key, (new_proposal_id + 1).serialize_to_vec())
+            .expect("write failed");
+        state
+            .write_log_mut()
+            .write(&type_key, proposal_type.serialize_to_vec())
+            .expect("write failed");
+        state
+            .write_log_mut()
+            .write(&grace_epoch_key, activation_epoch.serialize_to_vec())
+            .expect("write failed");
+        let keys_changed =
+            BTreeSet::from([counter_key, type_key, grace_epoch_key]);
+
+        let tx = new_tx(state);
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let verifiers = BTreeSet::new();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &*state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        let result = gov.is_valid_proposal_type(new_proposal_id);
+        state.write_log_mut().drop_tx();
+        result
+    }
+
+    fn new_tx(state: &TestState) -> Tx {
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(vec![])
+            .sign_wrapper(keypair_1());
+        tx
+    }
+
+    #[test]
+    fn test_voting_period_extension() {
+        let mut state = init_extension_storage();
+        state.in_mem_mut().block.epoch = Epoch(5);
+
+        let result = validate_voting_period_extension(&mut state, 4);
+        assert!(result.expect("validation failed"));
+
+        let result = validate_voting_period_extension(&mut state, 0);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_voting_period_extension_exceeding_max_period() {
+        let mut state = init_extension_storage();
+        state
+            .write(&gov_storage::get_max_proposal_period_key(), 10_u64)
+            .expect("write failed");
+        state.commit_block().expect("commit failed");
+        state.in_mem_mut().block.epoch = Epoch(5);
+
+        // the voting period would be 11 epochs
+        let result = validate_voting_period_extension(&mut state, 5);
+        assert!(!result.expect("validation failed"));
+
+        let result = validate_voting_period_extension(&mut state, 4);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_voting_period_extension_past_grace_epoch() {
+        let mut state = init_extension_storage();
+        state.in_mem_mut().block.epoch = Epoch(5);
+
+        // the min grace epochs are 6
+        let result = validate_voting_period_extension(&mut state, 7);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_voting_period_extension_of_finished_proposal() {
+        let mut state = init_extension_storage();
+        state.in_mem_mut().block.epoch = Epoch(9);
+
+        let result = validate_voting_period_extension(&mut state, 4);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_voting_period_extension_activated_after_end() {
+        let mut state = init_extension_storage();
+        state.in_mem_mut().block.epoch = Epoch(5);
+
+        // the extended proposal's voting ends in the epoch 8
+        let result = validate_voting_period_extension_activated_at(
+            &mut state,
+            4,
+            Epoch(8),
+        );
+        assert!(result.expect("validation failed"));
+        let result = validate_voting_period_extension_activated_at(
+            &mut state,
+            4,
+            Epoch(9),
+        );
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_extended_end_epoch_write_rejected() {
+        let mut state = init_extension_storage();
+        state.in_mem_mut().block.epoch = Epoch(5);
+
+        // the end epoch of an existing proposal can't be changed by a tx, even
+        // with an extension proposal
+        let end_epoch_key = gov_storage::get_voting_end_epoch_key(PROPOSAL_ID);
+        state
+            .write_log_mut()
+            .write(&end_epoch_key, Epoch(12).serialize_to_vec())
+            .expect("write failed");
+        let keys_changed = BTreeSet::from([end_epoch_key]);
+        let tx = new_tx(&state);
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let verifiers = BTreeSet::new();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        let result = gov.is_valid_end_epoch(PROPOSAL_ID);
+        assert!(!result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_apply_voting_period_extension() {
+        let mut state = init_extension_storage();
+        let extension = VotingPeriodExtension {
+            proposal_id: PROPOSAL_ID,
+            epochs: 4,
+        };
+        let end_epoch_key = gov_storage::get_voting_end_epoch_key(PROPOSAL_ID);
+
+        // the extension is applied when the proposal is still voting
+        let result =
+            apply_voting_period_extension(&mut state, &extension, Epoch(8));
+        assert!(result.expect("apply failed"));
+        let end_epoch: Option<Epoch> =
+            state.read(&end_epoch_key).expect("read failed");
+        assert_eq!(end_epoch, Some(Epoch(12)));
+
+        // another extension would end too close to the grace epoch
+        let result =
+            apply_voting_period_extension(&mut state, &extension, Epoch(10));
+        assert!(!result.expect("apply failed"));
+
+        // the voting has ended
+        let extension = VotingPeriodExtension {
+            proposal_id: PROPOSAL_ID,
+            epochs: 1,
+        };
+        let result =
+            apply_voting_period_extension(&mut state, &extension, Epoch(13));
+        assert!(!result.expect("apply failed"));
+        let end_epoch: Option<Epoch> =
+            state.read(&end_epoch_key).expect("read failed");
+        assert_eq!(end_epoch, Some(Epoch(12)));
+    }
+
+    #[test]
+    fn test_voting_period_extension_with_params_snapshot() {
+        let mut state = init_extension_storage();
+        // the proposal was created with the max period of 27 epochs and the
+        // min grace epochs of 6
+        let snapshot = read_proposal_params(&state).expect("read failed");
+        state
+            .write(
+                &gov_storage::get_proposal_params_snapshot_key(PROPOSAL_ID),
+                snapshot,
+            )
+            .expect("write failed");
+        // the parameters have been changed since
+        state
+            .write(&gov_storage::get_max_proposal_period_key(), 10_u64)
+            .expect("write failed");
+        state
+            .write(&gov_storage::get_min_proposal_grace_epoch_key(), 12_u64)
+            .expect("write failed");
+        state.commit_block().expect("commit failed");
+        state.in_mem_mut().block.epoch = Epoch(5);
+
+        // the voting period of 11 epochs and the 7 epochs before the grace
+        // epoch are valid for the snapshot
+        let result = validate_voting_period_extension(&mut state, 5);
+        assert!(result.expect("validation failed"));
+
+        // the min grace epochs of the snapshot are still enforced
+        let result = validate_voting_period_extension(&mut state, 7);
+        assert!(!result.expect("validation failed"));
+    }
+
+    /// Write the snapshot of the parameters of a proposal in a tx which
+    /// initializes a new proposal and validate it
+    fn validate_params_snapshot(
+        state: &mut TestState,
+        proposal_id: u64,
+        snapshot: ProposalParamsSnapshot,
+    ) -> Result<bool> {
+        let counter_key = gov_storage::get_counter_key();
+        let counter: u64 = state
+            .read(&counter_key)
+            .expect("read failed")
+            .unwrap_or_default();
+        state
+            .write_log_mut()
+            .write(&counter_key, (counter + 1).serialize_to_vec())
+            .expect("write failed");
+        let snapshot_key =
+            gov_storage::get_proposal_params_snapshot_key(proposal_id);
+        state
+            .write_log_mut()
+            .write(&snapshot_key, snapshot.serialize_to_vec())
+            .expect("write failed");
+        let keys_changed = BTreeSet::from([counter_key, snapshot_key]);
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(vec![])
+            .sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let verifiers = BTreeSet::new();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &*state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let gov = GovernanceVp { ctx };
+        gov.is_valid_params_snapshot(proposal_id)
+    }
+
+    #[test]
+    fn test_params_snapshot() {
+        let mut state = TestState::default();
+        GovernanceParameters::default()
+            .init_storage(&mut state)
+            .expect("init failed");
+        state.commit_block().expect("commit failed");
+        let params = read_proposal_params(&state).expect("read failed");
+
+        // the snapshot must match the current parameters
+        let result =
+            validate_params_snapshot(&mut state, PROPOSAL_ID, params.clone());
+        assert!(result.expect("validation failed"));
+        state.write_log_mut().drop_tx();
+
+        // the snapshot must belong to the proposal initialized by the tx
+        let result = validate_params_snapshot(
+            &mut state,
+            PROPOSAL_ID + 1,
+            params.clone(),
+        );
+        assert!(!result.expect("validation failed"));
+        state.write_log_mut().drop_tx();
+
+        let snapshot = ProposalParamsSnapshot {
+            min_proposal_grace_epochs: params.min_proposal_grace_epochs - 1,
+            ..params.clone()
+        };
+        let result =
+            validate_params_snapshot(&mut state, PROPOSAL_ID, snapshot);
+        assert!(!result.expect("validation failed"));
+        state.write_log_mut().drop_tx();
+
+        // the snapshot of an existing proposal can't be changed
+        state
+            .write(
+                &gov_storage::get_proposal_params_snapshot_key(PROPOSAL_ID),
+                &params,
+            )
+            .expect("write failed");
+        state.commit_block().expect("commit failed");
+        let result = validate_params_snapshot(&mut state, PROPOSAL_ID, params);
+        assert!(!result.expect("validation failed"));
+    }
 }
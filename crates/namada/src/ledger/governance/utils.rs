@@ -2,8 +2,14 @@
 
 use std::collections::HashMap;
 
-use namada_governance::utils::TallyResult;
+use namada_governance::storage as gov_api;
+use namada_governance::utils::{ProposalResult, TallyResult};
+pub use namada_governance::utils::{
+    DelegatorTally, DetailedTallyResult, ProposalResultRecord, ValidatorTally,
+};
 use namada_sdk::events::{Event, EventLevel};
+pub use namada_sdk::queries::vp::governance::compute_proposal_result_detailed;
+use namada_state::{StorageRead, StorageResult, StorageWrite};
 use thiserror::Error;
 
 use crate::ledger::events::EventType;
@@ -27,6 +33,23 @@ pub enum Error {
     Tally(String),
 }
 
+/// Finalize the result of a proposal at its activation epoch. The tally and
+/// its record are written to storage, so that the outcome of the proposal can
+/// be queried without tallying the votes again.
+pub fn finalize_proposal_result<S>(
+    storage: &mut S,
+    proposal_id: u64,
+    proposal_result: ProposalResult,
+) -> StorageResult<ProposalResultRecord>
+where
+    S: StorageRead + StorageWrite,
+{
+    let record = ProposalResultRecord::from(&proposal_result);
+    gov_api::write_proposal_result(storage, proposal_id, proposal_result)?;
+    gov_api::write_proposal_result_record(storage, proposal_id, record)?;
+    Ok(record)
+}
+
 /// Proposal event definition
 pub struct ProposalEvent {
     /// Proposal event type
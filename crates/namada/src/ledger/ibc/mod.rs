@@ -1,36 +1,481 @@
 //! IBC integration
 
-pub use namada_ibc::storage;
+use std::collections::BTreeSet;
+
+use namada_core::event::{EmitEvents, IbcThroughputEvent};
+use namada_core::storage::Epoch;
+pub use namada_ibc::audit::{
+    audit_ibc_escrow, escrowed_tokens, EscrowAuditReport, EscrowedPacket,
+};
+use namada_ibc::parameters::{read_params, IbcParameters};
+pub use namada_ibc::parameters::{
+    read_limits_usage, read_mint_usage, read_throughput_history,
+    read_throughput_usage, LimitsUsage, ThroughputRecord,
+};
+pub use namada_ibc::tokens::{list_ibc_tokens, IbcTokenInfo};
+pub use namada_ibc::{read_ibc_denom, storage};
 use namada_ibc::storage::{
     channel_counter_key, client_counter_key, connection_counter_key,
+    deposit_prefix, is_deposit_key, is_throughput_history_key, is_withdraw_key,
+    throughput_history_key, throughput_history_prefix, withdraw_prefix,
+    write_counter,
+};
+use namada_state::{
+    iter_prefix_bytes, State, StorageRead, StorageResult, StorageWrite,
 };
-use namada_state::State;
 
 /// Initialize storage in the genesis block.
 pub fn init_genesis_storage<S>(storage: &mut S)
 where
     S: State,
 {
-    // In ibc-go, u64 like a counter is encoded with big-endian:
-    // https://github.com/cosmos/ibc-go/blob/89ffaafb5956a5ea606e1f1bf249c880bea802ed/modules/core/04-channel/keeper/keeper.go#L115
-
     let init_value = 0_u64;
 
     // the client counter
     let key = client_counter_key();
-    storage
-        .write(&key, init_value)
+    write_counter(storage, &key, init_value)
         .expect("Unable to write the initial client counter");
 
     // the connection counter
     let key = connection_counter_key();
-    storage
-        .write(&key, init_value)
+    write_counter(storage, &key, init_value)
         .expect("Unable to write the initial connection counter");
 
     // the channel counter
     let key = channel_counter_key();
-    storage
-        .write(&key, init_value)
+    write_counter(storage, &key, init_value)
         .expect("Unable to write the initial channel counter");
+
+    // the parameters
+    IbcParameters::default()
+        .init_storage(storage)
+        .expect("Unable to write the IBC parameters");
+}
+
+/// Clear the per-epoch throughputs (deposit and withdraw) for the rate limits.
+/// The keys are deleted rather than zeroed so that the tokens without any
+/// transfer in the epoch don't keep keys in the storage. A missing throughput
+/// is read as zero. When called with the block-level write log, the prefixes
+/// are deleted from the DB with a single range deletion on the block commit.
+pub fn clear_throughputs<S>(storage: &mut S) -> StorageResult<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    storage.delete_prefix(&deposit_prefix())?;
+    storage.delete_prefix(&withdraw_prefix())
+}
+
+/// Record the throughputs of the tokens transferred in the ended epoch and
+/// emit an event per token, prune the records older than the retention of the
+/// IBC parameters and then clear the per-epoch throughputs. This is applied by
+/// the protocol on a new epoch, so the records aren't written by any tx.
+pub fn finalize_epoch_throughputs<S>(
+    storage: &mut S,
+    events: &mut impl EmitEvents,
+    ended_epoch: Epoch,
+) -> StorageResult<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let retention = read_params(storage)?.throughput_history_epochs;
+
+    let mut tokens = BTreeSet::new();
+    for prefix in [deposit_prefix(), withdraw_prefix()] {
+        for result in iter_prefix_bytes(storage, &prefix)? {
+            let (key, _) = result?;
+            if let Some(token) =
+                is_deposit_key(&key).or_else(|| is_withdraw_key(&key))
+            {
+                tokens.insert(token.clone());
+            }
+        }
+    }
+    for token in tokens {
+        let (deposit, withdraw, _) = read_throughput_usage(storage, &token)?;
+        let (minted, _) = read_mint_usage(storage, &token)?;
+        if retention > 0 {
+            let record = ThroughputRecord {
+                deposit,
+                withdraw,
+                minted,
+            };
+            storage
+                .write(&throughput_history_key(&token, ended_epoch), record)?;
+        }
+        events.emit(
+            IbcThroughputEvent {
+                token,
+                epoch: ended_epoch,
+                deposit,
+                withdraw,
+                minted,
+            }
+            .into(),
+        );
+    }
+
+    // Prune the records of the epochs out of the retention
+    let mut pruned = vec![];
+    for result in iter_prefix_bytes(storage, &throughput_history_prefix())? {
+        let (key, _) = result?;
+        if let Some((_, epoch)) = is_throughput_history_key(&key) {
+            if epoch.0.saturating_add(retention) <= ended_epoch.0 {
+                pruned.push(key);
+            }
+        }
+    }
+    for key in pruned {
+        storage.delete(&key)?;
+    }
+
+    clear_throughputs(storage)
+}
+
+#[cfg(test)]
+mod tests {
+    use namada_core::address::testing::{
+        established_address_1, established_address_2, nam,
+    };
+    use namada_core::address::{Address, InternalAddress, IBC_FEE_ESCROW};
+    use namada_core::borsh::BorshSerializeExt;
+    use namada_core::event::Event;
+    use namada_core::ibc::core::host::types::identifiers::{
+        ChannelId, PortId, Sequence,
+    };
+    use namada_core::ibc::{PacketEscrow, PacketFee};
+    use namada_core::token::{Amount, DenominatedAmount};
+    use namada_ibc::storage::{
+        calc_hash, commitment_key, deposit_key, escrow_amount_key,
+        fee_escrow_amount_key, ibc_denom_key, ibc_token, mint_amount_key,
+        mint_limit_key, next_sequence_send_key, packet_escrow_key,
+        packet_fee_key, read_counter, read_sequence, throughput_limit_key,
+        withdraw_key, write_sequence, U64Codec,
+    };
+    use namada_state::testing::TestState;
+
+    use super::*;
+    use crate::token::storage_key::balance_key;
+
+    #[test]
+    fn test_counter_and_sequence_codecs() {
+        let mut state = TestState::default();
+        init_genesis_storage(&mut state);
+
+        // the counters are borsh-encoded
+        let counter_key = client_counter_key();
+        assert_eq!(U64Codec::of_key(&counter_key), Some(U64Codec::Borsh));
+        assert_eq!(read_counter(&state, &counter_key).unwrap(), Some(0));
+        write_counter(&mut state, &counter_key, u64::MAX).unwrap();
+        assert_eq!(read_counter(&state, &counter_key).unwrap(), Some(u64::MAX));
+        assert_eq!(
+            state.read_bytes(&counter_key).unwrap(),
+            Some(u64::MAX.serialize_to_vec())
+        );
+
+        // the sequences are big-endian encoded
+        let seq_key =
+            next_sequence_send_key(&PortId::transfer(), &ChannelId::new(0));
+        assert_eq!(U64Codec::of_key(&seq_key), Some(U64Codec::BigEndian));
+        assert_eq!(read_sequence(&state, &seq_key).unwrap(), None);
+        write_sequence(&mut state, &seq_key, Sequence::from(7)).unwrap();
+        assert_eq!(
+            read_sequence(&state, &seq_key).unwrap(),
+            Some(Sequence::from(7))
+        );
+        assert_eq!(
+            state.read_bytes(&seq_key).unwrap(),
+            Some(7_u64.to_be_bytes().to_vec())
+        );
+
+        // a value which isn't a u64 can't be decoded
+        state.write_bytes(&seq_key, vec![0; 3]).unwrap();
+        assert!(read_sequence(&state, &seq_key).is_err());
+        assert_eq!(U64Codec::of_key(&mint_amount_key(&nam())), None);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "isn't a counter key")]
+    fn test_read_sequence_as_counter() {
+        let state = TestState::default();
+        let seq_key =
+            next_sequence_send_key(&PortId::transfer(), &ChannelId::new(0));
+        let _ = read_counter(&state, &seq_key);
+    }
+
+    #[test]
+    fn test_read_usage_without_transfers() {
+        let mut state = TestState::default();
+        init_genesis_storage(&mut state);
+        let token = nam();
+
+        let (minted, mint_limit) = read_mint_usage(&state, &token).unwrap();
+        assert_eq!(minted, Amount::zero());
+        assert_eq!(mint_limit, Amount::max());
+        let (deposit, withdraw, throughput_limit) =
+            read_throughput_usage(&state, &token).unwrap();
+        assert_eq!(deposit, Amount::zero());
+        assert_eq!(withdraw, Amount::zero());
+        assert_eq!(throughput_limit, Amount::max());
+    }
+
+    #[test]
+    fn test_read_usage() {
+        let mut state = TestState::default();
+        init_genesis_storage(&mut state);
+        let token = nam();
+        state
+            .write(&mint_limit_key(&token), Amount::native_whole(1000))
+            .unwrap();
+        state
+            .write(&throughput_limit_key(&token), Amount::native_whole(100))
+            .unwrap();
+        state
+            .write(&mint_amount_key(&token), Amount::native_whole(500))
+            .unwrap();
+        state
+            .write(&deposit_key(&token), Amount::native_whole(40))
+            .unwrap();
+        state
+            .write(&withdraw_key(&token), Amount::native_whole(10))
+            .unwrap();
+
+        let (minted, mint_limit) = read_mint_usage(&state, &token).unwrap();
+        assert_eq!(minted, Amount::native_whole(500));
+        assert_eq!(mint_limit, Amount::native_whole(1000));
+        let (deposit, withdraw, throughput_limit) =
+            read_throughput_usage(&state, &token).unwrap();
+        assert_eq!(deposit, Amount::native_whole(40));
+        assert_eq!(withdraw, Amount::native_whole(10));
+        assert_eq!(throughput_limit, Amount::native_whole(100));
+
+        // the throughputs are cleared on a new epoch
+        clear_throughputs(&mut state).unwrap();
+        assert!(!state.has_key(&deposit_key(&token)).unwrap());
+        assert!(!state.has_key(&withdraw_key(&token)).unwrap());
+        let usage = read_limits_usage(&state, &token).unwrap();
+        assert_eq!(
+            usage,
+            LimitsUsage {
+                minted: Amount::native_whole(500),
+                mint_limit: Amount::native_whole(1000),
+                deposit: Amount::zero(),
+                withdraw: Amount::zero(),
+                throughput_limit: Amount::native_whole(100),
+            }
+        );
+    }
+
+    #[test]
+    fn test_throughput_history() {
+        let mut state = TestState::default();
+        init_genesis_storage(&mut state);
+        let params = IbcParameters {
+            throughput_history_epochs: 2,
+            ..Default::default()
+        };
+        params.init_storage(&mut state).unwrap();
+        let token = nam();
+        let minted = Amount::native_whole(500);
+        state.write(&mint_amount_key(&token), minted).unwrap();
+
+        let mut history = vec![];
+        for epoch in 0..3 {
+            let epoch = Epoch(epoch);
+            let deposit = Amount::native_whole(10 * (epoch.0 + 1));
+            let withdraw = Amount::native_whole(epoch.0 + 1);
+            state.write(&deposit_key(&token), deposit).unwrap();
+            state.write(&withdraw_key(&token), withdraw).unwrap();
+
+            let mut events = vec![];
+            finalize_epoch_throughputs(&mut state, &mut events, epoch).unwrap();
+            let expected = IbcThroughputEvent {
+                token: token.clone(),
+                epoch,
+                deposit,
+                withdraw,
+                minted,
+            };
+            assert_eq!(events, vec![Event::from(expected)]);
+            assert!(!state.has_key(&deposit_key(&token)).unwrap());
+            assert!(!state.has_key(&withdraw_key(&token)).unwrap());
+            history.push((
+                epoch,
+                ThroughputRecord {
+                    deposit,
+                    withdraw,
+                    minted,
+                },
+            ));
+        }
+        // the record of the first epoch has been pruned
+        let actual = read_throughput_history(&state, &token).unwrap();
+        assert_eq!(actual, history[1..]);
+        assert!(
+            !state
+                .has_key(&throughput_history_key(&token, Epoch(0)))
+                .unwrap()
+        );
+
+        // nothing is recorded without any transfer in the epoch, but the old
+        // records are still pruned
+        let mut events = vec![];
+        finalize_epoch_throughputs(&mut state, &mut events, Epoch(3)).unwrap();
+        assert!(events.is_empty());
+        let actual = read_throughput_history(&state, &token).unwrap();
+        assert_eq!(actual, history[2..]);
+    }
+
+    #[test]
+    fn test_audit_ibc_escrow() {
+        let mut state = TestState::default();
+        init_genesis_storage(&mut state);
+        let token = nam();
+        let port_id = PortId::transfer();
+        let channel_id = ChannelId::new(0);
+
+        // 30 NAM has been escrowed, 10 NAM of which for an in-flight packet
+        // with 1 NAM of relayer fees
+        state
+            .write(&escrow_amount_key(&token), Amount::native_whole(30))
+            .unwrap();
+        let escrow = Address::Internal(InternalAddress::Ibc);
+        state
+            .write(&balance_key(&token, &escrow), Amount::native_whole(30))
+            .unwrap();
+        let sequence = Sequence::from(2);
+        state
+            .write_bytes(&commitment_key(&port_id, &channel_id, sequence), [0])
+            .unwrap();
+        let packet_escrow = PacketEscrow {
+            token: token.clone(),
+            amount: Amount::native_whole(10),
+        };
+        state
+            .write(
+                &packet_escrow_key(&port_id, &channel_id, sequence),
+                packet_escrow,
+            )
+            .unwrap();
+        let fee = PacketFee {
+            payer: established_address_1(),
+            token: token.clone(),
+            recv_fee: DenominatedAmount::native(Amount::native_whole(1)),
+            ack_fee: DenominatedAmount::native(Amount::zero()),
+            timeout_fee: DenominatedAmount::native(Amount::zero()),
+        };
+        state
+            .write(&packet_fee_key(&port_id, &channel_id, sequence), fee)
+            .unwrap();
+        state
+            .write(&fee_escrow_amount_key(&token), Amount::native_whole(1))
+            .unwrap();
+        state
+            .write(
+                &balance_key(&token, &IBC_FEE_ESCROW),
+                Amount::native_whole(1),
+            )
+            .unwrap();
+
+        assert_eq!(escrowed_tokens(&state).unwrap(), BTreeSet::from([nam()]));
+        let report = audit_ibc_escrow(&state, &token).unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(report.packet_fees, Amount::native_whole(1));
+        assert_eq!(report.packet_fee_records, Amount::native_whole(1));
+        assert_eq!(report.fee_balance, Amount::native_whole(1));
+        assert_eq!(report.in_flight, Amount::native_whole(10));
+
+        // the escrow record remaining after the commitment has been deleted
+        let sequence = Sequence::from(1);
+        let packet_escrow = PacketEscrow {
+            token: token.clone(),
+            amount: Amount::native_whole(20),
+        };
+        state
+            .write(
+                &packet_escrow_key(&port_id, &channel_id, sequence),
+                packet_escrow,
+            )
+            .unwrap();
+        let report = audit_ibc_escrow(&state, &token).unwrap();
+        assert!(!report.is_consistent());
+        assert_eq!(
+            report.stale_packets,
+            vec![EscrowedPacket {
+                port_id: port_id.to_string(),
+                channel_id: channel_id.to_string(),
+                sequence: 1,
+                amount: Amount::native_whole(20),
+            }]
+        );
+        assert!(report.uncovered_packets.is_empty());
+    }
+
+    #[test]
+    fn test_list_ibc_tokens() {
+        let mut state = TestState::default();
+        init_genesis_storage(&mut state);
+        assert!(list_ibc_tokens(&state, 0, u8::MAX).unwrap().is_empty());
+
+        // NAM received through the channel 0, whose trace is written under
+        // the receiver and under the token id as on a packet receipt
+        let trace_a = format!("transfer/channel-0/{}", nam());
+        let token_a = ibc_token(&trace_a);
+        let receiver = established_address_1();
+        for owner in [receiver.to_string(), nam().to_string()] {
+            state
+                .write(&ibc_denom_key(owner, calc_hash(&trace_a)), &trace_a)
+                .unwrap();
+        }
+        state
+            .write(&mint_amount_key(&token_a), Amount::from_u64(100))
+            .unwrap();
+        state
+            .write(&deposit_key(&token_a), Amount::from_u64(100))
+            .unwrap();
+
+        // another token received by two receivers through the channel 1
+        let trace_b = "transfer/channel-1/uatom".to_string();
+        let token_b = ibc_token(&trace_b);
+        for owner in [
+            receiver.to_string(),
+            established_address_2().to_string(),
+            "uatom".to_string(),
+        ] {
+            state
+                .write(&ibc_denom_key(owner, calc_hash(&trace_b)), &trace_b)
+                .unwrap();
+        }
+        state
+            .write(&mint_amount_key(&token_b), Amount::from_u64(50))
+            .unwrap();
+        state
+            .write(&withdraw_key(&token_b), Amount::from_u64(20))
+            .unwrap();
+
+        let tokens = list_ibc_tokens(&state, 0, u8::MAX).unwrap();
+        let mut expected = vec![
+            IbcTokenInfo {
+                address: token_a,
+                trace: trace_a,
+                minted: Amount::from_u64(100),
+                deposit: Amount::from_u64(100),
+                withdraw: Amount::zero(),
+            },
+            IbcTokenInfo {
+                address: token_b,
+                trace: trace_b,
+                minted: Amount::from_u64(50),
+                deposit: Amount::zero(),
+                withdraw: Amount::from_u64(20),
+            },
+        ];
+        expected.sort_by(|a, b| a.address.cmp(&b.address));
+        assert_eq!(tokens, expected);
+
+        // the pages only hold the tokens in their range
+        assert_eq!(list_ibc_tokens(&state, 0, 1).unwrap(), expected[..1]);
+        assert_eq!(list_ibc_tokens(&state, 1, 1).unwrap(), expected[1..]);
+        assert!(list_ibc_tokens(&state, 2, 1).unwrap().is_empty());
+    }
 }
@@ -0,0 +1,106 @@
+//! Metrics of the ledger, rendered in the Prometheus text exposition format.
+//!
+//! The counters are kept in the memory of the node and are reset on restart.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use namada_tx::data::VpsResult;
+
+use crate::address::{Address, InternalAddress};
+
+/// The name of the counter of the txs rejected by the native VPs
+pub const VP_REJECTIONS_TOTAL: &str = "namada_vp_rejections_total";
+
+/// The counts of the rejections by the native VPs, by the labels of the VP
+/// and of the reason
+static VP_REJECTIONS: Mutex<BTreeMap<(&'static str, &'static str), u64>> =
+    Mutex::new(BTreeMap::new());
+
+/// The label of the native VP of the given internal address
+pub fn vp_label(addr: &InternalAddress) -> &'static str {
+    match addr {
+        InternalAddress::PoS => "pos",
+        InternalAddress::PosSlashPool => "pos_slash_pool",
+        InternalAddress::Parameters => "parameters",
+        InternalAddress::Governance => "governance",
+        InternalAddress::Ibc => "ibc",
+        InternalAddress::IbcToken(_) => "ibc_token",
+        InternalAddress::EthBridge => "eth_bridge",
+        InternalAddress::EthBridgePool => "eth_bridge_pool",
+        InternalAddress::Erc20(_) => "erc20",
+        InternalAddress::Nut(_) => "nut",
+        InternalAddress::Multitoken => "multitoken",
+        InternalAddress::Pgf => "pgf",
+        InternalAddress::Masp => "masp",
+        InternalAddress::IbcFeeEscrow => "ibc_fee_escrow",
+    }
+}
+
+/// Increment the counter of the rejections by the native VP of the given
+/// internal address for the given reason
+pub fn inc_vp_rejections(addr: &InternalAddress, reason: &'static str) {
+    let mut rejections = VP_REJECTIONS.lock().unwrap();
+    *rejections.entry((vp_label(addr), reason)).or_default() += 1;
+}
+
+/// Count the rejections by the native VPs in the result of a tx. Only the
+/// results of the txs applied in a block should be recorded, so that the dry
+/// runs and the mempool checks aren't counted.
+pub fn record_vp_rejections(vps_result: &VpsResult) {
+    for (addr, reason) in &vps_result.rejection_reasons {
+        if let Address::Internal(addr) = addr {
+            inc_vp_rejections(addr, reason);
+        }
+    }
+}
+
+/// Read the counter of the rejections by the native VP of the given internal
+/// address for the given reason
+pub fn vp_rejections(addr: &InternalAddress, reason: &str) -> u64 {
+    VP_REJECTIONS
+        .lock()
+        .unwrap()
+        .get(&(vp_label(addr), reason))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Render the metrics in the Prometheus text exposition format
+pub fn render() -> String {
+    let mut rendered = format!(
+        "# HELP {VP_REJECTIONS_TOTAL} The number of the txs rejected by the \
+         native VPs\n# TYPE {VP_REJECTIONS_TOTAL} counter\n"
+    );
+    for ((vp, reason), count) in VP_REJECTIONS.lock().unwrap().iter() {
+        writeln!(
+            rendered,
+            "{VP_REJECTIONS_TOTAL}{{vp=\"{vp}\",reason=\"{reason}\"}} {count}"
+        )
+        .expect("Writing to a string cannot fail");
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_vp_rejections() {
+        // The reason is unique to this test as the counters are global
+        let reason = "test_render";
+        inc_vp_rejections(&InternalAddress::Pgf, reason);
+        inc_vp_rejections(&InternalAddress::Pgf, reason);
+        assert_eq!(vp_rejections(&InternalAddress::Pgf, reason), 2);
+
+        let rendered = render();
+        assert!(
+            rendered.contains(&format!("# TYPE {VP_REJECTIONS_TOTAL} counter"))
+        );
+        assert!(rendered.contains(&format!(
+            "{VP_REJECTIONS_TOTAL}{{vp=\"pgf\",reason=\"{reason}\"}} 2\n"
+        )));
+    }
+}
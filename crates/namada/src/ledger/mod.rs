@@ -1,8 +1,11 @@
 //! The ledger modules
 
 pub use namada_sdk::{eth_bridge, events};
+#[cfg(feature = "wasm-runtime")]
+pub mod engine;
 pub mod governance;
 pub mod ibc;
+pub mod metrics;
 pub mod native_vp;
 pub mod pgf;
 pub mod pos;
@@ -13,7 +16,7 @@ pub mod storage;
 pub mod vp_host_fns;
 
 #[cfg(feature = "wasm-runtime")]
-pub use dry_run_tx::dry_run_tx;
+pub use dry_run_tx::{dry_run_tx, dry_run_vps};
 pub use {
     namada_gas as gas, namada_parameters as parameters,
     namada_tx_env as tx_env, namada_vp_env as vp_env,
@@ -129,24 +132,83 @@ mod dry_run_tx {
             info: Default::default(),
         })
     }
+
+    /// Dry run a transaction against the native VPs of the internal addresses
+    /// that it touches, without committing any change. Returns the verdict of
+    /// each native VP. A wrapper transaction is dry run as its inner
+    /// transaction, without charging the fees.
+    pub fn dry_run_vps<'a, D, H, CA>(
+        mut ctx: RequestCtx<'a, D, H, VpCache<CA>, TxCache<CA>>,
+        request: &RequestQuery,
+    ) -> namada_state::StorageResult<EncodedResponseQuery>
+    where
+        D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+        H: 'static + StorageHasher + Sync,
+        CA: 'static + WasmCacheAccess + Sync,
+    {
+        use borsh_ext::BorshSerializeExt;
+        use namada_gas::TxGasMeter;
+        use namada_tx::data::{DecryptedTx, TxType};
+        use namada_tx::Tx;
+
+        use crate::ledger::protocol::ShellParams;
+        use crate::storage::TxIndex;
+
+        let mut temp_state = ctx.state.with_temp_write_log();
+        let mut tx = Tx::try_from(&request.data[..]).into_storage_result()?;
+        tx.validate_tx().into_storage_result()?;
+
+        if let TxType::Protocol(_) = tx.header().tx_type {
+            return Err(namada_state::StorageError::SimpleMessage(
+                "Protocol transactions are not checked by the VPs",
+            ));
+        }
+        // Cast tx to a decrypted for execution
+        tx.update_header(TxType::Decrypted(DecryptedTx::Decrypted));
+
+        // Use the max block gas as the gas limit
+        let tx_gas_meter = RefCell::new(TxGasMeter::new(GasLimit::from(
+            namada_parameters::get_max_block_gas(ctx.state)?,
+        )));
+        let vps_result = protocol::dry_run_native_vps(
+            tx,
+            &TxIndex(0),
+            ShellParams::new(
+                &tx_gas_meter,
+                &mut temp_state,
+                &mut ctx.vp_wasm_cache,
+                &mut ctx.tx_wasm_cache,
+            ),
+        )
+        .into_storage_result()?;
+        let data = vps_result.verdicts().serialize_to_vec();
+        Ok(EncodedResponseQuery {
+            data,
+            proof: None,
+            info: Default::default(),
+        })
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use std::collections::BTreeMap;
+
     use borsh::BorshDeserialize;
     use borsh_ext::BorshSerializeExt;
-    use namada_core::address;
+    use namada_core::address::{self, Address, InternalAddress};
     use namada_core::hash::Hash;
-    use namada_core::storage::{BlockHeight, Key};
+    use namada_core::storage::{BlockHeight, Key, KeySeg};
     use namada_sdk::queries::{
         EncodedResponseQuery, RequestCtx, RequestQuery, Router, RPC,
     };
     use namada_sdk::tendermint_rpc::{Error as RpcError, Response};
     use namada_state::testing::TestState;
-    use namada_state::StorageWrite;
+    use namada_state::{StorageRead, StorageWrite};
+    use namada_test_utils::tx_data::TxWriteData;
     use namada_test_utils::TestWasms;
     use namada_tx::data::decrypted::DecryptedTx;
-    use namada_tx::data::TxType;
+    use namada_tx::data::{TxType, VpVerdict};
     use namada_tx::{Code, Data, Tx};
     use tempfile::TempDir;
 
@@ -248,6 +310,8 @@ mod test {
             // really permit error types other than [`std::io::Error`]
             if request.path == "/shell/dry_run_tx" {
                 super::dry_run_tx(ctx, &request)
+            } else if request.path == "/shell/dry_run_vps" {
+                super::dry_run_vps(ctx, &request)
             } else {
                 self.rpc.handle(ctx, &request)
             }
@@ -364,4 +428,85 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_dry_run_vps() -> namada_state::StorageResult<()> {
+        let mut client = TestClient::new(RPC);
+        // store the wasm code
+        let tx_write = TestWasms::TxWriteStorageKey.read_bytes();
+        let tx_hash = Hash::sha256(&tx_write);
+        let key = Key::wasm_code(&tx_hash);
+        let len_key = Key::wasm_code_len(&tx_hash);
+        client.state.db_write(&key, &tx_write).unwrap();
+        client
+            .state
+            .db_write(&len_key, (tx_write.len() as u64).serialize_to_vec())
+            .unwrap();
+        // The parameters read by the PoS VP
+        crate::governance::parameters::GovernanceParameters::default()
+            .init_storage(&mut client.state)?;
+        crate::ledger::pos::write_pos_params(
+            &mut client.state,
+            &crate::ledger::pos::OwnedPosParams::default(),
+        )?;
+        client.state.commit_tx();
+        client.state.commit_block().unwrap();
+
+        let write_tx = |key: Key| {
+            let mut tx =
+                Tx::from_type(TxType::Decrypted(DecryptedTx::Decrypted));
+            tx.header.chain_id = client.state.in_mem().chain_id.clone();
+            tx.set_code(Code::from_hash(tx_hash, None));
+            tx.set_data(Data::new(
+                TxWriteData {
+                    key,
+                    value: vec![1],
+                }
+                .serialize_to_vec(),
+            ));
+            tx.to_bytes()
+        };
+
+        // An unknown key under PoS is accepted by its VP
+        let pos = Address::Internal(InternalAddress::PoS);
+        let pos_key = Key::from(pos.to_db_key())
+            .push(&"test".to_string())
+            .unwrap();
+        let result = RPC
+            .shell()
+            .dry_run_vps(&client, Some(write_tx(pos_key.clone())), None, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            result.data,
+            BTreeMap::from([(pos.clone(), VpVerdict::Accepted)])
+        );
+        assert!(!client.state.has_key(&pos_key)?);
+
+        // A protocol parameter may only be changed by governance
+        let params_key =
+            namada_parameters::storage::get_max_tx_write_count_key();
+        let result = RPC
+            .shell()
+            .dry_run_vps(
+                &client,
+                Some(write_tx(params_key.clone())),
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result.data,
+            BTreeMap::from([(
+                Address::Internal(InternalAddress::Parameters),
+                VpVerdict::Rejected(None)
+            )])
+        );
+        // The storage isn't modified
+        assert!(!client.state.has_key(&params_key)?);
+        assert!(client.state.write_log().get_keys().is_empty());
+
+        Ok(())
+    }
 }
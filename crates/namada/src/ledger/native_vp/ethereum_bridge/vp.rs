@@ -97,8 +97,9 @@ where
     ///
     /// Some other changes to the storage subspace of this account are expected
     /// to happen natively i.e. bypassing this validity predicate. For example,
-    /// changes to the `eth_msgs/...` keys. For those cases, we reject here as
-    /// no wasm transactions should be able to modify those keys.
+    /// changes to the `eth_msgs/...` keys. The storage subspace of this
+    /// account is reserved for the protocol, so wasm transactions already
+    /// fail to write those keys. We still reject them here as a safeguard.
     fn validate_tx(
         &self,
         _: &Tx,
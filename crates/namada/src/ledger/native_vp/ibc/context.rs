@@ -1,6 +1,6 @@
 //! Contexts for IBC validity predicate
 
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{HashMap, HashSet};
 
 use borsh_ext::BorshSerializeExt;
 use namada_core::storage::Epochs;
@@ -31,8 +31,8 @@ where
     store: HashMap<Key, StorageModification>,
     /// Context to read the previous value
     ctx: CtxPreStorageRead<'view, 'a, S, CA>,
-    /// IBC event
-    pub event: BTreeSet<IbcEvent>,
+    /// IBC events in the emission order
+    pub event: Vec<IbcEvent>,
 }
 
 impl<'view, 'a, S, CA> PseudoExecutionContext<'view, 'a, S, CA>
@@ -45,7 +45,7 @@ where
         Self {
             store: HashMap::new(),
             ctx,
-            event: BTreeSet::new(),
+            event: Vec::new(),
         }
     }
 
@@ -173,7 +173,7 @@ where
     CA: 'static + WasmCacheAccess,
 {
     fn emit_ibc_event(&mut self, event: IbcEvent) -> Result<()> {
-        self.event.insert(event);
+        self.event.push(event);
         Ok(())
     }
 
@@ -199,13 +199,12 @@ where
         let amount = crate::token::denom_to_amount(amount, token, self)?;
         let src_key = token::storage_key::balance_key(token, src);
         let dest_key = token::storage_key::balance_key(token, dest);
-        let src_bal: Option<Amount> = self.ctx.read(&src_key)?;
+        let src_bal: Option<Amount> = self.read(&src_key)?;
         let mut src_bal = src_bal.ok_or_else(|| {
             StorageError::new_const("the source has no balance")
         })?;
         src_bal.spend(&amount).into_storage_result()?;
-        let mut dest_bal: Amount =
-            self.ctx.read(&dest_key)?.unwrap_or_default();
+        let mut dest_bal: Amount = self.read(&dest_key)?.unwrap_or_default();
         dest_bal.receive(&amount).into_storage_result()?;
 
         self.write(&src_key, src_bal.serialize_to_vec())?;
@@ -230,12 +229,12 @@ where
         let amount = crate::token::denom_to_amount(amount, token, self)?;
         let target_key = token::storage_key::balance_key(token, target);
         let mut target_bal: Amount =
-            self.ctx.read(&target_key)?.unwrap_or_default();
+            self.read(&target_key)?.unwrap_or_default();
         target_bal.receive(&amount).into_storage_result()?;
 
         let minted_key = token::storage_key::minted_balance_key(token);
         let mut minted_bal: Amount =
-            self.ctx.read(&minted_key)?.unwrap_or_default();
+            self.read(&minted_key)?.unwrap_or_default();
         minted_bal.receive(&amount).into_storage_result()?;
 
         self.write(&target_key, target_bal.serialize_to_vec())?;
@@ -257,12 +256,12 @@ where
         let amount = crate::token::denom_to_amount(amount, token, self)?;
         let target_key = token::storage_key::balance_key(token, target);
         let mut target_bal: Amount =
-            self.ctx.read(&target_key)?.unwrap_or_default();
+            self.read(&target_key)?.unwrap_or_default();
         target_bal.spend(&amount).into_storage_result()?;
 
         let minted_key = token::storage_key::minted_balance_key(token);
         let mut minted_bal: Amount =
-            self.ctx.read(&minted_key)?.unwrap_or_default();
+            self.read(&minted_key)?.unwrap_or_default();
         minted_bal.spend(&amount).into_storage_result()?;
 
         self.write(&target_key, target_bal.serialize_to_vec())?;
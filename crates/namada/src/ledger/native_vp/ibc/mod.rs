@@ -2,31 +2,93 @@
 
 pub mod context;
 
+use std::any::TypeId;
 use std::cell::RefCell;
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Display;
 use std::rc::Rc;
+use std::sync::{OnceLock, RwLock};
 use std::time::Duration;
 
+use borsh::BorshDeserialize;
 use context::{PseudoExecutionContext, VpValidationContext};
-use namada_core::address::Address;
+use data_encoding::HEXUPPER;
+use masp_primitives::asset_type::AssetType;
+use masp_primitives::transaction::Transaction;
+use namada_core::address::{Address, InternalAddress, IBC_FEE_ESCROW, MASP};
+use namada_core::hash::StorageHasher;
+use namada_core::ibc::{
+    get_shielded_transfer, is_ibc_denom, IbcEvent, EVENT_TYPE_PACKET,
+};
+use namada_core::masp::encode_asset_type;
 use namada_core::storage::Key;
-use namada_gas::{IBC_ACTION_EXECUTE_GAS, IBC_ACTION_VALIDATE_GAS};
+use namada_core::token::{Amount, MaspDigitPos};
+use namada_gas::{
+    GasMetering, IBC_ACTION_EXECUTE_GAS, IBC_ACTION_EXECUTE_GAS_PER_BYTE,
+    IBC_ACTION_VALIDATE_GAS, IBC_ACTION_VALIDATE_GAS_PER_BYTE,
+    IBC_STATE_COMPARISON_GAS_PER_KEY, MEMORY_ACCESS_GAS_PER_BYTE,
+    VERIFY_TX_SIG_GAS,
+};
+use namada_governance::is_proposal_accepted;
+use namada_ibc::context::client::{AnyClientState, AnyConsensusState};
+use namada_ibc::parameters::{
+    get_channel_throughput_limit, get_limits, read_min_transfer_amount,
+    read_params, IbcParameters,
+};
 use namada_ibc::{
-    Error as ActionError, IbcActions, TransferModule, ValidationParams,
+    decode_message, received_ibc_token, token_amount, Error as ActionError,
+    IbcActions, IbcCommonContext, IbcMessage, PfmModule, ValidationParams,
 };
 use namada_proof_of_stake::storage::read_pos_params;
+use namada_proof_of_stake::PosParams;
 use namada_state::write_log::StorageModification;
-use namada_state::StateRead;
-use namada_tx::Tx;
+use namada_state::{ResultExt, StateRead, StorageResult};
+use namada_token::read_denom;
+use namada_token::storage_key::{balance_key, is_any_token_balance_key};
+use namada_tx::{Tx, VerifySigError};
 use namada_vp_env::VpEnv;
+use prost::Message;
 use thiserror::Error;
 
-use crate::ibc::core::host::types::identifiers::ChainId as IbcChainId;
-use crate::ledger::ibc::storage::{calc_hash, is_ibc_denom_key, is_ibc_key};
+use crate::ibc::apps::transfer::types::packet::PacketData;
+use crate::ibc::apps::transfer::types::{Amount as IbcAmount, PrefixedDenom};
+use crate::ibc::core::channel::types::channel::Order;
+use crate::ibc::core::channel::types::msgs::{ChannelMsg, PacketMsg};
+use crate::ibc::core::client::types::Height;
+use crate::ibc::core::commitment_types::specs::ProofSpecs;
+use crate::ibc::core::connection::types::msgs::ConnectionMsg;
+use crate::ibc::core::handler::types::msgs::MsgEnvelope;
+use crate::ibc::core::host::types::identifiers::{
+    ChainId as IbcChainId, ChannelId, ClientId, ConnectionId, PortId, Sequence,
+};
+use crate::ibc::primitives::proto::{Any, Protobuf};
+use crate::ibc::primitives::Timestamp;
+use crate::ledger::ibc::storage::{
+    calc_hash, channel_counter_key, client_counter_key, client_id,
+    client_state_key, connection_counter_key, consensus_height,
+    consensus_state_prefix, deposit_key, deposit_key_for_channel,
+    fee_escrow_amount_key, ibc_denom_key, ibc_token, is_blocked_trace_key,
+    is_client_update_height_key, is_client_update_timestamp_key,
+    is_consensus_state_key, is_deposit_key, is_deposit_key_for_channel,
+    is_fee_enabled_key, is_fee_escrow_amount_key, is_ibc_denom_by_token_key,
+    is_ibc_denom_key, is_ibc_key, is_ibc_params_key, is_limit_override_key,
+    is_payee_key, is_withdraw_key, is_withdraw_key_for_channel,
+    mint_amount_key, packet_fee_key, params_key, withdraw_key,
+    withdraw_key_for_channel, U64Codec,
+};
 use crate::ledger::native_vp::{self, Ctx, NativeVp};
 use crate::ledger::parameters::read_epoch_duration_parameter;
+use crate::tendermint::Time as TmTime;
 use crate::vm::WasmCacheAccess;
 
+/// The gas breakdown label of the pseudo execution of an IBC action
+pub const IBC_EXECUTE_GAS_LABEL: &str = "ibc_execute";
+/// The gas breakdown label of the validation of an IBC action
+pub const IBC_VALIDATE_GAS_LABEL: &str = "ibc_validate";
+/// The gas breakdown label of the comparison of the changed IBC keys and
+/// values with the result of the pseudo execution
+pub const IBC_STATE_READ_GAS_LABEL: &str = "ibc_state_read";
+
 #[allow(missing_docs)]
 #[derive(Error, Debug)]
 pub enum Error {
@@ -38,10 +100,347 @@ pub enum Error {
     NoTxData,
     #[error("IBC action error: {0}")]
     IbcAction(ActionError),
-    #[error("State change error: {0}")]
+    #[error("IBC VP rejected the tx: {0}")]
+    Rejected(VpRejectReason),
+    #[error("Relayer fee switch error: {0}")]
+    FeeSwitch(String),
+    #[error("Relayer fee payee error: {0}")]
+    Payee(String),
+    #[error("Relayer fee error: {0}")]
+    RelayerFee(String),
+    #[error("IBC parameter change error: {0}")]
+    ParameterChange(String),
+    #[error(
+        "The IBC parameters can be updated only by an accepted governance \
+         proposal: Keys {0:?}"
+    )]
+    UnauthorizedParameterChange(Vec<Key>),
+    #[error("IBC client update error: {0}")]
+    ClientUpdate(String),
+    #[error("IBC client recovery error: {0}")]
+    ClientRecovery(String),
+    #[error("IBC state change error: {0}")]
     StateChange(String),
-    #[error("IBC event error: {0}")]
-    IbcEvent(String),
+    #[error("IBC shielded transfer error: {0}")]
+    ShieldedTransfer(String),
+    #[error("Decoding the value of {key} as {type_name} failed: {source}")]
+    Decode {
+        key: Key,
+        type_name: &'static str,
+        source: std::io::Error,
+    },
+    #[error("The sender of the transfer didn't authorize the tx: {0}")]
+    Unauthorized(String),
+    #[error("The tx exceeds the IBC limit of {limit} {what}: {size}")]
+    TooLarge {
+        what: &'static str,
+        size: u64,
+        limit: u64,
+    },
+    #[error(
+        "The IBC event at index {index} is out of order: Actual {actual:?}, \
+         Expected {expected:?}"
+    )]
+    IbcEvent {
+        index: usize,
+        actual: Option<String>,
+        expected: Option<String>,
+    },
+    #[error(
+        "The IBC client {client_id} has expired and has to be updated: Last \
+         update {last_update}, Trusting period {trusting_period:?}"
+    )]
+    ClientExpired {
+        client_id: ClientId,
+        last_update: Timestamp,
+        trusting_period: Duration,
+    },
+}
+
+impl Error {
+    /// The structured reason if the tx has been rejected by a check of the
+    /// VP
+    pub fn reject_reason(&self) -> Option<&VpRejectReason> {
+        match self {
+            Self::Rejected(reason) => Some(reason),
+            _ => None,
+        }
+    }
+
+    /// A coarse label of the error, e.g. to be used in the metrics
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::NativeVpError(_) => "native_vp",
+            Self::Decoding(_) | Self::Decode { .. } => "decoding",
+            Self::NoTxData => "no_tx_data",
+            Self::IbcAction(_) => "ibc_action",
+            Self::Rejected(reason) => reason.label(),
+            Self::FeeSwitch(_) => "fee_switch",
+            Self::Payee(_) => "payee",
+            Self::RelayerFee(_) => "relayer_fee",
+            Self::ParameterChange(_) | Self::UnauthorizedParameterChange(_) => {
+                "parameter_change"
+            }
+            Self::ClientUpdate(_) => "client_update",
+            Self::ClientRecovery(_) => "client_recovery",
+            Self::StateChange(_) => "state_change",
+            Self::ShieldedTransfer(_) => "shielded_transfer",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::TooLarge { .. } => "too_large",
+            Self::IbcEvent { .. } => "event",
+            Self::ClientExpired { .. } => "client_expired",
+        }
+    }
+}
+
+/// The reason of the IBC VP rejecting a tx
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VpRejectReason {
+    /// The IBC keys changed by the tx differ from the ones changed by the
+    /// pseudo execution of the IBC message
+    KeyMismatch {
+        /// The number of the IBC keys changed by the tx
+        actual_count: usize,
+        /// The number of the IBC keys changed by the pseudo execution
+        expected_count: usize,
+        /// The keys changed only by the tx, sorted
+        unexpected: Vec<Key>,
+        /// The keys changed only by the pseudo execution, sorted
+        missing: Vec<Key>,
+    },
+    /// The value of a changed IBC key differs from the one written by the
+    /// pseudo execution. `None` is a deleted value.
+    ValueMismatch {
+        /// The changed key
+        key: Key,
+        /// The value written by the tx
+        actual: Option<Vec<u8>>,
+        /// The value written by the pseudo execution
+        expected: Option<Vec<u8>>,
+    },
+    /// The IBC events emitted by the tx differ from the ones emitted by the
+    /// pseudo execution
+    EventMismatch {
+        /// The types of the events emitted only by the tx
+        unexpected: Vec<String>,
+        /// The types of the events emitted only by the pseudo execution
+        missing: Vec<String>,
+    },
+    /// The amount of the token minted by IBC exceeds the mint limit
+    MintLimitExceeded {
+        /// The token
+        token: Address,
+        /// The mint limit of the token
+        limit: Amount,
+        /// The minted amount after the tx
+        minted: Amount,
+    },
+    /// The net amount of the token transferred in the epoch exceeds the
+    /// per-epoch throughput limit
+    ThroughputLimitExceeded {
+        /// The token
+        token: Address,
+        /// The per-epoch throughput limit of the token
+        limit: Amount,
+        /// The throughput after the tx
+        throughput: Amount,
+    },
+    /// The net amount of the token transferred through the channel in the
+    /// epoch exceeds the per-epoch throughput limit of the channel
+    ChannelThroughputLimitExceeded {
+        /// The token
+        token: Address,
+        /// The port ID on this chain
+        port_id: PortId,
+        /// The channel ID on this chain
+        channel_id: ChannelId,
+        /// The per-epoch throughput limit of the token for the channel
+        limit: Amount,
+        /// The throughput of the channel after the tx
+        throughput: Amount,
+    },
+    /// The memo of the outgoing transfer exceeds the max size
+    MemoSizeExceeded {
+        /// The size of the memo in bytes
+        size: u64,
+        /// The max size of the memo in bytes
+        max_size: u64,
+    },
+    /// The amount of the outgoing transfer is below the min transfer amount
+    /// of the token
+    TransferBelowMinAmount {
+        /// The token
+        token: Address,
+        /// The amount of the transfer
+        amount: Amount,
+        /// The min transfer amount of the token
+        min_amount: Amount,
+    },
+    /// The number of the consensus states of the client exceeds the max
+    /// number
+    ConsensusStateLimitExceeded {
+        /// The client ID
+        client_id: ClientId,
+        /// The max number of the consensus states per client
+        limit: u64,
+        /// The number of the consensus states of the client after the tx
+        count: u64,
+    },
+    /// The sequence of the packet received on an ordered channel isn't the
+    /// next sequence to be received
+    PacketSequenceMismatch {
+        /// The port ID on this chain
+        port_id: PortId,
+        /// The channel ID on this chain
+        channel_id: ChannelId,
+        /// The sequence of the received packet
+        sequence: Sequence,
+        /// The next sequence to be received on the channel
+        expected: Sequence,
+    },
+}
+
+impl VpRejectReason {
+    /// A coarse label of the reason, e.g. to be used in the metrics
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::KeyMismatch { .. } => "key_mismatch",
+            Self::ValueMismatch { .. } => "value_mismatch",
+            Self::EventMismatch { .. } => "event_mismatch",
+            Self::MintLimitExceeded { .. } => "mint_limit",
+            Self::ThroughputLimitExceeded { .. }
+            | Self::ChannelThroughputLimitExceeded { .. } => "throughput_limit",
+            Self::MemoSizeExceeded { .. } => "memo_size",
+            Self::TransferBelowMinAmount { .. } => "min_transfer_amount",
+            Self::ConsensusStateLimitExceeded { .. } => "consensus_state_limit",
+            Self::PacketSequenceMismatch { .. } => "packet_sequence",
+        }
+    }
+}
+
+impl Display for VpRejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The counts and the differing keys come first so that they are kept
+        // when the error is truncated in the tx result
+        match self {
+            Self::KeyMismatch {
+                actual_count,
+                expected_count,
+                unexpected,
+                missing,
+            } => write!(
+                f,
+                "The changed keys mismatched: {actual_count} actual and \
+                 {expected_count} expected keys, Unexpected {}, Missing {}",
+                render_keys(unexpected),
+                render_keys(missing),
+            ),
+            Self::ValueMismatch {
+                key,
+                actual,
+                expected,
+            } => {
+                write!(
+                    f,
+                    "The value mismatched: Key {key}, Actual {}, Expected {}",
+                    render_value(actual),
+                    render_value(expected),
+                )?;
+                // The counters and the sequences are easily written with the
+                // wrong encoding
+                match U64Codec::of_key(key) {
+                    Some(codec) => write!(f, ", Expected encoding {codec}"),
+                    None => Ok(()),
+                }
+            }
+            Self::EventMismatch {
+                unexpected,
+                missing,
+            } => write!(
+                f,
+                "The IBC events mismatched: Unexpected {unexpected:?}, \
+                 Missing {missing:?}",
+            ),
+            Self::MintLimitExceeded {
+                token,
+                limit,
+                minted,
+            } => write!(
+                f,
+                "Transfer exceeding the mint limit is not allowed: Token \
+                 {token}, Mint limit {limit}, Minted amount {minted}",
+            ),
+            Self::ThroughputLimitExceeded {
+                token,
+                limit,
+                throughput,
+            } => write!(
+                f,
+                "Transfer exceeding the per-epoch throughput limit is not \
+                 allowed: Token {token}, Per-epoch throughput limit {limit}, \
+                 Actual throughput {throughput}",
+            ),
+            Self::ChannelThroughputLimitExceeded {
+                token,
+                port_id,
+                channel_id,
+                limit,
+                throughput,
+            } => write!(
+                f,
+                "Transfer exceeding the per-epoch throughput limit of the \
+                 channel is not allowed: Token {token}, Port ID {port_id}, \
+                 Channel ID {channel_id}, Per-epoch throughput limit {limit}, \
+                 Actual throughput {throughput}",
+            ),
+            Self::MemoSizeExceeded { size, max_size } => write!(
+                f,
+                "The memo of the transfer exceeds the max size: Size {size}, \
+                 Max size {max_size}",
+            ),
+            Self::TransferBelowMinAmount {
+                token,
+                amount,
+                min_amount,
+            } => write!(
+                f,
+                "Transfer below the min transfer amount is not allowed: Token \
+                 {token}, Min transfer amount {min_amount}, Amount {amount}",
+            ),
+            Self::ConsensusStateLimitExceeded {
+                client_id,
+                limit,
+                count,
+            } => write!(
+                f,
+                "The consensus states of the client exceed the max number: \
+                 Client ID {client_id}, Max number {limit}, Number {count}",
+            ),
+            Self::PacketSequenceMismatch {
+                port_id,
+                channel_id,
+                sequence,
+                expected,
+            } => write!(
+                f,
+                "The packet received on the ordered channel is out of order: \
+                 Port ID {port_id}, Channel ID {channel_id}, Sequence \
+                 {sequence}, Next sequence {expected}",
+            ),
+        }
+    }
+}
+
+fn render_keys(keys: &[Key]) -> String {
+    let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+    format!("[{}]", keys.join(", "))
+}
+
+fn render_value(value: &Option<Vec<u8>>) -> String {
+    match value {
+        Some(value) => HEXUPPER.encode(value),
+        None => "deleted".to_string(),
+    }
 }
 
 /// IBC functions result
@@ -59,7 +458,7 @@ where
 
 impl<'a, S, CA> NativeVp for Ibc<'a, S, CA>
 where
-    S: StateRead,
+    S: StateRead + Sync,
     CA: 'static + WasmCacheAccess,
 {
     type Error = Error;
@@ -68,29 +467,116 @@ where
         &self,
         tx_data: &Tx,
         keys_changed: &BTreeSet<Key>,
-        _verifiers: &BTreeSet<Address>,
+        verifiers: &BTreeSet<Address>,
     ) -> VpResult<bool> {
         let signed = tx_data;
         let tx_data = signed.data().ok_or(Error::NoTxData)?;
 
+        // Bound the state to be executed and compared before paying for it
+        self.check_tx_size(&tx_data, keys_changed)?;
+
+        // The fee escrow account holds exactly the escrowed relayer fees
+        self.validate_fee_escrow(keys_changed)?;
+
+        // The relayer fee switches are updated only by a governance proposal
+        if keys_changed
+            .iter()
+            .any(|key| is_fee_enabled_key(key).is_some())
+        {
+            self.validate_fee_switches(&tx_data, keys_changed)?;
+            return Ok(true);
+        }
+
+        // A relayer registers the payee of its relayer fees by itself
+        if keys_changed.iter().any(|key| is_payee_key(key).is_some()) {
+            self.validate_payees(signed, keys_changed)?;
+            return Ok(true);
+        }
+
+        // The IBC parameters and the per-token limit overrides are updated
+        // only by a governance proposal
+        if keys_changed.iter().any(is_ibc_params_key) {
+            self.validate_parameter_change(&tx_data, keys_changed)?;
+            return Ok(true);
+        }
+
+        // The time and the host height of a client update never go back
+        self.validate_client_update_meta(keys_changed)?;
+
+        // The number of the consensus states of a client is capped
+        self.check_consensus_state_limit(keys_changed)?;
+
+        // The identifier counters are only incremented one by one
+        self.validate_counters(keys_changed)?;
+
+        // A client is substituted only by an accepted governance proposal,
+        // e.g. to recover the client expired while the counterparty halted
+        if is_proposal_accepted(&self.ctx.pre(), &tx_data)
+            .map_err(Error::NativeVpError)?
+        {
+            self.validate_client_recovery(keys_changed)?;
+            return Ok(true);
+        }
+
+        // An outgoing transfer has to be authorized by its sender
+        self.validate_sender(&tx_data, verifiers)?;
+
+        // The relayer paid with the relayer fees has to sign the tx
+        self.validate_relayer(signed, &tx_data)?;
+
         // Pseudo execution and compare them
         self.validate_state(&tx_data, keys_changed)?;
 
         // Validate the state according to the given IBC message
         self.validate_with_msg(&tx_data)?;
 
+        // A shielded transfer moves the token between the MASP pool and IBC
+        self.validate_shielded_transfer(&tx_data, keys_changed, verifiers)?;
+
         // Validate the denom store if a denom key has been changed
         self.validate_denom(keys_changed)?;
 
+        // Check the limits of the transferred tokens
+        self.check_limits(keys_changed)?;
+
         Ok(true)
     }
 }
 
 impl<'a, S, CA> Ibc<'a, S, CA>
 where
-    S: StateRead,
+    S: StateRead + Sync,
     CA: 'static + WasmCacheAccess,
 {
+    /// Check that the number of the changed IBC keys and the size of the tx
+    /// data don't exceed the limits of the IBC parameters
+    fn check_tx_size(
+        &self,
+        tx_data: &[u8],
+        keys_changed: &BTreeSet<Key>,
+    ) -> VpResult<()> {
+        let params =
+            read_params(&self.ctx.pre()).map_err(Error::NativeVpError)?;
+        let num_keys =
+            keys_changed.iter().filter(|k| is_ibc_key(k)).count() as u64;
+        if num_keys > params.max_ibc_keys_per_tx {
+            return Err(Error::TooLarge {
+                what: "changed IBC keys",
+                size: num_keys,
+                limit: params.max_ibc_keys_per_tx,
+            });
+        }
+        let data_len = tx_data.len() as u64;
+        if data_len > params.max_tx_data_len {
+            return Err(Error::TooLarge {
+                what: "bytes of the tx data",
+                size: data_len,
+                limit: params.max_tx_data_len,
+            });
+        }
+        Ok(())
+    }
+
     fn validate_state(
         &self,
         tx_data: &[u8],
@@ -100,151 +586,1374 @@ where
         let ctx = Rc::new(RefCell::new(exec_ctx));
 
         let mut actions = IbcActions::new(ctx.clone());
-        let module = TransferModule::new(ctx.clone());
-        actions.add_transfer_module(module.module_id(), module);
+        let module = PfmModule::new(ctx.clone());
+        actions.add_pfm_module(module);
         // Charge gas for the expensive execution
         self.ctx
-            .charge_gas(IBC_ACTION_EXECUTE_GAS)
+            .charge_gas_scoped(
+                IBC_EXECUTE_GAS_LABEL,
+                action_gas(
+                    IBC_ACTION_EXECUTE_GAS,
+                    IBC_ACTION_EXECUTE_GAS_PER_BYTE,
+                    tx_data,
+                ),
+            )
             .map_err(Error::NativeVpError)?;
         actions.execute(tx_data)?;
 
         let changed_ibc_keys: HashSet<&Key> =
             keys_changed.iter().filter(|k| is_ibc_key(k)).collect();
-        if changed_ibc_keys.len() != ctx.borrow().get_changed_keys().len() {
-            return Err(Error::StateChange(format!(
-                "The changed keys mismatched: Actual {:?}, Expected {:?}",
-                changed_ibc_keys,
-                ctx.borrow().get_changed_keys(),
-            )));
+        // Charge gas for the comparison of the changed keys
+        self.ctx
+            .charge_gas_scoped(
+                IBC_STATE_READ_GAS_LABEL,
+                (changed_ibc_keys.len() as u64)
+                    .saturating_mul(IBC_STATE_COMPARISON_GAS_PER_KEY),
+            )
+            .map_err(Error::NativeVpError)?;
+        if changed_ibc_keys != ctx.borrow().get_changed_keys() {
+            return Err(changed_keys_mismatch(
+                &changed_ibc_keys,
+                &ctx.borrow().get_changed_keys(),
+            ));
+        }
+
+        let exec_ctx = ctx.borrow();
+        // The modifications of the pseudo execution, sorted by the keys
+        let expected: BTreeMap<&Key, Option<&StorageModification>> =
+            changed_ibc_keys
+                .into_iter()
+                .map(|key| (key, exec_ctx.get_changed_value(key)))
+                .collect();
+        // Charge gas once for reading the values of all the changed keys
+        let read_bytes = expected
+            .iter()
+            .map(|(key, modification)| match modification {
+                Some(StorageModification::Write { value }) => {
+                    key.len() + value.len()
+                }
+                _ => key.len(),
+            })
+            .fold(0_u64, |acc, len| acc.saturating_add(len as u64));
+        self.ctx
+            .charge_gas_scoped(
+                IBC_STATE_READ_GAS_LABEL,
+                read_bytes.saturating_mul(MEMORY_ACCESS_GAS_PER_BYTE),
+            )
+            .map_err(Error::NativeVpError)?;
+        match_values(self.ctx.state, expected.into_iter().collect())?;
+
+        // check the event
+        let actual = self.ctx.state.write_log().get_ibc_events();
+        let expected = &exec_ctx.event;
+        if actual != expected.as_slice() {
+            let actual_set: BTreeSet<&IbcEvent> = actual.iter().collect();
+            let expected_set: BTreeSet<&IbcEvent> = expected.iter().collect();
+            if actual_set != expected_set {
+                let event_types = |events: Vec<&&IbcEvent>| {
+                    events.into_iter().map(|e| e.event_type.clone()).collect()
+                };
+                return Err(Error::Rejected(VpRejectReason::EventMismatch {
+                    unexpected: event_types(
+                        actual_set.difference(&expected_set).collect(),
+                    ),
+                    missing: event_types(
+                        expected_set.difference(&actual_set).collect(),
+                    ),
+                }));
+            }
+            // The same events have been emitted in a different order
+            let index = actual
+                .iter()
+                .zip(expected.iter())
+                .position(|(a, e)| a != e)
+                .unwrap_or_else(|| actual.len().min(expected.len()));
+            return Err(Error::IbcEvent {
+                index,
+                actual: actual.get(index).map(|e| e.event_type.clone()),
+                expected: expected.get(index).map(|e| e.event_type.clone()),
+            });
         }
 
-        for key in changed_ibc_keys {
-            let actual = self
+        Ok(())
+    }
+
+    /// Check that the number of the consensus states of each client whose
+    /// consensus state has been changed doesn't exceed the max number of the
+    /// IBC parameters. The states are counted in the post state, so the
+    /// states pruned by the tx aren't counted.
+    fn check_consensus_state_limit(
+        &self,
+        keys_changed: &BTreeSet<Key>,
+    ) -> VpResult<()> {
+        let client_ids: BTreeSet<ClientId> = keys_changed
+            .iter()
+            .filter_map(is_consensus_state_key)
+            .map(|(client_id, _)| client_id)
+            .collect();
+        if client_ids.is_empty() {
+            return Ok(());
+        }
+        let limit = read_params(&self.ctx.pre())
+            .map_err(Error::NativeVpError)?
+            .max_consensus_states_per_client;
+        for client_id in client_ids {
+            let prefix = consensus_state_prefix(&client_id);
+            let mut iter = self
                 .ctx
-                .read_bytes_post(key)
+                .iter_prefix_post(&prefix)
                 .map_err(Error::NativeVpError)?;
-            match_value(key, actual, ctx.borrow().get_changed_value(key))?;
+            let mut count = 0_u64;
+            while self
+                .ctx
+                .iter_next(&mut iter)
+                .map_err(Error::NativeVpError)?
+                .is_some()
+            {
+                count += 1;
+            }
+            if count > limit {
+                return Err(Error::Rejected(
+                    VpRejectReason::ConsensusStateLimitExceeded {
+                        client_id,
+                        limit,
+                        count,
+                    },
+                ));
+            }
         }
+        Ok(())
+    }
 
-        // check the event
-        let actual = self.ctx.state.write_log().get_ibc_events();
-        if *actual != ctx.borrow().event {
-            return Err(Error::IbcEvent(format!(
-                "The IBC event is invalid: Actual {:?}, Expected {:?}",
-                actual,
-                ctx.borrow().event
-            )));
+    fn validate_client_update_meta(
+        &self,
+        keys_changed: &BTreeSet<Key>,
+    ) -> VpResult<()> {
+        for key in keys_changed {
+            if let Some(client_id) = is_client_update_timestamp_key(key) {
+                let (pre, post) = match self.read_pre_post_bytes(key)? {
+                    Some(values) => values,
+                    None => continue,
+                };
+                let pre = TmTime::decode_vec(&pre).map_err(|e| {
+                    Error::ClientUpdate(format!(
+                        "Decoding the prior update time failed: Client ID \
+                         {client_id}, {e}"
+                    ))
+                })?;
+                let post = TmTime::decode_vec(&post).map_err(|e| {
+                    Error::ClientUpdate(format!(
+                        "Decoding the update time failed: Client ID \
+                         {client_id}, {e}"
+                    ))
+                })?;
+                if post < pre {
+                    return Err(Error::ClientUpdate(format!(
+                        "The update time went back: Client ID {client_id}, \
+                         Prior {pre}, Posterior {post}"
+                    )));
+                }
+            } else if let Some(client_id) = is_client_update_height_key(key) {
+                let (pre, post) = match self.read_pre_post_bytes(key)? {
+                    Some(values) => values,
+                    None => continue,
+                };
+                let pre = Height::decode_vec(&pre).map_err(|e| {
+                    Error::ClientUpdate(format!(
+                        "Decoding the prior update height failed: Client ID \
+                         {client_id}, {e}"
+                    ))
+                })?;
+                let post = Height::decode_vec(&post).map_err(|e| {
+                    Error::ClientUpdate(format!(
+                        "Decoding the update height failed: Client ID \
+                         {client_id}, {e}"
+                    ))
+                })?;
+                if post < pre {
+                    return Err(Error::ClientUpdate(format!(
+                        "The update height went back: Client ID \
+                         {client_id}, Prior {pre}, Posterior {post}"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a changed client, connection or channel counter has been
+    /// incremented by one, independently of the pseudo execution
+    fn validate_counters(&self, keys_changed: &BTreeSet<Key>) -> VpResult<()> {
+        for (key, counter) in [
+            (client_counter_key(), "client"),
+            (connection_counter_key(), "connection"),
+            (channel_counter_key(), "channel"),
+        ] {
+            if !keys_changed.contains(&key) {
+                continue;
+            }
+            let pre: Option<u64> = self.read_pre_decoded(&key)?;
+            let post: Option<u64> = self.read_post_decoded(&key)?;
+            let expected = pre.and_then(|pre| pre.checked_add(1));
+            if post.is_none() || post != expected {
+                return Err(Error::StateChange(format!(
+                    "The {counter} counter wasn't incremented by one as a \
+                     {}-encoded u64: Prior {pre:?}, Posterior {post:?}",
+                    U64Codec::Borsh,
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read and decode the prior value of the key. A corrupt value is
+    /// reported with the key and the type.
+    fn read_pre_decoded<T: BorshDeserialize>(
+        &self,
+        key: &Key,
+    ) -> VpResult<Option<T>> {
+        self.ctx
+            .read_bytes_pre(key)
+            .map_err(Error::NativeVpError)?
+            .map(|bytes| decode_value(key, &bytes))
+            .transpose()
+    }
+
+    /// Read and decode the posterior value of the key. A corrupt value is
+    /// reported with the key and the type.
+    fn read_post_decoded<T: BorshDeserialize>(
+        &self,
+        key: &Key,
+    ) -> VpResult<Option<T>> {
+        self.ctx
+            .read_bytes_post(key)
+            .map_err(Error::NativeVpError)?
+            .map(|bytes| decode_value(key, &bytes))
+            .transpose()
+    }
+
+    /// Read the prior and the posterior values of the key. Returns `None` if
+    /// the key is newly written or deleted.
+    fn read_pre_post_bytes(
+        &self,
+        key: &Key,
+    ) -> VpResult<Option<(Vec<u8>, Vec<u8>)>> {
+        let pre = self.ctx.read_bytes_pre(key).map_err(Error::NativeVpError)?;
+        let post = self
+            .ctx
+            .read_bytes_post(key)
+            .map_err(Error::NativeVpError)?;
+        Ok(pre.zip(post))
+    }
+
+    fn validate_fee_switches(
+        &self,
+        tx_data: &[u8],
+        keys_changed: &BTreeSet<Key>,
+    ) -> VpResult<()> {
+        let is_accepted = is_proposal_accepted(&self.ctx.pre(), tx_data)
+            .map_err(Error::NativeVpError)?;
+        if !is_accepted {
+            return Err(Error::FeeSwitch(
+                "The relayer fee switch can be updated only by governance"
+                    .to_string(),
+            ));
+        }
+        for key in keys_changed.iter().filter(|k| is_ibc_key(k)) {
+            if is_fee_enabled_key(key).is_none() {
+                return Err(Error::FeeSwitch(format!(
+                    "Other IBC keys can't be updated with the relayer fee \
+                     switch: Key {key}",
+                )));
+            }
+            self.ctx
+                .read_post::<bool>(key)
+                .map_err(Error::NativeVpError)?;
+        }
+        Ok(())
+    }
+
+    fn validate_payees(
+        &self,
+        tx: &Tx,
+        keys_changed: &BTreeSet<Key>,
+    ) -> VpResult<()> {
+        for key in keys_changed.iter().filter(|k| is_ibc_key(k)) {
+            let Some((_, _, relayer)) = is_payee_key(key) else {
+                return Err(Error::Payee(format!(
+                    "Other IBC keys can't be updated with the payee: Key {key}",
+                )));
+            };
+            if !self.is_signed_by(tx, relayer)? {
+                return Err(Error::Payee(format!(
+                    "The relayer didn't sign the payee: Relayer {relayer}",
+                )));
+            }
+            self.ctx
+                .read_post::<Address>(key)
+                .map_err(Error::NativeVpError)?;
+        }
+        Ok(())
+    }
+
+    fn validate_parameter_change(
+        &self,
+        tx_data: &[u8],
+        keys_changed: &BTreeSet<Key>,
+    ) -> VpResult<()> {
+        let is_accepted = is_proposal_accepted(&self.ctx.pre(), tx_data)
+            .map_err(Error::NativeVpError)?;
+        if !is_accepted {
+            return Err(Error::UnauthorizedParameterChange(
+                keys_changed
+                    .iter()
+                    .filter(|k| is_ibc_params_key(k))
+                    .cloned()
+                    .collect(),
+            ));
+        }
+        for key in keys_changed.iter().filter(|k| is_ibc_key(k)) {
+            if *key == params_key() {
+                self.ctx
+                    .read_post::<IbcParameters>(key)
+                    .map_err(Error::NativeVpError)?;
+            } else if is_limit_override_key(key).is_some() {
+                self.ctx
+                    .read_post::<Amount>(key)
+                    .map_err(Error::NativeVpError)?;
+            } else if let Some(hash) = is_blocked_trace_key(key) {
+                // Removing the entry unblocks the trace
+                let trace = self
+                    .ctx
+                    .read_post::<String>(key)
+                    .map_err(Error::NativeVpError)?;
+                match trace {
+                    Some(trace) if calc_hash(&trace) != *hash => {
+                        return Err(Error::ParameterChange(format!(
+                            "The blocked denom trace doesn't match the key: \
+                             Key {key}, Trace {trace}",
+                        )));
+                    }
+                    _ => {}
+                }
+            } else {
+                return Err(Error::ParameterChange(format!(
+                    "Other IBC keys can't be updated with the IBC parameters: \
+                     Key {key}",
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that only the keys of a single client have been changed by the
+    /// substitution and the new states can be decoded
+    fn validate_client_recovery(
+        &self,
+        keys_changed: &BTreeSet<Key>,
+    ) -> VpResult<()> {
+        let mut substituted = None;
+        for key in keys_changed.iter().filter(|k| is_ibc_key(k)) {
+            let id = client_id(key).map_err(|_| {
+                Error::ClientRecovery(format!(
+                    "Other IBC keys can't be updated with the client \
+                     substitution: Key {key}"
+                ))
+            })?;
+            match &substituted {
+                Some(substituted) if *substituted != id => {
+                    return Err(Error::ClientRecovery(format!(
+                        "Only one client can be substituted: Client IDs \
+                         {substituted} and {id}"
+                    )));
+                }
+                Some(_) => {}
+                None => substituted = Some(id),
+            }
+            if consensus_height(key).is_ok() {
+                if let Some(bytes) = self
+                    .ctx
+                    .read_bytes_post(key)
+                    .map_err(Error::NativeVpError)?
+                {
+                    AnyConsensusState::try_from(bytes).map_err(|e| {
+                        Error::ClientRecovery(format!(
+                            "Decoding the consensus state failed: Key {key}, \
+                             {e}"
+                        ))
+                    })?;
+                }
+            }
         }
 
+        let client_id = substituted.ok_or_else(|| {
+            Error::ClientRecovery("No client has been substituted".to_string())
+        })?;
+        let key = client_state_key(&client_id);
+        if !keys_changed.contains(&key) {
+            return Err(Error::ClientRecovery(format!(
+                "The client state hasn't been substituted: Client ID \
+                 {client_id}"
+            )));
+        }
+        let bytes = self
+            .ctx
+            .read_bytes_post(&key)
+            .map_err(Error::NativeVpError)?
+            .ok_or_else(|| {
+                Error::ClientRecovery(format!(
+                    "The client state can't be deleted: Client ID {client_id}"
+                ))
+            })?;
+        Any::decode(&bytes[..])
+            .map_err(|e| e.to_string())
+            .and_then(|any| {
+                AnyClientState::try_from(any).map_err(|e| e.to_string())
+            })
+            .map_err(|e| {
+                Error::ClientRecovery(format!(
+                    "Decoding the client state failed: Client ID {client_id}, \
+                     {e}"
+                ))
+            })?;
         Ok(())
     }
 
     fn validate_with_msg(&self, tx_data: &[u8]) -> VpResult<()> {
+        self.validate_client_expiry(tx_data)?;
+        self.validate_memo_size(tx_data)?;
+        self.validate_min_transfer_amount(tx_data)?;
+        self.validate_recv_sequence(tx_data)?;
+
         let validation_ctx = VpValidationContext::new(self.ctx.pre());
         let ctx = Rc::new(RefCell::new(validation_ctx));
 
         let mut actions = IbcActions::new(ctx.clone());
         actions.set_validation_params(self.validation_params()?);
 
-        let module = TransferModule::new(ctx);
-        actions.add_transfer_module(module.module_id(), module);
+        let module = PfmModule::new(ctx);
+        actions.add_pfm_module(module);
         // Charge gas for the expensive validation
         self.ctx
-            .charge_gas(IBC_ACTION_VALIDATE_GAS)
+            .charge_gas_scoped(
+                IBC_VALIDATE_GAS_LABEL,
+                action_gas(
+                    IBC_ACTION_VALIDATE_GAS,
+                    IBC_ACTION_VALIDATE_GAS_PER_BYTE,
+                    tx_data,
+                ),
+            )
             .map_err(Error::NativeVpError)?;
         actions.validate(tx_data).map_err(Error::IbcAction)
     }
 
-    /// Retrieve the validation params
-    pub fn validation_params(&self) -> VpResult<ValidationParams> {
-        use std::str::FromStr;
-        let chain_id = self.ctx.get_chain_id().map_err(Error::NativeVpError)?;
-        let proof_specs =
-            namada_state::ics23_specs::ibc_proof_specs::<<S as StateRead>::H>();
-        let pos_params =
-            read_pos_params(&self.ctx.post()).map_err(Error::NativeVpError)?;
-        let pipeline_len = pos_params.pipeline_len;
-        let epoch_duration = read_epoch_duration_parameter(&self.ctx.post())
-            .map_err(Error::NativeVpError)?;
-        let unbonding_period_secs =
-            pipeline_len * epoch_duration.min_duration.0;
-        Ok(ValidationParams {
-            chain_id: IbcChainId::from_str(&chain_id)
-                .map_err(ActionError::ChainId)?,
-            proof_specs: proof_specs.into(),
-            unbonding_period: Duration::from_secs(unbonding_period_secs),
-            upgrade_path: Vec::new(),
-        })
+    /// Check that the sender of an outgoing transfer is a verifier of the tx,
+    /// i.e. its VP has authorized the transfer. The refund on a timeout or a
+    /// failed acknowledgement is exempt since it's submitted by a relayer.
+    fn validate_sender(
+        &self,
+        tx_data: &[u8],
+        verifiers: &BTreeSet<Address>,
+    ) -> VpResult<()> {
+        let sender = match decode_message(tx_data)? {
+            IbcMessage::Transfer(msg) => msg.packet_data.sender,
+            IbcMessage::ShieldedTransfer(msg) => msg.message.packet_data.sender,
+            IbcMessage::TransferWithFee(msg) => msg.message.packet_data.sender,
+            IbcMessage::Envelope(_) => return Ok(()),
+        };
+        let sender: &str = sender.as_ref();
+        let sender = Address::decode(sender).map_err(|e| {
+            Error::Unauthorized(format!(
+                "Invalid sender address: Sender {sender}, Error {e}"
+            ))
+        })?;
+        if !verifiers.contains(&sender) {
+            return Err(Error::Unauthorized(format!(
+                "The sender {sender} isn't a verifier of the tx"
+            )));
+        }
+        Ok(())
     }
 
-    fn validate_denom(&self, keys_changed: &BTreeSet<Key>) -> VpResult<()> {
-        for key in keys_changed {
-            if let Some((_, hash)) = is_ibc_denom_key(key) {
-                match self.ctx.read_post::<String>(key).map_err(|e| {
-                    ActionError::Denom(format!(
-                        "Getting the denom failed: Key {}, Error {}",
-                        key, e
-                    ))
-                })? {
-                    Some(denom) => {
-                        if calc_hash(&denom) != hash {
-                            return Err(ActionError::Denom(format!(
-                                "The denom is invalid: Key {}, Denom {}",
-                                key, denom
-                            ))
-                            .into());
-                        }
-                    }
-                    None => {
-                        return Err(ActionError::Denom(format!(
-                            "The corresponding denom wasn't stored: Key {}",
-                            key
-                        ))
-                        .into());
-                    }
-                }
+    /// Check that the relayer of an acknowledgement or a timeout of a packet
+    /// with the relayer fees, i.e. the signer of the message to whom the fees
+    /// are paid, has signed the tx. Being a verifier of the tx isn't enough
+    /// since the VP of the relayer doesn't check a credit.
+    fn validate_relayer(&self, tx: &Tx, tx_data: &[u8]) -> VpResult<()> {
+        let (packet, signer) = match decode_message(tx_data)? {
+            IbcMessage::Envelope(MsgEnvelope::Packet(PacketMsg::Ack(msg))) => {
+                (msg.packet, msg.signer)
             }
+            IbcMessage::Envelope(MsgEnvelope::Packet(PacketMsg::Timeout(
+                msg,
+            ))) => (msg.packet, msg.signer),
+            IbcMessage::Envelope(MsgEnvelope::Packet(
+                PacketMsg::TimeoutOnClose(msg),
+            )) => (msg.packet, msg.signer),
+            _ => return Ok(()),
+        };
+        let fee_key = packet_fee_key(
+            &packet.port_id_on_a,
+            &packet.chan_id_on_a,
+            packet.seq_on_a,
+        );
+        if !self
+            .ctx
+            .has_key_pre(&fee_key)
+            .map_err(Error::NativeVpError)?
+        {
+            return Ok(());
+        }
+        let signer: &str = signer.as_ref();
+        let relayer = Address::decode(signer).map_err(|e| {
+            Error::RelayerFee(format!(
+                "Invalid relayer address: Signer {signer}, Error {e}"
+            ))
+        })?;
+        if !self.is_signed_by(tx, &relayer)? {
+            return Err(Error::RelayerFee(format!(
+                "The relayer {relayer} didn't sign the tx"
+            )));
         }
         Ok(())
     }
-}
 
-fn match_value(
-    key: &Key,
-    actual: Option<Vec<u8>>,
-    expected: Option<&StorageModification>,
-) -> VpResult<()> {
-    match (actual, expected) {
-        (Some(v), Some(StorageModification::Write { value })) => {
-            if v == *value {
-                Ok(())
-            } else {
-                Err(Error::StateChange(format!(
-                    "The value mismatched: Key {} actual {:?}, expected {:?}",
-                    key, v, value
+    /// Check that the balances of the relayer fee escrow account changed by
+    /// the tx are the escrowed relayer fees recorded by IBC
+    fn validate_fee_escrow(
+        &self,
+        keys_changed: &BTreeSet<Key>,
+    ) -> VpResult<()> {
+        let tokens: BTreeSet<&Address> = keys_changed
+            .iter()
+            .filter_map(|key| match is_any_token_balance_key(key) {
+                Some([token, owner]) if *owner == IBC_FEE_ESCROW => Some(token),
+                _ => is_fee_escrow_amount_key(key),
+            })
+            .collect();
+        for token in tokens {
+            let balance: Amount = self
+                .ctx
+                .read_post(&balance_key(token, &IBC_FEE_ESCROW))
+                .map_err(Error::NativeVpError)?
+                .unwrap_or_default();
+            let escrowed: Amount = self
+                .ctx
+                .read_post(&fee_escrow_amount_key(token))
+                .map_err(Error::NativeVpError)?
+                .unwrap_or_default();
+            if balance != escrowed {
+                return Err(Error::RelayerFee(format!(
+                    "The fee escrow balance of {token} doesn't match the \
+                     escrowed relayer fees: Balance {}, Escrowed {}",
+                    balance.to_string_native(),
+                    escrowed.to_string_native()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check if the tx has been signed by the keys of the given account
+    fn is_signed_by(&self, tx: &Tx, signer: &Address) -> VpResult<bool> {
+        let pre = self.ctx.pre();
+        let public_keys_index_map =
+            namada_account::public_keys_index_map(&pre, signer)
+                .map_err(Error::NativeVpError)?;
+        let threshold = namada_account::threshold(&pre, signer)
+            .map_err(Error::NativeVpError)?
+            .unwrap_or(1);
+        let max_signatures =
+            crate::parameters::max_signatures_per_transaction(&pre)
+                .map_err(Error::NativeVpError)?;
+        let deprecated_layouts =
+            crate::parameters::deprecated_signature_layouts(&pre)
+                .map_err(Error::NativeVpError)?;
+        match tx.verify_signatures_with_layouts(
+            &[tx.raw_header_hash()],
+            public_keys_index_map,
+            &Some(signer.clone()),
+            threshold,
+            max_signatures,
+            &deprecated_layouts,
+            || self.ctx.gas_meter.borrow_mut().consume(VERIFY_TX_SIG_GAS),
+        ) {
+            Ok(_) => Ok(true),
+            Err(VerifySigError::Gas(_)) => {
+                Err(Error::NativeVpError(native_vp::Error::SimpleMessage(
+                    "Gas limit exceeded in native vp",
                 )))
             }
+            Err(_) => Ok(false),
         }
-        (Some(_), _) => Err(Error::StateChange(format!(
-            "The value was invalid: Key {}",
-            key
-        ))),
-        (None, Some(StorageModification::Delete)) => Ok(()),
-        (None, _) => Err(Error::StateChange(format!(
-            "The key was deleted unexpectedly: Key {}",
-            key
-        ))),
     }
-}
 
-impl From<ActionError> for Error {
-    fn from(err: ActionError) -> Self {
-        Self::IbcAction(err)
+    /// Check that a shielded transfer moves the token of the packet between the
+    /// MASP pool and IBC. The MASP VP has to verify the MASP tx, the
+    /// transparent value of the MASP tx has to be the packet amount of the
+    /// token and no transparent balance of the token other than the ones of
+    /// MASP and IBC can change.
+    fn validate_shielded_transfer(
+        &self,
+        tx_data: &[u8],
+        keys_changed: &BTreeSet<Key>,
+        verifiers: &BTreeSet<Address>,
+    ) -> VpResult<()> {
+        let (shielded_transfer, token, coin, is_receiving) =
+            match decode_message(tx_data)? {
+                IbcMessage::ShieldedTransfer(msg) => {
+                    let coin = msg.message.packet_data.token;
+                    let token = sent_token(&coin.denom);
+                    (msg.shielded_transfer, token, coin, false)
+                }
+                IbcMessage::Envelope(MsgEnvelope::Packet(PacketMsg::Recv(
+                    msg,
+                ))) => {
+                    // The shielded transfer is taken from the memo only when
+                    // the packet has been received to a payment address
+                    let events = self.ctx.state.write_log().get_ibc_events();
+                    // Charge gas for reading the events from the write log
+                    let read_bytes = events
+                        .iter()
+                        .map(ibc_event_len)
+                        .fold(0_u64, |acc, len| acc.saturating_add(len));
+                    self.ctx
+                        .charge_gas_scoped(
+                            IBC_STATE_READ_GAS_LABEL,
+                            read_bytes
+                                .saturating_mul(MEMORY_ACCESS_GAS_PER_BYTE),
+                        )
+                        .map_err(Error::NativeVpError)?;
+                    let shielded_transfer = match events
+                        .iter()
+                        .find(|event| event.event_type == EVENT_TYPE_PACKET)
+                    {
+                        Some(event) => {
+                            get_shielded_transfer(event).map_err(|e| {
+                                Error::ShieldedTransfer(e.to_string())
+                            })?
+                        }
+                        None => None,
+                    };
+                    let Some(shielded_transfer) = shielded_transfer else {
+                        return Ok(());
+                    };
+                    let packet = msg.packet;
+                    let data =
+                        serde_json::from_slice::<PacketData>(&packet.data)
+                            .map_err(|e| {
+                                Error::ShieldedTransfer(format!(
+                                    "Decoding the packet data failed: {e}"
+                                ))
+                            })?;
+                    let token = received_ibc_token(
+                        &data.token.denom,
+                        &packet.port_id_on_a,
+                        &packet.chan_id_on_a,
+                        &packet.port_id_on_b,
+                        &packet.chan_id_on_b,
+                    )?;
+                    (shielded_transfer, token, data.token, true)
+                }
+                _ => return Ok(()),
+            };
+
+        let transfer = &shielded_transfer.transfer;
+        if transfer.token != token
+            || IbcAmount::from(transfer.amount) != coin.amount
+        {
+            return Err(Error::ShieldedTransfer(format!(
+                "The MASP transfer doesn't match the packet: Token {}, Amount \
+                 {}, Packet coin {coin}",
+                transfer.token, transfer.amount
+            )));
+        }
+        if !verifiers.contains(&MASP) {
+            return Err(Error::ShieldedTransfer(
+                "The MASP VP has to verify the MASP transaction".to_string(),
+            ));
+        }
+
+        // The token is escrowed, unescrowed, minted or burned only between
+        // the MASP pool and IBC
+        let ibc = Address::Internal(InternalAddress::Ibc);
+        for key in keys_changed {
+            if let Some([key_token, owner]) = is_any_token_balance_key(key) {
+                if *key_token == token && *owner != MASP && *owner != ibc {
+                    return Err(Error::ShieldedTransfer(format!(
+                        "The transparent balance of {owner} can't be changed \
+                         by the shielded transfer: Key {key}"
+                    )));
+                }
+            }
+        }
+
+        let asset_digits = self.masp_asset_digits(&token)?;
+        check_masp_transparent_value(
+            &shielded_transfer.masp_tx,
+            &asset_digits,
+            transfer.amount.amount(),
+            is_receiving,
+        )
     }
-}
 
-/// A dummy header used for testing
-#[cfg(any(test, feature = "testing"))]
+    /// Get the MASP asset types of the token with their digit positions, i.e.
+    /// the asset types without an epoch and the ones of the conversion state
+    fn masp_asset_digits(
+        &self,
+        token: &Address,
+    ) -> VpResult<HashMap<AssetType, MaspDigitPos>> {
+        let denom = read_denom(&self.ctx.post(), token)
+            .map_err(Error::NativeVpError)?
+            .ok_or_else(|| {
+                Error::ShieldedTransfer(format!(
+                    "No denomination found for the token {token}"
+                ))
+            })?;
+        let mut asset_digits = HashMap::new();
+        for digit in MaspDigitPos::iter() {
+            let asset_type =
+                encode_asset_type(token.clone(), denom, digit, None)
+                    .map_err(Error::Decoding)?;
+            asset_digits.insert(asset_type, digit);
+        }
+        let conversion_state = self.ctx.state.in_mem().get_conversion_state();
+        for (asset_type, ((asset_token, asset_denom, digit), _, _, _)) in
+            &conversion_state.assets
+        {
+            if asset_token == token && *asset_denom == denom {
+                asset_digits.insert(*asset_type, *digit);
+            }
+        }
+        Ok(asset_digits)
+    }
+
+    /// Check that the client of the connection or the channel of the message
+    /// hasn't expired, i.e. its trusting period hasn't elapsed since its last
+    /// update as of the current block time. The client messages are exempt so
+    /// that an expired client can be updated. A client that can't be resolved
+    /// is left to the validation of the message.
+    fn validate_client_expiry(&self, tx_data: &[u8]) -> VpResult<()> {
+        let ctx = VpValidationContext::new(self.ctx.pre());
+        let Some(client_id) = referenced_client(&ctx, decode_message(tx_data)?)
+        else {
+            return Ok(());
+        };
+        let (Ok(client_state), Ok(last_update), Ok(host_timestamp)) = (
+            ctx.client_state(&client_id),
+            ctx.client_update_time(&client_id),
+            ctx.host_timestamp(),
+        ) else {
+            return Ok(());
+        };
+        let trusting_period = client_state.trusting_period();
+        let is_expired = host_timestamp
+            .duration_since(&last_update)
+            .map_or(false, |elapsed| elapsed >= trusting_period);
+        if is_expired {
+            return Err(Error::ClientExpired {
+                client_id,
+                last_update,
+                trusting_period,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check that the memo of an outgoing transfer doesn't exceed the max size.
+    /// The memo of a received packet is checked by the transfer module.
+    fn validate_memo_size(&self, tx_data: &[u8]) -> VpResult<()> {
+        let memo = match decode_message(tx_data)? {
+            IbcMessage::Transfer(msg) => msg.packet_data.memo,
+            IbcMessage::ShieldedTransfer(msg) => msg.message.packet_data.memo,
+            IbcMessage::TransferWithFee(msg) => msg.message.packet_data.memo,
+            IbcMessage::Envelope(_) => return Ok(()),
+        };
+        let max_size = read_params(&self.ctx.pre())
+            .map_err(Error::NativeVpError)?
+            .max_memo_size;
+        let size = memo.as_ref().len() as u64;
+        if size > max_size {
+            return Err(Error::Rejected(VpRejectReason::MemoSizeExceeded {
+                size,
+                max_size,
+            }));
+        }
+        Ok(())
+    }
+
+    /// Check that the amount of an outgoing transfer isn't below the min
+    /// transfer amount of the token. The amount of a received packet is
+    /// checked by the transfer module.
+    fn validate_min_transfer_amount(&self, tx_data: &[u8]) -> VpResult<()> {
+        let coin = match decode_message(tx_data)? {
+            IbcMessage::Transfer(msg) => msg.packet_data.token,
+            IbcMessage::ShieldedTransfer(msg) => msg.message.packet_data.token,
+            IbcMessage::TransferWithFee(msg) => msg.message.packet_data.token,
+            IbcMessage::Envelope(_) => return Ok(()),
+        };
+        // The amount is converted with the denomination of the token as by
+        // the transfer module, e.g. the native token without a denomination
+        // in the storage has the max native decimal places
+        let (token, amount) = token_amount(&self.ctx.pre(), &coin)
+            .map_err(|e| Error::IbcAction(ActionError::TokenTransfer(e)))?;
+        let amount = amount.amount();
+        let min_amount = read_min_transfer_amount(&self.ctx.pre(), &token)
+            .map_err(Error::NativeVpError)?;
+        if amount < min_amount {
+            return Err(Error::Rejected(
+                VpRejectReason::TransferBelowMinAmount {
+                    token,
+                    amount,
+                    min_amount,
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that a packet received on an ordered channel has the next
+    /// sequence to be received. The IBC handler would accept an already
+    /// received packet as a no-op, which is rejected here.
+    fn validate_recv_sequence(&self, tx_data: &[u8]) -> VpResult<()> {
+        let packet = match decode_message(tx_data)? {
+            IbcMessage::Envelope(MsgEnvelope::Packet(PacketMsg::Recv(msg))) => {
+                msg.packet
+            }
+            _ => return Ok(()),
+        };
+        let ctx = VpValidationContext::new(self.ctx.pre());
+        let channel = ctx
+            .channel_end(&packet.port_id_on_b, &packet.chan_id_on_b)
+            .map_err(|e| Error::IbcAction(ActionError::Context(Box::new(e))))?;
+        if channel.ordering != Order::Ordered {
+            return Ok(());
+        }
+        let expected = ctx
+            .get_next_sequence_recv(&packet.port_id_on_b, &packet.chan_id_on_b)
+            .map_err(|e| Error::IbcAction(ActionError::Context(Box::new(e))))?;
+        if packet.seq_on_a != expected {
+            return Err(Error::Rejected(
+                VpRejectReason::PacketSequenceMismatch {
+                    port_id: packet.port_id_on_b,
+                    channel_id: packet.chan_id_on_b,
+                    sequence: packet.seq_on_a,
+                    expected,
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    /// Retrieve the validation params. Unless a fixed unbonding period is set
+    /// in the IBC params, it's computed from the PoS params of the current
+    /// epoch.
+    pub fn validation_params(&self) -> VpResult<ValidationParams> {
+        use std::str::FromStr;
+        let chain_id = self.ctx.get_chain_id().map_err(Error::NativeVpError)?;
+        let ibc_params =
+            read_params(&self.ctx.pre()).map_err(Error::NativeVpError)?;
+        let unbonding_period_secs = match ibc_params.unbonding_period_secs {
+            Some(secs) => secs,
+            None => {
+                let pos_params = read_pos_params(&self.ctx.post())
+                    .map_err(Error::NativeVpError)?;
+                let epoch_duration =
+                    read_epoch_duration_parameter(&self.ctx.post())
+                        .map_err(Error::NativeVpError)?;
+                unbonding_epochs(&pos_params)?
+                    .saturating_mul(epoch_duration.min_duration.0)
+            }
+        };
+        Ok(ValidationParams {
+            chain_id: IbcChainId::from_str(&chain_id)
+                .map_err(ActionError::ChainId)?,
+            proof_specs: ibc_proof_specs::<<S as StateRead>::H>(),
+            unbonding_period: Duration::from_secs(unbonding_period_secs),
+            upgrade_path: ibc_params.upgrade_path,
+        })
+    }
+
+    fn validate_denom(&self, keys_changed: &BTreeSet<Key>) -> VpResult<()> {
+        for key in keys_changed {
+            if let Some((_, hash)) = is_ibc_denom_key(key) {
+                match self.read_post_decoded::<String>(key)? {
+                    Some(denom) => {
+                        if calc_hash(&denom) != hash {
+                            return Err(ActionError::Denom(format!(
+                                "The denom is invalid: Key {}, Denom {}",
+                                key, denom
+                            ))
+                            .into());
+                        }
+                    }
+                    None => {
+                        return Err(ActionError::Denom(format!(
+                            "The corresponding denom wasn't stored: Key {}",
+                            key
+                        ))
+                        .into());
+                    }
+                }
+            }
+            if let Some(token) = is_ibc_denom_by_token_key(key) {
+                self.validate_denom_by_token(key, token)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the IBC denom looked up by the token derives the token and
+    /// that its trace is stored, which could have been stored by a previous
+    /// tx
+    fn validate_denom_by_token(
+        &self,
+        key: &Key,
+        token: &Address,
+    ) -> VpResult<()> {
+        let denom =
+            self.read_post_decoded::<String>(key)?.ok_or_else(|| {
+                ActionError::Denom(format!(
+                    "The denom of the IBC token wasn't stored: Key {key}"
+                ))
+            })?;
+        if ibc_token(&denom) != *token {
+            return Err(ActionError::Denom(format!(
+                "The denom doesn't derive the IBC token: Key {key}, Denom \
+                 {denom}"
+            ))
+            .into());
+        }
+        let Some((_, base_token)) = is_ibc_denom(&denom) else {
+            return Err(ActionError::Denom(format!(
+                "The denom of the IBC token has no trace: Key {key}, Denom \
+                 {denom}"
+            ))
+            .into());
+        };
+        let trace_key = ibc_denom_key(base_token, calc_hash(&denom));
+        let trace = self.read_post_decoded::<String>(&trace_key)?;
+        if trace.as_ref() != Some(&denom) {
+            return Err(ActionError::Denom(format!(
+                "The trace of the denom isn't stored: Key {key}, Denom \
+                 {denom}"
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    fn check_limits(&self, keys_changed: &BTreeSet<Key>) -> VpResult<()> {
+        let tokens: BTreeSet<&Address> = keys_changed
+            .iter()
+            .filter_map(|k| is_deposit_key(k).or_else(|| is_withdraw_key(k)))
+            .collect();
+        for token in tokens {
+            let (mint_limit, throughput_limit) =
+                get_limits(&self.ctx.pre(), token)
+                    .map_err(Error::NativeVpError)?;
+
+            // Check the supply
+            let minted: Amount = self
+                .ctx
+                .read_post(&mint_amount_key(token))
+                .map_err(Error::NativeVpError)?
+                .unwrap_or_default();
+            if mint_limit < minted {
+                return Err(Error::Rejected(
+                    VpRejectReason::MintLimitExceeded {
+                        token: token.clone(),
+                        limit: mint_limit,
+                        minted,
+                    },
+                ));
+            }
+
+            // Check the throughput in the epoch
+            let throughput = self.calculate_throughput(
+                &deposit_key(token),
+                &withdraw_key(token),
+            )?;
+            if throughput_limit < throughput {
+                return Err(Error::Rejected(
+                    VpRejectReason::ThroughputLimitExceeded {
+                        token: token.clone(),
+                        limit: throughput_limit,
+                        throughput,
+                    },
+                ));
+            }
+        }
+
+        // Check the throughput of the channels having their own limit
+        let channels: BTreeSet<(&Address, PortId, ChannelId)> = keys_changed
+            .iter()
+            .filter_map(|k| {
+                is_deposit_key_for_channel(k)
+                    .or_else(|| is_withdraw_key_for_channel(k))
+            })
+            .collect();
+        for (token, port_id, channel_id) in channels {
+            let Some(limit) = get_channel_throughput_limit(
+                &self.ctx.pre(),
+                token,
+                &port_id,
+                &channel_id,
+            )
+            .map_err(Error::NativeVpError)?
+            else {
+                continue;
+            };
+            let throughput = self.calculate_throughput(
+                &deposit_key_for_channel(token, &port_id, &channel_id),
+                &withdraw_key_for_channel(token, &port_id, &channel_id),
+            )?;
+            if limit < throughput {
+                return Err(Error::Rejected(
+                    VpRejectReason::ChannelThroughputLimitExceeded {
+                        token: token.clone(),
+                        port_id,
+                        channel_id,
+                        limit,
+                        throughput,
+                    },
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The net amount transferred in the epoch
+    fn calculate_throughput(
+        &self,
+        deposit_key: &Key,
+        withdraw_key: &Key,
+    ) -> VpResult<Amount> {
+        let deposit: Amount = self
+            .ctx
+            .read_post(deposit_key)
+            .map_err(Error::NativeVpError)?
+            .unwrap_or_default();
+        let withdraw: Amount = self
+            .ctx
+            .read_post(withdraw_key)
+            .map_err(Error::NativeVpError)?
+            .unwrap_or_default();
+        let throughput = if deposit < withdraw {
+            withdraw.checked_sub(deposit)
+        } else {
+            deposit.checked_sub(withdraw)
+        }
+        .expect("The difference shouldn't underflow");
+        Ok(throughput)
+    }
+}
+
+/// The client of the connection or the channel which the message is handled
+/// on, if any. The client messages don't reference a connection.
+fn referenced_client<C>(ctx: &C, message: IbcMessage) -> Option<ClientId>
+where
+    C: IbcCommonContext,
+{
+    let (port_id, channel_id) = match message {
+        IbcMessage::Transfer(msg) => (msg.port_id_on_a, msg.chan_id_on_a),
+        IbcMessage::ShieldedTransfer(msg) => {
+            (msg.message.port_id_on_a, msg.message.chan_id_on_a)
+        }
+        IbcMessage::TransferWithFee(msg) => {
+            (msg.message.port_id_on_a, msg.message.chan_id_on_a)
+        }
+        IbcMessage::Envelope(MsgEnvelope::Client(_)) => return None,
+        IbcMessage::Envelope(MsgEnvelope::Connection(msg)) => {
+            let conn_id = match msg {
+                ConnectionMsg::OpenInit(msg) => {
+                    return Some(msg.client_id_on_a);
+                }
+                ConnectionMsg::OpenTry(msg) => {
+                    return Some(msg.client_id_on_b);
+                }
+                ConnectionMsg::OpenAck(msg) => msg.conn_id_on_a,
+                ConnectionMsg::OpenConfirm(msg) => msg.conn_id_on_b,
+            };
+            return connection_client(ctx, &conn_id);
+        }
+        IbcMessage::Envelope(MsgEnvelope::Channel(msg)) => match msg {
+            ChannelMsg::OpenInit(msg) => {
+                return connection_client(
+                    ctx,
+                    msg.connection_hops_on_a.first()?,
+                );
+            }
+            ChannelMsg::OpenTry(msg) => {
+                return connection_client(
+                    ctx,
+                    msg.connection_hops_on_b.first()?,
+                );
+            }
+            ChannelMsg::OpenAck(msg) => (msg.port_id_on_a, msg.chan_id_on_a),
+            ChannelMsg::OpenConfirm(msg) => {
+                (msg.port_id_on_b, msg.chan_id_on_b)
+            }
+            ChannelMsg::CloseInit(msg) => (msg.port_id_on_a, msg.chan_id_on_a),
+            ChannelMsg::CloseConfirm(msg) => {
+                (msg.port_id_on_b, msg.chan_id_on_b)
+            }
+        },
+        IbcMessage::Envelope(MsgEnvelope::Packet(msg)) => match msg {
+            PacketMsg::Recv(msg) => {
+                (msg.packet.port_id_on_b, msg.packet.chan_id_on_b)
+            }
+            PacketMsg::Ack(msg) => {
+                (msg.packet.port_id_on_a, msg.packet.chan_id_on_a)
+            }
+            PacketMsg::Timeout(msg) => {
+                (msg.packet.port_id_on_a, msg.packet.chan_id_on_a)
+            }
+            PacketMsg::TimeoutOnClose(msg) => {
+                (msg.packet.port_id_on_a, msg.packet.chan_id_on_a)
+            }
+        },
+    };
+    let channel = ctx.channel_end(&port_id, &channel_id).ok()?;
+    connection_client(ctx, channel.connection_hops().first()?)
+}
+
+/// The client of the connection, if the connection exists
+fn connection_client<C>(ctx: &C, conn_id: &ConnectionId) -> Option<ClientId>
+where
+    C: IbcCommonContext,
+{
+    ctx.connection_end(conn_id)
+        .ok()
+        .map(|conn| conn.client_id().clone())
+}
+
+/// The gas of an IBC action: the base cost and the cost per byte of the tx
+/// data, so that a large message (e.g. a client update with a big header)
+/// costs more than a small one
+fn action_gas(base_gas: u64, gas_per_byte: u64, tx_data: &[u8]) -> u64 {
+    (tx_data.len() as u64)
+        .saturating_mul(gas_per_byte)
+        .saturating_add(base_gas)
+}
+
+/// Check the values of the changed IBC keys against the ones written by the
+/// pseudo execution. The values are read without charging gas and compared in
+/// parallel. The mismatch of the smallest key is reported, regardless of the
+/// order in which the comparisons complete.
+fn match_values<S>(
+    state: &S,
+    expected: Vec<(&Key, Option<&StorageModification>)>,
+) -> VpResult<()>
+where
+    S: StateRead + Sync,
+{
+    let check = |(key, expected): (&Key, Option<&StorageModification>)|
+     -> StorageResult<Option<VpRejectReason>> {
+        let actual = read_post_value(state, key)?;
+        Ok(value_mismatch(key, actual, expected))
+    };
+    #[cfg(feature = "wasm-runtime")]
+    let results: Vec<_> = {
+        use rayon::iter::{IntoParallelIterator, ParallelIterator};
+        expected.into_par_iter().map(check).collect()
+    };
+    #[cfg(not(feature = "wasm-runtime"))]
+    let results: Vec<_> = expected.into_iter().map(check).collect();
+    // The results are in the order of the keys
+    for result in results {
+        if let Some(reason) = result.map_err(Error::NativeVpError)? {
+            return Err(Error::Rejected(reason));
+        }
+    }
+    Ok(())
+}
+
+/// Get the number of bytes of an IBC event
+fn ibc_event_len(event: &IbcEvent) -> u64 {
+    event
+        .attributes
+        .iter()
+        .map(|(key, value)| key.len() + value.len())
+        .fold(event.event_type.len() as u64, |acc, len| {
+            acc.saturating_add(len as u64)
+        })
+}
+
+/// Get the token of the denom of a packet sent from this chain
+fn sent_token(denom: &PrefixedDenom) -> Address {
+    match Address::decode(denom.base_denom.as_str()) {
+        Ok(token) if denom.trace_path.is_empty() => token,
+        _ => ibc_token(denom.to_string()),
+    }
+}
+
+/// Check that the transparent value of the MASP tx is the transferred amount.
+/// The value of a shielding tx is the sum of its transparent inputs and the
+/// one of an unshielding tx is the sum of its transparent outputs, while the
+/// other side has to be empty. All the values have to be of the given asset
+/// types.
+fn check_masp_transparent_value(
+    masp_tx: &Transaction,
+    asset_digits: &HashMap<AssetType, MaspDigitPos>,
+    amount: Amount,
+    is_shielding: bool,
+) -> VpResult<()> {
+    let (values, num_unexpected) = match masp_tx.transparent_bundle() {
+        Some(bundle) if is_shielding => (
+            bundle
+                .vin
+                .iter()
+                .map(|vin| (vin.asset_type, vin.value))
+                .collect::<Vec<_>>(),
+            bundle.vout.len(),
+        ),
+        Some(bundle) => (
+            bundle
+                .vout
+                .iter()
+                .map(|vout| (vout.asset_type, vout.value))
+                .collect(),
+            bundle.vin.len(),
+        ),
+        None => (vec![], 0),
+    };
+    if num_unexpected != 0 {
+        return Err(Error::ShieldedTransfer(format!(
+            "The MASP transaction has {num_unexpected} unexpected transparent \
+             {}",
+            if is_shielding { "outputs" } else { "inputs" }
+        )));
+    }
+    let mut value = Amount::zero();
+    for (asset_type, asset_value) in values {
+        let digit = asset_digits.get(&asset_type).ok_or_else(|| {
+            Error::ShieldedTransfer(format!(
+                "The asset type {asset_type:?} isn't of the transferred token"
+            ))
+        })?;
+        value = value
+            .checked_add(Amount::from_masp_denominated(asset_value, *digit))
+            .ok_or_else(|| {
+                Error::ShieldedTransfer(
+                    "Overflow in the transparent value".to_string(),
+                )
+            })?;
+    }
+    if value != amount {
+        return Err(Error::ShieldedTransfer(format!(
+            "The transparent value of the MASP transaction {value} doesn't \
+             match the transferred amount {amount}"
+        )));
+    }
+    Ok(())
+}
+
+/// Get the IBC proof specs of the storage hasher. They never change, so
+/// they're computed once per hasher and reused by all the IBC txs.
+fn ibc_proof_specs<H: 'static + StorageHasher>() -> ProofSpecs {
+    static PROOF_SPECS: OnceLock<RwLock<HashMap<TypeId, ProofSpecs>>> =
+        OnceLock::new();
+    let proof_specs = PROOF_SPECS.get_or_init(Default::default);
+    let hasher = TypeId::of::<H>();
+    if let Some(specs) = proof_specs.read().unwrap().get(&hasher) {
+        return specs.clone();
+    }
+    let specs: ProofSpecs =
+        namada_state::ics23_specs::ibc_proof_specs::<H>().into();
+    proof_specs
+        .write()
+        .unwrap()
+        .entry(hasher)
+        .or_insert(specs)
+        .clone()
+}
+
+/// The number of the epochs for which a bond can still be slashed after it's
+/// unbonded
+fn unbonding_epochs(pos_params: &PosParams) -> VpResult<u64> {
+    pos_params
+        .unbonding_len
+        .checked_add(pos_params.cubic_slashing_window_length)
+        .ok_or_else(|| {
+            Error::NativeVpError(native_vp::Error::new_const(
+                "The unbonding epochs overflow",
+            ))
+        })
+}
+
+/// Decode the value of the key, keeping the key and the type in the error
+fn decode_value<T: BorshDeserialize>(key: &Key, bytes: &[u8]) -> VpResult<T> {
+    T::try_from_slice(bytes).map_err(|source| Error::Decode {
+        key: key.clone(),
+        type_name: std::any::type_name::<T>(),
+        source,
+    })
+}
+
+/// Read the posterior value of a key without charging gas
+fn read_post_value<S>(state: &S, key: &Key) -> StorageResult<Option<Vec<u8>>>
+where
+    S: StateRead,
+{
+    match state.write_log().read(key).0 {
+        Some(StorageModification::Write { value }) => Ok(Some(value.clone())),
+        Some(StorageModification::Delete) => Ok(None),
+        Some(StorageModification::InitAccount { vp_code_hash }) => {
+            Ok(Some(vp_code_hash.to_vec()))
+        }
+        Some(StorageModification::Temp { .. }) => Err(
+            native_vp::Error::new_const("A temporary value can't be compared"),
+        ),
+        None => Ok(state.db_read(key).into_storage_result()?.0),
+    }
+}
+
+fn value_mismatch(
+    key: &Key,
+    actual: Option<Vec<u8>>,
+    expected: Option<&StorageModification>,
+) -> Option<VpRejectReason> {
+    // The pseudo execution only writes or deletes values
+    let expected = match expected {
+        Some(StorageModification::Write { value }) => Some(value),
+        _ => None,
+    };
+    if actual.as_ref() == expected {
+        return None;
+    }
+    Some(VpRejectReason::ValueMismatch {
+        key: key.clone(),
+        actual,
+        expected: expected.cloned(),
+    })
+}
+
+impl From<ActionError> for Error {
+    fn from(err: ActionError) -> Self {
+        Self::IbcAction(err)
+    }
+}
+
+/// Build the error of a mismatch between the IBC keys changed by the tx and
+/// the ones expected by the VP. The keys are sorted to render the same message
+/// on all the nodes.
+fn changed_keys_mismatch(
+    actual: &HashSet<&Key>,
+    expected: &HashSet<&Key>,
+) -> Error {
+    let actual: BTreeSet<&Key> = actual.iter().copied().collect();
+    let expected: BTreeSet<&Key> = expected.iter().copied().collect();
+    Error::Rejected(VpRejectReason::KeyMismatch {
+        actual_count: actual.len(),
+        expected_count: expected.len(),
+        unexpected: actual.difference(&expected).copied().cloned().collect(),
+        missing: expected.difference(&actual).copied().cloned().collect(),
+    })
+}
+
+/// A dummy header used for testing
+#[cfg(any(test, feature = "testing"))]
 pub fn get_dummy_header() -> crate::storage::Header {
     use crate::tendermint::time::Time as TmTime;
     crate::storage::Header {
@@ -303,23 +2012,34 @@ pub fn get_dummy_genesis_validator()
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use std::str::FromStr;
 
-    use borsh::BorshDeserialize;
     use borsh_ext::BorshSerializeExt;
     use ibc_testkit::testapp::ibc::clients::mock::client_state::{
         client_type, MockClientState, MOCK_CLIENT_TYPE,
     };
     use ibc_testkit::testapp::ibc::clients::mock::consensus_state::MockConsensusState;
     use ibc_testkit::testapp::ibc::clients::mock::header::MockHeader;
+    use ibc_testkit::testapp::ibc::clients::mock::misbehaviour::Misbehaviour as MockMisbehaviour;
+    use masp_primitives::transaction::TransparentAddress;
+    use namada_core::hash::Sha256Hasher;
+    use namada_core::ibc::{IbcShieldedTransfer, MsgShieldedTransfer};
+    use namada_core::masp::AssetData;
     use namada_core::validity_predicate::VpSentinel;
-    use namada_gas::TxGasMeter;
+    use namada_gas::{Gas, TxGasMeter};
     use namada_governance::parameters::GovernanceParameters;
+    use namada_governance::storage::keys::get_proposal_execution_key;
+    use namada_ibc::context::client::MOCK_TRUSTING_PERIOD;
+    use namada_ibc::context::IbcContext;
+    use namada_sdk::masp::testing::{
+        arb_deshielding_transfer, arb_shielding_transfer,
+    };
     use namada_state::testing::TestState;
-    use namada_state::StorageRead;
-    use namada_tx::data::TxType;
+    use namada_state::{StorageRead, StorageWrite};
+    use namada_tx::data::{truncate_error, TxType};
     use namada_tx::{Code, Data, Section, Signature};
+    use proptest::prelude::*;
     use prost::Message;
     use sha2::Digest;
 
@@ -337,30 +2057,37 @@ mod tests {
     use crate::ibc::apps::transfer::types::{
         ack_success_b64, PrefixedCoin, TracePrefix, VERSION,
     };
+    use crate::ibc::clients::tendermint::client_state::ClientState as TmClientState;
+    use crate::ibc::clients::tendermint::types::{
+        AllowUpdate, ClientState as TmClientStateType, TrustThreshold,
+    };
     use crate::ibc::core::channel::types::acknowledgement::{
-        Acknowledgement, AcknowledgementStatus,
+        Acknowledgement, AcknowledgementStatus, StatusValue,
     };
     use crate::ibc::core::channel::types::channel::{
         ChannelEnd, Counterparty as ChanCounterparty, Order, State as ChanState,
     };
     use crate::ibc::core::channel::types::commitment::PacketCommitment;
     use crate::ibc::core::channel::types::events::{
-        AcknowledgePacket, OpenAck as ChanOpenAck,
+        AcknowledgePacket, ChannelClosed, CloseConfirm as ChanCloseConfirm,
+        CloseInit as ChanCloseInit, OpenAck as ChanOpenAck,
         OpenConfirm as ChanOpenConfirm, OpenInit as ChanOpenInit,
         OpenTry as ChanOpenTry, ReceivePacket, SendPacket, TimeoutPacket,
         WriteAcknowledgement,
     };
     use crate::ibc::core::channel::types::msgs::{
-        MsgAcknowledgement, MsgChannelOpenAck, MsgChannelOpenConfirm,
-        MsgChannelOpenInit, MsgChannelOpenTry, MsgRecvPacket, MsgTimeout,
-        MsgTimeoutOnClose,
+        MsgAcknowledgement, MsgChannelCloseConfirm, MsgChannelCloseInit,
+        MsgChannelOpenAck, MsgChannelOpenConfirm, MsgChannelOpenInit,
+        MsgChannelOpenTry, MsgRecvPacket, MsgTimeout, MsgTimeoutOnClose,
     };
     use crate::ibc::core::channel::types::packet::Packet;
     use crate::ibc::core::channel::types::timeout::TimeoutHeight;
     use crate::ibc::core::channel::types::Version as ChanVersion;
-    use crate::ibc::core::client::types::events::{CreateClient, UpdateClient};
+    use crate::ibc::core::client::types::events::{
+        ClientMisbehaviour, CreateClient, UpdateClient,
+    };
     use crate::ibc::core::client::types::msgs::{
-        MsgCreateClient, MsgUpdateClient,
+        MsgCreateClient, MsgSubmitMisbehaviour, MsgUpdateClient,
     };
     use crate::ibc::core::client::types::Height;
     use crate::ibc::core::commitment_types::commitment::{
@@ -381,32 +2108,37 @@ mod tests {
     use crate::ibc::core::handler::types::events::{
         IbcEvent as RawIbcEvent, MessageEvent,
     };
+    use crate::ibc::core::host::ValidationContext;
     use crate::ibc::core::host::types::identifiers::{
         ChannelId, ClientId, ConnectionId, PortId, Sequence,
     };
     use crate::ibc::core::router::types::event::ModuleEvent;
     use crate::ibc::primitives::proto::{Any, Protobuf};
     use crate::ibc::primitives::{Msg, Timestamp};
+    use crate::ibc::PacketEscrow;
     use crate::ibc::storage::{
-        ack_key, channel_counter_key, channel_key, client_connections_key,
-        client_counter_key, client_state_key, client_update_height_key,
-        client_update_timestamp_key, commitment_key, connection_counter_key,
-        connection_key, consensus_state_key, ibc_denom_key,
+        ack_key, blocked_trace_key, channel_counter_key, channel_key,
+        client_connections_key, client_counter_key, client_state_key,
+        client_update_height_key, client_update_timestamp_key, commitment_key,
+        connection_counter_key, connection_key, consensus_state_key,
+        escrow_amount_key, forwarded_packet_key, ibc_denom_by_token_key,
+        ibc_denom_key, ibc_token, min_transfer_amount_key, mint_limit_key,
         next_sequence_ack_key, next_sequence_recv_key, next_sequence_send_key,
-        receipt_key,
+        packet_escrow_key, read_counter, read_sequence, receipt_key,
+        throughput_limit_key, throughput_limit_key_for_channel,
     };
     use crate::key::testing::keypair_1;
     use crate::ledger::gas::VpGasMeter;
     use crate::ledger::parameters::storage::{
         get_epoch_duration_storage_key, get_max_expected_time_per_block_key,
     };
-    use crate::ledger::parameters::EpochDuration;
+    use crate::ledger::parameters::{self, EpochDuration};
     use crate::ledger::{ibc, pos};
     use crate::storage::{BlockHash, BlockHeight, TxIndex};
     use crate::tendermint::time::Time as TmTime;
     use crate::time::DurationSecs;
-    use crate::token::storage_key::balance_key;
-    use crate::token::Amount;
+    use crate::token::storage_key::{balance_key, minted_balance_key};
+    use crate::token::{Amount, DenominatedAmount, Transfer};
     use crate::vm::wasm;
 
     const ADDRESS: Address = Address::Internal(InternalAddress::Ibc);
@@ -418,7 +2150,8 @@ mod tests {
         ClientId::from_str(&id).expect("Creating a client ID failed")
     }
 
-    fn init_storage() -> TestState {
+    /// Initialize the storage of IBC and of the parameters read by its VP
+    pub(crate) fn init_storage() -> TestState {
         let mut state = TestState::default();
 
         // initialize the storage
@@ -565,45 +2298,104 @@ mod tests {
     fn get_next_seq(state: &TestState, key: &Key) -> Sequence {
         let (val, _) = state.db_read(key).expect("read failed");
         match val {
-            Some(v) => {
-                // IBC related data is encoded without borsh
-                let index: [u8; 8] = v.try_into().expect("decoding failed");
-                let index = u64::from_be_bytes(index);
-                Sequence::from(index)
-            }
+            Some(v) => U64Codec::BigEndian
+                .decode(&v)
+                .map(Sequence::from)
+                .expect("decoding failed"),
             // The sequence has not been used yet
             None => Sequence::from(1),
         }
     }
 
     fn increment_sequence(state: &mut TestState, key: &Key) {
-        let count = match state.read_bytes(key).expect("read failed") {
-            Some(value) => {
-                let count: [u8; 8] =
-                    value.try_into().expect("decoding a count failed");
-                u64::from_be_bytes(count)
-            }
-            None => 0,
-        };
+        let count = read_sequence(state, key)
+            .expect("read failed")
+            .map(u64::from)
+            .unwrap_or_default();
         state
             .write_log_mut()
-            .write(key, (count + 1).to_be_bytes().to_vec())
+            .write(key, U64Codec::BigEndian.encode(count + 1))
             .expect("write failed");
     }
 
     fn increment_counter(state: &mut TestState, key: &Key) {
-        let count = match state.read_bytes(key).expect("read failed") {
-            Some(value) => {
-                u64::try_from_slice(&value).expect("invalid counter value")
-            }
-            None => unreachable!("The counter should be initialized"),
+        let count = read_counter(state, key)
+            .expect("read failed")
+            .expect("The counter should be initialized");
+        state
+            .write_log_mut()
+            .write(key, U64Codec::Borsh.encode(count + 1))
+            .expect("write failed");
+    }
+
+    /// Add the amount to the one stored with the key as the transfer context
+    /// does for the rate limits
+    fn add_amount(
+        state: &mut TestState,
+        keys_changed: &mut BTreeSet<Key>,
+        key: Key,
+        amount: Amount,
+    ) {
+        let current: Amount =
+            state.read(&key).expect("read failed").unwrap_or_default();
+        let new_amount = current.checked_add(amount).expect("overflow");
+        state
+            .write_log_mut()
+            .write(&key, new_amount.serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(key);
+    }
+
+    fn sub_amount(
+        state: &mut TestState,
+        keys_changed: &mut BTreeSet<Key>,
+        key: Key,
+        amount: Amount,
+    ) {
+        let current: Amount =
+            state.read(&key).expect("read failed").unwrap_or_default();
+        let new_amount = current.checked_sub(amount).expect("underflow");
+        state
+            .write_log_mut()
+            .write(&key, new_amount.serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(key);
+    }
+
+    /// Record the escrow of the amount of NAM sent with the packet
+    fn insert_packet_escrow(
+        state: &mut TestState,
+        sequence: Sequence,
+        amount: Amount,
+    ) {
+        state
+            .write_log_mut()
+            .write(&escrow_amount_key(&nam()), amount.serialize_to_vec())
+            .expect("write failed");
+        let key =
+            packet_escrow_key(&get_port_id(), &get_channel_id(), sequence);
+        let escrow = PacketEscrow {
+            token: nam(),
+            amount,
         };
         state
             .write_log_mut()
-            .write(key, (count + 1).serialize_to_vec())
+            .write(&key, escrow.serialize_to_vec())
             .expect("write failed");
     }
 
+    /// Delete the escrow record of the packet as the commitment is deleted
+    fn delete_packet_escrow(
+        state: &mut TestState,
+        keys_changed: &mut BTreeSet<Key>,
+        sequence: Sequence,
+    ) {
+        let key =
+            packet_escrow_key(&get_port_id(), &get_channel_id(), sequence);
+        state.write_log_mut().delete(&key).expect("delete failed");
+        keys_changed.insert(key);
+    }
+
     fn dummy_proof() -> CommitmentProofBytes {
         CommitmentProofBytes::try_from(vec![0]).unwrap()
     }
@@ -652,9 +2444,15 @@ mod tests {
         sha2::Sha256::digest(&input).to_vec().into()
     }
 
-    #[test]
-    fn test_create_client() {
-        let mut state = init_storage();
+    /// Write the changes of the creation of a mock client to the write log.
+    /// The client counter is written with the given value instead of being
+    /// incremented, if any, and the events are emitted in the reverse order
+    /// if set. Returns the changed keys, the tx data and the emitted events.
+    pub(crate) fn write_create_client(
+        state: &mut TestState,
+        client_counter: Option<Vec<u8>>,
+        reverse_events: bool,
+    ) -> (BTreeSet<Key>, Vec<u8>, Vec<IbcEvent>) {
         let mut keys_changed = BTreeSet::new();
 
         let height = Height::new(0, 1).unwrap();
@@ -689,7 +2487,15 @@ mod tests {
         keys_changed.insert(consensus_key);
         // client counter
         let client_counter_key = client_counter_key();
-        increment_counter(&mut state, &client_counter_key);
+        match client_counter {
+            Some(value) => {
+                state
+                    .write_log_mut()
+                    .write(&client_counter_key, value)
+                    .expect("write failed");
+            }
+            None => increment_counter(state, &client_counter_key),
+        }
         keys_changed.insert(client_counter_key);
 
         let event = RawIbcEvent::CreateClient(CreateClient::new(
@@ -698,52 +2504,342 @@ mod tests {
             client_state.latest_height(),
         ));
         let message_event = RawIbcEvent::Message(MessageEvent::Client);
-        state
-            .write_log_mut()
-            .emit_ibc_event(message_event.try_into().unwrap());
-        state
-            .write_log_mut()
-            .emit_ibc_event(event.try_into().unwrap());
+        let mut events: Vec<IbcEvent> =
+            vec![message_event.try_into().unwrap(), event.try_into().unwrap()];
+        if reverse_events {
+            events.reverse();
+        }
+        for event in &events {
+            state.write_log_mut().emit_ibc_event(event.clone());
+        }
 
-        let tx_index = TxIndex::default();
-        let tx_code = vec![];
         let mut tx_data = vec![];
         msg.to_any().encode(&mut tx_data).expect("encoding failed");
-        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
-            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
-        ));
+        (keys_changed, tx_data, events)
+    }
+
+    /// Validate the tx of the given data, consuming the gas of the given
+    /// meter
+    fn validate_ibc_tx_data(
+        state: &TestState,
+        tx_data: Vec<u8>,
+        keys_changed: &BTreeSet<Key>,
+        gas_meter: &RefCell<VpGasMeter>,
+    ) -> VpResult<bool> {
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(tx_data)
+            .sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
         let (vp_wasm_cache, _vp_cache_dir) =
             wasm::compilation_cache::common::testing::cache();
-
         let verifiers = BTreeSet::new();
-        let mut outer_tx = Tx::from_type(TxType::Raw);
-        outer_tx.header.chain_id = state.in_mem().chain_id.clone();
-        outer_tx.set_code(Code::new(tx_code, None));
-        outer_tx.set_data(Data::new(tx_data));
-        outer_tx.add_section(Section::Signature(Signature::new(
-            vec![outer_tx.header_hash()],
-            [(0, keypair_1())].into_iter().collect(),
-            None,
-        )));
         let sentinel = RefCell::new(VpSentinel::default());
         let ctx = Ctx::new(
             &ADDRESS,
-            &state,
-            &outer_tx,
+            state,
+            &tx,
             &tx_index,
-            &gas_meter,
+            gas_meter,
             &sentinel,
-            &keys_changed,
+            keys_changed,
             &verifiers,
             vp_wasm_cache,
         );
-
         let ibc = Ibc { ctx };
-        // this should return true because state has been stored
-        assert!(
-            ibc.validate_tx(&outer_tx, &keys_changed, &verifiers)
-                .expect("validation failed")
-        );
+        ibc.validate_tx(&tx, keys_changed, &verifiers)
+    }
+
+    /// Make a gas meter of the limit of the tests
+    fn new_gas_meter() -> RefCell<VpGasMeter> {
+        RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ))
+    }
+
+    #[test]
+    fn test_create_client() {
+        let mut state = init_storage();
+        let (keys_changed, tx_data, _) =
+            write_create_client(&mut state, None, false);
+
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into())
+                .with_gas_trace(true),
+        ));
+        // this should return true because state has been stored
+        assert!(
+            validate_ibc_tx_data(&state, tx_data, &keys_changed, &gas_meter)
+                .expect("validation failed")
+        );
+
+        // the gas breakdown accounts for all the gas consumed by the VP
+        let gas_meter = gas_meter.borrow();
+        let breakdown = gas_meter.gas_breakdown().expect("tracing is enabled");
+        for label in [
+            IBC_EXECUTE_GAS_LABEL,
+            IBC_VALIDATE_GAS_LABEL,
+            IBC_STATE_READ_GAS_LABEL,
+        ] {
+            assert!(breakdown.contains_key(label));
+        }
+        let total = breakdown
+            .values()
+            .try_fold(Gas::default(), |acc, gas| acc.checked_add(*gas))
+            .expect("gas overflow");
+        assert_eq!(total, gas_meter.get_vp_consumed_gas());
+    }
+
+    #[test]
+    fn test_create_client_events_out_of_order() {
+        let mut state = init_storage();
+        // the same events as the pseudo execution, but in the reverse order
+        let (keys_changed, tx_data, events) =
+            write_create_client(&mut state, None, true);
+
+        // this should fail because the events are out of order
+        let result = validate_ibc_tx_data(
+            &state,
+            tx_data,
+            &keys_changed,
+            &new_gas_meter(),
+        )
+        .unwrap_err();
+        match result {
+            Error::IbcEvent {
+                index,
+                actual,
+                expected,
+            } => {
+                assert_eq!(index, 0);
+                assert_eq!(actual, Some(events[0].event_type.clone()));
+                assert_eq!(expected, Some(events[1].event_type.clone()));
+            }
+            _ => panic!("unexpected error: {result}"),
+        }
+    }
+
+    #[test]
+    fn test_create_client_skipped_counter() {
+        let mut state = init_storage();
+        // the client counter skips from 0 to 7
+        let (keys_changed, tx_data, _) = write_create_client(
+            &mut state,
+            Some(7_u64.serialize_to_vec()),
+            false,
+        );
+
+        // this should fail because the counter isn't incremented by one
+        let result = validate_ibc_tx_data(
+            &state,
+            tx_data,
+            &keys_changed,
+            &new_gas_meter(),
+        )
+        .unwrap_err();
+        match result {
+            Error::StateChange(msg) => assert!(msg.contains("client counter")),
+            _ => panic!("unexpected error: {result}"),
+        }
+    }
+
+    #[test]
+    fn test_create_client_counter_with_sequence_codec() {
+        let mut state = init_storage();
+        // the client counter is incremented, but encoded as a sequence
+        let (keys_changed, tx_data, _) = write_create_client(
+            &mut state,
+            Some(U64Codec::BigEndian.encode(1)),
+            false,
+        );
+
+        // this should fail with the expected encoding of the counter
+        let result = validate_ibc_tx_data(
+            &state,
+            tx_data,
+            &keys_changed,
+            &new_gas_meter(),
+        )
+        .unwrap_err();
+        match result {
+            Error::StateChange(msg) => {
+                assert!(msg.contains("client counter"));
+                assert!(msg.contains("borsh-encoded"));
+            }
+            _ => panic!("unexpected error: {result}"),
+        }
+    }
+
+    #[test]
+    fn test_corrupt_client_counter() {
+        let mut state = init_storage();
+        let mut keys_changed = BTreeSet::new();
+
+        // the client counter isn't a u64
+        let client_counter_key = client_counter_key();
+        state
+            .write_log_mut()
+            .write(&client_counter_key, vec![0xff; 3])
+            .expect("write failed");
+        keys_changed.insert(client_counter_key.clone());
+
+        let tx_index = TxIndex::default();
+        let tx_code = vec![];
+        let tx_data = vec![];
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+
+        let verifiers = BTreeSet::new();
+        let mut outer_tx = Tx::from_type(TxType::Raw);
+        outer_tx.header.chain_id = state.in_mem().chain_id.clone();
+        outer_tx.set_code(Code::new(tx_code, None));
+        outer_tx.set_data(Data::new(tx_data));
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &outer_tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+
+        let ibc = Ibc { ctx };
+        // this should fail because the counter can't be decoded
+        let result = ibc
+            .validate_tx(&outer_tx, &keys_changed, &verifiers)
+            .unwrap_err();
+        match &result {
+            Error::Decode { key, .. } => assert_eq!(*key, client_counter_key),
+            _ => panic!("unexpected error: {result}"),
+        }
+        assert!(result.to_string().contains(&client_counter_key.to_string()));
+    }
+
+    #[test]
+    fn test_corrupt_denom() {
+        let mut state = init_storage();
+        let mut keys_changed = BTreeSet::new();
+
+        // the denom isn't a string
+        let trace_hash = calc_hash("transfer/channel-0/nam");
+        let denom_key = ibc_denom_key(nam().to_string(), &trace_hash);
+        state
+            .write_log_mut()
+            .write(&denom_key, vec![0xff; 3])
+            .expect("write failed");
+        keys_changed.insert(denom_key.clone());
+
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+
+        let verifiers = BTreeSet::new();
+        let outer_tx = Tx::from_type(TxType::Raw);
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &outer_tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+
+        let ibc = Ibc { ctx };
+        let result = ibc.validate_denom(&keys_changed).unwrap_err();
+        match &result {
+            Error::Decode { key, .. } => assert_eq!(*key, denom_key),
+            _ => panic!("unexpected error: {result}"),
+        }
+        assert!(result.to_string().contains(&denom_key.to_string()));
+    }
+
+    #[test]
+    fn test_denom_by_token_with_stored_trace() {
+        let mut state = init_storage();
+        let mut keys_changed = BTreeSet::new();
+
+        // the trace has been stored by a previous receipt
+        let denom = format!("{}/{}/{}", get_port_id(), get_channel_id(), nam());
+        let trace_key = ibc_denom_key(nam().to_string(), calc_hash(&denom));
+        state
+            .write_log_mut()
+            .write(&trace_key, denom.serialize_to_vec())
+            .expect("write failed");
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+
+        // only the denom looked up by the token is written
+        let denom_key = ibc_denom_by_token_key(&ibc_token(&denom));
+        state
+            .write_log_mut()
+            .write(&denom_key, denom.serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(denom_key);
+
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+
+        let verifiers = BTreeSet::new();
+        let outer_tx = Tx::from_type(TxType::Raw);
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &outer_tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache.clone(),
+        );
+        let ibc = Ibc { ctx };
+        ibc.validate_denom(&keys_changed)
+            .expect("the stored trace should be accepted");
+
+        // the denom without any stored trace is rejected
+        let denom = format!("{}/{}/uatom", get_port_id(), get_channel_id());
+        let denom_key = ibc_denom_by_token_key(&ibc_token(&denom));
+        state
+            .write_log_mut()
+            .write(&denom_key, denom.serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(denom_key);
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &outer_tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let ibc = Ibc { ctx };
+        let result = ibc.validate_denom(&keys_changed);
+        assert!(matches!(
+            result,
+            Err(Error::IbcAction(ActionError::Denom(_)))
+        ));
     }
 
     #[test]
@@ -821,28 +2917,30 @@ mod tests {
         // this should fail because no state is stored
         let result =
             ibc.validate_tx(&tx, &keys_changed, &verifiers).unwrap_err();
-        assert_matches!(result, Error::StateChange(_));
+        let missing: BTreeSet<Key> = [
+            consensus_state_key(&get_client_id(), height),
+            client_update_timestamp_key(&get_client_id()),
+            client_update_height_key(&get_client_id()),
+            client_counter_key(),
+        ]
+        .into();
+        assert_eq!(
+            result.reject_reason(),
+            Some(&VpRejectReason::KeyMismatch {
+                actual_count: 1,
+                expected_count: 5,
+                unexpected: vec![],
+                missing: missing.into_iter().collect(),
+            })
+        );
     }
 
-    #[test]
-    fn test_update_client() {
-        let mut keys_changed = BTreeSet::new();
-        let mut state = init_storage();
-        insert_init_client(&mut state);
-        state.write_log_mut().commit_tx();
-        state.commit_block().expect("commit failed");
-
-        // for next block
-        state
-            .in_mem_mut()
-            .set_header(get_dummy_header())
-            .expect("Setting a dummy header shouldn't fail");
-        state
-            .in_mem_mut()
-            .begin_block(BlockHash::default(), BlockHeight(2))
-            .unwrap();
-
-        // update the client
+    /// Write the changes of an update of the initial client to a header
+    /// created before the current block, and return the update message
+    fn write_update_client(
+        state: &mut TestState,
+        keys_changed: &mut BTreeSet<Key>,
+    ) -> MsgUpdateClient {
         let client_id = get_client_id();
         let client_state_key = client_state_key(&get_client_id());
         let height = Height::new(0, 11).unwrap();
@@ -876,7 +2974,7 @@ mod tests {
         keys_changed.insert(consensus_key);
         // client update time
         let client_update_time_key = client_update_timestamp_key(&client_id);
-        let time = StateRead::get_block_header(&state, None)
+        let time = StateRead::get_block_header(state, None)
             .unwrap()
             .0
             .unwrap()
@@ -914,6 +3012,30 @@ mod tests {
             .write_log_mut()
             .emit_ibc_event(event.try_into().unwrap());
 
+        msg
+    }
+
+    #[test]
+    fn test_update_client() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+
+        // for next block
+        state
+            .in_mem_mut()
+            .set_header(get_dummy_header())
+            .expect("Setting a dummy header shouldn't fail");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        // update the client
+        let msg = write_update_client(&mut state, &mut keys_changed);
+
         let tx_index = TxIndex::default();
         let tx_code = vec![];
         let mut tx_data = vec![];
@@ -951,13 +3073,41 @@ mod tests {
         );
     }
 
-    #[test]
-    fn test_init_connection() {
+    /// Validate a client update which prunes an expired consensus state with
+    /// the given max number of the consensus states per client. The client
+    /// has the initial and the expired consensus states before the update.
+    fn validate_update_client_with_pruning(
+        max_consensus_states_per_client: u64,
+    ) -> VpResult<bool> {
         let mut keys_changed = BTreeSet::new();
         let mut state = init_storage();
+        let params = IbcParameters {
+            max_consensus_states_per_client,
+            ..Default::default()
+        };
+        params.init_storage(&mut state).expect("write failed");
         insert_init_client(&mut state);
+        // an expired consensus state
+        let client_id = get_client_id();
+        let expired_height = Height::new(0, 5).unwrap();
+        let expired_time = (TmTime::now()
+            - (MOCK_TRUSTING_PERIOD + std::time::Duration::new(100, 0)))
+        .unwrap();
+        let expired_header = MockHeader {
+            height: expired_height,
+            timestamp: expired_time.into(),
+        };
+        let expired_key = consensus_state_key(&client_id, expired_height);
+        let bytes = Protobuf::<Any>::encode_vec(MockConsensusState::new(
+            expired_header,
+        ));
+        state
+            .write_log_mut()
+            .write(&expired_key, bytes)
+            .expect("write failed");
         state.write_log_mut().commit_tx();
         state.commit_block().expect("commit failed");
+
         // for next block
         state
             .in_mem_mut()
@@ -968,17 +3118,296 @@ mod tests {
             .begin_block(BlockHash::default(), BlockHeight(2))
             .unwrap();
 
-        // prepare a message
-        let mut counterparty = get_conn_counterparty();
-        counterparty.connection_id = None;
-        let msg = MsgConnectionOpenInit {
-            client_id_on_a: get_client_id(),
-            counterparty,
-            version: Some(ConnVersion::default()),
-            delay_period: Duration::new(100, 0),
+        // update the client
+        let client_state_key = client_state_key(&client_id);
+        let height = Height::new(0, 11).unwrap();
+        // the header should be created before
+        let time = (TmTime::now() - std::time::Duration::new(100, 0)).unwrap();
+        let header = MockHeader {
+            height,
+            timestamp: time.into(),
+        };
+        let msg = MsgUpdateClient {
+            client_id: client_id.clone(),
+            client_message: header.into(),
             signer: "account0".to_string().into(),
         };
-
+        // client state
+        let client_state = MockClientState::new(header);
+        let bytes = Protobuf::<Any>::encode_vec(client_state);
+        state
+            .write_log_mut()
+            .write(&client_state_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(client_state_key);
+        // consensus state
+        let consensus_key = consensus_state_key(&client_id, height);
+        let consensus_state = MockConsensusState::new(header);
+        let bytes = Protobuf::<Any>::encode_vec(consensus_state);
+        state
+            .write_log_mut()
+            .write(&consensus_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(consensus_key);
+        // the expired consensus state is pruned
+        state
+            .write_log_mut()
+            .delete(&expired_key)
+            .expect("delete failed");
+        keys_changed.insert(expired_key);
+        // client update time
+        let client_update_time_key = client_update_timestamp_key(&client_id);
+        let time = StateRead::get_block_header(&state, None)
+            .unwrap()
+            .0
+            .unwrap()
+            .time;
+        let bytes = TmTime::try_from(time).unwrap().encode_vec();
+        state
+            .write_log_mut()
+            .write(&client_update_time_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(client_update_time_key);
+        // client update height
+        let client_update_height_key = client_update_height_key(&client_id);
+        let host_height = state.in_mem().get_block_height().0;
+        let host_height =
+            Height::new(0, host_height.0).expect("invalid height");
+        state
+            .write_log_mut()
+            .write(&client_update_height_key, host_height.encode_vec())
+            .expect("write failed");
+        keys_changed.insert(client_update_height_key);
+        // event
+        let consensus_height = client_state.latest_height();
+        let event = RawIbcEvent::UpdateClient(UpdateClient::new(
+            client_id,
+            client_type(),
+            consensus_height,
+            vec![consensus_height],
+            Protobuf::<Any>::encode_vec(header),
+        ));
+        let message_event = RawIbcEvent::Message(MessageEvent::Client);
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        validate_ibc_tx(&state, msg.to_any(), &keys_changed)
+    }
+
+    #[test]
+    fn test_update_client_with_pruning() {
+        // The initial and the new consensus states remain after the update.
+        // The expired one isn't counted though it's in the prior state.
+        let result = validate_update_client_with_pruning(2);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_update_client_exceeding_consensus_state_limit() {
+        let result = validate_update_client_with_pruning(1);
+        match result {
+            Err(Error::Rejected(
+                VpRejectReason::ConsensusStateLimitExceeded {
+                    client_id,
+                    limit,
+                    count,
+                },
+            )) => {
+                assert_eq!(client_id, get_client_id());
+                assert_eq!(limit, 1);
+                assert_eq!(count, 2);
+            }
+            _ => panic!("Unexpected result {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_update_client_regressed_height() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        let client_id = get_client_id();
+        let client_update_height_key = client_update_height_key(&client_id);
+        state
+            .write_log_mut()
+            .write(
+                &client_update_height_key,
+                Height::new(0, 5).expect("invalid height").encode_vec(),
+            )
+            .expect("write failed");
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+
+        // for next block
+        state
+            .in_mem_mut()
+            .set_header(get_dummy_header())
+            .expect("Setting a dummy header shouldn't fail");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        let header = MockHeader {
+            height: Height::new(0, 11).unwrap(),
+            timestamp: Timestamp::now(),
+        };
+        let msg = MsgUpdateClient {
+            client_id: client_id.clone(),
+            client_message: header.into(),
+            signer: "account0".to_string().into(),
+        };
+        // the update height is lower than the one of the last update
+        let host_height = Height::new(0, 1).expect("invalid height");
+        state
+            .write_log_mut()
+            .write(&client_update_height_key, host_height.encode_vec())
+            .expect("write failed");
+        keys_changed.insert(client_update_height_key);
+
+        let tx_index = TxIndex::default();
+        let tx_code = vec![];
+        let mut tx_data = vec![];
+        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(tx_code, None)
+            .add_serialized_data(tx_data)
+            .sign_wrapper(keypair_1());
+
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+
+        let verifiers = BTreeSet::new();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let ibc = Ibc { ctx };
+        let result = ibc.validate_tx(&tx, &keys_changed, &verifiers);
+        assert!(matches!(result, Err(Error::ClientUpdate(_))));
+    }
+
+    #[test]
+    fn test_submit_misbehaviour() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+
+        // for next block
+        state
+            .in_mem_mut()
+            .set_header(get_dummy_header())
+            .expect("Setting a dummy header shouldn't fail");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        // two different headers at the same height
+        let client_id = get_client_id();
+        let height = Height::new(0, 2).unwrap();
+        let header1 = MockHeader {
+            height,
+            timestamp: Timestamp::now(),
+        };
+        let header2 = MockHeader {
+            height,
+            timestamp: (TmTime::now() - std::time::Duration::new(100, 0))
+                .unwrap()
+                .into(),
+        };
+        let misbehaviour = MockMisbehaviour {
+            client_id: client_id.clone(),
+            header1,
+            header2,
+        };
+        let msg = MsgSubmitMisbehaviour {
+            client_id: client_id.clone(),
+            misbehaviour: misbehaviour.into(),
+            signer: "account0".to_string().into(),
+        };
+        // frozen client state
+        let client_state_key = client_state_key(&client_id);
+        let bytes = state
+            .read_bytes(&client_state_key)
+            .expect("read failed")
+            .expect("no client state");
+        let client_state = MockClientState::try_from(
+            Any::decode(&bytes[..]).expect("decoding failed"),
+        )
+        .expect("decoding failed");
+        let frozen_client_state =
+            client_state.with_frozen_height(Height::min(0));
+        let bytes = Protobuf::<Any>::encode_vec(frozen_client_state);
+        state
+            .write_log_mut()
+            .write(&client_state_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(client_state_key);
+        // event
+        let event = RawIbcEvent::ClientMisbehaviour(ClientMisbehaviour::new(
+            client_id,
+            client_type(),
+        ));
+        let message_event = RawIbcEvent::Message(MessageEvent::Client);
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        // the client is frozen by the misbehaviour
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_init_connection() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        // for next block
+        state
+            .in_mem_mut()
+            .set_header(get_dummy_header())
+            .expect("Setting a dummy header shouldn't fail");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        // prepare a message
+        let mut counterparty = get_conn_counterparty();
+        counterparty.connection_id = None;
+        let msg = MsgConnectionOpenInit {
+            client_id_on_a: get_client_id(),
+            counterparty,
+            version: Some(ConnVersion::default()),
+            delay_period: Duration::new(100, 0),
+            signer: "account0".to_string().into(),
+        };
+
         // insert an INIT connection
         let conn_id = get_connection_id();
         let conn_key = connection_key(&conn_id);
@@ -1156,7 +3585,27 @@ mod tests {
         // this should fail because no event
         let result =
             ibc.validate_tx(&tx, &keys_changed, &verifiers).unwrap_err();
-        assert_matches!(result, Error::IbcEvent(_));
+        let missing: BTreeSet<IbcEvent> = [
+            RawIbcEvent::OpenInitConnection(ConnOpenInit::new(
+                conn_id,
+                msg.client_id_on_a.clone(),
+                msg.counterparty.client_id().clone(),
+            )),
+            RawIbcEvent::Message(MessageEvent::Connection),
+        ]
+        .into_iter()
+        .map(|event| event.try_into().unwrap())
+        .collect();
+        assert_eq!(
+            result.reject_reason(),
+            Some(&VpRejectReason::EventMismatch {
+                unexpected: vec![],
+                missing: missing
+                    .into_iter()
+                    .map(|event| event.event_type)
+                    .collect(),
+            })
+        );
     }
 
     #[test]
@@ -1958,16 +4407,31 @@ mod tests {
         );
     }
 
-    // skip test_close_init_channel() and test_close_confirm_channel() since it
-    // is not allowed to close the transfer channel
+    /// A port which isn't bound to an application module
+    fn get_unbound_port_id() -> PortId {
+        PortId::from_str("icahost").unwrap()
+    }
 
-    #[test]
-    fn test_send_packet() {
-        let mut keys_changed = BTreeSet::new();
-        let mut state = init_storage();
-        insert_init_client(&mut state);
+    fn get_unbound_port_channel(channel_state: ChanState) -> ChannelEnd {
+        ChannelEnd::new(
+            channel_state,
+            Order::Ordered,
+            ChanCounterparty::new(
+                get_unbound_port_id(),
+                Some(ChannelId::new(0)),
+            ),
+            vec![get_connection_id()],
+            ChanVersion::new("ics27-1".to_string()),
+        )
+        .unwrap()
+    }
 
-        // insert an open connection
+    /// Insert an open connection and an open channel on the port
+    fn insert_open_channel(
+        state: &mut TestState,
+        port_id: &PortId,
+        channel: ChannelEnd,
+    ) {
         let conn_key = connection_key(&get_connection_id());
         let conn = get_connection(ConnState::Open);
         let bytes = conn.encode_vec();
@@ -1975,22 +4439,12 @@ mod tests {
             .write_log_mut()
             .write(&conn_key, bytes)
             .expect("write failed");
-        // insert an Open channel
-        let channel_key = channel_key(&get_port_id(), &get_channel_id());
-        let channel = get_channel(ChanState::Open, Order::Unordered);
+        let channel_key = channel_key(port_id, &get_channel_id());
         let bytes = channel.encode_vec();
         state
             .write_log_mut()
             .write(&channel_key, bytes)
             .expect("write failed");
-        // init balance
-        let sender = established_address_1();
-        let balance_key = balance_key(&nam(), &sender);
-        let amount = Amount::native_whole(100);
-        state
-            .write_log_mut()
-            .write(&balance_key, amount.serialize_to_vec())
-            .expect("write failed");
         state.write_log_mut().commit_tx();
         state.commit_block().expect("commit failed");
         // for next block
@@ -2002,73 +4456,28 @@ mod tests {
             .in_mem_mut()
             .begin_block(BlockHash::default(), BlockHeight(2))
             .unwrap();
+    }
 
-        // prepare data
-        let msg = MsgTransfer {
-            port_id_on_a: get_port_id(),
-            chan_id_on_a: get_channel_id(),
-            packet_data: PacketData {
-                token: PrefixedCoin {
-                    denom: nam().to_string().parse().unwrap(),
-                    amount: 100u64.into(),
-                },
-                sender: sender.to_string().into(),
-                receiver: "receiver".to_string().into(),
-                memo: "memo".to_string().into(),
-            },
-            timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
-            timeout_timestamp_on_b: Timestamp::none(),
-        };
-
-        // the sequence send
-        let seq_key = next_sequence_send_key(&get_port_id(), &get_channel_id());
-        let sequence = get_next_seq(&state, &seq_key);
-        state
-            .write_log_mut()
-            .write(&seq_key, (u64::from(sequence) + 1).to_be_bytes().to_vec())
-            .expect("write failed");
-        keys_changed.insert(seq_key);
-        // packet commitment
-        let packet =
-            packet_from_message(&msg, sequence, &get_channel_counterparty());
-        let commitment_key =
-            commitment_key(&msg.port_id_on_a, &msg.chan_id_on_a, sequence);
-        let commitment = commitment(&packet);
-        let bytes = commitment.into_vec();
-        state
-            .write_log_mut()
-            .write(&commitment_key, bytes)
-            .expect("write failed");
-        keys_changed.insert(commitment_key);
-        // event
-        let transfer_event = TransferEvent {
-            sender: msg.packet_data.sender.clone(),
-            receiver: msg.packet_data.receiver.clone(),
-            amount: msg.packet_data.token.amount,
-            denom: msg.packet_data.token.denom.clone(),
-            memo: msg.packet_data.memo.clone(),
-        };
-        let event = RawIbcEvent::Module(ModuleEvent::from(transfer_event));
-        state
-            .write_log_mut()
-            .emit_ibc_event(event.try_into().unwrap());
-        let event = RawIbcEvent::SendPacket(SendPacket::new(
-            packet,
-            Order::Unordered,
-            get_connection_id(),
-        ));
-        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
-        state
-            .write_log_mut()
-            .emit_ibc_event(message_event.try_into().unwrap());
-        state
-            .write_log_mut()
-            .emit_ibc_event(event.try_into().unwrap());
+    fn validate_ibc_tx(
+        state: &TestState,
+        msg: Any,
+        keys_changed: &BTreeSet<Key>,
+    ) -> VpResult<bool> {
+        validate_ibc_tx_with_gas_limit(state, msg, keys_changed, TX_GAS_LIMIT).0
+    }
 
+    /// Validate the IBC tx with the gas limit. Returns the result and the gas
+    /// consumed by the VP.
+    fn validate_ibc_tx_with_gas_limit(
+        state: &TestState,
+        msg: Any,
+        keys_changed: &BTreeSet<Key>,
+        gas_limit: u64,
+    ) -> (VpResult<bool>, Gas) {
         let tx_index = TxIndex::default();
         let tx_code = vec![];
         let mut tx_data = vec![];
-        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+        msg.encode(&mut tx_data).expect("encoding failed");
 
         let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
         tx.add_code(tx_code, None)
@@ -2076,7 +4485,7 @@ mod tests {
             .sign_wrapper(keypair_1());
 
         let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
-            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+            &TxGasMeter::new_from_sub_limit(gas_limit.into()),
         ));
         let (vp_wasm_cache, _vp_cache_dir) =
             wasm::compilation_cache::common::testing::cache();
@@ -2085,159 +4494,186 @@ mod tests {
         let sentinel = RefCell::new(VpSentinel::default());
         let ctx = Ctx::new(
             &ADDRESS,
-            &state,
+            state,
             &tx,
             &tx_index,
             &gas_meter,
             &sentinel,
-            &keys_changed,
+            keys_changed,
             &verifiers,
             vp_wasm_cache,
         );
         let ibc = Ibc { ctx };
-        assert!(
-            ibc.validate_tx(&tx, &keys_changed, &verifiers)
-                .expect("validation failed")
-        );
+        let result = ibc.validate_tx(&tx, keys_changed, &verifiers);
+        let consumed_gas = gas_meter.borrow().get_vp_consumed_gas();
+        (result, consumed_gas)
     }
 
     #[test]
-    fn test_recv_packet() {
+    fn test_close_init_channel() {
         let mut keys_changed = BTreeSet::new();
         let mut state = init_storage();
         insert_init_client(&mut state);
+        let port_id = get_unbound_port_id();
+        insert_open_channel(
+            &mut state,
+            &port_id,
+            get_unbound_port_channel(ChanState::Open),
+        );
 
-        // insert an open connection
-        let conn_key = connection_key(&get_connection_id());
-        let conn = get_connection(ConnState::Open);
-        let bytes = conn.encode_vec();
-        state
-            .write_log_mut()
-            .write(&conn_key, bytes)
-            .expect("write failed");
-        // insert an open channel
-        let channel_key = channel_key(&get_port_id(), &get_channel_id());
-        let channel = get_channel(ChanState::Open, Order::Unordered);
+        // prepare data
+        let msg = MsgChannelCloseInit {
+            port_id_on_a: port_id.clone(),
+            chan_id_on_a: get_channel_id(),
+            signer: "account0".to_string().into(),
+        };
+
+        // update the channel to Closed
+        let channel_key = channel_key(&port_id, &get_channel_id());
+        let channel = get_unbound_port_channel(ChanState::Closed);
         let bytes = channel.encode_vec();
         state
             .write_log_mut()
             .write(&channel_key, bytes)
             .expect("write failed");
-        state.write_log_mut().commit_tx();
-        state.commit_block().expect("commit failed");
-        // for next block
+        keys_changed.insert(channel_key);
+        // event
+        let event = RawIbcEvent::CloseInitChannel(ChanCloseInit::new(
+            msg.port_id_on_a.clone(),
+            msg.chan_id_on_a.clone(),
+            get_unbound_port_id(),
+            ChannelId::new(0),
+            get_connection_id(),
+        ));
+        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
         state
-            .in_mem_mut()
-            .set_header(get_dummy_header())
-            .expect("Setting a dummy header shouldn't fail");
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
         state
-            .in_mem_mut()
-            .begin_block(BlockHash::default(), BlockHeight(2))
-            .unwrap();
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        assert!(
+            validate_ibc_tx(&state, msg.to_any(), &keys_changed)
+                .expect("validation failed")
+        );
+    }
+
+    #[test]
+    fn test_close_init_transfer_channel_fail() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        insert_open_channel(
+            &mut state,
+            &get_port_id(),
+            get_channel(ChanState::Open, Order::Unordered),
+        );
 
         // prepare data
-        let sender = established_address_1();
-        let receiver = established_address_2();
-        let transfer_msg = MsgTransfer {
+        let msg = MsgChannelCloseInit {
             port_id_on_a: get_port_id(),
             chan_id_on_a: get_channel_id(),
-            packet_data: PacketData {
-                token: PrefixedCoin {
-                    denom: nam().to_string().parse().unwrap(),
-                    amount: 100u64.into(),
-                },
-                sender: sender.to_string().into(),
-                receiver: receiver.to_string().into(),
-                memo: "memo".to_string().into(),
-            },
-            timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
-            timeout_timestamp_on_b: Timestamp::none(),
-        };
-        let counterparty = get_channel_counterparty();
-        let mut packet =
-            packet_from_message(&transfer_msg, 1.into(), &counterparty);
-        packet.port_id_on_a = counterparty.port_id().clone();
-        packet.chan_id_on_a = counterparty.channel_id().cloned().unwrap();
-        packet.port_id_on_b = get_port_id();
-        packet.chan_id_on_b = get_channel_id();
-        let msg = MsgRecvPacket {
-            packet: packet.clone(),
-            proof_commitment_on_a: dummy_proof(),
-            proof_height_on_a: Height::new(0, 1).unwrap(),
             signer: "account0".to_string().into(),
         };
 
-        // the sequence send
-        let receipt_key = receipt_key(
-            &msg.packet.port_id_on_b,
-            &msg.packet.chan_id_on_b,
-            msg.packet.seq_on_a,
-        );
-        let bytes = [1_u8].to_vec();
+        // update the channel to Closed
+        let channel_key = channel_key(&get_port_id(), &get_channel_id());
+        let channel = get_channel(ChanState::Closed, Order::Unordered);
+        let bytes = channel.encode_vec();
         state
             .write_log_mut()
-            .write(&receipt_key, bytes)
+            .write(&channel_key, bytes)
             .expect("write failed");
-        keys_changed.insert(receipt_key);
-        // packet commitment
-        let ack_key = ack_key(
-            &packet.port_id_on_b,
-            &packet.chan_id_on_b,
-            msg.packet.seq_on_a,
+        keys_changed.insert(channel_key);
+
+        // the transfer channel can't be closed
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(matches!(result, Err(Error::IbcAction(_))));
+    }
+
+    #[test]
+    fn test_close_confirm_channel() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        let port_id = get_unbound_port_id();
+        insert_open_channel(
+            &mut state,
+            &port_id,
+            get_unbound_port_channel(ChanState::Open),
         );
-        let transfer_ack = AcknowledgementStatus::success(ack_success_b64());
-        let acknowledgement: Acknowledgement = transfer_ack.into();
-        let bytes = sha2::Sha256::digest(acknowledgement.as_bytes()).to_vec();
+
+        // prepare data
+        let proof_height = Height::new(0, 1).unwrap();
+        let msg = MsgChannelCloseConfirm {
+            port_id_on_b: port_id.clone(),
+            chan_id_on_b: get_channel_id(),
+            proof_chan_end_on_a: dummy_proof(),
+            proof_height_on_a: proof_height,
+            signer: "account0".to_string().into(),
+        };
+
+        // update the channel to Closed
+        let channel_key = channel_key(&port_id, &get_channel_id());
+        let channel = get_unbound_port_channel(ChanState::Closed);
+        let bytes = channel.encode_vec();
         state
             .write_log_mut()
-            .write(&ack_key, bytes)
+            .write(&channel_key, bytes)
             .expect("write failed");
-        keys_changed.insert(ack_key);
-        // denom
-        let mut coin = transfer_msg.packet_data.token;
-        coin.denom.add_trace_prefix(TracePrefix::new(
-            packet.port_id_on_b.clone(),
-            packet.chan_id_on_b.clone(),
+        keys_changed.insert(channel_key);
+        // event
+        let event = RawIbcEvent::CloseConfirmChannel(ChanCloseConfirm::new(
+            msg.port_id_on_b.clone(),
+            msg.chan_id_on_b.clone(),
+            get_unbound_port_id(),
+            ChannelId::new(0),
+            get_connection_id(),
         ));
-        let trace_hash = calc_hash(coin.denom.to_string());
-        let denom_key = ibc_denom_key(receiver.to_string(), &trace_hash);
-        let bytes = coin.denom.to_string().serialize_to_vec();
-        state
-            .write_log_mut()
-            .write(&denom_key, bytes)
-            .expect("write failed");
-        keys_changed.insert(denom_key);
-        let denom_key = ibc_denom_key(nam().to_string(), &trace_hash);
-        let bytes = coin.denom.to_string().serialize_to_vec();
+        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
         state
             .write_log_mut()
-            .write(&denom_key, bytes)
-            .expect("write failed");
-        keys_changed.insert(denom_key);
-        // event
-        let recv_event = RecvEvent {
-            sender: sender.to_string().into(),
-            receiver: receiver.to_string().into(),
-            denom: nam().to_string().parse().unwrap(),
-            amount: 100u64.into(),
-            memo: "memo".to_string().into(),
-            success: true,
-        };
-        let event = RawIbcEvent::Module(ModuleEvent::from(recv_event));
+            .emit_ibc_event(message_event.try_into().unwrap());
         state
             .write_log_mut()
             .emit_ibc_event(event.try_into().unwrap());
-        let denom_trace_event = DenomTraceEvent {
-            trace_hash: Some(trace_hash),
-            denom: coin.denom,
+
+        assert!(
+            validate_ibc_tx(&state, msg.to_any(), &keys_changed)
+                .expect("validation failed")
+        );
+    }
+
+    /// Prepare a closure of a channel on an unbound port with the signer.
+    /// Returns the message and the changed keys.
+    fn prepare_close_init_channel(
+        state: &mut TestState,
+        signer: String,
+    ) -> (Any, BTreeSet<Key>) {
+        insert_init_client(state);
+        let port_id = get_unbound_port_id();
+        insert_open_channel(
+            state,
+            &port_id,
+            get_unbound_port_channel(ChanState::Open),
+        );
+        let msg = MsgChannelCloseInit {
+            port_id_on_a: port_id.clone(),
+            chan_id_on_a: get_channel_id(),
+            signer: signer.into(),
         };
-        let event = RawIbcEvent::Module(ModuleEvent::from(denom_trace_event));
+        let channel_key = channel_key(&port_id, &get_channel_id());
+        let channel = get_unbound_port_channel(ChanState::Closed);
         state
             .write_log_mut()
-            .emit_ibc_event(event.try_into().unwrap());
-        let event = RawIbcEvent::ReceivePacket(ReceivePacket::new(
-            msg.packet.clone(),
-            Order::Unordered,
+            .write(&channel_key, channel.encode_vec())
+            .expect("write failed");
+        let event = RawIbcEvent::CloseInitChannel(ChanCloseInit::new(
+            msg.port_id_on_a.clone(),
+            msg.chan_id_on_a.clone(),
+            get_unbound_port_id(),
+            ChannelId::new(0),
             get_connection_id(),
         ));
         let message_event = RawIbcEvent::Message(MessageEvent::Channel);
@@ -2247,58 +4683,280 @@ mod tests {
         state
             .write_log_mut()
             .emit_ibc_event(event.try_into().unwrap());
-        let event =
-            RawIbcEvent::WriteAcknowledgement(WriteAcknowledgement::new(
-                packet,
-                acknowledgement,
-                get_connection_id(),
-            ));
-        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
-        state
-            .write_log_mut()
-            .emit_ibc_event(message_event.try_into().unwrap());
-        state
-            .write_log_mut()
-            .emit_ibc_event(event.try_into().unwrap());
+        (msg.to_any(), BTreeSet::from([channel_key]))
+    }
 
-        let tx_index = TxIndex::default();
-        let tx_code = vec![];
-        let mut tx_data = vec![];
-        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+    #[test]
+    fn test_proof_specs() {
+        // the memoized specs are the ones of the storage hasher
+        let expected: ProofSpecs =
+            namada_state::ics23_specs::ibc_proof_specs::<Sha256Hasher>().into();
+        let specs = ibc_proof_specs::<Sha256Hasher>();
+        assert_eq!(specs, expected);
+        // and they're reused by the next IBC txs
+        let specs = ibc_proof_specs::<Sha256Hasher>();
+        assert_eq!(specs, expected);
+
+        // the validation of an IBC tx charges the same gas every time
+        let mut state = init_storage();
+        let (msg, keys_changed) =
+            prepare_close_init_channel(&mut state, "account0".to_string());
+        let (result, gas) = validate_ibc_tx_with_gas_limit(
+            &state,
+            msg.clone(),
+            &keys_changed,
+            TX_GAS_LIMIT,
+        );
+        assert!(result.expect("validation failed"));
+        let (result, next_gas) = validate_ibc_tx_with_gas_limit(
+            &state,
+            msg,
+            &keys_changed,
+            TX_GAS_LIMIT,
+        );
+        assert!(result.expect("validation failed"));
+        assert_eq!(next_gas, gas);
+    }
 
-        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
-        tx.add_code(tx_code, None)
-            .add_serialized_data(tx_data)
-            .sign_wrapper(keypair_1());
+    #[test]
+    fn test_unbonding_period() {
+        let unbonding_period = |ibc_params: Option<IbcParameters>| {
+            let mut state = init_storage();
+            let pos_params = namada_proof_of_stake::OwnedPosParams {
+                pipeline_len: 2,
+                unbonding_len: 6,
+                cubic_slashing_window_length: 2,
+                ..Default::default()
+            };
+            namada_proof_of_stake::storage::write_pos_params(
+                &mut state,
+                &pos_params,
+            )
+            .expect("write failed");
+            if let Some(ibc_params) = ibc_params {
+                ibc_params.init_storage(&mut state).expect("write failed");
+            }
+            state.write_log_mut().commit_tx();
 
-        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
-            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
-        ));
-        let (vp_wasm_cache, _vp_cache_dir) =
-            wasm::compilation_cache::common::testing::cache();
+            let tx = Tx::new(state.in_mem().chain_id.clone(), None);
+            let tx_index = TxIndex::default();
+            let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+                &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+            ));
+            let (vp_wasm_cache, _vp_cache_dir) =
+                wasm::compilation_cache::common::testing::cache();
+            let keys_changed = BTreeSet::new();
+            let verifiers = BTreeSet::new();
+            let sentinel = RefCell::new(VpSentinel::default());
+            let ctx = Ctx::new(
+                &ADDRESS,
+                &state,
+                &tx,
+                &tx_index,
+                &gas_meter,
+                &sentinel,
+                &keys_changed,
+                &verifiers,
+                vp_wasm_cache,
+            );
+            let ibc = Ibc { ctx };
+            ibc.validation_params()
+                .expect("getting params failed")
+                .unbonding_period
+        };
 
-        let verifiers = BTreeSet::new();
-        let sentinel = RefCell::new(VpSentinel::default());
-        let ctx = Ctx::new(
-            &ADDRESS,
+        // the unbonding and the cubic slashing window of 8 epochs of 100
+        // seconds
+        assert_eq!(unbonding_period(None), Duration::from_secs(800));
+
+        // the fixed period takes precedence
+        let ibc_params = IbcParameters {
+            unbonding_period_secs: Some(1_209_600),
+            ..Default::default()
+        };
+        assert_eq!(
+            unbonding_period(Some(ibc_params)),
+            Duration::from_secs(1_209_600)
+        );
+    }
+
+    #[test]
+    fn test_unbonding_epochs_overflow() {
+        let mut pos_params = PosParams::default();
+        pos_params.owned.unbonding_len = u64::MAX;
+        pos_params.owned.cubic_slashing_window_length = 1;
+        assert!(unbonding_epochs(&pos_params).is_err());
+
+        pos_params.owned.unbonding_len = 6;
+        pos_params.owned.cubic_slashing_window_length = 2;
+        assert_eq!(unbonding_epochs(&pos_params).unwrap(), 8);
+    }
+
+    #[test]
+    fn test_gas_scales_with_message_size() {
+        let mut state = init_storage();
+        let (small_msg, keys_changed) =
+            prepare_close_init_channel(&mut state, "account0".to_string());
+        let (result, small_gas) = validate_ibc_tx_with_gas_limit(
             &state,
-            &tx,
-            &tx_index,
-            &gas_meter,
-            &sentinel,
+            small_msg.clone(),
             &keys_changed,
-            &verifiers,
-            vp_wasm_cache,
+            TX_GAS_LIMIT,
         );
-        let ibc = Ibc { ctx };
-        assert!(
-            ibc.validate_tx(&tx, &keys_changed, &verifiers)
-                .expect("validation failed")
+        assert!(result.expect("validation failed"));
+
+        let mut state = init_storage();
+        let (large_msg, keys_changed) =
+            prepare_close_init_channel(&mut state, "a".repeat(10_000));
+        let (result, large_gas) = validate_ibc_tx_with_gas_limit(
+            &state,
+            large_msg.clone(),
+            &keys_changed,
+            TX_GAS_LIMIT,
         );
+        assert!(result.expect("validation failed"));
+
+        let extra_bytes = (large_msg.encode_to_vec().len()
+            - small_msg.encode_to_vec().len()) as u64;
+        let extra_gas = extra_bytes
+            * (IBC_ACTION_EXECUTE_GAS_PER_BYTE
+                + IBC_ACTION_VALIDATE_GAS_PER_BYTE);
+        assert_eq!(large_gas, small_gas.checked_add(extra_gas.into()).unwrap());
     }
 
     #[test]
-    fn test_ack_packet() {
+    fn test_out_of_gas() {
+        let mut state = init_storage();
+        let (msg, mut keys_changed) =
+            prepare_close_init_channel(&mut state, "account0".to_string());
+        // an unexpected change which would be rejected by the state
+        // validation
+        let unexpected_key = client_counter_key();
+        state
+            .write_log_mut()
+            .write(&unexpected_key, 100_u64.to_be_bytes().to_vec())
+            .expect("write failed");
+        keys_changed.insert(unexpected_key);
+        let result = validate_ibc_tx(&state, msg.clone(), &keys_changed);
+        assert!(matches!(result, Err(Error::Rejected(_))));
+
+        // the gas runs out before the state validation
+        let (result, _) = validate_ibc_tx_with_gas_limit(
+            &state,
+            msg,
+            &keys_changed,
+            IBC_ACTION_EXECUTE_GAS,
+        );
+        match &result {
+            Err(Error::NativeVpError(err)) => {
+                assert!(err.to_string().contains("Gas limit exceeded"))
+            }
+            _ => panic!("Unexpected result {result:?}"),
+        }
+    }
+
+    /// Validate closing a channel with the given IBC size limits. Returns the
+    /// result and the gas consumed by the VP.
+    fn validate_close_init_channel_with_limits(
+        max_ibc_keys_per_tx: u64,
+        max_tx_data_len: u64,
+    ) -> (VpResult<bool>, Gas) {
+        let mut state = init_storage();
+        let params = IbcParameters {
+            max_ibc_keys_per_tx,
+            max_tx_data_len,
+            ..Default::default()
+        };
+        params.init_storage(&mut state).expect("write failed");
+        state.write_log_mut().commit_tx();
+        let (msg, keys_changed) =
+            prepare_close_init_channel(&mut state, "account0".to_string());
+        validate_ibc_tx_with_gas_limit(&state, msg, &keys_changed, TX_GAS_LIMIT)
+    }
+
+    #[test]
+    fn test_tx_size_limits() {
+        let (msg, keys_changed) = prepare_close_init_channel(
+            &mut init_storage(),
+            "account0".to_string(),
+        );
+        let num_keys = keys_changed.len() as u64;
+        let data_len = msg.encode_to_vec().len() as u64;
+
+        // the tx at the boundary of the limits
+        let (result, _) =
+            validate_close_init_channel_with_limits(num_keys, data_len);
+        assert!(result.expect("validation failed"));
+
+        // too many changed keys
+        let (result, gas) =
+            validate_close_init_channel_with_limits(num_keys - 1, data_len);
+        assert!(matches!(
+            result,
+            Err(Error::TooLarge {
+                what: "changed IBC keys",
+                size,
+                limit,
+            }) if size == num_keys && limit == num_keys - 1
+        ));
+        // rejected before the execution
+        assert!(gas < IBC_ACTION_EXECUTE_GAS.into());
+
+        // too large tx data
+        let (result, gas) =
+            validate_close_init_channel_with_limits(num_keys, data_len - 1);
+        assert!(matches!(
+            result,
+            Err(Error::TooLarge {
+                what: "bytes of the tx data",
+                size,
+                limit,
+            }) if size == data_len && limit == data_len - 1
+        ));
+        assert!(gas < IBC_ACTION_EXECUTE_GAS.into());
+    }
+
+    #[test]
+    fn test_unsupported_message() {
+        let state = init_storage();
+        let type_url = "/ibc.core.channel.v1.MsgChannelUpgradeInit";
+        let msg = Any {
+            type_url: type_url.to_string(),
+            value: vec![0x0a, 0x08, 0x74, 0x72, 0x61, 0x6e, 0x73],
+        };
+
+        let (result, gas) = validate_ibc_tx_with_gas_limit(
+            &state,
+            msg,
+            &BTreeSet::new(),
+            TX_GAS_LIMIT,
+        );
+        match result {
+            Err(Error::IbcAction(ActionError::UnsupportedMessage(url))) => {
+                assert_eq!(url, type_url)
+            }
+            _ => panic!("unexpected result: {result:?}"),
+        }
+        // refused before the execution
+        assert!(gas < IBC_ACTION_EXECUTE_GAS.into());
+    }
+
+    /// Validate a transfer of 100 NAM sending a packet. The given function
+    /// can update the storage before the block of the transfer.
+    fn validate_send_packet(
+        update_storage: impl FnOnce(&mut TestState),
+    ) -> VpResult<bool> {
+        // the sender has authorized the transfer
+        let verifiers = BTreeSet::from([established_address_1()]);
+        validate_send_packet_with_verifiers(update_storage, verifiers)
+    }
+
+    /// Validate the transfer from `established_address_1` with the given
+    /// verifiers of the tx
+    fn validate_send_packet_with_verifiers(
+        update_storage: impl FnOnce(&mut TestState),
+        verifiers: BTreeSet<Address>,
+    ) -> VpResult<bool> {
         let mut keys_changed = BTreeSet::new();
         let mut state = init_storage();
         insert_init_client(&mut state);
@@ -2319,9 +4977,29 @@ mod tests {
             .write_log_mut()
             .write(&channel_key, bytes)
             .expect("write failed");
-        // commitment
+        // init balance
         let sender = established_address_1();
-        let transfer_msg = MsgTransfer {
+        let balance_key = balance_key(&nam(), &sender);
+        let amount = Amount::native_whole(100);
+        state
+            .write_log_mut()
+            .write(&balance_key, amount.serialize_to_vec())
+            .expect("write failed");
+        update_storage(&mut state);
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        // for next block
+        state
+            .in_mem_mut()
+            .set_header(get_dummy_header())
+            .expect("Setting a dummy header shouldn't fail");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        // prepare data
+        let msg = MsgTransfer {
             port_id_on_a: get_port_id(),
             chan_id_on_a: get_channel_id(),
             packet_data: PacketData {
@@ -2336,67 +5014,67 @@ mod tests {
             timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
             timeout_timestamp_on_b: Timestamp::none(),
         };
-        let sequence = 1.into();
-        let packet = packet_from_message(
-            &transfer_msg,
-            sequence,
-            &get_channel_counterparty(),
-        );
-        let commitment_key = commitment_key(
-            &transfer_msg.port_id_on_a,
-            &transfer_msg.chan_id_on_a,
-            sequence,
-        );
+
+        // the sequence send
+        let seq_key = next_sequence_send_key(&get_port_id(), &get_channel_id());
+        let sequence = get_next_seq(&state, &seq_key);
+        state
+            .write_log_mut()
+            .write(
+                &seq_key,
+                U64Codec::BigEndian.encode(u64::from(sequence) + 1),
+            )
+            .expect("write failed");
+        keys_changed.insert(seq_key);
+        // packet commitment
+        let packet =
+            packet_from_message(&msg, sequence, &get_channel_counterparty());
+        let commitment_key =
+            commitment_key(&msg.port_id_on_a, &msg.chan_id_on_a, sequence);
         let commitment = commitment(&packet);
         let bytes = commitment.into_vec();
         state
             .write_log_mut()
             .write(&commitment_key, bytes)
             .expect("write failed");
-        state.write_log_mut().commit_tx();
-        state.commit_block().expect("commit failed");
-        // for next block
-        state
-            .in_mem_mut()
-            .set_header(get_dummy_header())
-            .expect("Setting a dummy header shouldn't fail");
-        state
-            .in_mem_mut()
-            .begin_block(BlockHash::default(), BlockHeight(2))
-            .unwrap();
-
-        // prepare data
-        let transfer_ack = AcknowledgementStatus::success(ack_success_b64());
-        let msg = MsgAcknowledgement {
-            packet: packet.clone(),
-            acknowledgement: transfer_ack.clone().into(),
-            proof_acked_on_b: dummy_proof(),
-            proof_height_on_b: Height::new(0, 1).unwrap(),
-            signer: "account0".to_string().into(),
+        keys_changed.insert(commitment_key);
+        // withdraw
+        let amount = Amount::from_u64(100);
+        add_amount(&mut state, &mut keys_changed, withdraw_key(&nam()), amount);
+        let withdraw_key =
+            withdraw_key_for_channel(&nam(), &get_port_id(), &get_channel_id());
+        add_amount(&mut state, &mut keys_changed, withdraw_key, amount);
+        // escrow
+        add_amount(
+            &mut state,
+            &mut keys_changed,
+            escrow_amount_key(&nam()),
+            amount,
+        );
+        let packet_escrow_key =
+            packet_escrow_key(&msg.port_id_on_a, &msg.chan_id_on_a, sequence);
+        let packet_escrow = PacketEscrow {
+            token: nam(),
+            amount,
         };
-
-        // delete the commitment
         state
             .write_log_mut()
-            .delete(&commitment_key)
-            .expect("delete failed");
-        keys_changed.insert(commitment_key);
+            .write(&packet_escrow_key, packet_escrow.serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(packet_escrow_key);
         // event
-        let data = serde_json::from_slice::<PacketData>(&packet.data)
-            .expect("decoding packet data failed");
-        let ack_event = AckEvent {
-            sender: data.sender,
-            receiver: data.receiver,
-            denom: data.token.denom,
-            amount: data.token.amount,
-            memo: data.memo,
-            acknowledgement: transfer_ack,
+        let transfer_event = TransferEvent {
+            sender: msg.packet_data.sender.clone(),
+            receiver: msg.packet_data.receiver.clone(),
+            amount: msg.packet_data.token.amount,
+            denom: msg.packet_data.token.denom.clone(),
+            memo: msg.packet_data.memo.clone(),
         };
-        let event = RawIbcEvent::Module(ModuleEvent::from(ack_event));
+        let event = RawIbcEvent::Module(ModuleEvent::from(transfer_event));
         state
             .write_log_mut()
             .emit_ibc_event(event.try_into().unwrap());
-        let event = RawIbcEvent::AcknowledgePacket(AcknowledgePacket::new(
+        let event = RawIbcEvent::SendPacket(SendPacket::new(
             packet,
             Order::Unordered,
             get_connection_id(),
@@ -2425,7 +5103,6 @@ mod tests {
         let (vp_wasm_cache, _vp_cache_dir) =
             wasm::compilation_cache::common::testing::cache();
 
-        let verifiers = BTreeSet::new();
         let sentinel = RefCell::new(VpSentinel::default());
         let ctx = Ctx::new(
             &ADDRESS,
@@ -2439,43 +5116,199 @@ mod tests {
             vp_wasm_cache,
         );
         let ibc = Ibc { ctx };
-        assert!(
-            ibc.validate_tx(&tx, &keys_changed, &verifiers)
-                .expect("validation failed")
+        ibc.validate_tx(&tx, &keys_changed, &verifiers)
+    }
+
+    #[test]
+    fn test_send_packet() {
+        assert!(validate_send_packet(|_| {}).expect("validation failed"));
+    }
+
+    #[test]
+    fn test_send_packet_unauthorized_sender() {
+        // the sender's VP hasn't run for the tx
+        let verifiers = BTreeSet::from([established_address_2()]);
+        let result = validate_send_packet_with_verifiers(|_| {}, verifiers);
+        assert!(matches!(result, Err(Error::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_send_packet_with_limit_override() {
+        let default_limit = |state: &mut TestState| {
+            let params = IbcParameters {
+                default_per_epoch_throughput_limit: Amount::from_u64(50),
+                ..Default::default()
+            };
+            params.init_storage(state).expect("write failed");
+        };
+
+        // the transfer exceeds the default throughput limit
+        let result = validate_send_packet(default_limit);
+        assert_eq!(
+            result.unwrap_err().reject_reason(),
+            Some(&VpRejectReason::ThroughputLimitExceeded {
+                token: nam(),
+                limit: Amount::from_u64(50),
+                throughput: Amount::from_u64(100),
+            })
         );
+
+        // the per-token override set by governance takes precedence
+        let result = validate_send_packet(|state| {
+            default_limit(state);
+            state
+                .write(&throughput_limit_key(&nam()), Amount::from_u64(100))
+                .expect("write failed");
+        });
+        assert!(result.expect("validation failed"));
     }
 
     #[test]
-    fn test_timeout_packet() {
-        let mut keys_changed = BTreeSet::new();
-        let mut state = init_storage();
-        insert_init_client(&mut state);
+    fn test_send_packet_with_memo_limit() {
+        let memo_limit = |state: &mut TestState| {
+            let params = IbcParameters {
+                max_memo_size: 2,
+                ..Default::default()
+            };
+            params.init_storage(state).expect("write failed");
+        };
 
-        // insert an open connection
-        let conn_key = connection_key(&get_connection_id());
-        let conn = get_connection(ConnState::Open);
-        let bytes = conn.encode_vec();
-        state
-            .write_log_mut()
-            .write(&conn_key, bytes)
-            .expect("write failed");
-        // insert an Open channel
-        let channel_key = channel_key(&get_port_id(), &get_channel_id());
-        let channel = get_channel(ChanState::Open, Order::Unordered);
-        let bytes = channel.encode_vec();
-        state
-            .write_log_mut()
-            .write(&channel_key, bytes)
-            .expect("write failed");
-        // init the escrow balance
-        let balance_key =
-            balance_key(&nam(), &Address::Internal(InternalAddress::Ibc));
-        let amount = Amount::native_whole(100);
-        state
-            .write_log_mut()
-            .write(&balance_key, amount.serialize_to_vec())
-            .expect("write failed");
-        // commitment
+        // the memo "memo" exceeds the max size
+        let result = validate_send_packet(memo_limit);
+        assert!(matches!(
+            result,
+            Err(Error::Rejected(VpRejectReason::MemoSizeExceeded {
+                size: 4,
+                max_size: 2,
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_send_packet_with_min_transfer_amount() {
+        let min_amount = |state: &mut TestState| {
+            let params = IbcParameters {
+                default_min_transfer_amount: Amount::native_whole(1000),
+                ..Default::default()
+            };
+            params.init_storage(state).expect("write failed");
+        };
+
+        // the transfer of 100 NAM is below the default min amount
+        let result = validate_send_packet(min_amount);
+        let err = result.unwrap_err();
+        assert!(matches!(
+            err.reject_reason(),
+            Some(VpRejectReason::TransferBelowMinAmount {
+                token,
+                min_amount,
+                ..
+            }) if *token == nam() && *min_amount == Amount::native_whole(1000)
+        ));
+        assert!(err.to_string().contains(&format!(
+            "Min transfer amount {}",
+            Amount::native_whole(1000)
+        )));
+
+        // the per-token override set by governance takes precedence
+        let result = validate_send_packet(|state| {
+            min_amount(state);
+            state
+                .write(&min_transfer_amount_key(&nam()), Amount::from_u64(100))
+                .expect("write failed");
+        });
+        assert!(result.expect("validation failed"));
+
+        // no min amount is enforced with the zero threshold
+        let result = validate_send_packet(|state| {
+            IbcParameters::default()
+                .init_storage(state)
+                .expect("write failed");
+        });
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_send_packet_with_channel_limit() {
+        // 30 NAM has been sent through another channel in the epoch
+        let other_channel_id = ChannelId::new(1);
+        let prior_transfer = |state: &mut TestState| {
+            let params = IbcParameters {
+                default_per_epoch_throughput_limit: Amount::from_u64(1000),
+                ..Default::default()
+            };
+            params.init_storage(state).expect("write failed");
+            let amount = Amount::from_u64(30);
+            state
+                .write(&withdraw_key(&nam()), amount)
+                .expect("write failed");
+            let key = withdraw_key_for_channel(
+                &nam(),
+                &get_port_id(),
+                &other_channel_id,
+            );
+            state.write(&key, amount).expect("write failed");
+        };
+        let set_channel_limit = |state: &mut TestState, channel_id| {
+            let key = throughput_limit_key_for_channel(
+                &nam(),
+                &get_port_id(),
+                channel_id,
+            );
+            state
+                .write(&key, Amount::from_u64(50))
+                .expect("write failed");
+        };
+
+        // the transfer of 100 NAM exceeds the limit of the channel, though
+        // the total throughput is under the token's limit
+        let result = validate_send_packet(|state| {
+            prior_transfer(state);
+            set_channel_limit(state, &get_channel_id());
+        });
+        assert_eq!(
+            result.unwrap_err().reject_reason(),
+            Some(&VpRejectReason::ChannelThroughputLimitExceeded {
+                token: nam(),
+                port_id: get_port_id(),
+                channel_id: get_channel_id(),
+                limit: Amount::from_u64(50),
+                throughput: Amount::from_u64(100),
+            })
+        );
+
+        // the limit of the other channel doesn't restrict this channel
+        let result = validate_send_packet(|state| {
+            prior_transfer(state);
+            set_channel_limit(state, &other_channel_id);
+        });
+        assert!(result.expect("validation failed"));
+    }
+
+    /// Write the changes and the events of receiving a transfer packet on the
+    /// open channel. Returns the message.
+    fn prepare_recv_packet(
+        state: &mut TestState,
+        keys_changed: &mut BTreeSet<Key>,
+    ) -> MsgRecvPacket {
+        prepare_recv_packet_on_channel(
+            state,
+            keys_changed,
+            Order::Unordered,
+            1.into(),
+        )
+    }
+
+    /// Write the changes of receiving the packet with the sequence on the
+    /// channel with the order
+    fn prepare_recv_packet_on_channel(
+        state: &mut TestState,
+        keys_changed: &mut BTreeSet<Key>,
+        order: Order,
+        sequence: Sequence,
+    ) -> MsgRecvPacket {
+        let sender = established_address_1();
+        let receiver = established_address_2();
         let transfer_msg = MsgTransfer {
             port_id_on_a: get_port_id(),
             chan_id_on_a: get_channel_id(),
@@ -2484,74 +5317,141 @@ mod tests {
                     denom: nam().to_string().parse().unwrap(),
                     amount: 100u64.into(),
                 },
-                sender: established_address_1().to_string().into(),
-                receiver: "receiver".to_string().into(),
+                sender: sender.to_string().into(),
+                receiver: receiver.to_string().into(),
                 memo: "memo".to_string().into(),
             },
-            timeout_height_on_b: TimeoutHeight::Never,
-            timeout_timestamp_on_b: (Timestamp::now() - Duration::new(10, 0))
-                .unwrap(),
+            timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
+            timeout_timestamp_on_b: Timestamp::none(),
         };
-        let sequence = 1.into();
-        let packet = packet_from_message(
-            &transfer_msg,
-            sequence,
-            &get_channel_counterparty(),
-        );
-        let commitment_key = commitment_key(
-            &transfer_msg.port_id_on_a,
-            &transfer_msg.chan_id_on_a,
-            sequence,
+        let counterparty = get_channel_counterparty();
+        let mut packet =
+            packet_from_message(&transfer_msg, sequence, &counterparty);
+        packet.port_id_on_a = counterparty.port_id().clone();
+        packet.chan_id_on_a = counterparty.channel_id().cloned().unwrap();
+        packet.port_id_on_b = get_port_id();
+        packet.chan_id_on_b = get_channel_id();
+        let msg = MsgRecvPacket {
+            packet: packet.clone(),
+            proof_commitment_on_a: dummy_proof(),
+            proof_height_on_a: Height::new(0, 1).unwrap(),
+            signer: "account0".to_string().into(),
+        };
+
+        if order == Order::Ordered {
+            // the next sequence to be received
+            let next_seq_recv_key = next_sequence_recv_key(
+                &msg.packet.port_id_on_b,
+                &msg.packet.chan_id_on_b,
+            );
+            let next_seq_recv =
+                get_next_seq(state, &next_seq_recv_key).increment();
+            state
+                .write_log_mut()
+                .write(
+                    &next_seq_recv_key,
+                    U64Codec::BigEndian.encode(next_seq_recv.into()),
+                )
+                .expect("write failed");
+            keys_changed.insert(next_seq_recv_key);
+        } else {
+            // the sequence send
+            let receipt_key = receipt_key(
+                &msg.packet.port_id_on_b,
+                &msg.packet.chan_id_on_b,
+                msg.packet.seq_on_a,
+            );
+            let bytes = [1_u8].to_vec();
+            state
+                .write_log_mut()
+                .write(&receipt_key, bytes)
+                .expect("write failed");
+            keys_changed.insert(receipt_key);
+        }
+        // packet commitment
+        let ack_key = ack_key(
+            &packet.port_id_on_b,
+            &packet.chan_id_on_b,
+            msg.packet.seq_on_a,
         );
-        let commitment = commitment(&packet);
-        let bytes = commitment.into_vec();
+        let transfer_ack = AcknowledgementStatus::success(ack_success_b64());
+        let acknowledgement: Acknowledgement = transfer_ack.into();
+        let bytes = sha2::Sha256::digest(acknowledgement.as_bytes()).to_vec();
         state
             .write_log_mut()
-            .write(&commitment_key, bytes)
+            .write(&ack_key, bytes)
             .expect("write failed");
-        state.write_log_mut().commit_tx();
-        state.commit_block().expect("commit failed");
-        // for next block
+        keys_changed.insert(ack_key);
+        // denom
+        let mut coin = transfer_msg.packet_data.token;
+        coin.denom.add_trace_prefix(TracePrefix::new(
+            packet.port_id_on_b.clone(),
+            packet.chan_id_on_b.clone(),
+        ));
+        let trace_hash = calc_hash(coin.denom.to_string());
+        let denom_key = ibc_denom_key(receiver.to_string(), &trace_hash);
+        let bytes = coin.denom.to_string().serialize_to_vec();
         state
-            .in_mem_mut()
-            .set_header(get_dummy_header())
-            .expect("Setting a dummy header shouldn't fail");
+            .write_log_mut()
+            .write(&denom_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(denom_key);
+        let denom_key = ibc_denom_key(nam().to_string(), &trace_hash);
+        let bytes = coin.denom.to_string().serialize_to_vec();
         state
-            .in_mem_mut()
-            .begin_block(BlockHash::default(), BlockHeight(2))
-            .unwrap();
-
-        // prepare data
-        let msg = MsgTimeout {
-            packet: packet.clone(),
-            next_seq_recv_on_b: sequence,
-            proof_unreceived_on_b: dummy_proof(),
-            proof_height_on_b: Height::new(0, 1).unwrap(),
-            signer: "account0".to_string().into(),
-        };
-
-        // delete the commitment
+            .write_log_mut()
+            .write(&denom_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(denom_key);
+        let ibc_token = ibc_token(coin.denom.to_string());
+        let denom_key = ibc_denom_by_token_key(&ibc_token);
+        let bytes = coin.denom.to_string().serialize_to_vec();
         state
             .write_log_mut()
-            .delete(&commitment_key)
-            .expect("delete failed");
-        keys_changed.insert(commitment_key);
+            .write(&denom_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(denom_key);
+        // mint and deposit
+        let mint_amount_key = mint_amount_key(&ibc_token);
+        state
+            .write_log_mut()
+            .write(&mint_amount_key, Amount::from_u64(100).serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(mint_amount_key);
+        // deposit
+        let amount = Amount::from_u64(100);
+        add_amount(state, keys_changed, deposit_key(&ibc_token), amount);
+        let deposit_key = deposit_key_for_channel(
+            &ibc_token,
+            &get_port_id(),
+            &get_channel_id(),
+        );
+        add_amount(state, keys_changed, deposit_key, amount);
         // event
-        let data = serde_json::from_slice::<PacketData>(&packet.data)
-            .expect("decoding packet data failed");
-        let timeout_event = TimeoutEvent {
-            refund_receiver: data.sender,
-            refund_denom: data.token.denom,
-            refund_amount: data.token.amount,
-            memo: data.memo,
+        let recv_event = RecvEvent {
+            sender: sender.to_string().into(),
+            receiver: receiver.to_string().into(),
+            denom: nam().to_string().parse().unwrap(),
+            amount: 100u64.into(),
+            memo: "memo".to_string().into(),
+            success: true,
         };
-        let event = RawIbcEvent::Module(ModuleEvent::from(timeout_event));
+        let event = RawIbcEvent::Module(ModuleEvent::from(recv_event));
         state
             .write_log_mut()
             .emit_ibc_event(event.try_into().unwrap());
-        let event = RawIbcEvent::TimeoutPacket(TimeoutPacket::new(
-            packet,
-            Order::Unordered,
+        let denom_trace_event = DenomTraceEvent {
+            trace_hash: Some(trace_hash),
+            denom: coin.denom,
+        };
+        let event = RawIbcEvent::Module(ModuleEvent::from(denom_trace_event));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        let event = RawIbcEvent::ReceivePacket(ReceivePacket::new(
+            msg.packet.clone(),
+            order,
+            get_connection_id(),
         ));
         let message_event = RawIbcEvent::Message(MessageEvent::Channel);
         state
@@ -2560,6 +5460,58 @@ mod tests {
         state
             .write_log_mut()
             .emit_ibc_event(event.try_into().unwrap());
+        let event =
+            RawIbcEvent::WriteAcknowledgement(WriteAcknowledgement::new(
+                packet,
+                acknowledgement,
+                get_connection_id(),
+            ));
+        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        msg
+    }
+
+    #[test]
+    fn test_recv_packet() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+
+        // insert an open connection
+        let conn_key = connection_key(&get_connection_id());
+        let conn = get_connection(ConnState::Open);
+        let bytes = conn.encode_vec();
+        state
+            .write_log_mut()
+            .write(&conn_key, bytes)
+            .expect("write failed");
+        // insert an open channel
+        let channel_key = channel_key(&get_port_id(), &get_channel_id());
+        let channel = get_channel(ChanState::Open, Order::Unordered);
+        let bytes = channel.encode_vec();
+        state
+            .write_log_mut()
+            .write(&channel_key, bytes)
+            .expect("write failed");
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        // for next block
+        state
+            .in_mem_mut()
+            .set_header(get_dummy_header())
+            .expect("Setting a dummy header shouldn't fail");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        let msg = prepare_recv_packet(&mut state, &mut keys_changed);
 
         let tx_index = TxIndex::default();
         let tx_code = vec![];
@@ -2598,7 +5550,7 @@ mod tests {
     }
 
     #[test]
-    fn test_timeout_on_close_packet() {
+    fn test_recv_packet_with_mismatching_denom_index() {
         let mut keys_changed = BTreeSet::new();
         let mut state = init_storage();
         insert_init_client(&mut state);
@@ -2611,7 +5563,7 @@ mod tests {
             .write_log_mut()
             .write(&conn_key, bytes)
             .expect("write failed");
-        // insert an Open channel
+        // insert an open channel
         let channel_key = channel_key(&get_port_id(), &get_channel_id());
         let channel = get_channel(ChanState::Open, Order::Unordered);
         let bytes = channel.encode_vec();
@@ -2619,48 +5571,6 @@ mod tests {
             .write_log_mut()
             .write(&channel_key, bytes)
             .expect("write failed");
-        // init the escrow balance
-        let balance_key =
-            balance_key(&nam(), &Address::Internal(InternalAddress::Ibc));
-        let amount = Amount::native_whole(100);
-        state
-            .write_log_mut()
-            .write(&balance_key, amount.serialize_to_vec())
-            .expect("write failed");
-        // commitment
-        let sender = established_address_1();
-        let transfer_msg = MsgTransfer {
-            port_id_on_a: get_port_id(),
-            chan_id_on_a: get_channel_id(),
-            packet_data: PacketData {
-                token: PrefixedCoin {
-                    denom: nam().to_string().parse().unwrap(),
-                    amount: 100u64.into(),
-                },
-                sender: sender.to_string().into(),
-                receiver: "receiver".to_string().into(),
-                memo: "memo".to_string().into(),
-            },
-            timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
-            timeout_timestamp_on_b: Timestamp::none(),
-        };
-        let sequence = 1.into();
-        let packet = packet_from_message(
-            &transfer_msg,
-            sequence,
-            &get_channel_counterparty(),
-        );
-        let commitment_key = commitment_key(
-            &transfer_msg.port_id_on_a,
-            &transfer_msg.chan_id_on_a,
-            sequence,
-        );
-        let commitment = commitment(&packet);
-        let bytes = commitment.into_vec();
-        state
-            .write_log_mut()
-            .write(&commitment_key, bytes)
-            .expect("write failed");
         state.write_log_mut().commit_tx();
         state.commit_block().expect("commit failed");
         // for next block
@@ -2673,46 +5583,14 @@ mod tests {
             .begin_block(BlockHash::default(), BlockHeight(2))
             .unwrap();
 
-        // prepare data
-        let msg = MsgTimeoutOnClose {
-            packet: packet.clone(),
-            next_seq_recv_on_b: sequence,
-            proof_unreceived_on_b: dummy_proof(),
-            proof_close_on_b: dummy_proof(),
-            proof_height_on_b: Height::new(0, 1).unwrap(),
-            signer: "account0".to_string().into(),
-        };
-
-        // delete the commitment
+        let msg = prepare_recv_packet(&mut state, &mut keys_changed);
+        // overwrite the denom of the received IBC token with another denom
+        let denom = format!("{}/{}/{}", get_port_id(), get_channel_id(), nam());
+        let denom_key = ibc_denom_by_token_key(&ibc_token(denom));
         state
             .write_log_mut()
-            .delete(&commitment_key)
-            .expect("delete failed");
-        keys_changed.insert(commitment_key);
-        // event
-        let data = serde_json::from_slice::<PacketData>(&packet.data)
-            .expect("decoding packet data failed");
-        let timeout_event = TimeoutEvent {
-            refund_receiver: data.sender,
-            refund_denom: data.token.denom,
-            refund_amount: data.token.amount,
-            memo: data.memo,
-        };
-        let event = RawIbcEvent::Module(ModuleEvent::from(timeout_event));
-        state
-            .write_log_mut()
-            .emit_ibc_event(event.try_into().unwrap());
-        let event = RawIbcEvent::TimeoutPacket(TimeoutPacket::new(
-            packet,
-            Order::Unordered,
-        ));
-        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
-        state
-            .write_log_mut()
-            .emit_ibc_event(message_event.try_into().unwrap());
-        state
-            .write_log_mut()
-            .emit_ibc_event(event.try_into().unwrap());
+            .write(&denom_key, "transfer/channel-9/uatom".serialize_to_vec())
+            .expect("write failed");
 
         let tx_index = TxIndex::default();
         let tx_code = vec![];
@@ -2744,9 +5622,2369 @@ mod tests {
             vp_wasm_cache,
         );
         let ibc = Ibc { ctx };
-        assert!(
-            ibc.validate_tx(&tx, &keys_changed, &verifiers)
-                .expect("validation failed")
-        );
+        assert!(ibc.validate_tx(&tx, &keys_changed, &verifiers).is_err());
+        // the denom doesn't derive the IBC token
+        let result = ibc.validate_denom(&keys_changed);
+        assert!(matches!(
+            result,
+            Err(Error::IbcAction(ActionError::Denom(_)))
+        ));
+    }
+
+    #[test]
+    fn test_recv_packet_on_frozen_client() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        // freeze the client
+        let client_state_key = client_state_key(&get_client_id());
+        let header = MockHeader {
+            height: Height::new(0, 1).unwrap(),
+            timestamp: Timestamp::now(),
+        };
+        let client_state =
+            MockClientState::new(header).with_frozen_height(Height::min(0));
+        let bytes = Protobuf::<Any>::encode_vec(client_state);
+        state
+            .write_log_mut()
+            .write(&client_state_key, bytes)
+            .expect("write failed");
+        let channel = get_channel(ChanState::Open, Order::Unordered);
+        insert_open_channel(&mut state, &get_port_id(), channel);
+
+        let msg = prepare_recv_packet(&mut state, &mut keys_changed);
+
+        // the packet on the frozen client should be rejected
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(matches!(
+            result,
+            Err(Error::IbcAction(ActionError::Context(_)))
+        ));
+    }
+
+    #[test]
+    fn test_recv_packet_on_expired_client() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        // the client hasn't been updated within the trusting period
+        let client_update_time_key =
+            client_update_timestamp_key(&get_client_id());
+        let expired_time = (TmTime::now()
+            - (MOCK_TRUSTING_PERIOD + std::time::Duration::new(100, 0)))
+        .unwrap();
+        state
+            .write_log_mut()
+            .write(&client_update_time_key, expired_time.encode_vec())
+            .expect("write failed");
+        let channel = get_channel(ChanState::Open, Order::Unordered);
+        insert_open_channel(&mut state, &get_port_id(), channel);
+
+        let msg = prepare_recv_packet(&mut state, &mut keys_changed);
+
+        // the packet on the expired client should be rejected
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        match result {
+            Err(Error::ClientExpired { client_id, .. }) => {
+                assert_eq!(client_id, get_client_id())
+            }
+            _ => panic!("unexpected result: {result:?}"),
+        }
+
+        // the update of the expired client is exempt from the expiry check
+        state.write_log_mut().drop_tx();
+        let mut keys_changed = BTreeSet::new();
+        let msg = write_update_client(&mut state, &mut keys_changed);
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(result.expect("validation failed"));
+        state.write_log_mut().commit_tx();
+
+        // the same packet should be received on the updated client
+        let mut keys_changed = BTreeSet::new();
+        let msg = prepare_recv_packet(&mut state, &mut keys_changed);
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_recv_packet_ordered() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        let channel = get_channel(ChanState::Open, Order::Ordered);
+        insert_open_channel(&mut state, &get_port_id(), channel);
+
+        let msg = prepare_recv_packet_on_channel(
+            &mut state,
+            &mut keys_changed,
+            Order::Ordered,
+            1.into(),
+        );
+        // the next sequence to be received advances instead of a receipt
+        let next_seq_recv_key =
+            next_sequence_recv_key(&get_port_id(), &get_channel_id());
+        assert_eq!(
+            read_sequence(&state, &next_seq_recv_key).expect("read failed"),
+            Some(2.into())
+        );
+        let receipt_key =
+            receipt_key(&get_port_id(), &get_channel_id(), 1.into());
+        assert!(!keys_changed.contains(&receipt_key));
+
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_recv_packet_ordered_out_of_order() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        let channel = get_channel(ChanState::Open, Order::Ordered);
+        insert_open_channel(&mut state, &get_port_id(), channel);
+
+        // the packet 2 is received before the packet 1
+        let msg = prepare_recv_packet_on_channel(
+            &mut state,
+            &mut keys_changed,
+            Order::Ordered,
+            2.into(),
+        );
+
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(matches!(
+            result,
+            Err(Error::Rejected(VpRejectReason::PacketSequenceMismatch {
+                sequence,
+                expected,
+                ..
+            })) if sequence == 2.into() && expected == 1.into()
+        ));
+    }
+
+    #[test]
+    fn test_recv_packet_with_memo_limit() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        let params = IbcParameters {
+            max_memo_size: 2,
+            ..Default::default()
+        };
+        params.init_storage(&mut state).expect("write failed");
+        let channel = get_channel(ChanState::Open, Order::Unordered);
+        insert_open_channel(&mut state, &get_port_id(), channel);
+
+        // prepare data
+        let transfer_msg = MsgTransfer {
+            port_id_on_a: get_port_id(),
+            chan_id_on_a: get_channel_id(),
+            packet_data: PacketData {
+                token: PrefixedCoin {
+                    denom: nam().to_string().parse().unwrap(),
+                    amount: 100u64.into(),
+                },
+                sender: established_address_1().to_string().into(),
+                receiver: established_address_2().to_string().into(),
+                memo: "memo".to_string().into(),
+            },
+            timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
+            timeout_timestamp_on_b: Timestamp::none(),
+        };
+        let counterparty = get_channel_counterparty();
+        let mut packet =
+            packet_from_message(&transfer_msg, 1.into(), &counterparty);
+        packet.port_id_on_a = counterparty.port_id().clone();
+        packet.chan_id_on_a = counterparty.channel_id().cloned().unwrap();
+        packet.port_id_on_b = get_port_id();
+        packet.chan_id_on_b = get_channel_id();
+        let msg = MsgRecvPacket {
+            packet: packet.clone(),
+            proof_commitment_on_a: dummy_proof(),
+            proof_height_on_a: Height::new(0, 1).unwrap(),
+            signer: "account0".to_string().into(),
+        };
+
+        // the receipt
+        let receipt_key = receipt_key(
+            &msg.packet.port_id_on_b,
+            &msg.packet.chan_id_on_b,
+            msg.packet.seq_on_a,
+        );
+        state
+            .write_log_mut()
+            .write(&receipt_key, [1_u8].to_vec())
+            .expect("write failed");
+        keys_changed.insert(receipt_key);
+        // the error acknowledgement without any token transfer
+        let ack_key = ack_key(
+            &packet.port_id_on_b,
+            &packet.chan_id_on_b,
+            msg.packet.seq_on_a,
+        );
+        let status = StatusValue::new(
+            "The memo exceeds the max size: Size 4, Max size 2",
+        )
+        .unwrap();
+        let acknowledgement: Acknowledgement =
+            AcknowledgementStatus::error(status).into();
+        let bytes = sha2::Sha256::digest(acknowledgement.as_bytes()).to_vec();
+        state
+            .write_log_mut()
+            .write(&ack_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(ack_key);
+        // event
+        let event = RawIbcEvent::ReceivePacket(ReceivePacket::new(
+            msg.packet.clone(),
+            Order::Unordered,
+            get_connection_id(),
+        ));
+        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        let event =
+            RawIbcEvent::WriteAcknowledgement(WriteAcknowledgement::new(
+                packet,
+                acknowledgement,
+                get_connection_id(),
+            ));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_recv_packet_with_blocked_trace() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        // block NAM received through the channel
+        let trace = format!("{}/{}/{}", get_port_id(), get_channel_id(), nam());
+        state
+            .write_log_mut()
+            .write(
+                &blocked_trace_key(calc_hash(&trace)),
+                trace.serialize_to_vec(),
+            )
+            .expect("write failed");
+        let channel = get_channel(ChanState::Open, Order::Unordered);
+        insert_open_channel(&mut state, &get_port_id(), channel);
+
+        // prepare data
+        let transfer_msg = MsgTransfer {
+            port_id_on_a: get_port_id(),
+            chan_id_on_a: get_channel_id(),
+            packet_data: PacketData {
+                token: PrefixedCoin {
+                    denom: nam().to_string().parse().unwrap(),
+                    amount: 100u64.into(),
+                },
+                sender: established_address_1().to_string().into(),
+                receiver: established_address_2().to_string().into(),
+                memo: "".to_string().into(),
+            },
+            timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
+            timeout_timestamp_on_b: Timestamp::none(),
+        };
+        let counterparty = get_channel_counterparty();
+        let mut packet =
+            packet_from_message(&transfer_msg, 1.into(), &counterparty);
+        packet.port_id_on_a = counterparty.port_id().clone();
+        packet.chan_id_on_a = counterparty.channel_id().cloned().unwrap();
+        packet.port_id_on_b = get_port_id();
+        packet.chan_id_on_b = get_channel_id();
+        let msg = MsgRecvPacket {
+            packet: packet.clone(),
+            proof_commitment_on_a: dummy_proof(),
+            proof_height_on_a: Height::new(0, 1).unwrap(),
+            signer: "account0".to_string().into(),
+        };
+
+        // the receipt
+        let receipt_key = receipt_key(
+            &msg.packet.port_id_on_b,
+            &msg.packet.chan_id_on_b,
+            msg.packet.seq_on_a,
+        );
+        state
+            .write_log_mut()
+            .write(&receipt_key, [1_u8].to_vec())
+            .expect("write failed");
+        keys_changed.insert(receipt_key);
+        // the error acknowledgement without any mint, balance or trace
+        let ack_key = ack_key(
+            &packet.port_id_on_b,
+            &packet.chan_id_on_b,
+            msg.packet.seq_on_a,
+        );
+        let status =
+            StatusValue::new(format!("The denom trace is blocked: {trace}"))
+                .unwrap();
+        let acknowledgement: Acknowledgement =
+            AcknowledgementStatus::error(status).into();
+        let bytes = sha2::Sha256::digest(acknowledgement.as_bytes()).to_vec();
+        state
+            .write_log_mut()
+            .write(&ack_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(ack_key);
+        // event
+        let event = RawIbcEvent::ReceivePacket(ReceivePacket::new(
+            msg.packet.clone(),
+            Order::Unordered,
+            get_connection_id(),
+        ));
+        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        let event =
+            RawIbcEvent::WriteAcknowledgement(WriteAcknowledgement::new(
+                packet,
+                acknowledgement,
+                get_connection_id(),
+            ));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_recv_packet_below_min_transfer_amount() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        // the min amount of NAM received through the channel
+        let trace = format!("{}/{}/{}", get_port_id(), get_channel_id(), nam());
+        let token = ibc_token(&trace);
+        let min_amount = Amount::from_u64(1000);
+        state
+            .write_log_mut()
+            .write(
+                &min_transfer_amount_key(&token),
+                min_amount.serialize_to_vec(),
+            )
+            .expect("write failed");
+        let channel = get_channel(ChanState::Open, Order::Unordered);
+        insert_open_channel(&mut state, &get_port_id(), channel);
+
+        // prepare data
+        let transfer_msg = MsgTransfer {
+            port_id_on_a: get_port_id(),
+            chan_id_on_a: get_channel_id(),
+            packet_data: PacketData {
+                token: PrefixedCoin {
+                    denom: nam().to_string().parse().unwrap(),
+                    amount: 100u64.into(),
+                },
+                sender: established_address_1().to_string().into(),
+                receiver: established_address_2().to_string().into(),
+                memo: "".to_string().into(),
+            },
+            timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
+            timeout_timestamp_on_b: Timestamp::none(),
+        };
+        let counterparty = get_channel_counterparty();
+        let mut packet =
+            packet_from_message(&transfer_msg, 1.into(), &counterparty);
+        packet.port_id_on_a = counterparty.port_id().clone();
+        packet.chan_id_on_a = counterparty.channel_id().cloned().unwrap();
+        packet.port_id_on_b = get_port_id();
+        packet.chan_id_on_b = get_channel_id();
+        let msg = MsgRecvPacket {
+            packet: packet.clone(),
+            proof_commitment_on_a: dummy_proof(),
+            proof_height_on_a: Height::new(0, 1).unwrap(),
+            signer: "account0".to_string().into(),
+        };
+
+        // the receipt
+        let receipt_key = receipt_key(
+            &msg.packet.port_id_on_b,
+            &msg.packet.chan_id_on_b,
+            msg.packet.seq_on_a,
+        );
+        state
+            .write_log_mut()
+            .write(&receipt_key, [1_u8].to_vec())
+            .expect("write failed");
+        keys_changed.insert(receipt_key);
+        // the error acknowledgement without any mint, balance or trace. The
+        // IBC token has no denomination.
+        let ack_key = ack_key(
+            &packet.port_id_on_b,
+            &packet.chan_id_on_b,
+            msg.packet.seq_on_a,
+        );
+        let status = StatusValue::new(format!(
+            "The transfer amount is below the min amount: Token {token}, \
+             Amount {}, Min amount {min_amount}",
+            Amount::from_u64(100)
+        ))
+        .unwrap();
+        let acknowledgement: Acknowledgement =
+            AcknowledgementStatus::error(status).into();
+        let bytes = sha2::Sha256::digest(acknowledgement.as_bytes()).to_vec();
+        state
+            .write_log_mut()
+            .write(&ack_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(ack_key);
+        // event
+        let event = RawIbcEvent::ReceivePacket(ReceivePacket::new(
+            msg.packet.clone(),
+            Order::Unordered,
+            get_connection_id(),
+        ));
+        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        let event =
+            RawIbcEvent::WriteAcknowledgement(WriteAcknowledgement::new(
+                packet,
+                acknowledgement,
+                get_connection_id(),
+            ));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(result.expect("validation failed"));
+    }
+
+    fn get_forward_channel_id() -> ChannelId {
+        ChannelId::new(1)
+    }
+
+    /// Initialize the storage with the packet forwarding enabled, an open
+    /// channel to receive a packet and another channel to forward the token
+    fn init_forward_storage(forward_channel_state: ChanState) -> TestState {
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        let params = IbcParameters {
+            pfm_enabled: true,
+            ..Default::default()
+        };
+        params.init_storage(&mut state).expect("write failed");
+        let channel_key =
+            channel_key(&get_port_id(), &get_forward_channel_id());
+        let channel = get_channel(forward_channel_state, Order::Unordered);
+        state
+            .write_log_mut()
+            .write(&channel_key, channel.encode_vec())
+            .expect("write failed");
+        let channel = get_channel(ChanState::Open, Order::Unordered);
+        insert_open_channel(&mut state, &get_port_id(), channel);
+        state
+    }
+
+    /// Make a message to receive NAM with the memo
+    fn forward_recv_packet(memo: &str) -> MsgRecvPacket {
+        let transfer_msg = MsgTransfer {
+            port_id_on_a: get_port_id(),
+            chan_id_on_a: get_channel_id(),
+            packet_data: PacketData {
+                token: PrefixedCoin {
+                    denom: nam().to_string().parse().unwrap(),
+                    amount: 100u64.into(),
+                },
+                sender: established_address_1().to_string().into(),
+                receiver: "intermediate".to_string().into(),
+                memo: memo.to_string().into(),
+            },
+            timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
+            timeout_timestamp_on_b: Timestamp::none(),
+        };
+        let counterparty = get_channel_counterparty();
+        let mut packet =
+            packet_from_message(&transfer_msg, 1.into(), &counterparty);
+        packet.port_id_on_a = counterparty.port_id().clone();
+        packet.chan_id_on_a = counterparty.channel_id().cloned().unwrap();
+        packet.port_id_on_b = get_port_id();
+        packet.chan_id_on_b = get_channel_id();
+        MsgRecvPacket {
+            packet,
+            proof_commitment_on_a: dummy_proof(),
+            proof_height_on_a: Height::new(0, 1).unwrap(),
+            signer: "account0".to_string().into(),
+        }
+    }
+
+    /// Write the receipt and the acknowledgement of the received packet with
+    /// the events. No acknowledgement is written if it's pending.
+    fn write_recv_packet(
+        state: &mut TestState,
+        keys_changed: &mut BTreeSet<Key>,
+        msg: &MsgRecvPacket,
+        acknowledgement: Option<Acknowledgement>,
+    ) {
+        let packet = &msg.packet;
+        let receipt_key = receipt_key(
+            &packet.port_id_on_b,
+            &packet.chan_id_on_b,
+            packet.seq_on_a,
+        );
+        state
+            .write_log_mut()
+            .write(&receipt_key, [1_u8].to_vec())
+            .expect("write failed");
+        keys_changed.insert(receipt_key);
+        if let Some(acknowledgement) = &acknowledgement {
+            let ack_key = ack_key(
+                &packet.port_id_on_b,
+                &packet.chan_id_on_b,
+                packet.seq_on_a,
+            );
+            let bytes =
+                sha2::Sha256::digest(acknowledgement.as_bytes()).to_vec();
+            state
+                .write_log_mut()
+                .write(&ack_key, bytes)
+                .expect("write failed");
+            keys_changed.insert(ack_key);
+        }
+        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
+        let event = RawIbcEvent::ReceivePacket(ReceivePacket::new(
+            packet.clone(),
+            Order::Unordered,
+            get_connection_id(),
+        ));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        if let Some(acknowledgement) = acknowledgement {
+            let event =
+                RawIbcEvent::WriteAcknowledgement(WriteAcknowledgement::new(
+                    packet.clone(),
+                    acknowledgement,
+                    get_connection_id(),
+                ));
+            state
+                .write_log_mut()
+                .emit_ibc_event(event.try_into().unwrap());
+        }
+    }
+
+    /// The memo to forward the received token over the forward channel
+    fn forward_memo() -> String {
+        serde_json::json!({
+            "forward": {
+                "receiver": "receiver",
+                "port": "transfer",
+                "channel": "channel-1",
+                "timeout": 100_000_000_000_u64,
+                "next": "next",
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_recv_packet_with_forward() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_forward_storage(ChanState::Open);
+        let memo = forward_memo();
+        let msg = forward_recv_packet(&memo);
+        let packet = &msg.packet;
+        let ibc_address = Address::Internal(InternalAddress::Ibc);
+
+        // the token is received to the IBC account and the acknowledgement is
+        // pending
+        write_recv_packet(&mut state, &mut keys_changed, &msg, None);
+        let mut coin = PrefixedCoin {
+            denom: nam().to_string().parse().unwrap(),
+            amount: 100u64.into(),
+        };
+        coin.denom.add_trace_prefix(TracePrefix::new(
+            packet.port_id_on_b.clone(),
+            packet.chan_id_on_b.clone(),
+        ));
+        let trace_hash = calc_hash(coin.denom.to_string());
+        for owner in [ibc_address.to_string(), nam().to_string()] {
+            let denom_key = ibc_denom_key(owner, &trace_hash);
+            state
+                .write_log_mut()
+                .write(&denom_key, coin.denom.to_string().serialize_to_vec())
+                .expect("write failed");
+            keys_changed.insert(denom_key);
+        }
+        let ibc_token = ibc_token(coin.denom.to_string());
+        let denom_key = ibc_denom_by_token_key(&ibc_token);
+        state
+            .write_log_mut()
+            .write(&denom_key, coin.denom.to_string().serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(denom_key);
+        let amount = Amount::from_u64(100);
+        add_amount(
+            &mut state,
+            &mut keys_changed,
+            mint_amount_key(&ibc_token),
+            amount,
+        );
+        add_amount(
+            &mut state,
+            &mut keys_changed,
+            deposit_key(&ibc_token),
+            amount,
+        );
+        // the token minted to the IBC account stays escrowed
+        add_amount(
+            &mut state,
+            &mut keys_changed,
+            escrow_amount_key(&ibc_token),
+            amount,
+        );
+        let deposit_key = deposit_key_for_channel(
+            &ibc_token,
+            &get_port_id(),
+            &get_channel_id(),
+        );
+        add_amount(&mut state, &mut keys_changed, deposit_key, amount);
+        let recv_event = RecvEvent {
+            sender: established_address_1().to_string().into(),
+            receiver: ibc_address.to_string().into(),
+            denom: nam().to_string().parse().unwrap(),
+            amount: 100u64.into(),
+            memo: memo.into(),
+            success: true,
+        };
+        let event = RawIbcEvent::Module(ModuleEvent::from(recv_event));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        let denom_trace_event = DenomTraceEvent {
+            trace_hash: Some(trace_hash),
+            denom: coin.denom.clone(),
+        };
+        let event = RawIbcEvent::Module(ModuleEvent::from(denom_trace_event));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        // the received token is sent onward over the forward channel
+        let time = StateRead::get_block_header(&state, None)
+            .unwrap()
+            .0
+            .unwrap()
+            .time;
+        let host_timestamp: Timestamp = TmTime::try_from(time).unwrap().into();
+        let forward_msg = MsgTransfer {
+            port_id_on_a: get_port_id(),
+            chan_id_on_a: get_forward_channel_id(),
+            packet_data: PacketData {
+                token: coin,
+                sender: ibc_address.to_string().into(),
+                receiver: "receiver".to_string().into(),
+                memo: "next".to_string().into(),
+            },
+            timeout_height_on_b: TimeoutHeight::Never,
+            timeout_timestamp_on_b: (host_timestamp + Duration::from_secs(100))
+                .unwrap(),
+        };
+        let seq_key =
+            next_sequence_send_key(&get_port_id(), &get_forward_channel_id());
+        let sequence = get_next_seq(&state, &seq_key);
+        state
+            .write_log_mut()
+            .write(
+                &seq_key,
+                U64Codec::BigEndian.encode(u64::from(sequence) + 1),
+            )
+            .expect("write failed");
+        keys_changed.insert(seq_key);
+        let forward_packet = packet_from_message(
+            &forward_msg,
+            sequence,
+            &get_channel_counterparty(),
+        );
+        let commitment_key = commitment_key(
+            &forward_msg.port_id_on_a,
+            &forward_msg.chan_id_on_a,
+            sequence,
+        );
+        state
+            .write_log_mut()
+            .write(&commitment_key, commitment(&forward_packet).into_vec())
+            .expect("write failed");
+        keys_changed.insert(commitment_key.clone());
+        // the received packet is acknowledged with the forward packet
+        let forwarded_packet_key = forwarded_packet_key(
+            &forward_msg.port_id_on_a,
+            &forward_msg.chan_id_on_a,
+            sequence,
+        );
+        state
+            .write_log_mut()
+            .write(&forwarded_packet_key, packet.encode_vec())
+            .expect("write failed");
+        keys_changed.insert(forwarded_packet_key);
+        // the token is escrowed by the IBC account itself
+        add_amount(
+            &mut state,
+            &mut keys_changed,
+            withdraw_key(&ibc_token),
+            amount,
+        );
+        let withdraw_key = withdraw_key_for_channel(
+            &ibc_token,
+            &get_port_id(),
+            &get_forward_channel_id(),
+        );
+        add_amount(&mut state, &mut keys_changed, withdraw_key, amount);
+        // the escrowed amount stays the minted one, but the escrow of the
+        // forward packet is recorded
+        let packet_escrow_key = packet_escrow_key(
+            &forward_msg.port_id_on_a,
+            &forward_msg.chan_id_on_a,
+            sequence,
+        );
+        let packet_escrow = PacketEscrow {
+            token: ibc_token.clone(),
+            amount,
+        };
+        state
+            .write_log_mut()
+            .write(&packet_escrow_key, packet_escrow.serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(packet_escrow_key);
+        let transfer_event = TransferEvent {
+            sender: forward_msg.packet_data.sender.clone(),
+            receiver: forward_msg.packet_data.receiver.clone(),
+            amount: forward_msg.packet_data.token.amount,
+            denom: forward_msg.packet_data.token.denom.clone(),
+            memo: forward_msg.packet_data.memo.clone(),
+        };
+        let event = RawIbcEvent::Module(ModuleEvent::from(transfer_event));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        let event = RawIbcEvent::SendPacket(SendPacket::new(
+            forward_packet,
+            Order::Unordered,
+            get_connection_id(),
+        ));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        // the receipt, the ack and the commitment of the chained send are
+        // validated together
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(result.expect("validation failed"));
+        let escrowed: Option<Amount> =
+            state.read(&escrow_amount_key(&ibc_token)).unwrap();
+        assert_eq!(escrowed, Some(amount));
+
+        // the VP rejects the receipt without the chained send
+        let mut state = init_forward_storage(ChanState::Open);
+        let mut keys_changed = BTreeSet::new();
+        write_recv_packet(&mut state, &mut keys_changed, &msg, None);
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        match result.unwrap_err().reject_reason() {
+            Some(VpRejectReason::KeyMismatch { missing, .. }) => {
+                assert!(missing.contains(&commitment_key))
+            }
+            reason => panic!("Unexpected reject reason {reason:?}"),
+        }
+    }
+
+    #[test]
+    fn test_recv_packet_with_invalid_forward() {
+        let mut keys_changed = BTreeSet::new();
+        // the forward channel isn't open yet
+        let mut state = init_forward_storage(ChanState::Init);
+        let memo = serde_json::json!({
+            "forward": {
+                "receiver": "receiver",
+                "port": "transfer",
+                "channel": "channel-1",
+            }
+        })
+        .to_string();
+        let msg = forward_recv_packet(&memo);
+
+        // the error acknowledgement without any token transfer
+        let status = StatusValue::new(
+            "The forward channel isn't open: Port ID transfer, Channel ID \
+             channel-1",
+        )
+        .unwrap();
+        let acknowledgement: Acknowledgement =
+            AcknowledgementStatus::error(status).into();
+        write_recv_packet(
+            &mut state,
+            &mut keys_changed,
+            &msg,
+            Some(acknowledgement),
+        );
+
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(result.expect("validation failed"));
+        // no onward send
+        let seq_key =
+            next_sequence_send_key(&get_port_id(), &get_forward_channel_id());
+        assert!(!keys_changed.contains(&seq_key));
+    }
+
+    #[test]
+    fn test_timeout_forwarded_packet() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_forward_storage(ChanState::Open);
+        let ibc_address = Address::Internal(InternalAddress::Ibc);
+
+        // the token has been received to the IBC account and sent onward
+        let received = forward_recv_packet(&forward_memo()).packet;
+        let mut coin = PrefixedCoin {
+            denom: nam().to_string().parse().unwrap(),
+            amount: 100u64.into(),
+        };
+        coin.denom.add_trace_prefix(TracePrefix::new(
+            received.port_id_on_b.clone(),
+            received.chan_id_on_b.clone(),
+        ));
+        let ibc_token = ibc_token(coin.denom.to_string());
+        let amount = Amount::from_u64(100);
+        for key in [
+            balance_key(&ibc_token, &ibc_address),
+            minted_balance_key(&ibc_token),
+            mint_amount_key(&ibc_token),
+            escrow_amount_key(&ibc_token),
+        ] {
+            state
+                .write_log_mut()
+                .write(&key, amount.serialize_to_vec())
+                .expect("write failed");
+        }
+        let forward_msg = MsgTransfer {
+            port_id_on_a: get_port_id(),
+            chan_id_on_a: get_forward_channel_id(),
+            packet_data: PacketData {
+                token: coin,
+                sender: ibc_address.to_string().into(),
+                receiver: "receiver".to_string().into(),
+                memo: "next".to_string().into(),
+            },
+            timeout_height_on_b: TimeoutHeight::Never,
+            timeout_timestamp_on_b: (Timestamp::now() - Duration::new(10, 0))
+                .unwrap(),
+        };
+        let sequence = 1.into();
+        let packet = packet_from_message(
+            &forward_msg,
+            sequence,
+            &get_channel_counterparty(),
+        );
+        let commitment_key = commitment_key(
+            &forward_msg.port_id_on_a,
+            &forward_msg.chan_id_on_a,
+            sequence,
+        );
+        state
+            .write_log_mut()
+            .write(&commitment_key, commitment(&packet).into_vec())
+            .expect("write failed");
+        let forwarded_packet_key = forwarded_packet_key(
+            &forward_msg.port_id_on_a,
+            &forward_msg.chan_id_on_a,
+            sequence,
+        );
+        state
+            .write_log_mut()
+            .write(&forwarded_packet_key, received.encode_vec())
+            .expect("write failed");
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        // for next block
+        state
+            .in_mem_mut()
+            .set_header(get_dummy_header())
+            .expect("Setting a dummy header shouldn't fail");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(3))
+            .unwrap();
+
+        // the forward packet is timed out
+        let msg = MsgTimeout {
+            packet: packet.clone(),
+            next_seq_recv_on_b: sequence,
+            proof_unreceived_on_b: dummy_proof(),
+            proof_height_on_b: Height::new(0, 1).unwrap(),
+            signer: "account0".to_string().into(),
+        };
+        state
+            .write_log_mut()
+            .delete(&commitment_key)
+            .expect("delete failed");
+        keys_changed.insert(commitment_key);
+        // the refund to the IBC account itself
+        add_amount(
+            &mut state,
+            &mut keys_changed,
+            deposit_key(&ibc_token),
+            amount,
+        );
+        let deposit_key = deposit_key_for_channel(
+            &ibc_token,
+            &get_port_id(),
+            &get_forward_channel_id(),
+        );
+        add_amount(&mut state, &mut keys_changed, deposit_key, amount);
+        // the received token is burned as it was minted
+        sub_amount(
+            &mut state,
+            &mut keys_changed,
+            mint_amount_key(&ibc_token),
+            amount,
+        );
+        add_amount(
+            &mut state,
+            &mut keys_changed,
+            withdraw_key(&ibc_token),
+            amount,
+        );
+        let withdraw_key = withdraw_key_for_channel(
+            &ibc_token,
+            &get_port_id(),
+            &get_channel_id(),
+        );
+        add_amount(&mut state, &mut keys_changed, withdraw_key, amount);
+        sub_amount(
+            &mut state,
+            &mut keys_changed,
+            escrow_amount_key(&ibc_token),
+            amount,
+        );
+        for key in [
+            balance_key(&ibc_token, &ibc_address),
+            minted_balance_key(&ibc_token),
+        ] {
+            state
+                .write_log_mut()
+                .write(&key, Amount::zero().serialize_to_vec())
+                .expect("write failed");
+            keys_changed.insert(key);
+        }
+        // the error acknowledgement of the received packet
+        state
+            .write_log_mut()
+            .delete(&forwarded_packet_key)
+            .expect("delete failed");
+        keys_changed.insert(forwarded_packet_key);
+        let status = StatusValue::new(
+            "The onward transfer failed: Port ID transfer, Channel ID \
+             channel-1, Sequence 1",
+        )
+        .unwrap();
+        let acknowledgement: Acknowledgement =
+            AcknowledgementStatus::error(status).into();
+        let ack_key = ack_key(
+            &received.port_id_on_b,
+            &received.chan_id_on_b,
+            received.seq_on_a,
+        );
+        let bytes = sha2::Sha256::digest(acknowledgement.as_bytes()).to_vec();
+        state
+            .write_log_mut()
+            .write(&ack_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(ack_key);
+        // events
+        let data = serde_json::from_slice::<PacketData>(&packet.data)
+            .expect("decoding packet data failed");
+        let timeout_event = TimeoutEvent {
+            refund_receiver: data.sender,
+            refund_denom: data.token.denom,
+            refund_amount: data.token.amount,
+            memo: data.memo,
+        };
+        let event = RawIbcEvent::Module(ModuleEvent::from(timeout_event));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.clone().try_into().unwrap());
+        let event = RawIbcEvent::TimeoutPacket(TimeoutPacket::new(
+            packet,
+            Order::Unordered,
+        ));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
+        let event =
+            RawIbcEvent::WriteAcknowledgement(WriteAcknowledgement::new(
+                received,
+                acknowledgement,
+                get_connection_id(),
+            ));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_ack_packet() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+
+        // insert an open connection
+        let conn_key = connection_key(&get_connection_id());
+        let conn = get_connection(ConnState::Open);
+        let bytes = conn.encode_vec();
+        state
+            .write_log_mut()
+            .write(&conn_key, bytes)
+            .expect("write failed");
+        // insert an Open channel
+        let channel_key = channel_key(&get_port_id(), &get_channel_id());
+        let channel = get_channel(ChanState::Open, Order::Unordered);
+        let bytes = channel.encode_vec();
+        state
+            .write_log_mut()
+            .write(&channel_key, bytes)
+            .expect("write failed");
+        // commitment
+        let sender = established_address_1();
+        let transfer_msg = MsgTransfer {
+            port_id_on_a: get_port_id(),
+            chan_id_on_a: get_channel_id(),
+            packet_data: PacketData {
+                token: PrefixedCoin {
+                    denom: nam().to_string().parse().unwrap(),
+                    amount: 100u64.into(),
+                },
+                sender: sender.to_string().into(),
+                receiver: "receiver".to_string().into(),
+                memo: "memo".to_string().into(),
+            },
+            timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
+            timeout_timestamp_on_b: Timestamp::none(),
+        };
+        let sequence = 1.into();
+        let packet = packet_from_message(
+            &transfer_msg,
+            sequence,
+            &get_channel_counterparty(),
+        );
+        let commitment_key = commitment_key(
+            &transfer_msg.port_id_on_a,
+            &transfer_msg.chan_id_on_a,
+            sequence,
+        );
+        let commitment = commitment(&packet);
+        let bytes = commitment.into_vec();
+        state
+            .write_log_mut()
+            .write(&commitment_key, bytes)
+            .expect("write failed");
+        insert_packet_escrow(&mut state, sequence, Amount::from_u64(100));
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        // for next block
+        state
+            .in_mem_mut()
+            .set_header(get_dummy_header())
+            .expect("Setting a dummy header shouldn't fail");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        // prepare data
+        let transfer_ack = AcknowledgementStatus::success(ack_success_b64());
+        let msg = MsgAcknowledgement {
+            packet: packet.clone(),
+            acknowledgement: transfer_ack.clone().into(),
+            proof_acked_on_b: dummy_proof(),
+            proof_height_on_b: Height::new(0, 1).unwrap(),
+            signer: "account0".to_string().into(),
+        };
+
+        // delete the commitment
+        state
+            .write_log_mut()
+            .delete(&commitment_key)
+            .expect("delete failed");
+        keys_changed.insert(commitment_key);
+        delete_packet_escrow(&mut state, &mut keys_changed, sequence);
+        // event
+        let data = serde_json::from_slice::<PacketData>(&packet.data)
+            .expect("decoding packet data failed");
+        let ack_event = AckEvent {
+            sender: data.sender,
+            receiver: data.receiver,
+            denom: data.token.denom,
+            amount: data.token.amount,
+            memo: data.memo,
+            acknowledgement: transfer_ack,
+        };
+        let event = RawIbcEvent::Module(ModuleEvent::from(ack_event));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        let event = RawIbcEvent::AcknowledgePacket(AcknowledgePacket::new(
+            packet,
+            Order::Unordered,
+            get_connection_id(),
+        ));
+        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        let tx_index = TxIndex::default();
+        let tx_code = vec![];
+        let mut tx_data = vec![];
+        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(tx_code, None)
+            .add_serialized_data(tx_data)
+            .sign_wrapper(keypair_1());
+
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+
+        let verifiers = BTreeSet::new();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let ibc = Ibc { ctx };
+        assert!(
+            ibc.validate_tx(&tx, &keys_changed, &verifiers)
+                .expect("validation failed")
+        );
+    }
+
+    #[test]
+    fn test_timeout_packet() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+
+        // insert an open connection
+        let conn_key = connection_key(&get_connection_id());
+        let conn = get_connection(ConnState::Open);
+        let bytes = conn.encode_vec();
+        state
+            .write_log_mut()
+            .write(&conn_key, bytes)
+            .expect("write failed");
+        // insert an Open channel
+        let channel_key = channel_key(&get_port_id(), &get_channel_id());
+        let channel = get_channel(ChanState::Open, Order::Unordered);
+        let bytes = channel.encode_vec();
+        state
+            .write_log_mut()
+            .write(&channel_key, bytes)
+            .expect("write failed");
+        // init the escrow balance
+        let balance_key =
+            balance_key(&nam(), &Address::Internal(InternalAddress::Ibc));
+        let amount = Amount::native_whole(100);
+        state
+            .write_log_mut()
+            .write(&balance_key, amount.serialize_to_vec())
+            .expect("write failed");
+        // commitment
+        let transfer_msg = MsgTransfer {
+            port_id_on_a: get_port_id(),
+            chan_id_on_a: get_channel_id(),
+            packet_data: PacketData {
+                token: PrefixedCoin {
+                    denom: nam().to_string().parse().unwrap(),
+                    amount: 100u64.into(),
+                },
+                sender: established_address_1().to_string().into(),
+                receiver: "receiver".to_string().into(),
+                memo: "memo".to_string().into(),
+            },
+            timeout_height_on_b: TimeoutHeight::Never,
+            timeout_timestamp_on_b: (Timestamp::now() - Duration::new(10, 0))
+                .unwrap(),
+        };
+        let sequence = 1.into();
+        let packet = packet_from_message(
+            &transfer_msg,
+            sequence,
+            &get_channel_counterparty(),
+        );
+        let commitment_key = commitment_key(
+            &transfer_msg.port_id_on_a,
+            &transfer_msg.chan_id_on_a,
+            sequence,
+        );
+        let commitment = commitment(&packet);
+        let bytes = commitment.into_vec();
+        state
+            .write_log_mut()
+            .write(&commitment_key, bytes)
+            .expect("write failed");
+        insert_packet_escrow(&mut state, sequence, Amount::from_u64(100));
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        // for next block
+        state
+            .in_mem_mut()
+            .set_header(get_dummy_header())
+            .expect("Setting a dummy header shouldn't fail");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        // prepare data
+        let msg = MsgTimeout {
+            packet: packet.clone(),
+            next_seq_recv_on_b: sequence,
+            proof_unreceived_on_b: dummy_proof(),
+            proof_height_on_b: Height::new(0, 1).unwrap(),
+            signer: "account0".to_string().into(),
+        };
+
+        // delete the commitment
+        state
+            .write_log_mut()
+            .delete(&commitment_key)
+            .expect("delete failed");
+        keys_changed.insert(commitment_key);
+        delete_packet_escrow(&mut state, &mut keys_changed, sequence);
+        // deposit
+        let amount = Amount::from_u64(100);
+        add_amount(&mut state, &mut keys_changed, deposit_key(&nam()), amount);
+        let deposit_key =
+            deposit_key_for_channel(&nam(), &get_port_id(), &get_channel_id());
+        add_amount(&mut state, &mut keys_changed, deposit_key, amount);
+        // unescrow
+        sub_amount(
+            &mut state,
+            &mut keys_changed,
+            escrow_amount_key(&nam()),
+            amount,
+        );
+        // event
+        let data = serde_json::from_slice::<PacketData>(&packet.data)
+            .expect("decoding packet data failed");
+        let timeout_event = TimeoutEvent {
+            refund_receiver: data.sender,
+            refund_denom: data.token.denom,
+            refund_amount: data.token.amount,
+            memo: data.memo,
+        };
+        let event = RawIbcEvent::Module(ModuleEvent::from(timeout_event));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        let event = RawIbcEvent::TimeoutPacket(TimeoutPacket::new(
+            packet,
+            Order::Unordered,
+        ));
+        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        let tx_index = TxIndex::default();
+        let tx_code = vec![];
+        let mut tx_data = vec![];
+        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(tx_code, None)
+            .add_serialized_data(tx_data)
+            .sign_wrapper(keypair_1());
+
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+
+        // the refund is submitted by a relayer without the sender as a verifier
+        let verifiers = BTreeSet::new();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let ibc = Ibc { ctx };
+        assert!(
+            ibc.validate_tx(&tx, &keys_changed, &verifiers)
+                .expect("validation failed")
+        );
+    }
+
+    #[test]
+    fn test_timeout_on_close_packet() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+
+        // insert an open connection
+        let conn_key = connection_key(&get_connection_id());
+        let conn = get_connection(ConnState::Open);
+        let bytes = conn.encode_vec();
+        state
+            .write_log_mut()
+            .write(&conn_key, bytes)
+            .expect("write failed");
+        // insert an Open channel
+        let channel_key = channel_key(&get_port_id(), &get_channel_id());
+        let channel = get_channel(ChanState::Open, Order::Unordered);
+        let bytes = channel.encode_vec();
+        state
+            .write_log_mut()
+            .write(&channel_key, bytes)
+            .expect("write failed");
+        // init the escrow balance
+        let balance_key =
+            balance_key(&nam(), &Address::Internal(InternalAddress::Ibc));
+        let amount = Amount::native_whole(100);
+        state
+            .write_log_mut()
+            .write(&balance_key, amount.serialize_to_vec())
+            .expect("write failed");
+        // commitment
+        let sender = established_address_1();
+        let transfer_msg = MsgTransfer {
+            port_id_on_a: get_port_id(),
+            chan_id_on_a: get_channel_id(),
+            packet_data: PacketData {
+                token: PrefixedCoin {
+                    denom: nam().to_string().parse().unwrap(),
+                    amount: 100u64.into(),
+                },
+                sender: sender.to_string().into(),
+                receiver: "receiver".to_string().into(),
+                memo: "memo".to_string().into(),
+            },
+            timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
+            timeout_timestamp_on_b: Timestamp::none(),
+        };
+        let sequence = 1.into();
+        let packet = packet_from_message(
+            &transfer_msg,
+            sequence,
+            &get_channel_counterparty(),
+        );
+        let commitment_key = commitment_key(
+            &transfer_msg.port_id_on_a,
+            &transfer_msg.chan_id_on_a,
+            sequence,
+        );
+        let commitment = commitment(&packet);
+        let bytes = commitment.into_vec();
+        state
+            .write_log_mut()
+            .write(&commitment_key, bytes)
+            .expect("write failed");
+        insert_packet_escrow(&mut state, sequence, Amount::from_u64(100));
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        // for next block
+        state
+            .in_mem_mut()
+            .set_header(get_dummy_header())
+            .expect("Setting a dummy header shouldn't fail");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        // prepare data
+        let msg = MsgTimeoutOnClose {
+            packet: packet.clone(),
+            next_seq_recv_on_b: sequence,
+            proof_unreceived_on_b: dummy_proof(),
+            proof_close_on_b: dummy_proof(),
+            proof_height_on_b: Height::new(0, 1).unwrap(),
+            signer: "account0".to_string().into(),
+        };
+
+        // delete the commitment
+        state
+            .write_log_mut()
+            .delete(&commitment_key)
+            .expect("delete failed");
+        keys_changed.insert(commitment_key);
+        delete_packet_escrow(&mut state, &mut keys_changed, sequence);
+        // deposit
+        let amount = Amount::from_u64(100);
+        add_amount(&mut state, &mut keys_changed, deposit_key(&nam()), amount);
+        let deposit_key =
+            deposit_key_for_channel(&nam(), &get_port_id(), &get_channel_id());
+        add_amount(&mut state, &mut keys_changed, deposit_key, amount);
+        // unescrow
+        sub_amount(
+            &mut state,
+            &mut keys_changed,
+            escrow_amount_key(&nam()),
+            amount,
+        );
+        // event
+        let data = serde_json::from_slice::<PacketData>(&packet.data)
+            .expect("decoding packet data failed");
+        let timeout_event = TimeoutEvent {
+            refund_receiver: data.sender,
+            refund_denom: data.token.denom,
+            refund_amount: data.token.amount,
+            memo: data.memo,
+        };
+        let event = RawIbcEvent::Module(ModuleEvent::from(timeout_event));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        let event = RawIbcEvent::TimeoutPacket(TimeoutPacket::new(
+            packet,
+            Order::Unordered,
+        ));
+        let message_event = RawIbcEvent::Message(MessageEvent::Channel);
+        state
+            .write_log_mut()
+            .emit_ibc_event(message_event.try_into().unwrap());
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+
+        let tx_index = TxIndex::default();
+        let tx_code = vec![];
+        let mut tx_data = vec![];
+        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(tx_code, None)
+            .add_serialized_data(tx_data)
+            .sign_wrapper(keypair_1());
+
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+
+        let verifiers = BTreeSet::new();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let ibc = Ibc { ctx };
+        assert!(
+            ibc.validate_tx(&tx, &keys_changed, &verifiers)
+                .expect("validation failed")
+        );
+    }
+
+    #[test]
+    fn test_timeout_ordered_closes_channel() {
+        let mut keys_changed = BTreeSet::new();
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        // init the escrow balance
+        let balance_key =
+            balance_key(&nam(), &Address::Internal(InternalAddress::Ibc));
+        let amount = Amount::native_whole(100);
+        state
+            .write_log_mut()
+            .write(&balance_key, amount.serialize_to_vec())
+            .expect("write failed");
+        // commitment
+        let transfer_msg = MsgTransfer {
+            port_id_on_a: get_port_id(),
+            chan_id_on_a: get_channel_id(),
+            packet_data: PacketData {
+                token: PrefixedCoin {
+                    denom: nam().to_string().parse().unwrap(),
+                    amount: 100u64.into(),
+                },
+                sender: established_address_1().to_string().into(),
+                receiver: "receiver".to_string().into(),
+                memo: "memo".to_string().into(),
+            },
+            timeout_height_on_b: TimeoutHeight::Never,
+            timeout_timestamp_on_b: (Timestamp::now() - Duration::new(10, 0))
+                .unwrap(),
+        };
+        let sequence = 1.into();
+        let packet = packet_from_message(
+            &transfer_msg,
+            sequence,
+            &get_channel_counterparty(),
+        );
+        let commitment_key = commitment_key(
+            &transfer_msg.port_id_on_a,
+            &transfer_msg.chan_id_on_a,
+            sequence,
+        );
+        let bytes = commitment(&packet).into_vec();
+        state
+            .write_log_mut()
+            .write(&commitment_key, bytes)
+            .expect("write failed");
+        insert_packet_escrow(&mut state, sequence, Amount::from_u64(100));
+        // an open ordered channel
+        let channel = get_channel(ChanState::Open, Order::Ordered);
+        insert_open_channel(&mut state, &get_port_id(), channel);
+
+        let msg = MsgTimeout {
+            packet: packet.clone(),
+            next_seq_recv_on_b: sequence,
+            proof_unreceived_on_b: dummy_proof(),
+            proof_height_on_b: Height::new(0, 1).unwrap(),
+            signer: "account0".to_string().into(),
+        };
+
+        // delete the commitment
+        state
+            .write_log_mut()
+            .delete(&commitment_key)
+            .expect("delete failed");
+        keys_changed.insert(commitment_key);
+        delete_packet_escrow(&mut state, &mut keys_changed, sequence);
+        // close the channel
+        let channel_key = channel_key(&get_port_id(), &get_channel_id());
+        let channel = get_channel(ChanState::Closed, Order::Ordered);
+        state
+            .write_log_mut()
+            .write(&channel_key, channel.encode_vec())
+            .expect("write failed");
+        keys_changed.insert(channel_key);
+        // deposit
+        let amount = Amount::from_u64(100);
+        add_amount(&mut state, &mut keys_changed, deposit_key(&nam()), amount);
+        let deposit_key =
+            deposit_key_for_channel(&nam(), &get_port_id(), &get_channel_id());
+        add_amount(&mut state, &mut keys_changed, deposit_key, amount);
+        // unescrow
+        sub_amount(
+            &mut state,
+            &mut keys_changed,
+            escrow_amount_key(&nam()),
+            amount,
+        );
+        // event
+        let data = serde_json::from_slice::<PacketData>(&packet.data)
+            .expect("decoding packet data failed");
+        let timeout_event = TimeoutEvent {
+            refund_receiver: data.sender,
+            refund_denom: data.token.denom,
+            refund_amount: data.token.amount,
+            memo: data.memo,
+        };
+        let event = RawIbcEvent::Module(ModuleEvent::from(timeout_event));
+        state
+            .write_log_mut()
+            .emit_ibc_event(event.try_into().unwrap());
+        let timeout_event = RawIbcEvent::TimeoutPacket(TimeoutPacket::new(
+            packet,
+            Order::Ordered,
+        ));
+        let counterparty = get_channel_counterparty();
+        let close_event = RawIbcEvent::ChannelClosed(ChannelClosed::new(
+            get_port_id(),
+            get_channel_id(),
+            counterparty.port_id().clone(),
+            counterparty.channel_id().cloned(),
+            get_connection_id(),
+            Order::Ordered,
+        ));
+        for event in [timeout_event, close_event] {
+            let message_event = RawIbcEvent::Message(MessageEvent::Channel);
+            state
+                .write_log_mut()
+                .emit_ibc_event(message_event.try_into().unwrap());
+            state
+                .write_log_mut()
+                .emit_ibc_event(event.try_into().unwrap());
+        }
+
+        let result = validate_ibc_tx(&state, msg.to_any(), &keys_changed);
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_changed_keys_mismatch_error_truncation() {
+        let keys: Vec<Key> = (0..10_000)
+            .map(|seq| {
+                commitment_key(
+                    &get_port_id(),
+                    &get_channel_id(),
+                    Sequence::from(seq),
+                )
+            })
+            .collect();
+        let (expected, actual) = keys.split_at(1);
+        let max_bytes = parameters::DEFAULT_MAX_TX_ERROR_BYTES as usize;
+
+        // Each node has its own hash set state and insertion order
+        let render = |actual: Vec<&Key>| {
+            let actual: HashSet<&Key> = actual.into_iter().collect();
+            let expected: HashSet<&Key> = expected.iter().collect();
+            changed_keys_mismatch(&actual, &expected).to_string()
+        };
+        let msg_a = render(actual.iter().collect());
+        let msg_b = render(actual.iter().rev().collect());
+        assert_eq!(msg_a, msg_b);
+        assert!(msg_a.len() > max_bytes);
+
+        let truncated_a = truncate_error(&msg_a, max_bytes).unwrap();
+        let truncated_b = truncate_error(&msg_b, max_bytes).unwrap();
+        assert_eq!(truncated_a, truncated_b);
+        assert!(truncated_a.len() <= max_bytes);
+        // The counts are kept
+        assert!(truncated_a.contains("9999 actual and 1 expected keys"));
+        // The hash of the full message is kept
+        let hash = crate::core::hash::Hash::sha256(msg_a.as_bytes());
+        assert!(truncated_a.ends_with(&format!("{hash}]")));
+
+        // A normal-sized error is unaffected
+        let msg = render(vec![&keys[1]]);
+        assert!(msg.len() <= max_bytes);
+        assert_eq!(truncate_error(&msg, max_bytes), None);
+    }
+
+    /// Write the packet commitments of the given sequences. Returns the
+    /// modifications expected from the pseudo execution.
+    fn write_commitments(
+        state: &mut TestState,
+        count: u64,
+    ) -> BTreeMap<Key, StorageModification> {
+        (0..count)
+            .map(|seq| {
+                let key = commitment_key(
+                    &get_port_id(),
+                    &get_channel_id(),
+                    Sequence::from(seq),
+                );
+                let value = seq.serialize_to_vec();
+                state
+                    .write_log_mut()
+                    .write(&key, value.clone())
+                    .expect("write failed");
+                (key, StorageModification::Write { value })
+            })
+            .collect()
+    }
+
+    fn expected_values(
+        modifications: &BTreeMap<Key, StorageModification>,
+    ) -> Vec<(&Key, Option<&StorageModification>)> {
+        modifications
+            .iter()
+            .map(|(key, modification)| (key, Some(modification)))
+            .collect()
+    }
+
+    #[test]
+    fn test_match_values_of_many_keys() {
+        let mut state = init_storage();
+        let modifications = write_commitments(&mut state, 200);
+
+        let start = std::time::Instant::now();
+        match_values(&state, expected_values(&modifications))
+            .expect("validation failed");
+        tracing::debug!(
+            "Compared the values of {} keys in {:?}",
+            modifications.len(),
+            start.elapsed()
+        );
+    }
+
+    #[test]
+    fn test_match_values_with_corrupted_value() {
+        let mut state = init_storage();
+        let modifications = write_commitments(&mut state, 200);
+        let keys: Vec<&Key> = modifications.keys().collect();
+
+        // Corrupt a single value among many
+        let corrupted = keys[150];
+        state
+            .write_log_mut()
+            .write(corrupted, vec![0xff])
+            .expect("write failed");
+        let err = match_values(&state, expected_values(&modifications))
+            .expect_err("validation should fail");
+        match err.reject_reason() {
+            Some(VpRejectReason::ValueMismatch { key, actual, .. }) => {
+                assert_eq!(key, corrupted);
+                assert_eq!(actual, &Some(vec![0xff]));
+            }
+            _ => panic!("unexpected error {err}"),
+        }
+
+        // With another corrupted value, the smallest key is reported
+        state
+            .write_log_mut()
+            .delete(keys[42])
+            .expect("delete failed");
+        let err = match_values(&state, expected_values(&modifications))
+            .expect_err("validation should fail");
+        match err.reject_reason() {
+            Some(VpRejectReason::ValueMismatch { key, .. }) => {
+                assert_eq!(key, keys[42]);
+            }
+            _ => panic!("unexpected error {err}"),
+        }
+    }
+
+    #[test]
+    fn test_limit_override_only_by_governance() {
+        let mut state = init_storage();
+        let proposal_id = 0_u64;
+        // the executed proposal
+        let execution_key = get_proposal_execution_key(proposal_id);
+        state
+            .write_log_mut()
+            .write(&execution_key, vec![])
+            .expect("write failed");
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        // override the mint limit of NAM
+        let mut keys_changed = BTreeSet::new();
+        let limit_key = mint_limit_key(&nam());
+        state
+            .write_log_mut()
+            .write(&limit_key, Amount::from_u64(1000).serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(limit_key);
+
+        let validate = |tx_data: Vec<u8>| {
+            let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+            tx.add_code(vec![], None)
+                .add_serialized_data(tx_data)
+                .sign_wrapper(keypair_1());
+            let tx_index = TxIndex::default();
+            let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+                &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+            ));
+            let (vp_wasm_cache, _vp_cache_dir) =
+                wasm::compilation_cache::common::testing::cache();
+            let verifiers = BTreeSet::new();
+            let sentinel = RefCell::new(VpSentinel::default());
+            let ctx = Ctx::new(
+                &ADDRESS,
+                &state,
+                &tx,
+                &tx_index,
+                &gas_meter,
+                &sentinel,
+                &keys_changed,
+                &verifiers,
+                vp_wasm_cache,
+            );
+            let ibc = Ibc { ctx };
+            ibc.validate_tx(&tx, &keys_changed, &verifiers)
+        };
+
+        // a normal tx can't write the override
+        let result = validate(b"not a proposal".to_vec());
+        assert!(matches!(
+            result,
+            Err(Error::UnauthorizedParameterChange(keys))
+                if keys == vec![mint_limit_key(&nam())]
+        ));
+
+        // the accepted proposal can write it
+        let result = validate(proposal_id.serialize_to_vec());
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_ibc_params_only_by_governance() {
+        let mut state = init_storage();
+        let proposal_id = 0_u64;
+        // the executed proposal
+        let execution_key = get_proposal_execution_key(proposal_id);
+        state
+            .write_log_mut()
+            .write(&execution_key, vec![])
+            .expect("write failed");
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        // update the default limits
+        let mut keys_changed = BTreeSet::new();
+        let params = IbcParameters {
+            default_mint_limit: Amount::native_whole(1_000_000),
+            default_per_epoch_throughput_limit: Amount::native_whole(10_000),
+            ..Default::default()
+        };
+        state
+            .write_log_mut()
+            .write(&params_key(), params.serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(params_key());
+
+        let validate = |tx_data: Vec<u8>| {
+            let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+            tx.add_code(vec![], None)
+                .add_serialized_data(tx_data)
+                .sign_wrapper(keypair_1());
+            let tx_index = TxIndex::default();
+            let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+                &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+            ));
+            let (vp_wasm_cache, _vp_cache_dir) =
+                wasm::compilation_cache::common::testing::cache();
+            let verifiers = BTreeSet::new();
+            let sentinel = RefCell::new(VpSentinel::default());
+            let ctx = Ctx::new(
+                &ADDRESS,
+                &state,
+                &tx,
+                &tx_index,
+                &gas_meter,
+                &sentinel,
+                &keys_changed,
+                &verifiers,
+                vp_wasm_cache,
+            );
+            let ibc = Ibc { ctx };
+            ibc.validate_tx(&tx, &keys_changed, &verifiers)
+        };
+
+        // a normal tx can't update the parameters
+        let result = validate(b"not a proposal".to_vec());
+        assert!(matches!(
+            result,
+            Err(Error::UnauthorizedParameterChange(keys))
+                if keys == vec![params_key()]
+        ));
+
+        // the accepted proposal can update them without any IBC action
+        let result = validate(proposal_id.serialize_to_vec());
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_blocked_trace_only_by_governance() {
+        let mut state = init_storage();
+        let proposal_id = 0_u64;
+        // the executed proposal
+        let execution_key = get_proposal_execution_key(proposal_id);
+        state
+            .write_log_mut()
+            .write(&execution_key, vec![])
+            .expect("write failed");
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        // block a denom trace
+        let mut keys_changed = BTreeSet::new();
+        let trace = "transfer/channel-0/uatom".to_string();
+        let blocked_key = blocked_trace_key(calc_hash(&trace));
+        state
+            .write_log_mut()
+            .write(&blocked_key, trace.serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(blocked_key.clone());
+
+        let validate = |state: &TestState, tx_data: Vec<u8>| {
+            validate_ibc_tx_data(
+                state,
+                tx_data,
+                &keys_changed,
+                &new_gas_meter(),
+            )
+        };
+
+        // a normal tx can't block it
+        let result = validate(&state, b"not a proposal".to_vec());
+        assert!(matches!(
+            result,
+            Err(Error::UnauthorizedParameterChange(keys))
+                if keys == vec![blocked_key.clone()]
+        ));
+
+        // the accepted proposal can block it
+        let result = validate(&state, proposal_id.serialize_to_vec());
+        assert!(result.expect("validation failed"));
+
+        // the trace has to match the hash in the key
+        let other_trace = "transfer/channel-1/uatom".to_string();
+        state
+            .write_log_mut()
+            .write(&blocked_key, other_trace.serialize_to_vec())
+            .expect("write failed");
+        let result = validate(&state, proposal_id.serialize_to_vec());
+        assert!(matches!(result, Err(Error::ParameterChange(_))));
+
+        // the accepted proposal can unblock it
+        state
+            .write_log_mut()
+            .delete(&blocked_key)
+            .expect("delete failed");
+        let result = validate(&state, proposal_id.serialize_to_vec());
+        assert!(result.expect("validation failed"));
+    }
+
+    #[test]
+    fn test_upgrade_path_param() {
+        let upgrade_path =
+            vec!["upgrade".to_string(), "upgradedIBCState".to_string()];
+
+        // Validate a client of this chain having the upgrade path, as done
+        // in the connection handshake
+        let validate_self_client = |params: IbcParameters| {
+            let mut state = init_storage();
+            params.init_storage(&mut state).expect("write failed");
+            state.write_log_mut().commit_tx();
+            state.commit_block().expect("commit failed");
+            state
+                .in_mem_mut()
+                .begin_block(BlockHash::default(), BlockHeight(2))
+                .unwrap();
+
+            let tx = Tx::new(state.in_mem().chain_id.clone(), None);
+            let tx_index = TxIndex::default();
+            let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+                &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+            ));
+            let (vp_wasm_cache, _vp_cache_dir) =
+                wasm::compilation_cache::common::testing::cache();
+            let keys_changed = BTreeSet::new();
+            let verifiers = BTreeSet::new();
+            let sentinel = RefCell::new(VpSentinel::default());
+            let ctx = Ctx::new(
+                &ADDRESS,
+                &state,
+                &tx,
+                &tx_index,
+                &gas_meter,
+                &sentinel,
+                &keys_changed,
+                &verifiers,
+                vp_wasm_cache,
+            );
+            let ibc = Ibc { ctx };
+            let validation_params =
+                ibc.validation_params().expect("getting params failed");
+
+            let client_state = TmClientStateType::new(
+                validation_params.chain_id.clone(),
+                TrustThreshold::ONE_THIRD,
+                Duration::from_secs(100),
+                validation_params.unbonding_period,
+                Duration::from_secs(1),
+                Height::new(0, 1).unwrap(),
+                validation_params.proof_specs.clone(),
+                upgrade_path.clone(),
+                AllowUpdate {
+                    after_expiry: true,
+                    after_misbehaviour: true,
+                },
+            )
+            .unwrap();
+            let client_state: TmClientState = client_state.into();
+
+            let validation_ctx = VpValidationContext::new(ibc.ctx.pre());
+            let mut ibc_ctx =
+                IbcContext::new(Rc::new(RefCell::new(validation_ctx)));
+            ibc_ctx.validation_params = validation_params;
+            ValidationContext::validate_self_client(
+                &ibc_ctx,
+                client_state.into(),
+            )
+        };
+
+        let params = IbcParameters {
+            upgrade_path: upgrade_path.clone(),
+            ..Default::default()
+        };
+        validate_self_client(params).expect("validation failed");
+
+        let result = validate_self_client(IbcParameters::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_client_recovery_only_by_governance() {
+        let mut state = init_storage();
+        insert_init_client(&mut state);
+        let proposal_id = 0_u64;
+        // the executed proposal
+        let execution_key = get_proposal_execution_key(proposal_id);
+        state
+            .write_log_mut()
+            .write(&execution_key, vec![])
+            .expect("write failed");
+        state.write_log_mut().commit_tx();
+        state.commit_block().expect("commit failed");
+        state
+            .in_mem_mut()
+            .set_header(get_dummy_header())
+            .expect("Setting a dummy header shouldn't fail");
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+
+        // substitute the expired client with the states of the substitute
+        let mut keys_changed = BTreeSet::new();
+        let client_id = get_client_id();
+        let height = Height::new(0, 11).unwrap();
+        let header = MockHeader {
+            height,
+            timestamp: Timestamp::now(),
+        };
+        let client_state_key = client_state_key(&client_id);
+        let bytes = Protobuf::<Any>::encode_vec(MockClientState::new(header));
+        state
+            .write_log_mut()
+            .write(&client_state_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(client_state_key);
+        let consensus_key = consensus_state_key(&client_id, height);
+        let bytes =
+            Protobuf::<Any>::encode_vec(MockConsensusState::new(header));
+        state
+            .write_log_mut()
+            .write(&consensus_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(consensus_key);
+        let client_update_time_key = client_update_timestamp_key(&client_id);
+        let time = StateRead::get_block_header(&state, None)
+            .unwrap()
+            .0
+            .unwrap()
+            .time;
+        let bytes = TmTime::try_from(time).unwrap().encode_vec();
+        state
+            .write_log_mut()
+            .write(&client_update_time_key, bytes)
+            .expect("write failed");
+        keys_changed.insert(client_update_time_key);
+        let client_update_height_key = client_update_height_key(&client_id);
+        let host_height = state.in_mem().get_block_height().0;
+        let host_height =
+            Height::new(0, host_height.0).expect("invalid height");
+        state
+            .write_log_mut()
+            .write(&client_update_height_key, host_height.encode_vec())
+            .expect("write failed");
+        keys_changed.insert(client_update_height_key);
+
+        let validate = |state: &TestState,
+                        keys_changed: &BTreeSet<Key>,
+                        tx_data: Vec<u8>| {
+            validate_ibc_tx_data(state, tx_data, keys_changed, &new_gas_meter())
+        };
+
+        // the accepted proposal can substitute the client
+        let result =
+            validate(&state, &keys_changed, proposal_id.serialize_to_vec());
+        assert!(result.expect("validation failed"));
+
+        // the same writes are rejected without the proposal
+        let result = validate(&state, &keys_changed, 1_u64.serialize_to_vec());
+        assert!(result.is_err());
+
+        // other IBC keys can't be updated with the substitution
+        let conn_key = connection_key(&ConnectionId::new(0));
+        state
+            .write_log_mut()
+            .write(&conn_key, vec![0])
+            .expect("write failed");
+        keys_changed.insert(conn_key);
+        let result =
+            validate(&state, &keys_changed, proposal_id.serialize_to_vec());
+        assert!(matches!(result, Err(Error::ClientRecovery(_))));
+    }
+
+    /// Get the asset type of the only asset of a generated MASP transaction
+    /// with its digit position, and the transferred amount of the asset
+    fn masp_asset_digits_and_amount(
+        asset_types: HashMap<AssetData, u64>,
+    ) -> (HashMap<AssetType, MaspDigitPos>, Amount) {
+        let (asset, value) = asset_types
+            .into_iter()
+            .next()
+            .expect("The MASP transaction must have an asset");
+        let asset_type = asset.encode().expect("invalid asset type");
+        let amount = Amount::from_masp_denominated(value, asset.position);
+        (HashMap::from([(asset_type, asset.position)]), amount)
+    }
+
+    /// Check the shielded send of the only asset of a generated unshielding
+    /// MASP transaction with the packet amount mapped from the sent amount, the
+    /// owners of the changed balances of the asset and the verifiers of the tx
+    fn validate_shielded_send(
+        masp_tx: Transaction,
+        asset_types: HashMap<AssetData, u64>,
+        packet_amount: impl FnOnce(Amount) -> Amount,
+        balance_owners: &[Address],
+        verifiers: BTreeSet<Address>,
+    ) -> VpResult<()> {
+        let (asset, value) = asset_types
+            .into_iter()
+            .next()
+            .expect("The MASP transaction must have an asset");
+        let token = asset.token;
+        let amount = Amount::from_masp_denominated(value, asset.position);
+        let msg = MsgShieldedTransfer {
+            message: MsgTransfer {
+                port_id_on_a: get_port_id(),
+                chan_id_on_a: get_channel_id(),
+                packet_data: PacketData {
+                    token: PrefixedCoin {
+                        denom: token.to_string().parse().unwrap(),
+                        amount: DenominatedAmount::new(
+                            packet_amount(amount),
+                            asset.denom,
+                        )
+                        .into(),
+                    },
+                    sender: MASP.to_string().into(),
+                    receiver: "receiver".to_string().into(),
+                    memo: "memo".to_string().into(),
+                },
+                timeout_height_on_b: TimeoutHeight::At(
+                    Height::new(0, 10).unwrap(),
+                ),
+                timeout_timestamp_on_b: Timestamp::none(),
+            },
+            shielded_transfer: IbcShieldedTransfer {
+                transfer: Transfer {
+                    source: MASP,
+                    target: ADDRESS,
+                    token: token.clone(),
+                    amount: DenominatedAmount::new(amount, asset.denom),
+                    key: None,
+                    shielded: None,
+                },
+                masp_tx,
+            },
+        };
+        let keys_changed = balance_owners
+            .iter()
+            .map(|owner| balance_key(&token, owner))
+            .collect();
+
+        let state = init_storage();
+        let tx_data = msg.serialize_to_vec();
+        let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(tx_data.clone())
+            .sign_wrapper(keypair_1());
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let ibc = Ibc { ctx };
+        ibc.validate_shielded_transfer(&tx_data, &keys_changed, &verifiers)
+    }
+
+    proptest! {
+        /// A shielded receive mints or unescrows the received amount to the
+        /// MASP pool as the transparent inputs of a shielding MASP transaction
+        #[test]
+        fn test_shielded_receive_value(
+            (shielded_transfer, asset_types) in
+                arb_shielding_transfer(TransparentAddress([0; 20]), 1),
+        ) {
+            let (asset_digits, amount) =
+                masp_asset_digits_and_amount(asset_types);
+            check_masp_transparent_value(
+                &shielded_transfer.masp_tx,
+                &asset_digits,
+                amount,
+                true,
+            )
+            .expect("the shielded receive must be accepted");
+        }
+
+        /// A shielded send spends the sent amount from the MASP pool as the
+        /// transparent outputs of an unshielding MASP transaction
+        #[test]
+        fn test_shielded_send_value(
+            (shielded_transfer, asset_types) in
+                arb_deshielding_transfer(TransparentAddress([0; 20]), 1),
+        ) {
+            let (asset_digits, amount) =
+                masp_asset_digits_and_amount(asset_types);
+            check_masp_transparent_value(
+                &shielded_transfer.masp_tx,
+                &asset_digits,
+                amount,
+                false,
+            )
+            .expect("the shielded send must be accepted");
+        }
+
+        /// The transparent value of the MASP transaction mismatching the
+        /// transferred amount is rejected
+        #[test]
+        fn test_shielded_transfer_value_mismatch(
+            (shielded_transfer, asset_types) in
+                arb_shielding_transfer(TransparentAddress([0; 20]), 1),
+        ) {
+            let (asset_digits, amount) =
+                masp_asset_digits_and_amount(asset_types);
+            let mismatched = amount
+                .checked_add(Amount::from(1))
+                .expect("no overflow");
+            let result = check_masp_transparent_value(
+                &shielded_transfer.masp_tx,
+                &asset_digits,
+                mismatched,
+                true,
+            );
+            assert!(matches!(result, Err(Error::ShieldedTransfer(_))));
+        }
+
+        /// A shielded send is rejected unless the MASP VP verifies the MASP
+        /// transaction
+        #[test]
+        fn test_shielded_send_without_masp_verifier(
+            (shielded_transfer, asset_types) in
+                arb_deshielding_transfer(TransparentAddress([0; 20]), 1),
+        ) {
+            let result = validate_shielded_send(
+                shielded_transfer.masp_tx,
+                asset_types,
+                |amount| amount,
+                &[MASP, ADDRESS],
+                BTreeSet::new(),
+            );
+            assert!(matches!(
+                result,
+                Err(Error::ShieldedTransfer(msg)) if msg.contains("MASP VP")
+            ));
+        }
+
+        /// A shielded send is rejected if the MASP transfer doesn't match the
+        /// token and the amount of the packet
+        #[test]
+        fn test_shielded_send_mismatched_packet(
+            (shielded_transfer, asset_types) in
+                arb_deshielding_transfer(TransparentAddress([0; 20]), 1),
+        ) {
+            let result = validate_shielded_send(
+                shielded_transfer.masp_tx,
+                asset_types,
+                |amount| {
+                    amount.checked_add(Amount::from(1)).expect("no overflow")
+                },
+                &[MASP, ADDRESS],
+                BTreeSet::from([MASP]),
+            );
+            assert!(matches!(
+                result,
+                Err(Error::ShieldedTransfer(msg))
+                    if msg.contains("doesn't match the packet")
+            ));
+        }
+
+        /// A shielded send is rejected if it changes the transparent balance
+        /// of the token of an owner other than MASP and IBC
+        #[test]
+        fn test_shielded_send_foreign_balance(
+            (shielded_transfer, asset_types) in
+                arb_deshielding_transfer(TransparentAddress([0; 20]), 1),
+        ) {
+            let result = validate_shielded_send(
+                shielded_transfer.masp_tx,
+                asset_types,
+                |amount| amount,
+                &[MASP, ADDRESS, established_address_1()],
+                BTreeSet::from([MASP]),
+            );
+            assert!(matches!(
+                result,
+                Err(Error::ShieldedTransfer(msg))
+                    if msg.contains("transparent balance of")
+            ));
+        }
     }
 }
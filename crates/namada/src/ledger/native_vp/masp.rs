@@ -14,6 +14,7 @@ use namada_core::address::InternalAddress::Masp;
 use namada_core::masp::encode_asset_type;
 use namada_core::storage::{IndexedTx, Key};
 use namada_gas::MASP_VERIFY_SHIELDED_TX_GAS;
+use namada_governance::is_proposal_accepted;
 use namada_sdk::masp::verify_shielded_tx;
 use namada_state::{OptionExt, ResultExt, StateRead};
 use namada_token::read_denom;
@@ -25,9 +26,9 @@ use sha2::Digest as Sha2Digest;
 use thiserror::Error;
 use token::storage_key::{
     balance_key, is_any_shielded_action_balance_key, is_masp_allowed_key,
-    is_masp_key, is_masp_nullifier_key, is_masp_tx_pin_key,
-    masp_commitment_anchor_key, masp_commitment_tree_key,
-    masp_convert_anchor_key, masp_nullifier_key,
+    is_masp_key, is_masp_nullifier_key, is_masp_reward_token_key,
+    is_masp_tx_pin_key, masp_commitment_anchor_key, masp_commitment_tree_key,
+    masp_convert_anchor_key, masp_nullifier_key, masp_reward_tokens_prefix,
 };
 use token::Amount;
 
@@ -238,6 +239,60 @@ where
         Ok(true)
     }
 
+    /// Check that the MASP reward tokens are updated by an accepted
+    /// governance proposal, without any other change of the MASP keys, and
+    /// that an added token has an alias
+    fn is_valid_reward_tokens_change(
+        &self,
+        tx: &Tx,
+        keys_changed: &BTreeSet<Key>,
+    ) -> Result<bool> {
+        let is_accepted = match tx.data() {
+            Some(data) => is_proposal_accepted(&self.ctx.pre(), data.as_ref())
+                .map_err(Error::NativeVpError)?,
+            None => false,
+        };
+        if !is_accepted {
+            tracing::debug!(
+                "The MASP reward tokens can only be updated by an accepted \
+                 governance proposal"
+            );
+            return Ok(false);
+        }
+        for key in keys_changed.iter().filter(|key| is_masp_key(key)) {
+            if is_masp_reward_token_key(key).is_none() {
+                tracing::debug!(
+                    "The MASP key {key} can't be updated with the reward tokens"
+                );
+                return Ok(false);
+            }
+            if let Some(alias) = self.ctx.read_post::<String>(key)? {
+                if alias.is_empty() {
+                    tracing::debug!("The MASP reward token {key} has no alias");
+                    return Ok(false);
+                }
+            }
+        }
+        // The conversion state maps the aliases to the reward tokens, so they
+        // must be unique
+        let mut aliases = BTreeSet::new();
+        let reward_tokens = namada_state::iter_prefix::<String>(
+            &self.ctx.post(),
+            &masp_reward_tokens_prefix(),
+        )
+        .map_err(Error::NativeVpError)?;
+        for result in reward_tokens {
+            let (key, alias) = result.map_err(Error::NativeVpError)?;
+            if !aliases.insert(alias) {
+                tracing::debug!(
+                    "The alias of the MASP reward token {key} is already used"
+                );
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     fn validate_state_and_get_transfer_data(
         &self,
         keys_changed: &BTreeSet<Key>,
@@ -408,6 +463,14 @@ where
         keys_changed: &BTreeSet<Key>,
         _verifiers: &BTreeSet<Address>,
     ) -> Result<bool> {
+        // The MASP reward tokens are updated only by a governance proposal
+        if keys_changed
+            .iter()
+            .any(|key| is_masp_reward_token_key(key).is_some())
+        {
+            return self.is_valid_reward_tokens_change(tx_data, keys_changed);
+        }
+
         let epoch = self.ctx.get_block_epoch()?;
         let conversion_state = self.ctx.state.in_mem().get_conversion_state();
         let shielded_tx = self.ctx.get_shielded_action(tx_data)?;
@@ -6,6 +6,7 @@ pub mod ibc;
 pub mod masp;
 pub mod multitoken;
 pub mod parameters;
+pub mod read_cache;
 
 use std::cell::RefCell;
 use std::collections::BTreeSet;
@@ -21,6 +22,7 @@ use namada_tx::Tx;
 pub use namada_vp_env::VpEnv;
 use state::StateRead;
 
+use self::read_cache::VpReadCache;
 use super::vp_host_fns;
 use crate::address::Address;
 use crate::hash::Hash;
@@ -88,6 +90,8 @@ where
     /// To avoid unused parameter without "wasm-runtime" feature
     #[cfg(not(feature = "wasm-runtime"))]
     pub cache_access: std::marker::PhantomData<CA>,
+    /// Read cache shared by the native VPs validating the same transaction
+    pub read_cache: Option<&'a VpReadCache>,
 }
 
 /// Read access to the prior storage (state before tx execution) via
@@ -112,6 +116,18 @@ where
     ctx: &'view Ctx<'a, S, CA>,
 }
 
+/// Read access to the committed storage as of a past block height via
+/// [`trait@StorageRead`]. Iterating a prefix isn't supported.
+#[derive(Debug)]
+pub struct CtxAtHeightStorageRead<'view, 'a: 'view, S, CA>
+where
+    S: StateRead,
+    CA: WasmCacheAccess,
+{
+    ctx: &'view Ctx<'a, S, CA>,
+    height: BlockHeight,
+}
+
 impl<'a, S, CA> Ctx<'a, S, CA>
 where
     S: StateRead,
@@ -145,9 +161,17 @@ where
             vp_wasm_cache,
             #[cfg(not(feature = "wasm-runtime"))]
             cache_access: std::marker::PhantomData,
+            read_cache: None,
         }
     }
 
+    /// Share the read cache with the other native VPs validating the same
+    /// transaction
+    pub fn with_read_cache(mut self, read_cache: &'a VpReadCache) -> Self {
+        self.read_cache = Some(read_cache);
+        self
+    }
+
     /// Read access to the prior storage (state before tx execution)
     /// via [`trait@StorageRead`].
     pub fn pre<'view>(&'view self) -> CtxPreStorageRead<'view, 'a, S, CA> {
@@ -159,6 +183,32 @@ where
     pub fn post<'view>(&'view self) -> CtxPostStorageRead<'view, 'a, S, CA> {
         CtxPostStorageRead { ctx: self }
     }
+
+    /// Read access to the committed storage as of the given past block height
+    /// via [`trait@StorageRead`]. A read fails when the height is past the
+    /// last committed height or older than the retained diffs.
+    pub fn at_height<'view>(
+        &'view self,
+        height: BlockHeight,
+    ) -> CtxAtHeightStorageRead<'view, 'a, S, CA> {
+        CtxAtHeightStorageRead { ctx: self, height }
+    }
+
+    /// Charge the given gas like [`VpEnv::charge_gas`], recording it under
+    /// the given label in the gas breakdown when gas tracing is enabled
+    pub fn charge_gas_scoped(
+        &self,
+        label: &'static str,
+        used_gas: u64,
+    ) -> Result<(), state::StorageError> {
+        self.gas_meter
+            .borrow_mut()
+            .scoped(label)
+            .consume(used_gas)
+            .map_err(|_| {
+                Error::SimpleMessage("Gas limit exceeded in native vp")
+            })
+    }
 }
 
 impl<'view, 'a: 'view, S, CA> StorageRead
@@ -178,6 +228,7 @@ where
             self.ctx.state,
             key,
             self.ctx.sentinel,
+            self.ctx.read_cache,
         )
         .into_storage_result()
     }
@@ -274,6 +325,7 @@ where
             self.ctx.state,
             key,
             self.ctx.sentinel,
+            self.ctx.read_cache,
         )
         .into_storage_result()
     }
@@ -353,6 +405,92 @@ where
     }
 }
 
+impl<'view, 'a: 'view, S, CA> StorageRead
+    for CtxAtHeightStorageRead<'view, 'a, S, CA>
+where
+    S: StateRead,
+    CA: 'static + WasmCacheAccess,
+{
+    type PrefixIter<'iter> = state::PrefixIter<'iter, <S as StateRead>::D> where Self: 'iter;
+
+    fn read_bytes(
+        &self,
+        key: &storage::Key,
+    ) -> Result<Option<Vec<u8>>, state::StorageError> {
+        vp_host_fns::read_at_height(
+            self.ctx.gas_meter,
+            self.ctx.state,
+            key,
+            self.height,
+            self.ctx.sentinel,
+        )
+        .into_storage_result()
+    }
+
+    fn has_key(&self, key: &storage::Key) -> Result<bool, state::StorageError> {
+        Ok(self.read_bytes(key)?.is_some())
+    }
+
+    fn iter_prefix<'iter>(
+        &'iter self,
+        _prefix: &storage::Key,
+    ) -> Result<Self::PrefixIter<'iter>, state::StorageError> {
+        Err(state::StorageError::new_const(
+            "Iterating a prefix at a past height isn't supported",
+        ))
+    }
+
+    // ---- Methods below are implemented in `self.ctx`, because they are
+    //      the same in `pre/post` ----
+
+    fn iter_next<'iter>(
+        &'iter self,
+        iter: &mut Self::PrefixIter<'iter>,
+    ) -> Result<Option<(String, Vec<u8>)>, state::StorageError> {
+        vp_host_fns::iter_next::<<S as StateRead>::D>(
+            self.ctx.gas_meter,
+            iter,
+            self.ctx.sentinel,
+        )
+        .into_storage_result()
+    }
+
+    fn get_chain_id(&self) -> Result<String, state::StorageError> {
+        self.ctx.get_chain_id()
+    }
+
+    fn get_block_height(&self) -> Result<BlockHeight, state::StorageError> {
+        self.ctx.get_block_height()
+    }
+
+    fn get_block_header(
+        &self,
+        height: BlockHeight,
+    ) -> Result<Option<Header>, state::StorageError> {
+        self.ctx.get_block_header(height)
+    }
+
+    fn get_block_hash(&self) -> Result<BlockHash, state::StorageError> {
+        self.ctx.get_block_hash()
+    }
+
+    fn get_block_epoch(&self) -> Result<Epoch, state::StorageError> {
+        self.ctx.get_block_epoch()
+    }
+
+    fn get_tx_index(&self) -> Result<TxIndex, state::StorageError> {
+        self.ctx.get_tx_index().into_storage_result()
+    }
+
+    fn get_native_token(&self) -> Result<Address, state::StorageError> {
+        self.ctx.get_native_token()
+    }
+
+    fn get_pred_epochs(&self) -> state::StorageResult<Epochs> {
+        self.ctx.get_pred_epochs()
+    }
+}
+
 impl<'view, 'a: 'view, S, CA> VpEnv<'view> for Ctx<'a, S, CA>
 where
     S: StateRead,
@@ -443,7 +581,7 @@ where
             .into_storage_result()
     }
 
-    fn iter_prefix<'iter>(
+    fn iter_prefix_pre<'iter>(
         &'iter self,
         prefix: &Key,
     ) -> Result<Self::PrefixIter<'iter>, state::StorageError> {
@@ -457,6 +595,32 @@ where
         .into_storage_result()
     }
 
+    fn iter_prefix_post<'iter>(
+        &'iter self,
+        prefix: &Key,
+    ) -> Result<Self::PrefixIter<'iter>, state::StorageError> {
+        vp_host_fns::iter_prefix_post(
+            self.gas_meter,
+            self.state.write_log(),
+            self.state.db(),
+            prefix,
+            self.sentinel,
+        )
+        .into_storage_result()
+    }
+
+    fn iter_next<'iter>(
+        &'iter self,
+        iter: &mut Self::PrefixIter<'iter>,
+    ) -> Result<Option<(String, Vec<u8>)>, state::StorageError> {
+        vp_host_fns::iter_next::<<S as StateRead>::D>(
+            self.gas_meter,
+            iter,
+            self.sentinel,
+        )
+        .into_storage_result()
+    }
+
     fn eval(
         &self,
         vp_code_hash: Hash,
@@ -664,3 +828,220 @@ pub(super) mod testing {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use borsh_ext::BorshSerializeExt;
+    use namada_gas::{Gas, TxGasMeter};
+    use namada_state::testing::TestState;
+    use namada_state::StorageWrite;
+
+    use super::*;
+    use crate::address::InternalAddress;
+    use crate::vm::wasm;
+
+    const TX_GAS_LIMIT: u64 = 1_000_000;
+
+    fn test_key() -> Key {
+        Key::parse("testing").unwrap()
+    }
+
+    /// A dummy VP accepting a tx when the value of the test key at a past
+    /// height is the expected one
+    struct ReadAtHeightVp<'a, S, CA>
+    where
+        S: StateRead,
+        CA: WasmCacheAccess,
+    {
+        ctx: Ctx<'a, S, CA>,
+        height: BlockHeight,
+        expected: Option<u64>,
+    }
+
+    impl<'a, S, CA> NativeVp for ReadAtHeightVp<'a, S, CA>
+    where
+        S: StateRead,
+        CA: 'static + WasmCacheAccess,
+    {
+        type Error = Error;
+
+        fn validate_tx(
+            &self,
+            _tx_data: &Tx,
+            _keys_changed: &BTreeSet<Key>,
+            _verifiers: &BTreeSet<Address>,
+        ) -> Result<bool, Error> {
+            let value: Option<u64> =
+                self.ctx.at_height(self.height).read(&test_key())?;
+            Ok(value == self.expected)
+        }
+    }
+
+    /// Commit the blocks 1 to 4, each writing its height to the test key
+    fn init_storage() -> TestState {
+        let mut state = TestState::default();
+        for height in 1..=4 {
+            state.in_mem_mut().block.height = BlockHeight(height);
+            state.write(&test_key(), height).expect("write failed");
+            state.commit_block().expect("commit failed");
+        }
+        state
+    }
+
+    fn validate_read_at_height(
+        state: &TestState,
+        height: BlockHeight,
+        expected: Option<u64>,
+    ) -> Result<bool, Error> {
+        let address = Address::Internal(InternalAddress::Parameters);
+        let tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let keys_changed = BTreeSet::new();
+        let verifiers = BTreeSet::new();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &address,
+            state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+        let vp = ReadAtHeightVp {
+            ctx,
+            height,
+            expected,
+        };
+        let result = vp.validate_tx(&tx, &keys_changed, &verifiers);
+        assert!(gas_meter.borrow().get_vp_consumed_gas() > Gas::default());
+        result
+    }
+
+    #[test]
+    fn test_read_at_height() {
+        let mut state = init_storage();
+
+        // the values at the past heights and the last height
+        for height in 1..=4 {
+            let result = validate_read_at_height(
+                &state,
+                BlockHeight(height),
+                Some(height),
+            );
+            assert!(result.expect("validation failed"));
+        }
+
+        // a height past the last committed height
+        let err = validate_read_at_height(&state, BlockHeight(5), None)
+            .expect_err("the read should fail");
+        let err = err.downcast::<vp_host_fns::RuntimeError>().unwrap();
+        assert!(matches!(
+            *err,
+            vp_host_fns::RuntimeError::StorageError(
+                state::Error::BlockHeightPastLast { .. }
+            )
+        ));
+
+        // a height older than the retained diffs
+        state.in_mem_mut().storage_read_past_height_limit = Some(1);
+        let err = validate_read_at_height(&state, BlockHeight(2), Some(2))
+            .expect_err("the read should fail");
+        let err = err.downcast::<vp_host_fns::RuntimeError>().unwrap();
+        assert!(matches!(
+            *err,
+            vp_host_fns::RuntimeError::StorageError(
+                state::Error::BlockHeightTooOld { .. }
+            )
+        ));
+    }
+
+    /// Iterate the prefix of the test key in the pre or the post state.
+    /// Returns the yielded keys and the gas consumed by each step: opening the
+    /// iterator and then yielding each item.
+    fn iter_test_prefix(
+        state: &TestState,
+        post: bool,
+    ) -> (Vec<String>, Vec<Gas>) {
+        let address = Address::Internal(InternalAddress::Parameters);
+        let tx = Tx::new(state.in_mem().chain_id.clone(), None);
+        let tx_index = TxIndex::default();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(TX_GAS_LIMIT.into()),
+        ));
+        let (vp_wasm_cache, _vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        let keys_changed = BTreeSet::new();
+        let verifiers = BTreeSet::new();
+        let sentinel = RefCell::new(VpSentinel::default());
+        let ctx = Ctx::new(
+            &address,
+            state,
+            &tx,
+            &tx_index,
+            &gas_meter,
+            &sentinel,
+            &keys_changed,
+            &verifiers,
+            vp_wasm_cache,
+        );
+
+        let consumed = || gas_meter.borrow().get_vp_consumed_gas();
+        let mut last_gas = consumed();
+        let mut step_gas = || {
+            let gas = consumed();
+            let step = gas.checked_sub(last_gas).unwrap();
+            last_gas = gas;
+            step
+        };
+        let mut iter = if post {
+            ctx.iter_prefix_post(&test_key())
+        } else {
+            ctx.iter_prefix_pre(&test_key())
+        }
+        .expect("iter failed");
+        let mut gas = vec![step_gas()];
+        let mut keys = vec![];
+        while let Some((key, _)) = ctx.iter_next(&mut iter).unwrap() {
+            gas.push(step_gas());
+            keys.push(key);
+        }
+        (keys, gas)
+    }
+
+    #[test]
+    fn test_iter_prefix_gas() {
+        let mut state = TestState::default();
+        let sub_key = |i: u64| test_key().push(&i).unwrap();
+        for i in 0..3 {
+            state.write(&sub_key(i), i).expect("write failed");
+        }
+        state.commit_block().expect("commit failed");
+        // a key written by the tx
+        state
+            .write_log_mut()
+            .write(&sub_key(3), 3u64.serialize_to_vec())
+            .expect("write failed");
+
+        let (keys, gas) = iter_test_prefix(&state, false);
+        let expected: Vec<_> = (0..3).map(|i| sub_key(i).to_string()).collect();
+        assert_eq!(keys, expected);
+        // the gas is charged for the prefix and then for each yielded item
+        assert_eq!(gas.len(), 4);
+        assert!(gas.iter().all(|gas| *gas > Gas::default()));
+        assert!(gas[1..].windows(2).all(|gas| gas[0] == gas[1]));
+
+        let (keys, gas) = iter_test_prefix(&state, true);
+        let expected: Vec<_> = (0..4).map(|i| sub_key(i).to_string()).collect();
+        assert_eq!(keys, expected);
+        assert_eq!(gas.len(), 5);
+        assert!(gas.iter().all(|gas| *gas > Gas::default()));
+    }
+}
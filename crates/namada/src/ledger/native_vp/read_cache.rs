@@ -0,0 +1,300 @@
+//! A read-through cache of the committed storage values shared by the native
+//! VPs validating the same transaction.
+//!
+//! The committed storage doesn't change while the VPs of a transaction are
+//! running, so a value read from it by one VP can be reused by all the others.
+//! Both the prior and the posterior state fall back to the committed storage
+//! for the keys that are not in the write log, so the entries are keyed by the
+//! storage key only. The gas is still charged per VP per access as if the
+//! value had been read from the storage.
+
+use std::collections::hash_map::RandomState;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use clru::{CLruCache, CLruCacheConfig, WeightScale};
+
+use crate::storage::Key;
+
+/// The default maximum number of bytes held by a [`VpReadCache`]
+pub const DEFAULT_READ_CACHE_BYTES: usize = 16 * 1024 * 1024;
+
+/// A value read from the committed storage with its gas cost
+#[derive(Debug, Clone)]
+struct CachedRead {
+    value: Option<Vec<u8>>,
+    gas: u64,
+}
+
+/// Weighs the cache entries by the number of bytes of the key and the value
+/// (CLruCache::len + CLruCache::weight <= CLruCache::capacity).
+#[derive(Debug)]
+struct ReadCacheScale;
+
+impl WeightScale<Key, CachedRead> for ReadCacheScale {
+    fn weight(&self, key: &Key, read: &CachedRead) -> usize {
+        key.len() + read.value.as_ref().map(Vec::len).unwrap_or_default()
+    }
+}
+
+type ReadCache = CLruCache<Key, CachedRead, RandomState, ReadCacheScale>;
+
+/// In-memory LRU cache of the committed storage reads
+pub struct VpReadCache {
+    cache: Mutex<ReadCache>,
+    storage_reads: AtomicUsize,
+}
+
+impl VpReadCache {
+    /// Create a read cache with a given size limit in bytes.
+    ///
+    /// # Panics
+    /// The `max_bytes` must be non-zero.
+    pub fn new(max_bytes: usize) -> Self {
+        let cache = CLruCache::with_config(
+            CLruCacheConfig::new(NonZeroUsize::new(max_bytes).unwrap())
+                .with_scale(ReadCacheScale),
+        );
+        Self {
+            cache: Mutex::new(cache),
+            storage_reads: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the value of the key with its gas cost from the cache, or read it
+    /// with the given function and cache it. The lock isn't held while
+    /// reading, so concurrent VPs missing the same key may read it more than
+    /// once.
+    pub fn read<E>(
+        &self,
+        key: &Key,
+        read_storage: impl FnOnce() -> Result<(Option<Vec<u8>>, u64), E>,
+    ) -> Result<(Option<Vec<u8>>, u64), E> {
+        if let Some(read) = self.cache.lock().unwrap().get(key) {
+            return Ok((read.value.clone(), read.gas));
+        }
+        let (value, gas) = read_storage()?;
+        self.storage_reads.fetch_add(1, Ordering::Relaxed);
+        // An entry larger than the capacity isn't cached
+        let _ = self.cache.lock().unwrap().put_with_weight(
+            key.clone(),
+            CachedRead {
+                value: value.clone(),
+                gas,
+            },
+        );
+        Ok((value, gas))
+    }
+
+    /// Get the number of reads that missed the cache
+    pub fn storage_reads(&self) -> usize {
+        self.storage_reads.load(Ordering::Relaxed)
+    }
+
+    /// Get the current number of bytes held in the cache
+    pub fn weight(&self) -> usize {
+        self.cache.lock().unwrap().weight()
+    }
+}
+
+impl Default for VpReadCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_READ_CACHE_BYTES)
+    }
+}
+
+impl std::fmt::Debug for VpReadCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VpReadCache")
+            .field("storage_reads", &self.storage_reads())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::BTreeSet;
+
+    use borsh_ext::BorshSerializeExt;
+    use namada_core::validity_predicate::VpSentinel;
+    use namada_gas::{Gas, TxGasMeter};
+    use namada_state::testing::TestState;
+    use namada_state::{StateRead, StorageRead, StorageWrite};
+    use namada_tx::data::TxType;
+    use namada_tx::Tx;
+
+    use super::*;
+    use crate::core::address::testing::{
+        established_address_1, established_address_2, nam,
+    };
+    use crate::core::address::{Address, InternalAddress};
+    use crate::governance::storage::keys::get_min_proposal_fund_key;
+    use crate::ibc::storage::params_key;
+    use crate::ledger::gas::VpGasMeter;
+    use crate::ledger::native_vp::Ctx;
+    use crate::parameters::storage::get_epoch_duration_storage_key;
+    use crate::storage::TxIndex;
+    use crate::token::storage_key::balance_key;
+    use crate::token::Amount;
+    use crate::vm::wasm::compilation_cache::common::testing::cache as wasm_cache;
+
+    fn new_gas_meter() -> RefCell<VpGasMeter> {
+        RefCell::new(VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(u64::MAX.into()),
+        ))
+    }
+
+    /// Read the keys with the contexts of the VPs and return the read values
+    /// and the gas consumed by each VP
+    fn read_with_vps(
+        state: &TestState,
+        vps: &[Address],
+        keys: &[Key],
+        read_cache: Option<&VpReadCache>,
+    ) -> (Vec<Option<Vec<u8>>>, Vec<Gas>) {
+        let tx_index = TxIndex::default();
+        let tx = Tx::from_type(TxType::Raw);
+        let keys_changed = BTreeSet::new();
+        let verifiers = BTreeSet::new();
+        let (vp_wasm_cache, _vp_cache_dir) = wasm_cache();
+        let mut values = vec![];
+        let mut gas = vec![];
+        for addr in vps {
+            let gas_meter = new_gas_meter();
+            let sentinel = RefCell::new(VpSentinel::default());
+            let mut ctx = Ctx::new(
+                addr,
+                state,
+                &tx,
+                &tx_index,
+                &gas_meter,
+                &sentinel,
+                &keys_changed,
+                &verifiers,
+                vp_wasm_cache.clone(),
+            );
+            if let Some(read_cache) = read_cache {
+                ctx = ctx.with_read_cache(read_cache);
+            }
+            for key in keys {
+                let pre = ctx.pre().read_bytes(key).expect("read failed");
+                let post = ctx.post().read_bytes(key).expect("read failed");
+                values.push(pre);
+                values.push(post);
+            }
+            gas.push(gas_meter.borrow().get_vp_consumed_gas());
+        }
+        (values, gas)
+    }
+
+    #[test]
+    fn test_unique_reads_once() {
+        let mut state = TestState::default();
+        let key1 = balance_key(&nam(), &established_address_1());
+        let key2 = balance_key(&nam(), &established_address_2());
+        state
+            .db_write(&key1, Amount::native_whole(100).serialize_to_vec())
+            .expect("write failed");
+        // the value in the write log should be read from it
+        state
+            .write_log_mut()
+            .write(&key1, Amount::native_whole(90).serialize_to_vec())
+            .expect("write failed");
+        let keys = [key1, key2];
+        let vps = [
+            Address::Internal(InternalAddress::Multitoken),
+            Address::Internal(InternalAddress::Ibc),
+        ];
+
+        let read_cache = VpReadCache::default();
+        let (cached_values, cached_gas) =
+            read_with_vps(&state, &vps, &keys, Some(&read_cache));
+        // each key is read from the storage once by the pre and post reads of
+        // both VPs
+        assert_eq!(read_cache.storage_reads(), keys.len());
+
+        let (values, gas) = read_with_vps(&state, &vps, &keys, None);
+        assert_eq!(cached_values, values);
+        assert_eq!(cached_gas, gas);
+    }
+
+    #[test]
+    fn test_params_read_once_per_block() {
+        let mut state = TestState::default();
+        let epoch_duration_key = get_epoch_duration_storage_key();
+        let min_fund_key = get_min_proposal_fund_key();
+        let ibc_params_key = params_key();
+        let keys = [epoch_duration_key, min_fund_key.clone(), ibc_params_key];
+        for key in &keys {
+            state
+                .db_write(key, Amount::native_whole(1).serialize_to_vec())
+                .expect("write failed");
+        }
+        let vps = [
+            Address::Internal(InternalAddress::Governance),
+            Address::Internal(InternalAddress::Ibc),
+        ];
+
+        // the parameters are read from the DB only by the first tx of the
+        // block, but the gas is charged as if by every tx
+        let (first_values, first_gas) =
+            read_with_vps(&state, &vps, &keys, None);
+        for _ in 1..20 {
+            let (values, gas) = read_with_vps(&state, &vps, &keys, None);
+            assert_eq!(values, first_values);
+            assert_eq!(gas, first_gas);
+        }
+        for key in &keys {
+            assert_eq!(state.db().subspace_reads(key), 1);
+        }
+
+        // a governance proposal changing a parameter drops its entry
+        let new_min_fund = Amount::native_whole(2).serialize_to_vec();
+        state
+            .write(&min_fund_key, new_min_fund.clone())
+            .expect("write failed");
+        let expected = Some(new_min_fund);
+        let read_cache = state.read_cache().expect("no read cache");
+        assert!(!read_cache.contains(&min_fund_key));
+        let (values, _) =
+            read_with_vps(&state, &vps, &[min_fund_key.clone()], None);
+        assert!(values.iter().all(|value| value == &expected));
+
+        // the committed parameter is read from the DB again in the next block
+        state.commit_block().expect("commit failed");
+        let (values, _) =
+            read_with_vps(&state, &vps, &[min_fund_key.clone()], None);
+        assert!(values.iter().all(|value| value == &expected));
+        assert_eq!(state.db().subspace_reads(&min_fund_key), 2);
+    }
+
+    #[test]
+    fn test_read_cache_lru_eviction() {
+        let key1 = balance_key(&nam(), &established_address_1());
+        let key2 = balance_key(&nam(), &established_address_2());
+        let value = vec![0u8; 64];
+        let entry_weight = key1.len() + value.len();
+        // only one entry can be held
+        let read_cache = VpReadCache::new(entry_weight + 1);
+        let read = || Ok::<_, ()>((Some(value.clone()), 1));
+
+        read_cache.read(&key1, read).unwrap();
+        read_cache.read(&key1, read).unwrap();
+        assert_eq!(read_cache.storage_reads(), 1);
+        // reading another key evicts the first one
+        read_cache.read(&key2, read).unwrap();
+        assert_eq!(read_cache.storage_reads(), 2);
+        assert!(read_cache.weight() <= entry_weight);
+        read_cache.read(&key1, read).unwrap();
+        assert_eq!(read_cache.storage_reads(), 3);
+
+        // an entry larger than the capacity isn't cached
+        let read_cache = VpReadCache::new(1);
+        read_cache.read(&key1, read).unwrap();
+        read_cache.read(&key1, read).unwrap();
+        assert_eq!(read_cache.storage_reads(), 2);
+        assert_eq!(read_cache.weight(), 0);
+    }
+}
@@ -96,6 +96,23 @@ where
 
                     Ok(is_valid)
                 }
+                KeyType::STEWARD_COMMISSION_RATE(address) => {
+                    // the commission rate can only be set by the steward
+                    // itself and must be in the range [0, 1]
+                    let rate_post = pgf::storage::get_steward_commission_rate(
+                        &self.ctx.post(),
+                        address,
+                    );
+                    let is_valid = match rate_post {
+                        Ok(Some(rate)) => {
+                            pgf::storage::is_valid_commission_rate(&rate)
+                                && verifiers.contains(address)
+                        }
+                        Ok(None) => verifiers.contains(address),
+                        Err(_) => false,
+                    };
+                    Ok(is_valid)
+                }
                 KeyType::FUNDINGS => Ok(false),
                 KeyType::PGF_INFLATION_RATE
                 | KeyType::STEWARD_INFLATION_RATE => {
@@ -127,10 +144,12 @@ where
 
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
-enum KeyType {
+enum KeyType<'a> {
     #[allow(non_camel_case_types)]
     STEWARDS,
     #[allow(non_camel_case_types)]
+    STEWARD_COMMISSION_RATE(&'a Address),
+    #[allow(non_camel_case_types)]
     FUNDINGS,
     #[allow(non_camel_case_types)]
     PGF_INFLATION_RATE,
@@ -142,10 +161,14 @@ enum KeyType {
     UNKNOWN,
 }
 
-impl From<&Key> for KeyType {
-    fn from(key: &Key) -> Self {
+impl<'a> From<&'a Key> for KeyType<'a> {
+    fn from(key: &'a Key) -> Self {
         if pgf_storage::is_stewards_key(key).is_some() {
             Self::STEWARDS
+        } else if let Some(address) =
+            pgf_storage::is_steward_commission_rate_key(key)
+        {
+            Self::STEWARD_COMMISSION_RATE(address)
         } else if pgf_storage::is_fundings_key(key) {
             KeyType::FUNDINGS
         } else if pgf_storage::is_pgf_inflation_rate_key(key) {
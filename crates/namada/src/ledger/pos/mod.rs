@@ -2,15 +2,23 @@
 
 pub mod vp;
 
+use std::collections::{BTreeMap, BTreeSet};
+
 use namada_core::address;
+use namada_core::borsh::BorshDeserialize;
 pub use namada_core::dec::Dec;
+use namada_core::event::{BondEvent, BondEventKind, Event};
 pub use namada_core::key::common;
+use namada_core::storage::Key;
 pub use namada_proof_of_stake::parameters::{OwnedPosParams, PosParams};
 pub use namada_proof_of_stake::pos_queries::*;
 pub use namada_proof_of_stake::storage::*;
+use namada_proof_of_stake::storage_key::is_bond_key;
 #[cfg(any(test, feature = "testing"))]
 pub use namada_proof_of_stake::test_utils;
 pub use namada_proof_of_stake::{staking_token_address, types};
+use namada_state::write_log::StorageModification;
+use namada_state::{ResultExt, State, StorageError, StorageResult};
 pub use vp::PosVP;
 pub use {namada_proof_of_stake, namada_state};
 
@@ -41,3 +49,85 @@ pub type BondId = namada_proof_of_stake::types::BondId;
 
 /// Alias for a PoS type with the same name with concrete type parameters
 pub type GenesisValidator = namada_proof_of_stake::types::GenesisValidator;
+
+/// Get the events of the PoS bonds and unbonds applied by a tx from the
+/// changes of the bonds left in the write log by the tx. These are emitted by
+/// the protocol once the tx has been accepted by the VPs, so that a tx cannot
+/// report a bond that it didn't apply. The increase of the bonds of a source
+/// to a validator is reported as a bond and their decrease as an unbond, so a
+/// redelegation is reported as an unbond from the source validator and a bond
+/// to the destination validator.
+pub fn bond_events<S>(
+    state: &S,
+    changed_keys: &BTreeSet<Key>,
+) -> StorageResult<Vec<Event>>
+where
+    S: State,
+{
+    // The bonded and unbonded amounts of the bonds
+    let mut changes: BTreeMap<BondId, (token::Amount, token::Amount)> =
+        BTreeMap::new();
+    for key in changed_keys {
+        let Some((bond_id, _start)) = is_bond_key(key) else {
+            continue;
+        };
+        let pre = read_pre_bond(state, key)?;
+        let post: token::Amount = state.read(key)?.unwrap_or_default();
+        let (bonded, unbonded) = changes.entry(bond_id).or_default();
+        let sum = if post >= pre {
+            bonded.checked_add(post - pre)
+        } else {
+            unbonded.checked_add(pre - post)
+        };
+        let sum = sum.ok_or_else(|| {
+            StorageError::new_const("Overflow of the bond changes of a tx")
+        })?;
+        if post >= pre {
+            *bonded = sum;
+        } else {
+            *unbonded = sum;
+        }
+    }
+    if changes.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // The bonds and unbonds apply to the stake at the pipeline epoch
+    let params = read_pos_params(state)?;
+    let epoch = state.in_mem().block.epoch + params.pipeline_len;
+    let mut events = vec![];
+    for (BondId { source, validator }, (bonded, unbonded)) in changes {
+        for (kind, amount) in [
+            (BondEventKind::Bond, bonded),
+            (BondEventKind::Unbond, unbonded),
+        ] {
+            if !amount.is_zero() {
+                events.push(Event::from(BondEvent {
+                    kind,
+                    validator: validator.clone(),
+                    source: source.clone(),
+                    amount,
+                    epoch,
+                }));
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// Read the amount of a bond before the latest tx
+fn read_pre_bond<S>(state: &S, key: &Key) -> StorageResult<token::Amount>
+where
+    S: State,
+{
+    let bytes = match state.write_log().read_pre(key).0 {
+        Some(StorageModification::Write { value }) => Some(value.clone()),
+        Some(_) => None,
+        None => state.db_read(key)?.0,
+    };
+    bytes
+        .map(|bytes| token::Amount::try_from_slice(&bytes))
+        .transpose()
+        .into_storage_result()
+        .map(Option::unwrap_or_default)
+}
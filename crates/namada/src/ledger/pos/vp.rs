@@ -1,14 +1,22 @@
 //! Proof-of-Stake native validity predicate.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 // use borsh::BorshDeserialize;
 pub use namada_proof_of_stake;
 pub use namada_proof_of_stake::parameters::PosParams;
 // use namada_proof_of_stake::validation::validate;
-use namada_proof_of_stake::storage::read_pos_params;
-use namada_proof_of_stake::storage_key::is_params_key;
+use namada_proof_of_stake::storage::{
+    bond_handle, is_consensus_key_used, read_pos_params,
+    validator_consensus_key_handle, validator_state_handle,
+};
+use namada_proof_of_stake::storage_key::{
+    is_bond_key, is_delegator_redelegated_bond_key, is_params_key,
+    is_slash_summaries_key, is_validator_consensus_key_key,
+};
 pub use namada_proof_of_stake::types;
+use namada_proof_of_stake::types::{BondId, ValidatorState};
+use namada_proof_of_stake::{is_validator, must_keep_min_self_bond};
 // use crate::ledger::pos::{
 //     is_validator_address_raw_hash_key,
 //     is_validator_max_commission_rate_change_key,
@@ -19,8 +27,10 @@ use namada_tx::Tx;
 use thiserror::Error;
 
 use crate::address::{Address, InternalAddress};
+use crate::key::common;
 use crate::ledger::native_vp::{self, Ctx, NativeVp};
-use crate::storage::{Key, KeySeg};
+use crate::storage::{Epoch, Key, KeySeg};
+use crate::token;
 use crate::vm::WasmCacheAccess;
 
 #[allow(missing_docs)]
@@ -52,6 +62,134 @@ where
     pub fn new(ctx: Ctx<'a, S, CA>) -> Self {
         Self { ctx }
     }
+
+    /// Check a change of the given validator's consensus key. The new key
+    /// must be an Ed25519 key that has never been used by any validator and
+    /// it may only take effect at the pipeline offset.
+    fn is_valid_consensus_key_change(
+        &self,
+        validator: &Address,
+        current_epoch: Epoch,
+        params: &PosParams,
+    ) -> Result<bool> {
+        let handle = validator_consensus_key_handle(validator);
+        let pipeline_epoch = current_epoch + params.pipeline_len;
+
+        // The keys in effect before the pipeline epoch must not change
+        for epoch in current_epoch.iter_range(params.pipeline_len) {
+            if handle.get(&self.ctx.pre(), epoch, params)?
+                != handle.get(&self.ctx.post(), epoch, params)?
+            {
+                tracing::info!(
+                    "Consensus key of validator {validator} changed before \
+                     the pipeline epoch {pipeline_epoch}"
+                );
+                return Ok(false);
+            }
+        }
+
+        let pre_key = handle.get(&self.ctx.pre(), pipeline_epoch, params)?;
+        let Some(new_key) =
+            handle.get(&self.ctx.post(), pipeline_epoch, params)?
+        else {
+            tracing::info!("Consensus key of validator {validator} removed");
+            return Ok(false);
+        };
+        if pre_key.as_ref() == Some(&new_key) {
+            return Ok(true);
+        }
+        if !matches!(new_key, common::PublicKey::Ed25519(_)) {
+            tracing::info!(
+                "The new consensus key of validator {validator} is not an \
+                 Ed25519 key"
+            );
+            return Ok(false);
+        }
+        if !is_validator(&self.ctx.post(), validator)? {
+            tracing::info!(
+                "Consensus key change of a non-validator address {validator}"
+            );
+            return Ok(false);
+        }
+        // The key must be new and recorded in the set of used keys
+        if is_consensus_key_used(&self.ctx.pre(), &new_key)?
+            || !is_consensus_key_used(&self.ctx.post(), &new_key)?
+        {
+            tracing::info!(
+                "The new consensus key of validator {validator} is not unique"
+            );
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Get the increase of a delegation's bond from the change of its bond
+    /// key, or `None` if the key is not a delegation's bond key or if the
+    /// bond hasn't increased
+    fn delegation_increase(
+        &self,
+        key: &Key,
+    ) -> Result<Option<(BondId, token::Amount)>> {
+        let Some((bond_id, _start_epoch)) = is_bond_key(key) else {
+            return Ok(None);
+        };
+        // The self-bonds are not limited
+        if bond_id.source == bond_id.validator {
+            return Ok(None);
+        }
+        let pre: token::Amount = self.ctx.pre().read(key)?.unwrap_or_default();
+        let post: token::Amount =
+            self.ctx.post().read(key)?.unwrap_or_default();
+        Ok(post
+            .checked_sub(pre)
+            .filter(|increase| !increase.is_zero())
+            .map(|increase| (bond_id, increase)))
+    }
+
+    /// Check a change of the given validator's self-bond. A validator in the
+    /// consensus or below-capacity validator sets at the pipeline epoch may
+    /// not decrease its self-bond below the minimum, unless it's deactivated
+    /// by the tx.
+    fn is_valid_self_bond_change(
+        &self,
+        validator: &Address,
+        current_epoch: Epoch,
+        params: &PosParams,
+    ) -> Result<bool> {
+        let pipeline_epoch = current_epoch + params.pipeline_len;
+        let handle = bond_handle(validator, validator);
+        let pre = handle
+            .get_sum(&self.ctx.pre(), pipeline_epoch, params)?
+            .unwrap_or_default();
+        let post = handle
+            .get_sum(&self.ctx.post(), pipeline_epoch, params)?
+            .unwrap_or_default();
+        if post >= pre || post >= params.min_validator_self_bond {
+            return Ok(true);
+        }
+        let is_deactivated = validator_state_handle(validator).get(
+            &self.ctx.post(),
+            pipeline_epoch,
+            params,
+        )? == Some(ValidatorState::Inactive);
+        if must_keep_min_self_bond(
+            &self.ctx.pre(),
+            validator,
+            pipeline_epoch,
+            params,
+        )? && !is_deactivated
+        {
+            tracing::info!(
+                "The self-bond of validator {validator} decreased to {} at \
+                 the pipeline epoch {pipeline_epoch}, below the minimum \
+                 validator self-bond {}",
+                post.to_string_native(),
+                params.min_validator_self_bond.to_string_native()
+            );
+            return Ok(false);
+        }
+        Ok(true)
+    }
 }
 
 impl<'a, S, CA> NativeVp for PosVP<'a, S, CA>
@@ -73,7 +211,16 @@ where
 
         let addr = Address::Internal(InternalAddress::PoS);
         // let mut changes: Vec<DataUpdate> = vec![];
-        let _current_epoch = self.ctx.pre().get_block_epoch()?;
+        let current_epoch = self.ctx.pre().get_block_epoch()?;
+        let params = read_pos_params(&self.ctx.pre())?;
+        // The validators whose consensus key has been changed
+        let mut consensus_key_changes = BTreeSet::new();
+        // The increases of the delegations and the bonds receiving
+        // redelegated tokens
+        let mut delegation_increases = BTreeMap::<BondId, token::Amount>::new();
+        let mut redelegated_bonds = BTreeSet::new();
+        // The validators whose self-bond has been changed
+        let mut self_bond_changes = BTreeSet::new();
 
         tracing::debug!("\nValidating PoS Tx\n");
 
@@ -92,6 +239,32 @@ where
                 {
                     return Ok(false);
                 }
+            } else if is_slash_summaries_key(key) {
+                // The slash summaries may only be written by the protocol
+                tracing::info!(
+                    "PoS slash summaries key change {} rejected",
+                    key
+                );
+                return Ok(false);
+            } else if let Some(validator) = is_validator_consensus_key_key(key)
+            {
+                consensus_key_changes.insert(validator);
+            } else if let Some((bond_id, increase)) =
+                self.delegation_increase(key)?
+            {
+                *delegation_increases.entry(bond_id).or_default() += increase;
+            } else if let Some((bond_id, _start_epoch)) = is_bond_key(key)
+                .filter(|(bond_id, _)| bond_id.source == bond_id.validator)
+            {
+                self_bond_changes.insert(bond_id.validator);
+            } else if let Some((delegator, dest_validator, _src_validator)) =
+                is_delegator_redelegated_bond_key(key)
+            {
+                // Only the destination's bond is increased by a redelegation
+                redelegated_bonds.insert(BondId {
+                    source: delegator.clone(),
+                    validator: dest_validator.clone(),
+                });
             } else if key.segments.first() == Some(&addr.to_db_key()) {
                 // Unknown changes to this address space are disallowed
                 // tracing::info!("PoS unrecognized key change {} rejected",
@@ -107,7 +280,43 @@ where
             }
         }
 
-        let _params = read_pos_params(&self.ctx.pre())?;
+        for validator in consensus_key_changes {
+            if !self.is_valid_consensus_key_change(
+                validator,
+                current_epoch,
+                &params,
+            )? {
+                return Ok(false);
+            }
+        }
+
+        for validator in self_bond_changes {
+            if !self.is_valid_self_bond_change(
+                &validator,
+                current_epoch,
+                &params,
+            )? {
+                return Ok(false);
+            }
+        }
+
+        // The redelegated bonds are not new delegations
+        for (bond_id, increase) in delegation_increases {
+            if !redelegated_bonds.contains(&bond_id)
+                && increase < params.min_delegation_amount
+            {
+                tracing::info!(
+                    "The delegation of {} from {} to the validator {} is \
+                     below the minimum delegation amount {}",
+                    increase.to_string_native(),
+                    bond_id.source,
+                    bond_id.validator,
+                    params.min_delegation_amount.to_string_native()
+                );
+                return Ok(false);
+            }
+        }
+
         // let errors = validate(&params, changes, current_epoch);
         // Ok(if errors.is_empty() {
         //     true
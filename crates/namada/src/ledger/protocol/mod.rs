@@ -1,6 +1,6 @@
 //! The ledger's protocol
 use std::cell::RefCell;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
 
 use borsh_ext::BorshSerializeExt;
@@ -9,12 +9,13 @@ use masp_primitives::transaction::Transaction;
 use namada_core::hash::Hash;
 use namada_core::storage::Key;
 use namada_core::validity_predicate::VpSentinel;
-use namada_gas::TxGasMeter;
+use namada_gas::{Gas, TxGasMeter, UNSCOPED_GAS_LABEL};
 use namada_sdk::tx::TX_TRANSFER_WASM;
 use namada_state::StorageWrite;
 use namada_tx::data::protocol::ProtocolTxType;
 use namada_tx::data::{
-    DecryptedTx, GasLimit, TxResult, TxType, VpsResult, WrapperTx,
+    truncate_error, DecryptedTx, GasLimit, TxResult, TxType, VpsResult,
+    WrapperTx,
 };
 use namada_tx::{Section, Tx};
 use namada_vote_ext::EthereumTxData;
@@ -31,6 +32,7 @@ use crate::ledger::native_vp::ibc::Ibc;
 use crate::ledger::native_vp::masp::MaspVp;
 use crate::ledger::native_vp::multitoken::MultitokenVp;
 use crate::ledger::native_vp::parameters::{self, ParametersVp};
+use crate::ledger::native_vp::read_cache::VpReadCache;
 use crate::ledger::native_vp::{self, NativeVp};
 use crate::ledger::pgf::PgfVp;
 use crate::ledger::pos::{self, PosVP};
@@ -41,6 +43,12 @@ use crate::token::Amount;
 use crate::vm::wasm::{TxCache, VpCache};
 use crate::vm::{self, wasm, WasmCacheAccess};
 
+/// The gas breakdown label of the gas consumed by wasm VPs
+pub const WASM_VP_GAS_LABEL: &str = "wasm_vp";
+/// The gas breakdown label of the gas consumed by native VPs outside of
+/// their own labels
+pub const NATIVE_VP_GAS_LABEL: &str = "native_vp";
+
 #[allow(missing_docs)]
 #[derive(Error, Debug)]
 pub enum Error {
@@ -98,6 +106,15 @@ pub enum Error {
     AccessForbidden(InternalAddress),
     #[error("Tx is not allowed in allowlist parameter.")]
     DisallowedTx,
+    #[error(
+        "The tx modified {count} storage keys, above the max of {max_count}"
+    )]
+    TxWriteCountExceeded { count: u64, max_count: u64 },
+    #[error(
+        "The tx modified {bytes} bytes of storage, above the max of \
+         {max_bytes}"
+    )]
+    TxWriteBytesExceeded { bytes: u64, max_bytes: u64 },
 }
 
 /// Shell parameters for running wasm transactions.
@@ -150,6 +167,28 @@ pub struct WrapperArgs<'a> {
     pub is_committed_fee_unshield: bool,
 }
 
+/// Get the storage prefixes that only the protocol may write under. The txs
+/// are rejected by the write log of a state built with them when they try to
+/// modify a key under any of these prefixes.
+///
+/// Deliberately not reserved:
+///   - IBC counters and sequences are written by IBC transactions.
+///   - The governance proposal counter is incremented by `init_proposal`.
+///   - The protocol parameters are written by the code of accepted governance
+///     proposals and are guarded by the parameters VP.
+pub fn reserved_prefixes() -> storage::ReservedPrefixes {
+    let mut reserved_prefixes = storage::ReservedPrefixes::default();
+    for prefix in crate::ethereum_bridge::storage::reserved_prefixes()
+        .into_iter()
+        .chain(crate::proof_of_stake::storage::reserved_prefixes())
+    {
+        reserved_prefixes
+            .register(prefix)
+            .expect("The protocol reserved prefixes must not overlap");
+    }
+    reserved_prefixes
+}
+
 /// Dispatch a given transaction to be applied based on its type. Some storage
 /// updates may be derived and applied natively rather than via the wasm
 /// environment, in which case validity predicates will be bypassed.
@@ -209,7 +248,8 @@ where
                 changed_keys,
                 vps_result: VpsResult::default(),
                 initialized_accounts: vec![],
-                ibc_events: BTreeSet::default(),
+                ibc_events: Vec::new(),
+                events: Vec::new(),
                 eth_bridge_events: BTreeSet::default(),
             })
         }
@@ -609,12 +649,21 @@ where
         tx_gas_meter: &mut tx_gas_meter.borrow_mut(),
         verifiers_from_tx: &verifiers,
         vp_wasm_cache,
+        native_vps_only: false,
     })?;
 
     let gas_used = tx_gas_meter.borrow().get_tx_consumed_gas();
     let initialized_accounts = state.write_log().get_initialized_accounts();
     let changed_keys = state.write_log().get_keys();
     let ibc_events = state.write_log_mut().take_ibc_events();
+    let mut events = state.write_log_mut().take_events();
+    // The PoS events are emitted once the tx has been accepted by the VPs
+    if vps_result.rejected_vps.is_empty() {
+        events.extend(
+            pos::bond_events(state, &changed_keys)
+                .map_err(Error::StorageError)?,
+        );
+    }
 
     Ok(TxResult {
         gas_used,
@@ -622,6 +671,102 @@ where
         vps_result,
         initialized_accounts,
         ibc_events,
+        events,
+        eth_bridge_events: BTreeSet::default(),
+    })
+}
+
+/// Execute a transaction and check its acceptance by the native VPs of the
+/// internal addresses that it touches, the same way as [`apply_wasm_tx`] does.
+/// The VPs of the implicit and established addresses are not run. The changes
+/// are left in the write log of the given state, which is expected to be
+/// dropped by the caller.
+pub fn dry_run_native_vps<'a, S, D, H, CA>(
+    tx: Tx,
+    tx_index: &TxIndex,
+    shell_params: ShellParams<'a, S, D, H, CA>,
+) -> Result<VpsResult>
+where
+    S: State<D = D, H = H> + Sync,
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+    CA: 'static + WasmCacheAccess + Sync,
+{
+    let ShellParams {
+        tx_gas_meter,
+        state,
+        vp_wasm_cache,
+        tx_wasm_cache,
+    } = shell_params;
+
+    let verifiers = execute_tx(
+        &tx,
+        tx_index,
+        state,
+        tx_gas_meter,
+        vp_wasm_cache,
+        tx_wasm_cache,
+    )?;
+
+    check_vps(CheckVps {
+        tx: &tx,
+        tx_index,
+        state,
+        tx_gas_meter: &mut tx_gas_meter.borrow_mut(),
+        verifiers_from_tx: &verifiers,
+        vp_wasm_cache,
+        native_vps_only: true,
+    })
+}
+
+/// Check the acceptance of the changes of a tx, which are already in the tx
+/// write log of the given state, by the native VPs of the internal addresses
+/// that it touches and collect the result of the tx, the same way as
+/// [`apply_wasm_tx`] does after executing the tx. This validates the txs
+/// applied without the wasm environment, e.g. the scripted txs of a
+/// simulation.
+pub fn apply_native_vps<S, CA>(
+    tx: &Tx,
+    tx_index: &TxIndex,
+    state: &mut S,
+    tx_gas_meter: &RefCell<TxGasMeter>,
+    verifiers_from_tx: &BTreeSet<Address>,
+    vp_wasm_cache: &mut VpCache<CA>,
+) -> Result<TxResult>
+where
+    S: State + Sync,
+    CA: 'static + WasmCacheAccess + Sync,
+{
+    let vps_result = check_vps(CheckVps {
+        tx,
+        tx_index,
+        state,
+        tx_gas_meter: &mut tx_gas_meter.borrow_mut(),
+        verifiers_from_tx,
+        vp_wasm_cache,
+        native_vps_only: true,
+    })?;
+
+    let gas_used = tx_gas_meter.borrow().get_tx_consumed_gas();
+    let initialized_accounts = state.write_log().get_initialized_accounts();
+    let changed_keys = state.write_log().get_keys();
+    let ibc_events = state.write_log_mut().take_ibc_events();
+    let mut events = state.write_log_mut().take_events();
+    // The PoS events are emitted once the tx has been accepted by the VPs
+    if vps_result.rejected_vps.is_empty() {
+        events.extend(
+            pos::bond_events(state, &changed_keys)
+                .map_err(Error::StorageError)?,
+        );
+    }
+
+    Ok(TxResult {
+        gas_used,
+        changed_keys,
+        vps_result,
+        initialized_accounts,
+        ibc_events,
+        events,
         eth_bridge_events: BTreeSet::default(),
     })
 }
@@ -764,9 +909,36 @@ where
     tx_gas_meter: &'a mut TxGasMeter,
     verifiers_from_tx: &'a BTreeSet<Address>,
     vp_wasm_cache: &'a mut VpCache<CA>,
+    /// Only run the VPs of the internal addresses
+    native_vps_only: bool,
+}
+
+/// Check that the storage modifications of a transaction are within the
+/// `max_tx_write_count` and `max_tx_write_bytes` protocol parameters, so that
+/// the validity predicates and the merkle tree update don't have to process
+/// an unbounded number of keys.
+fn check_tx_write_limits<S>(state: &S) -> Result<()>
+where
+    S: State,
+{
+    let max_count = crate::parameters::max_tx_write_count(state)
+        .map_err(Error::StorageError)?;
+    let count = state.write_log().tx_write_count();
+    if count > max_count {
+        return Err(Error::TxWriteCountExceeded { count, max_count });
+    }
+    let max_bytes = crate::parameters::max_tx_write_bytes(state)
+        .map_err(Error::StorageError)?;
+    let bytes = state.write_log().tx_write_bytes();
+    if bytes > max_bytes {
+        return Err(Error::TxWriteBytesExceeded { bytes, max_bytes });
+    }
+    Ok(())
 }
 
-/// Check the acceptance of a transaction by validity predicates
+/// Check the acceptance of a transaction by validity predicates. The
+/// transaction is rejected before running any of them if its storage
+/// modifications exceed the limits.
 fn check_vps<S, CA>(
     CheckVps {
         tx,
@@ -775,12 +947,15 @@ fn check_vps<S, CA>(
         tx_gas_meter,
         verifiers_from_tx,
         vp_wasm_cache,
+        native_vps_only,
     }: CheckVps<'_, S, CA>,
 ) -> Result<VpsResult>
 where
     S: State + Sync,
     CA: 'static + WasmCacheAccess + Sync,
 {
+    check_tx_write_limits(state)?;
+
     let (verifiers, keys_changed) = state
         .write_log()
         .verifiers_and_changed_keys(verifiers_from_tx);
@@ -793,6 +968,7 @@ where
         state,
         tx_gas_meter,
         vp_wasm_cache,
+        native_vps_only,
     )?;
     tracing::debug!("Total VPs gas cost {:?}", vps_result.gas_used);
 
@@ -803,7 +979,9 @@ where
     Ok(vps_result)
 }
 
-/// Execute verifiers' validity predicates
+/// Execute verifiers' validity predicates. When `native_vps_only` is set, the
+/// VPs of the implicit and established addresses are skipped, but they are
+/// still passed on as verifiers.
 #[allow(clippy::too_many_arguments)]
 fn execute_vps<S, CA>(
     verifiers: BTreeSet<Address>,
@@ -813,16 +991,28 @@ fn execute_vps<S, CA>(
     state: &S,
     tx_gas_meter: &TxGasMeter,
     vp_wasm_cache: &mut VpCache<CA>,
+    native_vps_only: bool,
 ) -> Result<VpsResult>
 where
     S: State + Sync,
     CA: 'static + WasmCacheAccess + Sync,
 {
+    // The committed storage reads are shared by the native VPs
+    let read_cache = VpReadCache::default();
+    let max_error_bytes = crate::parameters::max_tx_error_bytes(state)
+        .map_err(Error::StorageError)?;
     let vps_result = verifiers
         .par_iter()
+        .filter(|addr| !native_vps_only || matches!(addr, Address::Internal(_)))
         .try_fold(VpsResult::default, |mut result, addr| {
             let gas_meter =
                 RefCell::new(VpGasMeter::new_from_tx_meter(tx_gas_meter));
+            let unscoped_gas_label = match &addr {
+                Address::Implicit(_) | Address::Established(_) => {
+                    WASM_VP_GAS_LABEL
+                }
+                Address::Internal(_) => NATIVE_VP_GAS_LABEL,
+            };
             let accept = match &addr {
                 Address::Implicit(_) | Address::Established(_) => {
                     let (vp_hash, gas) = state
@@ -872,7 +1062,8 @@ where
                         &keys_changed,
                         &verifiers,
                         vp_wasm_cache.clone(),
-                    );
+                    )
+                    .with_read_cache(&read_cache);
 
                     let accepted: Result<bool> = match internal_addr {
                         InternalAddress::PoS => {
@@ -943,6 +1134,13 @@ where
                             masp.validate_tx(tx, &keys_changed, &verifiers)
                                 .map_err(Error::MaspNativeVpError)
                         }
+                        InternalAddress::IbcFeeEscrow => {
+                            // The balances of the fee escrow are validated
+                            // by the IBC VP
+                            Ok(verifiers.contains(&Address::Internal(
+                                InternalAddress::Ibc,
+                            )))
+                        }
                     };
 
                     accepted.map_err(|err| {
@@ -957,6 +1155,17 @@ where
                 }
             };
 
+            if let Address::Internal(internal_addr) = &addr {
+                if let Some(reason) = native_vp_rejection_reason(
+                    state,
+                    internal_addr,
+                    &keys_changed,
+                    &accept,
+                ) {
+                    result.rejection_reasons.insert(addr.clone(), reason);
+                }
+            }
+
             match accept {
                 Ok(accepted) => {
                     if accepted {
@@ -983,14 +1192,32 @@ where
                     }
                     _ => {
                         result.rejected_vps.insert(addr.clone());
-                        result.errors.push((addr.clone(), err.to_string()));
+                        result.errors.push((
+                            addr.clone(),
+                            bound_vp_error(tx, addr, err, max_error_bytes),
+                        ));
                     }
                 },
             }
 
+            let gas_meter = gas_meter.into_inner();
+            if let Some(breakdown) = gas_meter.gas_breakdown() {
+                for (label, gas) in breakdown {
+                    let label = if *label == UNSCOPED_GAS_LABEL {
+                        unscoped_gas_label
+                    } else {
+                        label
+                    };
+                    add_gas_breakdown(
+                        &mut result.gas_breakdown,
+                        label.to_string(),
+                        *gas,
+                    )?;
+                }
+            }
             result
                 .gas_used
-                .set(gas_meter.into_inner())
+                .set(gas_meter)
                 .map_err(|err| Error::GasError(err.to_string()))?;
 
             Ok(result)
@@ -1002,6 +1229,75 @@ where
     Ok(vps_result)
 }
 
+/// Get the coarse reason of a rejection of a tx by a native VP to label the
+/// metrics, derived from the typed error of the VP. The rejections of the
+/// governance VP are labeled by the types of the changed keys.
+fn native_vp_rejection_reason<S>(
+    state: &S,
+    internal_addr: &InternalAddress,
+    keys_changed: &BTreeSet<storage::Key>,
+    accept: &Result<bool>,
+) -> Option<&'static str>
+where
+    S: State,
+{
+    let reason = match accept {
+        // Running out of gas aborts the tx rather than rejecting it
+        Ok(true) | Err(Error::GasError(_)) => return None,
+        Ok(false) => match internal_addr {
+            InternalAddress::Governance => {
+                crate::ledger::governance::rejection_reason(
+                    keys_changed,
+                    &state.in_mem().native_token,
+                )
+            }
+            _ => "rejected",
+        },
+        Err(Error::IbcNativeVpError(err)) => err.label(),
+        Err(Error::GovernanceNativeVpError(err)) => err.label(),
+        Err(Error::AccessForbidden(_)) => "access_forbidden",
+        Err(_) => "error",
+    };
+    Some(reason)
+}
+
+/// Render the error of a VP to be stored in the tx result, truncated to the
+/// given number of bytes. The full error of a truncated message is logged.
+fn bound_vp_error(
+    tx: &Tx,
+    addr: &Address,
+    err: Error,
+    max_bytes: u64,
+) -> String {
+    let msg = err.to_string();
+    let max_bytes = usize::try_from(max_bytes).unwrap_or(usize::MAX);
+    match truncate_error(&msg, max_bytes) {
+        Some(truncated) => {
+            tracing::info!(
+                tx_hash = %tx.header_hash(),
+                vp = %addr,
+                error = %msg,
+                "The VP error has been truncated in the tx result"
+            );
+            truncated
+        }
+        None => msg,
+    }
+}
+
+/// Add the gas consumed under the given label to a gas breakdown
+fn add_gas_breakdown(
+    breakdown: &mut BTreeMap<String, Gas>,
+    label: String,
+    gas: Gas,
+) -> Result<()> {
+    let entry = breakdown.entry(label).or_default();
+    *entry = entry.checked_add(gas).ok_or_else(|| {
+        Error::GasError("Overflow in the gas breakdown".into())
+    })?;
+    Ok(())
+}
+
 /// Merge VP results from parallel runs
 fn merge_vp_results(
     a: VpsResult,
@@ -1016,6 +1312,12 @@ fn merge_vp_results(
     errors.append(&mut b.errors);
     let invalid_sig = a.invalid_sig || b.invalid_sig;
     let mut gas_used = a.gas_used;
+    let mut gas_breakdown = a.gas_breakdown;
+    for (label, gas) in b.gas_breakdown {
+        add_gas_breakdown(&mut gas_breakdown, label, gas)?;
+    }
+    let mut rejection_reasons = a.rejection_reasons;
+    rejection_reasons.append(&mut b.rejection_reasons);
 
     gas_used
         .merge(b.gas_used, tx_gas_meter)
@@ -1027,6 +1329,8 @@ fn merge_vp_results(
         gas_used,
         errors,
         invalid_sig,
+        gas_breakdown,
+        rejection_reasons,
     })
 }
 
@@ -1040,7 +1344,7 @@ mod tests {
     use namada_core::ethereum_events::testing::DAI_ERC20_ETH_ADDRESS;
     use namada_core::ethereum_events::{EthereumEvent, TransferToNamada};
     use namada_core::keccak::keccak_hash;
-    use namada_core::storage::BlockHeight;
+    use namada_core::storage::{BlockHeight, KeySeg};
     use namada_core::voting_power::FractionalVotingPower;
     use namada_core::{address, key};
     use namada_ethereum_bridge::protocol::transactions::votes::{
@@ -1050,6 +1354,9 @@ mod tests {
     use namada_ethereum_bridge::storage::proof::EthereumProof;
     use namada_ethereum_bridge::storage::{vote_tallies, vp};
     use namada_ethereum_bridge::test_utils;
+    use namada_state::testing::TestState;
+    use namada_state::TxHostEnvState;
+    use namada_tx::data::{TxSentinel, VpVerdict};
     use namada_tx::{SignableEthMessage, Signed};
     use namada_vote_ext::bridge_pool_roots::BridgePoolRootVext;
     use namada_vote_ext::ethereum_events::EthereumEventsVext;
@@ -1227,4 +1534,277 @@ mod tests {
             }
         }
     }
+
+    /// Write the given number of values of the given size under the PoS
+    /// address, whose native VP accepts them, in the tx write log
+    fn write_pos_keys(state: &mut TestState, count: u64, value_len: usize) {
+        let pos = Address::Internal(InternalAddress::PoS);
+        for i in 0..count {
+            let key = Key::from(pos.to_db_key())
+                .push(&format!("test_{i}"))
+                .unwrap();
+            state
+                .write_log_mut()
+                .write(&key, vec![0; value_len])
+                .unwrap();
+        }
+    }
+
+    /// Check the VPs of the tx write log. Returns the result and the gas
+    /// consumed by the VPs.
+    fn run_check_vps(state: &TestState) -> (super::Result<VpsResult>, Gas) {
+        run_check_vps_with(state, false)
+    }
+
+    /// Check the VPs of the tx write log, optionally only the native ones.
+    /// Returns the result and the gas consumed by the VPs.
+    fn run_check_vps_with(
+        state: &TestState,
+        native_vps_only: bool,
+    ) -> (super::Result<VpsResult>, Gas) {
+        let tx = Tx::new(ChainId::default(), None);
+        run_check_vps_of_tx(state, &tx, native_vps_only)
+    }
+
+    /// Check the VPs of the write log of the given tx, optionally only the
+    /// native ones as [`dry_run_native_vps`] does after executing the tx.
+    /// Returns the result and the gas consumed by the VPs.
+    fn run_check_vps_of_tx(
+        state: &TestState,
+        tx: &Tx,
+        native_vps_only: bool,
+    ) -> (super::Result<VpsResult>, Gas) {
+        let mut tx_gas_meter = TxGasMeter::new(u64::MAX);
+        let (mut vp_wasm_cache, _) =
+            wasm::compilation_cache::common::testing::cache();
+        let result = check_vps(CheckVps {
+            tx,
+            tx_index: &TxIndex::default(),
+            state,
+            tx_gas_meter: &mut tx_gas_meter,
+            verifiers_from_tx: &BTreeSet::new(),
+            vp_wasm_cache: &mut vp_wasm_cache,
+            native_vps_only,
+        });
+        (result, tx_gas_meter.get_tx_consumed_gas())
+    }
+
+    #[test]
+    fn test_tx_write_count_limit() {
+        let pos = Address::Internal(InternalAddress::PoS);
+        let (mut state, _validators) = test_utils::setup_default_storage();
+        crate::parameters::update_max_tx_write_count(&mut state, 3).unwrap();
+        state.commit_tx();
+
+        // A tx at the cap is validated by the VPs
+        write_pos_keys(&mut state, 3, 1);
+        let (result, vps_gas) = run_check_vps(&state);
+        let vps_result = result.unwrap();
+        assert!(vps_result.accepted_vps.contains(&pos));
+        assert!(vps_gas > Gas::default());
+        state.drop_tx();
+
+        // A tx above the cap is rejected without running any VP
+        write_pos_keys(&mut state, 4, 1);
+        let (result, vps_gas) = run_check_vps(&state);
+        assert_matches!(
+            result.unwrap_err(),
+            Error::TxWriteCountExceeded {
+                count: 4,
+                max_count: 3
+            }
+        );
+        assert_eq!(vps_gas, Gas::default());
+    }
+
+    #[test]
+    fn test_tx_write_bytes_limit() {
+        let (mut state, _validators) = test_utils::setup_default_storage();
+        crate::parameters::update_max_tx_write_bytes(&mut state, 1024).unwrap();
+        state.commit_tx();
+
+        // A tx within the cap is validated by the VPs
+        write_pos_keys(&mut state, 1, 512);
+        let (result, _) = run_check_vps(&state);
+        assert!(result.is_ok());
+        state.drop_tx();
+
+        // A tx above the cap is rejected without running any VP
+        write_pos_keys(&mut state, 1, 1024);
+        let (result, vps_gas) = run_check_vps(&state);
+        assert_matches!(
+            result.unwrap_err(),
+            Error::TxWriteBytesExceeded {
+                max_bytes: 1024,
+                ..
+            }
+        );
+        assert_eq!(vps_gas, Gas::default());
+    }
+
+    #[test]
+    fn test_check_native_vps_only() {
+        let pos = Address::Internal(InternalAddress::PoS);
+        let owner = address::testing::established_address_1();
+        let (mut state, _validators) = test_utils::setup_default_storage();
+        write_pos_keys(&mut state, 1, 1);
+        // The owner has no VP in storage
+        let key = Key::from(owner.to_db_key())
+            .push(&"test".to_string())
+            .unwrap();
+        state.write_log_mut().write(&key, vec![0]).unwrap();
+
+        // All the VPs are run
+        let (result, _) = run_check_vps(&state);
+        assert_matches!(
+            result.unwrap_err(),
+            Error::MissingAddress(addr) if addr == owner
+        );
+
+        // Only the native VPs are run
+        let (result, _) = run_check_vps_with(&state, true);
+        let vps_result = result.unwrap();
+        assert_eq!(
+            vps_result.verdicts().get(&pos),
+            Some(&namada_tx::data::VpVerdict::Accepted)
+        );
+        assert!(!vps_result.verdicts().contains_key(&owner));
+    }
+
+    /// A bond is accepted by the native VPs of its dry run
+    #[test]
+    fn test_dry_run_native_vps_bond() {
+        let pos = Address::Internal(InternalAddress::PoS);
+        let validator = address::testing::established_address_1();
+        let owner = address::testing::established_address_2();
+        let (mut state, _validators) = test_utils::setup_default_storage();
+        let native_token = state.in_mem().native_token.clone();
+        let amount = Amount::native_whole(10);
+        crate::token::credit_tokens(&mut state, &native_token, &owner, amount)
+            .unwrap();
+
+        // Bond in the tx write log as the bond tx would
+        let current_epoch = state.in_mem().block.epoch;
+        let tx_gas_meter = RefCell::new(TxGasMeter::new(u64::MAX));
+        let sentinel = RefCell::new(TxSentinel::default());
+        let (write_log, in_mem, db) = state.split_borrow();
+        let mut tx_state = TxHostEnvState {
+            write_log,
+            db,
+            in_mem,
+            gas_meter: &tx_gas_meter,
+            sentinel: &sentinel,
+        };
+        crate::proof_of_stake::bond_tokens(
+            &mut tx_state,
+            Some(&owner),
+            &validator,
+            amount,
+            current_epoch,
+            None,
+        )
+        .unwrap();
+
+        let (result, _) = run_check_vps_with(&state, true);
+        let verdicts = result.unwrap().verdicts();
+        assert_eq!(verdicts.get(&pos), Some(&VpVerdict::Accepted));
+        assert!(
+            verdicts
+                .values()
+                .all(|verdict| *verdict == VpVerdict::Accepted)
+        );
+    }
+
+    /// An IBC client creation is accepted by the native VPs of its dry run,
+    /// while the one with the events out of order is rejected
+    #[test]
+    fn test_dry_run_native_vps_create_client() {
+        use crate::ledger::native_vp::ibc::tests::{
+            init_storage, write_create_client,
+        };
+
+        let ibc = Address::Internal(InternalAddress::Ibc);
+        let dry_run_create_client = |reverse_events: bool| {
+            let mut state = init_storage();
+            state.commit_tx();
+            let (_, tx_data, _) =
+                write_create_client(&mut state, None, reverse_events);
+            let mut tx = Tx::new(state.in_mem().chain_id.clone(), None);
+            tx.add_serialized_data(tx_data);
+            let (result, _) = run_check_vps_of_tx(&state, &tx, true);
+            result.unwrap().verdicts()
+        };
+
+        assert_eq!(
+            dry_run_create_client(false),
+            BTreeMap::from([(ibc.clone(), VpVerdict::Accepted)])
+        );
+        assert_matches!(
+            dry_run_create_client(true).get(&ibc),
+            Some(VpVerdict::Rejected(Some(_)))
+        );
+    }
+
+    #[test]
+    fn test_native_vp_rejection_metrics() {
+        use namada_governance::storage::keys as gov_storage;
+        use namada_governance::ProposalVote;
+
+        use crate::ledger::metrics::{record_vp_rejections, vp_rejections};
+
+        let ibc = InternalAddress::Ibc;
+        let governance = InternalAddress::Governance;
+        let (mut state, _validators) = test_utils::setup_default_storage();
+        state.write(&gov_storage::get_counter_key(), 0_u64).unwrap();
+        state.commit_tx();
+
+        // The IBC VP fails without an IBC message in the tx data
+        state
+            .write_log_mut()
+            .write(&crate::ibc::storage::client_counter_key(), vec![0])
+            .unwrap();
+        // The governance VP rejects a vote on a proposal that doesn't exist
+        let vote_key = gov_storage::get_vote_proposal_key(
+            0,
+            address::testing::established_address_1(),
+            address::testing::established_address_2(),
+        );
+        state
+            .write_log_mut()
+            .write(&vote_key, ProposalVote::Yay.serialize_to_vec())
+            .unwrap();
+
+        let ibc_rejections = vp_rejections(&ibc, "no_tx_data");
+        let governance_rejections = vp_rejections(&governance, "vote");
+        let (result, _) = run_check_vps_with(&state, true);
+        let vps_result = result.unwrap();
+        assert!(
+            vps_result
+                .rejected_vps
+                .contains(&Address::Internal(ibc.clone()))
+        );
+        assert!(
+            vps_result
+                .rejected_vps
+                .contains(&Address::Internal(governance.clone()))
+        );
+        assert_eq!(
+            vps_result.rejection_reasons,
+            BTreeMap::from([
+                (Address::Internal(ibc.clone()), "no_tx_data"),
+                (Address::Internal(governance.clone()), "vote"),
+            ])
+        );
+        // Running the VPs doesn't count the rejections, only recording the
+        // result of a tx applied in a block does
+        assert_eq!(vp_rejections(&ibc, "no_tx_data"), ibc_rejections);
+        assert_eq!(vp_rejections(&governance, "vote"), governance_rejections);
+
+        record_vp_rejections(&vps_result);
+        assert_eq!(vp_rejections(&ibc, "no_tx_data"), ibc_rejections + 1);
+        assert_eq!(
+            vp_rejections(&governance, "vote"),
+            governance_rejections + 1
+        );
+    }
 }
@@ -20,6 +20,7 @@ use thiserror::Error;
 use crate::ibc::IbcEvent;
 use crate::ledger::gas;
 use crate::ledger::gas::{GasMetering, VpGasMeter};
+use crate::ledger::native_vp::read_cache::VpReadCache;
 
 /// These runtime errors will abort VP execution immediately
 #[allow(missing_docs)]
@@ -70,6 +71,7 @@ pub fn read_pre<S>(
     state: &S,
     key: &Key,
     sentinel: &RefCell<VpSentinel>,
+    read_cache: Option<&VpReadCache>,
 ) -> EnvResult<Option<Vec<u8>>>
 where
     S: StateRead + Debug,
@@ -95,8 +97,7 @@ where
         }
         None => {
             // When not found in write log, try to read from the storage
-            let (value, gas) =
-                state.db_read(key).map_err(RuntimeError::StorageError)?;
+            let (value, gas) = db_read(state, key, read_cache)?;
             add_gas(gas_meter, gas, sentinel)?;
             Ok(value)
         }
@@ -110,6 +111,7 @@ pub fn read_post<S>(
     state: &S,
     key: &Key,
     sentinel: &RefCell<VpSentinel>,
+    read_cache: Option<&VpReadCache>,
 ) -> EnvResult<Option<Vec<u8>>>
 where
     S: StateRead + Debug,
@@ -136,14 +138,49 @@ where
         }
         None => {
             // When not found in write log, try to read from the storage
-            let (value, gas) =
-                state.db_read(key).map_err(RuntimeError::StorageError)?;
+            let (value, gas) = db_read(state, key, read_cache)?;
             add_gas(gas_meter, gas, sentinel)?;
             Ok(value)
         }
     }
 }
 
+/// Storage read at a past block height. It only reads the committed storage
+/// and the height must be within the retained diffs.
+pub fn read_at_height<S>(
+    gas_meter: &RefCell<VpGasMeter>,
+    state: &S,
+    key: &Key,
+    height: BlockHeight,
+    sentinel: &RefCell<VpSentinel>,
+) -> EnvResult<Option<Vec<u8>>>
+where
+    S: StateRead + Debug,
+{
+    let (value, gas) = state
+        .db_read_at_height(key, height)
+        .map_err(RuntimeError::StorageError)?;
+    add_gas(gas_meter, gas, sentinel)?;
+    Ok(value)
+}
+
+/// Read the committed storage through the read cache, if any
+fn db_read<S>(
+    state: &S,
+    key: &Key,
+    read_cache: Option<&VpReadCache>,
+) -> EnvResult<(Option<Vec<u8>>, u64)>
+where
+    S: StateRead + Debug,
+{
+    let read_storage =
+        || state.db_read(key).map_err(RuntimeError::StorageError);
+    match read_cache {
+        Some(read_cache) => read_cache.read(key, read_storage),
+        None => read_storage(),
+    }
+}
+
 /// Storage read temporary state (after tx execution). It will try to read from
 /// only the write log.
 pub fn read_temp<S>(
@@ -27,6 +27,8 @@ pub use {
 
 pub mod ledger;
 pub use namada_tx::proto;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod vm;
 
 pub mod eth_bridge {
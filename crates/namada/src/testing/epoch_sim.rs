@@ -0,0 +1,716 @@
+//! A deterministic simulation of the blocks across the epoch transitions.
+//!
+//! The [`EpochSim`] applies blocks to a [`TestState`] with the same
+//! [`engine`] functions as the node's finalize block: the block is begun, the
+//! protocol's updates are applied by [`engine::finalize_block`], then the
+//! block's txs are applied by [`engine::apply_tx`] and the block is
+//! committed. The governance proposals and the votes of the validators are
+//! not simulated.
+//!
+//! The txs are scripted as closures writing to the tx write log instead of
+//! wasm, so they are validated by the native VPs only. The time of a block is
+//! derived from its height, so that the epochs begin at the same heights and
+//! the merkle roots are the same on every run.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+
+use namada_core::address::Address;
+use namada_core::dec::Dec;
+use namada_core::hash::Hash;
+use namada_core::storage::{BlockHash, BlockHeight, Epoch, Header, TxIndex};
+use namada_core::time::{DateTimeUtc, DurationSecs};
+use namada_core::token::Amount;
+use namada_core::uint::Uint;
+use namada_gas::TxGasMeter;
+use namada_ibc::storage::{
+    deposit_prefix, is_deposit_key, is_withdraw_key, withdraw_prefix,
+};
+use namada_ibc::{IbcActions, PfmModule};
+use namada_state::mockdb::MockDB;
+use namada_state::testing::TestState;
+use namada_state::{
+    iter_prefix_bytes, ResultExt, Sha256Hasher, State, StateRead, StorageError,
+    StorageRead, StorageResult, TxHostEnvState,
+};
+use namada_token::storage_key::{
+    masp_last_inflation_key, masp_last_locked_amount_key,
+    masp_max_reward_rate_key,
+};
+use namada_tx::data::{DecryptedTx, TxSentinel, TxType};
+use namada_tx::{new_tx_event, Tx};
+use tempfile::TempDir;
+
+use crate::ledger::events::Event;
+use crate::ledger::ibc::{
+    audit_ibc_escrow, escrowed_tokens, read_throughput_usage,
+};
+use crate::ledger::parameters::storage::get_epochs_per_year_key;
+use crate::ledger::{engine, protocol};
+use crate::vm::wasm::{self, VpCache};
+use crate::vm::WasmCacheRwAccess;
+
+/// The state given to a scripted tx, writing to the tx write log
+pub type SimTxState<'a> = TxHostEnvState<'a, MockDB, Sha256Hasher>;
+
+/// Apply the changes of a scripted tx
+type ApplyTx =
+    Box<dyn for<'a> FnOnce(&Tx, SimTxState<'a>) -> StorageResult<()>>;
+
+/// A tx scripted for a block of the simulation
+pub struct ScriptedTx {
+    /// The tx given to the VPs, e.g. with the data of an IBC message
+    pub tx: Tx,
+    /// The verifiers of the tx, e.g. the addresses which signed it
+    pub verifiers: BTreeSet<Address>,
+    apply: ApplyTx,
+}
+
+impl ScriptedTx {
+    /// A tx with the given data whose changes are written by the given
+    /// closure
+    pub fn new(
+        data: Vec<u8>,
+        verifiers: BTreeSet<Address>,
+        apply: impl for<'a> FnOnce(&Tx, SimTxState<'a>) -> StorageResult<()>
+            + 'static,
+    ) -> Self {
+        let mut tx = Tx::from_type(TxType::Decrypted(DecryptedTx::Decrypted));
+        tx.add_code(vec![], None).add_serialized_data(data);
+        Self {
+            tx,
+            verifiers,
+            apply: Box::new(apply),
+        }
+    }
+
+    /// A tx executing the IBC message of the given data, as the IBC tx does
+    pub fn ibc(data: Vec<u8>, verifiers: BTreeSet<Address>) -> Self {
+        Self::new(data, verifiers, |tx, state| {
+            let data = tx
+                .data()
+                .ok_or_else(|| StorageError::new_const("No IBC tx data"))?;
+            let state = Rc::new(RefCell::new(state));
+            let mut actions = IbcActions::new(state.clone());
+            actions.add_pfm_module(PfmModule::new(state));
+            actions.execute(&data).into_storage_result()
+        })
+    }
+}
+
+/// The outcome of a scripted tx
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxOutcome {
+    /// The tx has been accepted by the VPs and its changes are committed
+    Accepted,
+    /// The tx has failed or has been rejected by a VP, with the error
+    Rejected(String),
+}
+
+/// The trace of a simulated block
+#[derive(Debug, Clone)]
+pub struct BlockTrace {
+    /// The height of the block
+    pub height: BlockHeight,
+    /// The epoch of the block
+    pub epoch: Epoch,
+    /// Whether the block has begun a new epoch
+    pub new_epoch: bool,
+    /// The outcomes of the block's txs, in their order
+    pub txs: Vec<TxOutcome>,
+    /// The events emitted by the protocol and by the accepted txs
+    pub events: Vec<Event>,
+    /// The merkle root after the block's commit
+    pub root: Hash,
+}
+
+/// A simulation of the blocks applied to a state initialized at genesis
+pub struct EpochSim {
+    state: TestState,
+    vp_wasm_cache: VpCache<WasmCacheRwAccess>,
+    _vp_cache_dir: TempDir,
+    genesis_time: DateTimeUtc,
+    block_time: DurationSecs,
+    /// The scripted txs by the heights of their blocks
+    txs: BTreeMap<BlockHeight, Vec<ScriptedTx>>,
+}
+
+impl EpochSim {
+    /// Create a simulation over the given state. The time of a block is the
+    /// genesis time plus the block time for every height.
+    pub fn new(
+        state: TestState,
+        genesis_time: DateTimeUtc,
+        block_time: DurationSecs,
+    ) -> Self {
+        let (vp_wasm_cache, vp_cache_dir) =
+            wasm::compilation_cache::common::testing::cache();
+        Self {
+            state,
+            vp_wasm_cache,
+            _vp_cache_dir: vp_cache_dir,
+            genesis_time,
+            block_time,
+            txs: BTreeMap::new(),
+        }
+    }
+
+    /// Read-only access to the state
+    pub fn state(&self) -> &TestState {
+        &self.state
+    }
+
+    /// Mutable access to the state, e.g. to update a parameter between the
+    /// blocks
+    pub fn state_mut(&mut self) -> &mut TestState {
+        &mut self.state
+    }
+
+    /// Take the state back from the simulation
+    pub fn into_state(self) -> TestState {
+        self.state
+    }
+
+    /// Schedule a tx to be applied in the block of the given height, after
+    /// the txs already scheduled in it
+    pub fn schedule(&mut self, height: BlockHeight, tx: ScriptedTx) {
+        self.txs.entry(height).or_default().push(tx);
+    }
+
+    /// Run the given number of blocks
+    pub fn run_blocks(
+        &mut self,
+        num_blocks: u64,
+    ) -> StorageResult<Vec<BlockTrace>> {
+        (0..num_blocks).map(|_| self.run_block()).collect()
+    }
+
+    /// Run the blocks until the given number of new epochs have begun. The
+    /// last block of the traces is the first block of the last epoch.
+    pub fn run_epochs(
+        &mut self,
+        num_epochs: u64,
+    ) -> StorageResult<Vec<BlockTrace>> {
+        let mut traces = vec![];
+        let mut new_epochs = 0;
+        while new_epochs < num_epochs {
+            let trace = self.run_block()?;
+            if trace.new_epoch {
+                new_epochs += 1;
+            }
+            traces.push(trace);
+        }
+        Ok(traces)
+    }
+
+    /// Run the next block
+    pub fn run_block(&mut self) -> StorageResult<BlockTrace> {
+        let next_height = self.state.in_mem().get_last_block_height() + 1;
+        let header = Header {
+            hash: Hash::default(),
+            time: self.genesis_time
+                + DurationSecs(self.block_time.0 * next_height.0),
+            next_validators_hash: Hash::default(),
+        };
+        let (height, new_epoch) =
+            engine::begin_block(&mut self.state, header, BlockHash::default())?;
+        let epoch = self.state.in_mem().block.epoch;
+
+        let mut events = vec![];
+        engine::finalize_block(
+            &mut self.state,
+            &mut events,
+            new_epoch,
+            vec![],
+            vec![],
+        )?;
+
+        let txs = self.txs.remove(&height).unwrap_or_default();
+        let mut outcomes = Vec::with_capacity(txs.len());
+        for (tx_index, tx) in txs.into_iter().enumerate() {
+            let tx_index = TxIndex(tx_index as u32);
+            outcomes.push(self.apply_tx(height, tx_index, tx, &mut events)?);
+        }
+
+        let root = engine::commit_block(&mut self.state)?;
+        Ok(BlockTrace {
+            height,
+            epoch,
+            new_epoch,
+            txs: outcomes,
+            events,
+            root: Hash(root.0),
+        })
+    }
+
+    /// Apply a scripted tx through the engine, checking it with the native
+    /// VPs. The changes of an accepted tx are committed to the block,
+    /// otherwise they are dropped.
+    fn apply_tx(
+        &mut self,
+        height: BlockHeight,
+        tx_index: TxIndex,
+        ScriptedTx {
+            tx,
+            verifiers,
+            apply,
+        }: ScriptedTx,
+        events: &mut Vec<Event>,
+    ) -> StorageResult<TxOutcome> {
+        let tx_gas_meter = RefCell::new(TxGasMeter::new(u64::MAX));
+        let sentinel = RefCell::new(TxSentinel::default());
+        let tx_event = new_tx_event(&tx, height.0);
+        let (result, tx_events) = engine::apply_tx(
+            &mut self.state,
+            tx_event,
+            tx_index,
+            &tx_gas_meter,
+            |state| {
+                let (write_log, in_mem, db) = state.split_borrow();
+                let tx_state = TxHostEnvState {
+                    write_log,
+                    db,
+                    in_mem,
+                    gas_meter: &tx_gas_meter,
+                    sentinel: &sentinel,
+                };
+                apply(&tx, tx_state).map_err(protocol::Error::StorageError)?;
+                protocol::apply_native_vps(
+                    &tx,
+                    &tx_index,
+                    state,
+                    &tx_gas_meter,
+                    &verifiers,
+                    &mut self.vp_wasm_cache,
+                )
+            },
+        )?;
+        events.extend(tx_events);
+
+        let outcome = match result {
+            Ok(result) if result.is_accepted() => TxOutcome::Accepted,
+            Ok(result) if result.vps_result.errors.is_empty() => {
+                TxOutcome::Rejected(format!(
+                    "Rejected by the VPs of {:?}",
+                    result.vps_result.rejected_vps
+                ))
+            }
+            Ok(result) => TxOutcome::Rejected(
+                result
+                    .vps_result
+                    .errors
+                    .iter()
+                    .map(|(addr, err)| format!("{addr}: {err}"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            Err(err) => TxOutcome::Rejected(err.to_string()),
+        };
+        Ok(outcome)
+    }
+}
+
+/// Assert that the balance of the IBC account backs the escrow of every
+/// token escrowed by IBC, as audited by [`audit_ibc_escrow`]
+pub fn assert_invariant_escrow_backed<S: StorageRead>(storage: &S) {
+    let tokens =
+        escrowed_tokens(storage).expect("Reading the escrowed tokens failed");
+    for token in tokens {
+        let report = audit_ibc_escrow(storage, &token)
+            .expect("Auditing the IBC escrow failed");
+        assert!(
+            report.is_consistent(),
+            "The escrow of {token} isn't backed by the IBC account: {report:?}"
+        );
+    }
+}
+
+/// Assert that the throughput of every token transferred over IBC in the
+/// current epoch is within its per-epoch throughput limit
+pub fn assert_invariant_throughput_within_limit<S: StorageRead>(storage: &S) {
+    let mut tokens = BTreeSet::new();
+    for prefix in [deposit_prefix(), withdraw_prefix()] {
+        let iter = iter_prefix_bytes(storage, &prefix)
+            .expect("Reading the throughputs failed");
+        for result in iter {
+            let (key, _) = result.expect("Reading the throughputs failed");
+            if let Some(token) =
+                is_deposit_key(&key).or_else(|| is_withdraw_key(&key))
+            {
+                tokens.insert(token.clone());
+            }
+        }
+    }
+    for token in tokens {
+        let (deposit, withdraw, limit) = read_throughput_usage(storage, &token)
+            .expect("Reading the throughput failed");
+        let throughput = if deposit < withdraw {
+            withdraw.checked_sub(deposit)
+        } else {
+            deposit.checked_sub(withdraw)
+        }
+        .expect("The difference shouldn't underflow");
+        assert!(
+            throughput <= limit,
+            "The throughput {throughput} of {token} exceeds the limit {limit}"
+        );
+    }
+}
+
+/// Assert that the MASP rewards of the last epoch of every reward token don't
+/// exceed its max reward rate of the amount locked in the MASP
+pub fn assert_invariant_masp_rewards_bounded<S: StorageRead>(storage: &S) {
+    let epochs_per_year: u64 = storage
+        .read(&get_epochs_per_year_key())
+        .expect("Reading the epochs per year failed")
+        .expect("The epochs per year should exist");
+    let tokens = namada_token::read_reward_tokens(storage)
+        .expect("Reading the MASP reward tokens failed");
+    for token in tokens.values() {
+        let inflation: Amount = storage
+            .read(&masp_last_inflation_key(token))
+            .expect("Reading the last inflation failed")
+            .unwrap_or_default();
+        let locked_amount: Amount = storage
+            .read(&masp_last_locked_amount_key(token))
+            .expect("Reading the last locked amount failed")
+            .unwrap_or_default();
+        let max_reward_rate: Dec = storage
+            .read(&masp_max_reward_rate_key(token))
+            .expect("Reading the max reward rate failed")
+            .expect("The max reward rate should exist");
+        assert!(
+            inflation.raw_amount() * Uint::from(epochs_per_year)
+                <= (max_reward_rate * locked_amount).raw_amount(),
+            "The MASP inflation {inflation} of {token} exceeds the max \
+             reward rate {max_reward_rate} of the locked amount \
+             {locked_amount}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use ibc_testkit::testapp::ibc::clients::mock::client_state::{
+        client_type, MockClientState, MOCK_CLIENT_TYPE,
+    };
+    use ibc_testkit::testapp::ibc::clients::mock::consensus_state::MockConsensusState;
+    use ibc_testkit::testapp::ibc::clients::mock::header::MockHeader;
+    use namada_core::address::testing::{btc, established_address_2, nam};
+    use namada_core::address::{InternalAddress, MASP};
+    use namada_core::event::IbcThroughputEvent;
+    use namada_core::token::Denomination;
+    use namada_governance::pgf::parameters::PgfParameters;
+    use namada_ibc::parameters::IbcParameters;
+    use namada_proof_of_stake::test_utils::test_init_genesis;
+    use namada_proof_of_stake::OwnedPosParams;
+    use namada_state::StorageWrite;
+    use namada_token::{
+        credit_tokens, read_balance, write_denom, write_reward_token,
+        ShieldedParams,
+    };
+    use prost::Message;
+
+    use super::*;
+    use crate::ibc::apps::transfer::types::msgs::transfer::MsgTransfer;
+    use crate::ibc::apps::transfer::types::packet::PacketData;
+    use crate::ibc::apps::transfer::types::{PrefixedCoin, VERSION};
+    use crate::ibc::core::channel::types::channel::{
+        ChannelEnd, Counterparty as ChanCounterparty, Order, State as ChanState,
+    };
+    use crate::ibc::core::channel::types::timeout::TimeoutHeight;
+    use crate::ibc::core::channel::types::Version as ChanVersion;
+    use crate::ibc::core::client::types::Height;
+    use crate::ibc::core::commitment_types::commitment::CommitmentPrefix;
+    use crate::ibc::core::connection::types::version::Version as ConnVersion;
+    use crate::ibc::core::connection::types::{
+        ConnectionEnd, Counterparty as ConnCounterparty, State as ConnState,
+    };
+    use crate::ibc::core::host::types::identifiers::{
+        ChannelId, ClientId, ConnectionId, PortId,
+    };
+    use crate::ibc::primitives::proto::{Any, Protobuf};
+    use crate::ibc::primitives::{Msg, Timestamp};
+    use crate::ibc::storage::{
+        channel_key, client_state_key, client_update_height_key,
+        client_update_timestamp_key, connection_key, consensus_state_key,
+    };
+    use crate::ledger::ibc;
+    use crate::ledger::native_vp::ibc::get_dummy_genesis_validator;
+    use crate::ledger::parameters::{self, EpochDuration, Parameters};
+    use crate::tendermint::time::Time as TmTime;
+
+    /// The min number of blocks of an epoch. With the delay of the epoch
+    /// switch, a new epoch begins every four blocks from the block 5.
+    const MIN_NUM_OF_BLOCKS: u64 = 2;
+
+    fn genesis_time() -> DateTimeUtc {
+        DateTimeUtc::from_unix_timestamp(1_700_000_000).unwrap()
+    }
+
+    /// Initialize the genesis block with a single validator. The block has
+    /// to be committed by the caller.
+    fn init_state() -> TestState {
+        let mut state = TestState::default();
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(1))
+            .unwrap();
+        let params = Parameters {
+            max_tx_bytes: 1024 * 1024,
+            epoch_duration: EpochDuration {
+                min_num_of_blocks: MIN_NUM_OF_BLOCKS,
+                min_duration: DurationSecs(0),
+            },
+            max_expected_time_per_block: DurationSecs(60),
+            max_proposal_bytes: Default::default(),
+            max_block_gas: 20_000_000,
+            vp_allowlist: vec![],
+            tx_allowlist: vec![],
+            implicit_vp_code_hash: Default::default(),
+            epochs_per_year: 365,
+            max_signatures_per_transaction: 10,
+            staked_ratio: Dec::zero(),
+            pos_inflation_amount: Amount::zero(),
+            fee_unshielding_gas_limit: 0,
+            fee_unshielding_descriptions_limit: 0,
+            minimum_gas_price: Default::default(),
+        };
+        parameters::init_storage(&params, &mut state).unwrap();
+        state
+            .in_mem_mut()
+            .init_genesis_epoch(BlockHeight(1), genesis_time(), &params)
+            .unwrap();
+        ibc::init_genesis_storage(&mut state);
+        PgfParameters::default().init_storage(&mut state).unwrap();
+        test_init_genesis(
+            &mut state,
+            OwnedPosParams::default(),
+            vec![get_dummy_genesis_validator()].into_iter(),
+            Epoch(0),
+        )
+        .unwrap();
+        write_denom(&mut state, &nam(), Denomination(6)).unwrap();
+        state
+    }
+
+    /// Open a channel to the counterparty chain over a mock client
+    fn open_channel(state: &mut TestState) {
+        let client_id =
+            ClientId::from_str(&format!("{MOCK_CLIENT_TYPE}-0")).unwrap();
+        let height = Height::new(0, 1).unwrap();
+        let time = TmTime::try_from(genesis_time()).unwrap();
+        let header = MockHeader {
+            height,
+            timestamp: Timestamp::from(time),
+        };
+        let client_state = MockClientState::new(header);
+        state
+            .write_bytes(
+                &client_state_key(&client_id),
+                Protobuf::<Any>::encode_vec(client_state),
+            )
+            .unwrap();
+        let consensus_state = MockConsensusState::new(header);
+        state
+            .write_bytes(
+                &consensus_state_key(&client_id, height),
+                Protobuf::<Any>::encode_vec(consensus_state),
+            )
+            .unwrap();
+        state
+            .write_bytes(
+                &client_update_timestamp_key(&client_id),
+                time.encode_vec(),
+            )
+            .unwrap();
+        state
+            .write_bytes(
+                &client_update_height_key(&client_id),
+                height.encode_vec(),
+            )
+            .unwrap();
+
+        let conn_id = ConnectionId::new(0);
+        let conn = ConnectionEnd::new(
+            ConnState::Open,
+            client_id,
+            ConnCounterparty::new(
+                ClientId::new(client_type(), 22).unwrap(),
+                Some(ConnectionId::new(32)),
+                CommitmentPrefix::try_from(b"ibc".to_vec()).unwrap(),
+            ),
+            vec![ConnVersion::default()],
+            Duration::new(0, 0),
+        )
+        .unwrap();
+        state
+            .write_bytes(&connection_key(&conn_id), conn.encode_vec())
+            .unwrap();
+        let channel = ChannelEnd::new(
+            ChanState::Open,
+            Order::Unordered,
+            ChanCounterparty::new(PortId::transfer(), Some(ChannelId::new(0))),
+            vec![conn_id],
+            ChanVersion::new(VERSION.to_string()),
+        )
+        .unwrap();
+        state
+            .write_bytes(
+                &channel_key(&PortId::transfer(), &ChannelId::new(0)),
+                channel.encode_vec(),
+            )
+            .unwrap();
+    }
+
+    /// A tx sending the whole NAM amount to the counterparty chain
+    fn transfer_tx(sender: &Address, amount: u64) -> ScriptedTx {
+        let msg = MsgTransfer {
+            port_id_on_a: PortId::transfer(),
+            chan_id_on_a: ChannelId::new(0),
+            packet_data: PacketData {
+                token: PrefixedCoin {
+                    denom: nam().to_string().parse().unwrap(),
+                    amount: amount.into(),
+                },
+                sender: sender.to_string().into(),
+                receiver: "receiver".to_string().into(),
+                memo: String::default().into(),
+            },
+            timeout_height_on_b: TimeoutHeight::At(Height::new(0, 10).unwrap()),
+            timeout_timestamp_on_b: Timestamp::none(),
+        };
+        let mut data = vec![];
+        msg.to_any().encode(&mut data).expect("encoding failed");
+        ScriptedTx::ibc(data, BTreeSet::from([sender.clone()]))
+    }
+
+    /// Test that a transfer exceeding the throughput limit at the end of an
+    /// epoch is rejected, while the same transfer is accepted in the first
+    /// block of the next epoch once the throughput has been reset
+    #[test]
+    fn test_ibc_transfer_straddling_epoch_boundary() {
+        let mut state = init_state();
+        open_channel(&mut state);
+        let params = IbcParameters {
+            default_per_epoch_throughput_limit: Amount::native_whole(150),
+            ..Default::default()
+        };
+        params.init_storage(&mut state).unwrap();
+        let sender = established_address_2();
+        credit_tokens(&mut state, &nam(), &sender, Amount::native_whole(1000))
+            .unwrap();
+        state.commit_block().unwrap();
+
+        let mut sim = EpochSim::new(state, genesis_time(), DurationSecs(1));
+        for height in [2, 4, 5, 6] {
+            sim.schedule(BlockHeight(height), transfer_tx(&sender, 100));
+        }
+        let mut traces = vec![];
+        for _ in 0..5 {
+            let trace = sim.run_block().unwrap();
+            assert_invariant_escrow_backed(sim.state());
+            assert_invariant_throughput_within_limit(sim.state());
+            traces.push(trace);
+        }
+
+        let heights: Vec<_> = traces.iter().map(|trace| trace.height).collect();
+        assert_eq!(heights, (2..=6).map(BlockHeight).collect::<Vec<_>>());
+        let is_throughput_rejection = |outcome: &TxOutcome| {
+            matches!(
+                outcome,
+                TxOutcome::Rejected(err)
+                    if err.contains("per-epoch throughput limit")
+            )
+        };
+        // The second transfer of the epoch 0 exceeds the limit
+        assert_eq!(traces[0].txs, vec![TxOutcome::Accepted]);
+        assert!(traces[1].txs.is_empty());
+        assert!(is_throughput_rejection(&traces[2].txs[0]));
+        assert_eq!(traces[2].epoch, Epoch(0));
+
+        // The throughput of the epoch 0 is recorded at its end and reset
+        assert!(traces[3].new_epoch);
+        assert_eq!(traces[3].epoch, Epoch(1));
+        let throughputs: Vec<_> = traces[3]
+            .events
+            .iter()
+            .filter_map(|event| IbcThroughputEvent::try_from(event).ok())
+            .collect();
+        assert_eq!(
+            throughputs,
+            vec![IbcThroughputEvent {
+                token: nam(),
+                epoch: Epoch(0),
+                deposit: Amount::zero(),
+                withdraw: Amount::native_whole(100),
+                minted: Amount::zero(),
+            }]
+        );
+        assert_eq!(traces[3].txs, vec![TxOutcome::Accepted]);
+        assert!(is_throughput_rejection(&traces[4].txs[0]));
+
+        // Only the accepted transfers have been escrowed
+        let ibc_balance = read_balance(
+            sim.state(),
+            &nam(),
+            &Address::Internal(InternalAddress::Ibc),
+        )
+        .unwrap();
+        assert_eq!(ibc_balance, Amount::native_whole(200));
+        let sender_balance =
+            read_balance(sim.state(), &nam(), &sender).unwrap();
+        assert_eq!(sender_balance, Amount::native_whole(800));
+    }
+
+    /// Test that the MASP rewards minted on every epoch never exceed the max
+    /// reward rate of the tokens locked in the MASP across ten epochs
+    #[test]
+    fn test_masp_rewards_bounded_across_epochs() {
+        let mut state = init_state();
+        let shielded_params = ShieldedParams {
+            max_reward_rate: Dec::from_str("0.1").unwrap(),
+            kp_gain_nom: Dec::from_str("0.25").unwrap(),
+            kd_gain_nom: Dec::from_str("0.25").unwrap(),
+            locked_amount_target: 10_000,
+        };
+        for (token, alias, denom) in [(nam(), "nam", 6), (btc(), "btc", 8)] {
+            let denom = Denomination(denom);
+            write_denom(&mut state, &token, denom).unwrap();
+            namada_token::write_params(
+                &Some(shielded_params.clone()),
+                &mut state,
+                &token,
+                &denom,
+            )
+            .unwrap();
+            write_reward_token(&mut state, alias, &token).unwrap();
+            // A tenth of the locked amount target is shielded
+            credit_tokens(
+                &mut state,
+                &token,
+                &MASP,
+                Amount::from_uint(1_000, denom).unwrap(),
+            )
+            .unwrap();
+        }
+        state.commit_block().unwrap();
+
+        let mut sim = EpochSim::new(state, genesis_time(), DurationSecs(1));
+        let initial_rewards = read_balance(sim.state(), &nam(), &MASP).unwrap();
+        for epoch in 1..=10 {
+            let traces = sim.run_epochs(1).unwrap();
+            let last = traces.last().unwrap();
+            assert!(last.new_epoch);
+            assert_eq!(last.epoch, Epoch(epoch));
+            assert_invariant_masp_rewards_bounded(sim.state());
+        }
+        // The rewards of the shielded BTC have been minted in NAM
+        let rewards = read_balance(sim.state(), &nam(), &MASP).unwrap();
+        assert!(rewards > initial_rewards);
+    }
+}
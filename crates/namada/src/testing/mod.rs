@@ -0,0 +1,4 @@
+//! Testing helpers of the ledger
+
+#[cfg(feature = "wasm-runtime")]
+pub mod epoch_sim;
@@ -19,7 +19,7 @@ use namada_gas::{
 use namada_state::write_log::{self, WriteLog};
 use namada_state::{
     DBIter, InMemory, State, StateRead, StorageError, StorageHasher,
-    StorageRead, StorageWrite, TxHostEnvState, VpHostEnvState, DB,
+    StorageRead, TxHostEnvState, VpHostEnvState, DB,
 };
 use namada_token::storage_key::is_any_token_parameter_key;
 use namada_tx::data::TxSentinel;
@@ -862,9 +862,11 @@ where
     check_address_existence::<MEM, D, H, CA>(env, &key)?;
 
     let mut state = env.state();
-    state
-        .write_bytes(&key, value)
-        .map_err(TxRuntimeError::StorageError)
+    let (gas, _size_diff) = state
+        .write_log_mut()
+        .write(&key, value)
+        .map_err(TxRuntimeError::StorageModificationError)?;
+    tx_charge_gas::<MEM, D, H, CA>(env, gas)
 }
 
 /// Temporary storage write function exposed to the wasm VM Tx environment. The
@@ -989,7 +991,11 @@ where
     }
 
     let mut state = env.state();
-    state.delete(&key).map_err(TxRuntimeError::StorageError)
+    let (gas, _size_diff) = state
+        .write_log_mut()
+        .delete(&key)
+        .map_err(TxRuntimeError::StorageModificationError)?;
+    tx_charge_gas::<MEM, D, H, CA>(env, gas)
 }
 
 /// Emitting an IBC event function exposed to the wasm VM Tx environment.
@@ -1080,7 +1086,7 @@ where
     let key =
         Key::parse(key).map_err(vp_host_fns::RuntimeError::StorageDataError)?;
     let state = env.state();
-    let value = vp_host_fns::read_pre(gas_meter, &state, &key, sentinel)?;
+    let value = vp_host_fns::read_pre(gas_meter, &state, &key, sentinel, None)?;
     tracing::debug!(
         "vp_read_pre addr {}, key {}, value {:?}",
         unsafe { env.ctx.address.get() },
@@ -1132,7 +1138,8 @@ where
     let key =
         Key::parse(key).map_err(vp_host_fns::RuntimeError::StorageDataError)?;
     let state = env.state();
-    let value = vp_host_fns::read_post(gas_meter, &state, &key, sentinel)?;
+    let value =
+        vp_host_fns::read_post(gas_meter, &state, &key, sentinel, None)?;
     Ok(match value {
         Some(value) => {
             let len: i64 = value
@@ -1993,14 +2000,19 @@ where
     let max_signatures = Option::<u8>::try_from_slice(&max_signatures)
         .map_err(vp_host_fns::RuntimeError::EncodingError)?;
 
+    let deprecated_layouts =
+        crate::parameters::deprecated_signature_layouts(&env.state())
+            .map_err(|e| vp_host_fns::RuntimeError::StorageError(e.into()))?;
+
     let tx = unsafe { env.ctx.tx.get() };
 
-    match tx.verify_signatures(
+    match tx.verify_signatures_with_layouts(
         &hashes,
         public_keys_map,
         &Some(signer),
         threshold,
         max_signatures,
+        &deprecated_layouts,
         || gas_meter.borrow_mut().consume(gas::VERIFY_TX_SIG_GAS),
     ) {
         Ok(_) => Ok(HostEnvResult::Success.to_i64()),
@@ -2054,7 +2066,7 @@ where
 {
     use std::rc::Rc;
 
-    use namada_ibc::{IbcActions, TransferModule};
+    use namada_ibc::{IbcActions, PfmModule};
 
     let tx_data = unsafe { env.ctx.tx.get().data() }.ok_or_else(|| {
         let sentinel = unsafe { env.ctx.sentinel.get() };
@@ -2063,8 +2075,8 @@ where
     })?;
     let state = Rc::new(RefCell::new(env.state()));
     let mut actions = IbcActions::new(state.clone());
-    let module = TransferModule::new(state);
-    actions.add_transfer_module(module.module_id(), module);
+    let module = PfmModule::new(state);
+    actions.add_pfm_module(module);
     actions.execute(&tx_data)?;
 
     Ok(())
@@ -2196,15 +2208,20 @@ where
     let max_signatures = Option::<u8>::try_from_slice(&max_signatures)
         .map_err(TxRuntimeError::EncodingError)?;
 
+    let deprecated_layouts =
+        crate::parameters::deprecated_signature_layouts(&env.state())
+            .map_err(TxRuntimeError::StorageError)?;
+
     let tx = unsafe { env.ctx.tx.get() };
 
     let (gas_meter, sentinel) = env.ctx.gas_meter_and_sentinel();
-    match tx.verify_signatures(
+    match tx.verify_signatures_with_layouts(
         &hashes,
         public_keys_map,
         &None,
         threshold,
         max_signatures,
+        &deprecated_layouts,
         || gas_meter.borrow_mut().consume(gas::VERIFY_TX_SIG_GAS),
     ) {
         Ok(_) => Ok(HostEnvResult::Success.to_i64()),
@@ -622,7 +622,8 @@ mod tests {
 
     use borsh_ext::BorshSerializeExt;
     use itertools::Either;
-    use namada_state::StorageWrite;
+    use namada_state::{write_log, StorageWrite};
+    use namada_test_utils::tx_data::TxWriteData;
     use namada_test_utils::TestWasms;
     use namada_tx::data::TxType;
     use namada_tx::{Code, Data};
@@ -631,6 +632,7 @@ mod tests {
 
     use super::*;
     use crate::state::testing::TestState;
+    use crate::storage::KeySeg;
     use crate::tx::data::eval_vp::EvalVp;
     use crate::vm::host_env::TxRuntimeError;
     use crate::vm::wasm;
@@ -1153,6 +1155,86 @@ mod tests {
         assert_stack_overflow(&error);
     }
 
+    /// Test that a transaction wasm cannot write under a storage prefix
+    /// reserved for the protocol, while the protocol itself can.
+    #[test]
+    fn test_tx_write_protocol_reserved_key() {
+        let mut state = TestState::default().with_reserved_prefixes(
+            crate::ledger::protocol::reserved_prefixes(),
+        );
+        let tx_index = TxIndex::default();
+
+        let tx_write = TestWasms::TxWriteStorageKey.read_bytes();
+        // store the wasm code
+        let code_hash = Hash::sha256(&tx_write);
+        let code_len = (tx_write.len() as u64).serialize_to_vec();
+        let key = Key::wasm_code(&code_hash);
+        let len_key = Key::wasm_code_len(&code_hash);
+        state.write_log_mut().write(&key, tx_write.clone()).unwrap();
+        state.write_log_mut().write(&len_key, code_len).unwrap();
+
+        let reserved_key =
+            Key::from(crate::core::address::ETH_BRIDGE.to_db_key())
+                .with_segment("eth_msgs".to_owned());
+        let tx_data = TxWriteData {
+            key: reserved_key.clone(),
+            value: "tx".to_owned().serialize_to_vec(),
+        }
+        .serialize_to_vec();
+        let mut outer_tx = Tx::from_type(TxType::Raw);
+        outer_tx.set_code(Code::new(tx_write, None));
+        outer_tx.set_data(Data::new(tx_data));
+        let (mut vp_cache, _) =
+            wasm::compilation_cache::common::testing::cache();
+        let (mut tx_cache, _) =
+            wasm::compilation_cache::common::testing::cache();
+        let mut run_tx = |state: &mut TestState| {
+            let gas_meter = RefCell::new(TxGasMeter::new_from_sub_limit(
+                TX_GAS_LIMIT.into(),
+            ));
+            tx(
+                state,
+                &gas_meter,
+                &tx_index,
+                &outer_tx,
+                &mut vp_cache,
+                &mut tx_cache,
+            )
+        };
+
+        // the write must fail in the host env with the typed error
+        let error =
+            run_tx(&mut state).expect_err("Writing a reserved key must fail");
+        let Error::RuntimeError(rt_error) = &error else {
+            panic!("Unexpected error {error}");
+        };
+        let source_err =
+            rt_error.source().expect("No runtime error source found");
+        let tx_rt_err: &TxRuntimeError = source_err
+            .downcast_ref()
+            .unwrap_or_else(|| panic!("Unexpected error {source_err}"));
+        assert!(
+            matches!(
+                tx_rt_err,
+                TxRuntimeError::StorageModificationError(
+                    write_log::Error::ProtocolReservedKey(key)
+                ) if key == &reserved_key
+            ),
+            "Unexpected error {tx_rt_err}"
+        );
+        assert!(state.write_log().get_keys().get(&reserved_key).is_none());
+
+        // the protocol can write under the same prefix
+        state
+            .write(&reserved_key, "protocol".to_owned())
+            .expect("Protocol write must succeed");
+
+        // the tx can write when the protocol allows it
+        state.write_log_mut().allow_reserved_keys(true);
+        run_tx(&mut state).expect("Writing a reserved key must succeed");
+        assert!(state.write_log().get_keys().contains(&reserved_key));
+    }
+
     /// Test that when a validity predicate wasm goes over the wasm memory limit
     /// in the value returned from host environment call during wasm
     /// execution, the execution is aborted.
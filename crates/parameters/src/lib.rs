@@ -1,13 +1,14 @@
 //! Protocol parameters
 pub mod storage;
 mod wasm_allowlist;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use namada_core::address::{Address, InternalAddress};
 use namada_core::chain::ProposalBytes;
 use namada_core::dec::Dec;
 use namada_core::hash::Hash;
 pub use namada_core::parameters::*;
+use namada_core::sign::SignatureLayout;
 use namada_core::storage::Key;
 use namada_core::time::DurationSecs;
 use namada_core::token;
@@ -20,6 +21,15 @@ pub use wasm_allowlist::{is_tx_allowed, is_vp_allowed};
 /// can be changed via governance.
 pub const ADDRESS: Address = Address::Internal(InternalAddress::Parameters);
 
+/// The default max number of bytes of an error message in a tx result
+pub const DEFAULT_MAX_TX_ERROR_BYTES: u64 = 2048;
+
+/// The default max number of storage modifications of a tx
+pub const DEFAULT_MAX_TX_WRITE_COUNT: u64 = 10_000;
+
+/// The default max number of bytes of the keys and values modified by a tx
+pub const DEFAULT_MAX_TX_WRITE_BYTES: u64 = 4 * 1024 * 1024;
+
 #[allow(missing_docs)]
 #[derive(Error, Debug)]
 pub enum ReadError {
@@ -161,6 +171,96 @@ where
     storage.read(&key)
 }
 
+/// Read the tx signature layouts that have been deprecated. Txs signed with
+/// these layouts are rejected by the signature verification.
+pub fn deprecated_signature_layouts<S>(
+    storage: &S,
+) -> namada_storage::Result<BTreeSet<SignatureLayout>>
+where
+    S: StorageRead,
+{
+    let key = storage::get_deprecated_signature_layouts_key();
+    Ok(storage.read(&key)?.unwrap_or_default())
+}
+
+/// Update the deprecated tx signature layouts storage parameter
+pub fn update_deprecated_signature_layouts<S>(
+    storage: &mut S,
+    value: BTreeSet<SignatureLayout>,
+) -> namada_storage::Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let key = storage::get_deprecated_signature_layouts_key();
+    storage.write(&key, value)
+}
+
+/// Read the max number of bytes of an error message stored in a tx result.
+/// Defaults to [`DEFAULT_MAX_TX_ERROR_BYTES`] if the parameter is not set.
+pub fn max_tx_error_bytes<S>(storage: &S) -> namada_storage::Result<u64>
+where
+    S: StorageRead,
+{
+    let key = storage::get_max_tx_error_bytes_key();
+    Ok(storage.read(&key)?.unwrap_or(DEFAULT_MAX_TX_ERROR_BYTES))
+}
+
+/// Update the max tx error bytes storage parameter
+pub fn update_max_tx_error_bytes<S>(
+    storage: &mut S,
+    value: u64,
+) -> namada_storage::Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let key = storage::get_max_tx_error_bytes_key();
+    storage.write(&key, value)
+}
+
+/// Read the max number of storage modifications of a tx. Defaults to
+/// [`DEFAULT_MAX_TX_WRITE_COUNT`] if the parameter is not set.
+pub fn max_tx_write_count<S>(storage: &S) -> namada_storage::Result<u64>
+where
+    S: StorageRead,
+{
+    let key = storage::get_max_tx_write_count_key();
+    Ok(storage.read(&key)?.unwrap_or(DEFAULT_MAX_TX_WRITE_COUNT))
+}
+
+/// Update the max tx write count storage parameter
+pub fn update_max_tx_write_count<S>(
+    storage: &mut S,
+    value: u64,
+) -> namada_storage::Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let key = storage::get_max_tx_write_count_key();
+    storage.write(&key, value)
+}
+
+/// Read the max number of bytes of the keys and values modified by a tx.
+/// Defaults to [`DEFAULT_MAX_TX_WRITE_BYTES`] if the parameter is not set.
+pub fn max_tx_write_bytes<S>(storage: &S) -> namada_storage::Result<u64>
+where
+    S: StorageRead,
+{
+    let key = storage::get_max_tx_write_bytes_key();
+    Ok(storage.read(&key)?.unwrap_or(DEFAULT_MAX_TX_WRITE_BYTES))
+}
+
+/// Update the max tx write bytes storage parameter
+pub fn update_max_tx_write_bytes<S>(
+    storage: &mut S,
+    value: u64,
+) -> namada_storage::Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let key = storage::get_max_tx_write_bytes_key();
+    storage.write(&key, value)
+}
+
 /// Update the max_expected_time_per_block parameter in storage. Returns the
 /// parameters and gas cost.
 pub fn update_max_expected_time_per_block_parameter<S>(
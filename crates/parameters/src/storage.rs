@@ -45,6 +45,10 @@ struct Keys {
     fee_unshielding_gas_limit: &'static str,
     fee_unshielding_descriptions_limit: &'static str,
     max_signatures_per_transaction: &'static str,
+    deprecated_signature_layouts: &'static str,
+    max_tx_error_bytes: &'static str,
+    max_tx_write_count: &'static str,
+    max_tx_write_bytes: &'static str,
 }
 
 /// Returns if the key is a parameter key.
@@ -190,6 +194,26 @@ pub fn get_max_signatures_per_transaction_key() -> Key {
     get_max_signatures_per_transaction_key_at_addr(ADDRESS)
 }
 
+/// Storage key used for the deprecated tx signature layouts
+pub fn get_deprecated_signature_layouts_key() -> Key {
+    get_deprecated_signature_layouts_key_at_addr(ADDRESS)
+}
+
+/// Storage key used for the max bytes of an error message in a tx result
+pub fn get_max_tx_error_bytes_key() -> Key {
+    get_max_tx_error_bytes_key_at_addr(ADDRESS)
+}
+
+/// Storage key used for the max number of storage modifications of a tx
+pub fn get_max_tx_write_count_key() -> Key {
+    get_max_tx_write_count_key_at_addr(ADDRESS)
+}
+
+/// Storage key used for the max bytes of the storage modifications of a tx
+pub fn get_max_tx_write_bytes_key() -> Key {
+    get_max_tx_write_bytes_key_at_addr(ADDRESS)
+}
+
 /// Helper function to retrieve the `max_block_gas` protocol parameter from
 /// storage
 pub fn get_max_block_gas(
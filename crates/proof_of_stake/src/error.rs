@@ -3,6 +3,7 @@ use std::num::TryFromIntError;
 
 use namada_core::address::Address;
 use namada_core::dec::Dec;
+use namada_core::key::common;
 use namada_core::storage::Epoch;
 use thiserror::Error;
 
@@ -65,6 +66,11 @@ pub enum UnbondError {
     VotingPowerOverflow(TryFromIntError),
     #[error("Trying to unbond from a frozen validator: {0}")]
     ValidatorIsFrozen(Address),
+    #[error(
+        "Unbonding would leave the validator {0} with a self-bond of {1}, \
+         below the minimum validator self-bond {2}"
+    )]
+    SelfBondBelowMinimum(Address, String, String),
 }
 
 #[allow(missing_docs)]
@@ -174,6 +180,10 @@ pub enum MetadataError {
 pub enum ConsensusKeyChangeError {
     #[error("The consensus key is required to be an Ed25519 key")]
     MustBeEd25519,
+    #[error("The address {0} is not a validator")]
+    NotAValidator(Address),
+    #[error("The consensus key {0} is already in use")]
+    KeyAlreadyUsed(common::PublicKey),
 }
 
 impl From<BecomeValidatorError> for namada_storage::Error {
@@ -53,16 +53,17 @@ use crate::storage::{
     below_capacity_validator_set_handle, bond_handle,
     consensus_validator_set_handle, delegator_redelegated_bonds_handle,
     delegator_redelegated_unbonds_handle, get_last_reward_claim_epoch,
-    liveness_missed_votes_handle, liveness_sum_missed_votes_handle,
-    read_consensus_validator_set_addresses, read_non_pos_owned_params,
-    read_pos_params, read_validator_last_slash_epoch,
-    read_validator_max_commission_rate_change, read_validator_stake,
-    total_bonded_handle, total_consensus_stake_handle, total_unbonded_handle,
-    try_insert_consensus_key, unbond_handle, update_total_deltas,
-    update_validator_deltas, validator_addresses_handle,
+    is_consensus_key_used, liveness_missed_votes_handle,
+    liveness_sum_missed_votes_handle, read_consensus_validator_set_addresses,
+    read_non_pos_owned_params, read_pos_params,
+    read_validator_last_slash_epoch, read_validator_max_commission_rate_change,
+    read_validator_stake, total_bonded_handle, total_consensus_stake_handle,
+    total_unbonded_handle, try_insert_consensus_key, unbond_handle,
+    update_total_deltas, update_validator_deltas, validator_addresses_handle,
     validator_commission_rate_handle, validator_consensus_key_handle,
-    validator_deltas_handle, validator_eth_cold_key_handle,
-    validator_eth_hot_key_handle, validator_incoming_redelegations_handle,
+    validator_delegators_handle, validator_deltas_handle,
+    validator_eth_cold_key_handle, validator_eth_hot_key_handle,
+    validator_incoming_redelegations_handle,
     validator_outgoing_redelegations_handle, validator_protocol_key_handle,
     validator_rewards_products_handle, validator_set_positions_handle,
     validator_slashes_handle, validator_state_handle,
@@ -71,7 +72,8 @@ use crate::storage::{
     write_pos_params, write_validator_address_raw_hash, write_validator_avatar,
     write_validator_description, write_validator_discord_handle,
     write_validator_email, write_validator_max_commission_rate_change,
-    write_validator_metadata, write_validator_website,
+    write_validator_max_total_delegated, write_validator_metadata,
+    write_validator_website,
 };
 use crate::storage_key::{bonds_for_source_prefix, is_bond_key};
 use crate::types::{
@@ -258,6 +260,7 @@ where
     // Initialize or update the bond at the pipeline offset
     bond_handle.add(storage, amount, current_epoch, offset)?;
     total_bonded_handle.add(storage, amount, current_epoch, offset)?;
+    validator_delegators_handle(validator).insert(storage, source.clone())?;
 
     if tracing::level_enabled!(tracing::Level::DEBUG) {
         let bonds = find_bonds(storage, source, validator)?;
@@ -415,6 +418,28 @@ where
         .into());
     }
 
+    // Make sure that a validator in the consensus or below-capacity validator
+    // sets keeps the minimum self-bond. It has to be deactivated to unbond
+    // below it.
+    if source == validator {
+        let self_bond = remaining_at_pipeline - amount;
+        if self_bond < params.min_validator_self_bond
+            && must_keep_min_self_bond(
+                storage,
+                validator,
+                pipeline_epoch,
+                &params,
+            )?
+        {
+            return Err(UnbondError::SelfBondBelowMinimum(
+                validator.clone(),
+                self_bond.to_string_native(),
+                params.min_validator_self_bond.to_string_native(),
+            )
+            .into());
+        }
+    }
+
     if tracing::level_enabled!(tracing::Level::DEBUG) {
         let bonds = find_bonds(storage, source, validator)?;
         tracing::debug!("\nBonds before decrementing: {bonds:#?}");
@@ -1325,6 +1350,12 @@ where
 {
     tracing::debug!("Changing consensus key for validator {}", validator);
 
+    if !is_validator(storage, validator)? {
+        return Err(
+            ConsensusKeyChangeError::NotAValidator(validator.clone()).into()
+        );
+    }
+
     // Require that the new consensus key is an Ed25519 key
     match consensus_key {
         common::PublicKey::Ed25519(_) => {}
@@ -1334,6 +1365,12 @@ where
     }
 
     // Check for uniqueness of the consensus key
+    if is_consensus_key_used(storage, consensus_key)? {
+        return Err(ConsensusKeyChangeError::KeyAlreadyUsed(
+            consensus_key.clone(),
+        )
+        .into());
+    }
     try_insert_consensus_key(storage, consensus_key)?;
 
     // Set the new consensus key at the pipeline epoch
@@ -1551,6 +1588,26 @@ where
     commission_handle.set(storage, new_rate, current_epoch, params.pipeline_len)
 }
 
+/// Get the total amount delegated to a validator at the given epoch, i.e. its
+/// stake without its self-bond.
+pub fn validator_total_delegated<S>(
+    storage: &S,
+    validator: &Address,
+    epoch: Epoch,
+) -> namada_storage::Result<token::Amount>
+where
+    S: StorageRead,
+{
+    let params = read_pos_params(storage)?;
+    let stake = read_validator_stake(storage, &params, validator, epoch)?;
+    let self_bond_id = BondId {
+        source: validator.clone(),
+        validator: validator.clone(),
+    };
+    let self_bond = bond_amount(storage, &self_bond_id, epoch)?;
+    Ok(stake.checked_sub(self_bond).unwrap_or_default())
+}
+
 /// Get the total bond amount, including slashes, for a given bond ID and epoch.
 /// Returns the bond amount after slashing. For future epochs the value is
 /// subject to change.
@@ -1559,6 +1616,22 @@ pub fn bond_amount<S>(
     bond_id: &BondId,
     epoch: Epoch,
 ) -> namada_storage::Result<token::Amount>
+where
+    S: StorageRead,
+{
+    bond_amount_after_slashes(storage, bond_id, epoch, epoch)
+}
+
+/// Get the total bond amount for a given bond ID that contributes to the
+/// validator's stake in the given epoch, including the unbonds and the
+/// outgoing redelegations that still contribute to it, after applying the
+/// slashes processed before or at `slashes_epoch`.
+pub fn bond_amount_after_slashes<S>(
+    storage: &S,
+    bond_id: &BondId,
+    epoch: Epoch,
+    slashes_epoch: Epoch,
+) -> namada_storage::Result<token::Amount>
 where
     S: StorageRead,
 {
@@ -1681,15 +1754,16 @@ where
                     let processing_epoch =
                         slash.epoch + params.slash_processing_epoch_offset();
                     // Only use slashes that were processed before or at the
-                    // epoch associated with the bond amount. This assumes
-                    // that slashes are applied before inflation.
-                    processing_epoch <= epoch && start <= slash.epoch
+                    // given epoch. This assumes that slashes are applied
+                    // before inflation.
+                    processing_epoch <= slashes_epoch && start <= slash.epoch
                 })
                 .cloned()
                 .collect::<Vec<_>>();
 
-            let slash_epoch_filter =
-                |e: Epoch| e + params.slash_processing_epoch_offset() <= epoch;
+            let slash_epoch_filter = |e: Epoch| {
+                e + params.slash_processing_epoch_offset() <= slashes_epoch
+            };
 
             let redelegated_bonds =
                 redelegated_bonded.at(&start).collect_map(storage)?;
@@ -1941,6 +2015,25 @@ where
     }
 }
 
+/// Check if a validator must keep the minimum self-bond at the given epoch,
+/// i.e. if it's in the `consensus` or `below_capacity` validator sets.
+pub fn must_keep_min_self_bond<S>(
+    storage: &S,
+    validator: &Address,
+    epoch: Epoch,
+    params: &PosParams,
+) -> namada_storage::Result<bool>
+where
+    S: StorageRead,
+{
+    let state =
+        validator_state_handle(validator).get(storage, epoch, params)?;
+    Ok(matches!(
+        state,
+        Some(ValidatorState::Consensus | ValidatorState::BelowCapacity)
+    ))
+}
+
 /// Find the total amount of tokens staked at the given `epoch`,
 /// belonging to the set of consensus validators.
 pub fn get_total_consensus_stake<S>(
@@ -2084,6 +2177,8 @@ where
             current_epoch,
             params.pipeline_len,
         )?;
+        validator_delegators_handle(dest_validator)
+            .insert(storage, delegator.clone())?;
     }
 
     if tracing::level_enabled!(tracing::Level::DEBUG) {
@@ -2574,8 +2669,8 @@ pub mod test_utils {
 }
 
 /// Change validator's metadata. In addition to changing any of the data from
-/// [`ValidatorMetaData`], the validator's commission rate can be changed within
-/// here as well.
+/// [`ValidatorMetaData`], the validator's commission rate and the cap on the
+/// total amount delegated to it can be changed within here as well.
 #[allow(clippy::too_many_arguments)]
 pub fn change_validator_metadata<S>(
     storage: &mut S,
@@ -2586,6 +2681,7 @@ pub fn change_validator_metadata<S>(
     discord_handle: Option<String>,
     avatar: Option<String>,
     commission_rate: Option<Dec>,
+    max_total_delegated: Option<token::Amount>,
     current_epoch: Epoch,
 ) -> namada_storage::Result<()>
 where
@@ -2606,6 +2702,13 @@ where
     if let Some(avatar) = avatar {
         write_validator_avatar(storage, validator, &avatar)?;
     }
+    if let Some(max_total_delegated) = max_total_delegated {
+        write_validator_max_total_delegated(
+            storage,
+            validator,
+            max_total_delegated,
+        )?;
+    }
     if let Some(commission_rate) = commission_rate {
         change_validator_commission_rate(
             storage,
@@ -2867,6 +2970,9 @@ where
         // Prune liveness data from validators that are no longer in the
         // consensus set
         prune_liveness_data(storage, current_epoch)?;
+
+        // Prune the slash summaries past their retention period
+        slashing::prune_slash_summaries(storage, current_epoch)?;
     }
 
     Ok(())
@@ -70,6 +70,17 @@ pub struct OwnedPosParams {
     pub rewards_gain_p: Dec,
     /// PoS gain d (read only)
     pub rewards_gain_d: Dec,
+    /// The minimum amount of tokens of a delegation. Self-bonds of validators
+    /// are not limited. It's stored under its own key, like the
+    /// `min_validator_self_bond`.
+    #[borsh(skip)]
+    pub min_delegation_amount: token::Amount,
+    /// The minimum self-bond of a validator in the `consensus` or
+    /// `below_capacity` validator sets. A validator must be deactivated to
+    /// unbond below it. It's stored under its own key, so that the encoding
+    /// of the other params stays compatible with the existing chains.
+    #[borsh(skip)]
+    pub min_validator_self_bond: token::Amount,
 }
 
 impl Default for PosParams {
@@ -109,6 +120,10 @@ impl Default for OwnedPosParams {
             liveness_threshold: Dec::new(9, 1).expect("Test failed"),
             rewards_gain_p: Dec::from_str("0.25").expect("Test failed"),
             rewards_gain_d: Dec::from_str("0.25").expect("Test failed"),
+            // no minimum delegation
+            min_delegation_amount: token::Amount::zero(),
+            // no minimum self-bond
+            min_validator_self_bond: token::Amount::zero(),
         }
     }
 }
@@ -12,10 +12,14 @@ use namada_storage::collections::lazy_map::{NestedSubKey, SubKey};
 use namada_storage::StorageRead;
 
 use crate::slashing::{find_validator_slashes, get_slashed_amount};
-use crate::storage::{bond_handle, read_pos_params, unbond_handle};
+use crate::storage::{
+    bond_handle, read_all_validator_addresses, read_pos_params,
+    slash_summaries_handle, unbond_handle, validator_consensus_key_handle,
+};
 use crate::types::{
-    BondDetails, BondId, BondsAndUnbondsDetail, BondsAndUnbondsDetails, Slash,
-    UnbondDetails,
+    BondDetails, BondId, BondProjection, BondsAndUnbondsDetail,
+    BondsAndUnbondsDetails, EpochBondProjection, PendingConsensusKeyChange,
+    Slash, SlashSummary, UnbondDetails,
 };
 use crate::{storage_key, PosParams};
 
@@ -99,6 +103,85 @@ where
         .is_zero())
 }
 
+/// Find the consensus key changes that have been scheduled by the validators
+/// and that will take effect after the given current epoch.
+pub fn pending_consensus_key_changes<S>(
+    storage: &S,
+    current_epoch: Epoch,
+) -> namada_storage::Result<Vec<PendingConsensusKeyChange>>
+where
+    S: StorageRead,
+{
+    let params = read_pos_params(storage)?;
+    let pipeline_epoch = current_epoch + params.pipeline_len;
+    let mut validators = read_all_validator_addresses(storage, pipeline_epoch)?
+        .into_iter()
+        .collect::<Vec<_>>();
+    validators.sort();
+
+    let mut changes = vec![];
+    for validator in validators {
+        let handle = validator_consensus_key_handle(&validator);
+        let mut prev_key = handle.get(storage, current_epoch, &params)?;
+        for epoch in
+            Epoch::iter_bounds_inclusive(current_epoch.next(), pipeline_epoch)
+        {
+            let key = handle.get(storage, epoch, &params)?;
+            if let (Some(current_key), Some(new_key)) = (&prev_key, &key) {
+                if current_key != new_key {
+                    changes.push(PendingConsensusKeyChange {
+                        validator: validator.clone(),
+                        current_key: current_key.clone(),
+                        new_key: new_key.clone(),
+                        effective_epoch: epoch,
+                    });
+                }
+            }
+            prev_key = key;
+        }
+    }
+    Ok(changes)
+}
+
+/// Find the summaries of the slashes for the infractions committed in the
+/// given epoch.
+pub fn slashes_in_epoch<S>(
+    storage: &S,
+    epoch: Epoch,
+) -> namada_storage::Result<Vec<SlashSummary>>
+where
+    S: StorageRead,
+{
+    slash_summaries_handle()
+        .at(&epoch)
+        .iter(storage)?
+        .map(|res| {
+            let (_validator, summary) = res?;
+            Ok(summary)
+        })
+        .collect()
+}
+
+/// Find the summaries of the slashes that reduced the bonds of the given
+/// delegator. Only the summaries in which the delegator is listed individually
+/// are included.
+pub fn slashes_affecting<S>(
+    storage: &S,
+    delegator: &Address,
+) -> namada_storage::Result<Vec<SlashSummary>>
+where
+    S: StorageRead,
+{
+    let mut summaries = vec![];
+    for res in slash_summaries_handle().iter(storage)? {
+        let (_, summary) = res?;
+        if summary.delegators.contains_key(delegator) {
+            summaries.push(summary);
+        }
+    }
+    Ok(summaries)
+}
+
 /// Find raw bond deltas for the given source and validator address.
 pub fn find_bonds<S>(
     storage: &S,
@@ -138,6 +221,67 @@ where
         .collect()
 }
 
+/// Project the amounts of the bond of the given source and validator address
+/// over the epochs from the current epoch up to the unbonding length offset.
+/// For every epoch, the projection has the change of the bonded amount from
+/// the previous epoch, the bonded amount (including the unbonds that still
+/// contribute to the validator's stake) and the amount after slashing.
+pub fn bonds_and_unbonds_projection<S>(
+    storage: &S,
+    source: &Address,
+    validator: &Address,
+) -> namada_storage::Result<BondProjection>
+where
+    S: StorageRead,
+{
+    let params = read_pos_params(storage)?;
+    let current_epoch = storage.get_block_epoch()?;
+    let bonds = find_bonds(storage, source, validator)?;
+    let unbonds = find_unbonds(storage, source, validator)?;
+    let bond_id = BondId {
+        source: source.clone(),
+        validator: validator.clone(),
+    };
+
+    let bonded_at = |epoch: Epoch| -> token::Amount {
+        let bonded: token::Amount =
+            bonds.range(..=epoch).map(|(_start, amount)| *amount).sum();
+        let unbonding: token::Amount = unbonds
+            .iter()
+            .filter(|((start, withdraw), _amount)| {
+                // The first epoch in which the unbond stops contributing to
+                // the stake
+                let end = *withdraw - params.withdrawable_epoch_offset()
+                    + params.pipeline_len;
+                *start <= epoch && end > epoch
+            })
+            .map(|(_epoch_range, amount)| *amount)
+            .sum();
+        bonded + unbonding
+    };
+
+    let mut prev_bonded = current_epoch
+        .checked_sub(1)
+        .map(bonded_at)
+        .unwrap_or_default();
+    let mut epochs = Vec::new();
+    for epoch in Epoch::iter_bounds_inclusive(
+        current_epoch,
+        current_epoch + params.unbonding_len,
+    ) {
+        let bonded = bonded_at(epoch);
+        let total = crate::bond_amount(storage, &bond_id, epoch)?;
+        epochs.push(EpochBondProjection {
+            epoch,
+            delta: bonded.change() - prev_bonded.change(),
+            bonded,
+            total,
+        });
+        prev_bonded = bonded;
+    }
+    Ok(BondProjection { epochs })
+}
+
 /// Collect the details of all bonds and unbonds that match the source and
 /// validator arguments. If either source or validator is `None`, then grab the
 /// information for all sources or validators, respectively.
@@ -18,24 +18,34 @@ use namada_storage::{StorageRead, StorageWrite};
 
 use crate::storage::{
     enqueued_slashes_handle, read_pos_params, read_validator_last_slash_epoch,
-    read_validator_stake, total_bonded_handle, total_unbonded_handle,
-    update_total_deltas, update_validator_deltas,
-    validator_outgoing_redelegations_handle, validator_slashes_handle,
-    validator_state_handle, validator_total_redelegated_bonded_handle,
+    read_validator_stake, slash_summaries_handle, total_bonded_handle,
+    total_unbonded_handle, update_total_deltas, update_validator_deltas,
+    validator_delegators_handle, validator_outgoing_redelegations_handle,
+    validator_slashes_handle, validator_state_handle,
+    validator_total_redelegated_bonded_handle,
     validator_total_redelegated_unbonded_handle,
     write_validator_last_slash_epoch,
 };
 use crate::types::{
-    EagerRedelegatedBondsMap, ResultSlashing, Slash, SlashType, SlashedAmount,
-    Slashes, TotalRedelegatedUnbonded, ValidatorState,
+    BondId, EagerRedelegatedBondsMap, ResultSlashing, Slash, SlashSummary,
+    SlashType, SlashedAmount, SlashedDelegatorsAggregate, Slashes,
+    TotalRedelegatedUnbonded, ValidatorState,
 };
 use crate::validator_set_update::update_validator_set;
 use crate::{
-    fold_and_slash_redelegated_bonds, get_total_consensus_stake,
-    jail_validator, storage, storage_key, types, EagerRedelegatedUnbonds,
-    FoldRedelegatedBondsResult, OwnedPosParams, PosParams,
+    bond_amount_after_slashes, fold_and_slash_redelegated_bonds,
+    get_total_consensus_stake, jail_validator, storage, storage_key, types,
+    EagerRedelegatedUnbonds, FoldRedelegatedBondsResult, OwnedPosParams,
+    PosParams,
 };
 
+/// The maximum number of the delegators listed individually in a
+/// [`SlashSummary`]
+pub const MAX_SLASH_SUMMARY_DELEGATORS: usize = 100;
+
+/// The number of epochs for which the slash summaries are kept
+pub const SLASH_SUMMARIES_RETENTION_EPOCHS: u64 = 365;
+
 /// Apply PoS slashes from the evidence
 pub(crate) fn record_slashes_from_evidence<S>(
     storage: &mut S,
@@ -269,12 +279,12 @@ where
 
     // `resultSlashing`
     let mut map_validator_slash: EagerRedelegatedBondsMap = BTreeMap::new();
-    for (validator, slash_rate) in eager_validator_slash_rates {
+    for (validator, slash_rate) in &eager_validator_slash_rates {
         process_validator_slash(
             storage,
             &params,
-            &validator,
-            slash_rate,
+            validator,
+            *slash_rate,
             current_epoch,
             &mut map_validator_slash,
         )?;
@@ -283,14 +293,40 @@ where
 
     // Now update the remaining parts of storage
 
+    // Read the delegators' slashable amounts before the slashes are applied
+    // to them, to summarize the impact of the slashes
+    let mut delegator_bonds = BTreeMap::new();
+    for validator in eager_validator_slashes.keys() {
+        let amounts = read_slashable_bond_amounts(
+            storage,
+            validator,
+            infraction_epoch,
+            current_epoch,
+        )?;
+        delegator_bonds.insert(validator.clone(), amounts);
+    }
+
     // Write slashes themselves into storage
-    for (validator, slashes) in eager_validator_slashes {
-        let validator_slashes = validator_slashes_handle(&validator);
+    for (validator, slashes) in &eager_validator_slashes {
+        let validator_slashes = validator_slashes_handle(validator);
         for slash in slashes {
-            validator_slashes.push(storage, slash)?;
+            validator_slashes.push(storage, slash.clone())?;
         }
     }
 
+    // Write the summaries of the slashes
+    for (validator, slashes) in eager_validator_slashes {
+        write_slash_summary(
+            storage,
+            &validator,
+            infraction_epoch,
+            current_epoch,
+            slashes,
+            eager_validator_slash_rates[&validator],
+            delegator_bonds.remove(&validator).unwrap_or_default(),
+        )?;
+    }
+
     // Update the validator stakes
     for (validator, slash_amounts) in map_validator_slash {
         let mut slash_acc = token::Amount::zero();
@@ -342,6 +378,121 @@ where
     Ok(())
 }
 
+/// Read the slashable amounts of all the delegators of the given validator
+/// (including its self-bond), i.e. their bonds, unbonds and outgoing
+/// redelegations that contributed to the validator's stake in the infraction
+/// epoch, after applying the slashes processed so far. The delegators are
+/// found in the validator's index of the sources of its bonds.
+fn read_slashable_bond_amounts<S>(
+    storage: &S,
+    validator: &Address,
+    infraction_epoch: Epoch,
+    current_epoch: Epoch,
+) -> namada_storage::Result<BTreeMap<Address, token::Amount>>
+where
+    S: StorageRead,
+{
+    validator_delegators_handle(validator)
+        .iter(storage)?
+        .map(|source| {
+            let bond_id = BondId {
+                source: source?,
+                validator: validator.clone(),
+            };
+            let amount = bond_amount_after_slashes(
+                storage,
+                &bond_id,
+                infraction_epoch,
+                current_epoch,
+            )?;
+            Ok((bond_id.source, amount))
+        })
+        .collect()
+}
+
+/// Write the summary of the slashes of a validator processed in the current
+/// epoch. The amount slashed from each delegator is the reduction of its
+/// slashable amount, i.e. of its bonds and of its unbonds that are still
+/// slashable, given the amounts read before the slashes were applied.
+fn write_slash_summary<S>(
+    storage: &mut S,
+    validator: &Address,
+    infraction_epoch: Epoch,
+    current_epoch: Epoch,
+    slashes: Vec<Slash>,
+    rate: Dec,
+    amounts_before_slash: BTreeMap<Address, token::Amount>,
+) -> namada_storage::Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let mut slashed = vec![];
+    for (source, amount_before) in amounts_before_slash {
+        let bond_id = BondId {
+            source,
+            validator: validator.clone(),
+        };
+        let amount_after = bond_amount_after_slashes(
+            storage,
+            &bond_id,
+            infraction_epoch,
+            current_epoch,
+        )?;
+        let amount =
+            amount_before.checked_sub(amount_after).unwrap_or_default();
+        if !amount.is_zero() {
+            slashed.push((bond_id.source, amount));
+        }
+    }
+    let total_slashed = slashed.iter().map(|(_, amount)| *amount).sum();
+
+    // List the most affected delegators and aggregate the rest
+    slashed.sort_by_key(|(_, amount)| Reverse(*amount));
+    let other_delegators =
+        (slashed.len() > MAX_SLASH_SUMMARY_DELEGATORS).then(|| {
+            let others = slashed.split_off(MAX_SLASH_SUMMARY_DELEGATORS);
+            SlashedDelegatorsAggregate {
+                count: others.len() as u64,
+                amount: others.iter().map(|(_, amount)| *amount).sum(),
+            }
+        });
+
+    let summary = SlashSummary {
+        validator: validator.clone(),
+        infraction_epoch,
+        slashes,
+        rate,
+        total_slashed,
+        delegators: slashed.into_iter().collect(),
+        other_delegators,
+    };
+    tracing::debug!("Slash summary: {summary:#?}");
+    slash_summaries_handle().at(&infraction_epoch).insert(
+        storage,
+        validator.clone(),
+        summary,
+    )?;
+    Ok(())
+}
+
+/// Remove the summaries of the slashes for the infractions that are older than
+/// [`SLASH_SUMMARIES_RETENTION_EPOCHS`]. This function is called upon a new
+/// epoch.
+pub fn prune_slash_summaries<S>(
+    storage: &mut S,
+    current_epoch: Epoch,
+) -> namada_storage::Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    if let Some(epoch) =
+        current_epoch.checked_sub(SLASH_SUMMARIES_RETENTION_EPOCHS)
+    {
+        slash_summaries_handle().remove_all(storage, &epoch)?;
+    }
+    Ok(())
+}
+
 /// In the context of a redelegation, the function computes how much a validator
 /// (the destination validator of the redelegation) should be slashed due to the
 /// misbehaving of a second validator (the source validator of the
@@ -7,7 +7,7 @@ use namada_account::protocol_pk_key;
 use namada_core::address::Address;
 use namada_core::dec::Dec;
 use namada_core::key::{common, tm_consensus_key_raw_hash};
-use namada_core::storage::Epoch;
+use namada_core::storage::{Epoch, Key};
 use namada_core::token;
 use namada_governance::storage::get_max_proposal_period;
 use namada_storage::collections::lazy_map::NestedSubKey;
@@ -21,13 +21,13 @@ use crate::types::{
     ConsensusValidatorSets, DelegatorRedelegatedBonded,
     DelegatorRedelegatedUnbonded, EpochedSlashes, IncomingRedelegations,
     LivenessMissedVotes, LivenessSumMissedVotes, OutgoingRedelegations,
-    ReverseOrdTokenAmount, RewardsAccumulator, RewardsProducts, Slashes,
-    TotalConsensusStakes, TotalDeltas, TotalRedelegatedBonded,
+    ReverseOrdTokenAmount, RewardsAccumulator, RewardsProducts, SlashSummaries,
+    Slashes, TotalConsensusStakes, TotalDeltas, TotalRedelegatedBonded,
     TotalRedelegatedUnbonded, Unbonds, ValidatorAddresses,
-    ValidatorConsensusKeys, ValidatorDeltas, ValidatorEthColdKeys,
-    ValidatorEthHotKeys, ValidatorMetaData, ValidatorProtocolKeys,
-    ValidatorSetPositions, ValidatorState, ValidatorStates,
-    ValidatorTotalUnbonded, WeightedValidator,
+    ValidatorConsensusKeys, ValidatorDelegators, ValidatorDeltas,
+    ValidatorEthColdKeys, ValidatorEthHotKeys, ValidatorMetaData,
+    ValidatorProtocolKeys, ValidatorSetPositions, ValidatorState,
+    ValidatorStates, ValidatorTotalUnbonded, WeightedValidator,
 };
 use crate::{storage_key, MetadataError, OwnedPosParams, PosParams};
 
@@ -127,6 +127,13 @@ pub fn bond_handle(source: &Address, validator: &Address) -> Bonds {
     Bonds::open(key)
 }
 
+/// Get the storage handle to the index of the sources of the bonds to a
+/// validator
+pub fn validator_delegators_handle(validator: &Address) -> ValidatorDelegators {
+    let key = storage_key::validator_delegators_key(validator);
+    ValidatorDelegators::open(key)
+}
+
 /// Get the storage handle to a validator's total bonds, which are not updated
 /// due to unbonding
 pub fn total_bonded_handle(validator: &Address) -> Bonds {
@@ -169,6 +176,18 @@ pub fn enqueued_slashes_handle() -> EpochedSlashes {
     EpochedSlashes::open(key)
 }
 
+/// Get the storage handle to the summaries of the processed slashes
+pub fn slash_summaries_handle() -> SlashSummaries {
+    let key = storage_key::slash_summaries_key();
+    SlashSummaries::open(key)
+}
+
+/// The PoS storage prefixes that only the protocol may write under, i.e. the
+/// slash summaries
+pub fn reserved_prefixes() -> [Key; 1] {
+    [storage_key::slash_summaries_key()]
+}
+
 /// Get the storage handle to the rewards accumulator for the consensus
 /// validators in a given epoch
 pub fn rewards_accumulator_handle() -> RewardsAccumulator {
@@ -251,10 +270,18 @@ pub fn read_pos_params<S>(storage: &S) -> namada_storage::Result<PosParams>
 where
     S: StorageRead,
 {
-    let params = storage
+    let mut params: OwnedPosParams = storage
         .read(&storage_key::params_key())
         .transpose()
         .expect("PosParams should always exist in storage after genesis")?;
+    // The params stored under their own keys may be missing on the chains
+    // launched before they were added
+    params.min_validator_self_bond = storage
+        .read(&storage_key::min_validator_self_bond_key())?
+        .unwrap_or_default();
+    params.min_delegation_amount = storage
+        .read(&storage_key::min_delegation_amount_key())?
+        .unwrap_or_default();
     read_non_pos_owned_params(storage, params)
 }
 
@@ -283,7 +310,11 @@ where
     S: StorageRead + StorageWrite,
 {
     let key = storage_key::params_key();
-    storage.write(&key, params)
+    storage.write(&key, params)?;
+    let key = storage_key::min_validator_self_bond_key();
+    storage.write(&key, params.min_validator_self_bond)?;
+    let key = storage_key::min_delegation_amount_key();
+    storage.write(&key, params.min_delegation_amount)
 }
 
 /// Get the validator address given the raw hash of the Tendermint consensus key
@@ -780,6 +811,35 @@ where
     }
 }
 
+/// Read the cap on the total amount delegated to a PoS validator, if any.
+pub fn read_validator_max_total_delegated<S>(
+    storage: &S,
+    validator: &Address,
+) -> namada_storage::Result<Option<token::Amount>>
+where
+    S: StorageRead,
+{
+    storage.read(&storage_key::validator_max_total_delegated_key(validator))
+}
+
+/// Write the cap on the total amount delegated to a PoS validator. If the
+/// provided amount is zero, remove the cap.
+pub fn write_validator_max_total_delegated<S>(
+    storage: &mut S,
+    validator: &Address,
+    max_total_delegated: token::Amount,
+) -> namada_storage::Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let key = storage_key::validator_max_total_delegated_key(validator);
+    if max_total_delegated.is_zero() {
+        storage.delete(&key)
+    } else {
+        storage.write(&key, max_total_delegated)
+    }
+}
+
 /// Write validator's metadata.
 pub fn write_validator_metadata<S>(
     storage: &mut S,
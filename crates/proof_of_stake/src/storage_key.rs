@@ -9,6 +9,8 @@ use crate::epoched;
 use crate::types::BondId;
 
 const PARAMS_STORAGE_KEY: &str = "params";
+const MIN_VALIDATOR_SELF_BOND_KEY: &str = "min_validator_self_bond";
+const MIN_DELEGATION_AMOUNT_KEY: &str = "min_delegation_amount";
 const VALIDATOR_ADDRESSES_KEY: &str = "validator_addresses";
 #[allow(missing_docs)]
 pub const VALIDATOR_STORAGE_PREFIX: &str = "validator";
@@ -27,10 +29,12 @@ const VALIDATOR_LAST_KNOWN_PRODUCT_EPOCH_KEY: &str =
 const SLASHES_PREFIX: &str = "slash";
 const ENQUEUED_SLASHES_KEY: &str = "enqueued_slashes";
 const VALIDATOR_LAST_SLASH_EPOCH: &str = "last_slash_epoch";
+const SLASH_SUMMARIES_KEY: &str = "slash_summaries";
 const BOND_STORAGE_KEY: &str = "bond";
 const UNBOND_STORAGE_KEY: &str = "unbond";
 const VALIDATOR_TOTAL_BONDED_STORAGE_KEY: &str = "total_bonded";
 const VALIDATOR_TOTAL_UNBONDED_STORAGE_KEY: &str = "total_unbonded";
+const VALIDATOR_DELEGATORS_STORAGE_KEY: &str = "delegators";
 const VALIDATOR_SETS_STORAGE_PREFIX: &str = "validator_sets";
 const CONSENSUS_VALIDATOR_SET_STORAGE_KEY: &str = "consensus";
 const BELOW_CAPACITY_VALIDATOR_SET_STORAGE_KEY: &str = "below_capacity";
@@ -55,6 +59,7 @@ const VALIDATOR_DESCRIPTION_KEY: &str = "description";
 const VALIDATOR_WEBSITE_KEY: &str = "website";
 const VALIDATOR_DISCORD_KEY: &str = "discord_handle";
 const VALIDATOR_AVATAR_KEY: &str = "avatar";
+const VALIDATOR_MAX_TOTAL_DELEGATED_KEY: &str = "max_total_delegated";
 const LIVENESS_PREFIX: &str = "liveness";
 const LIVENESS_MISSED_VOTES: &str = "missed_votes";
 const LIVENESS_MISSED_VOTES_SUM: &str = "sum_missed_votes";
@@ -74,9 +79,24 @@ pub fn params_key() -> Key {
         .expect("Cannot obtain a storage key")
 }
 
-/// Is storage key for PoS parameters?
+/// Storage key for the PoS parameter of the minimum validator self-bond.
+pub fn min_validator_self_bond_key() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&MIN_VALIDATOR_SELF_BOND_KEY.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Storage key for the PoS parameter of the minimum delegation amount.
+pub fn min_delegation_amount_key() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&MIN_DELEGATION_AMOUNT_KEY.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Is storage key for PoS parameters, including the ones stored under their
+/// own keys?
 pub fn is_params_key(key: &Key) -> bool {
-    matches!(&key.segments[..], [DbKeySeg::AddressSeg(addr), DbKeySeg::StringSeg(key)] if addr == &ADDRESS && key == PARAMS_STORAGE_KEY)
+    matches!(&key.segments[..], [DbKeySeg::AddressSeg(addr), DbKeySeg::StringSeg(key)] if addr == &ADDRESS && (key == PARAMS_STORAGE_KEY || key == MIN_VALIDATOR_SELF_BOND_KEY || key == MIN_DELEGATION_AMOUNT_KEY))
 }
 
 /// Storage key prefix for validator data.
@@ -273,6 +293,7 @@ pub fn is_validator_metadata_key(key: &Key) -> Option<&Address> {
                     | VALIDATOR_WEBSITE_KEY
                     | VALIDATOR_DISCORD_KEY
                     | VALIDATOR_AVATAR_KEY
+                    | VALIDATOR_MAX_TOTAL_DELEGATED_KEY
             ) =>
         {
             Some(validator)
@@ -448,6 +469,39 @@ pub fn is_delegator_redelegations_key(key: &Key) -> Option<&Address> {
     }
 }
 
+/// Is the storage key of a delegator's redelegated bond? If so, returns the
+/// delegator's address, the destination validator and the source validator of
+/// the redelegation.
+pub fn is_delegator_redelegated_bond_key(
+    key: &Key,
+) -> Option<(&Address, &Address, &Address)> {
+    if key.segments.len() >= 9 {
+        match &key.segments[..9] {
+            [
+                DbKeySeg::AddressSeg(addr),
+                DbKeySeg::StringSeg(prefix),
+                DbKeySeg::AddressSeg(delegator),
+                DbKeySeg::StringSeg(data_dest),
+                DbKeySeg::AddressSeg(dest_validator),
+                DbKeySeg::StringSeg(data_epoch),
+                DbKeySeg::StringSeg(_redelegation_epoch),
+                DbKeySeg::StringSeg(data_src),
+                DbKeySeg::AddressSeg(src_validator),
+            ] if addr == &ADDRESS
+                && prefix == DELEGATOR_REDELEGATED_BONDS_KEY
+                && data_dest == lazy_map::DATA_SUBKEY
+                && data_epoch == lazy_map::DATA_SUBKEY
+                && data_src == lazy_map::DATA_SUBKEY =>
+            {
+                Some((delegator, dest_validator, src_validator))
+            }
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
 /// Storage key for validator's last known rewards product epoch.
 pub fn validator_last_known_product_epoch_key(validator: &Address) -> Key {
     validator_prefix(validator)
@@ -626,6 +680,18 @@ pub fn is_validator_slashes_key(key: &Key) -> Option<Address> {
     }
 }
 
+/// Storage key for the summaries of the processed slashes.
+pub fn slash_summaries_key() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&SLASH_SUMMARIES_KEY.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Is storage key for the summaries of the processed slashes?
+pub fn is_slash_summaries_key(key: &Key) -> bool {
+    matches!(&key.segments[..], [DbKeySeg::AddressSeg(addr), DbKeySeg::StringSeg(prefix), ..] if addr == &ADDRESS && prefix == SLASH_SUMMARIES_KEY)
+}
+
 /// Storage key for the last (most recent) epoch in which a slashable offense
 /// was detected for a given validator
 pub fn validator_last_slash_key(validator: &Address) -> Key {
@@ -716,6 +782,13 @@ pub fn is_bond_epoched_meta_key(key: &Key) -> Option<BondId> {
     }
 }
 
+/// Storage key for the index of the sources of the bonds to a given validator.
+pub fn validator_delegators_key(validator: &Address) -> Key {
+    validator_prefix(validator)
+        .push(&VALIDATOR_DELEGATORS_STORAGE_KEY.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
 /// Storage key for the total bonds for a given validator.
 pub fn validator_total_bonded_key(validator: &Address) -> Key {
     validator_prefix(validator)
@@ -1024,6 +1097,13 @@ pub fn validator_avatar_key(validator: &Address) -> Key {
         .expect("Cannot obtain a storage key")
 }
 
+/// Storage key for the cap on the total amount delegated to a validator
+pub fn validator_max_total_delegated_key(validator: &Address) -> Key {
+    validator_prefix(validator)
+        .push(&VALIDATOR_MAX_TOTAL_DELEGATED_KEY.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
 /// Storage prefix for the liveness data of the cosnensus validator set.
 pub fn liveness_data_prefix() -> Key {
     Key::from(ADDRESS.to_db_key())
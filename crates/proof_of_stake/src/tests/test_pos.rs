@@ -11,7 +11,7 @@ use namada_core::storage::{BlockHeight, Epoch};
 use namada_core::{address, key};
 use namada_state::testing::TestState;
 use namada_storage::collections::lazy_map::Collectable;
-use namada_storage::StorageRead;
+use namada_storage::{StorageRead, StorageWrite};
 use proptest::prelude::*;
 use proptest::test_runner::Config;
 // Use `RUST_LOG=info` (or another tracing level) and `--nocapture` to see
@@ -29,9 +29,12 @@ use crate::slashing::{process_slashes, slash};
 use crate::storage::{
     get_consensus_key_set, liveness_sum_missed_votes_handle,
     read_below_threshold_validator_set_addresses,
-    read_consensus_validator_set_addresses_with_stake, read_total_stake,
-    read_validator_deltas_value, rewards_accumulator_handle,
-    total_deltas_handle,
+    read_consensus_validator_set_addresses_with_stake, read_pos_params,
+    read_total_stake, read_validator_deltas_value, rewards_accumulator_handle,
+    total_deltas_handle, write_pos_params,
+};
+use crate::storage_key::{
+    min_delegation_amount_key, min_validator_self_bond_key,
 };
 use crate::test_utils::test_init_genesis;
 use crate::tests::helpers::{
@@ -1744,3 +1747,31 @@ fn test_jail_for_liveness_aux(validators: Vec<GenesisValidator>) {
         &storage_clone.write_log()
     );
 }
+
+/// Test that the params stored under their own keys are read with their
+/// default values on a chain launched before they were added
+#[test]
+fn test_read_pos_params_without_own_keys() {
+    let mut s = TestState::default();
+    namada_governance::parameters::GovernanceParameters::default()
+        .init_storage(&mut s)
+        .unwrap();
+    let params = OwnedPosParams {
+        min_validator_self_bond: token::Amount::native_whole(10),
+        min_delegation_amount: token::Amount::native_whole(5),
+        ..Default::default()
+    };
+    write_pos_params(&mut s, &params).unwrap();
+    let read = read_pos_params(&s).unwrap();
+    assert_eq!(read.min_validator_self_bond, params.min_validator_self_bond);
+    assert_eq!(read.min_delegation_amount, params.min_delegation_amount);
+
+    // Only the encoded params are stored on a chain launched before
+    s.delete(&min_validator_self_bond_key()).unwrap();
+    s.delete(&min_delegation_amount_key()).unwrap();
+    let read = read_pos_params(&s).unwrap();
+    assert_eq!(read.min_validator_self_bond, token::Amount::zero());
+    assert_eq!(read.min_delegation_amount, token::Amount::zero());
+    assert_eq!(read.pipeline_len, params.pipeline_len);
+    assert_eq!(read.unbonding_len, params.unbonding_len);
+}
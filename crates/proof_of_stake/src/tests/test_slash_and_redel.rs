@@ -15,7 +15,7 @@ use proptest::test_runner::Config;
 // `tracing` logs from tests
 use test_log::test;
 
-use crate::queries::bonds_and_unbonds;
+use crate::queries::{bonds_and_unbonds, slashes_affecting, slashes_in_epoch};
 use crate::slashing::{process_slashes, slash};
 use crate::storage::{
     bond_handle, delegator_redelegated_bonds_handle,
@@ -1500,3 +1500,204 @@ fn test_slashed_bond_amount_aux(validators: Vec<GenesisValidator>) {
     let diff = val_stake - self_bond_amount - del_bond_amount;
     assert!(diff <= 2.into());
 }
+
+proptest! {
+    // Generate arb valid input for `test_slash_summaries_aux`
+    #![proptest_config(Config {
+        cases: 1,
+        .. Config::default()
+    })]
+    #[test]
+    fn test_slash_summaries(
+
+    genesis_validators in arb_genesis_validators(4..5, None),
+
+    ) {
+        test_slash_summaries_aux(genesis_validators)
+    }
+}
+
+fn test_slash_summaries_aux(validators: Vec<GenesisValidator>) {
+    let mut storage = TestState::default();
+    let params = OwnedPosParams {
+        unbonding_len: 4,
+        validator_stake_threshold: token::Amount::zero(),
+        ..Default::default()
+    };
+
+    // Genesis
+    let mut current_epoch = storage.in_mem().block.epoch;
+    let params = test_init_genesis(
+        &mut storage,
+        params,
+        validators.clone().into_iter(),
+        current_epoch,
+    )
+    .unwrap();
+    storage.commit_block().unwrap();
+
+    let validator = validators[0].address.clone();
+
+    // Get two delegators with some tokens and bond to the validator
+    let staking_token = staking_token_address(&storage);
+    let delegators = [
+        address::testing::gen_implicit_address(),
+        address::testing::gen_implicit_address(),
+    ];
+    for (delegator, amount) in delegators.iter().zip([10_000_u64, 5_000]) {
+        let del_balance = token::Amount::from_uint(1_000_000, 0).unwrap();
+        credit_tokens(&mut storage, &staking_token, delegator, del_balance)
+            .unwrap();
+        bond_tokens(
+            &mut storage,
+            Some(delegator),
+            &validator,
+            amount.into(),
+            current_epoch,
+            None,
+        )
+        .unwrap();
+    }
+
+    // Unbond some from the first delegator
+    unbond_tokens(
+        &mut storage,
+        Some(&delegators[0]),
+        &validator,
+        2_000.into(),
+        current_epoch,
+        false,
+    )
+    .unwrap();
+
+    // Advance past the pipeline so that the bonds contribute to the stake
+    for _ in 0..params.pipeline_len {
+        current_epoch = advance_epoch(&mut storage, &params);
+        process_slashes(&mut storage, current_epoch).unwrap();
+    }
+
+    // Find a slash committed in the current epoch
+    let infraction_epoch = current_epoch;
+    slash(
+        &mut storage,
+        &params,
+        current_epoch,
+        infraction_epoch,
+        1_u64,
+        SlashType::DuplicateVote,
+        &validator,
+        current_epoch,
+    )
+    .unwrap();
+
+    // Unbond some from the second delegator after the infraction. The unbond
+    // stops contributing to the stake before the slash is processed, but it's
+    // still slashable.
+    unbond_tokens(
+        &mut storage,
+        Some(&delegators[1]),
+        &validator,
+        3_000.into(),
+        current_epoch,
+        false,
+    )
+    .unwrap();
+
+    // Advance to the epoch before the slash is processed
+    let processing_epoch =
+        infraction_epoch + params.slash_processing_epoch_offset();
+    while current_epoch.next() < processing_epoch {
+        current_epoch = advance_epoch(&mut storage, &params);
+        process_slashes(&mut storage, current_epoch).unwrap();
+    }
+    assert!(
+        slashes_in_epoch(&storage, infraction_epoch)
+            .unwrap()
+            .is_empty()
+    );
+
+    // Read the bond amounts before the slash is applied
+    let sources = [
+        validator.clone(),
+        delegators[0].clone(),
+        delegators[1].clone(),
+    ];
+    let read_bond_amounts = |storage: &TestState| {
+        sources
+            .iter()
+            .map(|source| {
+                crate::bond_amount(
+                    storage,
+                    &BondId {
+                        source: source.clone(),
+                        validator: validator.clone(),
+                    },
+                    processing_epoch,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>()
+    };
+    let bonds_before = read_bond_amounts(&storage);
+
+    // Process the slash
+    current_epoch = advance_epoch(&mut storage, &params);
+    assert_eq!(current_epoch, processing_epoch);
+    process_slashes(&mut storage, current_epoch).unwrap();
+    let bonds_after = read_bond_amounts(&storage);
+
+    let summaries = slashes_in_epoch(&storage, infraction_epoch).unwrap();
+    assert_eq!(summaries.len(), 1);
+    let summary = &summaries[0];
+    assert_eq!(summary.validator, validator);
+    assert_eq!(summary.infraction_epoch, infraction_epoch);
+    let slashes = validator_slashes_handle(&validator)
+        .iter(&storage)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(summary.slashes, slashes);
+    assert_eq!(summary.rate, slashes[0].rate);
+    assert!(summary.other_delegators.is_none());
+
+    // The individual amounts are the actual reductions of the bonds, plus the
+    // slash of the unbond of the second delegator
+    assert_eq!(summary.delegators.len(), sources.len());
+    for ((source, before), after) in
+        sources.iter().zip(bonds_before).zip(bonds_after)
+    {
+        assert!(after < before);
+        let slashed = summary.slashed_amount_of(source).unwrap();
+        if source == &delegators[1] {
+            assert!(slashed > before - after);
+        } else {
+            assert_eq!(slashed, before - after);
+        }
+    }
+
+    // The per-delegator amounts add up to the total, which is the slash of
+    // the validator's stake in the infraction epoch up to the rounding of
+    // each bond
+    assert_eq!(
+        summary.total_slashed,
+        summary.delegators.values().copied().sum::<token::Amount>()
+    );
+    let stake =
+        read_validator_stake(&storage, &params, &validator, infraction_epoch)
+            .unwrap();
+    let stake_slashed = stake.mul_ceil(summary.rate);
+    let diff = if stake_slashed > summary.total_slashed {
+        stake_slashed - summary.total_slashed
+    } else {
+        summary.total_slashed - stake_slashed
+    };
+    assert!(diff <= (sources.len() as u64).into());
+
+    // The summary is found for the affected delegators only
+    for delegator in &delegators {
+        let affecting = slashes_affecting(&storage, delegator).unwrap();
+        assert_eq!(affecting, vec![summary.clone()]);
+    }
+    let unaffected = address::testing::gen_implicit_address();
+    assert!(slashes_affecting(&storage, &unaffected).unwrap().is_empty());
+}
@@ -1,5 +1,6 @@
 use std::cmp::min;
 
+use assert_matches::assert_matches;
 use namada_core::address::testing::arb_established_address;
 use namada_core::address::{self, Address, EstablishedAddressGen};
 use namada_core::dec::Dec;
@@ -18,6 +19,7 @@ use proptest::test_runner::Config;
 use test_log::test;
 
 use crate::epoched::DEFAULT_NUM_PAST_EPOCHS;
+use crate::error::ConsensusKeyChangeError;
 use crate::storage::{
     below_capacity_validator_set_handle, bond_handle,
     consensus_validator_set_handle, find_validator_by_raw_hash,
@@ -35,14 +37,15 @@ use crate::tests::helpers::{
 };
 use crate::token::credit_tokens;
 use crate::types::{
-    into_tm_voting_power, ConsensusValidator, GenesisValidator, Position,
-    ReverseOrdTokenAmount, ValidatorSetUpdate, WeightedValidator,
+    into_tm_voting_power, ConsensusValidator, GenesisValidator,
+    PendingConsensusKeyChange, Position, ReverseOrdTokenAmount,
+    ValidatorSetUpdate, WeightedValidator,
 };
 use crate::validator_set_update::{
     insert_validator_into_validator_set, update_validator_set,
 };
 use crate::{
-    become_validator, bond_tokens, change_consensus_key, is_validator,
+    become_validator, bond_tokens, change_consensus_key, is_validator, queries,
     staking_token_address, unbond_tokens, withdraw_tokens, BecomeValidator,
     OwnedPosParams,
 };
@@ -1258,6 +1261,136 @@ fn test_validator_sets_swap() {
     );
 }
 
+#[test]
+fn test_consensus_key_rotation() {
+    let mut s = TestState::default();
+    let params = OwnedPosParams {
+        // Set 0.1 votes per token
+        tm_votes_per_token: Dec::new(1, 1).expect("Dec creation failed"),
+        ..Default::default()
+    };
+
+    let addr_seed = "seed";
+    let mut address_gen = EstablishedAddressGen::new(addr_seed);
+    let val1 = address_gen.generate_address(addr_seed);
+    let val2 = address_gen.generate_address(addr_seed);
+    let non_validator = address_gen.generate_address(addr_seed);
+    let pk1 = common_sk_from_simple_seed(0).to_public();
+    let pk2 = common_sk_from_simple_seed(1).to_public();
+    let stake = token::Amount::native_whole(10);
+
+    let genesis_validator = |address: &Address, consensus_key, seed| {
+        let protocol_key = common_sk_from_simple_seed(seed).to_public();
+        GenesisValidator {
+            address: address.clone(),
+            tokens: stake,
+            consensus_key,
+            protocol_key,
+            eth_hot_key: key::common::PublicKey::Secp256k1(
+                key::testing::gen_keypair::<key::secp256k1::SigScheme>()
+                    .ref_to(),
+            ),
+            eth_cold_key: key::common::PublicKey::Secp256k1(
+                key::testing::gen_keypair::<key::secp256k1::SigScheme>()
+                    .ref_to(),
+            ),
+            commission_rate: Dec::new(1, 1).expect("Dec creation failed"),
+            max_commission_rate_change: Dec::new(1, 1)
+                .expect("Dec creation failed"),
+            metadata: Default::default(),
+        }
+    };
+    let epoch = Epoch::default();
+    let params = test_init_genesis(
+        &mut s,
+        params,
+        [
+            genesis_validator(&val1, pk1.clone(), 2),
+            genesis_validator(&val2, pk2.clone(), 3),
+        ]
+        .into_iter(),
+        epoch,
+    )
+    .unwrap();
+
+    // A rotation submitted for an address that is not a validator is rejected
+    let new_ck2 = common_sk_from_simple_seed(4).to_public();
+    let err = change_consensus_key(&mut s, &non_validator, &new_ck2, epoch)
+        .unwrap_err()
+        .downcast::<ConsensusKeyChangeError>()
+        .unwrap();
+    assert_matches!(
+        *err,
+        ConsensusKeyChangeError::NotAValidator(addr) if addr == non_validator
+    );
+
+    // A rotation to another validator's key is rejected
+    let err = change_consensus_key(&mut s, &val2, &pk1, epoch)
+        .unwrap_err()
+        .downcast::<ConsensusKeyChangeError>()
+        .unwrap();
+    assert_matches!(
+        *err,
+        ConsensusKeyChangeError::KeyAlreadyUsed(key) if key == pk1
+    );
+    let pending = queries::pending_consensus_key_changes(&s, epoch).unwrap();
+    assert!(pending.is_empty());
+
+    // Schedule a rotation
+    change_consensus_key(&mut s, &val2, &new_ck2, epoch).unwrap();
+    let effective_epoch = epoch + params.pipeline_len;
+    assert_eq!(
+        queries::pending_consensus_key_changes(&s, epoch).unwrap(),
+        vec![PendingConsensusKeyChange {
+            validator: val2.clone(),
+            current_key: pk2.clone(),
+            new_key: new_ck2.clone(),
+            effective_epoch,
+        }]
+    );
+
+    // The same key cannot be used again
+    let err = change_consensus_key(&mut s, &val1, &new_ck2, epoch)
+        .unwrap_err()
+        .downcast::<ConsensusKeyChangeError>()
+        .unwrap();
+    assert_matches!(*err, ConsensusKeyChangeError::KeyAlreadyUsed(_));
+
+    // CometBFT keeps receiving the old key until the effective epoch
+    let mut epoch = epoch;
+    while epoch.next() < effective_epoch {
+        epoch = advance_epoch(&mut s, &params);
+        let tm_updates = get_tendermint_set_updates(&s, &params, epoch);
+        assert!(tm_updates.is_empty(), "Unexpected updates {tm_updates:?}");
+        assert_eq!(
+            validator_consensus_key_handle(&val2)
+                .get(&s, epoch, &params)
+                .unwrap(),
+            Some(pk2.clone())
+        );
+    }
+
+    // The new key replaces the old one at the effective epoch
+    let epoch = advance_epoch(&mut s, &params);
+    assert_eq!(epoch, effective_epoch);
+    let tm_updates = get_tendermint_set_updates(&s, &params, epoch);
+    assert_eq!(
+        tm_updates,
+        vec![
+            ValidatorSetUpdate::Consensus(ConsensusValidator {
+                consensus_key: new_ck2,
+                bonded_stake: into_tm_voting_power(
+                    params.tm_votes_per_token,
+                    stake
+                ),
+            }),
+            ValidatorSetUpdate::Deactivated(pk2),
+        ]
+    );
+    let pending = queries::pending_consensus_key_changes(&s, epoch).unwrap();
+    assert!(pending.is_empty());
+}
+
 proptest! {
     // Generate arb valid input for `test_purge_validator_information_aux`
     #![proptest_config(Config {
@@ -145,6 +145,10 @@ pub type EpochedSlashes = crate::epoched::NestedEpoched<
     crate::epoched::OffsetSlashProcessingLenPlus,
 >;
 
+/// Summaries of the processed slashes, keyed by the infraction epoch and the
+/// slashed validator
+pub type SlashSummaries = NestedMap<Epoch, LazyMap<Address, SlashSummary>>;
+
 /// Epoched validator's unbonds
 ///
 /// The map keys from outside in are:
@@ -155,6 +159,11 @@ pub type Unbonds = NestedMap<Epoch, LazyMap<Epoch, token::Amount>>;
 /// Consensus keys set, used to ensure uniqueness
 pub type ConsensusKeys = LazySet<common::PublicKey>;
 
+/// The sources of the bonds to a validator (including its self-bond), indexed
+/// by the validator. A source is kept once it has bonded, so it may have no
+/// bond or unbond left.
+pub type ValidatorDelegators = LazySet<Address>;
+
 /// Total unbonded for validators needed for slashing computations.
 /// The outer `Epoch` corresponds to the epoch at which the unbond is active
 /// (affects the deltas, pipeline after submission). The inner `Epoch`
@@ -581,6 +590,75 @@ pub enum SlashType {
     LightClientAttack,
 }
 
+/// A delegator-facing summary of the slashes processed for a validator's
+/// infractions in a single epoch
+#[derive(
+    Debug, Clone, BorshDeserialize, BorshSerialize, BorshSchema, PartialEq, Eq,
+)]
+pub struct SlashSummary {
+    /// The slashed validator
+    pub validator: Address,
+    /// Epoch at which the slashable events occurred
+    pub infraction_epoch: Epoch,
+    /// The processed slashes with their types and rates
+    pub slashes: Vec<Slash>,
+    /// The combined slash rate applied to the validator's bonds
+    pub rate: Dec,
+    /// The total amount slashed from the bonds to the validator
+    pub total_slashed: token::Amount,
+    /// The amounts slashed from the bonds of the affected delegators. When
+    /// there are too many delegators, only the most affected ones are listed
+    /// here and the rest are aggregated in `other_delegators`.
+    pub delegators: BTreeMap<Address, token::Amount>,
+    /// The aggregate of the affected delegators that are not listed
+    pub other_delegators: Option<SlashedDelegatorsAggregate>,
+}
+
+impl SlashSummary {
+    /// Get the amount slashed from the given delegator's bonds, if listed
+    pub fn slashed_amount_of(
+        &self,
+        delegator: &Address,
+    ) -> Option<token::Amount> {
+        self.delegators.get(delegator).copied()
+    }
+}
+
+/// An aggregate of the delegators affected by a slash
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    BorshDeserialize,
+    BorshSerialize,
+    BorshSchema,
+    PartialEq,
+    Eq,
+)]
+pub struct SlashedDelegatorsAggregate {
+    /// The number of the delegators
+    pub count: u64,
+    /// The total amount slashed from their bonds
+    pub amount: token::Amount,
+}
+
+/// A validator's consensus key change that has been scheduled, but hasn't
+/// taken effect yet
+#[derive(
+    Debug, Clone, BorshDeserialize, BorshSerialize, BorshSchema, PartialEq, Eq,
+)]
+pub struct PendingConsensusKeyChange {
+    /// The validator changing its consensus key
+    pub validator: Address,
+    /// The consensus key that is currently in effect
+    pub current_key: common::PublicKey,
+    /// The new consensus key
+    pub new_key: common::PublicKey,
+    /// The epoch from which the new key is handed to CometBFT
+    pub effective_epoch: Epoch,
+}
+
 /// VoteInfo inspired from tendermint for validators whose signature was
 /// included in the last block
 #[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
@@ -648,6 +726,37 @@ pub struct UnbondDetails {
     pub slashed_amount: Option<token::Amount>,
 }
 
+/// Projection of a bond's amounts over the epochs from the current epoch up to
+/// the unbonding length offset
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    BorshDeserialize,
+    BorshSerialize,
+    BorshSchema,
+    PartialEq,
+)]
+pub struct BondProjection {
+    /// The projected amounts, ordered by epoch
+    pub epochs: Vec<EpochBondProjection>,
+}
+
+/// Projected amounts of a bond in a single epoch
+#[derive(
+    Debug, Clone, BorshDeserialize, BorshSerialize, BorshSchema, PartialEq,
+)]
+pub struct EpochBondProjection {
+    /// The projected epoch
+    pub epoch: Epoch,
+    /// Change of the bonded amount from the previous epoch, before slashing
+    pub delta: token::Change,
+    /// Token amount contributing to the validator's stake, before slashing
+    pub bonded: token::Amount,
+    /// Token amount contributing to the validator's stake, after slashing
+    pub total: token::Amount,
+}
+
 impl Display for BondId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
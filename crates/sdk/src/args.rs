@@ -571,8 +571,13 @@ pub struct VoteProposal<C: NamadaTypes = SdkTypes> {
     pub proposal_id: Option<u64>,
     /// The vote
     pub vote: String,
+    /// The optional memo attached to the vote
+    pub memo: Option<String>,
     /// The address of the voter
     pub voter: C::Address,
+    /// The validators of the delegations to vote with, all the delegations
+    /// of the voter if empty
+    pub delegations: Vec<C::Address>,
     /// Flag if proposal vote should be run offline
     pub is_offline: bool,
     /// The proposal file path
@@ -607,11 +612,27 @@ impl<C: NamadaTypes> VoteProposal<C> {
         Self { vote, ..self }
     }
 
+    /// The memo attached to the vote
+    pub fn memo(self, memo: String) -> Self {
+        Self {
+            memo: Some(memo),
+            ..self
+        }
+    }
+
     /// The address of the voter
     pub fn voter(self, voter: C::Address) -> Self {
         Self { voter, ..self }
     }
 
+    /// The validators of the delegations to vote with
+    pub fn delegations(self, delegations: Vec<C::Address>) -> Self {
+        Self {
+            delegations,
+            ..self
+        }
+    }
+
     /// Flag if proposal vote should be run offline
     pub fn is_offline(self, is_offline: bool) -> Self {
         Self { is_offline, ..self }
@@ -1462,6 +1483,8 @@ pub struct MetaDataChange<C: NamadaTypes = SdkTypes> {
     pub avatar: Option<String>,
     /// New validator commission rate
     pub commission_rate: Option<Dec>,
+    /// New cap on the total amount delegated to the validator
+    pub max_total_delegated: Option<token::Amount>,
     /// Path to the TX WASM code file
     pub tx_code_path: PathBuf,
 }
@@ -1539,6 +1562,17 @@ impl<C: NamadaTypes> MetaDataChange<C> {
             ..self
         }
     }
+
+    /// New cap on the total amount delegated to the validator
+    pub fn max_total_delegated(
+        self,
+        max_total_delegated: token::Amount,
+    ) -> Self {
+        Self {
+            max_total_delegated: Some(max_total_delegated),
+            ..self
+        }
+    }
 }
 
 impl MetaDataChange {
@@ -3,11 +3,17 @@
 //! The log can only hold `N` events at a time, where `N` is a configurable
 //! parameter. If the log is holding `N` events, and a new event is logged,
 //! old events are pruned.
-use circular_queue::CircularQueue;
+//!
+//! The IBC packet events are indexed by their packet attributes as they are
+//! logged, such that relayers can look them up without scanning the log.
+use std::collections::VecDeque;
+
+use namada_core::storage::BlockHeight;
 
 use crate::events::Event;
 
 pub mod dumb_queries;
+pub mod ibc_packets;
 
 /// Parameters to configure the pruning of the event log.
 #[derive(Debug, Copy, Clone)]
@@ -32,7 +38,15 @@ impl Default for Params {
 /// `FinalizeBlock` calls, in the ledger.
 #[derive(Debug)]
 pub struct EventLog {
-    queue: CircularQueue<Event>,
+    /// The logged events, from the oldest to the newest
+    queue: VecDeque<Event>,
+    /// The maximum number of events held by the log
+    max_log_events: usize,
+    /// The position of the oldest event of the log, counted from the first
+    /// event ever logged
+    first_position: u64,
+    /// The index of the IBC packet events of the log
+    ibc_packets: ibc_packets::IbcPacketIndex,
 }
 
 impl Default for EventLog {
@@ -45,7 +59,10 @@ impl EventLog {
     /// Return a new event log.
     pub fn new(params: Params) -> Self {
         Self {
-            queue: CircularQueue::with_capacity(params.max_log_events),
+            queue: VecDeque::with_capacity(params.max_log_events),
+            max_log_events: params.max_log_events,
+            first_position: 0,
+            ibc_packets: Default::default(),
         }
     }
 
@@ -56,16 +73,28 @@ impl EventLog {
     {
         let mut num_entries = 0;
         for event in events.into_iter() {
-            self.queue.push(event);
+            if self.max_log_events == 0 {
+                break;
+            }
+            if self.queue.len() == self.max_log_events {
+                if let Some(pruned) = self.queue.pop_front() {
+                    self.ibc_packets.remove(self.first_position, &pruned);
+                    self.first_position += 1;
+                }
+            }
+            let position = self.first_position + self.queue.len() as u64;
+            self.ibc_packets.insert(position, &event);
+            self.queue.push_back(event);
             num_entries += 1;
         }
         tracing::debug!(num_entries, "Added new entries to the event log");
     }
 
-    /// Returns a new iterator over this [`EventLog`].
+    /// Returns a new iterator over this [`EventLog`], from the newest to the
+    /// oldest event.
     #[inline]
     pub fn iter(&self) -> impl Iterator<Item = &Event> {
-        self.queue.iter()
+        self.queue.iter().rev()
     }
 
     /// Returns a filtering iterator over this [`EventLog`].
@@ -74,9 +103,29 @@ impl EventLog {
         &self,
         matcher: dumb_queries::QueryMatcher,
     ) -> impl Iterator<Item = &Event> {
-        self.queue
-            .iter()
-            .filter(move |&event| matcher.matches(event))
+        self.iter().filter(move |&event| matcher.matches(event))
+    }
+
+    /// Returns the page of the IBC packet events matching the given filter,
+    /// emitted in the given range of block heights (inclusive), from the
+    /// oldest to the newest event. The pages are numbered from `0`.
+    pub fn events_by_ibc_packet(
+        &self,
+        filter: &ibc_packets::IbcPacketFilter,
+        from_height: BlockHeight,
+        to_height: BlockHeight,
+        page: u32,
+        per_page: u8,
+    ) -> Vec<&Event> {
+        self.ibc_packets
+            .positions(filter, from_height, to_height, page, per_page)
+            .into_iter()
+            .map(|position| {
+                let index = usize::try_from(position - self.first_position)
+                    .expect("The position of an event should fit in usize");
+                &self.queue[index]
+            })
+            .collect()
     }
 }
 
@@ -84,6 +133,7 @@ impl EventLog {
 mod tests {
     use namada_core::hash::Hash;
 
+    use super::ibc_packets::IbcPacketFilter;
     use super::*;
     use crate::events::{EventLevel, EventType};
 
@@ -120,6 +170,157 @@ mod tests {
         vec![event_1, event_2]
     }
 
+    /// Return a mock IBC packet event on the given source channel.
+    fn mock_packet_event(
+        event_type: &str,
+        src_channel: &str,
+        sequence: u64,
+        height: u64,
+    ) -> Event {
+        let mut attributes = std::collections::HashMap::new();
+        attributes
+            .insert("packet_src_port".to_string(), "transfer".to_string());
+        attributes
+            .insert("packet_src_channel".to_string(), src_channel.to_string());
+        attributes
+            .insert("packet_dst_port".to_string(), "transfer".to_string());
+        attributes
+            .insert("packet_dst_channel".to_string(), "channel-9".to_string());
+        attributes.insert("packet_sequence".to_string(), sequence.to_string());
+        attributes.insert("height".to_string(), height.to_string());
+        Event {
+            event_type: EventType::Ibc(event_type.to_string()),
+            level: EventLevel::Tx,
+            attributes,
+        }
+    }
+
+    /// Log `SendPacket` and `RecvPacket` events on the channels `channel-0`
+    /// and `channel-1` at the heights `1..=num_heights`, the sequences being
+    /// the heights, together with some non-IBC events.
+    fn log_packet_events(log: &mut EventLog, num_heights: u64) {
+        for height in 1..=num_heights {
+            let mut events = mock_tx_events(HASH);
+            for channel in ["channel-0", "channel-1"] {
+                for event_type in ["send_packet", "recv_packet"] {
+                    events.push(mock_packet_event(
+                        event_type, channel, height, height,
+                    ));
+                }
+            }
+            log.log_events(events);
+        }
+    }
+
+    /// Test querying the IBC packet events by their channel and sequence,
+    /// and paginating the results.
+    #[test]
+    fn test_log_ibc_packets() {
+        const NUM_HEIGHTS: u64 = 4;
+
+        let mut log = EventLog::new(Params::default());
+        log_packet_events(&mut log, NUM_HEIGHTS);
+
+        let by_channel: IbcPacketFilter =
+            "ibc.packet.src_channel='channel-0'".parse().unwrap();
+        let query = |filter: &IbcPacketFilter, from, to, page, per_page| {
+            log.events_by_ibc_packet(
+                filter,
+                BlockHeight(from),
+                BlockHeight(to),
+                page,
+                per_page,
+            )
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>()
+        };
+
+        // all the events of the channel, from the oldest to the newest
+        let expected: Vec<_> = (1..=NUM_HEIGHTS)
+            .flat_map(|height| {
+                ["send_packet", "recv_packet"].map(|event_type| {
+                    mock_packet_event(event_type, "channel-0", height, height)
+                })
+            })
+            .collect();
+        let events = query(&by_channel, 1, NUM_HEIGHTS, 0, u8::MAX);
+        assert_eq!(events, expected);
+
+        // the events of the channel and the sequence
+        let by_sequence: IbcPacketFilter =
+            "ibc.packet.src_channel='channel-1' AND ibc.packet.sequence='3'"
+                .parse()
+                .unwrap();
+        let events = query(&by_sequence, 1, NUM_HEIGHTS, 0, u8::MAX);
+        assert_eq!(
+            events,
+            vec![
+                mock_packet_event("send_packet", "channel-1", 3, 3),
+                mock_packet_event("recv_packet", "channel-1", 3, 3),
+            ]
+        );
+        let unknown: IbcPacketFilter =
+            "ibc.packet.src_channel='channel-2'".parse().unwrap();
+        assert!(query(&unknown, 1, NUM_HEIGHTS, 0, u8::MAX).is_empty());
+
+        // the range of heights is inclusive
+        let events = query(&by_channel, 2, 3, 0, u8::MAX);
+        assert_eq!(events, expected[2..6]);
+        let events = query(&by_sequence, 4, NUM_HEIGHTS, 0, u8::MAX);
+        assert!(events.is_empty());
+        assert!(query(&by_channel, 3, 2, 0, u8::MAX).is_empty());
+
+        // the pages are filled up to the last one
+        assert_eq!(query(&by_channel, 1, NUM_HEIGHTS, 0, 3), expected[..3]);
+        assert_eq!(query(&by_channel, 1, NUM_HEIGHTS, 1, 3), expected[3..6]);
+        assert_eq!(query(&by_channel, 1, NUM_HEIGHTS, 2, 3), expected[6..]);
+        assert!(query(&by_channel, 1, NUM_HEIGHTS, 3, 3).is_empty());
+        assert!(query(&by_channel, 1, NUM_HEIGHTS, 0, 0).is_empty());
+    }
+
+    /// Test that the pruned IBC packet events are removed from the index.
+    #[test]
+    fn test_log_prune_ibc_packets() {
+        const NUM_HEIGHTS: u64 = 4;
+        // `log_packet_events` logs 6 events per height
+        const LOG_CAP: usize = 6 * 3 - 3;
+
+        let mut log = EventLog::new(Params {
+            max_log_events: LOG_CAP,
+        });
+        log_packet_events(&mut log, NUM_HEIGHTS);
+
+        // the events of the first height are pruned, together with the
+        // `SendPacket` event on `channel-0` of the second height
+        let filter: IbcPacketFilter =
+            "ibc.packet.src_channel='channel-0'".parse().unwrap();
+        let events: Vec<_> = log
+            .events_by_ibc_packet(
+                &filter,
+                BlockHeight(1),
+                BlockHeight(NUM_HEIGHTS),
+                0,
+                u8::MAX,
+            )
+            .into_iter()
+            .cloned()
+            .collect();
+        let mut expected =
+            vec![mock_packet_event("recv_packet", "channel-0", 2, 2)];
+        for height in 3..=NUM_HEIGHTS {
+            for event_type in ["send_packet", "recv_packet"] {
+                expected.push(mock_packet_event(
+                    event_type,
+                    "channel-0",
+                    height,
+                    height,
+                ));
+            }
+        }
+        assert_eq!(events, expected);
+    }
+
     /// Test adding a couple of events to the event log, and
     /// reading those events back.
     #[test]
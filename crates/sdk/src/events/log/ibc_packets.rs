@@ -0,0 +1,364 @@
+//! Filtering of the IBC packet events in the event log.
+//!
+//! The filters are parsed from queries of the form:
+//!
+//! ```text
+//! ibc.packet.src_channel='channel-0' AND ibc.packet.sequence='1'
+//! ```
+//!
+//! The conditions of a query are matched against the packet attributes of the
+//! IBC events, named as the ones of the Tendermint events relayed by hermes
+//! (e.g. `packet_src_channel`).
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use namada_core::storage::BlockHeight;
+use thiserror::Error;
+
+use crate::events::{Event, EventType};
+
+/// The prefix of the keys of the conditions of an [`IbcPacketFilter`] query
+const QUERY_KEY_PREFIX: &str = "ibc.packet.";
+
+/// The separator of the conditions of an [`IbcPacketFilter`] query
+const QUERY_CONJUNCTION: &str = " AND ";
+
+/// Errors of the parsing of an [`IbcPacketFilter`]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FilterParseError {
+    #[error("The query has no conditions")]
+    Empty,
+    #[error("Invalid condition {0}, expected <key>='<value>'")]
+    InvalidCondition(String),
+    #[error("Unknown IBC packet attribute {0}")]
+    UnknownAttribute(String),
+    #[error("The IBC packet attribute {0} is given more than once")]
+    DuplicateAttribute(String),
+    #[error("Invalid packet sequence {0}")]
+    InvalidSequence(String),
+}
+
+/// The attributes of the IBC packet events that can be filtered on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PacketAttribute {
+    /// The port of the packet's source
+    SrcPort,
+    /// The channel of the packet's source
+    SrcChannel,
+    /// The port of the packet's destination
+    DstPort,
+    /// The channel of the packet's destination
+    DstChannel,
+    /// The sequence of the packet
+    Sequence,
+}
+
+impl PacketAttribute {
+    /// All the packet attributes
+    pub const ALL: [Self; 5] = [
+        Self::SrcPort,
+        Self::SrcChannel,
+        Self::DstPort,
+        Self::DstChannel,
+        Self::Sequence,
+    ];
+
+    /// The key of the attribute in the IBC events
+    pub fn event_key(&self) -> &'static str {
+        match self {
+            Self::SrcPort => "packet_src_port",
+            Self::SrcChannel => "packet_src_channel",
+            Self::DstPort => "packet_dst_port",
+            Self::DstChannel => "packet_dst_channel",
+            Self::Sequence => "packet_sequence",
+        }
+    }
+
+    /// The key of the attribute in the queries, without the
+    /// `ibc.packet.` prefix
+    pub fn query_key(&self) -> &'static str {
+        match self {
+            Self::SrcPort => "src_port",
+            Self::SrcChannel => "src_channel",
+            Self::DstPort => "dst_port",
+            Self::DstChannel => "dst_channel",
+            Self::Sequence => "sequence",
+        }
+    }
+}
+
+/// A filter of the IBC packet events by the values of their packet
+/// attributes. An event matches the filter if it has all the given values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IbcPacketFilter {
+    /// The port of the packet's source
+    pub src_port: Option<String>,
+    /// The channel of the packet's source
+    pub src_channel: Option<String>,
+    /// The port of the packet's destination
+    pub dst_port: Option<String>,
+    /// The channel of the packet's destination
+    pub dst_channel: Option<String>,
+    /// The sequence of the packet
+    pub sequence: Option<u64>,
+}
+
+impl IbcPacketFilter {
+    /// Returns the conditions of this filter as the attributes of the events
+    /// and their expected values
+    pub fn conditions(&self) -> Vec<(PacketAttribute, String)> {
+        let mut conditions = Vec::new();
+        for (attribute, value) in [
+            (PacketAttribute::SrcPort, self.src_port.clone()),
+            (PacketAttribute::SrcChannel, self.src_channel.clone()),
+            (PacketAttribute::DstPort, self.dst_port.clone()),
+            (PacketAttribute::DstChannel, self.dst_channel.clone()),
+            (
+                PacketAttribute::Sequence,
+                self.sequence.map(|seq| seq.to_string()),
+            ),
+        ] {
+            if let Some(value) = value {
+                conditions.push((attribute, value));
+            }
+        }
+        conditions
+    }
+}
+
+impl Display for IbcPacketFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let conditions = self
+            .conditions()
+            .into_iter()
+            .map(|(attribute, value)| {
+                format!("{QUERY_KEY_PREFIX}{}='{value}'", attribute.query_key())
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", conditions.join(QUERY_CONJUNCTION))
+    }
+}
+
+impl FromStr for IbcPacketFilter {
+    type Err = FilterParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err(FilterParseError::Empty);
+        }
+        let mut filter = Self::default();
+        for condition in s.split(QUERY_CONJUNCTION) {
+            let (key, value) = condition
+                .trim()
+                .split_once('=')
+                .and_then(|(key, value)| {
+                    let value = value.strip_prefix('\'')?.strip_suffix('\'')?;
+                    Some((key, value.to_owned()))
+                })
+                .ok_or_else(|| {
+                    FilterParseError::InvalidCondition(condition.to_owned())
+                })?;
+            let attribute = key
+                .strip_prefix(QUERY_KEY_PREFIX)
+                .and_then(|key| {
+                    PacketAttribute::ALL
+                        .into_iter()
+                        .find(|attribute| attribute.query_key() == key)
+                })
+                .ok_or_else(|| {
+                    FilterParseError::UnknownAttribute(key.to_owned())
+                })?;
+            let is_duplicate = match attribute {
+                PacketAttribute::SrcPort => {
+                    filter.src_port.replace(value).is_some()
+                }
+                PacketAttribute::SrcChannel => {
+                    filter.src_channel.replace(value).is_some()
+                }
+                PacketAttribute::DstPort => {
+                    filter.dst_port.replace(value).is_some()
+                }
+                PacketAttribute::DstChannel => {
+                    filter.dst_channel.replace(value).is_some()
+                }
+                PacketAttribute::Sequence => {
+                    let sequence = value.parse().map_err(|_| {
+                        FilterParseError::InvalidSequence(value)
+                    })?;
+                    filter.sequence.replace(sequence).is_some()
+                }
+            };
+            if is_duplicate {
+                return Err(FilterParseError::DuplicateAttribute(
+                    key.to_owned(),
+                ));
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// Returns the height of the given [`Event`] if it's an IBC event of a packet
+fn packet_event_height(event: &Event) -> Option<BlockHeight> {
+    if !matches!(event.event_type, EventType::Ibc(_))
+        || !event
+            .attributes
+            .contains_key(PacketAttribute::Sequence.event_key())
+    {
+        return None;
+    }
+    event.attributes.get("height")?.parse().ok()
+}
+
+/// The entry of an IBC packet event in an [`IbcPacketIndex`], i.e. the height
+/// at which the event was emitted and its position in the log, counted from
+/// the first event ever logged. The entries are ordered by height, and then
+/// by position.
+type PacketEntry = (BlockHeight, u64);
+
+/// An index of the IBC packet events of the event log by the values of their
+/// packet attributes, and by the heights at which they were emitted.
+#[derive(Debug, Default)]
+pub struct IbcPacketIndex {
+    /// The entries of all the indexed events
+    packets: BTreeSet<PacketEntry>,
+    /// The entries of the indexed events by their attributes' values
+    by_attribute: HashMap<(PacketAttribute, String), BTreeSet<PacketEntry>>,
+}
+
+impl IbcPacketIndex {
+    /// Index the event at the given position, if it's an IBC packet event
+    /// with a height
+    pub fn insert(&mut self, position: u64, event: &Event) {
+        let Some(height) = packet_event_height(event) else {
+            return;
+        };
+        self.packets.insert((height, position));
+        for attribute in PacketAttribute::ALL {
+            if let Some(value) = event.attributes.get(attribute.event_key()) {
+                self.by_attribute
+                    .entry((attribute, value.clone()))
+                    .or_default()
+                    .insert((height, position));
+            }
+        }
+    }
+
+    /// Remove the event at the given position from the index
+    pub fn remove(&mut self, position: u64, event: &Event) {
+        let Some(height) = packet_event_height(event) else {
+            return;
+        };
+        let entry = (height, position);
+        if !self.packets.remove(&entry) {
+            return;
+        }
+        for attribute in PacketAttribute::ALL {
+            if let Some(value) = event.attributes.get(attribute.event_key()) {
+                let key = (attribute, value.clone());
+                if let Some(entries) = self.by_attribute.get_mut(&key) {
+                    entries.remove(&entry);
+                    if entries.is_empty() {
+                        self.by_attribute.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the page of the positions of the events matching the given
+    /// filter, emitted in the given range of block heights (inclusive), in
+    /// ascending order. The pages are numbered from `0`.
+    pub fn positions(
+        &self,
+        filter: &IbcPacketFilter,
+        from_height: BlockHeight,
+        to_height: BlockHeight,
+        page: u32,
+        per_page: u8,
+    ) -> Vec<u64> {
+        if from_height > to_height {
+            return vec![];
+        }
+        let mut sets = Vec::new();
+        for condition in filter.conditions() {
+            match self.by_attribute.get(&condition) {
+                Some(entries) => sets.push(entries),
+                None => return vec![],
+            }
+        }
+        sets.sort_by_key(|entries| entries.len());
+        let (smallest, others) = match sets.split_first() {
+            Some((smallest, others)) => (*smallest, others),
+            None => (&self.packets, &[][..]),
+        };
+        let per_page = usize::from(per_page);
+        smallest
+            .range((from_height, 0)..=(to_height, u64::MAX))
+            .filter(|entry| {
+                others.iter().all(|entries| entries.contains(*entry))
+            })
+            .skip(per_page.saturating_mul(page as usize))
+            .take(per_page)
+            .map(|(_height, position)| *position)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test the parsing of the queries and their round-trip through the
+    /// filters' display.
+    #[test]
+    fn test_parse_ibc_packet_filter() {
+        let query = "ibc.packet.src_port='transfer' AND \
+                     ibc.packet.src_channel='channel-0' AND \
+                     ibc.packet.dst_channel='channel-1' AND \
+                     ibc.packet.sequence='3'";
+        let filter = IbcPacketFilter::from_str(query).unwrap();
+        assert_eq!(
+            filter,
+            IbcPacketFilter {
+                src_port: Some("transfer".to_string()),
+                src_channel: Some("channel-0".to_string()),
+                dst_port: None,
+                dst_channel: Some("channel-1".to_string()),
+                sequence: Some(3),
+            }
+        );
+        assert_eq!(filter.to_string(), query);
+
+        let tests = [
+            ("", FilterParseError::Empty),
+            (
+                "ibc.packet.src_channel=channel-0",
+                FilterParseError::InvalidCondition(
+                    "ibc.packet.src_channel=channel-0".to_string(),
+                ),
+            ),
+            (
+                "ibc.packet.channel='channel-0'",
+                FilterParseError::UnknownAttribute(
+                    "ibc.packet.channel".to_string(),
+                ),
+            ),
+            (
+                "ibc.packet.sequence='1' AND ibc.packet.sequence='2'",
+                FilterParseError::DuplicateAttribute(
+                    "ibc.packet.sequence".to_string(),
+                ),
+            ),
+            (
+                "ibc.packet.sequence='one'",
+                FilterParseError::InvalidSequence("one".to_string()),
+            ),
+        ];
+        for (query, error) in tests {
+            assert_eq!(IbcPacketFilter::from_str(query), Err(error));
+        }
+    }
+}
@@ -3,7 +3,10 @@ pub mod log;
 
 use std::collections::HashMap;
 
-pub use namada_core::event::{Event, EventError, EventLevel, EventType};
+pub use namada_core::event::{
+    BondEvent, BondEventKind, Event, EventError, EventLevel, EventType,
+    IbcThroughputEvent,
+};
 use serde_json::Value;
 
 // use crate::ledger::governance::utils::ProposalEvent;
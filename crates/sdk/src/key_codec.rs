@@ -0,0 +1,486 @@
+//! Classification of the storage keys by the encoding of their values, to let
+//! the clients decode the raw values read from the storage
+
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use namada_core::address::Address;
+use namada_core::dec::Dec;
+use namada_core::ibc::core::channel::types::channel::ChannelEnd;
+use namada_core::ibc::core::channel::types::commitment::{
+    AcknowledgementCommitment, PacketCommitment,
+};
+use namada_core::ibc::core::channel::types::packet::Receipt;
+use namada_core::ibc::core::client::types::Height;
+use namada_core::ibc::core::connection::types::ConnectionEnd;
+use namada_core::ibc::core::host::types::identifiers::Sequence;
+use namada_core::ibc::core::host::types::path::Path;
+use namada_core::ibc::{PacketEscrow, PacketFee};
+use namada_core::storage::{DbKeySeg, Epoch, Key};
+use namada_core::tendermint::Time as TmTime;
+use namada_core::token::{Amount, Denomination};
+use namada_governance::parameters::ProposalParamsSnapshot;
+use namada_governance::storage::keys as gov_storage;
+use namada_governance::storage::VotingPowerSnapshot;
+use namada_governance::utils::{ProposalResult, ProposalResultRecord};
+use namada_governance::{ProposalType, ProposalVote};
+use namada_ibc::context::client::{AnyClientState, AnyConsensusState};
+use namada_ibc::parameters::{IbcParameters, ThroughputRecord};
+use namada_ibc::storage as ibc_storage;
+use namada_token::storage_key::{
+    denom_key, is_any_minted_balance_key, is_any_minter_key,
+    is_any_token_balance_key, is_any_token_parameter_key,
+};
+use serde::{Deserialize, Serialize};
+
+/// The type URL of a protobuf `Any` wrapping a message with its own type URL
+const ANY_TYPE_URL: &str = "/google.protobuf.Any";
+/// The type URL of an IBC connection end
+const CONNECTION_TYPE_URL: &str = "/ibc.core.connection.v1.ConnectionEnd";
+/// The type URL of an IBC channel end
+const CHANNEL_TYPE_URL: &str = "/ibc.core.channel.v1.Channel";
+/// The type URL of an IBC height
+const HEIGHT_TYPE_URL: &str = "/ibc.core.client.v1.Height";
+/// The type URL of a timestamp
+const TIMESTAMP_TYPE_URL: &str = "/google.protobuf.Timestamp";
+
+/// The encoding of a storage value
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+)]
+pub enum Codec {
+    /// Borsh encoding of the type, laid out as its `BorshSchema`
+    Borsh,
+    /// Protobuf encoding of the message
+    Protobuf {
+        /// The type URL of the message
+        type_url: String,
+    },
+    /// Big-endian bytes of a `u64`, unlike the borsh encoded integers
+    BigEndianU64,
+    /// The raw bytes, e.g. a hash or a wasm code
+    Raw,
+}
+
+/// The descriptor of the schema of a storage value
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+)]
+pub struct SchemaDescriptor {
+    /// The encoding of the value
+    pub codec: Codec,
+    /// The name of the Rust type of the decoded value
+    pub type_name: String,
+}
+
+impl SchemaDescriptor {
+    fn new<T: ?Sized>(codec: Codec) -> Self {
+        Self {
+            codec,
+            type_name: type_name::<T>().to_string(),
+        }
+    }
+
+    fn borsh<T: BorshDeserialize>() -> Self {
+        Self::new::<T>(Codec::Borsh)
+    }
+
+    fn protobuf<T>(type_url: &str) -> Self {
+        Self::new::<T>(Codec::Protobuf {
+            type_url: type_url.to_string(),
+        })
+    }
+
+    fn big_endian_u64<T>() -> Self {
+        Self::new::<T>(Codec::BigEndianU64)
+    }
+
+    fn raw<T>() -> Self {
+        Self::new::<T>(Codec::Raw)
+    }
+}
+
+/// Returns the schema of the value under the given key of governance, IBC or
+/// tokens, or `None` for a key of another family
+pub fn classify(key: &Key) -> Option<SchemaDescriptor> {
+    classify_governance_key(key)
+        .or_else(|| classify_ibc_key(key))
+        .or_else(|| classify_token_key(key))
+}
+
+fn classify_governance_key(key: &Key) -> Option<SchemaDescriptor> {
+    if !gov_storage::is_governance_key(key) {
+        return None;
+    }
+    let descriptor = if gov_storage::is_counter_key(key) {
+        SchemaDescriptor::borsh::<u64>()
+    } else if gov_storage::is_content_key(key) {
+        SchemaDescriptor::borsh::<BTreeMap<String, String>>()
+    } else if gov_storage::is_author_key(key) {
+        SchemaDescriptor::borsh::<Address>()
+    } else if gov_storage::is_proposal_type_key(key) {
+        SchemaDescriptor::borsh::<ProposalType>()
+    } else if gov_storage::is_start_or_end_epoch_key(key)
+        || gov_storage::is_grace_epoch_key(key)
+        || gov_storage::is_author_proposal_key(key)
+    {
+        SchemaDescriptor::borsh::<Epoch>()
+    } else if gov_storage::is_balance_key(key)
+        || gov_storage::is_min_proposal_fund_key(key)
+    {
+        SchemaDescriptor::borsh::<Amount>()
+    } else if gov_storage::is_proposal_code_key(key) {
+        SchemaDescriptor::raw::<Vec<u8>>()
+    } else if gov_storage::is_commit_proposal_key(key)
+        || gov_storage::is_withdrawn_key(key)
+        || is_proposal_execution_key(key)
+    {
+        SchemaDescriptor::borsh::<()>()
+    } else if gov_storage::is_vote_key(key) {
+        SchemaDescriptor::borsh::<ProposalVote>()
+    } else if gov_storage::is_vote_memo_key(key) {
+        SchemaDescriptor::borsh::<String>()
+    } else if gov_storage::is_proposal_params_snapshot_key(key) {
+        SchemaDescriptor::borsh::<ProposalParamsSnapshot>()
+    } else if gov_storage::is_proposal_result_key(key) {
+        let id = gov_storage::get_proposal_id(key)?;
+        if *key == gov_storage::get_proposal_result_record_key(id) {
+            SchemaDescriptor::borsh::<ProposalResultRecord>()
+        } else {
+            SchemaDescriptor::borsh::<ProposalResult>()
+        }
+    } else if gov_storage::is_voting_power_snapshot_key(key).is_some() {
+        SchemaDescriptor::borsh::<VotingPowerSnapshot>()
+    } else if gov_storage::is_content_required_fields_key(key) {
+        SchemaDescriptor::borsh::<Vec<String>>()
+    } else if gov_storage::is_threshold_default_key(key)
+        || gov_storage::is_threshold_pgf_steward_key(key)
+        || gov_storage::is_threshold_pgf_payment_key(key)
+    {
+        SchemaDescriptor::borsh::<Dec>()
+    } else if gov_storage::is_parameter_key(key) {
+        // The other parameters are the sizes and the periods
+        SchemaDescriptor::borsh::<u64>()
+    } else {
+        return None;
+    };
+    Some(descriptor)
+}
+
+/// Check if the key is the key of a proposal pending for execution
+fn is_proposal_execution_key(key: &Key) -> bool {
+    gov_storage::get_proposal_id(key)
+        .map(|id| *key == gov_storage::get_proposal_execution_key(id))
+        .unwrap_or_default()
+}
+
+fn classify_ibc_key(key: &Key) -> Option<SchemaDescriptor> {
+    if !ibc_storage::is_ibc_key(key) {
+        return None;
+    }
+    let descriptor = if ibc_storage::is_ibc_counter_key(key) {
+        SchemaDescriptor::borsh::<u64>()
+    } else if ibc_storage::is_client_update_timestamp_key(key).is_some() {
+        SchemaDescriptor::protobuf::<TmTime>(TIMESTAMP_TYPE_URL)
+    } else if ibc_storage::is_client_update_height_key(key).is_some() {
+        SchemaDescriptor::protobuf::<Height>(HEIGHT_TYPE_URL)
+    } else if *key == ibc_storage::params_key() {
+        SchemaDescriptor::borsh::<IbcParameters>()
+    } else if ibc_storage::is_blocked_trace_key(key).is_some()
+        || ibc_storage::is_ibc_denom_key(key).is_some()
+        || ibc_storage::is_ibc_denom_by_token_key(key).is_some()
+    {
+        SchemaDescriptor::borsh::<String>()
+    } else if ibc_storage::is_fee_enabled_key(key).is_some() {
+        SchemaDescriptor::borsh::<bool>()
+    } else if ibc_storage::is_packet_fee_key(key).is_some() {
+        SchemaDescriptor::borsh::<PacketFee>()
+    } else if ibc_storage::is_packet_escrow_key(key).is_some() {
+        SchemaDescriptor::borsh::<PacketEscrow>()
+    } else if ibc_storage::is_throughput_history_key(key).is_some() {
+        SchemaDescriptor::borsh::<ThroughputRecord>()
+    } else if ibc_storage::is_limit_override_key(key).is_some()
+        || ibc_storage::is_deposit_key(key).is_some()
+        || ibc_storage::is_withdraw_key(key).is_some()
+        || ibc_storage::is_escrow_amount_key(key).is_some()
+        || ibc_storage::is_deposit_key_for_channel(key).is_some()
+        || ibc_storage::is_withdraw_key_for_channel(key).is_some()
+        || is_mint_amount_key(key)
+    {
+        SchemaDescriptor::borsh::<Amount>()
+    } else {
+        return classify_ibc_path(key);
+    };
+    Some(descriptor)
+}
+
+/// Check if the key is the key of the amount of a token minted by IBC
+fn is_mint_amount_key(key: &Key) -> bool {
+    match &key.segments[..] {
+        [_, _, DbKeySeg::AddressSeg(token)] => {
+            *key == ibc_storage::mint_amount_key(token)
+        }
+        _ => false,
+    }
+}
+
+/// Classify the keys of the IBC paths, stored as in ibc-go
+fn classify_ibc_path(key: &Key) -> Option<SchemaDescriptor> {
+    let path = key.sub_key().ok()?.to_string();
+    let descriptor = match Path::from_str(&path).ok()? {
+        Path::ClientState(_) => {
+            SchemaDescriptor::protobuf::<AnyClientState>(ANY_TYPE_URL)
+        }
+        Path::ClientConsensusState(_) => {
+            SchemaDescriptor::protobuf::<AnyConsensusState>(ANY_TYPE_URL)
+        }
+        Path::Connection(_) => {
+            SchemaDescriptor::protobuf::<ConnectionEnd>(CONNECTION_TYPE_URL)
+        }
+        Path::ChannelEnd(_) => {
+            SchemaDescriptor::protobuf::<ChannelEnd>(CHANNEL_TYPE_URL)
+        }
+        Path::SeqSend(_) | Path::SeqRecv(_) | Path::SeqAck(_) => {
+            SchemaDescriptor::big_endian_u64::<Sequence>()
+        }
+        Path::Commitment(_) => SchemaDescriptor::raw::<PacketCommitment>(),
+        Path::Receipt(_) => SchemaDescriptor::raw::<Receipt>(),
+        Path::Ack(_) => SchemaDescriptor::raw::<AcknowledgementCommitment>(),
+        _ => return None,
+    };
+    Some(descriptor)
+}
+
+fn classify_token_key(key: &Key) -> Option<SchemaDescriptor> {
+    if is_any_token_balance_key(key).is_some()
+        || is_any_minted_balance_key(key).is_some()
+    {
+        Some(SchemaDescriptor::borsh::<Amount>())
+    } else if is_any_minter_key(key).is_some() {
+        Some(SchemaDescriptor::borsh::<Address>())
+    } else {
+        is_any_token_parameter_key(key)
+            .filter(|token| *key == denom_key(token))
+            .map(|_| SchemaDescriptor::borsh::<Denomination>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use namada_core::address::testing::{
+        established_address_1, established_address_2, nam,
+    };
+    use namada_core::ibc::core::host::types::identifiers::{
+        ChannelId, ClientId, ConnectionId, PortId,
+    };
+    use namada_token::storage_key::{balance_key, minted_balance_key};
+
+    use super::*;
+
+    fn borsh<T: BorshDeserialize>() -> Option<SchemaDescriptor> {
+        Some(SchemaDescriptor::borsh::<T>())
+    }
+
+    #[test]
+    fn test_classify_governance_keys() {
+        let author = established_address_1();
+        let validator = established_address_2();
+        let cases = [
+            (gov_storage::get_counter_key(), borsh::<u64>()),
+            (
+                gov_storage::get_content_key(0),
+                borsh::<BTreeMap<String, String>>(),
+            ),
+            (gov_storage::get_author_key(0), borsh::<Address>()),
+            (
+                gov_storage::get_proposal_type_key(0),
+                borsh::<ProposalType>(),
+            ),
+            (gov_storage::get_voting_start_epoch_key(0), borsh::<Epoch>()),
+            (gov_storage::get_voting_end_epoch_key(0), borsh::<Epoch>()),
+            (gov_storage::get_grace_epoch_key(0), borsh::<Epoch>()),
+            (gov_storage::get_funds_key(0), borsh::<Amount>()),
+            (
+                gov_storage::get_proposal_code_key(0),
+                Some(SchemaDescriptor::raw::<Vec<u8>>()),
+            ),
+            (
+                gov_storage::get_committing_proposals_key(0, 3),
+                borsh::<()>(),
+            ),
+            (gov_storage::get_withdrawn_key(0), borsh::<()>()),
+            (gov_storage::get_proposal_execution_key(0), borsh::<()>()),
+            (
+                gov_storage::get_vote_proposal_key(
+                    0,
+                    author.clone(),
+                    validator.clone(),
+                ),
+                borsh::<ProposalVote>(),
+            ),
+            (
+                gov_storage::get_vote_memo_key(0, author.clone(), validator),
+                borsh::<String>(),
+            ),
+            (
+                gov_storage::get_proposal_params_snapshot_key(0),
+                borsh::<ProposalParamsSnapshot>(),
+            ),
+            (
+                gov_storage::get_proposal_result_key(0),
+                borsh::<ProposalResult>(),
+            ),
+            (
+                gov_storage::get_proposal_result_record_key(0),
+                borsh::<ProposalResultRecord>(),
+            ),
+            (
+                gov_storage::get_author_proposal_key(&author, 0),
+                borsh::<Epoch>(),
+            ),
+            (
+                gov_storage::get_voting_power_snapshot_key(Epoch(1)),
+                borsh::<VotingPowerSnapshot>(),
+            ),
+            (gov_storage::get_min_proposal_fund_key(), borsh::<Amount>()),
+            (gov_storage::get_max_proposal_period_key(), borsh::<u64>()),
+            (
+                gov_storage::get_content_required_fields_key(),
+                borsh::<Vec<String>>(),
+            ),
+            (gov_storage::get_threshold_default_key(), borsh::<Dec>()),
+        ];
+        for (key, expected) in cases {
+            assert_eq!(classify(&key), expected, "Key {key}");
+        }
+    }
+
+    #[test]
+    fn test_classify_ibc_keys() {
+        let client_id = ClientId::from_str("07-tendermint-0").unwrap();
+        let conn_id = ConnectionId::new(0);
+        let port_id = PortId::transfer();
+        let channel_id = ChannelId::new(0);
+        let sequence = Sequence::from(1);
+        let height = Height::new(0, 1).unwrap();
+        let any = |type_name: &str| {
+            Some(SchemaDescriptor {
+                codec: Codec::Protobuf {
+                    type_url: ANY_TYPE_URL.to_string(),
+                },
+                type_name: type_name.to_string(),
+            })
+        };
+        let cases = [
+            (ibc_storage::client_counter_key(), borsh::<u64>()),
+            (ibc_storage::channel_counter_key(), borsh::<u64>()),
+            (
+                ibc_storage::client_state_key(&client_id),
+                any(type_name::<AnyClientState>()),
+            ),
+            (
+                ibc_storage::consensus_state_key(&client_id, height),
+                any(type_name::<AnyConsensusState>()),
+            ),
+            (
+                ibc_storage::connection_key(&conn_id),
+                Some(SchemaDescriptor::protobuf::<ConnectionEnd>(
+                    CONNECTION_TYPE_URL,
+                )),
+            ),
+            (
+                ibc_storage::channel_key(&port_id, &channel_id),
+                Some(SchemaDescriptor::protobuf::<ChannelEnd>(
+                    CHANNEL_TYPE_URL,
+                )),
+            ),
+            (
+                ibc_storage::client_update_timestamp_key(&client_id),
+                Some(SchemaDescriptor::protobuf::<TmTime>(TIMESTAMP_TYPE_URL)),
+            ),
+            (
+                ibc_storage::client_update_height_key(&client_id),
+                Some(SchemaDescriptor::protobuf::<Height>(HEIGHT_TYPE_URL)),
+            ),
+            (
+                ibc_storage::commitment_key(&port_id, &channel_id, sequence),
+                Some(SchemaDescriptor::raw::<PacketCommitment>()),
+            ),
+            (
+                ibc_storage::receipt_key(&port_id, &channel_id, sequence),
+                Some(SchemaDescriptor::raw::<Receipt>()),
+            ),
+            (
+                ibc_storage::ack_key(&port_id, &channel_id, sequence),
+                Some(SchemaDescriptor::raw::<AcknowledgementCommitment>()),
+            ),
+            (ibc_storage::params_key(), borsh::<IbcParameters>()),
+            (ibc_storage::mint_limit_key(&nam()), borsh::<Amount>()),
+            (ibc_storage::mint_amount_key(&nam()), borsh::<Amount>()),
+            (ibc_storage::deposit_key(&nam()), borsh::<Amount>()),
+            (
+                ibc_storage::throughput_history_key(&nam(), Epoch(1)),
+                borsh::<ThroughputRecord>(),
+            ),
+            (
+                ibc_storage::ibc_denom_key(nam().to_string(), "hash"),
+                borsh::<String>(),
+            ),
+            (ibc_storage::blocked_trace_key("hash"), borsh::<String>()),
+            (
+                ibc_storage::fee_enabled_key(&port_id, &channel_id),
+                borsh::<bool>(),
+            ),
+            (
+                ibc_storage::packet_fee_key(&port_id, &channel_id, sequence),
+                borsh::<PacketFee>(),
+            ),
+            (
+                ibc_storage::packet_escrow_key(&port_id, &channel_id, sequence),
+                borsh::<PacketEscrow>(),
+            ),
+        ];
+        for (key, expected) in cases {
+            assert_eq!(classify(&key), expected, "Key {key}");
+        }
+
+        // the sequences are big-endian, unlike the borsh counters
+        for key in [
+            ibc_storage::next_sequence_send_key(&port_id, &channel_id),
+            ibc_storage::next_sequence_recv_key(&port_id, &channel_id),
+            ibc_storage::next_sequence_ack_key(&port_id, &channel_id),
+        ] {
+            let descriptor = classify(&key).expect("The key should be known");
+            assert_eq!(descriptor.codec, Codec::BigEndianU64, "Key {key}");
+            assert_eq!(descriptor.type_name, type_name::<Sequence>());
+        }
+    }
+
+    #[test]
+    fn test_classify_token_keys() {
+        let owner = established_address_1();
+        assert_eq!(classify(&balance_key(&nam(), &owner)), borsh::<Amount>());
+        assert_eq!(classify(&minted_balance_key(&nam())), borsh::<Amount>());
+        assert_eq!(classify(&denom_key(&nam())), borsh::<Denomination>());
+
+        // unknown keys
+        let key = Key::parse("unknown/key").unwrap();
+        assert_eq!(classify(&key), None);
+    }
+}
@@ -24,6 +24,7 @@ pub mod error;
 pub mod events;
 pub(crate) mod internal_macros;
 pub mod io;
+pub mod key_codec;
 pub mod queries;
 pub mod wallet;
 
@@ -299,7 +300,9 @@ pub trait Namada: Sized + MaybeSync + MaybeSend {
     ) -> args::VoteProposal {
         args::VoteProposal {
             vote,
+            memo: None,
             voter,
+            delegations: vec![],
             proposal_id: None,
             is_offline: false,
             proposal_data: None,
@@ -348,6 +351,7 @@ pub trait Namada: Sized + MaybeSync + MaybeSend {
             discord_handle: None,
             avatar: None,
             commission_rate: None,
+            max_total_delegated: None,
             tx_code_path: PathBuf::from(TX_CHANGE_METADATA_WASM),
             tx: self.tx_builder(),
         }
@@ -17,17 +17,22 @@ use namada_core::storage::{
 };
 use namada_core::token::{Denomination, MaspDigitPos};
 use namada_core::uint::Uint;
-use namada_state::{DBIter, LastBlock, StateRead, StorageHasher, DB};
+use namada_state::{DBIter, DBStats, LastBlock, StateRead, StorageHasher, DB};
 use namada_storage::{ResultExt, StorageRead};
+use namada_token::conversion::{
+    compute_conversion_updates, ConversionUpdateReport,
+};
 #[cfg(any(test, feature = "async-client"))]
-use namada_tx::data::TxResult;
+use namada_tx::data::{TxResult, VpVerdict};
 
 use self::eth_bridge::{EthBridge, ETH_BRIDGE};
 use crate::events::log::dumb_queries;
+use crate::events::log::ibc_packets::IbcPacketFilter;
 use crate::events::{Event, EventType};
 use crate::ibc::core::host::types::identifiers::{
     ChannelId, ClientId, PortId, Sequence,
 };
+use crate::key_codec::{self, SchemaDescriptor};
 use crate::masp::MaspTokenRewardData;
 use crate::queries::types::{RequestCtx, RequestQuery};
 use crate::queries::{require_latest_height, EncodedResponseQuery};
@@ -68,6 +73,9 @@ router! {SHELL,
     // Query the last committed block
     ( "last_block" ) -> Option<LastBlock> = last_block,
 
+    // Statistics of the DB column families
+    ( "db_stats" ) -> DBStats = db_stats,
+
     // First block height of the current epoch
     ( "first_block_height_of_current_epoch" ) -> BlockHeight = first_block_height_of_current_epoch,
 
@@ -78,6 +86,15 @@ router! {SHELL,
     // Dry run a transaction
     ( "dry_run_tx" ) -> TxResult = (with_options dry_run_tx),
 
+    // Dry run a transaction against the native VPs of the internal addresses
+    // that it touches
+    ( "dry_run_vps" ) -> BTreeMap<Address, VpVerdict> = (with_options dry_run_vps),
+
+    // Raw storage access - ICS23 commitment proof of the existence or the
+    // non-existence of the key
+    ( "proof" / [storage_key: storage::Key] )
+        -> Vec<u8> = (with_options storage_proof),
+
     // Raw storage access - prefix iterator
     ( "prefix" / [storage_key: storage::Key] )
         -> Vec<PrefixValue> = (with_options storage_prefix),
@@ -86,6 +103,10 @@ router! {SHELL,
     ( "has_key" / [storage_key: storage::Key] )
         -> bool = storage_has_key,
 
+    // The encoding of the value under the given storage key
+    ( "storage_schema" / [storage_key: storage::Key] )
+        -> Option<SchemaDescriptor> = storage_schema,
+
     // Conversion state access - read conversion
     ( "conv" / [asset_type: AssetType] ) -> Option<Conversion> = read_conversion,
 
@@ -95,6 +116,10 @@ router! {SHELL,
     // Conversion state access - read conversion
     ( "masp_reward_tokens" ) -> Vec<MaspTokenRewardData> = masp_reward_tokens,
 
+    // Conversion state access - dry-run the conversion updates of the next
+    // epoch
+    ( "conversion_updates" ) -> Option<ConversionUpdateReport> = conversion_updates,
+
     // Block results access - read bit-vec
     ( "results" ) -> Vec<BlockResults> = read_results,
 
@@ -115,6 +140,10 @@ router! {SHELL,
 
     // IBC packet event
     ( "ibc_packet" / [event_type: EventType] / [source_port: PortId] / [source_channel: ChannelId] / [destination_port: PortId] / [destination_channel: ChannelId] / [sequence: Sequence]) -> Option<Event> = ibc_packet,
+
+    // IBC packet events matching the filter in the range of block heights,
+    // paginated
+    ( "events_by_ibc_packet" / [filter: IbcPacketFilter] / [from_height: BlockHeight] / [to_height: BlockHeight] / [page: u32] / [per_page: u8] ) -> Vec<Event> = events_by_ibc_packet,
 }
 
 // Handlers:
@@ -130,6 +159,21 @@ where
     unimplemented!("Dry running tx requires \"wasm-runtime\" feature.")
 }
 
+/// The VPs are dry run by the node with the "wasm-runtime" feature, which
+/// handles the query before it reaches the router
+fn dry_run_vps<D, H, V, T>(
+    _ctx: RequestCtx<'_, D, H, V, T>,
+    _request: &RequestQuery,
+) -> namada_storage::Result<EncodedResponseQuery>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    Err(namada_storage::Error::SimpleMessage(
+        "Dry running VPs requires the \"wasm-runtime\" feature",
+    ))
+}
+
 /// Query to read block results from storage
 pub fn read_results<D, H, V, T>(
     ctx: RequestCtx<'_, D, H, V, T>,
@@ -221,6 +265,21 @@ where
 }
 
 /// Query to read the tokens that earn masp rewards.
+fn conversion_updates<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+) -> namada_storage::Result<Option<ConversionUpdateReport>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    let next_epoch = ctx.state.in_mem().last_epoch.next();
+    compute_conversion_updates(
+        ctx.state,
+        &ctx.state.in_mem().conversion_state,
+        next_epoch,
+    )
+}
+
 fn masp_reward_tokens<D, H, V, T>(
     ctx: RequestCtx<'_, D, H, V, T>,
 ) -> namada_storage::Result<Vec<MaspTokenRewardData>>
@@ -228,7 +287,7 @@ where
     D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
     H: 'static + StorageHasher + Sync,
 {
-    let tokens = ctx.state.in_mem().conversion_state.tokens.clone();
+    let tokens = namada_token::read_reward_tokens(ctx.state)?;
     let mut data = Vec::<MaspTokenRewardData>::new();
     for (name, token) in tokens {
         let max_reward_rate = ctx
@@ -342,6 +401,16 @@ where
     Ok(ctx.state.in_mem().last_block.clone())
 }
 
+fn db_stats<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+) -> namada_storage::Result<DBStats>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    ctx.state.db().db_stats().into_storage_result()
+}
+
 fn first_block_height_of_current_epoch<D, H, V, T>(
     ctx: RequestCtx<'_, D, H, V, T>,
 ) -> namada_storage::Result<BlockHeight>
@@ -440,6 +509,66 @@ where
     }
 }
 
+/// Returns the encoded `CommitmentProofBytes` of the existence of the key if
+/// it has a value at the queried height, or of its non-existence otherwise
+fn storage_proof<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    request: &RequestQuery,
+    storage_key: storage::Key,
+) -> namada_storage::Result<EncodedResponseQuery>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    let last_committed_height = ctx.state.in_mem().get_last_block_height();
+    let queried_height = {
+        let height: BlockHeight = request.height.into();
+        let is_last_height_query = height.0 == 0;
+
+        if hints::likely(is_last_height_query) {
+            last_committed_height
+        } else {
+            height
+        }
+    };
+
+    if let Some(past_height_limit) = ctx.storage_read_past_height_limit {
+        if queried_height + past_height_limit < last_committed_height {
+            return Err(namada_storage::Error::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Cannot query more than {past_height_limit} blocks in the \
+                     past (configured via \
+                     `shell.storage_read_past_height_limit`)."
+                ),
+            )));
+        }
+    }
+
+    let (value, _gas) = ctx
+        .state
+        .db_read_with_height(&storage_key, queried_height)
+        .into_storage_result()?;
+    let (proof, info) = if value.is_some() {
+        let proof = ctx
+            .state
+            .get_existence_commitment_proof(&storage_key, queried_height)
+            .into_storage_result()?;
+        (proof, Default::default())
+    } else {
+        let proof = ctx
+            .state
+            .get_non_existence_commitment_proof(&storage_key, queried_height)
+            .into_storage_result()?;
+        (proof, format!("No value found for key: {}", storage_key))
+    };
+    Ok(EncodedResponseQuery {
+        data: proof.into(),
+        proof: None,
+        info,
+    })
+}
+
 fn storage_prefix<D, H, V, T>(
     ctx: RequestCtx<'_, D, H, V, T>,
     request: &RequestQuery,
@@ -507,6 +636,17 @@ where
     Ok(data)
 }
 
+fn storage_schema<D, H, V, T>(
+    _ctx: RequestCtx<'_, D, H, V, T>,
+    storage_key: storage::Key,
+) -> namada_storage::Result<Option<SchemaDescriptor>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    Ok(key_codec::classify(&storage_key))
+}
+
 fn accepted<D, H, V, T>(
     ctx: RequestCtx<'_, D, H, V, T>,
     tx_hash: Hash,
@@ -591,6 +731,26 @@ where
         .cloned())
 }
 
+fn events_by_ibc_packet<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    filter: IbcPacketFilter,
+    from_height: BlockHeight,
+    to_height: BlockHeight,
+    page: u32,
+    per_page: u8,
+) -> namada_storage::Result<Vec<Event>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    Ok(ctx
+        .event_log
+        .events_by_ibc_packet(&filter, from_height, to_height, page, per_page)
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
 fn account<D, H, V, T>(
     ctx: RequestCtx<'_, D, H, V, T>,
     owner: Address,
@@ -631,8 +791,10 @@ where
 #[cfg(test)]
 mod test {
     use namada_core::address;
+    use namada_core::storage::BlockHeight;
     use namada_token::storage_key::balance_key;
 
+    use crate::events::log::ibc_packets::IbcPacketFilter;
     use crate::queries::RPC;
 
     #[test]
@@ -649,10 +811,35 @@ mod test {
         let path = RPC.shell().dry_run_tx_path();
         assert_eq!("/shell/dry_run_tx", path);
 
+        let path = RPC.shell().dry_run_vps_path();
+        assert_eq!("/shell/dry_run_vps", path);
+
+        let path = RPC.shell().storage_proof_path(&key);
+        assert_eq!(format!("/shell/proof/{}", key), path);
+
         let path = RPC.shell().storage_prefix_path(&key);
         assert_eq!(format!("/shell/prefix/{}", key), path);
 
         let path = RPC.shell().storage_has_key_path(&key);
         assert_eq!(format!("/shell/has_key/{}", key), path);
+
+        let path = RPC.shell().storage_schema_path(&key);
+        assert_eq!(format!("/shell/storage_schema/{}", key), path);
+
+        let filter: IbcPacketFilter =
+            "ibc.packet.src_channel='channel-0' AND ibc.packet.sequence='1'"
+                .parse()
+                .unwrap();
+        let path = RPC.shell().events_by_ibc_packet_path(
+            &filter,
+            &BlockHeight(1),
+            &BlockHeight(10),
+            &0,
+            &20,
+        );
+        assert_eq!(
+            format!("/shell/events_by_ibc_packet/{}/1/10/0/20", filter),
+            path
+        );
     }
 }
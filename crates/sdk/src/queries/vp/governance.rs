@@ -1,9 +1,26 @@
+//! Queries router and handlers for governance validity predicate
+
 // cd namada && cargo expand ledger::queries::vp::governance
 
-use namada_governance::parameters::GovernanceParameters;
-use namada_governance::storage::proposal::StorageProposal;
-use namada_governance::utils::{ProposalResult, Vote};
-use namada_state::{DBIter, StorageHasher, DB};
+use std::collections::BTreeMap;
+
+use namada_core::address::Address;
+use namada_core::storage::Epoch;
+use namada_governance::parameters::{
+    GovernanceParameters, ProposalParamsSnapshot,
+};
+use namada_governance::storage::keys as governance_keys;
+use namada_governance::storage::proposal::{ProposalType, StorageProposal};
+use namada_governance::utils::{
+    tally_threshold, DelegatorTally, DetailedTallyResult, ProposalFull,
+    ProposalResult, ProposalResultRecord, TallyType, ValidatorTally, Vote,
+};
+use namada_proof_of_stake::bond_amount;
+use namada_proof_of_stake::storage::{
+    read_pos_params, read_total_stake, read_validator_stake,
+};
+use namada_proof_of_stake::types::BondId;
+use namada_state::{DBIter, StorageHasher, StorageRead, DB};
 
 use crate::queries::types::RequestCtx;
 
@@ -11,8 +28,12 @@ use crate::queries::types::RequestCtx;
 router! {GOV,
     ( "proposal" / [id: u64 ] ) -> Option<StorageProposal> = proposal_id,
     ( "proposal" / [id: u64 ] / "votes" ) -> Vec<Vote> = proposal_id_votes,
+    ( "proposal" / [id: u64 ] / "full" ) -> Option<ProposalFull> = proposal_full,
+    ( "proposal" / [id: u64 ] / "tally" / [epoch: opt Epoch] ) -> Option<DetailedTallyResult> = proposal_tally,
+    ( "proposal" / [id: u64 ] / "parameters" ) -> ProposalParamsSnapshot = proposal_parameters,
     ( "parameters" ) -> GovernanceParameters = parameters,
     ( "stored_proposal_result" / [id: u64] ) -> Option<ProposalResult> = proposal_result,
+    ( "proposal_result_record" / [id: u64] ) -> Option<ProposalResultRecord> = proposal_result_record,
 }
 
 /// Query the provided proposal id
@@ -39,6 +60,52 @@ where
     namada_governance::storage::get_proposal_votes(ctx.state, id)
 }
 
+/// Query all the data of the given proposal id at once
+fn proposal_full<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    id: u64,
+) -> namada_storage::Result<Option<ProposalFull>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    namada_governance::utils::query_proposal(ctx.state, id)
+}
+
+/// Tally the votes of the given proposal id at the given epoch, which
+/// defaults to the voting end epoch of the proposal, as in the proposal
+/// execution. Returns `None` if the proposal doesn't exist.
+fn proposal_tally<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    id: u64,
+    epoch: Option<Epoch>,
+) -> namada_storage::Result<Option<DetailedTallyResult>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    let end_epoch_key = governance_keys::get_voting_end_epoch_key(id);
+    let end_epoch: Epoch = match ctx.state.read(&end_epoch_key)? {
+        Some(end_epoch) => end_epoch,
+        None => return Ok(None),
+    };
+    let epoch = epoch.unwrap_or(end_epoch);
+    compute_proposal_result_detailed(ctx.state, id, epoch).map(Some)
+}
+
+/// Get the governance parameters that the given proposal id is validated and
+/// tallied against
+fn proposal_parameters<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    id: u64,
+) -> namada_storage::Result<ProposalParamsSnapshot>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    namada_governance::storage::get_proposal_params_snapshot(ctx.state, id)
+}
+
 /// Get the governance parameters
 fn parameters<D, H, V, T>(
     ctx: RequestCtx<'_, D, H, V, T>,
@@ -61,3 +128,442 @@ where
 {
     namada_governance::storage::get_proposal_result(ctx.state, id)
 }
+
+/// Get the record of the governance proposal result, written at the
+/// activation epoch of the proposal
+fn proposal_result_record<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    id: u64,
+) -> namada_storage::Result<Option<ProposalResultRecord>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    namada_governance::storage::get_proposal_result_record(ctx.state, id)
+}
+
+/// Tally the votes of a proposal with the stakes at the given epoch. The
+/// breakdown contains the vote, the memo and the stake of each validator and
+/// the vote, the memo and the delegations of each delegator, which override
+/// the votes of their validators for the delegated amounts. The threshold is
+/// selected by the type of the proposal.
+pub fn compute_proposal_result_detailed<S>(
+    storage: &S,
+    proposal_id: u64,
+    epoch: Epoch,
+) -> namada_storage::Result<DetailedTallyResult>
+where
+    S: StorageRead,
+{
+    let params = read_pos_params(storage)?;
+    let total_voting_power = read_total_stake(storage, &params, epoch)?;
+    let proposal_type: ProposalType = storage
+        .read(&governance_keys::get_proposal_type_key(proposal_id))?
+        .ok_or_else(|| {
+            namada_storage::Error::new_const("Missing the proposal type")
+        })?;
+    let author: Address = storage
+        .read(&governance_keys::get_author_key(proposal_id))?
+        .ok_or_else(|| {
+            namada_storage::Error::new_const("Missing the proposal author")
+        })?;
+    let is_steward =
+        namada_governance::pgf::storage::is_steward(storage, &author)?;
+    let tally_type = TallyType::from(proposal_type.clone(), is_steward);
+    let threshold = tally_threshold(
+        &proposal_type,
+        &tally_type,
+        &namada_governance::storage::get_proposal_params_snapshot(
+            storage,
+            proposal_id,
+        )?,
+    );
+    let votes =
+        namada_governance::storage::get_proposal_votes(storage, proposal_id)?;
+
+    let mut validators: BTreeMap<Address, ValidatorTally> = BTreeMap::new();
+    let mut delegators: BTreeMap<Address, DelegatorTally> = BTreeMap::new();
+    for vote in votes {
+        let memo = namada_governance::storage::get_vote_memo(
+            storage,
+            proposal_id,
+            &vote.delegator,
+            &vote.validator,
+        )?;
+        if vote.is_validator() {
+            let stake =
+                read_validator_stake(storage, &params, &vote.validator, epoch)
+                    .unwrap_or_default();
+            validators.insert(
+                vote.validator,
+                ValidatorTally {
+                    vote: vote.data,
+                    stake,
+                    memo,
+                },
+            );
+        } else {
+            let bond_id = BondId {
+                source: vote.delegator.clone(),
+                validator: vote.validator.clone(),
+            };
+            // As in the proposal execution, a delegation whose amount can't
+            // be read isn't tallied
+            if let Ok(amount) = bond_amount(storage, &bond_id, epoch) {
+                delegators
+                    .entry(vote.delegator)
+                    .or_insert_with(|| DelegatorTally {
+                        vote: vote.data,
+                        delegations: BTreeMap::new(),
+                        memo,
+                    })
+                    .delegations
+                    .insert(vote.validator, amount);
+            }
+        }
+    }
+
+    Ok(DetailedTallyResult::new(
+        validators,
+        delegators,
+        total_voting_power,
+        threshold,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use namada_core::address::testing::{
+        established_address_1, established_address_2, established_address_3,
+    };
+    use namada_core::borsh::{BorshDeserialize, BorshSerializeExt};
+    use namada_core::dec::Dec;
+    use namada_core::hash::Hash;
+    use namada_core::key::testing::{common_sk_from_simple_seed, gen_keypair};
+    use namada_core::key::{common, secp256k1, RefTo};
+    use namada_core::token::Amount;
+    use namada_governance::storage::proposal::{
+        InitProposalData, VoteProposalData,
+    };
+    use namada_governance::storage::vote::ProposalVote;
+    use namada_governance::utils::{query_proposal, IncompleteProposalError};
+    use namada_proof_of_stake::types::GenesisValidator;
+    use namada_proof_of_stake::OwnedPosParams;
+    use namada_state::testing::TestState;
+    use namada_storage::StorageWrite;
+
+    use super::*;
+    use crate::queries::testing::TestClient;
+    use crate::queries::RPC;
+
+    fn genesis_validator(address: Address, tokens: Amount) -> GenesisValidator {
+        let eth_key = || {
+            common::SecretKey::Secp256k1(gen_keypair::<secp256k1::SigScheme>())
+                .ref_to()
+        };
+        GenesisValidator {
+            address,
+            tokens,
+            consensus_key: common_sk_from_simple_seed(0).ref_to(),
+            protocol_key: common_sk_from_simple_seed(1).ref_to(),
+            eth_cold_key: eth_key(),
+            eth_hot_key: eth_key(),
+            commission_rate: Dec::new(1, 1).unwrap(),
+            max_commission_rate_change: Dec::new(1, 1).unwrap(),
+            metadata: Default::default(),
+        }
+    }
+
+    /// Test the tally of a proposal with two validators and a delegator
+    /// overriding the vote of its validator, with the memo of a vote
+    #[tokio::test]
+    async fn test_proposal_tally() {
+        let mut client = TestClient::new(RPC);
+        assert_eq!(
+            RPC.vp().gov().proposal_tally_path(&0, &None),
+            "/vp/governance/proposal/0/tally"
+        );
+
+        let validator_1 = established_address_1();
+        let validator_2 = established_address_2();
+        let delegator = established_address_3();
+        namada_proof_of_stake::test_utils::test_init_genesis(
+            &mut client.state,
+            OwnedPosParams::default(),
+            vec![
+                genesis_validator(
+                    validator_1.clone(),
+                    Amount::native_whole(100),
+                ),
+                genesis_validator(
+                    validator_2.clone(),
+                    Amount::native_whole(200),
+                ),
+            ]
+            .into_iter(),
+            Epoch(0),
+        )
+        .unwrap();
+        let native_token = client.state.in_mem().native_token.clone();
+        let delegation = Amount::native_whole(50);
+        namada_token::credit_tokens(
+            &mut client.state,
+            &native_token,
+            &delegator,
+            delegation,
+        )
+        .unwrap();
+        namada_proof_of_stake::bond_tokens(
+            &mut client.state,
+            Some(&delegator),
+            &validator_1,
+            delegation,
+            Epoch(0),
+            None,
+        )
+        .unwrap();
+
+        for (voter, validator, vote) in [
+            (&validator_1, &validator_1, ProposalVote::Yay),
+            (&validator_2, &validator_2, ProposalVote::Nay),
+            (&delegator, &validator_1, ProposalVote::Nay),
+        ] {
+            let key = governance_keys::get_vote_proposal_key(
+                0,
+                voter.clone(),
+                validator.clone(),
+            );
+            client.state.write(&key, vote).unwrap();
+        }
+        let memo = "Nay because of the migration plan".to_string();
+        client
+            .state
+            .write(
+                &governance_keys::get_vote_memo_key(
+                    0,
+                    validator_2.clone(),
+                    validator_2.clone(),
+                ),
+                &memo,
+            )
+            .unwrap();
+        client
+            .state
+            .write(&governance_keys::get_voting_end_epoch_key(0), Epoch(2))
+            .unwrap();
+        client
+            .state
+            .write(
+                &governance_keys::get_proposal_type_key(0),
+                ProposalType::Default(None),
+            )
+            .unwrap();
+        let gov_params = GovernanceParameters::default();
+        gov_params.init_storage(&mut client.state).unwrap();
+        client.state.commit_block().unwrap();
+
+        let result =
+            compute_proposal_result_detailed(&client.state, 0, Epoch(2))
+                .unwrap();
+        let expected = DetailedTallyResult {
+            total_voting_power: Amount::native_whole(350),
+            total_yay_power: Amount::native_whole(100),
+            total_nay_power: Amount::native_whole(250),
+            total_abstain_power: Amount::zero(),
+            threshold: gov_params.threshold_default,
+            validators: BTreeMap::from([
+                (
+                    validator_1.clone(),
+                    ValidatorTally {
+                        vote: ProposalVote::Yay,
+                        stake: Amount::native_whole(150),
+                        memo: None,
+                    },
+                ),
+                (
+                    validator_2,
+                    ValidatorTally {
+                        vote: ProposalVote::Nay,
+                        stake: Amount::native_whole(200),
+                        memo: Some(memo),
+                    },
+                ),
+            ]),
+            delegators: BTreeMap::from([(
+                delegator,
+                DelegatorTally {
+                    vote: ProposalVote::Nay,
+                    delegations: BTreeMap::from([(validator_1, delegation)]),
+                    memo: None,
+                },
+            )]),
+        };
+        assert_eq!(result, expected);
+
+        // the tally epoch defaults to the voting end epoch
+        let queried = RPC
+            .vp()
+            .gov()
+            .proposal_tally(&client, &0, &None)
+            .await
+            .unwrap();
+        assert_eq!(queried, Some(expected));
+        // a proposal that doesn't exist has no tally
+        let queried = RPC
+            .vp()
+            .gov()
+            .proposal_tally(&client, &1, &None)
+            .await
+            .unwrap();
+        assert_eq!(queried, None);
+    }
+
+    /// Initialize a proposal of the given author and the votes of a validator
+    /// and a delegator on it
+    fn init_proposal_with_votes(state: &mut TestState, author: &Address) {
+        GovernanceParameters::default().init_storage(state).unwrap();
+        let native_token = state.in_mem().native_token.clone();
+        namada_token::credit_tokens(
+            state,
+            &native_token,
+            author,
+            Amount::native_whole(500),
+        )
+        .unwrap();
+        let content = BTreeMap::from([(
+            "title".to_string(),
+            "Upgrade the protocol".to_string(),
+        )]);
+        namada_governance::init_proposal(
+            state,
+            InitProposalData {
+                id: 0,
+                content: Hash::default(),
+                author: author.clone(),
+                r#type: ProposalType::Default(None),
+                voting_start_epoch: Epoch(3),
+                voting_end_epoch: Epoch(9),
+                grace_epoch: Epoch(12),
+            },
+            content.serialize_to_vec(),
+            None,
+        )
+        .unwrap();
+
+        let validator = established_address_2();
+        let delegator = established_address_3();
+        for (voter, vote) in [
+            (&validator, ProposalVote::Yay),
+            (&delegator, ProposalVote::Nay),
+        ] {
+            namada_governance::vote_proposal(
+                state,
+                VoteProposalData {
+                    id: 0,
+                    vote,
+                    voter: voter.clone(),
+                    delegations: vec![validator.clone()],
+                    memo: None,
+                },
+            )
+            .unwrap();
+        }
+        state.commit_block().unwrap();
+    }
+
+    /// Test that the aggregated proposal is the same as the data read from
+    /// the individual keys, with the result record once it's written
+    #[tokio::test]
+    async fn test_query_proposal_full() {
+        let mut client = TestClient::new(RPC);
+        assert_eq!(
+            RPC.vp().gov().proposal_full_path(&0),
+            "/vp/governance/proposal/0/full"
+        );
+        let author = established_address_1();
+        init_proposal_with_votes(&mut client.state, &author);
+
+        let full = query_proposal(&client.state, 0).unwrap().unwrap();
+        let proposal =
+            namada_governance::storage::get_proposal_by_id(&client.state, 0)
+                .unwrap()
+                .unwrap();
+        assert_eq!(full.id, proposal.id);
+        assert_eq!(
+            BTreeMap::<String, String>::try_from_slice(&full.content).unwrap(),
+            proposal.content
+        );
+        assert_eq!(full.author, proposal.author);
+        assert_eq!(full.r#type, proposal.r#type);
+        assert_eq!(full.voting_start_epoch, proposal.voting_start_epoch);
+        assert_eq!(full.voting_end_epoch, proposal.voting_end_epoch);
+        assert_eq!(full.grace_epoch, proposal.grace_epoch);
+        let funds: Amount = client
+            .state
+            .read(&governance_keys::get_funds_key(0))
+            .unwrap()
+            .unwrap();
+        assert_eq!(full.funds, funds);
+        let votes =
+            namada_governance::storage::get_proposal_votes(&client.state, 0)
+                .unwrap();
+        assert_eq!(full.vote_count, votes.len() as u64);
+        assert_eq!(full.vote_count, 2);
+        assert_eq!(full.result, None);
+
+        // the result record is included once the proposal is activated
+        let record = ProposalResultRecord {
+            passed: true,
+            yay: Amount::native_whole(200),
+            nay: Amount::native_whole(100),
+            abstain: Amount::zero(),
+            total_voting_power: Amount::native_whole(400),
+            turnout: Dec::new(75, 2).unwrap(),
+        };
+        namada_governance::storage::write_proposal_result_record(
+            &mut client.state,
+            0,
+            record,
+        )
+        .unwrap();
+        client.state.commit_block().unwrap();
+        let queried = RPC
+            .vp()
+            .gov()
+            .proposal_full(&client, &0)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            queried,
+            ProposalFull {
+                result: Some(record),
+                ..full
+            }
+        );
+        // a proposal that doesn't exist isn't found
+        let queried = RPC.vp().gov().proposal_full(&client, &1).await.unwrap();
+        assert_eq!(queried, None);
+    }
+
+    /// Test that a proposal missing its funds key yields an error listing the
+    /// missing key
+    #[test]
+    fn test_query_proposal_full_incomplete() {
+        let mut client = TestClient::new(RPC);
+        let author = established_address_1();
+        init_proposal_with_votes(&mut client.state, &author);
+        let funds_key = governance_keys::get_funds_key(0);
+        client.state.delete(&funds_key).unwrap();
+
+        let err = query_proposal(&client.state, 0).unwrap_err();
+        let err = err.downcast::<IncompleteProposalError>().unwrap();
+        assert_eq!(
+            *err,
+            IncompleteProposalError {
+                id: 0,
+                missing_keys: vec![funds_key],
+            }
+        );
+    }
+}
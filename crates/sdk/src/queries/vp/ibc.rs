@@ -0,0 +1,311 @@
+//! IBC validity predicate queries
+
+use namada_core::address::Address;
+use namada_core::storage::Epoch;
+use namada_ibc::audit::{audit_ibc_escrow, EscrowAuditReport};
+use namada_ibc::parameters::{
+    read_limits_usage, read_throughput_history, LimitsUsage, ThroughputRecord,
+};
+use namada_ibc::tokens::{list_ibc_tokens, IbcTokenInfo};
+use namada_ibc::SUPPORTED_MESSAGE_TYPE_URLS;
+use namada_state::{DBIter, StorageHasher, DB};
+
+use crate::queries::RequestCtx;
+
+router! {IBC,
+    ( "limits" / [token: Address] ) -> LimitsUsage = limits,
+
+    ( "denom" / [token: Address] ) -> Option<String> = denom,
+
+    ( "throughput_history" / [token: Address] )
+        -> Vec<(Epoch, ThroughputRecord)> = throughput_history,
+
+    ( "escrow_audit" / [token: Address] ) -> EscrowAuditReport = escrow_audit,
+
+    ( "tokens" / [page: u32] / [per_page: u8] )
+        -> Vec<IbcTokenInfo> = tokens,
+
+    ( "supported_messages" ) -> Vec<String> = supported_messages,
+}
+
+/// Get the current usage of the IBC mint and throughput limits of the token
+/// with the configured limits
+fn limits<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    token: Address,
+) -> namada_storage::Result<LimitsUsage>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    read_limits_usage(ctx.state, &token)
+}
+
+/// Get the denom trace of the IBC token, if it has been received
+fn denom<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    token: Address,
+) -> namada_storage::Result<Option<String>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    namada_ibc::read_ibc_denom(ctx.state, &token)
+}
+
+/// Get the retained throughput records of the token in the ended epochs
+fn throughput_history<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    token: Address,
+) -> namada_storage::Result<Vec<(Epoch, ThroughputRecord)>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    read_throughput_history(ctx.state, &token)
+}
+
+/// Audit the balance of the token escrowed by IBC against the packet
+/// accounting, for debugging
+fn escrow_audit<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    token: Address,
+) -> namada_storage::Result<EscrowAuditReport>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    audit_ibc_escrow(ctx.state, &token)
+}
+
+/// Get a page of the IBC tokens received by the chain with their traces and
+/// amounts, ordered by the token addresses. The pages are counted from `0`.
+fn tokens<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    page: u32,
+    per_page: u8,
+) -> namada_storage::Result<Vec<IbcTokenInfo>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    list_ibc_tokens(ctx.state, page, per_page)
+}
+
+/// Get the type URLs of the IBC messages which can be handled, for the
+/// relayers to detect the supported features
+fn supported_messages<D, H, V, T>(
+    _ctx: RequestCtx<'_, D, H, V, T>,
+) -> namada_storage::Result<Vec<String>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    Ok(SUPPORTED_MESSAGE_TYPE_URLS
+        .iter()
+        .map(|type_url| type_url.to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use namada_core::address::testing::nam;
+    use namada_core::address::InternalAddress;
+    use namada_core::token::Amount;
+    use namada_ibc::storage::{
+        calc_hash, deposit_key, escrow_amount_key, ibc_denom_by_token_key,
+        ibc_denom_key, ibc_token, mint_amount_key, mint_limit_key,
+        throughput_history_key,
+    };
+    use namada_storage::StorageWrite;
+    use namada_token::storage_key::balance_key;
+
+    use super::*;
+    use crate::queries::testing::TestClient;
+    use crate::queries::RPC;
+
+    /// Test that the usage of the IBC limits can be queried
+    #[tokio::test]
+    async fn test_ibc_limits_query() {
+        let mut client = TestClient::new(RPC);
+        let token = nam();
+        assert_eq!(
+            RPC.vp().ibc().limits_path(&token),
+            format!("/vp/ibc/limits/{token}")
+        );
+
+        client
+            .state
+            .write(&mint_limit_key(&token), Amount::native_whole(1000))
+            .expect("Test failed");
+        client
+            .state
+            .write(&mint_amount_key(&token), Amount::native_whole(500))
+            .expect("Test failed");
+        client
+            .state
+            .write(&deposit_key(&token), Amount::native_whole(40))
+            .expect("Test failed");
+        client.state.commit_block().expect("Test failed");
+
+        let usage = RPC.vp().ibc().limits(&client, &token).await.unwrap();
+        assert_eq!(
+            usage,
+            LimitsUsage {
+                minted: Amount::native_whole(500),
+                mint_limit: Amount::native_whole(1000),
+                deposit: Amount::native_whole(40),
+                withdraw: Amount::zero(),
+                throughput_limit: Amount::max(),
+            }
+        );
+    }
+
+    /// Test that the denom of an IBC token can be queried
+    #[tokio::test]
+    async fn test_ibc_denom_query() {
+        let mut client = TestClient::new(RPC);
+        let denom = format!("transfer/channel-0/{}", nam());
+        let token = ibc_token(&denom);
+
+        let result = RPC.vp().ibc().denom(&client, &token).await.unwrap();
+        assert!(result.is_none());
+
+        client
+            .state
+            .write(&ibc_denom_by_token_key(&token), &denom)
+            .expect("Test failed");
+        client.state.commit_block().expect("Test failed");
+
+        let result = RPC.vp().ibc().denom(&client, &token).await.unwrap();
+        assert_eq!(result, Some(denom));
+    }
+
+    /// Test that the throughput history of a token can be queried
+    #[tokio::test]
+    async fn test_ibc_throughput_history_query() {
+        let mut client = TestClient::new(RPC);
+        let token = nam();
+
+        let history = RPC
+            .vp()
+            .ibc()
+            .throughput_history(&client, &token)
+            .await
+            .unwrap();
+        assert!(history.is_empty());
+
+        let record = ThroughputRecord {
+            deposit: Amount::native_whole(40),
+            withdraw: Amount::native_whole(10),
+            minted: Amount::native_whole(500),
+        };
+        for epoch in [Epoch(2), Epoch(1)] {
+            client
+                .state
+                .write(&throughput_history_key(&token, epoch), &record)
+                .expect("Test failed");
+        }
+        client.state.commit_block().expect("Test failed");
+
+        let history = RPC
+            .vp()
+            .ibc()
+            .throughput_history(&client, &token)
+            .await
+            .unwrap();
+        assert_eq!(
+            history,
+            vec![(Epoch(1), record.clone()), (Epoch(2), record)]
+        );
+    }
+
+    /// Test that the audit of the IBC escrow can be queried
+    #[tokio::test]
+    async fn test_ibc_escrow_audit_query() {
+        let mut client = TestClient::new(RPC);
+        let token = nam();
+        let escrow = Address::Internal(InternalAddress::Ibc);
+
+        client
+            .state
+            .write(&escrow_amount_key(&token), Amount::native_whole(100))
+            .expect("Test failed");
+        client
+            .state
+            .write(&balance_key(&token, &escrow), Amount::native_whole(100))
+            .expect("Test failed");
+        client.state.commit_block().expect("Test failed");
+
+        let report =
+            RPC.vp().ibc().escrow_audit(&client, &token).await.unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(report.balance, Amount::native_whole(100));
+
+        client
+            .state
+            .write(&balance_key(&token, &escrow), Amount::native_whole(90))
+            .expect("Test failed");
+        client.state.commit_block().expect("Test failed");
+
+        let report =
+            RPC.vp().ibc().escrow_audit(&client, &token).await.unwrap();
+        assert!(!report.is_consistent());
+        assert_eq!(report.escrowed, Amount::native_whole(100));
+        assert_eq!(report.balance, Amount::native_whole(90));
+    }
+
+    /// Test that the IBC tokens can be queried by pages
+    #[tokio::test]
+    async fn test_ibc_tokens_query() {
+        let mut client = TestClient::new(RPC);
+        assert_eq!(RPC.vp().ibc().tokens_path(&1, &10), "/vp/ibc/tokens/1/10");
+
+        let mut expected = vec![];
+        for channel in 0..3 {
+            let trace = format!("transfer/channel-{channel}/{}", nam());
+            let token = ibc_token(&trace);
+            client
+                .state
+                .write(
+                    &ibc_denom_key(nam().to_string(), calc_hash(&trace)),
+                    &trace,
+                )
+                .expect("Test failed");
+            client
+                .state
+                .write(&mint_amount_key(&token), Amount::native_whole(channel))
+                .expect("Test failed");
+            expected.push(IbcTokenInfo {
+                address: token,
+                trace,
+                minted: Amount::native_whole(channel),
+                deposit: Amount::zero(),
+                withdraw: Amount::zero(),
+            });
+        }
+        client.state.commit_block().expect("Test failed");
+        expected.sort_by(|a, b| a.address.cmp(&b.address));
+
+        let first = RPC.vp().ibc().tokens(&client, &0, &2).await.unwrap();
+        assert_eq!(first, expected[..2]);
+        let second = RPC.vp().ibc().tokens(&client, &1, &2).await.unwrap();
+        assert_eq!(second, expected[2..]);
+        let third = RPC.vp().ibc().tokens(&client, &2, &2).await.unwrap();
+        assert!(third.is_empty());
+    }
+
+    /// Test that the supported IBC messages can be queried
+    #[tokio::test]
+    async fn test_ibc_supported_messages_query() {
+        let client = TestClient::new(RPC);
+        assert_eq!(
+            RPC.vp().ibc().supported_messages_path(),
+            "/vp/ibc/supported_messages"
+        );
+
+        let messages =
+            RPC.vp().ibc().supported_messages(&client).await.unwrap();
+        assert_eq!(messages, SUPPORTED_MESSAGE_TYPE_URLS);
+    }
+}
@@ -26,8 +26,9 @@ use namada_proof_of_stake::storage::{
     validator_slashes_handle, validator_state_handle,
 };
 use namada_proof_of_stake::types::{
-    BondId, BondsAndUnbondsDetail, BondsAndUnbondsDetails, CommissionPair,
-    Slash, ValidatorMetaData, ValidatorState, WeightedValidator,
+    BondId, BondProjection, BondsAndUnbondsDetail, BondsAndUnbondsDetails,
+    CommissionPair, PendingConsensusKeyChange, Slash, SlashSummary,
+    ValidatorMetaData, ValidatorState, WeightedValidator,
 };
 use namada_proof_of_stake::{bond_amount, query_reward_tokens};
 use namada_state::{DBIter, StorageHasher, DB};
@@ -117,6 +118,9 @@ router! {POS,
     ( "bonds_and_unbonds" / [source: opt Address] / "to" / [validator: opt Address] )
         -> BondsAndUnbondsDetails = bonds_and_unbonds,
 
+    ( "bonds_and_unbonds_projection" / [source: Address] / [validator: Address] )
+        -> BondProjection = bonds_and_unbonds_projection,
+
     ( "enqueued_slashes" )
         -> HashMap<Address, BTreeMap<Epoch, Vec<Slash>>> = enqueued_slashes,
 
@@ -129,9 +133,18 @@ router! {POS,
 
     ( "consensus_keys" ) -> BTreeSet<common::PublicKey> = consensus_key_set,
 
+    ( "pending_consensus_key_changes" )
+        -> Vec<PendingConsensusKeyChange> = pending_consensus_key_changes,
+
     ( "has_bonds" / [source: Address] )
         -> bool = has_bonds,
 
+    ( "slashes_in_epoch" / [epoch: Epoch] )
+        -> Vec<SlashSummary> = slashes_in_epoch,
+
+    ( "slashes_affecting" / [delegator: Address] )
+        -> Vec<SlashSummary> = slashes_affecting,
+
 }
 
 /// Enriched bonds data with extra information calculated from the data queried
@@ -565,6 +578,22 @@ where
     )
 }
 
+/// Project the bond amounts of the given source and validator from the current
+/// epoch up to the unbonding length offset
+fn bonds_and_unbonds_projection<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    source: Address,
+    validator: Address,
+) -> namada_storage::Result<BondProjection>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    namada_proof_of_stake::queries::bonds_and_unbonds_projection(
+        ctx.state, &source, &validator,
+    )
+}
+
 /// Find all the validator addresses to whom the given `owner` address has
 /// some delegation in any epoch
 fn delegation_validators<D, H, V, T>(
@@ -654,6 +683,22 @@ where
     namada_proof_of_stake::storage::get_consensus_key_set(ctx.state)
 }
 
+/// Find the scheduled validator consensus key changes that haven't taken
+/// effect yet
+fn pending_consensus_key_changes<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+) -> namada_storage::Result<Vec<PendingConsensusKeyChange>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    let current_epoch = ctx.state.in_mem().last_epoch;
+    namada_proof_of_stake::queries::pending_consensus_key_changes(
+        ctx.state,
+        current_epoch,
+    )
+}
+
 /// Find if the given source address has any bonds.
 fn has_bonds<D, H, V, T>(
     ctx: RequestCtx<'_, D, H, V, T>,
@@ -666,6 +711,30 @@ where
     namada_proof_of_stake::queries::has_bonds(ctx.state, &source)
 }
 
+/// Summaries of the slashes for the infractions committed in the given epoch
+fn slashes_in_epoch<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    epoch: Epoch,
+) -> namada_storage::Result<Vec<SlashSummary>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    namada_proof_of_stake::queries::slashes_in_epoch(ctx.state, epoch)
+}
+
+/// Summaries of the slashes that reduced the bonds of the given delegator
+fn slashes_affecting<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    delegator: Address,
+) -> namada_storage::Result<Vec<SlashSummary>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    namada_proof_of_stake::queries::slashes_affecting(ctx.state, &delegator)
+}
+
 /// Client-only methods for the router type are composed from router functions.
 #[cfg(any(test, feature = "async-client"))]
 pub mod client_only_methods {
@@ -12,6 +12,7 @@ use masp_primitives::sapling::Node;
 use namada_account::Account;
 use namada_core::address::{Address, InternalAddress};
 use namada_core::hash::Hash;
+use namada_core::ibc::core::commitment_types::commitment::CommitmentProofBytes;
 use namada_core::key::common;
 use namada_core::storage::{
     BlockHeight, BlockResults, Epoch, Key, PrefixValue,
@@ -20,23 +21,29 @@ use namada_core::token::{
     Amount, DenominatedAmount, Denomination, MaspDigitPos,
 };
 use namada_core::{storage, token};
-use namada_governance::parameters::GovernanceParameters;
+use namada_governance::parameters::{
+    GovernanceParameters, ProposalParamsSnapshot,
+};
 use namada_governance::pgf::parameters::PgfParameters;
 use namada_governance::pgf::storage::steward::StewardDetail;
 use namada_governance::storage::proposal::StorageProposal;
 use namada_governance::utils::{
-    compute_proposal_result, ProposalResult, ProposalVotes, Vote,
+    compute_proposal_result, tally_threshold, ProposalFull, ProposalResult,
+    ProposalResultRecord, ProposalVotes, Vote,
 };
 use namada_ibc::storage::{
     ibc_denom_key, ibc_denom_key_prefix, is_ibc_denom_key,
 };
 use namada_parameters::{storage as params_storage, EpochDuration};
 use namada_proof_of_stake::parameters::PosParams;
+use namada_proof_of_stake::storage_key as pos_storage_key;
 use namada_proof_of_stake::types::{
-    BondsAndUnbondsDetails, CommissionPair, ValidatorMetaData, ValidatorState,
+    BondProjection, BondsAndUnbondsDetails, CommissionPair,
+    PendingConsensusKeyChange, ValidatorMetaData, ValidatorState,
 };
-use namada_state::LastBlock;
-use namada_tx::data::{ResultCode, TxResult};
+use namada_state::{DBStats, LastBlock};
+use namada_token::conversion::ConversionUpdateReport;
+use namada_tx::data::{ResultCode, TxResult, VpVerdict};
 use serde::Serialize;
 
 use crate::args::InputAmount;
@@ -45,6 +52,7 @@ use crate::error::{EncodingError, Error, QueryError, TxSubmitError};
 use crate::events::Event;
 use crate::internal_macros::echo_error;
 use crate::io::Io;
+use crate::key_codec::SchemaDescriptor;
 use crate::masp::MaspTokenRewardData;
 use crate::queries::vp::pos::EnrichedBondsAndUnbondsDetails;
 use crate::queries::{Client, RPC};
@@ -146,6 +154,13 @@ pub async fn query_block<C: crate::queries::Client + Sync>(
     convert_response::<C, _>(RPC.shell().last_block(client).await)
 }
 
+/// Query the statistics of the DB column families of the node
+pub async fn query_db_stats<C: crate::queries::Client + Sync>(
+    client: &C,
+) -> Result<DBStats, error::Error> {
+    convert_response::<C, _>(RPC.shell().db_stats(client).await)
+}
+
 /// A helper to unwrap client's response. Will shut down process on error.
 fn unwrap_client_response<C: crate::queries::Client, T>(
     response: Result<T, C::Error>,
@@ -270,6 +285,18 @@ pub async fn get_consensus_keys<C: crate::queries::Client + Sync>(
     )
 }
 
+/// Get the validator consensus key changes that are scheduled to take effect
+/// in a future epoch
+pub async fn get_pending_consensus_key_changes<
+    C: crate::queries::Client + Sync,
+>(
+    client: &C,
+) -> Result<Vec<PendingConsensusKeyChange>, error::Error> {
+    convert_response::<C, Vec<PendingConsensusKeyChange>>(
+        RPC.vp().pos().pending_consensus_key_changes(client).await,
+    )
+}
+
 /// Check if the address exists on chain. Established address exists if it has a
 /// stored validity predicate. Implicit and internal addresses always return
 /// true.
@@ -330,6 +357,13 @@ pub async fn query_masp_reward_tokens<C: crate::queries::Client + Sync>(
     convert_response::<C, _>(RPC.shell().masp_reward_tokens(client).await)
 }
 
+/// Query a dry-run of the MASP conversion updates of the next epoch
+pub async fn query_conversion_updates<C: crate::queries::Client + Sync>(
+    client: &C,
+) -> Result<Option<ConversionUpdateReport>, Error> {
+    convert_response::<C, _>(RPC.shell().conversion_updates(client).await)
+}
+
 /// Query a wasm code hash
 pub async fn query_wasm_code_hash(
     context: &impl Namada,
@@ -412,6 +446,23 @@ pub async fn query_storage_value_bytes<C: crate::queries::Client + Sync>(
     })
 }
 
+/// Query the ICS23 commitment proof of the existence of a storage key if it has
+/// a value at the given height, or of its non-existence otherwise.
+pub async fn query_storage_proof<C: crate::queries::Client + Sync>(
+    client: &C,
+    key: &storage::Key,
+    height: Option<BlockHeight>,
+) -> Result<CommitmentProofBytes, error::Error> {
+    let data = None;
+    let response = convert_response::<C, _>(
+        RPC.shell()
+            .storage_proof(client, data, height, false, key)
+            .await,
+    )?;
+    CommitmentProofBytes::try_from(response.data)
+        .map_err(|err| Error::from(EncodingError::Decoding(err.to_string())))
+}
+
 /// Query a range of storage values with a matching prefix and decode them with
 /// [`BorshDeserialize`]. Returns an iterator of the storage keys paired with
 /// their associated values.
@@ -457,6 +508,15 @@ pub async fn query_has_storage_key<C: crate::queries::Client + Sync>(
     convert_response::<C, _>(RPC.shell().storage_has_key(client, key).await)
 }
 
+/// Query the encoding of the value under the given storage key. Returns `None`
+/// for a key of an unknown family.
+pub async fn query_storage_schema<C: crate::queries::Client + Sync>(
+    client: &C,
+    key: &storage::Key,
+) -> Result<Option<SchemaDescriptor>, Error> {
+    convert_response::<C, _>(RPC.shell().storage_schema(client, key).await)
+}
+
 /// Represents a query for an event pertaining to the specified transaction
 #[derive(Debug, Copy, Clone)]
 pub enum TxEventQuery<'a> {
@@ -547,6 +607,38 @@ pub async fn dry_run_tx<N: Namada>(
     Ok(result)
 }
 
+/// Dry run a transaction against the native VPs of the internal addresses
+/// that it touches. Returns the verdict of each native VP.
+pub async fn dry_run_vps<N: Namada>(
+    context: &N,
+    tx_bytes: Vec<u8>,
+) -> Result<BTreeMap<Address, VpVerdict>, Error> {
+    let (data, height, prove) = (Some(tx_bytes), None, false);
+    let verdicts = convert_response::<N::Client, _>(
+        RPC.shell()
+            .dry_run_vps(context.client(), data, height, prove)
+            .await,
+    )?
+    .data;
+    for (addr, verdict) in &verdicts {
+        match verdict {
+            VpVerdict::Accepted => {
+                display_line!(context.io(), "The VP of {addr} accepted the tx.")
+            }
+            VpVerdict::Rejected(None) => {
+                display_line!(context.io(), "The VP of {addr} rejected the tx.")
+            }
+            VpVerdict::Rejected(Some(err)) => {
+                display_line!(
+                    context.io(),
+                    "The VP of {addr} rejected the tx: {err}"
+                )
+            }
+        }
+    }
+    Ok(verdicts)
+}
+
 /// Data needed for broadcasting a tx and
 /// monitoring its progress on chain
 ///
@@ -750,11 +842,46 @@ pub async fn query_tx_response<C: crate::queries::Client + Sync>(
     Ok(result)
 }
 
-/// Get the PoS parameters
+/// Get the PoS parameters. The params stored under their own keys aren't
+/// encoded with the others, so they're queried separately.
 pub async fn get_pos_params<C: crate::queries::Client + Sync>(
     client: &C,
 ) -> Result<PosParams, error::Error> {
-    convert_response::<C, _>(RPC.vp().pos().pos_params(client).await)
+    let mut params =
+        convert_response::<C, _>(RPC.vp().pos().pos_params(client).await)?;
+    params.owned.min_validator_self_bond = query_storage_value_or_default(
+        client,
+        &pos_storage_key::min_validator_self_bond_key(),
+    )
+    .await?;
+    params.owned.min_delegation_amount = query_storage_value_or_default(
+        client,
+        &pos_storage_key::min_delegation_amount_key(),
+    )
+    .await?;
+    Ok(params)
+}
+
+/// Query a storage value and decode it with [`BorshDeserialize`], or get the
+/// default value if the key doesn't exist.
+async fn query_storage_value_or_default<C, T>(
+    client: &C,
+    key: &storage::Key,
+) -> Result<T, error::Error>
+where
+    T: BorshDeserialize + Default,
+    C: crate::queries::Client + Sync,
+{
+    let (bytes, _proof) =
+        query_storage_value_bytes(client, key, None, false).await?;
+    bytes
+        .map(|bytes| {
+            T::try_from_slice(&bytes).map_err(|err| {
+                Error::from(EncodingError::Decoding(err.to_string()))
+            })
+        })
+        .transpose()
+        .map(Option::unwrap_or_default)
 }
 
 /// Get all validators in the given epoch
@@ -843,6 +970,16 @@ pub async fn query_proposal_by_id<C: crate::queries::Client + Sync>(
     )
 }
 
+/// Query all the data of a proposal by Id at once
+pub async fn query_proposal_full<C: crate::queries::Client + Sync>(
+    client: &C,
+    proposal_id: u64,
+) -> Result<Option<ProposalFull>, Error> {
+    convert_response::<C, _>(
+        RPC.vp().gov().proposal_full(client, &proposal_id).await,
+    )
+}
+
 /// Query and return validator's commission rate and max commission rate change
 /// per epoch
 pub async fn query_commission_rate<C: crate::queries::Client + Sync>(
@@ -951,6 +1088,20 @@ pub async fn get_public_key_at<C: crate::queries::Client + Sync>(
     }
 }
 
+/// Query the record of a proposal result, written at the activation epoch of
+/// the proposal. Returns `None` if the proposal hasn't been activated yet.
+pub async fn query_proposal_result_record<C: crate::queries::Client + Sync>(
+    client: &C,
+    proposal_id: u64,
+) -> Result<Option<ProposalResultRecord>, Error> {
+    convert_response::<C, _>(
+        RPC.vp()
+            .gov()
+            .proposal_result_record(client, &proposal_id)
+            .await,
+    )
+}
+
 /// Query the proposal result
 pub async fn query_proposal_result<C: crate::queries::Client + Sync>(
     client: &C,
@@ -976,6 +1127,11 @@ pub async fn query_proposal_result<C: crate::queries::Client + Sync>(
                 .await
                 .unwrap_or_default();
             let tally_type = proposal.get_tally_type(is_author_pgf_steward);
+            let threshold = tally_threshold(
+                &proposal.r#type,
+                &tally_type,
+                &query_proposal_parameters(client, proposal_id).await,
+            );
             let total_staked_token =
                 get_total_staked_tokens(client, tally_epoch)
                     .await
@@ -1023,6 +1179,7 @@ pub async fn query_proposal_result<C: crate::queries::Client + Sync>(
                 proposal_votes,
                 total_staked_token,
                 tally_type,
+                threshold,
             )
         }
     };
@@ -1106,6 +1263,20 @@ pub async fn query_governance_parameters<C: crate::queries::Client + Sync>(
     unwrap_client_response::<C, _>(RPC.vp().gov().parameters(client).await)
 }
 
+/// Get the governance parameters of a proposal, as they were when it was
+/// created
+pub async fn query_proposal_parameters<C: crate::queries::Client + Sync>(
+    client: &C,
+    proposal_id: u64,
+) -> ProposalParamsSnapshot {
+    unwrap_client_response::<C, _>(
+        RPC.vp()
+            .gov()
+            .proposal_parameters(client, &proposal_id)
+            .await,
+    )
+}
+
 /// Get the public good fundings parameters
 pub async fn query_pgf_parameters<C: crate::queries::Client + Sync>(
     client: &C,
@@ -1170,6 +1341,21 @@ pub async fn bonds_and_unbonds<C: crate::queries::Client + Sync>(
     )
 }
 
+/// Get the projection of the bond amounts of the given source and validator
+/// from the current epoch up to the unbonding length offset.
+pub async fn bonds_and_unbonds_projection<C: crate::queries::Client + Sync>(
+    client: &C,
+    source: &Address,
+    validator: &Address,
+) -> Result<BondProjection, error::Error> {
+    convert_response::<C, _>(
+        RPC.vp()
+            .pos()
+            .bonds_and_unbonds_projection(client, source, validator)
+            .await,
+    )
+}
+
 /// Get bonds and unbonds with all details (slashes and rewards, if any)
 /// grouped by their bond IDs, enriched with extra information calculated from
 /// the data.
@@ -1366,13 +1552,21 @@ pub async fn query_ibc_denom<N: Namada>(
     token: impl AsRef<str>,
     owner: Option<&Address>,
 ) -> String {
-    let hash = match Address::decode(token.as_ref()) {
-        Ok(Address::Internal(InternalAddress::IbcToken(hash))) => {
-            hash.to_string()
-        }
+    let Ok(token_addr) = Address::decode(token.as_ref()) else {
+        return token.as_ref().to_string();
+    };
+    let hash = match &token_addr {
+        Address::Internal(InternalAddress::IbcToken(hash)) => hash.to_string(),
         _ => return token.as_ref().to_string(),
     };
 
+    if let Ok(Some(ibc_denom)) =
+        RPC.vp().ibc().denom(context.client(), &token_addr).await
+    {
+        return ibc_denom;
+    }
+
+    // The denom has been received before the lookup by the token was added
     if let Some(owner) = owner {
         let ibc_denom_key = ibc_denom_key(owner.to_string(), &hash);
         if let Ok(ibc_denom) =
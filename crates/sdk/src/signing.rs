@@ -928,6 +928,9 @@ impl<'a> Display for LedgerProposalType<'a> {
             }
             ProposalType::PGFSteward(_) => write!(f, "PGF Steward"),
             ProposalType::PGFPayment(_) => write!(f, "PGF Payment"),
+            ProposalType::ExtendVotingPeriod(_) => {
+                write!(f, "Voting Period Extension")
+            }
         }
     }
 }
@@ -1062,6 +1065,12 @@ fn proposal_type_to_ledger_vector(
                 }
             }
         }
+        ProposalType::ExtendVotingPeriod(extension) => {
+            output.push("Proposal type : Voting Period Extension".to_string());
+            output
+                .push(format!("Extended proposal : {}", extension.proposal_id));
+            output.push(format!("Epochs : {}", extension.epochs));
+        }
     }
 }
 
@@ -695,6 +695,7 @@ pub async fn build_validator_metadata_change(
         discord_handle,
         avatar,
         commission_rate,
+        max_total_delegated,
         tx_code_path,
     }: &args::MetaDataChange,
 ) -> Result<(Tx, SigningTxData)> {
@@ -807,6 +808,7 @@ pub async fn build_validator_metadata_change(
         discord_handle: discord_handle.clone(),
         avatar: avatar.clone(),
         commission_rate: *commission_rate,
+        max_total_delegated: *max_total_delegated,
     };
 
     build(
@@ -1929,7 +1931,9 @@ pub async fn build_vote_proposal(
         tx,
         proposal_id,
         vote,
+        memo,
         voter,
+        delegations: selected_delegations,
         is_offline: _,
         proposal_data: _,
         tx_code_path,
@@ -1992,11 +1996,56 @@ pub async fn build_vote_proposal(
         ));
     }
 
+    // Vote with the selected delegations only, if any
+    let delegations = if selected_delegations.is_empty() {
+        delegations
+    } else {
+        for validator in selected_delegations {
+            if !delegations.contains(validator) {
+                if tx.force {
+                    eprintln!(
+                        "The voter {} has no delegation to {} at the start \
+                         epoch of the voting period.",
+                        voter, validator
+                    );
+                } else {
+                    return Err(Error::Other(format!(
+                        "The voter {} has no delegation to {} at the start \
+                         epoch of the voting period",
+                        voter, validator
+                    )));
+                }
+            }
+        }
+        selected_delegations.clone()
+    };
+
+    if let Some(memo) = memo {
+        let max_vote_memo_length =
+            rpc::query_governance_parameters(context.client())
+                .await
+                .max_vote_memo_length;
+        if memo.len() as u64 > max_vote_memo_length {
+            if tx.force {
+                eprintln!(
+                    "The vote memo exceeds the maximum length of {} bytes.",
+                    max_vote_memo_length
+                );
+            } else {
+                return Err(Error::Other(format!(
+                    "The vote memo exceeds the maximum length of {} bytes",
+                    max_vote_memo_length
+                )));
+            }
+        }
+    }
+
     let data = VoteProposalData {
         id: proposal_id,
         vote: proposal_vote,
         voter: voter.clone(),
         delegations,
+        memo: memo.clone(),
     };
 
     build(
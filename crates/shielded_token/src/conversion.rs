@@ -1,31 +1,78 @@
 //! MASP rewards conversions
 
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use masp_primitives::transaction::components::I128Sum as MaspAmount;
 use namada_core::address::{Address, MASP};
+use namada_core::borsh::{BorshDeserialize, BorshSerialize};
 use namada_core::dec::Dec;
+use namada_core::masp::encode_asset_type;
+use namada_core::storage::Epoch;
 use namada_core::uint::Uint;
 use namada_parameters as parameters;
-use namada_storage::{StorageRead, StorageWrite};
+use namada_storage::{ResultExt, StorageRead, StorageWrite};
 use namada_trans_token::inflation::{
     ShieldedRewardsController, ShieldedValsToUpdate,
 };
 use namada_trans_token::storage_key::{balance_key, minted_balance_key};
-use namada_trans_token::{read_denom, Amount, DenominatedAmount, Denomination};
+use namada_trans_token::{
+    read_denom, Amount, DenominatedAmount, Denomination, MaspDigitPos,
+    NATIVE_MAX_DECIMAL_PLACES,
+};
 
 use crate::storage_key::{
     masp_kd_gain_key, masp_kp_gain_key, masp_last_inflation_key,
     masp_last_locked_amount_key, masp_locked_amount_target_key,
     masp_max_reward_rate_key,
 };
-use crate::WithConversionState;
+use crate::{read_reward_tokens, ConversionState, WithConversionState};
+
+/// The MASP rewards of a token computed by the PD-controller
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct MaspTokenRewards {
+    /// The token address
+    pub token: Address,
+    /// The token denomination
+    pub denom: Denomination,
+    /// The reward per unit of the token, as the noterized inflation and the
+    /// precision
+    pub reward: (u128, u128),
+    /// The inflation amount, to be stored as the last inflation
+    pub inflation: Amount,
+    /// The amount of the token locked in the MASP, to be stored as the last
+    /// locked amount
+    pub locked_amount: Amount,
+}
+
+/// The updates of the MASP conversions for an epoch, computed without
+/// modifying the storage
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ConversionUpdateReport {
+    /// The epoch of the new conversions
+    pub epoch: Epoch,
+    /// The rewards of the shielded tokens, the native token first
+    pub token_rewards: Vec<MaspTokenRewards>,
+    /// The tokens without a shielded balance, for which no rewards nor
+    /// conversions are computed
+    pub unshielded_tokens: Vec<(Address, Denomination)>,
+    /// The new normed inflation of the native token
+    pub normed_inflation: Option<u128>,
+    /// The transparent value of the rewards to be credited to the MASP
+    pub total_reward: Amount,
+    /// The conversions from the assets of the previous epoch
+    pub conversions:
+        BTreeMap<(Address, Denomination, MaspDigitPos), MaspAmount>,
+}
 
 /// Compute the precision of MASP rewards for the given token. This function
 /// must be a non-zero constant for a given token.
 pub fn calculate_masp_rewards_precision<S>(
-    storage: &mut S,
+    storage: &S,
     addr: &Address,
 ) -> namada_storage::Result<(u128, Denomination)>
 where
-    S: StorageWrite + StorageRead,
+    S: StorageRead,
 {
     let denomination =
         read_denom(storage, addr)?.expect("failed to read token denomination");
@@ -42,6 +89,19 @@ where
     ))
 }
 
+/// Scale a raw amount from a denomination up to a larger one, i.e. multiply
+/// it by `10^(to - from)`. Returns `None` on overflow or if the target
+/// denomination is smaller, which would truncate the amount.
+fn scale_up_to_denomination(
+    amount: Uint,
+    from: Denomination,
+    to: Denomination,
+) -> Option<Uint> {
+    let exponent = to.0.checked_sub(from.0)?;
+    let factor = Uint::from(10u64).checked_pow(Uint::from(exponent))?;
+    amount.checked_mul(factor)
+}
+
 /// Compute the MASP rewards by applying the PD-controller to the genesis
 /// parameters and the last inflation and last locked rewards ratio values.
 pub fn calculate_masp_rewards<S>(
@@ -50,6 +110,20 @@ pub fn calculate_masp_rewards<S>(
 ) -> namada_storage::Result<((u128, u128), Denomination)>
 where
     S: StorageWrite + StorageRead,
+{
+    let rewards = compute_masp_rewards(storage, token)?;
+    write_masp_rewards(storage, &rewards)?;
+    Ok((rewards.reward, rewards.denom))
+}
+
+/// Compute the MASP rewards of a token like [`calculate_masp_rewards`],
+/// without writing the last inflation and last locked amount.
+pub fn compute_masp_rewards<S>(
+    storage: &S,
+    token: &Address,
+) -> namada_storage::Result<MaspTokenRewards>
+where
+    S: StorageRead,
 {
     let (precision, denomination) =
         calculate_masp_rewards_precision(storage, token)?;
@@ -59,10 +133,29 @@ where
     // Query the storage for information -------------------------
 
     let native_token = storage.get_native_token()?;
+    let native_denomination =
+        read_denom(storage, &native_token)?.ok_or_else(|| {
+            namada_storage::Error::new_const(
+                "The native token denomination is missing from storage",
+            )
+        })?;
     //// information about the amount of native tokens on the chain
     let total_native_tokens: Amount = storage
         .read(&minted_balance_key(&native_token))?
         .expect("the total supply key should be here");
+    // The PD-controller compares the raw amounts of the token to the native
+    // supply, so they're all scaled up to the larger of the two denominations
+    // to not truncate either side
+    let controller_denom = std::cmp::max(native_denomination, denomination);
+    let scale_up = |amount: Amount, from: Denomination| {
+        scale_up_to_denomination(amount.raw_amount(), from, controller_denom)
+            .ok_or_else(|| {
+                namada_storage::Error::new_const(
+                    "The MASP rewards amounts overflow in the larger \
+                     denomination of the token and the native token",
+                )
+            })
+    };
 
     // total locked amount in the Shielded pool
     let total_tokens_in_masp: Amount = storage
@@ -73,14 +166,15 @@ where
         .read(&parameters::storage::get_epochs_per_year_key())?
         .expect("epochs per year should properly decode");
 
-    //// Values from the last epoch
+    //// Values from the last epoch, missing for a token added after the
+    //// genesis
     let last_inflation: Amount = storage
         .read(&masp_last_inflation_key(token))?
-        .expect("failure to read last inflation");
+        .unwrap_or_default();
 
     let last_locked_amount: Amount = storage
         .read(&masp_last_locked_amount_key(token))?
-        .expect("failure to read last inflation");
+        .unwrap_or_default();
 
     //// Parameters for each token
     let max_reward_rate: Dec = storage
@@ -100,13 +194,17 @@ where
         .expect("locked ratio target should properly decode");
 
     // Creating the PD controller for handing out tokens
+    let locked_tokens = scale_up(total_tokens_in_masp, denomination)?;
     let controller = ShieldedRewardsController {
-        locked_tokens: total_tokens_in_masp.raw_amount(),
-        total_native_tokens: total_native_tokens.raw_amount(),
-        locked_tokens_target: target_locked_amount.raw_amount(),
-        locked_tokens_last: last_locked_amount.raw_amount(),
+        locked_tokens,
+        total_native_tokens: scale_up(
+            total_native_tokens,
+            native_denomination,
+        )?,
+        locked_tokens_target: scale_up(target_locked_amount, denomination)?,
+        locked_tokens_last: scale_up(last_locked_amount, denomination)?,
         max_reward_rate,
-        last_inflation_amount: last_inflation.raw_amount(),
+        last_inflation_amount: scale_up(last_inflation, denomination)?,
         p_gain_nom: kp_gain_nom,
         d_gain_nom: kd_gain_nom,
         epochs_per_year,
@@ -115,19 +213,28 @@ where
     let ShieldedValsToUpdate { inflation } =
         ShieldedRewardsController::run(controller);
 
+    // The reward of a unit of the locked token must not exceed the maximum
+    // reward rate for an epoch, i.e. max_reward_rate * PRECISION /
+    // epochs_per_year
+    let max_noterized_inflation: u128 = (max_reward_rate * precision
+        / epochs_per_year)
+        .to_uint()
+        .and_then(|x| x.try_into().ok())
+        .unwrap_or(u128::MAX);
     // inflation-per-token = inflation / locked tokens = n/PRECISION
     // ∴ n = (inflation * PRECISION) / locked tokens
     // Since we must put the notes in a compatible format with the
-    // note format, we must make the inflation amount discrete.
+    // note format, we must make the inflation amount discrete. The ratio
+    // doesn't depend on the denomination of the controller's amounts.
     let noterized_inflation = if total_tokens_in_masp.is_zero() {
         0u128
     } else {
         inflation
-            .checked_mul_div(
-                Uint::from(precision),
-                total_tokens_in_masp.raw_amount(),
-            )
+            .checked_mul_div(Uint::from(precision), locked_tokens)
             .and_then(|x| x.0.try_into().ok())
+            .map(|noterized_inflation| {
+                std::cmp::min(noterized_inflation, max_noterized_inflation)
+            })
             .unwrap_or_else(|| {
                 tracing::warn!(
                     "MASP inflation for {} assumed to be 0 because the \
@@ -148,85 +255,124 @@ where
     tracing::info!("MASP inflation for {token} is {denom_amount}");
 
     tracing::debug!(
-        "Controller, call: total_in_masp {:?}, total_native_tokens {:?}, \
-         locked_target_amount {:?}, last_locked_amount {:?}, max_reward_rate \
-         {:?}, last_inflation {:?}, kp_gain_nom {:?}, kd_gain_nom {:?}, \
-         epochs_per_year {:?}",
-        total_tokens_in_masp,
-        total_native_tokens,
-        target_locked_amount,
-        last_locked_amount,
-        max_reward_rate,
-        last_inflation,
-        kp_gain_nom,
-        kd_gain_nom,
+        %token,
+        %total_tokens_in_masp,
+        %total_native_tokens,
+        %target_locked_amount,
+        %last_locked_amount,
+        %max_reward_rate,
+        %last_inflation,
+        %kp_gain_nom,
+        %kd_gain_nom,
         epochs_per_year,
+        %inflation,
+        %precision,
+        %max_noterized_inflation,
+        %noterized_inflation,
+        "Computed the MASP inflation with the PD-controller"
     );
-    tracing::debug!("Token address: {:?}", token);
-    tracing::debug!("inflation from the pd controller {:?}", inflation);
-    tracing::debug!("total in the masp {:?}", total_tokens_in_masp);
-    tracing::debug!("precision {}", precision);
-    tracing::debug!("Noterized inflation: {}", noterized_inflation);
-
-    // Is it fine to write the inflation rate, this is accurate,
-    // but we should make sure the return value's ratio matches
-    // this new inflation rate in 'update_allowed_conversions',
-    // otherwise we will have an inaccurate view of inflation
-    storage.write(&masp_last_inflation_key(token), inflation_amount)?;
 
-    storage.write(&masp_last_locked_amount_key(token), total_tokens_in_masp)?;
+    Ok(MaspTokenRewards {
+        token: token.clone(),
+        denom: denomination,
+        reward: (noterized_inflation, precision),
+        inflation: inflation_amount,
+        locked_amount: total_tokens_in_masp,
+    })
+}
 
-    Ok(((noterized_inflation, precision), denomination))
+/// Read the tokens receiving MASP rewards from the storage. A chain
+/// initialized before the reward tokens were written to the storage has none
+/// of them, in which case the tokens of the conversion state are kept.
+fn reward_tokens<S>(
+    storage: &S,
+    conversion_state: &ConversionState,
+) -> namada_storage::Result<BTreeMap<String, Address>>
+where
+    S: StorageRead,
+{
+    let tokens = read_reward_tokens(storage)?;
+    if tokens.is_empty() {
+        return Ok(conversion_state.tokens.clone());
+    }
+    Ok(tokens)
 }
 
-// This is only enabled when "wasm-runtime" is on, because we're using rayon
-#[cfg(not(any(feature = "multicore", test)))]
-/// Update the MASP's allowed conversions
-pub fn update_allowed_conversions<S>(
-    _storage: &mut S,
-) -> namada_storage::Result<()>
+/// Find a rewards parameter of the token missing from the storage, e.g. for a
+/// token added to the reward tokens without all its parameters
+fn missing_masp_reward_param<S>(
+    storage: &S,
+    token: &Address,
+) -> namada_storage::Result<Option<&'static str>>
 where
-    S: StorageWrite + StorageRead + WithConversionState,
+    S: StorageRead,
 {
-    Ok(())
+    if read_denom(storage, token)?.is_none() {
+        return Ok(Some("denomination"));
+    }
+    for (key, param) in [
+        (masp_max_reward_rate_key(token), "max reward rate"),
+        (masp_kp_gain_key(token), "kp gain"),
+        (masp_kd_gain_key(token), "kd gain"),
+        (masp_locked_amount_target_key(token), "locked amount target"),
+    ] {
+        if !storage.has_key(&key)? {
+            return Ok(Some(param));
+        }
+    }
+    Ok(None)
 }
 
-#[cfg(any(feature = "multicore", test))]
-/// Update the MASP's allowed conversions
-pub fn update_allowed_conversions<S>(
+/// Write the last inflation and last locked amount of a token's rewards
+fn write_masp_rewards<S>(
     storage: &mut S,
+    rewards: &MaspTokenRewards,
 ) -> namada_storage::Result<()>
 where
-    S: StorageWrite + StorageRead + WithConversionState,
+    S: StorageWrite + StorageRead,
 {
-    use std::cmp::Ordering;
-    use std::collections::BTreeMap;
+    // Is it fine to write the inflation rate, this is accurate,
+    // but we should make sure the return value's ratio matches
+    // this new inflation rate in 'update_allowed_conversions',
+    // otherwise we will have an inaccurate view of inflation
+    storage
+        .write(&masp_last_inflation_key(&rewards.token), rewards.inflation)?;
 
-    use masp_primitives::bls12_381;
-    use masp_primitives::convert::AllowedConversion;
-    use masp_primitives::ff::PrimeField;
-    use masp_primitives::merkle_tree::FrozenCommitmentTree;
-    use masp_primitives::sapling::Node;
-    use masp_primitives::transaction::components::I128Sum as MaspAmount;
-    use namada_core::masp::encode_asset_type;
-    use namada_core::storage::Epoch;
-    use namada_storage::ResultExt;
-    use namada_trans_token::{MaspDigitPos, NATIVE_MAX_DECIMAL_PLACES};
-    use rayon::iter::{
-        IndexedParallelIterator, IntoParallelIterator, ParallelIterator,
-    };
-    use rayon::prelude::ParallelSlice;
+    storage.write(
+        &masp_last_locked_amount_key(&rewards.token),
+        rewards.locked_amount,
+    )
+}
+
+/// Compute the updates of the MASP conversions for the given epoch without
+/// modifying the storage, e.g. to preview the MASP rewards of the next
+/// epoch. Returns `None` for the first epoch, which has no conversions.
+pub fn compute_conversion_updates<S>(
+    storage: &S,
+    conversion_state: &ConversionState,
+    epoch: Epoch,
+) -> namada_storage::Result<Option<ConversionUpdateReport>>
+where
+    S: StorageRead,
+{
+    if epoch == Epoch::default() {
+        return Ok(None);
+    }
+    let prev_epoch = epoch.prev();
 
     // The derived conversions will be placed in MASP address space
     let masp_addr = MASP;
 
-    let mut masp_reward_keys: Vec<_> = storage
-        .conversion_state()
-        .tokens
-        .values()
-        .cloned()
-        .collect();
-    let mut masp_reward_denoms = BTreeMap::new();
+    let mut masp_reward_keys = vec![];
+    for token in reward_tokens(storage, conversion_state)?.into_values() {
+        match missing_masp_reward_param(storage, &token)? {
+            None => masp_reward_keys.push(token),
+            Some(param) => tracing::warn!(
+                "Skipping the MASP rewards of {token} because its {param} is \
+                 missing"
+            ),
+        }
+    }
     // Put the native rewards first because other inflation computations depend
     // on it
     let native_token = storage.get_native_token()?;
@@ -277,32 +423,34 @@ where
         .into_storage_result()?,
     ];
     // Conversions from the previous to current asset for each address
-    let mut current_convs = BTreeMap::<
-        (Address, Denomination, MaspDigitPos),
-        AllowedConversion,
-    >::new();
+    let mut current_convs =
+        BTreeMap::<(Address, Denomination, MaspDigitPos), MaspAmount>::new();
+    let mut token_rewards = Vec::with_capacity(masp_reward_keys.len());
+    let mut unshielded_tokens = vec![];
     // Native token inflation values are always with respect to this
     let ref_inflation =
         calculate_masp_rewards_precision(storage, &native_token)?.0;
+    let mut normed_inflation_state = conversion_state.normed_inflation;
 
     // Reward all tokens according to above reward rates
-    let epoch = storage.get_block_epoch()?;
-    if epoch == Epoch::default() {
-        return Ok(());
-    }
-    let prev_epoch = epoch.prev();
     for token in &masp_reward_keys {
-        let (reward, denom) = calculate_masp_rewards(storage, token)?;
-        masp_reward_denoms.insert(token.clone(), denom);
         // Dispense a transparent reward in parallel to the shielded rewards
         let addr_bal: Amount = storage
             .read(&balance_key(token, &masp_addr))?
             .unwrap_or_default();
+        // There are no notes of a token without a shielded balance, so it
+        // needs no conversions
+        if addr_bal.is_zero() {
+            let (_, denom) = calculate_masp_rewards_precision(storage, token)?;
+            unshielded_tokens.push((token.clone(), denom));
+            continue;
+        }
+        let rewards = compute_masp_rewards(storage, token)?;
+        let (reward, denom) = (rewards.reward, rewards.denom);
+        token_rewards.push(rewards);
         // Get the last rewarded amount of the native token
-        let normed_inflation = *storage
-            .conversion_state_mut()
-            .normed_inflation
-            .get_or_insert(ref_inflation);
+        let normed_inflation =
+            *normed_inflation_state.get_or_insert(ref_inflation);
 
         for digit in MaspDigitPos::iter() {
             // Provide an allowed conversion from previous timestamp. The
@@ -343,7 +491,7 @@ where
                 // telescope out
                 current_convs.insert(
                     (token.clone(), denom, digit),
-                    (MaspAmount::from_pair(
+                    MaspAmount::from_pair(
                         old_asset,
                         -(normed_inflation as i128),
                     )
@@ -352,8 +500,7 @@ where
                             new_asset,
                             new_normed_inflation as i128,
                         )
-                        .unwrap())
-                    .into(),
+                        .unwrap(),
                 );
                 // Operations that happen exactly once for each token
                 if digit == MaspDigitPos::Three {
@@ -368,11 +515,7 @@ where
                         .checked_sub(addr_bal)
                         .unwrap_or_default();
                     // Save the new normed inflation
-
-                    let _ = storage
-                        .conversion_state_mut()
-                        .normed_inflation
-                        .insert(new_normed_inflation);
+                    normed_inflation_state = Some(new_normed_inflation);
                 }
             } else {
                 // Express the inflation reward in real terms, that is, with
@@ -396,7 +539,7 @@ where
                 // intermediate tokens cancel/ telescope out
                 current_convs.insert(
                     (token.clone(), denom, digit),
-                    (MaspAmount::from_pair(old_asset, -(reward.1 as i128))
+                    MaspAmount::from_pair(old_asset, -(reward.1 as i128))
                         .unwrap()
                         + MaspAmount::from_pair(new_asset, reward.1 as i128)
                             .unwrap()
@@ -404,8 +547,7 @@ where
                             reward_assets[digit as usize],
                             real_reward as i128,
                         )
-                        .unwrap())
-                    .into(),
+                        .unwrap(),
                 );
                 // Operations that happen exactly once for each token
                 if digit == MaspDigitPos::Three {
@@ -414,19 +556,133 @@ where
                     total_reward += (addr_bal * (reward.0, reward.1)).0;
                 }
             }
-            // Add a conversion from the previous asset type
-            storage.conversion_state_mut().assets.insert(
-                old_asset,
-                (
-                    (token.clone(), denom, digit),
-                    prev_epoch,
-                    MaspAmount::zero().into(),
-                    0,
-                ),
-            );
         }
     }
 
+    Ok(Some(ConversionUpdateReport {
+        epoch,
+        token_rewards,
+        unshielded_tokens,
+        normed_inflation: normed_inflation_state,
+        total_reward,
+        conversions: current_convs,
+    }))
+}
+
+// This is only enabled when "wasm-runtime" is on, because we're using rayon
+#[cfg(not(any(feature = "multicore", test)))]
+/// Update the MASP's allowed conversions
+pub fn update_allowed_conversions<S>(
+    _storage: &mut S,
+) -> namada_storage::Result<()>
+where
+    S: StorageWrite + StorageRead + WithConversionState,
+{
+    Ok(())
+}
+
+#[cfg(any(feature = "multicore", test))]
+/// Update the MASP's allowed conversions
+pub fn update_allowed_conversions<S>(
+    storage: &mut S,
+) -> namada_storage::Result<()>
+where
+    S: StorageWrite + StorageRead + WithConversionState,
+{
+    // Keep the tokens of the conversion state in sync with the reward tokens
+    // in storage, which can be updated by governance
+    let tokens = reward_tokens(storage, storage.conversion_state())?;
+    storage.conversion_state_mut().tokens = tokens;
+
+    let epoch = storage.get_block_epoch()?;
+    match compute_conversion_updates(
+        storage,
+        storage.conversion_state(),
+        epoch,
+    )? {
+        Some(report) => apply_conversion_updates(storage, report),
+        None => Ok(()),
+    }
+}
+
+#[cfg(any(feature = "multicore", test))]
+/// Apply the updates of the MASP conversions computed by
+/// [`compute_conversion_updates`]
+fn apply_conversion_updates<S>(
+    storage: &mut S,
+    report: ConversionUpdateReport,
+) -> namada_storage::Result<()>
+where
+    S: StorageWrite + StorageRead + WithConversionState,
+{
+    use masp_primitives::bls12_381;
+    use masp_primitives::convert::AllowedConversion;
+    use masp_primitives::ff::PrimeField;
+    use masp_primitives::merkle_tree::FrozenCommitmentTree;
+    use masp_primitives::sapling::Node;
+    use rayon::iter::{
+        IndexedParallelIterator, IntoParallelIterator, ParallelIterator,
+    };
+    use rayon::prelude::ParallelSlice;
+
+    let ConversionUpdateReport {
+        epoch,
+        token_rewards,
+        unshielded_tokens,
+        normed_inflation,
+        total_reward,
+        conversions,
+    } = report;
+    let prev_epoch = epoch.prev();
+
+    // The derived conversions will be placed in MASP address space
+    let masp_addr = MASP;
+    let native_token = storage.get_native_token()?;
+
+    for rewards in &token_rewards {
+        write_masp_rewards(storage, rewards)?;
+    }
+    for (token, denom) in &unshielded_tokens {
+        // Nothing is locked, so the token gets no rewards
+        storage.write(&masp_last_inflation_key(token), Amount::zero())?;
+        storage.write(&masp_last_locked_amount_key(token), Amount::zero())?;
+        // Without a shielded balance there are no notes of the previous
+        // asset, so its decoding entry is dropped instead of being committed
+        // to the tree
+        for digit in MaspDigitPos::iter() {
+            let old_asset = encode_asset_type(
+                token.clone(),
+                *denom,
+                digit,
+                Some(prev_epoch),
+            )
+            .into_storage_result()?;
+            storage.conversion_state_mut().assets.remove(&old_asset);
+        }
+    }
+    storage.conversion_state_mut().normed_inflation = normed_inflation;
+
+    let mut current_convs = BTreeMap::<
+        (Address, Denomination, MaspDigitPos),
+        AllowedConversion,
+    >::new();
+    for ((token, denom, digit), conv) in conversions {
+        // Add a conversion from the previous asset type
+        let old_asset =
+            encode_asset_type(token.clone(), denom, digit, Some(prev_epoch))
+                .into_storage_result()?;
+        storage.conversion_state_mut().assets.insert(
+            old_asset,
+            (
+                (token.clone(), denom, digit),
+                prev_epoch,
+                MaspAmount::zero().into(),
+                0,
+            ),
+        );
+        current_convs.insert((token, denom, digit), conv.into());
+    }
+
     // Try to distribute Merkle leaf updating as evenly as possible across
     // multiple cores
     let num_threads = rayon::current_num_threads();
@@ -491,25 +747,24 @@ where
         ),
     )?;
 
-    if !masp_reward_keys.contains(&native_token) {
-        // Since MASP rewards are denominated in NAM tokens, ensure that clients
-        // are able to decode them.
-        masp_reward_keys.push(native_token.clone());
-    }
     // Add purely decoding entries to the assets map. These will be
     // overwritten before the creation of the next commitment tree
-    for (addr, denom) in masp_reward_denoms {
+    let tokens = token_rewards
+        .into_iter()
+        .map(|MaspTokenRewards { token, denom, .. }| (token, denom))
+        .chain(unshielded_tokens);
+    for (token, denom) in tokens {
         for digit in MaspDigitPos::iter() {
             // Add the decoding entry for the new asset type. An uncommitted
             // node position is used since this is not a conversion.
             let new_asset =
-                encode_asset_type(addr.clone(), denom, digit, Some(epoch))
+                encode_asset_type(token.clone(), denom, digit, Some(epoch))
                     .into_storage_result()?;
             let tree_size = storage.conversion_state().tree.size();
             storage.conversion_state_mut().assets.insert(
                 new_asset,
                 (
-                    (addr.clone(), denom, digit),
+                    (token.clone(), denom, digit),
                     epoch,
                     MaspAmount::zero().into(),
                     tree_size,
@@ -538,6 +793,7 @@ mod tests {
     use test_log::test;
 
     use super::*;
+    use crate::storage_key::masp_reward_token_key;
     use crate::ShieldedParams;
 
     proptest! {
@@ -552,6 +808,226 @@ mod tests {
         ) {
             test_updated_allowed_conversions_aux(initial_balance, masp_locked_ratio)
         }
+
+        #[test]
+        fn test_masp_rewards_bounded_by_max_reward_rate(
+            initial_balance in arb_amount(),
+            masp_locked_ratio in arb_non_negative_dec(),
+            last_inflation in arb_amount(),
+        ) {
+            test_masp_rewards_bounded_by_max_reward_rate_aux(
+                initial_balance,
+                masp_locked_ratio,
+                last_inflation,
+            )
+        }
+    }
+
+    /// Check that the rewards of an epoch never exceed the maximum reward
+    /// rate of the locked amount
+    fn test_masp_rewards_bounded_by_max_reward_rate_aux(
+        initial_balance: Amount,
+        masp_locked_ratio: Dec,
+        last_inflation: Amount,
+    ) {
+        let mut s = TestStorage::default();
+        init_conversions_storage(&mut s, initial_balance, masp_locked_ratio);
+        let epochs_per_year: u64 = s
+            .read(&parameters::storage::get_epochs_per_year_key())
+            .unwrap()
+            .unwrap();
+
+        for token in tokens().keys() {
+            s.write(&masp_last_inflation_key(token), last_inflation)
+                .unwrap();
+            let rewards = compute_masp_rewards(&s, token).unwrap();
+            let max_reward_rate: Dec =
+                s.read(&masp_max_reward_rate_key(token)).unwrap().unwrap();
+            assert!(
+                rewards.inflation.raw_amount() * Uint::from(epochs_per_year)
+                    <= (max_reward_rate * rewards.locked_amount).raw_amount()
+            );
+        }
+    }
+
+    /// Apply the conversions of the first epoch with the given last inflation
+    /// and locked amount of BTC. Returns the stored inflation of BTC and the
+    /// amount of the native reward asset in the conversion of BTC.
+    fn apply_btc_rewards(
+        last_inflation: Amount,
+        locked_amount: Amount,
+    ) -> (Amount, i128) {
+        let mut s = TestStorage::default();
+        init_conversions_storage(
+            &mut s,
+            Amount::from_u64(1_000_000_000_000_000),
+            Dec::zero(),
+        );
+        let btc = address::testing::btc();
+        s.write(&masp_last_inflation_key(&btc), last_inflation)
+            .unwrap();
+        s.write(&masp_last_locked_amount_key(&btc), locked_amount)
+            .unwrap();
+        s.write(&balance_key(&btc, &address::MASP), locked_amount)
+            .unwrap();
+
+        s.set_block_epoch(Epoch(1));
+        update_allowed_conversions(&mut s).unwrap();
+
+        let inflation: Amount =
+            s.read(&masp_last_inflation_key(&btc)).unwrap().unwrap();
+        let old_asset = encode_asset_type(
+            btc,
+            8.into(),
+            MaspDigitPos::Zero,
+            Some(Epoch(0)),
+        )
+        .unwrap();
+        let reward_asset = encode_asset_type(
+            s.get_native_token().unwrap(),
+            NATIVE_MAX_DECIMAL_PLACES.into(),
+            MaspDigitPos::Zero,
+            Some(Epoch(0)),
+        )
+        .unwrap();
+        let (_, _, conv, _) = &s.conversion_state().assets[&old_asset];
+        let reward = MaspAmount::from(conv.clone()).get(&reward_asset);
+        (inflation, reward)
+    }
+
+    /// Test the rewards of a small controller output, below the maximum
+    /// reward rate
+    #[test]
+    fn test_masp_rewards_small_inflation() {
+        // The locked amount is at the 10_000 BTC target, so the controller
+        // keeps the last inflation
+        let (inflation, reward) = apply_btc_rewards(
+            Amount::from_u64(100_000_000),
+            Amount::from_u64(1_000_000_000_000),
+        );
+        // 100_000_000 * 10^5 / 10^12 = 10 per 10^5 units of BTC
+        assert_eq!(inflation, Amount::from_u64(100_000_000));
+        assert_eq!(reward, 10);
+    }
+
+    /// Test that the rewards of a huge controller output are capped by the
+    /// maximum reward rate
+    #[test]
+    fn test_masp_rewards_huge_inflation() {
+        let (inflation, reward) = apply_btc_rewards(
+            Amount::from_u64(100_000_000_000),
+            Amount::from_u64(1_000_000),
+        );
+        // The reward of 10^5 units of BTC is capped to 0.1 * 10^5 / 365
+        assert_eq!(reward, 27);
+        assert_eq!(inflation, Amount::from_u64(270));
+    }
+
+    #[test]
+    fn test_scale_up_to_denomination() {
+        let native_denom = Denomination(6);
+        let amount = Uint::from(1_234_567_u64);
+        assert_eq!(
+            scale_up_to_denomination(amount, native_denom, Denomination(6)),
+            Some(amount)
+        );
+        assert_eq!(
+            scale_up_to_denomination(amount, native_denom, Denomination(18)),
+            Some(amount * Uint::exp10(12))
+        );
+        // A 0-decimal token is scaled up to the native denomination
+        assert_eq!(
+            scale_up_to_denomination(
+                Uint::from(1_u64),
+                Denomination(0),
+                native_denom
+            ),
+            Some(Uint::from(1_000_000_u64))
+        );
+        // The scaling down would truncate the amount
+        assert_eq!(
+            scale_up_to_denomination(amount, native_denom, Denomination(0)),
+            None
+        );
+        // The overflows are reported instead of panicking
+        assert_eq!(
+            scale_up_to_denomination(
+                amount,
+                Denomination(0),
+                Denomination(255)
+            ),
+            None
+        );
+    }
+
+    /// Test that the inflation of a token with a larger denomination than the
+    /// native token is capped by the native supply in the token's
+    /// denomination
+    #[test]
+    fn test_masp_rewards_of_18_decimal_token() {
+        let mut s = TestStorage::default();
+        // 10^9 NAM of supply
+        init_conversions_storage(
+            &mut s,
+            Amount::from_u64(1_000_000_000_000_000),
+            Dec::zero(),
+        );
+        // 10^10 ETH locked in the MASP with a huge last inflation, so that
+        // the inflation is capped by the controller's max inflation
+        let eth = address::testing::eth();
+        let locked_amount = Amount::from_uint(Uint::exp10(28), 0).unwrap();
+        s.write(
+            &masp_last_inflation_key(&eth),
+            Amount::from_uint(Uint::exp10(30), 0).unwrap(),
+        )
+        .unwrap();
+        s.write(&balance_key(&eth, &address::MASP), locked_amount)
+            .unwrap();
+
+        let rewards = compute_masp_rewards(&s, &eth).unwrap();
+        // The max inflation is 10^9 NAM * 10^18 * 0.1 / 365 =
+        // 273972602739726027397260 units of ETH, i.e. a reward of
+        // 273972602739726027397260 * 10^15 / 10^28 = 27397260273 per 10^15
+        // units of ETH
+        assert_eq!(rewards.reward, (27_397_260_273, 1_000_000_000_000_000));
+        assert_eq!(
+            rewards.inflation,
+            Amount::from_uint(
+                Uint::exp10(13) * Uint::from(27_397_260_273_u64),
+                0
+            )
+            .unwrap()
+        );
+        assert_eq!(rewards.locked_amount, locked_amount);
+    }
+
+    /// Test that the rewards of a 0-decimal token, e.g. an IBC token, are
+    /// computed without panicking
+    #[test]
+    fn test_masp_rewards_of_0_decimal_token() {
+        let mut s = TestStorage::default();
+        init_conversions_storage(
+            &mut s,
+            Amount::from_u64(1_000_000_000_000_000),
+            Dec::zero(),
+        );
+        let token = address::testing::kartoffel();
+        write_denom(&mut s, &token, Denomination(0)).unwrap();
+        s.write(
+            &balance_key(&token, &address::MASP),
+            Amount::from_u64(1_000),
+        )
+        .unwrap();
+
+        let rewards = compute_masp_rewards(&s, &token).unwrap();
+        // The precision of a 0-decimal token is a whole token, for which the
+        // max reward of 0.1 / 365 per epoch is truncated to zero
+        assert_eq!(rewards.denom, Denomination(0));
+        assert_eq!(rewards.reward, (0, 1));
+        assert!(rewards.inflation.is_zero());
+
+        s.set_block_epoch(Epoch(1));
+        update_allowed_conversions(&mut s).unwrap();
     }
 
     fn test_updated_allowed_conversions_aux(
@@ -561,6 +1037,299 @@ mod tests {
         const ROUNDS: usize = 10;
 
         let mut s = TestStorage::default();
+        init_conversions_storage(&mut s, initial_balance, masp_locked_ratio);
+
+        for i in 0..ROUNDS {
+            tracing::debug!("Round {i}");
+            update_allowed_conversions(&mut s).unwrap();
+        }
+    }
+
+    /// Test that the dry-run computation of the conversion updates matches the
+    /// changes applied by `update_allowed_conversions`
+    #[test]
+    fn test_conversion_updates_report() {
+        const ROUNDS: u64 = 3;
+
+        let mut s = TestStorage::default();
+        init_conversions_storage(
+            &mut s,
+            Amount::native_whole(100_000),
+            Dec::from_str("0.5").unwrap(),
+        );
+
+        // There are no conversions in the first epoch
+        let report =
+            compute_conversion_updates(&s, s.conversion_state(), Epoch(0))
+                .unwrap();
+        assert!(report.is_none());
+
+        let native_token = s.get_native_token().unwrap();
+        let masp_balance_key = balance_key(&native_token, &address::MASP);
+        for epoch in 1..=ROUNDS {
+            let epoch = Epoch(epoch);
+            s.set_block_epoch(epoch);
+            let report =
+                compute_conversion_updates(&s, s.conversion_state(), epoch)
+                    .unwrap()
+                    .unwrap();
+            // Computing the report twice gives the same result as it doesn't
+            // modify the storage
+            assert_eq!(
+                compute_conversion_updates(&s, s.conversion_state(), epoch)
+                    .unwrap()
+                    .unwrap(),
+                report
+            );
+            assert_eq!(report.epoch, epoch);
+            assert_eq!(report.token_rewards.len(), tokens().len());
+            assert_eq!(report.token_rewards[0].token, native_token);
+
+            let pre_masp_balance: Amount =
+                s.read(&masp_balance_key).unwrap().unwrap_or_default();
+            update_allowed_conversions(&mut s).unwrap();
+
+            for rewards in &report.token_rewards {
+                let last_inflation: Amount = s
+                    .read(&masp_last_inflation_key(&rewards.token))
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(last_inflation, rewards.inflation);
+                let last_locked_amount: Amount = s
+                    .read(&masp_last_locked_amount_key(&rewards.token))
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(last_locked_amount, rewards.locked_amount);
+            }
+            let masp_balance: Amount =
+                s.read(&masp_balance_key).unwrap().unwrap_or_default();
+            assert_eq!(masp_balance, pre_masp_balance + report.total_reward);
+            assert_eq!(
+                s.conversion_state().normed_inflation,
+                report.normed_inflation
+            );
+            for ((token, denom, digit), conv) in &report.conversions {
+                let old_asset = encode_asset_type(
+                    token.clone(),
+                    *denom,
+                    *digit,
+                    Some(epoch.prev()),
+                )
+                .unwrap();
+                let (_, _, allowed_conv, _) =
+                    &s.conversion_state().assets[&old_asset];
+                assert_eq!(&MaspAmount::from(allowed_conv.clone()), conv);
+            }
+        }
+    }
+
+    /// Test that the tokens without a shielded balance get no conversions
+    #[test]
+    fn test_unshielded_tokens_skipped() {
+        let mut s = TestStorage::default();
+        init_conversions_storage(
+            &mut s,
+            Amount::native_whole(100_000),
+            Dec::zero(),
+        );
+        // Only keep three reward tokens and shield one of them
+        let (nam, btc, eth) = (
+            address::testing::nam(),
+            address::testing::btc(),
+            address::testing::eth(),
+        );
+        for token in tokens().keys() {
+            if ![&nam, &btc, &eth].contains(&token) {
+                s.delete(&masp_reward_token_key(token)).unwrap();
+            }
+        }
+        s.write(
+            &balance_key(&btc, &address::MASP),
+            Amount::native_whole(1_000),
+        )
+        .unwrap();
+
+        let digits = MaspDigitPos::iter().count();
+        let assert_assets = |s: &TestStorage, epoch: Epoch| {
+            let tree_size = s.conversion_state().tree.size();
+            for (token, denom) in [(&nam, 6), (&btc, 8), (&eth, 18)] {
+                for digit in MaspDigitPos::iter() {
+                    // Each token has a decoding entry for the current epoch
+                    let asset = encode_asset_type(
+                        token.clone(),
+                        denom.into(),
+                        digit,
+                        Some(epoch),
+                    )
+                    .unwrap();
+                    let (_, _, _, pos) = &s.conversion_state().assets[&asset];
+                    assert_eq!(*pos, tree_size);
+                }
+            }
+            // The positions of the other assets are the tree's leaves
+            let mut positions: Vec<_> = s
+                .conversion_state()
+                .assets
+                .values()
+                .map(|(_, _, _, pos)| *pos)
+                .filter(|pos| *pos != tree_size)
+                .collect();
+            positions.sort_unstable();
+            assert_eq!(positions, (0..tree_size).collect::<Vec<_>>());
+        };
+
+        for epoch in 1..=3 {
+            s.set_block_epoch(Epoch(epoch));
+            update_allowed_conversions(&mut s).unwrap();
+            assert_assets(&s, Epoch(epoch));
+            // The tree only grows with the conversions of BTC
+            assert_eq!(
+                s.conversion_state().tree.size(),
+                digits * epoch as usize
+            );
+        }
+
+        // Shield ETH in the 3rd epoch
+        s.write(
+            &balance_key(&eth, &address::MASP),
+            Amount::native_whole(1_000),
+        )
+        .unwrap();
+        s.set_block_epoch(Epoch(4));
+        let report =
+            compute_conversion_updates(&s, s.conversion_state(), Epoch(4))
+                .unwrap()
+                .unwrap();
+        assert_eq!(report.unshielded_tokens, vec![(nam.clone(), 6.into())]);
+        update_allowed_conversions(&mut s).unwrap();
+        assert_assets(&s, Epoch(4));
+        assert_eq!(s.conversion_state().tree.size(), digits * 5);
+        // The ETH of the 3rd epoch converts to the 4th epoch
+        for digit in MaspDigitPos::iter() {
+            let old_asset = encode_asset_type(
+                eth.clone(),
+                18.into(),
+                digit,
+                Some(Epoch(3)),
+            )
+            .unwrap();
+            let new_asset = encode_asset_type(
+                eth.clone(),
+                18.into(),
+                digit,
+                Some(Epoch(4)),
+            )
+            .unwrap();
+            let (_, _, conv, _) = &s.conversion_state().assets[&old_asset];
+            let conv = MaspAmount::from(conv.clone());
+            assert!(conv.get(&old_asset) < 0);
+            assert_eq!(conv.get(&new_asset), -conv.get(&old_asset));
+        }
+    }
+
+    /// Test that a token added to the reward tokens in storage receives
+    /// conversions from the next epoch
+    #[test]
+    fn test_reward_token_added_via_storage() {
+        let mut s = TestStorage::default();
+        init_conversions_storage(
+            &mut s,
+            Amount::native_whole(100_000),
+            Dec::from_str("0.5").unwrap(),
+        );
+        let btc = address::testing::btc();
+        s.delete(&masp_reward_token_key(&btc)).unwrap();
+
+        let btc_conversion = |s: &TestStorage, epoch: Epoch| {
+            let old_asset = encode_asset_type(
+                btc.clone(),
+                8.into(),
+                MaspDigitPos::Zero,
+                Some(epoch.prev()),
+            )
+            .unwrap();
+            s.conversion_state().assets.get(&old_asset).cloned()
+        };
+
+        s.set_block_epoch(Epoch(1));
+        update_allowed_conversions(&mut s).unwrap();
+        assert!(btc_conversion(&s, Epoch(1)).is_none());
+        assert!(!s.conversion_state().tokens.contains_key("btc"));
+
+        // e.g. written by a governance proposal
+        crate::write_reward_token(&mut s, "btc", &btc).unwrap();
+
+        s.set_block_epoch(Epoch(2));
+        let report =
+            compute_conversion_updates(&s, s.conversion_state(), Epoch(2))
+                .unwrap()
+                .unwrap();
+        assert!(report.token_rewards.iter().any(|r| r.token == btc));
+        update_allowed_conversions(&mut s).unwrap();
+        assert!(btc_conversion(&s, Epoch(2)).is_some());
+        assert_eq!(s.conversion_state().tokens.get("btc"), Some(&btc));
+    }
+
+    /// Test that the tokens of the conversion state keep receiving the
+    /// conversions on a chain without the reward tokens in storage
+    #[test]
+    fn test_reward_tokens_fallback_to_conversion_state() {
+        let mut s = TestStorage::default();
+        init_conversions_storage(
+            &mut s,
+            Amount::native_whole(100_000),
+            Dec::from_str("0.5").unwrap(),
+        );
+        // e.g. a chain initialized before the reward tokens were stored
+        for (token, (alias, _denom)) in tokens() {
+            s.delete(&masp_reward_token_key(&token)).unwrap();
+            s.conversion_state_mut()
+                .tokens
+                .insert(alias.to_string(), token);
+        }
+        assert!(read_reward_tokens(&s).unwrap().is_empty());
+
+        s.set_block_epoch(Epoch(1));
+        let report =
+            compute_conversion_updates(&s, s.conversion_state(), Epoch(1))
+                .unwrap()
+                .unwrap();
+        assert_eq!(report.token_rewards.len(), tokens().len());
+        update_allowed_conversions(&mut s).unwrap();
+        assert_eq!(s.conversion_state().tokens.len(), tokens().len());
+    }
+
+    /// Test that a reward token missing a parameter is skipped instead of
+    /// failing the conversions of the other tokens
+    #[test]
+    fn test_reward_token_missing_param_skipped() {
+        let mut s = TestStorage::default();
+        init_conversions_storage(
+            &mut s,
+            Amount::native_whole(100_000),
+            Dec::from_str("0.5").unwrap(),
+        );
+        let btc = address::testing::btc();
+        s.delete(&masp_kd_gain_key(&btc)).unwrap();
+
+        s.set_block_epoch(Epoch(1));
+        let report =
+            compute_conversion_updates(&s, s.conversion_state(), Epoch(1))
+                .unwrap()
+                .unwrap();
+        assert_eq!(report.token_rewards.len(), tokens().len() - 1);
+        assert!(report.token_rewards.iter().all(|r| r.token != btc));
+        assert!(report.unshielded_tokens.iter().all(|(t, _)| *t != btc));
+        update_allowed_conversions(&mut s).unwrap();
+        assert!(report.conversions.keys().all(|(token, _, _)| *token != btc));
+    }
+
+    /// Initialize the parameters and the MASP rewards of the test tokens
+    fn init_conversions_storage(
+        s: &mut TestStorage,
+        initial_balance: Amount,
+        masp_locked_ratio: Dec,
+    ) {
         let params = Parameters {
             max_tx_bytes: 1024 * 1024,
             epoch_duration: EpochDuration {
@@ -582,50 +1351,37 @@ mod tests {
             minimum_gas_price: Default::default(),
         };
 
-        // Initialize the state
-        {
-            // Parameters
-            namada_parameters::init_storage(&params, &mut s).unwrap();
-
-            // Tokens
-            let token_params = ShieldedParams {
-                max_reward_rate: Dec::from_str("0.1").unwrap(),
-                kp_gain_nom: Dec::from_str("0.1").unwrap(),
-                kd_gain_nom: Dec::from_str("0.1").unwrap(),
-                locked_amount_target: 10_000_u64,
-            };
-
-            for (token_addr, (alias, denom)) in tokens() {
-                namada_trans_token::write_params(&mut s, &token_addr).unwrap();
-                crate::write_params(&token_params, &mut s, &token_addr, &denom)
-                    .unwrap();
+        // Parameters
+        namada_parameters::init_storage(&params, s).unwrap();
 
-                write_denom(&mut s, &token_addr, denom).unwrap();
+        // Tokens
+        let token_params = ShieldedParams {
+            max_reward_rate: Dec::from_str("0.1").unwrap(),
+            kp_gain_nom: Dec::from_str("0.1").unwrap(),
+            kd_gain_nom: Dec::from_str("0.1").unwrap(),
+            locked_amount_target: 10_000_u64,
+        };
 
-                // Write a minted token balance
-                let total_token_balance = initial_balance;
-                s.write(&minted_balance_key(&token_addr), total_token_balance)
-                    .unwrap();
+        for (token_addr, (alias, denom)) in tokens() {
+            namada_trans_token::write_params(s, &token_addr).unwrap();
+            crate::write_params(&token_params, s, &token_addr, &denom).unwrap();
 
-                // Put the locked ratio into MASP
-                s.write(
-                    &balance_key(&token_addr, &address::MASP),
-                    masp_locked_ratio * total_token_balance,
-                )
+            write_denom(s, &token_addr, denom).unwrap();
+
+            // Write a minted token balance
+            let total_token_balance = initial_balance;
+            s.write(&minted_balance_key(&token_addr), total_token_balance)
                 .unwrap();
 
-                // Insert tokens into MASP conversion state
-                s.conversion_state_mut()
-                    .tokens
-                    .insert(alias.to_string(), token_addr.clone());
-            }
-        }
+            // Put the locked ratio into MASP
+            s.write(
+                &balance_key(&token_addr, &address::MASP),
+                masp_locked_ratio * total_token_balance,
+            )
+            .unwrap();
 
-        for i in 0..ROUNDS {
-            println!("Round {i}");
-            update_allowed_conversions(&mut s).unwrap();
-            println!();
-            println!();
+            // Add the tokens to the MASP reward tokens
+            crate::write_reward_token(s, alias, &token_addr).unwrap();
         }
     }
 
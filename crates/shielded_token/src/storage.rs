@@ -1,9 +1,11 @@
+use std::collections::BTreeMap;
+
 use namada_core::address::Address;
 use namada_core::token;
 use namada_core::token::Amount;
 use namada_core::uint::Uint;
 use namada_storage as storage;
-use namada_storage::{StorageRead, StorageWrite};
+use namada_storage::{iter_prefix, StorageRead, StorageWrite};
 use storage::ResultExt;
 
 use crate::storage_key::*;
@@ -37,3 +39,35 @@ where
     storage.write(&masp_locked_amount_target_key(address), raw_target)?;
     Ok(())
 }
+
+/// Add a token to the tokens receiving MASP rewards, e.g. during the genesis
+/// block. Its rewards parameters must be written with [`write_params`].
+pub fn write_reward_token<S>(
+    storage: &mut S,
+    alias: &str,
+    address: &Address,
+) -> storage::Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    storage.write(&masp_reward_token_key(address), alias.to_owned())
+}
+
+/// Read the tokens receiving MASP rewards, as a map from the alias to the
+/// address of each token
+pub fn read_reward_tokens<S>(
+    storage: &S,
+) -> storage::Result<BTreeMap<String, Address>>
+where
+    S: StorageRead,
+{
+    let mut tokens = BTreeMap::new();
+    for result in iter_prefix::<String>(storage, &masp_reward_tokens_prefix())?
+    {
+        let (key, alias) = result?;
+        if let Some(token) = is_masp_reward_token_key(&key) {
+            tokens.insert(alias, token.clone());
+        }
+    }
+    Ok(tokens)
+}
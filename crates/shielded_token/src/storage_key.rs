@@ -30,6 +30,8 @@ pub const MASP_KD_GAIN_KEY: &str = "derivative_gain";
 pub const MASP_LOCKED_AMOUNT_TARGET_KEY: &str = "locked_ratio_target";
 /// The key for the max reward rate for a given asset
 pub const MASP_MAX_REWARD_RATE_KEY: &str = "max_reward_rate";
+/// Key segment prefix for the tokens receiving MASP rewards
+pub const MASP_REWARD_TOKENS_KEY: &str = "reward_tokens";
 
 /// Obtain the nominal proportional key for the given token
 pub fn masp_kp_gain_key(token_addr: &Address) -> storage::Key {
@@ -65,6 +67,36 @@ pub fn masp_last_inflation_key(token_address: &Address) -> storage::Key {
         .with_segment(MASP_LAST_INFLATION_KEY.to_owned())
 }
 
+/// Obtain the storage key prefix of the tokens receiving MASP rewards
+pub fn masp_reward_tokens_prefix() -> storage::Key {
+    storage::Key::from(address::MASP.to_db_key())
+        .push(&MASP_REWARD_TOKENS_KEY.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Obtain the storage key of a token receiving MASP rewards, holding the
+/// alias of the token
+pub fn masp_reward_token_key(token_address: &Address) -> storage::Key {
+    masp_reward_tokens_prefix()
+        .push(&token_address.to_db_key())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Check if the given storage key is the key of a token receiving MASP
+/// rewards. Returns the token address if so.
+pub fn is_masp_reward_token_key(key: &storage::Key) -> Option<&Address> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::StringSeg(prefix),
+            DbKeySeg::AddressSeg(token),
+        ] if *addr == address::MASP && prefix == MASP_REWARD_TOKENS_KEY => {
+            Some(token)
+        }
+        _ => None,
+    }
+}
+
 /// Check if the given storage key is a masp key
 pub fn is_masp_key(key: &storage::Key) -> bool {
     matches!(&key.segments[..],
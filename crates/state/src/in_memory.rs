@@ -69,6 +69,28 @@ where
     pub eth_events_queue: EthEventsQueue,
     /// How many block heights in the past can the storage be queried
     pub storage_read_past_height_limit: Option<u64>,
+    /// The retention of the merkle tree stores
+    pub merkle_tree_pruning: PruningConfig,
+    /// The oldest height whose state can be read from the diffs, if any
+    /// diffs have been pruned
+    pub diffs_oldest_height: Option<BlockHeight>,
+}
+
+/// The retention of the provable merkle tree stores in epochs by key-space.
+/// When a retention isn't set, the stores are retained for the
+/// `storage_read_past_height_limit`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PruningConfig {
+    /// The number of past epochs for which the IBC subtree stores are
+    /// retained to prove the IBC state at past heights
+    pub ibc_retention_epochs: Option<u64>,
+    /// The number of past epochs for which the other provable subtree stores
+    /// are retained
+    pub general_retention_epochs: Option<u64>,
+    /// The number of past blocks for which the diffs of the subspace are
+    /// retained to read the state at past heights. When it isn't set, the
+    /// diffs are never pruned.
+    pub diff_retention_blocks: Option<u64>,
 }
 
 /// Last committed block
@@ -145,6 +167,8 @@ where
             ethereum_height: None,
             eth_events_queue: EthEventsQueue::default(),
             storage_read_past_height_limit,
+            merkle_tree_pruning: PruningConfig::default(),
+            diffs_oldest_height: None,
         }
     }
 
@@ -283,17 +307,39 @@ where
             .unwrap_or_default()
     }
 
-    /// Get the oldest epoch where we can read a value
-    pub fn get_oldest_epoch(&self) -> Epoch {
+    /// Get the oldest height where we can read a value
+    pub fn get_oldest_height(&self) -> BlockHeight {
         let oldest_height = match self.storage_read_past_height_limit {
             Some(limit) if limit < self.get_last_block_height().0 => {
                 (self.get_last_block_height().0 - limit).into()
             }
             _ => BlockHeight(1),
         };
+        // The older diffs may have been pruned
+        match self.diffs_oldest_height {
+            Some(diffs_oldest_height) => oldest_height.max(diffs_oldest_height),
+            None => oldest_height,
+        }
+    }
+
+    /// Get the oldest epoch where we can read a value
+    pub fn get_oldest_epoch(&self) -> Epoch {
         self.block
             .pred_epochs
-            .get_epoch(oldest_height)
+            .get_epoch(self.get_oldest_height())
             .unwrap_or_default()
     }
+
+    /// Get the oldest epoch of the merkle tree stores retained for the given
+    /// number of past epochs. When it's not set, it's the oldest epoch where
+    /// we can read a value.
+    pub fn get_oldest_retained_epoch(
+        &self,
+        retention_epochs: Option<u64>,
+    ) -> Epoch {
+        match retention_epochs {
+            Some(epochs) => Epoch(self.block.epoch.0.saturating_sub(epochs)),
+            None => self.get_oldest_epoch(),
+        }
+    }
 }
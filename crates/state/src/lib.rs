@@ -2,6 +2,7 @@
 
 mod host_env;
 mod in_memory;
+mod read_cache;
 mod wl_state;
 pub mod write_log;
 
@@ -9,7 +10,7 @@ use std::fmt::Debug;
 use std::iter::Peekable;
 
 pub use host_env::{TxHostEnvState, VpHostEnvState};
-pub use in_memory::{BlockStorage, InMemory, LastBlock};
+pub use in_memory::{BlockStorage, InMemory, LastBlock, PruningConfig};
 use namada_core::address::Address;
 use namada_core::eth_bridge_pool::is_pending_transfer_key;
 pub use namada_core::hash::Sha256Hasher;
@@ -36,11 +37,13 @@ pub use namada_storage::conversion_state::{
 pub use namada_storage::types::{KVBytes, PrefixIterator};
 pub use namada_storage::{
     collections, iter_prefix, iter_prefix_bytes, iter_prefix_with_filter,
-    mockdb, tx_queue, BlockStateRead, BlockStateWrite, DBIter, DBWriteBatch,
-    DbError, DbResult, Error as StorageError, OptionExt,
+    mockdb, tx_queue, BlockStateRead, BlockStateWrite, ColumnFamilyStats,
+    DBIter, DBStats, DBWriteBatch, DbError, DbResult, DiffOp, DiffsIterator,
+    Error as StorageError, IterOpts, OptionExt,
     Result as StorageResult, ResultExt, StorageHasher, StorageRead,
     StorageWrite, DB,
 };
+pub use read_cache::{is_block_cached_key, BlockReadCache};
 use thiserror::Error;
 pub use wl_state::{FullAccessState, TempWlState, WlState};
 use write_log::WriteLog;
@@ -52,6 +55,9 @@ pub type Result<T> = std::result::Result<T, Error>;
 /// it has 2 blocks delay on validator set update.
 pub const EPOCH_SWITCH_BLOCKS_DELAY: u32 = 2;
 
+/// The maximum number of block headers that can be read at once
+pub const MAX_BLOCK_HEADERS_RANGE: u64 = 100;
+
 /// Common trait for read-only access to write log, DB and in-memory state.
 pub trait StateRead: StorageRead + Debug {
     /// DB type
@@ -70,6 +76,11 @@ pub trait StateRead: StorageRead + Debug {
 
     fn charge_gas(&self, gas: u64) -> Result<()>;
 
+    /// Borrow the block-scoped cache of the committed storage reads, if any
+    fn read_cache(&self) -> Option<&BlockReadCache> {
+        None
+    }
+
     /// Check if the given key is present in storage. Returns the result and the
     /// gas cost.
     fn db_has_key(&self, key: &storage::Key) -> Result<(bool, u64)> {
@@ -79,20 +90,69 @@ pub trait StateRead: StorageRead + Debug {
         ))
     }
 
-    /// Returns a value from the specified subspace and the gas cost
+    /// Returns a value from the specified subspace and the gas cost. The
+    /// parameters are read through the block read cache, if any, but the gas
+    /// cost is the same as reading them from the DB.
     fn db_read(&self, key: &storage::Key) -> Result<(Option<Vec<u8>>, u64)> {
-        tracing::debug!("storage read key {}", key);
+        let read_db = || -> Result<(Option<Vec<u8>>, u64)> {
+            tracing::debug!("storage read key {}", key);
 
-        match self.db().read_subspace_val(key)? {
-            Some(v) => {
-                let gas =
-                    (key.len() + v.len()) as u64 * STORAGE_ACCESS_GAS_PER_BYTE;
-                Ok((Some(v), gas))
+            match self.db().read_subspace_val(key)? {
+                Some(v) => {
+                    let gas = (key.len() + v.len()) as u64
+                        * STORAGE_ACCESS_GAS_PER_BYTE;
+                    Ok((Some(v), gas))
+                }
+                None => {
+                    Ok((None, key.len() as u64 * STORAGE_ACCESS_GAS_PER_BYTE))
+                }
             }
-            None => Ok((None, key.len() as u64 * STORAGE_ACCESS_GAS_PER_BYTE)),
+        };
+        match self.read_cache() {
+            Some(read_cache) => read_cache.read(key, read_db),
+            None => read_db(),
         }
     }
 
+    /// Returns a value from the specified subspace as of the given past height
+    /// and the gas cost. The height must be within the retained diffs, i.e. not
+    /// older than the `storage_read_past_height_limit`, and not past the last
+    /// committed height. Note that the diffs of the keys excluded from the
+    /// merkle tree are only retained at the last height where they were
+    /// changed.
+    fn db_read_at_height(
+        &self,
+        key: &storage::Key,
+        height: BlockHeight,
+    ) -> Result<(Option<Vec<u8>>, u64)> {
+        let last_height = self.in_mem().get_last_block_height();
+        if height > last_height {
+            return Err(Error::BlockHeightPastLast {
+                height,
+                last_height,
+            });
+        }
+        let oldest_height = self.in_mem().get_oldest_height();
+        if height < oldest_height {
+            return Err(Error::BlockHeightTooOld {
+                height,
+                oldest_height,
+            });
+        }
+        if height == last_height {
+            return self.db_read(key);
+        }
+
+        tracing::debug!("storage read key {} at height {}", key, height);
+        let value = self.db().read_subspace_val_with_height(
+            key,
+            height,
+            last_height,
+        )?;
+        let len = key.len() + value.as_ref().map(Vec::len).unwrap_or_default();
+        Ok((value, len as u64 * STORAGE_ACCESS_GAS_PER_BYTE))
+    }
+
     /// WARNING: This only works for values that have been committed to DB.
     /// To be able to see values written or deleted, but not yet committed,
     /// use the `StorageWithWriteLog`.
@@ -108,6 +168,17 @@ pub trait StateRead: StorageRead + Debug {
         )
     }
 
+    /// Returns a prefix iterator of the key-vals posterior to the current tx,
+    /// merged from the write log and the DB, in the order, from the start and
+    /// up to the limit of the given options, and the gas cost.
+    fn iter_prefix_opts(
+        &self,
+        prefix: &Key,
+        opts: &IterOpts,
+    ) -> (PrefixIter<'_, Self::D>, u64) {
+        iter_prefix_post_opts(self.write_log(), self.db(), prefix, opts)
+    }
+
     /// Returns an iterator over the block results
     fn db_iter_results(&self) -> (<Self::D as DBIter<'_>>::PrefixIter, u64) {
         (self.db().iter_results(), 0)
@@ -163,6 +234,51 @@ pub trait StateRead: StorageRead + Debug {
             }
         }
     }
+
+    /// Get the committed block headers with the heights in the given
+    /// inclusive range and the gas cost. The range can't be longer than
+    /// [`MAX_BLOCK_HEADERS_RANGE`], nor go past the last committed height or
+    /// before the oldest height that can be read.
+    fn get_block_headers(
+        &self,
+        from: BlockHeight,
+        to: BlockHeight,
+    ) -> Result<(Vec<(BlockHeight, Header)>, u64)> {
+        if from > to {
+            return Err(Error::InvalidBlockHeightRange { from, to });
+        }
+        if to.0 - from.0 >= MAX_BLOCK_HEADERS_RANGE {
+            return Err(Error::BlockHeightRangeTooLong {
+                from,
+                to,
+                max: MAX_BLOCK_HEADERS_RANGE,
+            });
+        }
+        let last_height = self.in_mem().get_last_block_height();
+        if to > last_height {
+            return Err(Error::BlockHeightPastLast {
+                height: to,
+                last_height,
+            });
+        }
+        let oldest_height = self.in_mem().get_oldest_height();
+        if from < oldest_height {
+            return Err(Error::BlockHeightTooOld {
+                height: from,
+                oldest_height,
+            });
+        }
+
+        let headers = self.db().read_block_headers(from, to)?;
+        let gas = headers
+            .iter()
+            .map(|(_, header)| {
+                header.encoded_len() as u64 * STORAGE_ACCESS_GAS_PER_BYTE
+            })
+            .sum::<u64>()
+            .max(STORAGE_ACCESS_GAS_PER_BYTE);
+        Ok((headers, gas))
+    }
 }
 
 /// Common trait for write log, DB and in-memory state.
@@ -380,6 +496,9 @@ macro_rules! impl_storage_write_by_protocol {
                     .write_log_mut()
                     .protocol_write(key, val.as_ref().to_vec())
                     .into_storage_result()?;
+                if let Some(read_cache) = self.read_cache() {
+                    read_cache.invalidate(key);
+                }
                 Ok(())
             }
 
@@ -388,6 +507,33 @@ macro_rules! impl_storage_write_by_protocol {
                     .write_log_mut()
                     .protocol_delete(key)
                     .into_storage_result()?;
+                if let Some(read_cache) = self.read_cache() {
+                    read_cache.invalidate(key);
+                }
+                Ok(())
+            }
+
+            fn delete_prefix(
+                &mut self,
+                prefix: &storage::Key,
+            ) -> namada_storage::Result<()> {
+                // The prefix of an address may match its validity predicate,
+                // which cannot be deleted, so its keys are deleted one by one
+                if prefix.segments.len() < 2 {
+                    let keys = namada_storage::iter_prefix_bytes(self, prefix)?
+                        .map(|res| res.map(|(key, _val)| key))
+                        .collect::<namada_storage::Result<Vec<_>>>()?;
+                    for key in keys {
+                        if key.is_validity_predicate().is_none() {
+                            self.delete(&key)?;
+                        }
+                    }
+                } else {
+                    self.write_log_mut().protocol_delete_prefix(prefix);
+                    if let Some(read_cache) = self.read_cache() {
+                        read_cache.invalidate_prefix(prefix);
+                    }
+                }
                 Ok(())
             }
         }
@@ -428,6 +574,33 @@ pub enum Error {
     BorshCodingError(std::io::Error),
     #[error("Merkle tree at the height {height} is not stored")]
     NoMerkleTree { height: BlockHeight },
+    #[error("Invalid block height range from {from} to {to}")]
+    InvalidBlockHeightRange { from: BlockHeight, to: BlockHeight },
+    #[error(
+        "The block height range from {from} to {to} is longer than the \
+         maximum of {max} blocks"
+    )]
+    BlockHeightRangeTooLong {
+        from: BlockHeight,
+        to: BlockHeight,
+        max: u64,
+    },
+    #[error(
+        "The block height {height} is past the last committed height \
+         {last_height}"
+    )]
+    BlockHeightPastLast {
+        height: BlockHeight,
+        last_height: BlockHeight,
+    },
+    #[error(
+        "The block height {height} is older than the oldest height that can \
+         be read {oldest_height}"
+    )]
+    BlockHeightTooOld {
+        height: BlockHeight,
+        oldest_height: BlockHeight,
+    },
     #[error("Code hash error: {0}")]
     InvalidCodeHash(HashError),
     #[error("DB error: {0}")]
@@ -454,6 +627,14 @@ where
     pub storage_iter: Peekable<<D as DBIter<'iter>>::PrefixIter>,
     /// Peekable write log iterator
     pub write_log_iter: Peekable<write_log::PrefixIter>,
+    /// The prefixes of the storage keys deleted at block-level, whose items
+    /// from the storage iterator are skipped
+    pub deleted_prefixes: Vec<String>,
+    /// Iterate in the descending order of the storage keys. Both of the
+    /// iterators have to be in this order.
+    pub reverse: bool,
+    /// The number of the remaining key-vals to be iterated, if limited
+    pub remaining: Option<usize>,
 }
 
 /// Iterate write-log storage items prior to a tx execution, matching the
@@ -469,15 +650,23 @@ pub fn iter_prefix_pre<'a, D>(
 where
     D: DB + for<'iter> DBIter<'iter>,
 {
-    let storage_iter = db.iter_prefix(Some(prefix)).peekable();
-    let write_log_iter = write_log.iter_prefix_pre(prefix).peekable();
-    (
-        PrefixIter::<D> {
-            storage_iter,
-            write_log_iter,
-        },
-        prefix.len() as u64 * namada_gas::STORAGE_ACCESS_GAS_PER_BYTE,
-    )
+    iter_prefix_pre_opts(write_log, db, prefix, &IterOpts::default())
+}
+
+/// Iterate write-log storage items prior to a tx execution, matching the
+/// given prefix, in the order, from the start and up to the limit of the given
+/// options. Returns the iterator and gas cost.
+pub fn iter_prefix_pre_opts<'a, D>(
+    write_log: &'a WriteLog,
+    db: &'a D,
+    prefix: &storage::Key,
+    opts: &IterOpts,
+) -> (PrefixIter<'a, D>, u64)
+where
+    D: DB + for<'iter> DBIter<'iter>,
+{
+    let write_log_iter = write_log.iter_prefix_pre_opts(prefix, opts);
+    merge_prefix_iters(write_log, db, prefix, opts, write_log_iter)
 }
 
 /// Iterate write-log storage items posterior to a tx execution, matching the
@@ -493,12 +682,51 @@ pub fn iter_prefix_post<'a, D>(
 where
     D: DB + for<'iter> DBIter<'iter>,
 {
-    let storage_iter = db.iter_prefix(Some(prefix)).peekable();
-    let write_log_iter = write_log.iter_prefix_post(prefix).peekable();
+    iter_prefix_post_opts(write_log, db, prefix, &IterOpts::default())
+}
+
+/// Iterate write-log storage items posterior to a tx execution, matching the
+/// given prefix, in the order, from the start and up to the limit of the given
+/// options. Returns the iterator and gas cost.
+pub fn iter_prefix_post_opts<'a, D>(
+    write_log: &'a WriteLog,
+    db: &'a D,
+    prefix: &storage::Key,
+    opts: &IterOpts,
+) -> (PrefixIter<'a, D>, u64)
+where
+    D: DB + for<'iter> DBIter<'iter>,
+{
+    let write_log_iter = write_log.iter_prefix_post_opts(prefix, opts);
+    merge_prefix_iters(write_log, db, prefix, opts, write_log_iter)
+}
+
+/// Make the iterator merging the given write-log iterator with the storage
+/// iterator of the same prefix and options. The limit is applied only to the
+/// merged items since the deleted keys in the write log cancel out the items
+/// from the storage.
+fn merge_prefix_iters<'a, D>(
+    write_log: &'a WriteLog,
+    db: &'a D,
+    prefix: &storage::Key,
+    opts: &IterOpts,
+    write_log_iter: write_log::PrefixIter,
+) -> (PrefixIter<'a, D>, u64)
+where
+    D: DB + for<'iter> DBIter<'iter>,
+{
+    let storage_opts = IterOpts {
+        limit: None,
+        ..opts.clone()
+    };
+    let storage_iter = db.iter_prefix_opts(Some(prefix), &storage_opts);
     (
         PrefixIter::<D> {
-            storage_iter,
-            write_log_iter,
+            storage_iter: storage_iter.peekable(),
+            write_log_iter: write_log_iter.peekable(),
+            deleted_prefixes: write_log.deleted_db_prefixes(),
+            reverse: opts.reverse,
+            remaining: opts.limit,
         },
         prefix.len() as u64 * namada_gas::STORAGE_ACCESS_GAS_PER_BYTE,
     )
@@ -511,6 +739,25 @@ where
     type Item = (String, Vec<u8>, u64);
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+        let next = self.next_merged();
+        if let (Some(remaining), Some(_)) = (self.remaining.as_mut(), &next) {
+            *remaining -= 1;
+        }
+        next
+    }
+}
+
+impl<'iter, D> PrefixIter<'iter, D>
+where
+    D: DB + DBIter<'iter>,
+{
+    /// Get the next item from the write log or the storage, whichever comes
+    /// first in the iteration order. An item from the write log takes
+    /// precedence over the one of the same key from the storage.
+    fn next_merged(&mut self) -> Option<(String, Vec<u8>, u64)> {
         enum Next {
             ReturnWl { advance_storage: bool },
             ReturnStorage,
@@ -531,7 +778,12 @@ where
                         what = Next::ReturnStorage;
                     }
                     (Some((storage_key, _, _)), Some((wl_key, _))) => {
-                        if wl_key <= storage_key {
+                        let wl_first = if self.reverse {
+                            wl_key >= storage_key
+                        } else {
+                            wl_key <= storage_key
+                        };
+                        if wl_first {
                             what = Next::ReturnWl {
                                 advance_storage: wl_key == storage_key,
                             };
@@ -570,6 +822,14 @@ where
                 }
                 Next::ReturnStorage => {
                     if let Some(next) = self.storage_iter.next() {
+                        let (key, _, _) = &next;
+                        if self
+                            .deleted_prefixes
+                            .iter()
+                            .any(|prefix| key.starts_with(prefix))
+                        {
+                            continue;
+                        }
                         return Some(next);
                     }
                 }
@@ -599,6 +859,7 @@ pub mod testing {
                 db: MockDB::default(),
                 in_mem: Default::default(),
                 merkle_tree_key_filter: merklize_all_keys,
+                read_cache: Default::default(),
             })
         }
     }
@@ -648,9 +909,11 @@ mod tests {
     use std::collections::BTreeMap;
 
     use chrono::{TimeZone, Utc};
+    use ics23::HostFunctionsManager;
     use namada_core::address::InternalAddress;
     use namada_core::borsh::{BorshDeserialize, BorshSerializeExt};
     use namada_core::dec::Dec;
+    use namada_core::ibc::core::commitment_types::merkle::MerkleProof;
     use namada_core::storage::DbKeySeg;
     use namada_core::time::{self, DateTimeUtc, Duration};
     use namada_core::token;
@@ -1154,7 +1417,145 @@ mod tests {
             read_post.insert(key, val);
         }
         dbg!(keys_to_string(&expected_post), keys_to_string(&read_post));
-        itertools::assert_equal(expected_post, read_post);
+        itertools::assert_equal(expected_post.clone(), read_post);
+
+        // The posterior key-vals in the iteration order of the storage keys
+        let mut expected_post: Vec<(String, i8)> = expected_post
+            .into_iter()
+            .map(|(key, val)| (key.to_string(), val))
+            .collect();
+        expected_post.sort();
+
+        // Check the reverse iteration
+        let read_rev = read_post_opts(
+            &s,
+            IterOpts {
+                reverse: true,
+                ..Default::default()
+            },
+        );
+        itertools::assert_equal(expected_post.iter().rev().cloned(), read_rev);
+
+        // Check the pagination in both directions
+        for reverse in [false, true] {
+            let read_pages = read_post_pages(&s, reverse, 3);
+            if reverse {
+                itertools::assert_equal(
+                    expected_post.iter().rev().cloned(),
+                    read_pages,
+                );
+            } else {
+                itertools::assert_equal(expected_post.iter().cloned(), read_pages);
+            }
+        }
+    }
+
+    /// Check that the storage key-vals overwritten and deleted in the write
+    /// log are iterated exactly once in both directions and across pages
+    #[test]
+    fn test_prefix_iter_opts_with_write_log() {
+        let mut s = TestState::default();
+        let key = |key: &str| storage::Key::parse(key).unwrap();
+
+        for (k, val) in [("a", 1_i8), ("b", 2), ("c", 3), ("d", 4)] {
+            s.db_write(&key(k), val.serialize_to_vec()).unwrap();
+        }
+        // Overwrite and delete at block-level
+        s.write_log_mut()
+            .protocol_write(&key("b"), 20_i8.serialize_to_vec())
+            .unwrap();
+        s.delete(&key("c")).unwrap();
+        // Write a new key and delete at tx-level
+        s.write_log_mut()
+            .write(&key("e"), 5_i8.serialize_to_vec())
+            .unwrap();
+        s.write_log_mut().delete(&key("a")).unwrap();
+
+        let expected = vec![
+            ("b".to_string(), 20_i8),
+            ("d".to_string(), 4),
+            ("e".to_string(), 5),
+        ];
+        let read = read_post_opts(&s, IterOpts::default());
+        assert_eq!(read, expected);
+        let read = read_post_opts(
+            &s,
+            IterOpts {
+                reverse: true,
+                ..Default::default()
+            },
+        );
+        itertools::assert_equal(expected.iter().rev().cloned(), read);
+
+        // Resume after a key that has been deleted
+        let read = read_post_opts(
+            &s,
+            IterOpts {
+                reverse: true,
+                start_after: Some(key("c")),
+                limit: Some(1),
+            },
+        );
+        assert_eq!(read, vec![("b".to_string(), 20)]);
+        let read = read_post_opts(
+            &s,
+            IterOpts {
+                reverse: false,
+                start_after: Some(key("c")),
+                limit: Some(1),
+            },
+        );
+        assert_eq!(read, vec![("d".to_string(), 4)]);
+
+        for limit in 1..=4 {
+            let read = read_post_pages(&s, false, limit);
+            assert_eq!(read, expected);
+            let read = read_post_pages(&s, true, limit);
+            itertools::assert_equal(expected.iter().rev().cloned(), read);
+        }
+    }
+
+    /// Collect the posterior key-vals of all the keys with the given options
+    fn read_post_opts(s: &TestState, opts: IterOpts) -> Vec<(String, i8)> {
+        let (iter, _gas) = iter_prefix_post_opts(
+            s.write_log(),
+            s.db(),
+            &storage::Key::default(),
+            &opts,
+        );
+        iter.map(|(key, val, _gas)| {
+            (key, BorshDeserialize::try_from_slice(&val).unwrap())
+        })
+        .collect()
+    }
+
+    /// Collect the posterior key-vals of all the keys page by page, resuming
+    /// after the last key of the previous page
+    fn read_post_pages(
+        s: &TestState,
+        reverse: bool,
+        limit: usize,
+    ) -> Vec<(String, i8)> {
+        let mut read = vec![];
+        let mut start_after = None;
+        loop {
+            let page = read_post_opts(
+                s,
+                IterOpts {
+                    reverse,
+                    start_after,
+                    limit: Some(limit),
+                },
+            );
+            assert!(page.len() <= limit);
+            match page.last() {
+                Some((key, _)) => {
+                    start_after = Some(storage::Key::parse(key).unwrap())
+                }
+                None => return read,
+            }
+            read.extend(page);
+        }
     }
 
     fn apply_to_state(s: &mut TestState, kvs: &[KeyVal<i8>]) {
@@ -1337,4 +1738,98 @@ mod tests {
             },
         )
     }
+
+    /// Test the round-trip of the commitment proofs of the existence and the
+    /// non-existence of the keys in the IBC and the account sub trees
+    #[test]
+    fn test_commitment_proofs() {
+        let mut state = TestState::default();
+        let ibc_prefix: Key =
+            Address::Internal(InternalAddress::Ibc).to_db_key().into();
+        let ibc_key = ibc_prefix.push(&"key".to_string()).unwrap();
+        let ibc_non_key = ibc_prefix.push(&"non_key".to_string()).unwrap();
+        let account_key = test_key_1();
+        let account_non_key = test_key_2();
+
+        let height = BlockHeight(1);
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), height)
+            .unwrap();
+        state.db_write(&ibc_key, [1u8; 8]).unwrap();
+        state.db_write(&account_key, [2u8; 8]).unwrap();
+        state.commit_block().unwrap();
+        let root = state.in_mem().merkle_root().0.to_vec();
+
+        let ibc_specs = ics23_specs::ibc_proof_specs::<Sha256Hasher>();
+        let specs = ics23_specs::proof_specs::<Sha256Hasher>();
+        for (key, specs) in [(&ibc_key, &ibc_specs), (&account_key, &specs)] {
+            let proof =
+                state.get_existence_commitment_proof(key, height).unwrap();
+            let MerkleProof { proofs } = MerkleProof::try_from(&proof).unwrap();
+            let (store_type, sub_key) = StoreType::sub_key(key).unwrap();
+            let (value, _gas) = state.db_read(key).unwrap();
+            let ics23::commitment_proof::Proof::Exist(ep) =
+                proofs[0].proof.clone().unwrap()
+            else {
+                panic!("The existence proof is expected");
+            };
+            let sub_root =
+                ics23::calculate_existence_root::<HostFunctionsManager>(&ep)
+                    .unwrap();
+            assert!(ics23::verify_membership::<HostFunctionsManager>(
+                &proofs[0],
+                &specs[0],
+                &sub_root,
+                sub_key.to_string().as_bytes(),
+                &value.unwrap(),
+            ));
+            assert!(ics23::verify_membership::<HostFunctionsManager>(
+                &proofs[1],
+                &specs[1],
+                &root,
+                store_type.to_string().as_bytes(),
+                &sub_root,
+            ));
+        }
+
+        for key in [&ibc_non_key, &account_non_key] {
+            let proof = state
+                .get_non_existence_commitment_proof(key, height)
+                .unwrap();
+            let MerkleProof { proofs } = MerkleProof::try_from(&proof).unwrap();
+            let (store_type, _) = StoreType::sub_key(key).unwrap();
+            let ics23::commitment_proof::Proof::Nonexist(nep) =
+                proofs[0].proof.clone().unwrap()
+            else {
+                panic!("The non-existence proof is expected");
+            };
+            let neighbor = nep.left.or(nep.right).unwrap();
+            let sub_root = ics23::calculate_existence_root::<
+                HostFunctionsManager,
+            >(&neighbor)
+            .unwrap();
+            let non_key =
+                merkle_tree::non_existence_proof_key::<Sha256Hasher>(key)
+                    .unwrap();
+            assert!(ics23::verify_non_membership::<HostFunctionsManager>(
+                &proofs[0],
+                &ibc_specs[0],
+                &sub_root,
+                &non_key,
+            ));
+            assert!(ics23::verify_membership::<HostFunctionsManager>(
+                &proofs[1],
+                &ibc_specs[1],
+                &root,
+                store_type.to_string().as_bytes(),
+                &sub_root,
+            ));
+        }
+
+        // A key without a value can't be proven to exist
+        let result =
+            state.get_existence_commitment_proof(&account_non_key, height);
+        assert!(matches!(result, Err(Error::UnknownKey { .. })));
+    }
 }
@@ -0,0 +1,92 @@
+//! A block-scoped read-through cache of the committed values of the
+//! parameters, which are read by most of the transactions and native VPs.
+//!
+//! The committed storage only changes when a block is committed, so the
+//! entries are valid until then. The values written within the block are read
+//! from the write log before the committed storage, but a protocol write to a
+//! cached key still drops its entry. The gas is charged on every access as if
+//! the value had been read from the storage.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use namada_core::address::{Address, InternalAddress};
+use namada_core::storage::{DbKeySeg, Key};
+
+/// The storage key segment of the IBC parameters
+const IBC_PARAMS_SEG: &str = "params";
+
+/// Check if the value of the key is cached for a block. These are the protocol
+/// parameters and the parameters of the governance and the IBC, which can only
+/// be changed by the protocol.
+pub fn is_block_cached_key(key: &Key) -> bool {
+    let [DbKeySeg::AddressSeg(Address::Internal(addr)), rest @ ..] =
+        &key.segments[..]
+    else {
+        return false;
+    };
+    match (addr, rest) {
+        (InternalAddress::Parameters, _) => true,
+        // The governance parameters are stored right under its address, while
+        // the proposals are nested deeper
+        (InternalAddress::Governance, [DbKeySeg::StringSeg(_)]) => true,
+        (InternalAddress::Ibc, [DbKeySeg::StringSeg(seg)]) => {
+            seg == IBC_PARAMS_SEG
+        }
+        _ => false,
+    }
+}
+
+/// In-memory cache of the committed storage reads of the parameters
+#[derive(Debug, Default)]
+pub struct BlockReadCache {
+    entries: Mutex<HashMap<Key, (Option<Vec<u8>>, u64)>>,
+}
+
+impl BlockReadCache {
+    /// Get the value of the key with its gas cost from the cache, or read it
+    /// with the given function and cache it if it's a parameter. The lock
+    /// isn't held while reading, so concurrent VPs missing the same key may
+    /// read it more than once.
+    pub fn read<E>(
+        &self,
+        key: &Key,
+        read_storage: impl FnOnce() -> Result<(Option<Vec<u8>>, u64), E>,
+    ) -> Result<(Option<Vec<u8>>, u64), E> {
+        if !is_block_cached_key(key) {
+            return read_storage();
+        }
+        if let Some(entry) = self.entries.lock().unwrap().get(key) {
+            return Ok(entry.clone());
+        }
+        let (value, gas) = read_storage()?;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.clone(), (value.clone(), gas));
+        Ok((value, gas))
+    }
+
+    /// Check if the value of the key is cached
+    pub fn contains(&self, key: &Key) -> bool {
+        self.entries.lock().unwrap().contains_key(key)
+    }
+
+    /// Drop the entry of the key, if any
+    pub fn invalidate(&self, key: &Key) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    /// Drop the entries of the keys with the given prefix
+    pub fn invalidate_prefix(&self, prefix: &Key) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|key, _| !key.starts_with(prefix));
+    }
+
+    /// Drop all the entries when the committed storage changes
+    pub fn clear(&mut self) {
+        self.entries.get_mut().unwrap().clear();
+    }
+}
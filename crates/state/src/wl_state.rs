@@ -4,6 +4,7 @@ use std::ops::{Deref, DerefMut};
 use namada_core::address::Address;
 use namada_core::borsh::BorshSerializeExt;
 use namada_core::chain::ChainId;
+use namada_core::ibc::core::commitment_types::commitment::CommitmentProofBytes;
 use namada_core::storage;
 use namada_core::time::DateTimeUtc;
 use namada_parameters::EpochDuration;
@@ -11,16 +12,16 @@ use namada_replay_protection as replay_protection;
 use namada_storage::conversion_state::{ConversionState, WithConversionState};
 use namada_storage::{BlockHeight, BlockStateRead, BlockStateWrite, ResultExt};
 
-use crate::in_memory::InMemory;
+use crate::in_memory::{InMemory, PruningConfig};
 use crate::write_log::{
     self, ReProtStorageModification, StorageModification, WriteLog,
 };
 use crate::{
-    is_pending_transfer_key, DBIter, Epoch, Error, Hash, Key, LastBlock,
-    MembershipProof, MerkleTree, MerkleTreeError, ProofOps, Result, State,
-    StateRead, StorageHasher, StorageResult, StoreType, DB,
-    EPOCH_SWITCH_BLOCKS_DELAY, STORAGE_ACCESS_GAS_PER_BYTE,
-    STORAGE_WRITE_GAS_PER_BYTE,
+    is_pending_transfer_key, merkle_tree, BlockReadCache, DBIter,
+    DiffsIterator, Epoch, Error, Hash, Key, LastBlock, MembershipProof,
+    MerkleTree, MerkleTreeError, ProofOps, Result, State, StateRead,
+    StorageHasher, StorageResult, StoreType, DB, EPOCH_SWITCH_BLOCKS_DELAY,
+    STORAGE_ACCESS_GAS_PER_BYTE, STORAGE_WRITE_GAS_PER_BYTE,
 };
 
 /// Owned state with full R/W access.
@@ -47,6 +48,8 @@ where
     pub(crate) in_mem: InMemory<H>,
     /// Static merkle tree storage key filter
     pub merkle_tree_key_filter: fn(&storage::Key) -> bool,
+    /// Cache of the parameters read from the DB, cleared when the DB changes
+    pub(crate) read_cache: BlockReadCache,
 }
 
 /// State with a temporary write log. This is used for dry-running txs and ABCI
@@ -63,6 +66,8 @@ where
     pub(crate) db: &'a D,
     /// State
     pub(crate) in_mem: &'a InMemory<H>,
+    /// Cache of the parameters read from the DB
+    pub(crate) read_cache: &'a BlockReadCache,
 }
 
 impl<D, H> FullAccessState<D, H>
@@ -79,6 +84,8 @@ where
     }
 
     pub fn db_mut(&mut self) -> &mut D {
+        // The DB may be changed directly, so the cached reads are dropped
+        self.0.read_cache.clear();
         &mut self.0.db
     }
 
@@ -90,6 +97,8 @@ where
         &self.0
     }
 
+    /// Open the state. The merkle tree stores are retained for the
+    /// `storage_read_past_height_limit`.
     pub fn open(
         db_path: impl AsRef<std::path::Path>,
         cache: Option<&D::Cache>,
@@ -97,24 +106,69 @@ where
         native_token: Address,
         storage_read_past_height_limit: Option<u64>,
         merkle_tree_key_filter: fn(&storage::Key) -> bool,
+    ) -> Self {
+        Self::open_with_pruning(
+            db_path,
+            cache,
+            chain_id,
+            native_token,
+            storage_read_past_height_limit,
+            PruningConfig::default(),
+            merkle_tree_key_filter,
+        )
+    }
+
+    /// Open the state with the given retention of the merkle tree stores by
+    /// key-space and of the diffs
+    pub fn open_with_pruning(
+        db_path: impl AsRef<std::path::Path>,
+        cache: Option<&D::Cache>,
+        chain_id: ChainId,
+        native_token: Address,
+        storage_read_past_height_limit: Option<u64>,
+        merkle_tree_pruning: PruningConfig,
+        merkle_tree_key_filter: fn(&storage::Key) -> bool,
     ) -> Self {
         let write_log = WriteLog::default();
-        let db = D::open(db_path, cache);
-        let in_mem = InMemory::new(
+        // The diffs are retained at least for the heights that can be read
+        let diff_retention_blocks = merkle_tree_pruning
+            .diff_retention_blocks
+            .map(|retention| match storage_read_past_height_limit {
+                Some(limit) => retention.max(limit),
+                None => retention,
+            });
+        let merkle_tree_pruning = PruningConfig {
+            diff_retention_blocks,
+            ..merkle_tree_pruning
+        };
+        let db = D::open(db_path, cache, diff_retention_blocks);
+        let mut in_mem = InMemory::new(
             chain_id,
             native_token,
             storage_read_past_height_limit,
         );
+        in_mem.merkle_tree_pruning = merkle_tree_pruning;
         let mut state = Self(WlState {
             write_log,
             db,
             in_mem,
             merkle_tree_key_filter,
+            read_cache: BlockReadCache::default(),
         });
         state.load_last_state();
         state
     }
 
+    /// Reject the tx modifications under the given prefixes reserved for the
+    /// protocol
+    pub fn with_reserved_prefixes(
+        mut self,
+        reserved_prefixes: storage::ReservedPrefixes,
+    ) -> Self {
+        self.0.write_log.reserved_prefixes = reserved_prefixes;
+        self
+    }
+
     #[allow(dead_code)]
     /// Check if the given address exists on chain and return the gas cost.
     pub fn db_exists(&self, addr: &Address) -> Result<(bool, u64)> {
@@ -196,6 +250,14 @@ where
         &mut self,
         batch: &mut D::WriteBatch,
     ) -> Result<()> {
+        // The prefix deletions come first, so that they don't remove the keys
+        // written after them
+        for prefix in
+            std::mem::take(&mut self.0.write_log.block_deleted_prefixes)
+        {
+            self.batch_delete_prefix(batch, &prefix)?;
+        }
+
         for (key, entry) in
             std::mem::take(&mut self.0.write_log.block_write_log).into_iter()
         {
@@ -261,6 +323,7 @@ where
 
     /// Execute write batch.
     pub fn exec_batch(&mut self, batch: D::WriteBatch) -> Result<()> {
+        self.0.read_cache.clear();
         Ok(self.db.exec_batch(batch)?)
     }
 
@@ -317,6 +380,29 @@ where
         )?)
     }
 
+    /// Batch delete all the key-vals with the given prefix from the DB and
+    /// remove them from the merkle tree. Returns the total size of the removed
+    /// values.
+    pub fn batch_delete_prefix(
+        &mut self,
+        batch: &mut D::WriteBatch,
+        prefix: &Key,
+    ) -> Result<i64> {
+        let is_key_merklized = self.merkle_tree_key_filter;
+        let (keys, deleted_len) = self.0.db.delete_prefix(
+            batch,
+            self.0.in_mem.block.height,
+            prefix,
+            is_key_merklized,
+        )?;
+        self.0
+            .in_mem
+            .block
+            .tree
+            .delete_keys(keys.iter().filter(|key| is_key_merklized(key)))?;
+        Ok(deleted_len)
+    }
+
     // Prune merkle tree stores. Use after updating self.block.height in the
     // commit.
     fn prune_merkle_tree_stores(
@@ -334,18 +420,34 @@ where
                 self.in_mem.block.epoch.prev(),
             )?;
         }
-        // Prune provable stores
-        let oldest_epoch = self.in_mem.get_oldest_epoch();
+        // Prune provable stores. The IBC subtree stores can be retained
+        // longer than the others for the proofs of the older packets.
+        let pruning = self.in_mem.merkle_tree_pruning;
+        let oldest_ibc_epoch = self
+            .in_mem
+            .get_oldest_retained_epoch(pruning.ibc_retention_epochs);
+        let oldest_epoch = self
+            .in_mem
+            .get_oldest_retained_epoch(pruning.general_retention_epochs);
+        // Remove stores at the previous epoch because the Merkle tree stores
+        // at the starting height of the epoch would be used to restore stores
+        // at a height (> oldest_height) in the epoch
+        if oldest_ibc_epoch.0 > 0 {
+            self.db.prune_merkle_tree_store(
+                batch,
+                &StoreType::Ibc,
+                oldest_ibc_epoch.prev(),
+            )?;
+        }
         if oldest_epoch.0 > 0 {
-            // Remove stores at the previous epoch because the Merkle tree
-            // stores at the starting height of the epoch would be used to
-            // restore stores at a height (> oldest_height) in the epoch
             for st in StoreType::iter_provable() {
-                self.db.prune_merkle_tree_store(
-                    batch,
-                    st,
-                    oldest_epoch.prev(),
-                )?;
+                if *st != StoreType::Ibc {
+                    self.db.prune_merkle_tree_store(
+                        batch,
+                        st,
+                        oldest_epoch.prev(),
+                    )?;
+                }
             }
 
             // Prune the BridgePool subtree stores with invalid nonce
@@ -496,6 +598,11 @@ where
             let tree = MerkleTree::new(merkle_tree_stores)
                 .or_else(|_| self.rebuild_full_merkle_tree(height))
                 .unwrap();
+            let diffs_oldest_height = self
+                .0
+                .db
+                .read_diffs_oldest_height()
+                .expect("Read diffs oldest height call must not fail");
 
             let in_mem = &mut self.0.in_mem;
             in_mem.block.tree = tree;
@@ -503,6 +610,7 @@ where
             in_mem.tx_queue = tx_queue;
             in_mem.ethereum_height = ethereum_height;
             in_mem.eth_events_queue = eth_events_queue;
+            in_mem.diffs_oldest_height = diffs_oldest_height;
             tracing::debug!("Loaded storage from DB");
         } else {
             tracing::info!("No state could be found");
@@ -576,6 +684,8 @@ where
             self.prune_merkle_tree_stores(&mut batch)?;
         }
         self.db.exec_batch(batch)?;
+        self.0.read_cache.clear();
+        self.in_mem.diffs_oldest_height = self.db.read_diffs_oldest_height()?;
         Ok(())
     }
 }
@@ -608,9 +718,10 @@ where
 
     pub fn with_temp_write_log(&self) -> TempWlState<'_, D, H> {
         TempWlState {
-            write_log: WriteLog::default(),
+            write_log: WriteLog::new(self.write_log.reserved_prefixes.clone()),
             db: &self.db,
             in_mem: &self.in_mem,
+            read_cache: &self.read_cache,
         }
     }
 
@@ -654,6 +765,33 @@ where
             .unwrap_or(false)
     }
 
+    /// Iterate the subspace diffs committed at the given height, optionally
+    /// only the keys with the given prefix, ordered by the storage keys. The
+    /// height can't be older than the `storage_read_past_height_limit`. Note
+    /// that the diffs of the keys excluded from the merkle tree are only
+    /// retained at the last height where they were changed.
+    pub fn iter_diffs_at_height<'iter>(
+        &'iter self,
+        height: BlockHeight,
+        prefix: Option<&'iter Key>,
+    ) -> Result<DiffsIterator<<D as DBIter<'iter>>::PrefixIter>> {
+        let last_height = self.in_mem.get_last_block_height();
+        if height > last_height {
+            return Err(Error::BlockHeightPastLast {
+                height,
+                last_height,
+            });
+        }
+        let oldest_height = self.in_mem.get_oldest_height();
+        if height < oldest_height {
+            return Err(Error::BlockHeightTooOld {
+                height,
+                oldest_height,
+            });
+        }
+        Ok(self.db.iter_diffs_at_height(height, prefix))
+    }
+
     /// Returns a value from the specified subspace at the given height (or the
     /// last committed height when 0) and the gas cost.
     pub fn db_read_with_height(
@@ -713,6 +851,7 @@ where
             }
         }
 
+        self.read_cache.invalidate(key);
         let len = value.len();
         let gas = (key.len() + len) as u64 * STORAGE_WRITE_GAS_PER_BYTE;
         let size_diff = self.db.write_subspace_val(
@@ -729,6 +868,7 @@ where
     pub fn db_delete(&mut self, key: &Key) -> Result<(u64, i64)> {
         // Note that this method is the same as `StorageWrite::delete`,
         // but with gas and storage bytes len diff accounting
+        self.read_cache.invalidate(key);
         let mut deleted_bytes_len = 0;
         if self.db_has_key(key)?.0 {
             let is_key_merklized = (self.merkle_tree_key_filter)(key);
@@ -758,6 +898,62 @@ where
         value: namada_merkle_tree::StorageBytes,
         height: BlockHeight,
     ) -> Result<ProofOps> {
+        self.get_existence_merkle_proof(key, value, height)
+            .map(Into::into)
+    }
+
+    /// Get the non-existence proof
+    pub fn get_non_existence_proof(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+    ) -> Result<ProofOps> {
+        self.get_non_existence_merkle_proof(key, height)
+            .map(Into::into)
+    }
+
+    /// Get the ICS23 commitment proof of the existence of the key with its
+    /// value at the given committed height (`0` means the last committed
+    /// height). The proof of a key in the IBC sub tree is verified against
+    /// `ibc_proof_specs` and the proof of a key in the other sub trees
+    /// against `proof_specs`.
+    pub fn get_existence_commitment_proof(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+    ) -> Result<CommitmentProofBytes> {
+        let height = if height == BlockHeight(0) {
+            self.in_mem.get_last_block_height()
+        } else {
+            height
+        };
+        let (value, _gas) = self.db_read_at_height(key, height)?;
+        let value = value.ok_or_else(|| Error::UnknownKey {
+            key: key.to_string(),
+        })?;
+        self.get_existence_merkle_proof(key, &value, height)
+            .map(Into::into)
+    }
+
+    /// Get the ICS23 commitment proof of the non-existence of the key at the
+    /// given committed height (`0` means the last committed height). The
+    /// proof is verified against `ibc_proof_specs` with the key given by
+    /// [`merkle_tree::non_existence_proof_key`].
+    pub fn get_non_existence_commitment_proof(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+    ) -> Result<CommitmentProofBytes> {
+        self.get_non_existence_merkle_proof(key, height)
+            .map(Into::into)
+    }
+
+    fn get_existence_merkle_proof(
+        &self,
+        key: &Key,
+        value: namada_merkle_tree::StorageBytes,
+        height: BlockHeight,
+    ) -> Result<merkle_tree::Proof> {
         use std::array;
 
         // `0` means last committed height
@@ -779,7 +975,6 @@ where
                     .block
                     .tree
                     .get_sub_tree_proof(key, proof)
-                    .map(Into::into)
                     .map_err(Error::MerkleTreeError)
             } else {
                 Err(Error::MerkleTreeError(MerkleTreeError::TendermintProof))
@@ -792,7 +987,6 @@ where
                 .map_err(Error::MerkleTreeError)?
             {
                 tree.get_sub_tree_proof(key, proof)
-                    .map(Into::into)
                     .map_err(Error::MerkleTreeError)
             } else {
                 Err(Error::MerkleTreeError(MerkleTreeError::TendermintProof))
@@ -800,12 +994,11 @@ where
         }
     }
 
-    /// Get the non-existence proof
-    pub fn get_non_existence_proof(
+    fn get_non_existence_merkle_proof(
         &self,
         key: &Key,
         height: BlockHeight,
-    ) -> Result<ProofOps> {
+    ) -> Result<merkle_tree::Proof> {
         // `0` means last committed height
         let height = if height == BlockHeight(0) {
             self.in_mem.get_last_block_height()
@@ -824,7 +1017,6 @@ where
             let (store_type, _) = StoreType::sub_key(key)?;
             self.get_merkle_tree(height, Some(store_type))?
                 .get_non_existence_proof(key)
-                .map(Into::into)
                 .map_err(Error::MerkleTreeError)
         }
     }
@@ -860,6 +1052,10 @@ where
             Some(height) => height,
             None => BlockHeight(1),
         };
+        if height > epoch_start_height {
+            // The tree is restored with the diffs after the epoch start
+            self.db.check_diffs_retained(epoch_start_height)?;
+        }
         let stores = self
             .db
             .read_merkle_tree_stores(epoch, epoch_start_height, store_type)?
@@ -1055,6 +1251,10 @@ where
     fn charge_gas(&self, _gas: u64) -> Result<()> {
         Ok(())
     }
+
+    fn read_cache(&self) -> Option<&BlockReadCache> {
+        Some(&self.0.read_cache)
+    }
 }
 
 impl<D, H> State for FullAccessState<D, H>
@@ -1110,6 +1310,10 @@ where
     fn charge_gas(&self, _gas: u64) -> Result<()> {
         Ok(())
     }
+
+    fn read_cache(&self) -> Option<&BlockReadCache> {
+        Some(&self.read_cache)
+    }
 }
 
 impl<D, H> State for WlState<D, H>
@@ -1151,6 +1355,10 @@ where
     fn charge_gas(&self, _gas: u64) -> Result<()> {
         Ok(())
     }
+
+    fn read_cache(&self) -> Option<&BlockReadCache> {
+        Some(self.read_cache)
+    }
 }
 
 impl<D, H> State for TempWlState<'_, D, H>
@@ -5,10 +5,12 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use itertools::Itertools;
 use namada_core::address::{Address, EstablishedAddressGen, InternalAddress};
+use namada_core::event::Event;
 use namada_core::hash::Hash;
 use namada_core::ibc::IbcEvent;
 use namada_core::storage;
 use namada_gas::{MEMORY_ACCESS_GAS_PER_BYTE, STORAGE_WRITE_GAS_PER_BYTE};
+use namada_storage::IterOpts;
 use namada_trans_token::storage_key::{
     is_any_minted_balance_key, is_any_minter_key, is_any_token_balance_key,
     is_any_token_parameter_key,
@@ -33,6 +35,10 @@ pub enum Error {
     WriteTempAfterDelete,
     #[error("Replay protection key: {0}")]
     ReplayProtection(String),
+    #[error("Trying to modify a key reserved for the protocol: {0}")]
+    ProtocolReservedKey(storage::Key),
+    #[error("The savepoint is unknown or has already been released")]
+    UnknownSavepoint,
 }
 
 /// Result for functions that may fail
@@ -74,6 +80,53 @@ pub(crate) enum ReProtStorageModification {
     Finalize,
 }
 
+/// A savepoint of the write log to roll back to, see [`WriteLog::savepoint`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use]
+pub struct Savepoint {
+    /// The position of the savepoint in the stack of the active savepoints
+    depth: usize,
+    /// The length of the journal when the savepoint has been taken
+    journal_len: usize,
+}
+
+/// A log of the storage modifications in the [`WriteLog`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Log {
+    Block,
+    Tx,
+    TxPrecommit,
+}
+
+/// An entry of the journal to undo a change of the write log made after a
+/// savepoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum JournalEntry {
+    /// The previous modification of the key in the log
+    Modification {
+        log: Log,
+        key: storage::Key,
+        prev: Option<StorageModification>,
+    },
+    /// The previous replay protection modification of the hash
+    ReplayProtection {
+        hash: Hash,
+        prev: Option<ReProtStorageModification>,
+    },
+    /// A prefix newly deleted at block-level
+    DeletedPrefix(storage::Key),
+    /// The previous generator of established addresses
+    AddressGen(Option<EstablishedAddressGen>),
+    /// An emitted IBC event
+    IbcEvent,
+    /// An emitted event
+    Event,
+    /// The taken IBC events
+    TakenIbcEvents(Vec<IbcEvent>),
+    /// The taken events
+    TakenEvents(Vec<Event>),
+}
+
 /// The write log storage
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WriteLog {
@@ -84,6 +137,8 @@ pub struct WriteLog {
     pub(crate) block_write_log: HashMap<storage::Key, StorageModification>,
     /// The storage modifications for the current transaction
     pub(crate) tx_write_log: HashMap<storage::Key, StorageModification>,
+    /// The number of bytes of the keys and values in the `tx_write_log`
+    pub(crate) tx_write_bytes: u64,
     /// A precommit bucket for the `tx_write_log`. This is useful for
     /// validation when a clean `tx_write_log` is needed without committing any
     /// modification already in there. These modifications can be temporarily
@@ -94,11 +149,43 @@ pub struct WriteLog {
     /// cleaned either when committing or dumping the `tx_write_log`
     pub(crate) tx_precommit_write_log:
         HashMap<storage::Key, StorageModification>,
-    /// The IBC events for the current transaction
-    pub(crate) ibc_events: BTreeSet<IbcEvent>,
+    /// The IBC events for the current transaction, in the emission order
+    pub(crate) ibc_events: Vec<IbcEvent>,
+    /// The other events emitted by the current transaction, in the emission
+    /// order
+    pub(crate) events: Vec<Event>,
     /// Storage modifications for the replay protection storage, always
     /// committed regardless of the result of the transaction
     pub(crate) replay_protection: HashMap<Hash, ReProtStorageModification>,
+    /// The prefixes reserved for the protocol, which txs can't modify
+    pub(crate) reserved_prefixes: storage::ReservedPrefixes,
+    /// Allow the tx writes under the prefixes reserved for the protocol
+    pub(crate) allow_reserved_keys: bool,
+    /// The prefixes whose keys have been deleted at block-level. The keys in
+    /// the storage under these prefixes are deleted on the block commit.
+    pub(crate) block_deleted_prefixes: BTreeSet<storage::Key>,
+    /// The entries to undo the changes made after the oldest active savepoint,
+    /// in the order of the changes. Empty without any active savepoint.
+    journal: Vec<JournalEntry>,
+    /// The lengths of the journal at the active savepoints, the latest last
+    savepoints: Vec<usize>,
+}
+
+/// The modification of a key under a prefix deleted at block-level
+static DELETED_BY_PREFIX: StorageModification = StorageModification::Delete;
+
+/// The number of bytes of a key and its modification
+fn modification_bytes(
+    key: &storage::Key,
+    modification: &StorageModification,
+) -> u64 {
+    let value_len = match modification {
+        StorageModification::Write { value }
+        | StorageModification::Temp { value } => value.len(),
+        StorageModification::Delete => 0,
+        StorageModification::InitAccount { vp_code_hash } => vp_code_hash.len(),
+    };
+    (key.len() + value_len) as u64
 }
 
 /// Write log prefix iterator
@@ -107,30 +194,49 @@ pub struct PrefixIter {
     /// The concrete iterator for modifications sorted by storage keys
     pub iter:
         std::collections::btree_map::IntoIter<String, StorageModification>,
+    /// Iterate in the descending order of the storage keys
+    pub reverse: bool,
 }
 
 impl Iterator for PrefixIter {
     type Item = (String, StorageModification);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next()
+        if self.reverse {
+            self.iter.next_back()
+        } else {
+            self.iter.next()
+        }
     }
 }
 
 impl Default for WriteLog {
     fn default() -> Self {
+        Self::new(storage::ReservedPrefixes::default())
+    }
+}
+
+impl WriteLog {
+    /// Create a new write log that rejects the tx modifications under the
+    /// given prefixes reserved for the protocol
+    pub fn new(reserved_prefixes: storage::ReservedPrefixes) -> Self {
         Self {
             address_gen: None,
             block_write_log: HashMap::with_capacity(100_000),
             tx_write_log: HashMap::with_capacity(100),
+            tx_write_bytes: 0,
             tx_precommit_write_log: HashMap::with_capacity(100),
-            ibc_events: BTreeSet::new(),
+            ibc_events: Vec::new(),
+            events: Vec::new(),
             replay_protection: HashMap::with_capacity(1_000),
+            reserved_prefixes,
+            allow_reserved_keys: false,
+            block_deleted_prefixes: BTreeSet::new(),
+            journal: Vec::new(),
+            savepoints: Vec::new(),
         }
     }
-}
 
-impl WriteLog {
     /// Read a value at the given key and return the value and the gas cost,
     /// returns [`None`] if the key is not present in the write log
     pub fn read(
@@ -148,7 +254,9 @@ impl WriteLog {
             .or_else(|| {
                 // if not found, then try to read from block write log
                 self.block_write_log.get(key)
-            }) {
+            })
+            .or_else(|| self.read_deleted_by_prefix(key))
+        {
             Some(v) => {
                 let gas = match v {
                     StorageModification::Write { ref value } => {
@@ -175,7 +283,11 @@ impl WriteLog {
         &self,
         key: &storage::Key,
     ) -> (Option<&StorageModification>, u64) {
-        match self.block_write_log.get(key) {
+        match self
+            .block_write_log
+            .get(key)
+            .or_else(|| self.read_deleted_by_prefix(key))
+        {
             Some(v) => {
                 let gas = match v {
                     StorageModification::Write { ref value } => {
@@ -200,16 +312,18 @@ impl WriteLog {
     /// validity predicate of a new account that's not yet committed to storage.
     /// Fails with [`Error::UpdateTemporaryValue`] when attempting to update a
     /// temporary value.
+    /// Fails with [`Error::ProtocolReservedKey`] when attempting to write
+    /// under a prefix reserved for the protocol.
     pub fn write(
         &mut self,
         key: &storage::Key,
         value: Vec<u8>,
     ) -> Result<(u64, i64)> {
+        self.check_reserved_key(key)?;
         let len = value.len();
         let gas = key.len() + len;
         let size_diff = match self
-            .tx_write_log
-            .insert(key.clone(), StorageModification::Write { value })
+            .insert_tx_modification(key, StorageModification::Write { value })
         {
             Some(prev) => match prev {
                 StorageModification::Write { ref value } => {
@@ -240,10 +354,11 @@ impl WriteLog {
         key: &storage::Key,
         value: Vec<u8>,
     ) -> Result<()> {
-        if let Some(prev) = self
+        let prev = self
             .block_write_log
-            .insert(key.clone(), StorageModification::Write { value })
-        {
+            .insert(key.clone(), StorageModification::Write { value });
+        self.journal_modification(Log::Block, key, prev.as_ref());
+        if let Some(prev) = prev {
             match prev {
                 StorageModification::InitAccount { .. } => {
                     return Err(Error::UpdateVpOfNewAccount);
@@ -263,16 +378,18 @@ impl WriteLog {
     /// validity predicate of a new account that's not yet committed to storage.
     /// Fails with [`Error::WriteTempAfterDelete`] when attempting to update a
     /// temporary value after deleting.
+    /// Fails with [`Error::ProtocolReservedKey`] when attempting to write
+    /// under a prefix reserved for the protocol.
     pub fn write_temp(
         &mut self,
         key: &storage::Key,
         value: Vec<u8>,
     ) -> Result<(u64, i64)> {
+        self.check_reserved_key(key)?;
         let len = value.len();
         let gas = key.len() + len;
         let size_diff = match self
-            .tx_write_log
-            .insert(key.clone(), StorageModification::Temp { value })
+            .insert_tx_modification(key, StorageModification::Temp { value })
         {
             Some(prev) => match prev {
                 StorageModification::Write { ref value } => {
@@ -301,13 +418,15 @@ impl WriteLog {
     /// difference.
     /// Fails with [`Error::DeleteVp`] for a validity predicate key, which are
     /// not possible to delete.
+    /// Fails with [`Error::ProtocolReservedKey`] when attempting to delete a
+    /// key under a prefix reserved for the protocol.
     pub fn delete(&mut self, key: &storage::Key) -> Result<(u64, i64)> {
         if key.is_validity_predicate().is_some() {
             return Err(Error::DeleteVp);
         }
+        self.check_reserved_key(key)?;
         let size_diff = match self
-            .tx_write_log
-            .insert(key.clone(), StorageModification::Delete)
+            .insert_tx_modification(key, StorageModification::Delete)
         {
             Some(prev) => match prev {
                 StorageModification::Write { ref value } => value.len() as i64,
@@ -332,10 +451,11 @@ impl WriteLog {
         if key.is_validity_predicate().is_some() {
             return Err(Error::DeleteVp);
         }
-        if let Some(prev) = self
+        let prev = self
             .block_write_log
-            .insert(key.clone(), StorageModification::Delete)
-        {
+            .insert(key.clone(), StorageModification::Delete);
+        self.journal_modification(Log::Block, key, prev.as_ref());
+        if let Some(prev) = prev {
             match prev {
                 StorageModification::InitAccount { .. } => {
                     return Err(Error::DeleteVp);
@@ -348,12 +468,77 @@ impl WriteLog {
         Ok(())
     }
 
+    /// Delete all the key-vals with the given prefix at block-level without
+    /// iterating them. The keys in the storage under the prefix are read as
+    /// deleted and they're removed from the storage with a single batched
+    /// deletion on the block commit. Validity predicates are kept.
+    pub fn protocol_delete_prefix(&mut self, prefix: &storage::Key) {
+        let is_journaling = self.is_journaling();
+        let journal = &mut self.journal;
+        self.block_write_log.retain(|key, modification| {
+            let keep = key.is_validity_predicate().is_some()
+                || !matches!(key.split_prefix(prefix), Some(Some(_)));
+            if !keep && is_journaling {
+                journal.push(JournalEntry::Modification {
+                    log: Log::Block,
+                    key: key.clone(),
+                    prev: Some(modification.clone()),
+                });
+            }
+            keep
+        });
+        if self.block_deleted_prefixes.insert(prefix.clone()) && is_journaling {
+            self.journal
+                .push(JournalEntry::DeletedPrefix(prefix.clone()));
+        }
+    }
+
+    /// The prefixes deleted at block-level in the format of the DB keys, to
+    /// match the keys of a storage iterator
+    pub(crate) fn deleted_db_prefixes(&self) -> Vec<String> {
+        self.block_deleted_prefixes
+            .iter()
+            .map(|prefix| format!("{prefix}/"))
+            .collect()
+    }
+
+    /// Read a key as deleted if it is under a prefix deleted at block-level
+    fn read_deleted_by_prefix(
+        &self,
+        key: &storage::Key,
+    ) -> Option<&StorageModification> {
+        self.block_deleted_prefixes
+            .iter()
+            .any(|prefix| matches!(key.split_prefix(prefix), Some(Some(_))))
+            .then_some(&DELETED_BY_PREFIX)
+    }
+
+    /// Allow or disallow the tx writes under the prefixes reserved for the
+    /// protocol. This is only meant to be enabled while the protocol itself
+    /// runs a transaction, e.g. the code of an accepted governance proposal.
+    pub fn allow_reserved_keys(&mut self, allow: bool) {
+        self.allow_reserved_keys = allow;
+    }
+
+    /// Check that a tx is allowed to modify the given key
+    fn check_reserved_key(&self, key: &storage::Key) -> Result<()> {
+        if !self.allow_reserved_keys && self.reserved_prefixes.is_reserved(key)
+        {
+            return Err(Error::ProtocolReservedKey(key.clone()));
+        }
+        Ok(())
+    }
+
     /// Initialize a new account and return the gas cost.
     pub fn init_account(
         &mut self,
         storage_address_gen: &EstablishedAddressGen,
         vp_code_hash: Hash,
     ) -> (Address, u64) {
+        if self.is_journaling() {
+            self.journal
+                .push(JournalEntry::AddressGen(self.address_gen.clone()));
+        }
         // If we've previously generated a new account, we use the local copy of
         // the generator. Otherwise, we create a new copy from the storage
         let address_gen =
@@ -363,18 +548,68 @@ impl WriteLog {
         let key = storage::Key::validity_predicate(&addr);
         let gas = (key.len() + vp_code_hash.len()) as u64
             * STORAGE_WRITE_GAS_PER_BYTE;
-        self.tx_write_log
-            .insert(key, StorageModification::InitAccount { vp_code_hash });
+        self.insert_tx_modification(
+            &key,
+            StorageModification::InitAccount { vp_code_hash },
+        );
         (addr, gas)
     }
 
+    /// Insert a modification in the tx write log, keeping count of its
+    /// bytes, and return the previous modification of the key, if any
+    fn insert_tx_modification(
+        &mut self,
+        key: &storage::Key,
+        modification: StorageModification,
+    ) -> Option<StorageModification> {
+        self.tx_write_bytes = self
+            .tx_write_bytes
+            .saturating_add(modification_bytes(key, &modification));
+        let prev = self.tx_write_log.insert(key.clone(), modification);
+        self.journal_modification(Log::Tx, key, prev.as_ref());
+        if let Some(prev) = prev.as_ref() {
+            self.tx_write_bytes = self
+                .tx_write_bytes
+                .saturating_sub(modification_bytes(key, prev));
+        }
+        prev
+    }
+
+    /// Get the number of the storage modifications in the current
+    /// transaction's write log. The precommit is not included.
+    pub fn tx_write_count(&self) -> u64 {
+        self.tx_write_log.len() as u64
+    }
+
+    /// Get the number of bytes of the keys and values in the current
+    /// transaction's write log. The precommit is not included.
+    pub fn tx_write_bytes(&self) -> u64 {
+        self.tx_write_bytes
+    }
+
     /// Set an IBC event and return the gas cost.
     pub fn emit_ibc_event(&mut self, event: IbcEvent) -> u64 {
         let len = event
             .attributes
             .iter()
             .fold(0, |acc, (k, v)| acc + k.len() + v.len());
-        self.ibc_events.insert(event);
+        self.ibc_events.push(event);
+        if self.is_journaling() {
+            self.journal.push(JournalEntry::IbcEvent);
+        }
+        len as u64 * MEMORY_ACCESS_GAS_PER_BYTE
+    }
+
+    /// Set an event of the current transaction and return the gas cost.
+    pub fn emit_event(&mut self, event: Event) -> u64 {
+        let len = event
+            .attributes
+            .iter()
+            .fold(0, |acc, (k, v)| acc + k.len() + v.len());
+        self.events.push(event);
+        if self.is_journaling() {
+            self.journal.push(JournalEntry::Event);
+        }
         len as u64 * MEMORY_ACCESS_GAS_PER_BYTE
     }
 
@@ -432,16 +667,36 @@ impl WriteLog {
             .collect()
     }
 
-    /// Take the IBC event of the current transaction
-    pub fn take_ibc_events(&mut self) -> BTreeSet<IbcEvent> {
-        std::mem::take(&mut self.ibc_events)
+    /// Take the IBC events of the current transaction, in the emission order
+    pub fn take_ibc_events(&mut self) -> Vec<IbcEvent> {
+        let events = std::mem::take(&mut self.ibc_events);
+        if self.is_journaling() {
+            self.journal
+                .push(JournalEntry::TakenIbcEvents(events.clone()));
+        }
+        events
     }
 
-    /// Get the IBC event of the current transaction
-    pub fn get_ibc_events(&self) -> &BTreeSet<IbcEvent> {
+    /// Get the IBC events of the current transaction, in the emission order
+    pub fn get_ibc_events(&self) -> &[IbcEvent] {
         &self.ibc_events
     }
 
+    /// Take the other events of the current transaction, in the emission
+    /// order
+    pub fn take_events(&mut self) -> Vec<Event> {
+        let events = std::mem::take(&mut self.events);
+        if self.is_journaling() {
+            self.journal.push(JournalEntry::TakenEvents(events.clone()));
+        }
+        events
+    }
+
+    /// Get the other events of the current transaction, in the emission order
+    pub fn get_events(&self) -> &[Event] {
+        &self.events
+    }
+
     /// Add the entire content of the tx write log to the precommit one. The tx
     /// log gets reset in the process.
     pub fn precommit_tx(&mut self) {
@@ -449,8 +704,23 @@ impl WriteLog {
             &mut self.tx_write_log,
             HashMap::with_capacity(100),
         );
-
-        self.tx_precommit_write_log.extend(tx_log)
+        self.tx_write_bytes = 0;
+
+        if self.is_journaling() {
+            for (key, modification) in tx_log {
+                self.journal_modification(Log::Tx, &key, Some(&modification));
+                let prev = self
+                    .tx_precommit_write_log
+                    .insert(key.clone(), modification);
+                self.journal_modification(
+                    Log::TxPrecommit,
+                    &key,
+                    prev.as_ref(),
+                );
+            }
+        } else {
+            self.tx_precommit_write_log.extend(tx_log)
+        }
     }
 
     /// Commit the current transaction's write log and precommit log to the
@@ -461,24 +731,49 @@ impl WriteLog {
         self.precommit_tx();
 
         // Then commit to block
-        self.tx_precommit_write_log.retain(|_, v| {
-            !matches!(v, StorageModification::Temp { value: _ })
+        let is_journaling = self.is_journaling();
+        let journal = &mut self.journal;
+        self.tx_precommit_write_log.retain(|k, v| {
+            let keep = !matches!(v, StorageModification::Temp { value: _ });
+            if !keep && is_journaling {
+                journal.push(JournalEntry::Modification {
+                    log: Log::TxPrecommit,
+                    key: k.clone(),
+                    prev: Some(v.clone()),
+                });
+            }
+            keep
         });
         let tx_precommit_write_log = std::mem::replace(
             &mut self.tx_precommit_write_log,
             HashMap::with_capacity(100),
         );
 
-        self.block_write_log.extend(tx_precommit_write_log);
+        if is_journaling {
+            for (key, modification) in tx_precommit_write_log {
+                self.journal_modification(
+                    Log::TxPrecommit,
+                    &key,
+                    Some(&modification),
+                );
+                let prev =
+                    self.block_write_log.insert(key.clone(), modification);
+                self.journal_modification(Log::Block, &key, prev.as_ref());
+            }
+        } else {
+            self.block_write_log.extend(tx_precommit_write_log);
+        }
         self.take_ibc_events();
+        self.take_events();
     }
 
     /// Drop the current transaction's write log and precommit when it's
     /// declined by any of the triggered validity predicates. Starts a new
     /// transaction write log.
     pub fn drop_tx(&mut self) {
-        self.tx_precommit_write_log.clear();
-        self.tx_write_log.clear();
+        self.clear_log(Log::TxPrecommit);
+        self.clear_log(Log::Tx);
+        self.tx_write_bytes = 0;
     }
 
     /// Drop the current transaction's write log but keep the precommit one.
@@ -486,7 +781,159 @@ impl WriteLog {
     /// be valid and we want to keep the changes applied before the failed
     /// section.
     pub fn drop_tx_keep_precommit(&mut self) {
-        self.tx_write_log.clear();
+        self.clear_log(Log::Tx);
+        self.tx_write_bytes = 0;
+    }
+
+    /// Take a savepoint of the write log. All the changes made after it,
+    /// including the deletions and the emitted events, can be undone with
+    /// [`WriteLog::rollback_to`] or kept with [`WriteLog::release`].
+    /// Savepoints can be nested and are rolled back or released in the LIFO
+    /// order. They have to be released before the block is committed.
+    pub fn savepoint(&mut self) -> Savepoint {
+        let savepoint = Savepoint {
+            depth: self.savepoints.len(),
+            journal_len: self.journal.len(),
+        };
+        self.savepoints.push(savepoint.journal_len);
+        savepoint
+    }
+
+    /// Undo all the changes made after the savepoint and release it together
+    /// with the savepoints taken after it. The cost is linear in the number of
+    /// the changes made after the savepoint.
+    pub fn rollback_to(&mut self, savepoint: Savepoint) -> Result<()> {
+        self.check_savepoint(&savepoint)?;
+        while self.journal.len() > savepoint.journal_len {
+            let entry = self.journal.pop().expect("The journal can't be empty");
+            self.undo(entry);
+        }
+        self.savepoints.truncate(savepoint.depth);
+        Ok(())
+    }
+
+    /// Release the savepoint together with the savepoints taken after it,
+    /// keeping all the changes made after it
+    pub fn release(&mut self, savepoint: Savepoint) -> Result<()> {
+        self.check_savepoint(&savepoint)?;
+        self.savepoints.truncate(savepoint.depth);
+        if self.savepoints.is_empty() {
+            self.journal.clear();
+        }
+        Ok(())
+    }
+
+    /// Check that the savepoint is active
+    fn check_savepoint(&self, savepoint: &Savepoint) -> Result<()> {
+        match self.savepoints.get(savepoint.depth) {
+            Some(journal_len) if *journal_len == savepoint.journal_len => {
+                Ok(())
+            }
+            _ => Err(Error::UnknownSavepoint),
+        }
+    }
+
+    /// Check if the changes have to be journaled for an active savepoint
+    fn is_journaling(&self) -> bool {
+        !self.savepoints.is_empty()
+    }
+
+    /// Journal the previous modification of the key in the log, if there's
+    /// an active savepoint
+    fn journal_modification(
+        &mut self,
+        log: Log,
+        key: &storage::Key,
+        prev: Option<&StorageModification>,
+    ) {
+        if self.is_journaling() {
+            self.journal.push(JournalEntry::Modification {
+                log,
+                key: key.clone(),
+                prev: prev.cloned(),
+            });
+        }
+    }
+
+    /// Clear the log, journaling its modifications if there's an active
+    /// savepoint
+    fn clear_log(&mut self, log: Log) {
+        let is_journaling = self.is_journaling();
+        let (modifications, journal) = match log {
+            Log::Block => (&mut self.block_write_log, &mut self.journal),
+            Log::Tx => (&mut self.tx_write_log, &mut self.journal),
+            Log::TxPrecommit => {
+                (&mut self.tx_precommit_write_log, &mut self.journal)
+            }
+        };
+        if is_journaling {
+            journal.extend(modifications.drain().map(|(key, modification)| {
+                JournalEntry::Modification {
+                    log,
+                    key,
+                    prev: Some(modification),
+                }
+            }));
+        } else {
+            modifications.clear();
+        }
+    }
+
+    /// Undo a change with its journal entry
+    fn undo(&mut self, entry: JournalEntry) {
+        match entry {
+            JournalEntry::Modification { log, key, prev } => {
+                let prev_bytes = prev
+                    .as_ref()
+                    .map(|prev| modification_bytes(&key, prev))
+                    .unwrap_or_default();
+                let modifications = match log {
+                    Log::Block => &mut self.block_write_log,
+                    Log::Tx => &mut self.tx_write_log,
+                    Log::TxPrecommit => &mut self.tx_precommit_write_log,
+                };
+                let current = match prev {
+                    Some(prev) => modifications.insert(key.clone(), prev),
+                    None => modifications.remove(&key),
+                };
+                if log == Log::Tx {
+                    let current_bytes = current
+                        .as_ref()
+                        .map(|current| modification_bytes(&key, current))
+                        .unwrap_or_default();
+                    self.tx_write_bytes = self
+                        .tx_write_bytes
+                        .saturating_sub(current_bytes)
+                        .saturating_add(prev_bytes);
+                }
+            }
+            JournalEntry::ReplayProtection { hash, prev } => match prev {
+                Some(prev) => {
+                    self.replay_protection.insert(hash, prev);
+                }
+                None => {
+                    self.replay_protection.remove(&hash);
+                }
+            },
+            JournalEntry::DeletedPrefix(prefix) => {
+                self.block_deleted_prefixes.remove(&prefix);
+            }
+            JournalEntry::AddressGen(address_gen) => {
+                self.address_gen = address_gen;
+            }
+            JournalEntry::IbcEvent => {
+                self.ibc_events.pop();
+            }
+            JournalEntry::Event => {
+                self.events.pop();
+            }
+            JournalEntry::TakenIbcEvents(events) => {
+                self.ibc_events = events;
+            }
+            JournalEntry::TakenEvents(events) => {
+                self.events = events;
+            }
+        }
     }
 
     /// Get the verifiers set whose validity predicates should validate the
@@ -549,6 +996,18 @@ impl WriteLog {
     /// Iterate modifications prior to the current transaction, whose storage
     /// key matches the given prefix, sorted by their storage key.
     pub fn iter_prefix_pre(&self, prefix: &storage::Key) -> PrefixIter {
+        self.iter_prefix_pre_opts(prefix, &IterOpts::default())
+    }
+
+    /// Iterate modifications prior to the current transaction, whose storage
+    /// key matches the given prefix, in the order and from the start of the
+    /// given options. The limit is not applied since the deleted keys are
+    /// iterated too.
+    pub fn iter_prefix_pre_opts(
+        &self,
+        prefix: &storage::Key,
+        opts: &IterOpts,
+    ) -> PrefixIter {
         let mut matches = BTreeMap::new();
 
         for (key, modification) in &self.block_write_log {
@@ -557,13 +1016,24 @@ impl WriteLog {
             }
         }
 
-        let iter = matches.into_iter();
-        PrefixIter { iter }
+        prefix_iter_from(matches, opts)
     }
 
     /// Iterate modifications posterior of the current tx, whose storage key
     /// matches the given prefix, sorted by their storage key.
     pub fn iter_prefix_post(&self, prefix: &storage::Key) -> PrefixIter {
+        self.iter_prefix_post_opts(prefix, &IterOpts::default())
+    }
+
+    /// Iterate modifications posterior of the current tx, whose storage key
+    /// matches the given prefix, in the order and from the start of the
+    /// given options. The limit is not applied since the deleted keys are
+    /// iterated too.
+    pub fn iter_prefix_post_opts(
+        &self,
+        prefix: &storage::Key,
+        opts: &IterOpts,
+    ) -> PrefixIter {
         let mut matches = BTreeMap::new();
 
         for (key, modification) in &self.block_write_log {
@@ -577,8 +1047,7 @@ impl WriteLog {
             }
         }
 
-        let iter = matches.into_iter();
-        PrefixIter { iter }
+        prefix_iter_from(matches, opts)
     }
 
     /// Check if the given tx hash has already been processed. Returns `None` if
@@ -592,8 +1061,7 @@ impl WriteLog {
     /// Write the transaction hash
     pub fn write_tx_hash(&mut self, hash: Hash) -> Result<()> {
         if self
-            .replay_protection
-            .insert(hash, ReProtStorageModification::Write)
+            .insert_replay_protection(hash, ReProtStorageModification::Write)
             .is_some()
         {
             // Cannot write an hash if other requests have already been
@@ -609,8 +1077,7 @@ impl WriteLog {
     /// Remove the transaction hash
     pub fn delete_tx_hash(&mut self, hash: Hash) -> Result<()> {
         match self
-            .replay_protection
-            .insert(hash, ReProtStorageModification::Delete)
+            .insert_replay_protection(hash, ReProtStorageModification::Delete)
         {
             None => Ok(()),
             // Allow overwriting a previous finalize request
@@ -632,8 +1099,7 @@ impl WriteLog {
     /// processing, before any other replay protection operation is done
     pub fn finalize_tx_hash(&mut self, hash: Hash) -> Result<()> {
         if self
-            .replay_protection
-            .insert(hash, ReProtStorageModification::Finalize)
+            .insert_replay_protection(hash, ReProtStorageModification::Finalize)
             .is_some()
         {
             // Cannot finalize an hash if other requests have already been
@@ -645,12 +1111,46 @@ impl WriteLog {
 
         Ok(())
     }
+
+    /// Insert a replay protection modification of the hash, journaling the
+    /// previous one if there's an active savepoint
+    fn insert_replay_protection(
+        &mut self,
+        hash: Hash,
+        modification: ReProtStorageModification,
+    ) -> Option<ReProtStorageModification> {
+        let prev = self.replay_protection.insert(hash, modification);
+        if self.is_journaling() {
+            self.journal.push(JournalEntry::ReplayProtection {
+                hash,
+                prev: prev.clone(),
+            });
+        }
+        prev
+    }
+}
+
+/// Make a write log prefix iterator of the matched modifications, skipping the
+/// ones before the start of the given options
+fn prefix_iter_from(
+    mut matches: BTreeMap<String, StorageModification>,
+    opts: &IterOpts,
+) -> PrefixIter {
+    if opts.start_after.is_some() {
+        matches.retain(|key, _| !opts.is_before_start(key));
+    }
+    PrefixIter {
+        iter: matches.into_iter(),
+        reverse: opts.reverse,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
     use namada_core::address;
+    use namada_core::event::{EventLevel, EventType};
+    use namada_core::storage::KeySeg;
     use pretty_assertions::assert_eq;
     use proptest::prelude::*;
 
@@ -814,6 +1314,74 @@ mod tests {
         assert_matches!(result, Error::DeleteVp);
     }
 
+    #[test]
+    fn test_tx_write_counters() {
+        let mut write_log = WriteLog::default();
+        let key1 = storage::Key::parse("key1").unwrap();
+        let key2 = storage::Key::parse("key2").unwrap();
+
+        write_log.write(&key1, vec![1; 10]).unwrap();
+        write_log.write(&key2, vec![2; 20]).unwrap();
+        assert_eq!(write_log.tx_write_count(), 2);
+        assert_eq!(
+            write_log.tx_write_bytes(),
+            (key1.len() + 10 + key2.len() + 20) as u64
+        );
+
+        // Overwriting and deleting replace the previous bytes of the key
+        write_log.write(&key1, vec![1; 5]).unwrap();
+        write_log.delete(&key2).unwrap();
+        assert_eq!(write_log.tx_write_count(), 2);
+        assert_eq!(
+            write_log.tx_write_bytes(),
+            (key1.len() + 5 + key2.len()) as u64
+        );
+
+        // The counters are reset with the tx write log
+        write_log.precommit_tx();
+        assert_eq!(write_log.tx_write_count(), 0);
+        assert_eq!(write_log.tx_write_bytes(), 0);
+        write_log.write(&key1, vec![1; 5]).unwrap();
+        write_log.drop_tx();
+        assert_eq!(write_log.tx_write_count(), 0);
+        assert_eq!(write_log.tx_write_bytes(), 0);
+    }
+
+    #[test]
+    fn test_write_reserved_key_should_fail() {
+        let prefix = storage::Key::from(address::ETH_BRIDGE.to_db_key());
+        let mut reserved_prefixes = storage::ReservedPrefixes::default();
+        reserved_prefixes.register(prefix.clone()).unwrap();
+        let mut write_log = WriteLog::new(reserved_prefixes);
+        let key = prefix.with_segment("eth_msgs".to_owned());
+        let value = "value".as_bytes().to_vec();
+
+        // tx writes should fail
+        let result = write_log.write(&key, value.clone()).unwrap_err();
+        assert_matches!(result, Error::ProtocolReservedKey(k) if k == key);
+        let result = write_log.write_temp(&key, value.clone()).unwrap_err();
+        assert_matches!(result, Error::ProtocolReservedKey(_));
+        let result = write_log.delete(&key).unwrap_err();
+        assert_matches!(result, Error::ProtocolReservedKey(_));
+        assert!(write_log.read(&key).0.is_none());
+
+        // protocol writes are allowed
+        write_log.protocol_write(&key, value.clone()).unwrap();
+        write_log.protocol_delete(&key).unwrap();
+
+        // tx writes are allowed when the protocol runs the tx
+        write_log.allow_reserved_keys(true);
+        write_log.write(&key, value).unwrap();
+        write_log.delete(&key).unwrap();
+        write_log.allow_reserved_keys(false);
+        let result = write_log.delete(&key).unwrap_err();
+        assert_matches!(result, Error::ProtocolReservedKey(_));
+
+        // nothing is reserved by default
+        let mut write_log = WriteLog::default();
+        write_log.write(&key, "value".as_bytes().to_vec()).unwrap();
+    }
+
     #[test]
     fn test_commit() {
         let mut state = crate::testing::TestState::default();
@@ -870,6 +1438,140 @@ mod tests {
         assert_eq!(value, None);
     }
 
+    #[test]
+    fn test_savepoint_rollback() {
+        let mut write_log = WriteLog::default();
+        let address_gen = EstablishedAddressGen::new("test");
+        let key = |k: &str| {
+            storage::Key::parse(k).expect("cannot parse the key string")
+        };
+        let ibc_event = |t: &str| IbcEvent {
+            event_type: t.to_string(),
+            attributes: HashMap::new(),
+        };
+        let event = Event {
+            event_type: EventType::Proposal,
+            level: EventLevel::Block,
+            attributes: HashMap::new(),
+        };
+
+        // the changes before any savepoint
+        write_log
+            .protocol_write(&key("block/a"), vec![1])
+            .expect("write failed");
+        write_log
+            .protocol_write(&key("block/b"), vec![2])
+            .expect("write failed");
+        write_log
+            .write(&key("tx/a"), vec![3])
+            .expect("write failed");
+        write_log.precommit_tx();
+        write_log
+            .write(&key("tx/b"), vec![4])
+            .expect("write failed");
+        write_log.emit_ibc_event(ibc_event("before"));
+        write_log.emit_event(event.clone());
+        write_log
+            .write_tx_hash(Hash::sha256("tx1".as_bytes()))
+            .expect("write failed");
+        let before = write_log.clone();
+
+        let savepoint = write_log.savepoint();
+        // interleave writes, deletes and events
+        write_log
+            .protocol_write(&key("block/a"), vec![5])
+            .expect("write failed");
+        write_log
+            .protocol_delete(&key("block/c"))
+            .expect("delete failed");
+        write_log.delete(&key("tx/b")).expect("delete failed");
+        write_log.emit_ibc_event(ibc_event("after"));
+        write_log
+            .write_temp(&key("tx/c"), vec![6])
+            .expect("write failed");
+        write_log.init_account(&address_gen, Hash::sha256(b"vp"));
+        let mid = write_log.clone();
+
+        let mid_savepoint = write_log.savepoint();
+        write_log.protocol_delete_prefix(&key("block"));
+        write_log.emit_event(event);
+        write_log.commit_tx();
+        write_log
+            .write(&key("tx/d"), vec![7])
+            .expect("write failed");
+        write_log.drop_tx();
+        write_log
+            .delete_tx_hash(Hash::sha256("tx2".as_bytes()))
+            .expect("delete failed");
+        assert_ne!(write_log, mid);
+
+        // roll back to the mid-point
+        write_log
+            .rollback_to(mid_savepoint)
+            .expect("rollback failed");
+        assert_eq!(write_log, mid);
+        assert_eq!(write_log.tx_write_bytes(), mid.tx_write_bytes());
+
+        // roll back to the view before the savepoint
+        write_log.rollback_to(savepoint).expect("rollback failed");
+        assert_eq!(write_log, before);
+        assert_eq!(write_log.tx_write_bytes(), before.tx_write_bytes());
+
+        // the savepoint can't be rolled back twice
+        assert_matches!(
+            write_log.rollback_to(savepoint).unwrap_err(),
+            Error::UnknownSavepoint
+        );
+    }
+
+    #[test]
+    fn test_nested_savepoints() {
+        let mut write_log = WriteLog::default();
+        let key1 =
+            storage::Key::parse("key1").expect("cannot parse the key string");
+        let key2 =
+            storage::Key::parse("key2").expect("cannot parse the key string");
+        let key3 =
+            storage::Key::parse("key3").expect("cannot parse the key string");
+
+        // releasing the inner savepoint keeps its changes until the outer one
+        // is rolled back
+        let outer = write_log.savepoint();
+        write_log.write(&key1, vec![1]).expect("write failed");
+        let inner = write_log.savepoint();
+        write_log.write(&key2, vec![2]).expect("write failed");
+        write_log.release(inner).expect("release failed");
+        assert!(write_log.read(&key2).0.is_some());
+        write_log.rollback_to(outer).expect("rollback failed");
+        assert_eq!(write_log, WriteLog::default());
+
+        // rolling back the outer savepoint releases the inner one
+        let outer = write_log.savepoint();
+        write_log.write(&key1, vec![1]).expect("write failed");
+        let inner = write_log.savepoint();
+        write_log.write(&key2, vec![2]).expect("write failed");
+        write_log.rollback_to(outer).expect("rollback failed");
+        assert_matches!(
+            write_log.rollback_to(inner).unwrap_err(),
+            Error::UnknownSavepoint
+        );
+        assert_eq!(write_log, WriteLog::default());
+
+        // the inner savepoint is rolled back first
+        let outer = write_log.savepoint();
+        write_log.write(&key1, vec![1]).expect("write failed");
+        let inner = write_log.savepoint();
+        write_log.write(&key2, vec![2]).expect("write failed");
+        write_log.rollback_to(inner).expect("rollback failed");
+        assert!(write_log.read(&key1).0.is_some());
+        assert!(write_log.read(&key2).0.is_none());
+        write_log.write(&key3, vec![3]).expect("write failed");
+        write_log.release(outer).expect("release failed");
+        assert!(write_log.read(&key1).0.is_some());
+        assert!(write_log.read(&key3).0.is_some());
+        assert!(write_log.journal.is_empty());
+    }
+
     #[test]
     fn test_replay_protection_commit() {
         let mut state = crate::testing::TestState::default();
@@ -10,6 +10,7 @@ use namada_core::masp_primitives::merkle_tree::FrozenCommitmentTree;
 use namada_core::masp_primitives::sapling;
 use namada_core::storage::Epoch;
 use namada_core::token::{Denomination, MaspDigitPos};
+use namada_core::{decode, encode, DecodeError};
 
 /// A representation of the conversion state
 #[derive(Debug, Default, BorshSerialize, BorshDeserialize)]
@@ -18,19 +19,125 @@ pub struct ConversionState {
     pub normed_inflation: Option<u128>,
     /// The tree currently containing all the conversions
     pub tree: FrozenCommitmentTree<sapling::Node>,
-    /// A map from token alias to actual address.
+    /// A map from token alias to actual address of the MASP reward tokens,
+    /// synced from the storage on each conversions update.
     pub tokens: BTreeMap<String, Address>,
     /// Map assets to their latest conversion and position in Merkle tree
-    #[allow(clippy::type_complexity)]
-    pub assets: BTreeMap<
-        AssetType,
-        (
-            (Address, Denomination, MaspDigitPos),
-            Epoch,
-            AllowedConversion,
-            usize,
-        ),
-    >,
+    pub assets: BTreeMap<AssetType, ConversionStateAsset>,
+}
+
+/// The key of the conversion state persisted as a single value, used by chains
+/// that have not yet migrated to the split representation
+pub const LEGACY_KEY: &str = "conversion_state";
+/// The key of the persisted conversion state header
+pub const HEADER_KEY: &str = "conversion_state/header";
+/// The key of the persisted conversion tree
+pub const TREE_KEY: &str = "conversion_state/tree";
+/// The prefix of the keys of the persisted conversion state assets
+pub const ASSETS_KEY_PREFIX: &str = "conversion_state/assets/";
+
+/// An entry of the assets map of the conversion state
+pub type ConversionStateAsset = (
+    (Address, Denomination, MaspDigitPos),
+    Epoch,
+    AllowedConversion,
+    usize,
+);
+
+/// The small part of the conversion state that is persisted together
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+struct ConversionStateHeader {
+    normed_inflation: Option<u128>,
+    tree_size: usize,
+    tokens: BTreeMap<String, Address>,
+}
+
+/// The key of a persisted conversion state asset
+pub fn asset_key(asset_type: &AssetType) -> String {
+    format!("{ASSETS_KEY_PREFIX}{asset_type}")
+}
+
+impl ConversionState {
+    /// Encode the conversion state into the key-vals of its split persisted
+    /// representation: a header, the tree and an entry for each asset.
+    pub fn encode_split(&self) -> BTreeMap<String, Vec<u8>> {
+        let header = ConversionStateHeader {
+            normed_inflation: self.normed_inflation,
+            tree_size: self.tree.size(),
+            tokens: self.tokens.clone(),
+        };
+        let mut split = BTreeMap::from([
+            (HEADER_KEY.to_string(), encode(&header)),
+            (TREE_KEY.to_string(), encode(&self.tree)),
+        ]);
+        for (asset_type, asset) in &self.assets {
+            // The asset type is persisted with the entry so that it doesn't
+            // have to be parsed back from the key
+            split.insert(asset_key(asset_type), encode(&(asset_type, asset)));
+        }
+        split
+    }
+
+    /// Decode the conversion state from the key-vals of its split persisted
+    /// representation. Returns `None` if the header or the tree is missing.
+    pub fn decode_split(
+        split: &BTreeMap<String, Vec<u8>>,
+    ) -> Result<Option<Self>, DecodeError> {
+        let (Some(header), Some(tree)) =
+            (split.get(HEADER_KEY), split.get(TREE_KEY))
+        else {
+            return Ok(None);
+        };
+        let ConversionStateHeader {
+            normed_inflation,
+            tree_size,
+            tokens,
+        } = decode(header)?;
+        let tree: FrozenCommitmentTree<sapling::Node> = decode(tree)?;
+        if tree.size() != tree_size {
+            return Err(DecodeError::DeserializationError(
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "The conversion tree size {} doesn't match the \
+                         persisted size {tree_size}",
+                        tree.size()
+                    ),
+                ),
+            ));
+        }
+        let assets = split
+            .iter()
+            .filter(|(key, _)| key.starts_with(ASSETS_KEY_PREFIX))
+            .map(|(_, bytes)| {
+                decode::<(AssetType, ConversionStateAsset)>(bytes)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Some(Self {
+            normed_inflation,
+            tree,
+            tokens,
+            assets,
+        }))
+    }
+}
+
+/// Find the writes needed to turn the `prev` persisted key-vals into the
+/// `next` ones. A `None` value stands for a deletion. The key-vals that are
+/// unchanged are not included.
+pub fn diff_split(
+    prev: &BTreeMap<String, Vec<u8>>,
+    next: &BTreeMap<String, Vec<u8>>,
+) -> Vec<(String, Option<Vec<u8>>)> {
+    let deleted = prev
+        .keys()
+        .filter(|key| !next.contains_key(*key))
+        .map(|key| (key.clone(), None));
+    let written = next
+        .iter()
+        .filter(|(key, value)| prev.get(*key) != Some(*value))
+        .map(|(key, value)| (key.clone(), Some(value.clone())));
+    deleted.chain(written).collect()
 }
 
 /// Able to borrow mutable conversion state.
@@ -41,3 +148,93 @@ pub trait WithConversionState {
     /// Borrow mutable conversion state
     fn conversion_state_mut(&mut self) -> &mut ConversionState;
 }
+
+#[cfg(test)]
+mod tests {
+    use namada_core::address::testing::nam;
+    use namada_core::masp::encode_asset_type;
+    use namada_core::masp_primitives::transaction::components::I128Sum;
+
+    use super::*;
+
+    /// Make a conversion state with the assets of the given number of epochs
+    fn conversion_state(num_epochs: u64) -> ConversionState {
+        let mut state = ConversionState {
+            normed_inflation: Some(1),
+            ..Default::default()
+        };
+        state.tokens.insert("nam".to_string(), nam());
+        for epoch in 0..num_epochs {
+            for digit in MaspDigitPos::iter() {
+                let asset_type = encode_asset_type(
+                    nam(),
+                    Denomination(6),
+                    digit,
+                    Some(Epoch(epoch)),
+                )
+                .unwrap();
+                let conv = I128Sum::from_pair(asset_type, 1).into();
+                let pos = state.assets.len();
+                state.assets.insert(
+                    asset_type,
+                    ((nam(), Denomination(6), digit), Epoch(epoch), conv, pos),
+                );
+            }
+        }
+        state
+    }
+
+    /// Test that the split representation decodes into the same conversion
+    /// state as the legacy one
+    #[test]
+    fn test_split_round_trip() {
+        let state = conversion_state(10);
+        let split = state.encode_split();
+        assert_eq!(split.len(), state.assets.len() + 2);
+
+        let decoded = ConversionState::decode_split(&split).unwrap().unwrap();
+        assert_eq!(encode(&decoded), encode(&state));
+
+        // Without the header, there's nothing to decode
+        let mut split = split;
+        split.remove(HEADER_KEY);
+        assert!(ConversionState::decode_split(&split).unwrap().is_none());
+    }
+
+    /// Test that only the changed key-vals of the split representation are
+    /// rewritten
+    #[test]
+    fn test_split_diff() {
+        let mut state = conversion_state(10);
+
+        // Migrating from the legacy value rewrites everything
+        let legacy = BTreeMap::from([(LEGACY_KEY.to_string(), encode(&state))]);
+        let prev = state.encode_split();
+        let changes = diff_split(&legacy, &prev);
+        assert_eq!(changes.len(), prev.len() + 1);
+        assert!(changes.contains(&(LEGACY_KEY.to_string(), None)));
+
+        // An epoch with no new tokens only changes the header and the updated
+        // asset
+        state.normed_inflation = Some(2);
+        let (_, (_, _, conv, _)) = state.assets.iter_mut().next().unwrap();
+        *conv = I128Sum::zero().into();
+        let changes = diff_split(&prev, &state.encode_split());
+        assert_eq!(changes.len(), 2);
+        let changed_bytes: usize = changes
+            .iter()
+            .map(|(_, value)| value.as_ref().map(Vec::len).unwrap_or_default())
+            .sum();
+        let all_bytes: usize = prev.values().map(Vec::len).sum();
+        assert!(changed_bytes * 10 < all_bytes);
+
+        // Removed assets are deleted
+        let removed = *state.assets.keys().last().unwrap();
+        state.assets.remove(&removed);
+        let changes = diff_split(&prev, &state.encode_split());
+        assert!(changes.contains(&(asset_key(&removed), None)));
+
+        // No changes, no writes
+        assert!(diff_split(&prev, &prev).is_empty());
+    }
+}
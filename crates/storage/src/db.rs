@@ -1,6 +1,9 @@
+use std::cmp::Ordering;
 use std::fmt::Debug;
+use std::iter::Peekable;
 
 use namada_core::address::EstablishedAddressGen;
+use namada_core::borsh::{BorshDeserialize, BorshSerialize};
 use namada_core::hash::{Error as HashError, Hash};
 use namada_core::storage::{
     BlockHash, BlockHeight, BlockResults, Epoch, Epochs, EthEventsQueue,
@@ -12,6 +15,7 @@ use namada_merkle_tree::{
     Error as MerkleTreeError, MerkleTreeStoresRead, MerkleTreeStoresWrite,
     StoreType,
 };
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::conversion_state::ConversionState;
@@ -38,6 +42,19 @@ pub enum Error {
     NoMerkleTree { height: BlockHeight },
     #[error("Code hash error: {0}")]
     InvalidCodeHash(HashError),
+    #[error(
+        "The write batch interrupted while writing its {num_sub_batches} \
+         sub-batches has been rolled forward, restart the node to continue"
+    )]
+    PartialBatchRecovered { num_sub_batches: u64 },
+    #[error(
+        "The diffs to read the block height {height} have been pruned, the \
+         oldest height that can be read is {oldest_height}"
+    )]
+    PrunedHeight {
+        height: BlockHeight,
+        oldest_height: BlockHeight,
+    },
 }
 
 /// A result of a function that may fail
@@ -115,6 +132,53 @@ pub struct BlockStateWrite<'a> {
     pub eth_events_queue: &'a EthEventsQueue,
 }
 
+/// The statistics of a column family of a DB
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+)]
+pub struct ColumnFamilyStats {
+    /// Name of the column family
+    pub name: String,
+    /// Estimated number of keys
+    pub estimated_keys: u64,
+    /// Total size of the live SST files in bytes
+    pub live_sst_bytes: u64,
+    /// Estimated number of bytes pending compaction
+    pub pending_compaction_bytes: u64,
+}
+
+/// The statistics of a DB, per column family
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+)]
+pub struct DBStats {
+    /// Statistics of the column families
+    pub column_families: Vec<ColumnFamilyStats>,
+}
+
+impl DBStats {
+    /// Get the statistics of a column family by its name
+    pub fn column_family(&self, name: &str) -> Option<&ColumnFamilyStats> {
+        self.column_families.iter().find(|cf| cf.name == name)
+    }
+}
+
 /// A database backend.
 pub trait DB: Debug {
     /// A DB's cache
@@ -122,15 +186,21 @@ pub trait DB: Debug {
     /// A handle for batch writes
     type WriteBatch: DBWriteBatch;
 
-    /// Open the database from provided path
+    /// Open the database from provided path. When a `diff_retention` is
+    /// given, the diffs older than that number of blocks are pruned on a
+    /// block commit.
     fn open(
         db_path: impl AsRef<std::path::Path>,
         cache: Option<&Self::Cache>,
+        diff_retention: Option<u64>,
     ) -> Self;
 
     /// Flush data on the memory to persistent them
     fn flush(&self, wait: bool) -> Result<()>;
 
+    /// Get the statistics of the DB
+    fn db_stats(&self) -> Result<DBStats>;
+
     /// Read the last committed block's metadata
     fn read_last_block(&self) -> Result<Option<BlockStateRead>>;
 
@@ -146,6 +216,14 @@ pub trait DB: Debug {
     /// Read the block header with the given height from the DB
     fn read_block_header(&self, height: BlockHeight) -> Result<Option<Header>>;
 
+    /// Read the block headers with the heights in the given inclusive range
+    /// from the DB. The heights without a header are skipped.
+    fn read_block_headers(
+        &self,
+        from: BlockHeight,
+        to: BlockHeight,
+    ) -> Result<Vec<(BlockHeight, Header)>>;
+
     /// Read the merkle tree stores with the given epoch. If a store_type is
     /// given, it reads only the the specified tree. Otherwise, it reads all
     /// trees.
@@ -173,6 +251,14 @@ pub trait DB: Debug {
         last_height: BlockHeight,
     ) -> Result<Option<Vec<u8>>>;
 
+    /// Read the oldest height whose state can be read from the diffs, if any
+    /// diffs have been pruned
+    fn read_diffs_oldest_height(&self) -> Result<Option<BlockHeight>>;
+
+    /// Check that the diffs to read the state as of the given height haven't
+    /// been pruned
+    fn check_diffs_retained(&self, height: BlockHeight) -> Result<()>;
+
     /// Read the value for the account diffs at the corresponding height from
     /// the DB
     fn read_diffs_val(
@@ -232,6 +318,19 @@ pub trait DB: Debug {
         persist_diffs: bool,
     ) -> Result<i64>;
 
+    /// Batch delete all the key-vals with the given prefix from the DB. The
+    /// previous values are kept as diffs at the given height, as in
+    /// `batch_delete_subspace_val`, and `persist_diffs` tells whether the diffs
+    /// of a deleted key are persisted. Returns the deleted keys and the total
+    /// size of the removed values.
+    fn delete_prefix(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        prefix: &Key,
+        persist_diffs: impl Fn(&Key) -> bool,
+    ) -> Result<(Vec<Key>, i64)>;
+
     /// Prune Merkle tree stores at the given epoch
     fn prune_merkle_tree_store(
         &mut self,
@@ -262,6 +361,36 @@ pub trait DB: Debug {
     ) -> Result<()>;
 }
 
+/// The options of a prefix iteration
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IterOpts {
+    /// Iterate in the descending order of the storage keys
+    pub reverse: bool,
+    /// Start the iteration after this key, e.g. the last key of the previous
+    /// page. The key itself is not iterated.
+    pub start_after: Option<Key>,
+    /// The max number of the iterated key-vals
+    pub limit: Option<usize>,
+}
+
+impl IterOpts {
+    /// Check if the given storage key comes before the `start_after` key or
+    /// is the key itself in the iteration order, i.e. it has to be skipped
+    pub fn is_before_start(&self, key: &str) -> bool {
+        match &self.start_after {
+            Some(start_after) => {
+                let start_after = start_after.to_string();
+                if self.reverse {
+                    key >= start_after.as_str()
+                } else {
+                    key <= start_after.as_str()
+                }
+            }
+            None => false,
+        }
+    }
+}
+
 /// A database prefix iterator.
 pub trait DBIter<'iter> {
     /// The concrete type of the iterator
@@ -273,7 +402,21 @@ pub trait DBIter<'iter> {
     ///
     /// Read account subspace key value pairs with the given prefix from the DB,
     /// ordered by the storage keys.
-    fn iter_prefix(&'iter self, prefix: Option<&Key>) -> Self::PrefixIter;
+    fn iter_prefix(&'iter self, prefix: Option<&Key>) -> Self::PrefixIter {
+        self.iter_prefix_opts(prefix, &IterOpts::default())
+    }
+
+    /// WARNING: This only works for values that have been committed to DB.
+    /// To be able to see values written or deleted, but not yet committed,
+    /// use the `StorageWithWriteLog`.
+    ///
+    /// Read account subspace key value pairs with the given prefix from the DB
+    /// in the order, from the start and up to the limit of the given options.
+    fn iter_prefix_opts(
+        &'iter self,
+        prefix: Option<&Key>,
+        opts: &IterOpts,
+    ) -> Self::PrefixIter;
 
     /// Read results subspace key value pairs from the DB
     fn iter_results(&'iter self) -> Self::PrefixIter;
@@ -294,6 +437,70 @@ pub trait DBIter<'iter> {
 
     /// Read replay protection storage from the last block
     fn iter_replay_protection(&'iter self) -> Self::PrefixIter;
+
+    /// Read the subspace diffs at a given height, i.e. the key-vals written
+    /// and deleted in the block, ordered by the storage keys
+    fn iter_diffs_at_height(
+        &'iter self,
+        height: BlockHeight,
+        prefix: Option<&'iter Key>,
+    ) -> DiffsIterator<Self::PrefixIter> {
+        DiffsIterator {
+            old: self.iter_old_diffs(height, prefix).peekable(),
+            new: self.iter_new_diffs(height, prefix).peekable(),
+        }
+    }
+}
+
+/// A change of a subspace key-val in a block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffOp {
+    /// The key-val was written with the value
+    Write(Vec<u8>),
+    /// The key-val was deleted
+    Delete,
+}
+
+/// An iterator of the subspace diffs at a height, merging the old and new
+/// diffs ordered by the storage keys. A key with a new value was written,
+/// otherwise a key with only an old value was deleted.
+#[derive(Debug)]
+pub struct DiffsIterator<I: Iterator> {
+    old: Peekable<I>,
+    new: Peekable<I>,
+}
+
+impl<I> Iterator for DiffsIterator<I>
+where
+    I: Iterator<Item = (String, Vec<u8>, u64)>,
+{
+    type Item = (Key, DiffOp);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ordering = match (self.old.peek(), self.new.peek()) {
+            (Some(old), Some(new)) => old.0.cmp(&new.0),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return None,
+        };
+        let (key, op) = match ordering {
+            Ordering::Less => {
+                let (key, _old_value, _gas) = self.old.next()?;
+                (key, DiffOp::Delete)
+            }
+            Ordering::Equal => {
+                self.old.next();
+                let (key, new_value, _gas) = self.new.next()?;
+                (key, DiffOp::Write(new_value))
+            }
+            Ordering::Greater => {
+                let (key, new_value, _gas) = self.new.next()?;
+                (key, DiffOp::Write(new_value))
+            }
+        };
+        let key = Key::parse(key).expect("the key should be parsable");
+        Some((key, op))
+    }
 }
 
 /// Atomic batch write.
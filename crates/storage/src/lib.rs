@@ -331,6 +331,13 @@ pub mod testing {
         }
     }
 
+    impl TestStorage {
+        /// Set the epoch of the current block
+        pub fn set_block_epoch(&mut self, epoch: Epoch) {
+            self.epoch = epoch;
+        }
+    }
+
     impl StorageRead for TestStorage {
         type PrefixIter<'iter> = PrefixIter<'iter> where Self: 'iter;
 
@@ -5,6 +5,7 @@ use std::collections::{btree_map, BTreeMap};
 use std::ops::Bound::{Excluded, Included};
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::Mutex;
 
 use itertools::Either;
 use namada_core::borsh::{BorshDeserialize, BorshSerializeExt};
@@ -22,7 +23,8 @@ use namada_replay_protection as replay_protection;
 
 use crate::conversion_state::ConversionState;
 use crate::db::{
-    BlockStateRead, BlockStateWrite, DBIter, DBWriteBatch, Error, Result, DB,
+    BlockStateRead, BlockStateWrite, ColumnFamilyStats, DBIter, DBStats,
+    DBWriteBatch, Error, IterOpts, Result, DB,
 };
 use crate::tx_queue::TxQueue;
 use crate::types::{KVBytes, PrefixIterator};
@@ -38,6 +40,8 @@ pub struct MockDB(
     // The state is wrapped in `RefCell` to allow modifying it directly from
     // batch write method (which requires immutable self ref).
     RefCell<BTreeMap<String, Vec<u8>>>,
+    // The number of reads of each subspace key
+    Mutex<BTreeMap<Key, usize>>,
 );
 
 // The `MockDB` is not `Sync`, but we're sharing it across threads for reading
@@ -50,12 +54,24 @@ unsafe impl Sync for MockDB {}
 #[derive(Debug, Default)]
 pub struct MockDBWriteBatch;
 
+impl MockDB {
+    /// Get the number of times the value of the subspace key has been read
+    pub fn subspace_reads(&self, key: &Key) -> usize {
+        self.1.lock().unwrap().get(key).copied().unwrap_or_default()
+    }
+}
+
 impl DB for MockDB {
     /// There is no cache for MockDB
     type Cache = ();
     type WriteBatch = MockDBWriteBatch;
 
-    fn open(_db_path: impl AsRef<Path>, _cache: Option<&Self::Cache>) -> Self {
+    /// The diffs of MockDB are never pruned
+    fn open(
+        _db_path: impl AsRef<Path>,
+        _cache: Option<&Self::Cache>,
+        _diff_retention: Option<u64>,
+    ) -> Self {
         Self::default()
     }
 
@@ -63,6 +79,20 @@ impl DB for MockDB {
         Ok(())
     }
 
+    fn db_stats(&self) -> Result<DBStats> {
+        let db = self.0.borrow();
+        let live_bytes: usize =
+            db.iter().map(|(key, val)| key.len() + val.len()).sum();
+        Ok(DBStats {
+            column_families: vec![ColumnFamilyStats {
+                name: "mock".to_string(),
+                estimated_keys: db.len() as u64,
+                live_sst_bytes: live_bytes as u64,
+                pending_compaction_bytes: 0,
+            }],
+        })
+    }
+
     fn read_last_block(&self) -> Result<Option<BlockStateRead>> {
         // Block height
         let height: BlockHeight = match self.0.borrow().get("height") {
@@ -375,6 +405,21 @@ impl DB for MockDB {
         }
     }
 
+    fn read_block_headers(
+        &self,
+        from: BlockHeight,
+        to: BlockHeight,
+    ) -> Result<Vec<(BlockHeight, Header)>> {
+        let mut headers = vec![];
+        for height in from.0..=to.0 {
+            let height = BlockHeight(height);
+            if let Some(header) = self.read_block_header(height)? {
+                headers.push((height, header));
+            }
+        }
+        Ok(headers)
+    }
+
     fn read_merkle_tree_stores(
         &self,
         epoch: Epoch,
@@ -451,20 +496,51 @@ impl DB for MockDB {
     }
 
     fn read_subspace_val(&self, key: &Key) -> Result<Option<Vec<u8>>> {
+        *self.1.lock().unwrap().entry(key.clone()).or_default() += 1;
         let key = Key::parse(SUBSPACE_CF).map_err(Error::KeyError)?.join(key);
         Ok(self.0.borrow().get(&key.to_string()).cloned())
     }
 
+    fn read_diffs_oldest_height(&self) -> Result<Option<BlockHeight>> {
+        Ok(None)
+    }
+
+    fn check_diffs_retained(&self, _height: BlockHeight) -> Result<()> {
+        Ok(())
+    }
+
     fn read_subspace_val_with_height(
         &self,
         key: &Key,
-        _height: BlockHeight,
-        _last_height: BlockHeight,
+        height: BlockHeight,
+        last_height: BlockHeight,
     ) -> Result<Option<Vec<u8>>> {
-        tracing::warn!(
-            "read_subspace_val_with_height is not implemented, will read \
-             subspace value from latest height"
-        );
+        // If it has a "new" val, it was written at this height
+        if let Some(new_val) = self.read_diffs_val(key, height, false)? {
+            return Ok(Some(new_val));
+        }
+        // If it has an "old" val, it was deleted at this height
+        if self.read_diffs_val(key, height, true)?.is_some() {
+            return Ok(None);
+        }
+
+        // If the value didn't change at the given height, we try to look for it
+        // at successor heights, up to the `last_height`
+        let mut next_height = height.next_height();
+        while next_height <= last_height {
+            // If it has an "old" val, it's the one we're looking for
+            if let Some(old_val) =
+                self.read_diffs_val(key, next_height, true)?
+            {
+                return Ok(Some(old_val));
+            }
+            // If the value was created at this height instead, it wasn't
+            // present before
+            if self.read_diffs_val(key, next_height, false)?.is_some() {
+                return Ok(None);
+            }
+            next_height = next_height.next_height();
+        }
         self.read_subspace_val(key)
     }
 
@@ -618,6 +694,29 @@ impl DB for MockDB {
         Ok(size_diff)
     }
 
+    fn delete_prefix(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        prefix: &Key,
+        persist_diffs: impl Fn(&Key) -> bool,
+    ) -> Result<(Vec<Key>, i64)> {
+        let keys = self
+            .iter_prefix(Some(prefix))
+            .map(|(key, _val, _gas)| Key::parse(key).map_err(Error::KeyError))
+            .collect::<Result<Vec<_>>>()?;
+        let mut deleted_len = 0;
+        for key in &keys {
+            deleted_len += self.batch_delete_subspace_val(
+                batch,
+                height,
+                key,
+                persist_diffs(key),
+            )?;
+        }
+        Ok((keys, deleted_len))
+    }
+
     fn prune_merkle_tree_store(
         &mut self,
         _batch: &mut Self::WriteBatch,
@@ -679,7 +778,11 @@ impl DB for MockDB {
 impl<'iter> DBIter<'iter> for MockDB {
     type PrefixIter = MockPrefixIterator;
 
-    fn iter_prefix(&'iter self, prefix: Option<&Key>) -> MockPrefixIterator {
+    fn iter_prefix_opts(
+        &'iter self,
+        prefix: Option<&Key>,
+        opts: &IterOpts,
+    ) -> MockPrefixIterator {
         let stripped_prefix = "subspace/".to_owned();
         let prefix = format!(
             "{}{}",
@@ -695,15 +798,33 @@ impl<'iter> DBIter<'iter> for MockDB {
                 None => "".to_string(),
             }
         );
-        let iter = self.0.borrow().clone().into_iter();
-        MockPrefixIterator::new(MockIterator { prefix, iter }, stripped_prefix)
+        // Apply the start here as the keys are compared without the stripped
+        // prefix
+        let matches: BTreeMap<String, Vec<u8>> = self
+            .0
+            .borrow()
+            .iter()
+            .filter(|(key, _)| {
+                key.starts_with(&prefix)
+                    && !opts.is_before_start(&key[stripped_prefix.len()..])
+            })
+            .map(|(key, val)| (key.clone(), val.clone()))
+            .collect();
+        let iter = MockIterator {
+            prefix,
+            iter: matches.into_iter(),
+            reverse: opts.reverse,
+            remaining: opts.limit,
+        };
+        MockPrefixIterator::new(iter, stripped_prefix)
     }
 
     fn iter_results(&'iter self) -> MockPrefixIterator {
         let stripped_prefix = "results/".to_owned();
         let prefix = "results".to_owned();
         let iter = self.0.borrow().clone().into_iter();
-        MockPrefixIterator::new(MockIterator { prefix, iter }, stripped_prefix)
+        let iter = MockIterator::new(prefix, iter);
+        MockPrefixIterator::new(iter, stripped_prefix)
     }
 
     fn iter_old_diffs(
@@ -724,7 +845,8 @@ impl<'iter> DBIter<'iter> for MockDB {
             })
             .unwrap_or("".to_string());
         let iter = self.0.borrow().clone().into_iter();
-        MockPrefixIterator::new(MockIterator { prefix, iter }, stripped_prefix)
+        let iter = MockIterator::new(prefix, iter);
+        MockPrefixIterator::new(iter, stripped_prefix)
     }
 
     fn iter_new_diffs(
@@ -745,7 +867,8 @@ impl<'iter> DBIter<'iter> for MockDB {
             })
             .unwrap_or("".to_string());
         let iter = self.0.borrow().clone().into_iter();
-        MockPrefixIterator::new(MockIterator { prefix, iter }, stripped_prefix)
+        let iter = MockIterator::new(prefix, iter);
+        MockPrefixIterator::new(iter, stripped_prefix)
     }
 
     fn iter_replay_protection(&'iter self) -> Self::PrefixIter {
@@ -753,7 +876,8 @@ impl<'iter> DBIter<'iter> for MockDB {
             format!("replay_protection/{}/", replay_protection::last_prefix());
         let prefix = stripped_prefix.clone();
         let iter = self.0.borrow().clone().into_iter();
-        MockPrefixIterator::new(MockIterator { prefix, iter }, stripped_prefix)
+        let iter = MockIterator::new(prefix, iter);
+        MockPrefixIterator::new(iter, stripped_prefix)
     }
 }
 
@@ -763,6 +887,26 @@ pub struct MockIterator {
     prefix: String,
     /// The concrete iterator
     pub iter: btree_map::IntoIter<String, Vec<u8>>,
+    /// Iterate in the descending order of the keys
+    reverse: bool,
+    /// The number of the remaining key-vals to be iterated, if limited
+    remaining: Option<usize>,
+}
+
+impl MockIterator {
+    /// Iterate the key-vals matching the prefix in the ascending order of the
+    /// keys
+    pub fn new(
+        prefix: String,
+        iter: btree_map::IntoIter<String, Vec<u8>>,
+    ) -> Self {
+        Self {
+            prefix,
+            iter,
+            reverse: false,
+            remaining: None,
+        }
+    }
 }
 
 /// A prefix iterator for the [`MockDB`].
@@ -772,15 +916,25 @@ impl Iterator for MockIterator {
     type Item = Result<KVBytes>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        for (key, val) in &mut self.iter {
+        if self.remaining == Some(0) {
+            return None;
+        }
+        loop {
+            let (key, val) = if self.reverse {
+                self.iter.next_back()?
+            } else {
+                self.iter.next()?
+            };
             if key.starts_with(&self.prefix) {
+                if let Some(remaining) = self.remaining.as_mut() {
+                    *remaining -= 1;
+                }
                 return Some(Ok((
                     Box::from(key.as_bytes()),
                     Box::from(val.as_slice()),
                 )));
             }
         }
-        None
     }
 }
 
@@ -0,0 +1,282 @@
+//! Tests of the ledger engine facade, driven like an out-of-tree consumer
+//! would, with the in-memory DB.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use borsh_ext::BorshSerializeExt;
+use namada::core::address::testing::nam;
+use namada::core::chain::ChainId;
+use namada::core::dec::Dec;
+use namada::core::hash::Hash;
+use namada::core::storage::{BlockHash, Header, Key, TxIndex};
+use namada::core::time::{DateTimeUtc, DurationSecs};
+use namada::core::token;
+use namada::ledger::engine::{self, Engine};
+use namada::ledger::gas::TxGasMeter;
+use namada::ledger::parameters::{self, EpochDuration, Parameters};
+use namada::ledger::pos::{write_pos_params, OwnedPosParams};
+use namada::ledger::protocol;
+use namada::state::merkle_tree::MerkleRoot;
+use namada::state::mockdb::MockDB;
+use namada::state::{FullAccessState, Sha256Hasher, StorageRead};
+use namada::tx::data::{DecryptedTx, ResultCode, TxType};
+use namada::tx::{Code, Data, Tx};
+use namada::vm::wasm::{TxCache, VpCache};
+use namada::vm::WasmCacheRwAccess;
+use namada_test_utils::tx_data::TxWriteData;
+use namada_test_utils::TestWasms;
+use tempfile::TempDir;
+
+type State = FullAccessState<MockDB, Sha256Hasher>;
+
+const MAX_BLOCK_GAS: u64 = 20_000_000;
+
+/// Open an in-memory state with the protocol and PoS parameters and the tx
+/// wasm
+fn new_state() -> State {
+    let mut state =
+        State::open("", None, ChainId::default(), nam(), None, |_key| true)
+            .with_reserved_prefixes(protocol::reserved_prefixes());
+    let params = Parameters {
+        max_tx_bytes: 1024 * 1024,
+        max_proposal_bytes: Default::default(),
+        max_block_gas: MAX_BLOCK_GAS,
+        epoch_duration: EpochDuration {
+            min_num_of_blocks: 10,
+            min_duration: DurationSecs(60),
+        },
+        max_expected_time_per_block: DurationSecs(30),
+        vp_allowlist: vec![],
+        tx_allowlist: vec![],
+        implicit_vp_code_hash: Some(Hash::zero()),
+        epochs_per_year: 100,
+        max_signatures_per_transaction: 15,
+        staked_ratio: Dec::zero(),
+        pos_inflation_amount: token::Amount::zero(),
+        fee_unshielding_gas_limit: 20_000,
+        fee_unshielding_descriptions_limit: 15,
+        minimum_gas_price: BTreeMap::default(),
+    };
+    parameters::init_storage(&params, &mut state).unwrap();
+    // The PoS parameters are read by the PoS updates of every block
+    write_pos_params(&mut state, &OwnedPosParams::default()).unwrap();
+
+    let tx_write = TestWasms::TxWriteStorageKey.read_bytes();
+    let code_hash = Hash::sha256(&tx_write);
+    let code_len = (tx_write.len() as u64).serialize_to_vec();
+    state
+        .db_write(&Key::wasm_code(&code_hash), tx_write)
+        .unwrap();
+    state
+        .db_write(&Key::wasm_code_len(&code_hash), code_len)
+        .unwrap();
+    state
+}
+
+fn new_caches() -> (
+    VpCache<WasmCacheRwAccess>,
+    TxCache<WasmCacheRwAccess>,
+    TempDir,
+) {
+    let dir = tempfile::tempdir().unwrap();
+    let vp_cache = VpCache::new(dir.path().join("vp"), 50 * 1024 * 1024);
+    let tx_cache = TxCache::new(dir.path().join("tx"), 50 * 1024 * 1024);
+    (vp_cache, tx_cache, dir)
+}
+
+fn header(seconds: i64) -> Header {
+    Header {
+        hash: Hash::default(),
+        time: DateTimeUtc::from_unix_timestamp(seconds).unwrap(),
+        next_validators_hash: Hash::default(),
+    }
+}
+
+fn counter_key() -> Key {
+    Key::parse("engine_test_counter").unwrap()
+}
+
+/// Build a tx writing the given data with the `tx_write` wasm
+fn tx_write_bytes(data: Vec<u8>) -> Vec<u8> {
+    let mut tx = Tx::from_type(TxType::Decrypted(DecryptedTx::Decrypted));
+    tx.set_code(Code::new(TestWasms::TxWriteStorageKey.read_bytes(), None));
+    tx.set_data(Data::new(data));
+    tx.to_bytes()
+}
+
+fn write_counter_tx(value: u64) -> Vec<u8> {
+    tx_write_bytes(
+        TxWriteData {
+            key: counter_key(),
+            value: value.serialize_to_vec(),
+        }
+        .serialize_to_vec(),
+    )
+}
+
+/// The scenarios: each block is a list of txs. The data of the last tx can't
+/// be decoded by the wasm, so its execution fails.
+fn blocks() -> Vec<Vec<Vec<u8>>> {
+    vec![
+        vec![write_counter_tx(1), write_counter_tx(2)],
+        vec![write_counter_tx(3), tx_write_bytes(vec![0xff; 8])],
+    ]
+}
+
+/// Apply the blocks to the state with the protocol functions as the node did
+/// before the engine. Returns the roots of the blocks and the rendered
+/// results of the txs.
+fn apply_with_protocol(
+    state: &mut State,
+    blocks: &[Vec<Vec<u8>>],
+) -> (Vec<MerkleRoot>, Vec<String>) {
+    let (mut vp_cache, mut tx_cache, _dir) = new_caches();
+    let mut roots = vec![];
+    let mut results = vec![];
+    for (block_index, txs) in blocks.iter().enumerate() {
+        let height = state.in_mem().get_last_block_height() + 1;
+        state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), height)
+            .unwrap();
+        let header = header(block_index as i64);
+        let header_time = header.time;
+        state.in_mem_mut().set_header(header).unwrap();
+        state.update_epoch(height, header_time).unwrap();
+
+        for (tx_index, tx_bytes) in txs.iter().enumerate() {
+            let tx = Tx::try_from(&tx_bytes[..]).unwrap();
+            let tx_gas_meter = RefCell::new(TxGasMeter::new(MAX_BLOCK_GAS));
+            let result =
+                protocol::check_tx_allowed(&tx, state).and_then(|()| {
+                    protocol::dispatch_tx(
+                        tx,
+                        tx_bytes,
+                        TxIndex(tx_index as u32),
+                        &tx_gas_meter,
+                        state,
+                        &mut vp_cache,
+                        &mut tx_cache,
+                        None,
+                    )
+                });
+            match result {
+                Ok(result) if result.is_accepted() => {
+                    state.commit_tx();
+                    state.in_mem_mut().block.results.accept(tx_index);
+                    results.push(result.to_string());
+                }
+                Ok(result) => {
+                    state.drop_tx();
+                    results.push(result.to_string());
+                }
+                Err(err) => {
+                    state.drop_tx();
+                    results.push(err.to_string());
+                }
+            }
+        }
+        state.commit_block().unwrap();
+        roots.push(state.in_mem().merkle_root());
+    }
+    (roots, results)
+}
+
+/// Apply the blocks with the engine
+fn apply_with_engine(
+    engine: &mut Engine<MockDB, Sha256Hasher, WasmCacheRwAccess>,
+    blocks: &[Vec<Vec<u8>>],
+) -> (Vec<MerkleRoot>, Vec<String>) {
+    let mut roots = vec![];
+    let mut results = vec![];
+    for (block_index, txs) in blocks.iter().enumerate() {
+        engine
+            .begin_block(
+                header(block_index as i64),
+                BlockHash::default(),
+                vec![],
+                vec![],
+            )
+            .unwrap();
+        for tx_bytes in txs {
+            let result = match engine.apply_tx(tx_bytes) {
+                Ok(result) => result.to_string(),
+                Err(engine::Error::TxApply(err)) => err.to_string(),
+                Err(err) => panic!("Unexpected engine error {err}"),
+            };
+            results.push(result);
+        }
+        let events = engine.end_block(None).unwrap();
+        assert_eq!(events.len(), txs.len());
+        roots.push(engine.commit().unwrap());
+    }
+    (roots, results)
+}
+
+/// Test that the engine produces the same roots and tx results as applying
+/// the txs with the protocol functions.
+#[test]
+fn test_engine_matches_protocol_path() {
+    let blocks = blocks();
+
+    let mut state = new_state();
+    let expected = apply_with_protocol(&mut state, &blocks);
+
+    let (vp_cache, tx_cache, _dir) = new_caches();
+    let mut engine = Engine::new(new_state(), vp_cache, tx_cache);
+    let actual = apply_with_engine(&mut engine, &blocks);
+
+    assert_eq!(actual, expected);
+}
+
+/// A minimal example of embedding the engine
+#[test]
+fn test_engine_example() {
+    let (vp_cache, tx_cache, _dir) = new_caches();
+    let mut engine = Engine::new(new_state(), vp_cache, tx_cache);
+
+    // a tx can't be applied outside of a block
+    assert!(matches!(
+        engine.apply_tx(&write_counter_tx(1)),
+        Err(engine::Error::NoBlock)
+    ));
+
+    engine
+        .begin_block(header(0), BlockHash::default(), vec![], vec![])
+        .unwrap();
+    let result = engine.apply_tx(&write_counter_tx(1)).unwrap();
+    assert!(result.is_accepted());
+    assert!(result.changed_keys.contains(&counter_key()));
+    let failed = engine.apply_tx(&tx_write_bytes(vec![0xff; 8]));
+    assert!(matches!(failed, Err(engine::Error::TxApply(_))));
+    // only decrypted txs can be applied
+    let raw_tx = Tx::from_type(TxType::Raw).to_bytes();
+    assert!(matches!(
+        engine.apply_tx(&raw_tx),
+        Err(engine::Error::UnsupportedTxType)
+    ));
+
+    let events = engine.end_block(None).unwrap();
+    let codes: Vec<_> = events.iter().map(|event| &event["code"]).collect();
+    assert_eq!(
+        codes,
+        [
+            &String::from(ResultCode::Ok),
+            &String::from(ResultCode::WasmRuntimeError)
+        ]
+    );
+    let first_root = engine.commit().unwrap();
+
+    engine
+        .begin_block(header(1), BlockHash::default(), vec![], vec![])
+        .unwrap();
+    engine.apply_tx(&write_counter_tx(2)).unwrap();
+    engine.end_block(None).unwrap();
+    let second_root = engine.commit().unwrap();
+    assert_ne!(first_root, second_root);
+
+    let state = engine.into_state();
+    let counter: u64 = state.read(&counter_key()).unwrap().unwrap();
+    assert_eq!(counter, 2);
+}
@@ -2129,16 +2129,13 @@ fn dynamic_assets() -> Result<()> {
         // Only distribute rewards for NAM tokens
         let state = &mut node.shell.lock().unwrap().state;
         let tokens = state.in_mem().conversion_state.tokens.clone();
-        state
-            .in_mem_mut()
-            .conversion_state
-            .tokens
-            .insert(btc.clone(), tokens[&btc].clone());
-        state
-            .in_mem_mut()
-            .conversion_state
-            .tokens
-            .retain(|k, _v| *k == nam);
+        for (alias, token) in &tokens {
+            if *alias != nam {
+                state
+                    .delete(&token::storage_key::masp_reward_token_key(token))
+                    .unwrap();
+            }
+        }
         tokens
     };
     // add necessary viewing keys to shielded context
@@ -2226,10 +2223,11 @@ fn dynamic_assets() -> Result<()> {
         // epoch
         let state = &mut node.shell.lock().unwrap().state;
         state
-            .in_mem_mut()
-            .conversion_state
-            .tokens
-            .insert(btc.clone(), tokens[&btc].clone());
+            .write(
+                &token::storage_key::masp_reward_token_key(&tokens[&btc]),
+                btc.clone(),
+            )
+            .unwrap();
     }
 
     // Wait till epoch boundary
@@ -2456,7 +2454,9 @@ fn dynamic_assets() -> Result<()> {
     {
         // Stop decoding and distributing shielded rewards for BTC in next epoch
         let state = &mut node.shell.lock().unwrap().state;
-        state.in_mem_mut().conversion_state.tokens.remove(&btc);
+        state
+            .delete(&token::storage_key::masp_reward_token_key(&tokens[&btc]))
+            .unwrap();
     }
 
     // Wait till epoch boundary
@@ -12,6 +12,8 @@ pub use vm_host_env::{ibc, tx, vp};
 #[cfg(test)]
 mod e2e;
 #[cfg(test)]
+mod engine;
+#[cfg(test)]
 mod integration;
 pub mod native_vp;
 pub mod storage;
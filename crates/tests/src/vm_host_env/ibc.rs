@@ -55,13 +55,15 @@ pub use namada::ibc::core::host::types::identifiers::{
 };
 use namada::ibc::primitives::proto::{Any, Protobuf};
 use namada::ibc::primitives::Timestamp;
+use namada::ibc::{MsgTransferWithFee, PacketFee};
 use namada::ledger::gas::VpGasMeter;
 pub use namada::ledger::ibc::storage::{
     ack_key, channel_counter_key, channel_key, client_counter_key,
     client_state_key, client_update_height_key, client_update_timestamp_key,
     commitment_key, connection_counter_key, connection_key,
-    consensus_state_key, ibc_token, next_sequence_ack_key,
-    next_sequence_recv_key, next_sequence_send_key, port_key, receipt_key,
+    consensus_state_key, fee_enabled_key, ibc_token, next_sequence_ack_key,
+    next_sequence_recv_key, next_sequence_send_key, packet_fee_key, port_key,
+    receipt_key, U64Codec,
 };
 use namada::ledger::native_vp::ibc::{
     get_dummy_genesis_validator, get_dummy_header as tm_dummy_header, Ibc,
@@ -332,7 +334,7 @@ pub fn prepare_client() -> (ClientId, Any, HashMap<storage::Key, Vec<u8>>) {
     writes.insert(key, bytes);
     // client counter
     let key = client_counter_key();
-    let bytes = 1_u64.to_be_bytes().to_vec();
+    let bytes = U64Codec::Borsh.encode(1);
     writes.insert(key, bytes);
 
     (client_id, client_state.into(), writes)
@@ -370,7 +372,7 @@ pub fn prepare_opened_connection(
     writes.insert(key, bytes);
     // connection counter
     let key = connection_counter_key();
-    let bytes = 1_u64.to_be_bytes().to_vec();
+    let bytes = U64Codec::Borsh.encode(1);
     writes.insert(key, bytes);
 
     (conn_id, writes)
@@ -655,6 +657,23 @@ pub fn msg_transfer(
     }
 }
 
+pub fn msg_transfer_with_fee(
+    port_id: PortId,
+    channel_id: ChannelId,
+    token: &Address,
+    sender: &Address,
+) -> MsgTransferWithFee {
+    let message = msg_transfer(port_id, channel_id, token.to_string(), sender);
+    let fee = PacketFee {
+        payer: sender.clone(),
+        token: token.clone(),
+        recv_fee: DenominatedAmount::new(Amount::from_u64(10), 0.into()),
+        ack_fee: DenominatedAmount::new(Amount::from_u64(5), 0.into()),
+        timeout_fee: DenominatedAmount::new(Amount::from_u64(3), 0.into()),
+    };
+    MsgTransferWithFee { message, fee }
+}
+
 pub fn set_timeout_timestamp(msg: &mut MsgTransfer) {
     msg.timeout_timestamp_on_b =
         (msg.timeout_timestamp_on_b - Duration::from_secs(201)).unwrap();
@@ -0,0 +1,638 @@
+//! A state machine test of the conservation of the tokens transferred over IBC.
+//!
+//! A random sequence of packets sent to and received from a modeled
+//! counterparty chain is executed on a test chain: sends of the native token
+//! and of the vouchers, acknowledgements with a success or an error, timeouts
+//! and receives of the counterparty's token or of the native token coming back.
+//! Every step must be accepted by the IBC and the multitoken VPs and after
+//! each step the escrowed, minted and owned amounts are checked against the
+//! model.
+
+use std::collections::BTreeMap;
+
+use namada::core::address::{Address, InternalAddress};
+use namada::core::storage::{BlockHash, BlockHeight, Key};
+use namada::ibc::apps::transfer::types::msgs::transfer::MsgTransfer;
+use namada::ibc::apps::transfer::types::packet::PacketData;
+use namada::ibc::primitives::Msg;
+use namada::ledger::native_vp::ibc::get_dummy_header as tm_dummy_header;
+use namada::token::{self, Amount};
+use namada::tx::Tx;
+use namada_tx_prelude::chain::ChainId;
+use namada_tx_prelude::{StorageRead, StorageWrite};
+use proptest::prelude::*;
+use proptest::strategy::Union;
+use proptest::test_runner::Config;
+use proptest_state_machine::{
+    prop_state_machine, ReferenceStateMachine, StateMachineTest,
+};
+use prost::Message;
+use test_log::test;
+
+use super::{ibc, tx};
+use crate::tx::tx_host_env;
+
+/// The base denomination of the counterparty's native token
+const COUNTERPARTY_DENOM: &str = "uatom";
+/// The native token balance of the account initialized by
+/// [`ibc::init_storage`] in the IBC amount units
+const INIT_BALANCE: u64 = 100;
+/// The maximum amount received from the counterparty in a single packet
+const MAX_RECEIVED_AMOUNT: u64 = 50;
+
+prop_state_machine! {
+    #![proptest_config(Config {
+        // Each case starts a new chain with an opened channel, so the number
+        // of transitions is kept low to run a few hundred cases in CI
+        cases: 200,
+        verbose: 1,
+        .. Config::default()
+    })]
+    #[test]
+    fn ibc_conservation_state_machine_test(
+        sequential 1..15 => ConcreteIbcState
+    );
+}
+
+/// A deliberately introduced bug crediting an extra amount on a refund must be
+/// caught by the conservation invariant.
+#[test]
+fn test_ibc_conservation_catches_refund_bug() {
+    let mut ref_state = AbstractIbcState::default();
+    let mut state = ConcreteIbcState::init_test(&ref_state);
+    state.refund_bug = true;
+
+    for transition in [
+        Transition::SendNative {
+            amount: 10,
+            expired: true,
+        },
+        Transition::Timeout { sequence: 1 },
+    ] {
+        assert!(AbstractIbcState::preconditions(&ref_state, &transition));
+        ref_state = AbstractIbcState::apply(ref_state, &transition);
+        state = ConcreteIbcState::apply(state, &ref_state, transition);
+    }
+
+    let err = state
+        .check_conservation(&ref_state)
+        .expect_err("The refund bug must break the conservation invariant");
+    assert!(err.contains("native balance"), "Unexpected error: {err}");
+}
+
+/// The modeled state of the test chain and its counterparty
+#[derive(Clone, Debug)]
+struct AbstractIbcState {
+    /// The native token balance of the account
+    balance: u64,
+    /// The voucher balance of the account
+    vouchers: u64,
+    /// The sent packets waiting for an ack or a timeout by their sequence
+    pending: BTreeMap<u64, PendingPacket>,
+    /// The native tokens delivered to the counterparty and not returned yet
+    native_on_counterparty: u64,
+    /// The sequence of the next sent packet
+    next_send_sequence: u64,
+}
+
+/// A modeled packet sent to the counterparty
+#[derive(Clone, Debug)]
+struct PendingPacket {
+    /// `true` for the native token, `false` for the vouchers
+    is_native: bool,
+    /// The transferred amount
+    amount: u64,
+    /// Whether the packet times out instead of being received
+    expired: bool,
+}
+
+/// The IBC operations on the test chain
+#[derive(Clone, Debug)]
+enum Transition {
+    /// Send the native token to the counterparty
+    SendNative { amount: u64, expired: bool },
+    /// Send the vouchers back to the counterparty
+    SendVoucher { amount: u64, expired: bool },
+    /// Acknowledge a sent packet with a success or an error
+    Ack { sequence: u64, success: bool },
+    /// Time out a sent packet
+    Timeout { sequence: u64 },
+    /// Receive the counterparty's token as vouchers
+    ReceiveVoucher { amount: u64 },
+    /// Receive the native token back from the counterparty
+    ReceiveNative { amount: u64 },
+}
+
+impl Default for AbstractIbcState {
+    fn default() -> Self {
+        Self {
+            balance: INIT_BALANCE,
+            vouchers: 0,
+            pending: BTreeMap::new(),
+            native_on_counterparty: 0,
+            next_send_sequence: 1,
+        }
+    }
+}
+
+impl AbstractIbcState {
+    /// The native tokens that left the chain and haven't come back
+    fn escrowed(&self) -> u64 {
+        let in_flight: u64 = self
+            .pending
+            .values()
+            .filter(|packet| packet.is_native)
+            .map(|packet| packet.amount)
+            .sum();
+        in_flight + self.native_on_counterparty
+    }
+
+    /// Find the sequences of the pending packets that have or haven't expired
+    fn pending_sequences(&self, expired: bool) -> Vec<u64> {
+        self.pending
+            .iter()
+            .filter(|(_, packet)| packet.expired == expired)
+            .map(|(sequence, _)| *sequence)
+            .collect()
+    }
+
+    fn send(&mut self, is_native: bool, amount: u64, expired: bool) {
+        let packet = PendingPacket {
+            is_native,
+            amount,
+            expired,
+        };
+        self.pending.insert(self.next_send_sequence, packet);
+        self.next_send_sequence += 1;
+    }
+
+    fn refund(&mut self, sequence: u64) {
+        let packet = self.pending.remove(&sequence).unwrap();
+        if packet.is_native {
+            self.balance += packet.amount;
+        } else {
+            self.vouchers += packet.amount;
+        }
+    }
+}
+
+impl ReferenceStateMachine for AbstractIbcState {
+    type State = Self;
+    type Transition = Transition;
+
+    fn init_state() -> BoxedStrategy<Self::State> {
+        Just(Self::default()).boxed()
+    }
+
+    fn transitions(state: &Self::State) -> BoxedStrategy<Self::Transition> {
+        let mut transitions = vec![
+            (1..=MAX_RECEIVED_AMOUNT)
+                .prop_map(|amount| Transition::ReceiveVoucher { amount })
+                .boxed(),
+        ];
+        if state.balance > 0 {
+            transitions.push(
+                (1..=state.balance, any::<bool>())
+                    .prop_map(|(amount, expired)| Transition::SendNative {
+                        amount,
+                        expired,
+                    })
+                    .boxed(),
+            );
+        }
+        if state.vouchers > 0 {
+            transitions.push(
+                (1..=state.vouchers, any::<bool>())
+                    .prop_map(|(amount, expired)| Transition::SendVoucher {
+                        amount,
+                        expired,
+                    })
+                    .boxed(),
+            );
+        }
+        if state.native_on_counterparty > 0 {
+            transitions.push(
+                (1..=state.native_on_counterparty)
+                    .prop_map(|amount| Transition::ReceiveNative { amount })
+                    .boxed(),
+            );
+        }
+        let unexpired = state.pending_sequences(false);
+        if !unexpired.is_empty() {
+            transitions.push(
+                (proptest::sample::select(unexpired), any::<bool>())
+                    .prop_map(|(sequence, success)| Transition::Ack {
+                        sequence,
+                        success,
+                    })
+                    .boxed(),
+            );
+        }
+        let expired = state.pending_sequences(true);
+        if !expired.is_empty() {
+            transitions.push(
+                proptest::sample::select(expired)
+                    .prop_map(|sequence| Transition::Timeout { sequence })
+                    .boxed(),
+            );
+        }
+        Union::new(transitions).boxed()
+    }
+
+    fn apply(mut state: Self::State, transition: &Self::Transition) -> Self {
+        match transition {
+            Transition::SendNative { amount, expired } => {
+                state.balance -= amount;
+                state.send(true, *amount, *expired);
+            }
+            Transition::SendVoucher { amount, expired } => {
+                state.vouchers -= amount;
+                state.send(false, *amount, *expired);
+            }
+            Transition::Ack {
+                sequence,
+                success: true,
+            } => {
+                let packet = state.pending.remove(sequence).unwrap();
+                // The burned vouchers are gone for good
+                if packet.is_native {
+                    state.native_on_counterparty += packet.amount;
+                }
+            }
+            Transition::Ack {
+                sequence,
+                success: false,
+            }
+            | Transition::Timeout { sequence } => {
+                state.refund(*sequence);
+            }
+            Transition::ReceiveVoucher { amount } => {
+                state.vouchers += amount;
+            }
+            Transition::ReceiveNative { amount } => {
+                state.native_on_counterparty -= amount;
+                state.balance += amount;
+            }
+        }
+        state
+    }
+
+    fn preconditions(
+        state: &Self::State,
+        transition: &Self::Transition,
+    ) -> bool {
+        match transition {
+            Transition::SendNative { amount, .. } => *amount <= state.balance,
+            Transition::SendVoucher { amount, .. } => *amount <= state.vouchers,
+            Transition::Ack { sequence, .. } => state
+                .pending
+                .get(sequence)
+                .map(|packet| !packet.expired)
+                .unwrap_or_default(),
+            Transition::Timeout { sequence } => state
+                .pending
+                .get(sequence)
+                .map(|packet| packet.expired)
+                .unwrap_or_default(),
+            Transition::ReceiveVoucher { .. } => true,
+            Transition::ReceiveNative { amount } => {
+                *amount <= state.native_on_counterparty
+            }
+        }
+    }
+}
+
+/// The test chain with an opened channel to the counterparty
+#[derive(Debug)]
+struct ConcreteIbcState {
+    /// The native token
+    token: Address,
+    /// The account sending and receiving the tokens
+    account: Address,
+    port_id: ibc::PortId,
+    channel_id: ibc::ChannelId,
+    /// The sent messages waiting for an ack or a timeout with the key of the
+    /// escrow balance or the minted vouchers changed by a refund
+    sent: BTreeMap<u64, (MsgTransfer, Key)>,
+    /// The sequence of the next sent packet
+    next_send_sequence: u64,
+    /// The sequence of the next received packet
+    next_recv_sequence: u64,
+    /// The current block height
+    height: BlockHeight,
+    /// Credit an extra amount to the account on a refund of the native token
+    /// to check that the invariants catch it
+    refund_bug: bool,
+}
+
+impl StateMachineTest for ConcreteIbcState {
+    type Reference = AbstractIbcState;
+    type SystemUnderTest = Self;
+
+    fn init_test(
+        _initial_state: &<Self::Reference as ReferenceStateMachine>::State,
+    ) -> Self::SystemUnderTest {
+        // The environment must be initialized first
+        tx_host_env::init();
+
+        // Set the initial state before starting transactions
+        let (token, account) = ibc::init_storage();
+        let (client_id, _client_state, mut writes) = ibc::prepare_client();
+        let (conn_id, conn_writes) = ibc::prepare_opened_connection(&client_id);
+        writes.extend(conn_writes);
+        // The packets are acknowledged and timed out in any order
+        let (port_id, channel_id, channel_writes) =
+            ibc::prepare_opened_channel(&conn_id, false);
+        writes.extend(channel_writes);
+        let height = tx_host_env::with(|env| {
+            for (key, val) in writes {
+                env.state.write_bytes(&key, &val).expect("write error");
+            }
+            env.state.in_mem().get_block_height().0
+        });
+
+        Self {
+            token,
+            account,
+            port_id,
+            channel_id,
+            sent: BTreeMap::new(),
+            next_send_sequence: 1,
+            next_recv_sequence: 1,
+            height,
+            refund_bug: false,
+        }
+    }
+
+    fn apply(
+        mut state: Self::SystemUnderTest,
+        _ref_state: &<Self::Reference as ReferenceStateMachine>::State,
+        transition: <Self::Reference as ReferenceStateMachine>::Transition,
+    ) -> Self::SystemUnderTest {
+        match transition {
+            Transition::SendNative { amount, expired } => {
+                let denom = state.token.to_string();
+                let key = state.escrow_key();
+                state.send(denom, amount, expired, key);
+            }
+            Transition::SendVoucher { amount, expired } => {
+                let denom = state.voucher_denom();
+                let key = state.minted_key();
+                state.send(denom, amount, expired, key);
+            }
+            Transition::Ack { sequence, success } => {
+                let (msg, refund_key) = state.sent.remove(&sequence).unwrap();
+                let packet = ibc::packet_from_message(
+                    &msg,
+                    ibc::Sequence::from(sequence),
+                    &ibc::dummy_channel_counterparty(),
+                );
+                let mut msg = ibc::msg_packet_ack(packet);
+                if success {
+                    state.execute(msg.to_any(), None);
+                } else {
+                    msg.acknowledgement = ibc::transfer_ack_with_error().into();
+                    state.execute(msg.to_any(), Some(&refund_key));
+                    state.inject_refund_bug(&refund_key);
+                }
+            }
+            Transition::Timeout { sequence } => {
+                let (msg, refund_key) = state.sent.remove(&sequence).unwrap();
+                let packet = ibc::packet_from_message(
+                    &msg,
+                    ibc::Sequence::from(sequence),
+                    &ibc::dummy_channel_counterparty(),
+                );
+                let msg =
+                    ibc::msg_timeout(packet, ibc::Sequence::from(sequence));
+                state.execute(msg.to_any(), Some(&refund_key));
+                state.inject_refund_bug(&refund_key);
+            }
+            Transition::ReceiveVoucher { amount } => {
+                let packet = state
+                    .received_packet(COUNTERPARTY_DENOM.to_string(), amount);
+                let key = state.minted_key();
+                state
+                    .execute(ibc::msg_packet_recv(packet).to_any(), Some(&key));
+            }
+            Transition::ReceiveNative { amount } => {
+                // The native token is prefixed with the counterparty's port
+                // and channel on its way back
+                let counterparty = ibc::dummy_channel_counterparty();
+                let denom = format!(
+                    "{}/{}/{}",
+                    counterparty.port_id(),
+                    counterparty.channel_id().unwrap(),
+                    state.token
+                );
+                let packet = state.received_packet(denom, amount);
+                let key = state.escrow_key();
+                state
+                    .execute(ibc::msg_packet_recv(packet).to_any(), Some(&key));
+            }
+        }
+        state
+    }
+
+    fn check_invariants(
+        state: &Self::SystemUnderTest,
+        ref_state: &<Self::Reference as ReferenceStateMachine>::State,
+    ) {
+        if let Err(err) = state.check_conservation(ref_state) {
+            panic!("{err}");
+        }
+    }
+}
+
+impl ConcreteIbcState {
+    fn escrow_key(&self) -> Key {
+        token::storage_key::balance_key(
+            &self.token,
+            &Address::Internal(InternalAddress::Ibc),
+        )
+    }
+
+    /// The denomination of the vouchers of the counterparty's token
+    fn voucher_denom(&self) -> String {
+        format!(
+            "{}/{}/{}",
+            self.port_id, self.channel_id, COUNTERPARTY_DENOM
+        )
+    }
+
+    fn minted_key(&self) -> Key {
+        let voucher = ibc::ibc_token(self.voucher_denom());
+        token::storage_key::minted_balance_key(&voucher)
+    }
+
+    /// Send a packet with the given amount of the token
+    fn send(&mut self, denom: String, amount: u64, expired: bool, key: Key) {
+        let mut msg = ibc::msg_transfer(
+            self.port_id.clone(),
+            self.channel_id.clone(),
+            denom,
+            &self.account,
+        );
+        msg.packet_data.token.amount = Amount::from_u64(amount).into();
+        if expired {
+            ibc::set_timeout_timestamp(&mut msg);
+        }
+        self.execute(msg.clone().to_any(), Some(&key));
+        self.sent.insert(self.next_send_sequence, (msg, key));
+        self.next_send_sequence += 1;
+    }
+
+    /// Make a packet sent by the counterparty with the given amount of the
+    /// token
+    fn received_packet(&mut self, denom: String, amount: u64) -> ibc::Packet {
+        let sequence = ibc::Sequence::from(self.next_recv_sequence);
+        self.next_recv_sequence += 1;
+        let mut packet = ibc::received_packet(
+            self.port_id.clone(),
+            self.channel_id.clone(),
+            sequence,
+            denom,
+            &self.account,
+        );
+        let mut data: PacketData =
+            serde_json::from_slice(&packet.data).expect("decoding failed");
+        data.token.amount = Amount::from_u64(amount).into();
+        packet.data = serde_json::to_vec(&data).expect("encoding failed");
+        packet
+    }
+
+    /// Execute the IBC message in a transaction, check that it's accepted by
+    /// the IBC VP and the multitoken VP (when a token key is given) and commit
+    /// it in a new block
+    fn execute(
+        &mut self,
+        msg: namada::ibc::primitives::proto::Any,
+        token_key: Option<&Key>,
+    ) {
+        let mut tx_data = vec![];
+        msg.encode(&mut tx_data).expect("encoding failed");
+        let mut tx = Tx::new(ChainId::default(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(tx_data.clone());
+
+        tx_host_env::ibc::ibc_actions(tx::ctx())
+            .execute(&tx_data)
+            .expect("the IBC operation failed");
+
+        let mut env = tx_host_env::take();
+        let result = ibc::validate_ibc_vp_from_tx(&env, &tx);
+        assert!(
+            matches!(result, Ok(true)),
+            "The IBC VP rejected the tx: {result:?}"
+        );
+        if let Some(key) = token_key {
+            let result = ibc::validate_multitoken_vp_from_tx(&env, &tx, key);
+            assert!(
+                matches!(result, Ok(true)),
+                "The multitoken VP rejected the tx: {result:?}"
+            );
+        }
+
+        // Commit and start the next block
+        env.commit_tx_and_block();
+        self.height = self.height.next_height();
+        env.state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), self.height)
+            .unwrap();
+        env.state
+            .in_mem_mut()
+            .set_header(tm_dummy_header())
+            .unwrap();
+        tx_host_env::set(env);
+    }
+
+    /// Credit an extra amount of the native token on its refund, when the bug
+    /// is enabled
+    fn inject_refund_bug(&self, refund_key: &Key) {
+        if !self.refund_bug || refund_key != &self.escrow_key() {
+            return;
+        }
+        let key = token::storage_key::balance_key(&self.token, &self.account);
+        tx_host_env::with(|env| {
+            let balance: Amount =
+                env.state.read(&key).unwrap().unwrap_or_default();
+            let extra = native_amount(1);
+            env.state.write(&key, balance + extra).unwrap();
+        });
+    }
+
+    fn read_amount(&self, key: &Key) -> Amount {
+        tx_host_env::with(|env| {
+            env.state.read(key).expect("read error").unwrap_or_default()
+        })
+    }
+
+    /// Check the escrowed, minted and owned amounts against the model
+    fn check_conservation(
+        &self,
+        ref_state: &AbstractIbcState,
+    ) -> Result<(), String> {
+        let escrow = self.read_amount(&self.escrow_key());
+        let expected = native_amount(ref_state.escrowed());
+        if escrow != expected {
+            return Err(format!(
+                "The escrowed amount {} doesn't equal the outstanding \
+                 outbound amount {}",
+                escrow.to_string_native(),
+                expected.to_string_native(),
+            ));
+        }
+
+        let balance_key =
+            token::storage_key::balance_key(&self.token, &self.account);
+        let balance = self.read_amount(&balance_key);
+        let expected = native_amount(ref_state.balance);
+        if balance != expected {
+            return Err(format!(
+                "The native balance {} doesn't match the model {}",
+                balance.to_string_native(),
+                expected.to_string_native(),
+            ));
+        }
+        if balance + escrow != native_amount(INIT_BALANCE) {
+            return Err(format!(
+                "The native balance {} and the escrow {} don't add up to the \
+                 initial supply",
+                balance.to_string_native(),
+                escrow.to_string_native(),
+            ));
+        }
+
+        let minted = self.read_amount(&self.minted_key());
+        let voucher = ibc::ibc_token(self.voucher_denom());
+        let vouchers = self.read_amount(&token::storage_key::balance_key(
+            &voucher,
+            &self.account,
+        ));
+        let expected = Amount::from_u64(ref_state.vouchers);
+        if minted != expected || vouchers != expected {
+            return Err(format!(
+                "The minted amount {} and the voucher balance {} don't equal \
+                 the live inbound vouchers {}",
+                minted.to_string_native(),
+                vouchers.to_string_native(),
+                expected.to_string_native(),
+            ));
+        }
+
+        if self.sent.len() != ref_state.pending.len() {
+            return Err(format!(
+                "There are {} packets in flight, expected {}",
+                self.sent.len(),
+                ref_state.pending.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Convert the IBC amount of the native token to its amount on the chain
+fn native_amount(amount: u64) -> Amount {
+    Amount::from_uint(amount, ibc::ANY_DENOMINATION).unwrap()
+}
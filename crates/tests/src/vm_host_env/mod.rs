@@ -12,6 +12,8 @@
 //! `RUST_LOG=debug cargo test test_tx_read_write -- --nocapture`
 
 pub mod ibc;
+#[cfg(test)]
+mod ibc_state_machine;
 pub mod tx;
 pub mod vp;
 
@@ -32,13 +34,15 @@ mod tests {
     use namada::ibc::context::transfer_mod::testing::DummyTransferModule;
     use namada::ibc::primitives::Msg;
     use namada::ibc::Error as IbcActionError;
+    use namada::ledger::ibc::audit_ibc_escrow;
     use namada::ledger::ibc::storage as ibc_storage;
     use namada::ledger::native_vp::ibc::{
         get_dummy_header as tm_dummy_header, Error as IbcError,
     };
     use namada::ledger::tx_env::TxEnv;
+    use namada::parameters;
     use namada::token::{self, Amount};
-    use namada::tx::Tx;
+    use namada::tx::{SignatureLayout, Tx};
     use namada_test_utils::TestWasms;
     use namada_tx_prelude::address::InternalAddress;
     use namada_tx_prelude::chain::ChainId;
@@ -598,6 +602,78 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_vp_verify_tx_signature_layouts() {
+        let mut env = TestVpEnv::default();
+
+        let addr = address::testing::established_address_1();
+
+        // Write the public key to storage
+        let keypair = key::testing::keypair_1();
+        let pk = keypair.ref_to();
+
+        let _ = pks_handle(&addr).insert(&mut env.state, 0_u8, pk.clone());
+
+        // Initialize the environment
+        vp_host_env::set(env);
+
+        let pks_map = AccountPublicKeysMap::from_iter(vec![pk.clone()]);
+        let layouts =
+            [SignatureLayout::RawHeader, SignatureLayout::AllSections];
+        // Build a tx signed with the given layout and set it in the env
+        let set_signed_tx = |layout: SignatureLayout| {
+            vp_host_env::with(|env| {
+                let chain_id = env.state.in_mem().chain_id.clone();
+                let mut tx = Tx::new(chain_id, None);
+                tx.add_code(vec![4, 3, 2, 1, 0], None)
+                    .add_serialized_data(vec![1, 2, 3, 4]);
+                match layout {
+                    SignatureLayout::RawHeader => tx.sign_raw(
+                        vec![keypair.clone()],
+                        pks_map.clone(),
+                        None,
+                    ),
+                    SignatureLayout::AllSections => {
+                        tx.sign_wrapper(keypair.clone())
+                    }
+                };
+                env.tx = tx;
+                env.tx.clone()
+            })
+        };
+
+        // Both layouts are valid when none is deprecated
+        for layout in layouts {
+            let tx = set_signed_tx(layout);
+            assert!(
+                namada_vp_prelude::verify_signatures(&vp::CTX, &tx, &addr)
+                    .unwrap(),
+                "The {layout} signature layout must be valid by default"
+            );
+        }
+
+        // Only the deprecated layout gets rejected
+        for deprecated in layouts {
+            vp_host_env::with(|env| {
+                parameters::update_deprecated_signature_layouts(
+                    &mut env.state,
+                    BTreeSet::from([deprecated]),
+                )
+                .unwrap();
+            });
+            for layout in layouts {
+                let tx = set_signed_tx(layout);
+                assert_eq!(
+                    namada_vp_prelude::verify_signatures(&vp::CTX, &tx, &addr)
+                        .unwrap(),
+                    layout != deprecated,
+                    "Unexpected result for the {layout} signature layout with \
+                     the {deprecated} layout deprecated"
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_vp_get_metadata() {
         // The environment must be initialized first
@@ -1409,7 +1485,7 @@ mod tests {
         assert!(result.expect("token validation failed unexpectedly"));
         // Check the balance
         tx_host_env::set(env);
-        let key = ibc::balance_key_with_ibc_prefix(denom, &receiver);
+        let key = ibc::balance_key_with_ibc_prefix(denom.clone(), &receiver);
         let balance: Option<Amount> =
             tx_host_env::with(|env| env.state.read(&key).expect("read error"));
         assert_eq!(balance, Some(Amount::from_u64(100)));
@@ -1417,6 +1493,20 @@ mod tests {
             env.state.read(&minted_key).expect("read error")
         });
         assert_eq!(minted, Some(Amount::from_u64(100)));
+        // Check the denom trace and its lookup by the IBC token
+        let trace_key = ibc_storage::ibc_denom_key(
+            receiver.to_string(),
+            ibc_storage::calc_hash(&denom),
+        );
+        let trace: Option<String> = tx_host_env::with(|env| {
+            env.state.read(&trace_key).expect("read error")
+        });
+        assert_eq!(trace.as_ref(), Some(&denom));
+        let ibc_denom = tx_host_env::with(|env| {
+            namada::ledger::ibc::read_ibc_denom(&env.state, &ibc_token)
+                .expect("read error")
+        });
+        assert_eq!(ibc_denom, Some(denom));
     }
 
     #[test]
@@ -1528,8 +1618,12 @@ mod tests {
             &address::Address::Internal(address::InternalAddress::Ibc),
         );
         let val = Amount::from_uint(100, ibc::ANY_DENOMINATION).unwrap();
+        let escrow_amount_key = ibc_storage::escrow_amount_key(&token);
         tx_host_env::with(|env| {
             env.state.write(&escrow_key, val).expect("write error");
+            env.state
+                .write(&escrow_amount_key, val)
+                .expect("write error");
         });
 
         // Set this chain as the source zone
@@ -1618,6 +1712,8 @@ mod tests {
         let dummy_src_channel = "channel_42";
         let denom =
             format!("{}/{}/{}", dummy_src_port, dummy_src_channel, token);
+        let escrow_amount_key =
+            ibc_storage::escrow_amount_key(&ibc::ibc_token(&denom));
         let escrow_key = ibc::balance_key_with_ibc_prefix(
             denom,
             &address::Address::Internal(address::InternalAddress::Ibc),
@@ -1625,6 +1721,9 @@ mod tests {
         let val = Amount::from_u64(100);
         tx_host_env::with(|env| {
             env.state.write(&escrow_key, val).expect("write error");
+            env.state
+                .write(&escrow_amount_key, val)
+                .expect("write error");
         });
 
         // Set this chain as the source zone
@@ -1856,4 +1955,600 @@ mod tests {
         let result = ibc::validate_multitoken_vp_from_tx(&env, &tx, &escrow);
         assert!(result.expect("token validation failed unexpectedly"));
     }
+
+    #[test]
+    fn test_ibc_escrow_audit() {
+        // The environment must be initialized first
+        tx_host_env::init();
+
+        let keypair = key::testing::keypair_1();
+        let keypairs = vec![keypair.clone()];
+        let pks_map = AccountPublicKeysMap::from_iter([
+            key::testing::keypair_1().ref_to(),
+        ]);
+
+        // Set the initial state before starting transactions
+        let (token, sender) = ibc::init_storage();
+        let (client_id, _client_state, mut writes) = ibc::prepare_client();
+        let (conn_id, conn_writes) = ibc::prepare_opened_connection(&client_id);
+        writes.extend(conn_writes);
+        let (port_id, channel_id, channel_writes) =
+            ibc::prepare_opened_channel(&conn_id, true);
+        writes.extend(channel_writes);
+        writes.into_iter().for_each(|(key, val)| {
+            tx_host_env::with(|env| {
+                env.state.write_bytes(&key, &val).expect("write error");
+            })
+        });
+
+        // Execute and validate an IBC tx, then commit it for the next block
+        let mut height = 1;
+        let mut execute = |tx_data: Vec<u8>| {
+            let mut tx = Tx::new(ChainId::default(), None);
+            tx.add_code(vec![], None)
+                .add_serialized_data(tx_data.clone())
+                .sign_raw(keypairs.clone(), pks_map.clone(), None)
+                .sign_wrapper(keypair.clone());
+            tx_host_env::ibc::ibc_actions(tx::ctx())
+                .execute(&tx_data)
+                .expect("executing the IBC tx failed");
+
+            let mut env = tx_host_env::take();
+            let result = ibc::validate_ibc_vp_from_tx(&env, &tx);
+            assert!(result.expect("validation failed unexpectedly"));
+            env.commit_tx_and_block();
+            height += 1;
+            env.state
+                .in_mem_mut()
+                .begin_block(BlockHash::default(), BlockHeight(height))
+                .unwrap();
+            env.state
+                .in_mem_mut()
+                .set_header(tm_dummy_header())
+                .unwrap();
+            tx_host_env::set(env);
+        };
+        let audit = || {
+            tx_host_env::with(|env| {
+                audit_ibc_escrow(&env.state, &token).expect("audit failed")
+            })
+        };
+        let amount = Amount::from_uint(100, ibc::ANY_DENOMINATION).unwrap();
+        let counterparty = ibc::dummy_channel_counterparty();
+
+        // Send a packet
+        let msg = ibc::msg_transfer(
+            port_id.clone(),
+            channel_id.clone(),
+            token.to_string(),
+            &sender,
+        );
+        let mut tx_data = vec![];
+        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+        execute(tx_data);
+        let report = audit();
+        assert!(report.is_consistent());
+        assert_eq!(report.escrowed, amount);
+        assert_eq!(report.in_flight, amount);
+
+        // Acknowledge the packet. The token stays escrowed.
+        let packet = ibc::packet_from_message(
+            &msg,
+            ibc::Sequence::from(1),
+            &counterparty,
+        );
+        let msg = ibc::msg_packet_ack(packet);
+        let mut tx_data = vec![];
+        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+        execute(tx_data);
+        let report = audit();
+        assert!(report.is_consistent());
+        assert_eq!(report.escrowed, amount);
+        assert_eq!(report.in_flight, Amount::zero());
+        assert_eq!(report.balance, amount);
+
+        // Receive the token back
+        let denom = format!(
+            "{}/{}/{}",
+            counterparty.port_id().clone(),
+            counterparty.channel_id().unwrap().clone(),
+            token
+        );
+        let packet = ibc::received_packet(
+            port_id.clone(),
+            channel_id.clone(),
+            ibc::Sequence::from(1),
+            denom,
+            &sender,
+        );
+        let msg = ibc::msg_packet_recv(packet);
+        let mut tx_data = vec![];
+        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+        execute(tx_data);
+        let report = audit();
+        assert!(report.is_consistent());
+        assert_eq!(report.escrowed, Amount::zero());
+        assert_eq!(report.balance, Amount::zero());
+
+        // Send another packet which will time out
+        let mut msg =
+            ibc::msg_transfer(port_id, channel_id, token.to_string(), &sender);
+        ibc::set_timeout_timestamp(&mut msg);
+        let mut tx_data = vec![];
+        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+        execute(tx_data);
+        let report = audit();
+        assert!(report.is_consistent());
+        assert_eq!(report.in_flight, amount);
+
+        // The escrow balance short of the in-flight packet is reported
+        let escrow_key = token::storage_key::balance_key(
+            &token,
+            &address::Address::Internal(address::InternalAddress::Ibc),
+        );
+        let shortfall = Amount::from_uint(1, ibc::ANY_DENOMINATION).unwrap();
+        tx_host_env::with(|env| {
+            let corrupted = amount.checked_sub(shortfall).unwrap();
+            env.state
+                .write(&escrow_key, corrupted)
+                .expect("write error");
+        });
+        let report = audit();
+        assert!(!report.is_consistent());
+        assert_eq!(report.escrowed, amount);
+        assert_eq!(report.balance, amount.checked_sub(shortfall).unwrap());
+        assert!(report.stale_packets.is_empty());
+        let offending = report
+            .uncovered_packets
+            .iter()
+            .map(|packet| packet.sequence)
+            .collect::<Vec<_>>();
+        assert_eq!(offending, vec![2]);
+        tx_host_env::with(|env| {
+            env.state.write(&escrow_key, amount).expect("write error");
+        });
+
+        // Time out the packet to refund the token
+        let packet = ibc::packet_from_message(
+            &msg,
+            ibc::Sequence::from(2),
+            &counterparty,
+        );
+        let msg = ibc::msg_timeout(packet, ibc::Sequence::from(2));
+        let mut tx_data = vec![];
+        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+        execute(tx_data);
+        let report = audit();
+        assert!(report.is_consistent());
+        assert_eq!(report.escrowed, Amount::zero());
+        assert_eq!(report.in_flight, Amount::zero());
+        assert_eq!(report.balance, Amount::zero());
+    }
+
+    #[test]
+    fn test_ibc_send_token_with_fee() {
+        // The environment must be initialized first
+        tx_host_env::init();
+
+        // Set the initial state before starting transactions
+        let (token, sender) = ibc::init_storage();
+        let (client_id, _client_state, mut writes) = ibc::prepare_client();
+        let (conn_id, conn_writes) = ibc::prepare_opened_connection(&client_id);
+        writes.extend(conn_writes);
+        let (port_id, channel_id, channel_writes) =
+            ibc::prepare_opened_channel(&conn_id, false);
+        writes.extend(channel_writes);
+        // enable the relayer fees for the channel
+        let fee_enabled_key = ibc::fee_enabled_key(&port_id, &channel_id);
+        writes.insert(fee_enabled_key, true.serialize_to_vec());
+        // the relayer has registered the payee of the fees
+        let relayer = address::testing::established_address_2();
+        let payee = address::testing::established_address_3();
+        let payee_key = ibc_storage::payee_key(&port_id, &channel_id, &relayer);
+        writes.insert(payee_key, payee.serialize_to_vec());
+        // the sender should have enough balance for the fees
+        let balance_key = token::storage_key::balance_key(&token, &sender);
+        let init_bal = Amount::from_uint(200, ibc::ANY_DENOMINATION).unwrap();
+        writes.insert(balance_key.clone(), init_bal.serialize_to_vec());
+        writes.into_iter().for_each(|(key, val)| {
+            tx_host_env::with(|env| {
+                env.state.write_bytes(&key, &val).expect("write error");
+            });
+        });
+
+        let keypair = key::testing::keypair_1();
+        let keypairs = vec![keypair.clone()];
+        let pks_map = AccountPublicKeysMap::from_iter([
+            key::testing::keypair_1().ref_to(),
+        ]);
+        // the relayer signs the ack with its key
+        tx_host_env::with(|env| {
+            env.init_account_storage(&relayer, vec![keypair.ref_to()], 1);
+        });
+
+        // Start a transaction to send a packet with the relayer fees
+        let msg = ibc::msg_transfer_with_fee(
+            port_id.clone(),
+            channel_id.clone(),
+            &token,
+            &sender,
+        );
+        let tx_data = msg.serialize_to_vec();
+
+        let mut tx = Tx::new(ChainId::default(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(tx_data.clone())
+            .sign_raw(keypairs.clone(), pks_map.clone(), None)
+            .sign_wrapper(keypair.clone());
+        // send the token and escrow the fees
+        tx_host_env::ibc::ibc_actions(tx::ctx())
+            .execute(&tx_data)
+            .expect("sending a token with fees failed");
+
+        // Check
+        let mut env = tx_host_env::take();
+        let result = ibc::validate_ibc_vp_from_tx(&env, &tx);
+        assert!(result.expect("validation failed unexpectedly"));
+        // Check if the token and the fees were escrowed apart
+        let escrow_key = token::storage_key::balance_key(
+            &token,
+            &address::Address::Internal(address::InternalAddress::Ibc),
+        );
+        let token_vp_result =
+            ibc::validate_multitoken_vp_from_tx(&env, &tx, &escrow_key);
+        assert!(token_vp_result.expect("token validation failed unexpectedly"));
+        let escrow: Option<Amount> =
+            env.state.read(&escrow_key).expect("read error");
+        assert_eq!(
+            escrow,
+            Some(Amount::from_uint(100, ibc::ANY_DENOMINATION).unwrap())
+        );
+        let fee_escrow_bal_key =
+            token::storage_key::balance_key(&token, &address::IBC_FEE_ESCROW);
+        let fee_escrow_bal: Option<Amount> =
+            env.state.read(&fee_escrow_bal_key).expect("read error");
+        assert_eq!(
+            fee_escrow_bal,
+            Some(Amount::from_uint(18, ibc::ANY_DENOMINATION).unwrap())
+        );
+        let packet_fee_key =
+            ibc::packet_fee_key(&port_id, &channel_id, ibc::Sequence::from(1));
+        let packet_fee: Option<namada::ibc::PacketFee> =
+            env.state.read(&packet_fee_key).expect("read error");
+        assert_eq!(packet_fee, Some(msg.fee.clone()));
+        let fee_escrow_key = ibc_storage::fee_escrow_amount_key(&token);
+        let fee_escrow: Option<Amount> =
+            env.state.read(&fee_escrow_key).expect("read error");
+        assert_eq!(
+            fee_escrow,
+            Some(Amount::from_uint(18, ibc::ANY_DENOMINATION).unwrap())
+        );
+
+        // Commit
+        env.commit_tx_and_block();
+        // for the next block
+        env.state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+        env.state
+            .in_mem_mut()
+            .set_header(tm_dummy_header())
+            .unwrap();
+        tx_host_env::set(env);
+
+        // Start the next transaction for receiving an ack
+        let counterparty = ibc::dummy_channel_counterparty();
+        let packet = ibc::packet_from_message(
+            &msg.message,
+            ibc::Sequence::from(1),
+            &counterparty,
+        );
+        let mut msg = ibc::msg_packet_ack(packet);
+        msg.signer = relayer.to_string().into();
+        let mut tx_data = vec![];
+        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+
+        let mut tx = Tx::new(ChainId::default(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(tx_data.clone())
+            .sign_raw(keypairs, pks_map, None)
+            .sign_wrapper(keypair);
+        // ack the packet with the message
+        tx_host_env::ibc::ibc_actions(tx::ctx())
+            .execute(&tx_data)
+            .expect("ack failed");
+
+        // Check
+        let env = tx_host_env::take();
+        let result = ibc::validate_ibc_vp_from_tx(&env, &tx);
+        assert!(result.expect("validation failed unexpectedly"));
+        let token_vp_result =
+            ibc::validate_multitoken_vp_from_tx(&env, &tx, &escrow_key);
+        assert!(token_vp_result.expect("token validation failed unexpectedly"));
+        // the ack isn't valid without the signature of the relayer
+        let other_keypair = key::testing::keypair_2();
+        let mut unsigned_tx = Tx::new(ChainId::default(), None);
+        unsigned_tx
+            .add_code(vec![], None)
+            .add_serialized_data(tx_data)
+            .sign_raw(
+                vec![other_keypair.clone()],
+                AccountPublicKeysMap::from_iter([other_keypair.ref_to()]),
+                None,
+            )
+            .sign_wrapper(other_keypair);
+        let result = ibc::validate_ibc_vp_from_tx(&env, &unsigned_tx);
+        assert!(matches!(result, Err(IbcError::RelayerFee(_))));
+        // Check the payee got the recv and ack fees instead of the relayer and
+        // the timeout fee was refunded
+        let relayer_key = token::storage_key::balance_key(&token, &relayer);
+        let relayer_bal: Option<Amount> =
+            env.state.read(&relayer_key).expect("read error");
+        assert!(relayer_bal.is_none());
+        let payee_key = token::storage_key::balance_key(&token, &payee);
+        let payee_bal: Option<Amount> =
+            env.state.read(&payee_key).expect("read error");
+        assert_eq!(
+            payee_bal,
+            Some(Amount::from_uint(15, ibc::ANY_DENOMINATION).unwrap())
+        );
+        let balance: Option<Amount> =
+            env.state.read(&balance_key).expect("read error");
+        assert_eq!(
+            balance,
+            Some(Amount::from_uint(85, ibc::ANY_DENOMINATION).unwrap())
+        );
+        let escrow: Option<Amount> =
+            env.state.read(&escrow_key).expect("read error");
+        assert_eq!(
+            escrow,
+            Some(Amount::from_uint(100, ibc::ANY_DENOMINATION).unwrap())
+        );
+        let packet_fee: Option<namada::ibc::PacketFee> =
+            env.state.read(&packet_fee_key).expect("read error");
+        assert!(packet_fee.is_none());
+        let fee_escrow: Option<Amount> =
+            env.state.read(&fee_escrow_key).expect("read error");
+        assert_eq!(fee_escrow, Some(Amount::zero()));
+        let fee_escrow_bal: Option<Amount> =
+            env.state.read(&fee_escrow_bal_key).expect("read error");
+        assert_eq!(fee_escrow_bal, Some(Amount::zero()));
+    }
+
+    #[test]
+    fn test_ibc_packet_timeout_with_fee() {
+        // The environment must be initialized first
+        tx_host_env::init();
+
+        let keypair = key::testing::keypair_1();
+        let keypairs = vec![keypair.clone()];
+        let pks_map = AccountPublicKeysMap::from_iter([
+            key::testing::keypair_1().ref_to(),
+        ]);
+
+        // Set the initial state before starting transactions
+        let (token, sender) = ibc::init_storage();
+        let (client_id, _client_state, mut writes) = ibc::prepare_client();
+        let (conn_id, conn_writes) = ibc::prepare_opened_connection(&client_id);
+        writes.extend(conn_writes);
+        let (port_id, channel_id, channel_writes) =
+            ibc::prepare_opened_channel(&conn_id, true);
+        writes.extend(channel_writes);
+        // enable the relayer fees for the channel
+        let fee_enabled_key = ibc::fee_enabled_key(&port_id, &channel_id);
+        writes.insert(fee_enabled_key, true.serialize_to_vec());
+        // the sender should have enough balance for the fees
+        let balance_key = token::storage_key::balance_key(&token, &sender);
+        let init_bal = Amount::from_uint(200, ibc::ANY_DENOMINATION).unwrap();
+        writes.insert(balance_key.clone(), init_bal.serialize_to_vec());
+        writes.into_iter().for_each(|(key, val)| {
+            tx_host_env::with(|env| {
+                env.state.write_bytes(&key, &val).expect("write error");
+            })
+        });
+        // the relayer signs the timeout with its key
+        let relayer = address::testing::established_address_2();
+        tx_host_env::with(|env| {
+            env.init_account_storage(&relayer, vec![keypair.ref_to()], 1);
+        });
+
+        // Start a transaction to send a packet with the relayer fees
+        let mut msg = ibc::msg_transfer_with_fee(
+            port_id.clone(),
+            channel_id.clone(),
+            &token,
+            &sender,
+        );
+        ibc::set_timeout_timestamp(&mut msg.message);
+        let tx_data = msg.serialize_to_vec();
+        // send a packet with the message
+        tx_host_env::ibc::ibc_actions(tx::ctx())
+            .execute(&tx_data)
+            .expect("sending a token with fees failed");
+
+        // Commit
+        let mut env = tx_host_env::take();
+        env.commit_tx_and_block();
+        // for the next block
+        env.state
+            .in_mem_mut()
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+        env.state
+            .in_mem_mut()
+            .set_header(tm_dummy_header())
+            .unwrap();
+        tx_host_env::set(env);
+
+        // Start a transaction to notify the timeout
+        let counterparty = ibc::dummy_channel_counterparty();
+        let packet = ibc::packet_from_message(
+            &msg.message,
+            ibc::Sequence::from(1),
+            &counterparty,
+        );
+        let mut msg = ibc::msg_timeout(packet, ibc::Sequence::from(1));
+        msg.signer = relayer.to_string().into();
+        let mut tx_data = vec![];
+        msg.to_any().encode(&mut tx_data).expect("encoding failed");
+        let mut tx = Tx::new(ChainId::default(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(tx_data.clone())
+            .sign_raw(keypairs, pks_map, None)
+            .sign_wrapper(keypair);
+
+        // timeout the packet
+        tx_host_env::ibc::ibc_actions(tx::ctx())
+            .execute(&tx_data)
+            .expect("timeout failed");
+
+        // Check
+        let env = tx_host_env::take();
+        let result = ibc::validate_ibc_vp_from_tx(&env, &tx);
+        assert!(result.expect("validation failed unexpectedly"));
+        // the timeout isn't valid without the signature of the relayer
+        let other_keypair = key::testing::keypair_2();
+        let mut unsigned_tx = Tx::new(ChainId::default(), None);
+        unsigned_tx
+            .add_code(vec![], None)
+            .add_serialized_data(tx_data)
+            .sign_raw(
+                vec![other_keypair.clone()],
+                AccountPublicKeysMap::from_iter([other_keypair.ref_to()]),
+                None,
+            )
+            .sign_wrapper(other_keypair);
+        let result = ibc::validate_ibc_vp_from_tx(&env, &unsigned_tx);
+        assert!(matches!(result, Err(IbcError::RelayerFee(_))));
+        let escrow_key = token::storage_key::balance_key(
+            &token,
+            &address::Address::Internal(address::InternalAddress::Ibc),
+        );
+        let result =
+            ibc::validate_multitoken_vp_from_tx(&env, &tx, &escrow_key);
+        assert!(result.expect("token validation failed unexpectedly"));
+        // Check the relayer got the timeout fee and the other fees were
+        // refunded with the token
+        let relayer_key = token::storage_key::balance_key(&token, &relayer);
+        let relayer_bal: Option<Amount> =
+            env.state.read(&relayer_key).expect("read error");
+        assert_eq!(
+            relayer_bal,
+            Some(Amount::from_uint(3, ibc::ANY_DENOMINATION).unwrap())
+        );
+        let balance: Option<Amount> =
+            env.state.read(&balance_key).expect("read error");
+        assert_eq!(
+            balance,
+            Some(Amount::from_uint(197, ibc::ANY_DENOMINATION).unwrap())
+        );
+        let escrow: Option<Amount> =
+            env.state.read(&escrow_key).expect("read error");
+        assert_eq!(escrow, Some(Amount::from_u64(0)));
+        let fee_escrow: Option<Amount> = env
+            .state
+            .read(&ibc_storage::fee_escrow_amount_key(&token))
+            .expect("read error");
+        assert_eq!(fee_escrow, Some(Amount::zero()));
+        let fee_escrow_bal: Option<Amount> = env
+            .state
+            .read(&token::storage_key::balance_key(
+                &token,
+                &address::IBC_FEE_ESCROW,
+            ))
+            .expect("read error");
+        assert_eq!(fee_escrow_bal, Some(Amount::zero()));
+    }
+
+    #[test]
+    fn test_ibc_register_payee() {
+        // The environment must be initialized first
+        tx_host_env::init();
+
+        // Set the initial state before starting transactions
+        ibc::init_storage();
+        let (client_id, _client_state, mut writes) = ibc::prepare_client();
+        let (conn_id, conn_writes) = ibc::prepare_opened_connection(&client_id);
+        writes.extend(conn_writes);
+        let (port_id, channel_id, channel_writes) =
+            ibc::prepare_opened_channel(&conn_id, false);
+        writes.extend(channel_writes);
+        writes.into_iter().for_each(|(key, val)| {
+            tx_host_env::with(|env| {
+                env.state.write_bytes(&key, &val).expect("write error");
+            });
+        });
+
+        let relayer = address::testing::established_address_2();
+        let payee = address::testing::established_address_3();
+        // the relayer signs the tx with its key
+        let keypair = key::testing::keypair_1();
+        tx_host_env::with(|env| {
+            env.init_account_storage(&relayer, vec![keypair.ref_to()], 1);
+        });
+        let mut tx = Tx::new(ChainId::default(), None);
+        tx.add_serialized_data(payee.serialize_to_vec()).sign_raw(
+            vec![keypair.clone()],
+            AccountPublicKeysMap::from_iter([keypair.ref_to()]),
+            None,
+        );
+
+        // the relayer registers the payee
+        tx_host_env::ibc::register_payee(
+            tx::ctx(),
+            &port_id,
+            &channel_id,
+            &relayer,
+            &payee,
+        )
+        .expect("registering the payee failed");
+        let env = tx_host_env::take();
+        let result = ibc::validate_ibc_vp_from_tx(&env, &tx);
+        assert!(result.expect("validation failed unexpectedly"));
+
+        // the payee can't be registered without the relayer's signature
+        let other_keypair = key::testing::keypair_2();
+        let mut unsigned_tx = Tx::new(ChainId::default(), None);
+        unsigned_tx
+            .add_serialized_data(payee.serialize_to_vec())
+            .sign_raw(
+                vec![other_keypair.clone()],
+                AccountPublicKeysMap::from_iter([other_keypair.ref_to()]),
+                None,
+            );
+        let result = ibc::validate_ibc_vp_from_tx(&env, &unsigned_tx);
+        assert!(matches!(result, Err(IbcError::Payee(_))));
+    }
+
+    #[test]
+    fn test_ibc_send_token_with_fee_disabled() {
+        // The environment must be initialized first
+        tx_host_env::init();
+
+        // Set the initial state before starting transactions
+        let (token, sender) = ibc::init_storage();
+        let (client_id, _client_state, mut writes) = ibc::prepare_client();
+        let (conn_id, conn_writes) = ibc::prepare_opened_connection(&client_id);
+        writes.extend(conn_writes);
+        let (port_id, channel_id, channel_writes) =
+            ibc::prepare_opened_channel(&conn_id, false);
+        writes.extend(channel_writes);
+        writes.into_iter().for_each(|(key, val)| {
+            tx_host_env::with(|env| {
+                env.state.write_bytes(&key, &val).expect("write error");
+            });
+        });
+
+        // Start a transaction to send a packet with the relayer fees
+        let msg =
+            ibc::msg_transfer_with_fee(port_id, channel_id, &token, &sender);
+        let tx_data = msg.serialize_to_vec();
+        // the fees can't be attached because the channel doesn't enable them
+        let result = tx_host_env::ibc::ibc_actions(tx::ctx()).execute(&tx_data);
+        assert!(matches!(
+            result.expect_err("sending succeeded unexpectedly"),
+            IbcActionError::Fee(_),
+        ));
+    }
 }
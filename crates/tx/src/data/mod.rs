@@ -13,7 +13,7 @@ pub mod protocol;
 /// wrapper txs with encrypted payloads
 pub mod wrapper;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{self, Display};
 use std::str::FromStr;
 
@@ -23,6 +23,7 @@ use namada_core::borsh::{
     BorshDeserialize, BorshSchema, BorshSerialize, BorshSerializeExt,
 };
 use namada_core::ethereum_structs::EthBridgeEvent;
+use namada_core::event::Event;
 use namada_core::hash::Hash;
 use namada_core::ibc::IbcEvent;
 use namada_core::storage;
@@ -181,8 +182,10 @@ pub struct TxResult {
     pub vps_result: VpsResult,
     /// New established addresses created by the transaction
     pub initialized_accounts: Vec<Address>,
-    /// IBC events emitted by the transaction
-    pub ibc_events: BTreeSet<IbcEvent>,
+    /// IBC events emitted by the transaction, in the emission order
+    pub ibc_events: Vec<IbcEvent>,
+    /// Other events emitted by the transaction, in the emission order
+    pub events: Vec<Event>,
     /// Ethereum bridge events emitted by the transaction
     pub eth_bridge_events: BTreeSet<EthBridgeEvent>,
 }
@@ -216,6 +219,55 @@ pub struct VpsResult {
     pub errors: Vec<(Address, String)>,
     /// Sentinel to signal an invalid transaction signature
     pub invalid_sig: bool,
+    /// The gas consumed by the VPs per label, before the discount of the
+    /// parallel runs. Only recorded when gas tracing is enabled and not part
+    /// of the encoded result.
+    #[borsh(skip)]
+    #[serde(skip)]
+    pub gas_breakdown: BTreeMap<String, Gas>,
+    /// The coarse reasons of the rejections by the native VPs, used to label
+    /// the metrics. Not part of the encoded result.
+    #[borsh(skip)]
+    #[serde(skip)]
+    pub rejection_reasons: BTreeMap<Address, &'static str>,
+}
+
+impl VpsResult {
+    /// Get the verdict of each VP that has been run on the transaction
+    pub fn verdicts(&self) -> BTreeMap<Address, VpVerdict> {
+        let mut verdicts: BTreeMap<Address, VpVerdict> = self
+            .accepted_vps
+            .iter()
+            .map(|addr| (addr.clone(), VpVerdict::Accepted))
+            .collect();
+        for addr in &self.rejected_vps {
+            verdicts.insert(addr.clone(), VpVerdict::Rejected(None));
+        }
+        for (addr, err) in &self.errors {
+            verdicts
+                .insert(addr.clone(), VpVerdict::Rejected(Some(err.clone())));
+        }
+        verdicts
+    }
+}
+
+/// The verdict of a validity predicate on a transaction
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+)]
+pub enum VpVerdict {
+    /// The VP accepted the transaction
+    Accepted,
+    /// The VP rejected the transaction, with the error that it failed with,
+    /// if any
+    Rejected(Option<String>),
 }
 
 impl fmt::Display for TxResult {
@@ -283,6 +335,31 @@ fn iterable_to_string<T: fmt::Display>(
     }
 }
 
+/// The marker placed between the kept prefix of a truncated error message and
+/// the hash of the full message
+pub const TRUNCATED_ERROR_MARKER: &str = "... [truncated, sha256: ";
+
+/// Truncate an error message to be stored in a tx result to at most
+/// `max_bytes` bytes. The truncation is deterministic: the longest prefix
+/// ending on a char boundary is kept and followed by the
+/// [`TRUNCATED_ERROR_MARKER`] and the hash of the full message, so that the
+/// full message can be matched with the one logged by a node. The marker and
+/// the hash are always kept, even if they don't fit in the budget.
+///
+/// Returns `None` if the message is within the budget.
+pub fn truncate_error(msg: &str, max_bytes: usize) -> Option<String> {
+    if msg.len() <= max_bytes {
+        return None;
+    }
+    let hash = Hash::sha256(msg.as_bytes());
+    let suffix = format!("{TRUNCATED_ERROR_MARKER}{hash}]");
+    let mut end = max_bytes.saturating_sub(suffix.len());
+    while !msg.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(format!("{}{suffix}", &msg[..end]))
+}
+
 /// Struct that classifies that kind of Tx
 /// based on the contents of its data.
 #[derive(
@@ -338,6 +415,68 @@ impl TxSentinel {
     }
 }
 
+#[cfg(test)]
+mod test_vp_verdicts {
+    use namada_core::address::testing::{
+        established_address_1, established_address_2, established_address_3,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_vps_result_verdicts() {
+        let accepted = established_address_1();
+        let rejected = established_address_2();
+        let failed = established_address_3();
+        let vps_result = VpsResult {
+            accepted_vps: BTreeSet::from([accepted.clone()]),
+            rejected_vps: BTreeSet::from([rejected.clone(), failed.clone()]),
+            errors: vec![(failed.clone(), "error".to_string())],
+            ..Default::default()
+        };
+        assert_eq!(
+            vps_result.verdicts(),
+            BTreeMap::from([
+                (accepted, VpVerdict::Accepted),
+                (rejected, VpVerdict::Rejected(None)),
+                (failed, VpVerdict::Rejected(Some("error".to_string()))),
+            ])
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_truncate_error {
+    use super::*;
+
+    #[test]
+    fn test_short_error_not_truncated() {
+        let msg = "The changed keys mismatched";
+        assert_eq!(truncate_error(msg, msg.len()), None);
+        assert_eq!(truncate_error("", 0), None);
+    }
+
+    #[test]
+    fn test_truncated_error_is_bounded() {
+        let msg = "é".repeat(10_000);
+        let max_bytes = 512;
+        let truncated = truncate_error(&msg, max_bytes).unwrap();
+        assert!(truncated.len() <= max_bytes);
+        // the cut is on a char boundary
+        let (prefix, suffix) =
+            truncated.split_once(TRUNCATED_ERROR_MARKER).unwrap();
+        assert!(msg.starts_with(prefix));
+        assert_eq!(suffix, format!("{}]", Hash::sha256(msg.as_bytes())));
+    }
+
+    #[test]
+    fn test_marker_kept_over_budget() {
+        let msg = "a".repeat(100);
+        let truncated = truncate_error(&msg, 10).unwrap();
+        assert!(truncated.starts_with(TRUNCATED_ERROR_MARKER));
+    }
+}
+
 #[cfg(test)]
 mod test_process_tx {
     use assert_matches::assert_matches;
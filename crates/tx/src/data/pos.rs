@@ -72,6 +72,27 @@ pub struct Bond {
     pub source: Option<Address>,
 }
 
+/// A bond funded by a payer account other than the bond's source. The bonded
+/// tokens are debited from the payer, but the bond is owned by the source.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    BorshSerialize,
+    BorshDeserialize,
+    BorshSchema,
+    Hash,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub struct BondFor {
+    /// The bond
+    pub bond: Bond,
+    /// The account paying the bonded tokens
+    pub payer: Address,
+}
+
 /// An unbond of a bond.
 pub type Unbond = Bond;
 
@@ -189,6 +210,9 @@ pub struct MetaDataChange {
     pub avatar: Option<String>,
     /// Validator's commission rate
     pub commission_rate: Option<Dec>,
+    /// The cap on the total amount delegated to the validator. A zero
+    /// amount removes the cap.
+    pub max_total_delegated: Option<token::Amount>,
 }
 
 /// A change to the validator's consensus key.
@@ -273,6 +297,7 @@ pub mod tests {
             discord_handle in option::of("[a-zA-Z0-9_]*"),
             avatar in option::of("[a-zA-Z0-9_]*"),
             commission_rate in option::of(arb_dec()),
+            max_total_delegated in option::of(arb_amount()),
         ) -> MetaDataChange {
             MetaDataChange {
                 validator,
@@ -282,6 +307,7 @@ pub mod tests {
                 discord_handle,
                 avatar,
                 commission_rate,
+                max_total_delegated,
             }
         }
     }
@@ -9,11 +9,11 @@ use std::collections::HashMap;
 use data::TxType;
 use namada_core::event::{Event, EventLevel, EventType};
 pub use namada_core::key::SignableEthMessage;
-pub use namada_core::sign::SignatureIndex;
+pub use namada_core::sign::{SignatureIndex, SignatureLayout};
 pub use types::{
     standalone_signature, verify_standalone_sig, Code, Commitment,
     CompressedSignature, Data, DecodeError, Header, MaspBuilder, Memo, Section,
-    Signature, Signed, Signer, Tx, TxError, VerifySigError,
+    Signature, Signed, Signer, Tx, TxError, TxVersion, VerifySigError,
 };
 
 /// Creates a new event with the hash and height of the transaction
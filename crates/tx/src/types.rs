@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 
@@ -18,7 +18,7 @@ use namada_core::borsh::{
 use namada_core::chain::ChainId;
 use namada_core::key::*;
 use namada_core::masp::AssetData;
-use namada_core::sign::SignatureIndex;
+use namada_core::sign::{SignatureIndex, SignatureLayout};
 use namada_core::storage::Epoch;
 use namada_core::time::DateTimeUtc;
 use serde::de::Error as SerdeError;
@@ -42,6 +42,20 @@ pub enum VerifySigError {
     InvalidWrapperSignature,
     #[error("The section signature is invalid: {0}")]
     InvalidSectionSignature(String),
+    #[error("Unsupported transaction format version: {0}")]
+    UnsupportedTxVersion(u8),
+    #[error(
+        "The {layout} signature layout is not valid for the transaction \
+         format version {version}"
+    )]
+    UnsupportedSignatureLayout {
+        version: u8,
+        layout: SignatureLayout,
+    },
+    #[error("The {0} signature layout is deprecated")]
+    DeprecatedSignatureLayout(SignatureLayout),
+    #[error("The transaction format version is not signed")]
+    UnsignedTxVersion,
 }
 
 #[allow(missing_docs)]
@@ -451,6 +465,20 @@ impl Signature {
         .get_hash()
     }
 
+    /// Get the layout of this signature's targets given the hash of the raw
+    /// header of the transaction containing it. Any targets other than the
+    /// raw header alone are considered to cover all the sections.
+    pub fn layout(
+        &self,
+        raw_header_hash: &namada_core::hash::Hash,
+    ) -> SignatureLayout {
+        if self.targets == [*raw_header_hash] {
+            SignatureLayout::RawHeader
+        } else {
+            SignatureLayout::AllSections
+        }
+    }
+
     /// Verify that the signature contained in this section is valid
     pub fn verify_signature<F>(
         &self,
@@ -730,6 +758,9 @@ pub enum Section {
     MaspBuilder(MaspBuilder),
     /// Wrap a header with a section for the purposes of computing hashes
     Header(Header),
+    /// The version of the format of the transaction. The transactions without
+    /// this section have the initial version.
+    Version(TxVersion),
 }
 
 impl Section {
@@ -752,6 +783,10 @@ impl Section {
                 hasher
             }
             Self::Header(header) => header.hash(hasher),
+            Self::Version(version) => {
+                hasher.update(version.serialize_to_vec());
+                hasher
+            }
         }
     }
 
@@ -844,6 +879,51 @@ impl Section {
     }
 }
 
+/// The version of the format of a transaction's sections. It's carried by a
+/// section rather than by the header, so that the encoding and the hash of the
+/// header stay the same as before the versioning.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    BorshSerialize,
+    BorshDeserialize,
+    BorshSchema,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub struct TxVersion(pub u8);
+
+impl TxVersion {
+    /// The version of the transactions built by this node
+    pub const CURRENT: Self = Self::INITIAL;
+    /// The version of the transactions without a version section
+    pub const INITIAL: Self = Self(1);
+
+    /// Get the signature layouts that are valid for transactions of this
+    /// version. Returns `None` if the version isn't supported.
+    pub fn signature_layouts(&self) -> Option<&'static [SignatureLayout]> {
+        match self.0 {
+            1 => Some(&[
+                SignatureLayout::RawHeader,
+                SignatureLayout::AllSections,
+            ]),
+            _ => None,
+        }
+    }
+}
+
+impl Default for TxVersion {
+    fn default() -> Self {
+        Self::CURRENT
+    }
+}
+
 /// A Namada transaction header indicating where transaction subcomponents can
 /// be found
 #[derive(
@@ -934,6 +1014,8 @@ pub enum TxError {
     SigError(String),
     #[error("Failed to deserialize Tx: {0}")]
     Deserialization(String),
+    #[error("Unsupported transaction format version: {0}")]
+    UnsupportedVersion(u8),
 }
 
 /// A Namada transaction is represented as a header followed by a series of
@@ -1044,6 +1126,34 @@ impl Tx {
         hashes
     }
 
+    /// Get the version of the format of this transaction from its version
+    /// section, or the initial version if it has none
+    pub fn version(&self) -> TxVersion {
+        self.version_section()
+            .map(|(version, _hash)| version)
+            .unwrap_or(TxVersion::INITIAL)
+    }
+
+    /// Get the first version section of this transaction with its hash
+    fn version_section(&self) -> Option<(TxVersion, namada_core::hash::Hash)> {
+        self.sections.iter().find_map(|section| match section {
+            Section::Version(version) => Some((*version, section.get_hash())),
+            _ => None,
+        })
+    }
+
+    /// Set the version of the format of this transaction. The transactions of
+    /// the initial version don't have a version section, so that they're
+    /// encoded as before the versioning.
+    pub fn set_version(&mut self, version: TxVersion) -> &mut Self {
+        self.sections
+            .retain(|section| !matches!(section, Section::Version(_)));
+        if version != TxVersion::INITIAL {
+            self.add_section(Section::Version(version));
+        }
+        self
+    }
+
     /// Update the header whilst maintaining existing cross-references
     pub fn update_header(&mut self, tx_type: TxType) -> &mut Self {
         self.header.tx_type = tx_type;
@@ -1174,6 +1284,37 @@ impl Tx {
         bytes
     }
 
+    /// Check that the given signature section has a layout that is valid for
+    /// the format version of this transaction and that is not deprecated
+    pub fn check_signature_layout(
+        &self,
+        signature: &Signature,
+        deprecated_layouts: &BTreeSet<SignatureLayout>,
+    ) -> std::result::Result<SignatureLayout, VerifySigError> {
+        let version = self.version();
+        let layouts = version
+            .signature_layouts()
+            .ok_or(VerifySigError::UnsupportedTxVersion(version.0))?;
+        // The header doesn't commit to the version section, so it must be
+        // signed for the version of a signed tx not to be changed
+        if let Some((_version, hash)) = self.version_section() {
+            if !signature.targets.contains(&hash) {
+                return Err(VerifySigError::UnsignedTxVersion);
+            }
+        }
+        let layout = signature.layout(&self.raw_header_hash());
+        if !layouts.contains(&layout) {
+            return Err(VerifySigError::UnsupportedSignatureLayout {
+                version: version.0,
+                layout,
+            });
+        }
+        if deprecated_layouts.contains(&layout) {
+            return Err(VerifySigError::DeprecatedSignatureLayout(layout));
+        }
+        Ok(layout)
+    }
+
     /// Verify that the section with the given hash has been signed by the given
     /// public key
     pub fn verify_signatures<F>(
@@ -1183,6 +1324,34 @@ impl Tx {
         signer: &Option<Address>,
         threshold: u8,
         max_signatures: Option<u8>,
+        consume_verify_sig_gas: F,
+    ) -> std::result::Result<Vec<&Signature>, VerifySigError>
+    where
+        F: FnMut() -> std::result::Result<(), namada_gas::Error>,
+    {
+        self.verify_signatures_with_layouts(
+            hashes,
+            public_keys_index_map,
+            signer,
+            threshold,
+            max_signatures,
+            &BTreeSet::new(),
+            consume_verify_sig_gas,
+        )
+    }
+
+    /// Verify that the section with the given hash has been signed by the given
+    /// public key with signatures whose layout is valid for this transaction's
+    /// format version and not amongst the given deprecated layouts
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_signatures_with_layouts<F>(
+        &self,
+        hashes: &[namada_core::hash::Hash],
+        public_keys_index_map: AccountPublicKeysMap,
+        signer: &Option<Address>,
+        threshold: u8,
+        max_signatures: Option<u8>,
+        deprecated_layouts: &BTreeSet<SignatureLayout>,
         mut consume_verify_sig_gas: F,
     ) -> std::result::Result<Vec<&Signature>, VerifySigError>
     where
@@ -1206,6 +1375,11 @@ impl Tx {
                     .iter()
                     .all(|x| self.get_section(x).is_some())
                 {
+                    self.check_signature_layout(
+                        signatures,
+                        deprecated_layouts,
+                    )?;
+
                     if signatures.total_signatures() > max_signatures {
                         return Err(VerifySigError::InvalidSectionSignature(
                             "too many signatures.".to_string(),
@@ -1320,6 +1494,10 @@ impl Tx {
     pub fn validate_tx(
         &self,
     ) -> std::result::Result<Option<&Signature>, TxError> {
+        let version = self.version();
+        if version.signature_layouts().is_none() {
+            return Err(TxError::UnsupportedVersion(version.0));
+        }
         match &self.header.tx_type {
             // verify signature and extract signed data
             TxType::Wrapper(wrapper) => self
@@ -1563,3 +1741,218 @@ impl Tx {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use data_encoding::HEXLOWER;
+    use namada_core::key::testing::keypair_1;
+
+    use super::*;
+
+    /// The signature layouts supported by the current tx format version
+    const LAYOUTS: [SignatureLayout; 2] =
+        [SignatureLayout::RawHeader, SignatureLayout::AllSections];
+
+    /// Serialized txs of the initial format version signed with each of the
+    /// supported layouts. They don't have a version section, so they're
+    /// encoded as the txs built before the versioning. These must remain
+    /// parseable and verifiable for as long as the layout is supported, so a
+    /// change to the tx encoding or to the signature targets fails these
+    /// tests.
+    const GOLDEN_TXS: [(SignatureLayout, &str); 2] = [
+        (
+            SignatureLayout::RawHeader,
+            include_str!("../testdata/raw_header_signature_v1.hex"),
+        ),
+        (
+            SignatureLayout::AllSections,
+            include_str!("../testdata/all_sections_signature_v1.hex"),
+        ),
+    ];
+
+    /// Build the tx of the golden fixtures with the given format version and
+    /// signature layout
+    fn fixture_tx(version: TxVersion, layout: SignatureLayout) -> Tx {
+        let chain_id = ChainId("fixture-chain.000000000000000".to_string());
+        let mut tx = Tx::new(chain_id, None);
+        tx.set_version(version);
+        tx.header.timestamp = DateTimeUtc::unix_epoch();
+        tx.set_code(Code {
+            salt: [0; 8],
+            code: Commitment::Id(b"fixture code".to_vec()),
+            tag: Some("tx_fixture.wasm".to_string()),
+        });
+        tx.set_data(Data {
+            salt: [0; 8],
+            data: b"fixture data".to_vec(),
+        });
+        let keypair = keypair_1();
+        match layout {
+            SignatureLayout::RawHeader => tx.sign_raw(
+                vec![keypair.clone()],
+                AccountPublicKeysMap::from_iter([keypair.ref_to()]),
+                None,
+            ),
+            SignatureLayout::AllSections => tx.sign_wrapper(keypair),
+        };
+        tx
+    }
+
+    /// Verify the signature of the fixtures' key over the raw header, as done
+    /// by the VPs
+    fn verify(
+        tx: &Tx,
+        deprecated_layouts: &BTreeSet<SignatureLayout>,
+    ) -> std::result::Result<Vec<&Signature>, VerifySigError> {
+        tx.verify_signatures_with_layouts(
+            &[tx.raw_header_hash()],
+            AccountPublicKeysMap::from_iter([keypair_1().ref_to()]),
+            &None,
+            1,
+            None,
+            deprecated_layouts,
+            || Ok(()),
+        )
+    }
+
+    #[test]
+    fn test_signature_layouts_valid_by_default() {
+        for layout in LAYOUTS {
+            let tx = fixture_tx(TxVersion::CURRENT, layout);
+            let signature = tx
+                .sections
+                .iter()
+                .find_map(Section::signature)
+                .expect("The tx must be signed");
+            assert_eq!(
+                tx.check_signature_layout(&signature, &BTreeSet::new())
+                    .unwrap(),
+                layout
+            );
+            assert!(verify(&tx, &BTreeSet::new()).is_ok());
+            // Txs built by this node use the default parameter
+            assert!(
+                tx.verify_signatures(
+                    &[tx.raw_header_hash()],
+                    AccountPublicKeysMap::from_iter([keypair_1().ref_to()]),
+                    &None,
+                    1,
+                    None,
+                    || Ok(()),
+                )
+                .is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn test_deprecated_signature_layout_rejected() {
+        for deprecated in LAYOUTS {
+            let deprecated_layouts = BTreeSet::from([deprecated]);
+            for layout in LAYOUTS {
+                let tx = fixture_tx(TxVersion::CURRENT, layout);
+                let result = verify(&tx, &deprecated_layouts);
+                if layout == deprecated {
+                    assert_matches!(
+                        result,
+                        Err(VerifySigError::DeprecatedSignatureLayout(
+                            rejected
+                        )) if rejected == deprecated
+                    );
+                } else {
+                    assert!(result.is_ok());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_unsupported_tx_version_rejected() {
+        let version = TxVersion(TxVersion::CURRENT.0 + 1);
+        for layout in LAYOUTS {
+            let tx = fixture_tx(version, layout);
+            assert_matches!(
+                verify(&tx, &BTreeSet::new()),
+                Err(VerifySigError::UnsupportedTxVersion(v)) if v == version.0
+            );
+        }
+
+        let tx = fixture_tx(version, SignatureLayout::AllSections);
+        assert_matches!(
+            tx.validate_tx(),
+            Err(TxError::UnsupportedVersion(v)) if v == version.0
+        );
+    }
+
+    #[test]
+    fn test_version_section_keeps_header() {
+        let unversioned =
+            fixture_tx(TxVersion::INITIAL, SignatureLayout::AllSections);
+        assert!(
+            !unversioned
+                .sections
+                .iter()
+                .any(|section| matches!(section, Section::Version(_)))
+        );
+
+        // A version section doesn't change the encoding nor the hash of the
+        // header
+        let version = TxVersion(TxVersion::INITIAL.0 + 1);
+        let versioned = fixture_tx(version, SignatureLayout::AllSections);
+        assert_eq!(
+            versioned.header.serialize_to_vec(),
+            unversioned.header.serialize_to_vec()
+        );
+        assert_eq!(versioned.header_hash(), unversioned.header_hash());
+        let decoded = Tx::try_from_slice(&versioned.serialize_to_vec())
+            .expect("The versioned tx must be parseable");
+        assert_eq!(decoded.version(), version);
+    }
+
+    #[test]
+    fn test_unsigned_version_section_rejected() {
+        // A version section added after the signature isn't covered by it
+        for layout in LAYOUTS {
+            let mut tx = fixture_tx(TxVersion::INITIAL, layout);
+            tx.add_section(Section::Version(TxVersion::CURRENT));
+            assert_eq!(tx.version(), TxVersion::CURRENT);
+            assert_matches!(
+                verify(&tx, &BTreeSet::new()),
+                Err(VerifySigError::UnsignedTxVersion)
+            );
+        }
+    }
+
+    #[test]
+    fn test_golden_txs() {
+        for (layout, golden) in GOLDEN_TXS {
+            let bytes = HEXLOWER
+                .decode(golden.trim().as_bytes())
+                .expect("The golden tx must be valid hex");
+            let tx = Tx::try_from_slice(&bytes)
+                .expect("The golden tx must be parseable");
+            assert_eq!(tx.version(), TxVersion::INITIAL);
+            assert_eq!(
+                tx.serialize_to_vec(),
+                bytes,
+                "The golden tx must be re-encoded identically"
+            );
+            assert_eq!(
+                fixture_tx(TxVersion::CURRENT, layout).serialize_to_vec(),
+                bytes,
+                "The {layout} golden tx must match the tx built by this node"
+            );
+            let signature = tx
+                .sections
+                .iter()
+                .find_map(Section::signature)
+                .expect("The golden tx must be signed");
+            assert_eq!(signature.layout(&tx.raw_header_hash()), layout);
+            assert!(
+                verify(&tx, &BTreeSet::new()).is_ok(),
+                "The {layout} golden tx must be verifiable"
+            );
+        }
+    }
+}
@@ -4,27 +4,43 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use namada_core::address::{Address, InternalAddress};
+use namada_core::ibc::core::host::types::identifiers::{ChannelId, PortId};
 pub use namada_core::ibc::{IbcEvent, IbcShieldedTransfer};
 use namada_core::token::DenominatedAmount;
 pub use namada_ibc::storage::is_ibc_key;
 pub use namada_ibc::{
-    IbcActions, IbcCommonContext, IbcStorageContext, ProofSpec, TransferModule,
+    IbcActions, IbcCommonContext, IbcStorageContext, PfmModule, ProofSpec,
+    TransferModule,
 };
 use namada_token::denom_to_amount;
 use namada_tx_env::TxEnv;
 
 use crate::token::{burn, mint, transfer};
-use crate::{Ctx, Error};
+use crate::{Ctx, Error, StorageWrite};
 
 /// IBC actions to handle an IBC message
 pub fn ibc_actions(ctx: &mut Ctx) -> IbcActions<Ctx> {
     let ctx = Rc::new(RefCell::new(ctx.clone()));
     let mut actions = IbcActions::new(ctx.clone());
-    let module = TransferModule::new(ctx);
-    actions.add_transfer_module(module.module_id(), module);
+    let module = PfmModule::new(ctx);
+    actions.add_pfm_module(module);
     actions
 }
 
+/// Register the payee to receive the relayer fees of the packets relayed by
+/// the relayer on the channel. The tx has to be signed by the relayer.
+pub fn register_payee(
+    ctx: &mut Ctx,
+    port_id: &PortId,
+    channel_id: &ChannelId,
+    relayer: &Address,
+    payee: &Address,
+) -> Result<(), Error> {
+    let key = namada_ibc::storage::payee_key(port_id, channel_id, relayer);
+    ctx.insert_verifier(relayer)?;
+    ctx.write(&key, payee)
+}
+
 impl IbcStorageContext for Ctx {
     fn emit_ibc_event(
         &mut self,
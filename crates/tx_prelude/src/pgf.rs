@@ -15,6 +15,18 @@ pub fn update_steward_commission(
     Ok(())
 }
 
+pub fn set_steward_commission_rate(
+    ctx: &mut Ctx,
+    steward: Address,
+    rate: dec::Dec,
+) -> EnvResult<()> {
+    namada_governance::pgf::storage::write_steward_commission_rate(
+        ctx, steward, rate,
+    )?;
+
+    Ok(())
+}
+
 pub fn remove_steward(ctx: &mut Ctx, data: &Address) -> EnvResult<()> {
     namada_governance::pgf::storage::remove_steward(ctx, data)?;
 
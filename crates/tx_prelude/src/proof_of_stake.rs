@@ -3,13 +3,18 @@
 use namada_core::dec::Dec;
 use namada_core::{key, token};
 pub use namada_proof_of_stake::parameters::PosParams;
-use namada_proof_of_stake::storage::read_pos_params;
-use namada_proof_of_stake::types::{ResultSlashing, ValidatorMetaData};
+use namada_proof_of_stake::storage::{
+    read_pos_params, read_validator_max_total_delegated, validator_state_handle,
+};
+use namada_proof_of_stake::types::{
+    ResultSlashing, ValidatorMetaData, ValidatorState,
+};
 use namada_proof_of_stake::{
     become_validator, bond_tokens, change_consensus_key,
     change_validator_commission_rate, change_validator_metadata,
     claim_reward_tokens, deactivate_validator, reactivate_validator,
-    redelegate_tokens, unbond_tokens, unjail_validator, withdraw_tokens,
+    redelegate_tokens, unbond_tokens, unjail_validator,
+    validator_total_delegated, withdraw_tokens,
 };
 pub use namada_proof_of_stake::{parameters, types};
 use namada_tx::data::pos::BecomeValidator;
@@ -30,6 +35,65 @@ impl Ctx {
         bond_tokens(self, source, validator, amount, current_epoch, None)
     }
 
+    /// Bond tokens like [`Ctx::bond_tokens`], but debit the bonded tokens
+    /// from the `payer`. The payer is added to the verifiers of the tx, so
+    /// that its VP authorizes the payment.
+    pub fn bond_tokens_for(
+        &mut self,
+        payer: &Address,
+        source: Option<&Address>,
+        validator: &Address,
+        amount: token::Amount,
+    ) -> TxResult {
+        self.insert_verifier(payer)?;
+        // Fund the bond's source, which transfers the tokens to PoS
+        let staking_token = self.get_native_token()?;
+        let bond_source = source.unwrap_or(validator);
+        crate::token::undenominated_transfer(
+            self,
+            payer,
+            bond_source,
+            &staking_token,
+            amount,
+        )?;
+        self.bond_tokens(source, validator, amount)
+    }
+
+    /// Read the PoS parameters
+    pub fn read_pos_params(&self) -> EnvResult<PosParams> {
+        read_pos_params(self)
+    }
+
+    /// Read the state of a validator at the pipeline epoch. Returns `None`
+    /// if the address is not a validator.
+    pub fn validator_state_at_pipeline(
+        &self,
+        validator: &Address,
+    ) -> EnvResult<Option<ValidatorState>> {
+        let params = read_pos_params(self)?;
+        let pipeline_epoch = self.get_block_epoch()? + params.pipeline_len;
+        validator_state_handle(validator).get(self, pipeline_epoch, &params)
+    }
+
+    /// Read the cap on the total amount delegated to a validator and the
+    /// amount delegated to it at the pipeline epoch. Returns `None` if the
+    /// validator has no cap.
+    pub fn validator_delegation_cap_at_pipeline(
+        &self,
+        validator: &Address,
+    ) -> EnvResult<Option<(token::Amount, token::Amount)>> {
+        let Some(max_total_delegated) =
+            read_validator_max_total_delegated(self, validator)?
+        else {
+            return Ok(None);
+        };
+        let params = read_pos_params(self)?;
+        let pipeline_epoch = self.get_block_epoch()? + params.pipeline_len;
+        let total_delegated =
+            validator_total_delegated(self, validator, pipeline_epoch)?;
+        Ok(Some((max_total_delegated, total_delegated)))
+    }
+
     /// Unbond self-bonded tokens from a validator when `source` is `None`
     /// or equal to the `validator` address, or unbond delegated tokens from
     /// the `source` to the `validator`.
@@ -183,6 +247,7 @@ impl Ctx {
         discord_handle: Option<String>,
         avatar: Option<String>,
         commission_rate: Option<Dec>,
+        max_total_delegated: Option<token::Amount>,
     ) -> TxResult {
         let current_epoch = self.get_block_epoch()?;
         change_validator_metadata(
@@ -194,6 +259,7 @@ impl Ctx {
             discord_handle,
             avatar,
             commission_rate,
+            max_total_delegated,
             current_epoch,
         )
     }
@@ -96,8 +96,32 @@ where
     fn iter_prefix<'iter>(
         &'iter self,
         prefix: &Key,
+    ) -> Result<Self::PrefixIter<'iter>, namada_storage::Error> {
+        self.iter_prefix_pre(prefix)
+    }
+
+    /// Storage prefix iterator for prior state (before tx execution), ordered
+    /// by storage keys. The gas is charged for the prefix and then for each
+    /// item yielded by [`VpEnv::iter_next`].
+    fn iter_prefix_pre<'iter>(
+        &'iter self,
+        prefix: &Key,
+    ) -> Result<Self::PrefixIter<'iter>, namada_storage::Error>;
+
+    /// Storage prefix iterator for posterior state (after tx execution),
+    /// ordered by storage keys. The gas is charged for the prefix and then for
+    /// each item yielded by [`VpEnv::iter_next`].
+    fn iter_prefix_post<'iter>(
+        &'iter self,
+        prefix: &Key,
     ) -> Result<Self::PrefixIter<'iter>, namada_storage::Error>;
 
+    /// Get the next key-value pair of a prefix iterator (pre or post).
+    fn iter_next<'iter>(
+        &'iter self,
+        iter: &mut Self::PrefixIter<'iter>,
+    ) -> Result<Option<(String, Vec<u8>)>, namada_storage::Error>;
+
     /// Evaluate a validity predicate with given data. The address, changed
     /// storage keys and verifiers will have the same values as the input to
     /// caller's validity predicate.
@@ -285,13 +285,31 @@ impl<'view> VpEnv<'view> for Ctx {
         }
     }
 
-    fn iter_prefix<'iter>(
+    fn iter_prefix_pre<'iter>(
         &'iter self,
         prefix: &storage::Key,
     ) -> Result<Self::PrefixIter<'iter>, Error> {
         iter_prefix_pre_impl(prefix)
     }
 
+    fn iter_prefix_post<'iter>(
+        &'iter self,
+        prefix: &storage::Key,
+    ) -> Result<Self::PrefixIter<'iter>, Error> {
+        iter_prefix_post_impl(prefix)
+    }
+
+    fn iter_next<'iter>(
+        &'iter self,
+        iter: &mut Self::PrefixIter<'iter>,
+    ) -> Result<Option<(String, Vec<u8>)>, Error> {
+        let read_result = unsafe { namada_vp_iter_next(iter.0) };
+        Ok(read_key_val_bytes_from_buffer(
+            read_result,
+            namada_vp_result_buffer,
+        ))
+    }
+
     fn eval(&self, vp_code_hash: Hash, input_data: Tx) -> Result<bool, Error> {
         let input_data_bytes = borsh::to_vec(&input_data).unwrap();
         let result = unsafe {
@@ -1,5 +1,6 @@
 //! A tx for a PoS bond that stakes tokens via a self-bond or delegation.
 
+use namada_tx_prelude::proof_of_stake::types::ValidatorState;
 use namada_tx_prelude::*;
 
 #[transaction(gas = 1342908)]
@@ -9,11 +10,103 @@ fn apply_tx(ctx: &mut Ctx, tx_data: Tx) -> TxResult {
         ctx.set_commitment_sentinel();
         err
     })?;
-    let bond = transaction::pos::Bond::try_from_slice(&data[..])
-        .wrap_err("failed to decode Bond")
-        .unwrap();
+    let (bond, payer) = match transaction::pos::Bond::try_from_slice(&data) {
+        Ok(bond) => (bond, None),
+        // A bond funded by a payer
+        Err(_) => {
+            let transaction::pos::BondFor { bond, payer } =
+                transaction::pos::BondFor::try_from_slice(&data)
+                    .wrap_err("failed to decode Bond")
+                    .unwrap();
+            (bond, Some(payer))
+        }
+    };
+
+    check_bond_validator(ctx, &bond.validator)?;
+    check_min_delegation(ctx, &bond)?;
+    check_max_total_delegated(ctx, &bond)?;
 
-    ctx.bond_tokens(bond.source.as_ref(), &bond.validator, bond.amount)
+    match payer {
+        Some(payer) => ctx.bond_tokens_for(
+            &payer,
+            bond.source.as_ref(),
+            &bond.validator,
+            bond.amount,
+        ),
+        None => {
+            ctx.bond_tokens(bond.source.as_ref(), &bond.validator, bond.amount)
+        }
+    }
+}
+
+/// Check that the bond's validator can receive bonds at the pipeline epoch,
+/// i.e. that it's neither jailed nor inactive
+fn check_bond_validator(ctx: &Ctx, validator: &Address) -> TxResult {
+    match ctx.validator_state_at_pipeline(validator)? {
+        None => Err(Error::new(format!(
+            "Cannot bond to {validator}: the address is not a validator"
+        ))),
+        Some(state @ (ValidatorState::Jailed | ValidatorState::Inactive)) => {
+            Err(Error::new(format!(
+                "Cannot bond to the validator {validator}: the validator is \
+                 {state:?} at the pipeline epoch"
+            )))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// Check that a delegation is at least the minimum delegation amount. The
+/// self-bonds are exempt, so that validators can top up their stake by small
+/// amounts.
+fn check_min_delegation(ctx: &Ctx, bond: &transaction::pos::Bond) -> TxResult {
+    let is_delegation =
+        matches!(&bond.source, Some(source) if *source != bond.validator);
+    if !is_delegation {
+        return Ok(());
+    }
+    let min_delegation_amount = ctx.read_pos_params()?.min_delegation_amount;
+    if bond.amount < min_delegation_amount {
+        return Err(Error::new(format!(
+            "The delegation of {} to the validator {} is below the minimum \
+             delegation amount {}",
+            bond.amount.to_string_native(),
+            bond.validator,
+            min_delegation_amount.to_string_native()
+        )));
+    }
+    Ok(())
+}
+
+/// Check that a delegation doesn't raise the total amount delegated to the
+/// validator at the pipeline epoch above the validator's cap, if it has set
+/// one. The self-bonds are exempt.
+fn check_max_total_delegated(
+    ctx: &Ctx,
+    bond: &transaction::pos::Bond,
+) -> TxResult {
+    let is_delegation =
+        matches!(&bond.source, Some(source) if *source != bond.validator);
+    if !is_delegation {
+        return Ok(());
+    }
+    let Some((max_total_delegated, total_delegated)) =
+        ctx.validator_delegation_cap_at_pipeline(&bond.validator)?
+    else {
+        return Ok(());
+    };
+    let new_total_delegated = total_delegated.checked_add(bond.amount);
+    if new_total_delegated.map_or(true, |total| total > max_total_delegated) {
+        return Err(Error::new(format!(
+            "The delegation of {} to the validator {} would exceed its max \
+             total delegated amount {}, of which {} is already delegated",
+            bond.amount.to_string_native(),
+            bond.validator,
+            max_total_delegated.to_string_native(),
+            total_delegated.to_string_native()
+        )));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -23,12 +116,18 @@ mod tests {
 
     use namada::core::dec::Dec;
     use namada::ledger::gas::VpGasMeter;
-    use namada::ledger::pos::{OwnedPosParams, PosVP};
+    use namada::ledger::pos::{bond_events, OwnedPosParams, PosVP};
+    use namada::proof_of_stake::queries::bonds_and_unbonds_projection;
     use namada::proof_of_stake::storage::{
         bond_handle, read_consensus_validator_set_addresses_with_stake,
-        read_total_stake, read_validator_stake,
+        read_pos_params, read_total_stake, read_validator_stake,
+        validator_slashes_handle, validator_state_handle,
+        write_validator_max_total_delegated,
+    };
+    use namada::proof_of_stake::storage_key::is_pos_key;
+    use namada::proof_of_stake::types::{
+        BondProjection, GenesisValidator, Slash, SlashType, WeightedValidator,
     };
-    use namada::proof_of_stake::types::{GenesisValidator, WeightedValidator};
     use namada::validity_predicate::VpSentinel;
     use namada_tests::log::test;
     use namada_tests::native_vp::pos::init_pos;
@@ -60,13 +159,68 @@ mod tests {
             // A key to sign the transaction
             key in arb_common_keypair(),
             pos_params in arb_pos_params(None)) {
-            test_tx_bond_aux(initial_stake, bond, key, pos_params).unwrap()
+            test_tx_bond_aux(initial_stake, bond, None, key, pos_params)
+                .unwrap()
+        }
+
+        /// Test a bond with the tokens paid by another account than the
+        /// bond's source
+        #[test]
+        fn test_tx_bond_for(
+            (initial_stake, bond) in arb_initial_stake_and_bond(),
+            payer in arb_established_address(),
+            // A key to sign the transaction
+            key in arb_common_keypair(),
+            pos_params in arb_pos_params(None)) {
+            let payer = Address::Established(payer);
+            prop_assume!(Some(&payer) != bond.source.as_ref());
+            prop_assume!(payer != bond.validator);
+            test_tx_bond_aux(initial_stake, bond, Some(payer), key, pos_params)
+                .unwrap()
+        }
+
+        /// Test that a bond can't be paid by an account with an insufficient
+        /// balance
+        #[test]
+        fn test_tx_bond_for_insufficient_balance(
+            (initial_stake, bond) in arb_initial_stake_and_bond(),
+            payer in arb_established_address(),
+            // A key to sign the transaction
+            key in arb_common_keypair(),
+            pos_params in arb_pos_params(None)) {
+            let payer = Address::Established(payer);
+            prop_assume!(Some(&payer) != bond.source.as_ref());
+            prop_assume!(payer != bond.validator);
+            test_tx_bond_for_insufficient_balance_aux(
+                initial_stake,
+                bond,
+                payer,
+                key,
+                pos_params,
+            )
+        }
+
+        /// Test that a bond to a validator jailed at the pipeline epoch is
+        /// rejected before any PoS storage changes
+        #[test]
+        fn test_tx_bond_to_jailed_validator(
+            (initial_stake, bond) in arb_initial_stake_and_bond(),
+            // A key to sign the transaction
+            key in arb_common_keypair(),
+            pos_params in arb_pos_params(None)) {
+            test_tx_bond_to_jailed_validator_aux(
+                initial_stake,
+                bond,
+                key,
+                pos_params,
+            )
         }
     }
 
     fn test_tx_bond_aux(
         initial_stake: token::Amount,
         bond: transaction::pos::Bond,
+        payer: Option<Address>,
         key: key::common::SecretKey,
         pos_params: OwnedPosParams,
     ) -> TxResult {
@@ -105,16 +259,30 @@ mod tests {
             if let Some(source) = &bond.source {
                 tx_env.spawn_accounts([source]);
             }
+            if let Some(payer) = &payer {
+                tx_env.spawn_accounts([payer]);
+            }
 
-            // Ensure that the bond's source has enough tokens for the bond
-            let target = bond.source.as_ref().unwrap_or(&bond.validator);
+            // Ensure that the bond's payer or source has enough tokens for
+            // the bond
+            let target = payer
+                .as_ref()
+                .or(bond.source.as_ref())
+                .unwrap_or(&bond.validator);
             let native_token = tx_env.state.in_mem().native_token.clone();
             tx_env.credit_tokens(target, &native_token, bond.amount);
             native_token
         });
 
         let tx_code = vec![];
-        let tx_data = bond.serialize_to_vec();
+        let tx_data = match &payer {
+            Some(payer) => transaction::pos::BondFor {
+                bond: bond.clone(),
+                payer: payer.clone(),
+            }
+            .serialize_to_vec(),
+            None => bond.serialize_to_vec(),
+        };
         let mut tx = Tx::new(ChainId::default(), None);
         tx.add_code(tx_code, None)
             .add_serialized_data(tx_data)
@@ -162,6 +330,30 @@ mod tests {
 
         apply_tx(ctx(), signed_tx)?;
 
+        // Check the bond event emitted by the protocol for the tx
+        let events: Vec<event::BondEvent> = tx_host_env::with(|tx_env| {
+            let keys_changed = tx_env.state.write_log().get_keys();
+            bond_events(&tx_env.state, &keys_changed)
+                .unwrap()
+                .iter()
+                .map(|event| event::BondEvent::try_from(event).unwrap())
+                .collect()
+        });
+        let pipeline_epoch = ctx().get_block_epoch()? + pos_params.pipeline_len;
+        assert_eq!(
+            events,
+            vec![event::BondEvent {
+                kind: event::BondEventKind::Bond,
+                validator: bond.validator.clone(),
+                source: bond
+                    .source
+                    .clone()
+                    .unwrap_or_else(|| bond.validator.clone()),
+                amount: bond.amount,
+                epoch: pipeline_epoch,
+            }]
+        );
+
         // Read the data after the tx is executed.
         let mut epoched_total_stake_post: Vec<token::Amount> = Vec::new();
         let mut epoched_validator_stake_post: Vec<token::Amount> = Vec::new();
@@ -262,6 +454,19 @@ mod tests {
             ctx().read(&pos_balance_key)?.unwrap();
         assert_eq!(pos_balance_pre + bond.amount, pos_balance_post);
 
+        //     - `#{staking_token}/balance/#{payer}`
+        // Check that the payer paid for the bond and must authorize it
+        if let Some(payer) = &payer {
+            let payer_balance_key =
+                token::storage_key::balance_key(&native_token, payer);
+            let payer_balance_post: token::Amount =
+                ctx().read(&payer_balance_key)?.unwrap_or_default();
+            assert!(payer_balance_post.is_zero());
+            let is_payer_verifier =
+                tx_host_env::with(|tx_env| tx_env.verifiers.contains(payer));
+            assert!(is_payer_verifier);
+        }
+
         //     - `#{PoS}/bond/#{owner}/#{validator}`
         let bond_src = bond
             .source
@@ -326,6 +531,38 @@ mod tests {
             }
         }
 
+        // Check that the projection of the bond from the current epoch has
+        // the bonded amount added at the pipeline offset
+        let projection =
+            bonds_and_unbonds_projection(ctx(), &bond_src, &bond.validator)?;
+        let bond_pre = if is_delegation {
+            token::Amount::zero()
+        } else {
+            initial_stake
+        };
+        let expected_bonded = (0..=pos_params.unbonding_len)
+            .map(|epoch| {
+                if epoch < pos_params.pipeline_len {
+                    bond_pre
+                } else {
+                    bond_pre + bond.amount
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_bond_projection(
+            &projection,
+            Epoch(0),
+            token::Amount::zero(),
+            &expected_bonded,
+        );
+        for projected in &projection.epochs {
+            assert_eq!(
+                projected.total, projected.bonded,
+                "The bond is not slashed - checking epoch {}",
+                projected.epoch
+            );
+        }
+
         // Use the tx_env to run PoS VP
         let tx_env = tx_host_env::take();
         let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
@@ -343,6 +580,637 @@ mod tests {
         Ok(())
     }
 
+    fn test_tx_bond_for_insufficient_balance_aux(
+        initial_stake: token::Amount,
+        bond: transaction::pos::Bond,
+        payer: Address,
+        key: key::common::SecretKey,
+        pos_params: OwnedPosParams,
+    ) {
+        let pos_params = OwnedPosParams {
+            validator_stake_threshold: token::Amount::zero(),
+            ..pos_params
+        };
+        let genesis_validators =
+            [genesis_validator(bond.validator.clone(), initial_stake)];
+        init_pos(&genesis_validators[..], &pos_params, Epoch(0));
+
+        tx_host_env::with(|tx_env| {
+            if let Some(source) = &bond.source {
+                tx_env.spawn_accounts([source]);
+            }
+            tx_env.spawn_accounts([&payer]);
+
+            // The payer is short of a token for the bond, while the source
+            // could pay for it
+            let native_token = tx_env.state.in_mem().native_token.clone();
+            let source = bond.source.as_ref().unwrap_or(&bond.validator);
+            tx_env.credit_tokens(source, &native_token, bond.amount);
+            let payer_amount = bond
+                .amount
+                .checked_sub(token::Amount::from(1))
+                .expect("The bond amount is non-zero");
+            tx_env.credit_tokens(&payer, &native_token, payer_amount);
+        });
+
+        let tx_data =
+            transaction::pos::BondFor { bond, payer }.serialize_to_vec();
+        let mut tx = Tx::new(ChainId::default(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(tx_data)
+            .sign_wrapper(key);
+
+        assert!(apply_tx(ctx(), tx).is_err());
+    }
+
+    fn test_tx_bond_to_jailed_validator_aux(
+        initial_stake: token::Amount,
+        bond: transaction::pos::Bond,
+        key: key::common::SecretKey,
+        pos_params: OwnedPosParams,
+    ) {
+        let pos_params = OwnedPosParams {
+            validator_stake_threshold: token::Amount::zero(),
+            ..pos_params
+        };
+        let genesis_validators =
+            [genesis_validator(bond.validator.clone(), initial_stake)];
+        let pos_params =
+            init_pos(&genesis_validators[..], &pos_params, Epoch(0));
+
+        tx_host_env::with(|tx_env| {
+            if let Some(source) = &bond.source {
+                tx_env.spawn_accounts([source]);
+            }
+            let native_token = tx_env.state.in_mem().native_token.clone();
+            let source = bond.source.as_ref().unwrap_or(&bond.validator);
+            tx_env.credit_tokens(source, &native_token, bond.amount);
+        });
+        // Jail the validator at the pipeline epoch
+        validator_state_handle(&bond.validator)
+            .set(
+                ctx(),
+                ValidatorState::Jailed,
+                Epoch(0),
+                pos_params.pipeline_len,
+            )
+            .unwrap();
+        tx_host_env::commit_tx_and_block();
+
+        let validator = bond.validator.clone();
+        let tx_data = bond.serialize_to_vec();
+        let mut tx = Tx::new(ChainId::default(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(tx_data)
+            .sign_wrapper(key);
+
+        let err = apply_tx(ctx(), tx).expect_err("The bond must be rejected");
+        let err = err.to_string();
+        assert!(err.contains(&validator.to_string()));
+        assert!(err.contains("Jailed"));
+
+        let changed_keys =
+            tx_host_env::with(|tx_env| tx_env.all_touched_storage_keys());
+        assert!(!changed_keys.iter().any(is_pos_key));
+    }
+
+    fn genesis_validator(
+        address: Address,
+        tokens: token::Amount,
+    ) -> GenesisValidator {
+        GenesisValidator {
+            address,
+            tokens,
+            consensus_key: key::testing::keypair_1().ref_to(),
+            protocol_key: key::testing::keypair_2().ref_to(),
+            eth_cold_key: key::testing::keypair_3().ref_to(),
+            eth_hot_key: key::testing::keypair_4().ref_to(),
+            commission_rate: Dec::new(5, 2).expect("Cannot fail"),
+            max_commission_rate_change: Dec::new(1, 2).expect("Cannot fail"),
+            metadata: Default::default(),
+        }
+    }
+
+    /// Init PoS with a validator and a delegator with the given min
+    /// delegation amount. Returns the validator and the delegator.
+    fn init_min_delegation(
+        min_delegation_amount: token::Amount,
+    ) -> (Address, Address) {
+        let validator = address::testing::established_address_1();
+        let delegator = address::testing::established_address_2();
+        let pos_params = OwnedPosParams {
+            validator_stake_threshold: token::Amount::zero(),
+            min_delegation_amount,
+            ..Default::default()
+        };
+        init_pos(
+            &[genesis_validator(
+                validator.clone(),
+                token::Amount::native_whole(100),
+            )],
+            &pos_params,
+            Epoch(0),
+        );
+        tx_host_env::with(|tx_env| {
+            tx_env.spawn_accounts([&delegator]);
+            let native_token = tx_env.state.in_mem().native_token.clone();
+            for owner in [&validator, &delegator] {
+                tx_env.credit_tokens(
+                    owner,
+                    &native_token,
+                    min_delegation_amount,
+                );
+            }
+        });
+        (validator, delegator)
+    }
+
+    /// Apply the bond tx with the given bond
+    fn apply_bond_tx(bond: transaction::pos::Bond) -> TxResult {
+        let mut tx = Tx::new(ChainId::default(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(bond.serialize_to_vec())
+            .sign_wrapper(key::testing::keypair_1());
+        apply_tx(ctx(), tx)
+    }
+
+    /// Run the PoS VP on the changes of the tx
+    fn validate_pos_vp() -> bool {
+        let tx_env = tx_host_env::take();
+        let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
+            &tx_env.gas_meter.borrow(),
+        ));
+        let sentinel = RefCell::new(VpSentinel::default());
+        let vp_env = TestNativeVpEnv::from_tx_env(tx_env, address::POS);
+        vp_env
+            .validate_tx(&gas_meter, &sentinel, PosVP::new)
+            .expect("Validation must not fail")
+    }
+
+    #[test]
+    fn test_tx_bond_below_min_delegation() {
+        let min_delegation_amount = token::Amount::native_whole(10);
+        let below_min = min_delegation_amount - token::Amount::from(1);
+
+        // The tx rejects the delegation
+        let (validator, delegator) = init_min_delegation(min_delegation_amount);
+        let result = apply_bond_tx(transaction::pos::Bond {
+            validator: validator.clone(),
+            amount: below_min,
+            source: Some(delegator.clone()),
+        });
+        assert!(result.is_err());
+
+        // The VP rejects a delegation bypassing the tx's check
+        ctx()
+            .bond_tokens(Some(&delegator), &validator, below_min)
+            .unwrap();
+        assert!(!validate_pos_vp());
+    }
+
+    #[test]
+    fn test_tx_bond_at_min_delegation() {
+        let min_delegation_amount = token::Amount::native_whole(10);
+        let (validator, delegator) = init_min_delegation(min_delegation_amount);
+        apply_bond_tx(transaction::pos::Bond {
+            validator,
+            amount: min_delegation_amount,
+            source: Some(delegator),
+        })
+        .unwrap();
+        assert!(validate_pos_vp());
+    }
+
+    #[test]
+    fn test_tx_self_bond_below_min_delegation() {
+        let min_delegation_amount = token::Amount::native_whole(10);
+        let (validator, _delegator) =
+            init_min_delegation(min_delegation_amount);
+        apply_bond_tx(transaction::pos::Bond {
+            validator,
+            amount: token::Amount::from(1),
+            source: None,
+        })
+        .unwrap();
+        assert!(validate_pos_vp());
+    }
+
+    /// Init PoS with three validators and a delegator with the given min
+    /// delegation amount. The delegator has a delegation of the min amount to
+    /// the first validator. Returns the validators and the delegator.
+    fn init_min_delegation_redelegation(
+        min_delegation_amount: token::Amount,
+    ) -> ([Address; 3], Address) {
+        let validators = [
+            address::testing::established_address_1(),
+            address::testing::established_address_3(),
+            address::testing::established_address_4(),
+        ];
+        let delegator = address::testing::established_address_2();
+        let pos_params = OwnedPosParams {
+            validator_stake_threshold: token::Amount::zero(),
+            min_delegation_amount,
+            ..Default::default()
+        };
+        let genesis_validators: Vec<_> = (1_u64..)
+            .zip(&validators)
+            .map(|(seed, validator)| GenesisValidator {
+                consensus_key: key::testing::common_sk_from_simple_seed(seed)
+                    .ref_to(),
+                ..genesis_validator(
+                    validator.clone(),
+                    token::Amount::native_whole(100),
+                )
+            })
+            .collect();
+        init_pos(&genesis_validators, &pos_params, Epoch(0));
+        tx_host_env::with(|tx_env| {
+            tx_env.spawn_accounts([&delegator]);
+            let native_token = tx_env.state.in_mem().native_token.clone();
+            tx_env.credit_tokens(
+                &delegator,
+                &native_token,
+                min_delegation_amount,
+            );
+        });
+        ctx()
+            .bond_tokens(
+                Some(&delegator),
+                &validators[0],
+                min_delegation_amount,
+            )
+            .unwrap();
+        tx_host_env::commit_tx_and_block();
+        (validators, delegator)
+    }
+
+    #[test]
+    fn test_tx_redelegation_below_min_delegation() {
+        let min_delegation_amount = token::Amount::native_whole(10);
+        let below_min = min_delegation_amount - token::Amount::from(1);
+        let ([src, dest, _other], delegator) =
+            init_min_delegation_redelegation(min_delegation_amount);
+
+        // A redelegation of less than the min amount is not a new delegation
+        ctx()
+            .redelegate_tokens(&delegator, &src, &dest, below_min)
+            .unwrap();
+        assert!(validate_pos_vp());
+    }
+
+    #[test]
+    fn test_tx_redelegation_with_bond_below_min_delegation() {
+        let min_delegation_amount = token::Amount::native_whole(10);
+        let below_min = min_delegation_amount - token::Amount::from(1);
+        let ([src, dest, other], delegator) =
+            init_min_delegation_redelegation(min_delegation_amount);
+        tx_host_env::with(|tx_env| {
+            let native_token = tx_env.state.in_mem().native_token.clone();
+            tx_env.credit_tokens(&delegator, &native_token, below_min);
+        });
+
+        // Only the redelegated bond is exempted, not the other delegations of
+        // the same delegator in the tx
+        ctx()
+            .redelegate_tokens(&delegator, &src, &dest, below_min)
+            .unwrap();
+        ctx()
+            .bond_tokens(Some(&delegator), &other, below_min)
+            .unwrap();
+        assert!(!validate_pos_vp());
+    }
+
+    /// Init PoS with a validator that has the given max total delegated
+    /// amount, if any, and a delegator. Returns the validator and the
+    /// delegator.
+    fn init_max_total_delegated(
+        max_total_delegated: Option<token::Amount>,
+    ) -> (Address, Address) {
+        let (validator, delegator) = init_min_delegation(token::Amount::zero());
+        tx_host_env::with(|tx_env| {
+            let native_token = tx_env.state.in_mem().native_token.clone();
+            for owner in [&validator, &delegator] {
+                tx_env.credit_tokens(
+                    owner,
+                    &native_token,
+                    token::Amount::native_whole(100),
+                );
+            }
+        });
+        if let Some(max_total_delegated) = max_total_delegated {
+            write_validator_max_total_delegated(
+                ctx(),
+                &validator,
+                max_total_delegated,
+            )
+            .unwrap();
+        }
+        tx_host_env::commit_tx_and_block();
+        (validator, delegator)
+    }
+
+    #[test]
+    fn test_tx_bond_above_max_total_delegated() {
+        let max_total_delegated = token::Amount::native_whole(10);
+        let (validator, delegator) =
+            init_max_total_delegated(Some(max_total_delegated));
+        let err = apply_bond_tx(transaction::pos::Bond {
+            validator: validator.clone(),
+            amount: max_total_delegated + token::Amount::from(1),
+            source: Some(delegator),
+        })
+        .expect_err("The delegation must be rejected");
+        let err = err.to_string();
+        assert!(err.contains(&validator.to_string()));
+        assert!(err.contains("max total delegated amount"));
+
+        let changed_keys =
+            tx_host_env::with(|tx_env| tx_env.all_touched_storage_keys());
+        assert!(!changed_keys.iter().any(is_pos_key));
+    }
+
+    #[test]
+    fn test_tx_bond_at_max_total_delegated() {
+        let max_total_delegated = token::Amount::native_whole(10);
+        let (validator, delegator) =
+            init_max_total_delegated(Some(max_total_delegated));
+        apply_bond_tx(transaction::pos::Bond {
+            validator,
+            amount: max_total_delegated,
+            source: Some(delegator),
+        })
+        .unwrap();
+        assert!(validate_pos_vp());
+    }
+
+    #[test]
+    fn test_tx_self_bond_above_max_total_delegated() {
+        let max_total_delegated = token::Amount::native_whole(10);
+        let (validator, _delegator) =
+            init_max_total_delegated(Some(max_total_delegated));
+        apply_bond_tx(transaction::pos::Bond {
+            validator,
+            amount: max_total_delegated + token::Amount::from(1),
+            source: None,
+        })
+        .unwrap();
+        assert!(validate_pos_vp());
+    }
+
+    #[test]
+    fn test_tx_bond_without_max_total_delegated() {
+        let (validator, delegator) = init_max_total_delegated(None);
+        apply_bond_tx(transaction::pos::Bond {
+            validator,
+            amount: token::Amount::native_whole(100),
+            source: Some(delegator),
+        })
+        .unwrap();
+        assert!(validate_pos_vp());
+    }
+
+    /// Init PoS with a validator with a self-bond of 100 NAM and the given
+    /// min validator self-bond, and a delegator with a bond of 20 NAM to it.
+    /// Returns the validator and the delegator.
+    fn init_min_self_bond(
+        min_validator_self_bond: token::Amount,
+    ) -> (Address, Address) {
+        let validator = address::testing::established_address_1();
+        let delegator = address::testing::established_address_2();
+        let pos_params = OwnedPosParams {
+            validator_stake_threshold: token::Amount::zero(),
+            min_validator_self_bond,
+            ..Default::default()
+        };
+        init_pos(
+            &[genesis_validator(
+                validator.clone(),
+                token::Amount::native_whole(100),
+            )],
+            &pos_params,
+            Epoch(0),
+        );
+        let delegation = token::Amount::native_whole(20);
+        tx_host_env::with(|tx_env| {
+            tx_env.spawn_accounts([&delegator]);
+            let native_token = tx_env.state.in_mem().native_token.clone();
+            tx_env.credit_tokens(&delegator, &native_token, delegation);
+        });
+        ctx()
+            .bond_tokens(Some(&delegator), &validator, delegation)
+            .unwrap();
+        tx_host_env::commit_tx_and_block();
+        (validator, delegator)
+    }
+
+    #[test]
+    fn test_tx_self_unbond_below_min_self_bond() {
+        let (validator, delegator) =
+            init_min_self_bond(token::Amount::native_whole(90));
+        let amount = token::Amount::native_whole(20);
+
+        // The self-unbond is rejected
+        let err = ctx()
+            .unbond_tokens(None, &validator, amount)
+            .expect_err("The self-unbond must be rejected");
+        let err = err.to_string();
+        assert!(err.contains(&validator.to_string()));
+        assert!(err.contains("minimum validator self-bond"));
+
+        // The same amount from the delegator is fine
+        ctx()
+            .unbond_tokens(Some(&delegator), &validator, amount)
+            .unwrap();
+        assert!(validate_pos_vp());
+    }
+
+    #[test]
+    fn test_tx_self_unbond_at_min_self_bond() {
+        let (validator, _delegator) =
+            init_min_self_bond(token::Amount::native_whole(90));
+        ctx()
+            .unbond_tokens(None, &validator, token::Amount::native_whole(10))
+            .unwrap();
+        assert!(validate_pos_vp());
+    }
+
+    #[test]
+    fn test_tx_self_unbond_deactivated_validator() {
+        let (validator, _delegator) =
+            init_min_self_bond(token::Amount::native_whole(90));
+        // A deactivated validator may unbond its whole self-bond
+        ctx().deactivate_validator(&validator).unwrap();
+        ctx()
+            .unbond_tokens(None, &validator, token::Amount::native_whole(100))
+            .unwrap();
+        assert!(validate_pos_vp());
+    }
+
+    #[test]
+    fn test_vp_self_bond_below_min_self_bond() {
+        let (validator, _delegator) =
+            init_min_self_bond(token::Amount::native_whole(90));
+        // The VP rejects a decrease of the self-bond bypassing the PoS checks
+        bond_handle(&validator, &validator)
+            .get_data_handler()
+            .insert(ctx(), Epoch(0), token::Amount::native_whole(80))
+            .unwrap();
+        assert!(!validate_pos_vp());
+    }
+
+    /// Check that the bond projection starts at the `start` epoch and that it
+    /// has the expected bonded amounts with their deltas, given the bonded
+    /// amount in the epoch before the `start`
+    fn assert_bond_projection(
+        projection: &BondProjection,
+        start: Epoch,
+        prev_bonded: token::Amount,
+        expected_bonded: &[token::Amount],
+    ) {
+        assert_eq!(projection.epochs.len(), expected_bonded.len());
+        let mut prev_bonded = prev_bonded;
+        for ((projected, expected), epoch) in projection
+            .epochs
+            .iter()
+            .zip(expected_bonded)
+            .zip(start.iter_range(expected_bonded.len() as u64))
+        {
+            assert_eq!(projected.epoch, epoch);
+            assert_eq!(
+                projected.bonded, *expected,
+                "Unexpected bonded amount - checking epoch {epoch}"
+            );
+            assert_eq!(
+                projected.delta,
+                expected.change() - prev_bonded.change(),
+                "Unexpected bond delta - checking epoch {epoch}"
+            );
+            prev_bonded = *expected;
+        }
+    }
+
+    #[test]
+    fn test_bond_projection_multiple_bonds_in_same_epoch() {
+        let (validator, delegator) = init_max_total_delegated(None);
+        for amount in [10, 5] {
+            apply_bond_tx(transaction::pos::Bond {
+                validator: validator.clone(),
+                amount: token::Amount::native_whole(amount),
+                source: Some(delegator.clone()),
+            })
+            .unwrap();
+        }
+
+        // Both bonds are added up at the pipeline offset
+        let pos_params = read_pos_params(ctx()).unwrap();
+        let projection =
+            bonds_and_unbonds_projection(ctx(), &delegator, &validator)
+                .unwrap();
+        let expected_bonded = (0..=pos_params.unbonding_len)
+            .map(|epoch| {
+                if epoch < pos_params.pipeline_len {
+                    token::Amount::zero()
+                } else {
+                    token::Amount::native_whole(15)
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_bond_projection(
+            &projection,
+            Epoch(0),
+            token::Amount::zero(),
+            &expected_bonded,
+        );
+        for projected in &projection.epochs {
+            assert_eq!(projected.total, projected.bonded);
+        }
+    }
+
+    #[test]
+    fn test_bond_projection_partial_unbond() {
+        let (validator, _delegator) =
+            init_min_delegation(token::Amount::zero());
+        ctx()
+            .unbond_tokens(None, &validator, token::Amount::native_whole(40))
+            .unwrap();
+
+        // The unbonded part of the genesis self-bond keeps contributing to
+        // the stake until the pipeline offset
+        let pos_params = read_pos_params(ctx()).unwrap();
+        let projection =
+            bonds_and_unbonds_projection(ctx(), &validator, &validator)
+                .unwrap();
+        let expected_bonded = (0..=pos_params.unbonding_len)
+            .map(|epoch| {
+                if epoch < pos_params.pipeline_len {
+                    token::Amount::native_whole(100)
+                } else {
+                    token::Amount::native_whole(60)
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_bond_projection(
+            &projection,
+            Epoch(0),
+            token::Amount::zero(),
+            &expected_bonded,
+        );
+        for projected in &projection.epochs {
+            assert_eq!(projected.total, projected.bonded);
+        }
+    }
+
+    #[test]
+    fn test_bond_projection_slashed_validator() {
+        let (validator, _delegator) =
+            init_min_delegation(token::Amount::zero());
+        let pos_params = read_pos_params(ctx()).unwrap();
+
+        // Fast forward past the processing of a slash for an infraction
+        // committed after the genesis self-bond started contributing to the
+        // stake
+        let infraction_epoch = Epoch(5);
+        let current_epoch =
+            infraction_epoch + pos_params.slash_processing_epoch_offset();
+        tx_host_env::with(|env| {
+            env.state.in_mem_mut().block.epoch = current_epoch;
+        });
+        validator_slashes_handle(&validator)
+            .push(
+                ctx(),
+                Slash {
+                    epoch: infraction_epoch,
+                    block_height: 0,
+                    r#type: SlashType::DuplicateVote,
+                    rate: Dec::new(1, 1).expect("Cannot fail"),
+                },
+            )
+            .unwrap();
+
+        // The slash reduces the projected total, but not the bonded amount
+        let projection =
+            bonds_and_unbonds_projection(ctx(), &validator, &validator)
+                .unwrap();
+        let expected_bonded = vec![
+            token::Amount::native_whole(100);
+            pos_params.unbonding_len as usize + 1
+        ];
+        assert_bond_projection(
+            &projection,
+            current_epoch,
+            token::Amount::native_whole(100),
+            &expected_bonded,
+        );
+        for projected in &projection.epochs {
+            assert_eq!(
+                projected.total,
+                token::Amount::native_whole(90),
+                "The slashed bond must be reduced - checking epoch {}",
+                projected.epoch
+            );
+        }
+    }
+
     prop_compose! {
         /// Generates an initial validator stake and a bond, while making sure
         /// that the `initial_stake + bond.amount <= u64::MAX` to avoid
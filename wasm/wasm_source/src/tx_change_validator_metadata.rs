@@ -17,6 +17,7 @@ fn apply_tx(ctx: &mut Ctx, tx_data: Tx) -> TxResult {
         discord_handle,
         avatar,
         commission_rate,
+        max_total_delegated,
     } = transaction::pos::MetaDataChange::try_from_slice(&data[..])
         .wrap_err("failed to decode Dec value")?;
     ctx.change_validator_metadata(
@@ -27,5 +28,6 @@ fn apply_tx(ctx: &mut Ctx, tx_data: Tx) -> TxResult {
         discord_handle,
         avatar,
         commission_rate,
+        max_total_delegated,
     )
 }
@@ -17,9 +17,37 @@ fn apply_tx(ctx: &mut Ctx, tx_data: Tx) -> TxResult {
         amount,
     } = transaction::pos::Redelegation::try_from_slice(&data[..])
         .wrap_err("failed to decode a Redelegation")?;
+    check_max_total_delegated(ctx, &dest_validator, amount)?;
     ctx.redelegate_tokens(&owner, &src_validator, &dest_validator, amount)
 }
 
+/// Check that a redelegation doesn't raise the total amount delegated to the
+/// destination validator at the pipeline epoch above the validator's cap, if
+/// it has set one
+fn check_max_total_delegated(
+    ctx: &Ctx,
+    dest_validator: &Address,
+    amount: token::Amount,
+) -> TxResult {
+    let Some((max_total_delegated, total_delegated)) =
+        ctx.validator_delegation_cap_at_pipeline(dest_validator)?
+    else {
+        return Ok(());
+    };
+    let new_total_delegated = total_delegated.checked_add(amount);
+    if new_total_delegated.map_or(true, |total| total > max_total_delegated) {
+        return Err(Error::new(format!(
+            "The redelegation of {} to the validator {} would exceed its max \
+             total delegated amount {}, of which {} is already delegated",
+            amount.to_string_native(),
+            dest_validator,
+            max_total_delegated.to_string_native(),
+            total_delegated.to_string_native()
+        )));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::cell::RefCell;
@@ -27,10 +55,11 @@ mod tests {
 
     use namada::core::dec::Dec;
     use namada::ledger::gas::VpGasMeter;
-    use namada::ledger::pos::{OwnedPosParams, PosVP};
+    use namada::ledger::pos::{OwnedPosParams, PosParams, PosVP};
     use namada::proof_of_stake::storage::{
         bond_handle, read_consensus_validator_set_addresses_with_stake,
         read_total_stake, read_validator_stake, unbond_handle,
+        validator_deltas_handle, write_validator_max_total_delegated,
     };
     use namada::proof_of_stake::types::{GenesisValidator, WeightedValidator};
     use namada::validity_predicate::VpSentinel;
@@ -64,76 +93,98 @@ mod tests {
         pos_params in arb_pos_params(None)) {
             test_tx_redelegate_aux(initial_stake, redelegation, key, pos_params).unwrap()
         }
+
+        /// Test that a redelegation of more tokens than are bonded to the
+        /// source validator is rejected.
+        #[test]
+        fn test_tx_redelegate_exceeding_bond(
+        (initial_stake, redelegation) in arb_initial_stake_and_redelegation(),
+        excess in token::testing::arb_amount_non_zero_ceiled(1_000_000),
+        pos_params in arb_pos_params(None)) {
+            test_tx_redelegate_exceeding_bond_aux(
+                initial_stake,
+                redelegation,
+                excess,
+                pos_params,
+            ).unwrap()
+        }
+
+        /// Test that redelegating tokens that have been redelegated within
+        /// the slash processing window (a chained redelegation) is rejected.
+        #[test]
+        fn test_tx_redelegate_chained(
+        (initial_stake, redelegation) in arb_initial_stake_and_redelegation(),
+        key in arb_common_keypair(),
+        pos_params in arb_pos_params(None)) {
+            test_tx_redelegate_chained_aux(
+                initial_stake,
+                redelegation,
+                key,
+                pos_params,
+            ).unwrap()
+        }
     }
 
-    // TODO: more assertions needed!!
-    fn test_tx_redelegate_aux(
+    fn test_tx_redelegate_exceeding_bond_aux(
         initial_stake: token::Amount,
         redelegation: transaction::pos::Redelegation,
-        key: key::common::SecretKey,
+        excess: token::Amount,
         pos_params: OwnedPosParams,
     ) -> TxResult {
-        // Remove the validator stake threshold for simplicity
-        let pos_params = OwnedPosParams {
-            validator_stake_threshold: token::Amount::zero(),
-            ..pos_params
-        };
-        dbg!(&initial_stake, &redelegation);
+        init_delegation(initial_stake, &redelegation, pos_params)?;
 
-        let consensus_key_1 = key::testing::keypair_1().ref_to();
-        let consensus_key_2 = key::testing::keypair_2().ref_to();
-        let protocol_key = key::testing::keypair_2().ref_to();
-        let eth_cold_key = key::testing::keypair_3().ref_to();
-        let eth_hot_key = key::testing::keypair_4().ref_to();
-        let commission_rate = Dec::new(5, 2).expect("Cannot fail");
-        let max_commission_rate_change = Dec::new(1, 2).expect("Cannot fail");
+        let amount = initial_stake + excess;
+        let result = ctx().redelegate_tokens(
+            &redelegation.owner,
+            &redelegation.src_validator,
+            &redelegation.dest_validator,
+            amount,
+        );
+        assert!(
+            result.is_err(),
+            "Redelegation of more than the bonded amount must be rejected"
+        );
+        Ok(())
+    }
 
-        let genesis_validators = [
-            GenesisValidator {
-                address: redelegation.src_validator.clone(),
-                tokens: token::Amount::zero(),
-                consensus_key: consensus_key_1,
-                protocol_key: protocol_key.clone(),
-                eth_cold_key: eth_cold_key.clone(),
-                eth_hot_key: eth_hot_key.clone(),
-                commission_rate,
-                max_commission_rate_change,
-                metadata: Default::default(),
-            },
-            GenesisValidator {
-                address: redelegation.dest_validator.clone(),
-                tokens: token::Amount::zero(),
-                consensus_key: consensus_key_2,
-                protocol_key,
-                eth_cold_key,
-                eth_hot_key,
-                commission_rate,
-                max_commission_rate_change,
-                metadata: Default::default(),
-            },
-        ];
+    fn test_tx_redelegate_chained_aux(
+        initial_stake: token::Amount,
+        redelegation: transaction::pos::Redelegation,
+        key: key::common::SecretKey,
+        pos_params: OwnedPosParams,
+    ) -> TxResult {
+        init_delegation(initial_stake, &redelegation, pos_params)?;
 
-        let pos_params =
-            init_pos(&genesis_validators[..], &pos_params, Epoch(0));
+        let tx_data = redelegation.serialize_to_vec();
+        let mut tx = Tx::new(ChainId::default(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(tx_data)
+            .sign_wrapper(key);
+        apply_tx(ctx(), tx)?;
+        tx_host_env::commit_tx_and_block();
 
-        let native_token = tx_host_env::with(|tx_env| {
-            let native_token = tx_env.state.in_mem().native_token.clone();
-            let owner = &redelegation.owner;
-            tx_env.spawn_accounts([owner]);
+        // Try to redelegate the redelegated tokens back to the original
+        // validator before the slash processing window has passed
+        let result = ctx().redelegate_tokens(
+            &redelegation.owner,
+            &redelegation.dest_validator,
+            &redelegation.src_validator,
+            redelegation.amount,
+        );
+        assert!(result.is_err(), "Chained redelegation must be rejected");
+        Ok(())
+    }
 
-            // First, credit the delegator with the initial stake,
-            // before we initialize the bond below
-            tx_env.credit_tokens(owner, &native_token, initial_stake);
-            native_token
-        });
+    fn test_tx_redelegate_aux(
+        initial_stake: token::Amount,
+        redelegation: transaction::pos::Redelegation,
+        key: key::common::SecretKey,
+        pos_params: OwnedPosParams,
+    ) -> TxResult {
+        dbg!(&initial_stake, &redelegation);
 
-        // Create the initial bond.
-        ctx().bond_tokens(
-            Some(&redelegation.owner),
-            &redelegation.src_validator,
-            initial_stake,
-        )?;
-        tx_host_env::commit_tx_and_block();
+        let (pos_params, native_token) =
+            init_delegation(initial_stake, &redelegation, pos_params)?;
 
         let tx_code = vec![];
         let tx_data = redelegation.serialize_to_vec();
@@ -186,7 +237,7 @@ mod tests {
                     .get_delta_val(ctx(), Epoch(epoch))?,
             );
             epoched_dest_bonds_pre.push(
-                bond_handle(&redelegation.owner, &redelegation.src_validator)
+                bond_handle(&redelegation.owner, &redelegation.dest_validator)
                     .get_delta_val(ctx(), Epoch(epoch))?,
             );
             epoched_validator_set_pre.push(
@@ -320,7 +371,7 @@ mod tests {
             ctx().read(&pos_balance_key)?.unwrap();
         assert_eq!(
             pos_balance_pre, pos_balance_post,
-            "Unbonding should not affect PoS system balance"
+            "Redelegation should not affect PoS system balance"
         );
 
         // Check that no unbonds exist
@@ -361,6 +412,35 @@ mod tests {
             );
         }
 
+        // Check validator deltas - the redelegated amount is moved from the
+        // source to the destination validator at the pipeline offset
+        for epoch in 0..pos_params.withdrawable_epoch_offset() {
+            let (exp_src_delta, exp_dest_delta) =
+                if epoch == pos_params.pipeline_len {
+                    (
+                        Some((initial_stake - redelegation.amount).change()),
+                        Some(redelegation.amount.change()),
+                    )
+                } else {
+                    (None, None)
+                };
+
+            assert_eq!(
+                validator_deltas_handle(&redelegation.src_validator)
+                    .get_delta_val(ctx(), Epoch(epoch))?,
+                exp_src_delta,
+                "The source validator deltas must be decremented by the \
+                 redelegated amount, checking epoch {epoch}"
+            );
+            assert_eq!(
+                validator_deltas_handle(&redelegation.dest_validator)
+                    .get_delta_val(ctx(), Epoch(epoch))?,
+                exp_dest_delta,
+                "The destination validator deltas must be incremented by the \
+                 redelegated amount, checking epoch {epoch}"
+            );
+        }
+
         // Use the tx_env to run PoS VP
         let tx_env = tx_host_env::take();
         let gas_meter = RefCell::new(VpGasMeter::new_from_tx_meter(
@@ -378,6 +458,126 @@ mod tests {
         Ok(())
     }
 
+    /// Apply the redelegation tx to a destination validator with the given
+    /// cap on the total amount delegated to it
+    fn apply_redelegate_tx_with_max_total_delegated(
+        max_total_delegated: token::Amount,
+    ) -> TxResult {
+        let redelegation = transaction::pos::Redelegation {
+            src_validator: address::testing::established_address_1(),
+            dest_validator: address::testing::established_address_2(),
+            owner: address::testing::established_address_3(),
+            amount: token::Amount::native_whole(10),
+        };
+        init_delegation(
+            token::Amount::native_whole(100),
+            &redelegation,
+            OwnedPosParams::default(),
+        )?;
+        write_validator_max_total_delegated(
+            ctx(),
+            &redelegation.dest_validator,
+            max_total_delegated,
+        )?;
+        tx_host_env::commit_tx_and_block();
+
+        let mut tx = Tx::new(ChainId::default(), None);
+        tx.add_code(vec![], None)
+            .add_serialized_data(redelegation.serialize_to_vec())
+            .sign_wrapper(key::testing::keypair_1());
+        apply_tx(ctx(), tx)
+    }
+
+    #[test]
+    fn test_tx_redelegate_above_max_total_delegated() {
+        let max_total_delegated =
+            token::Amount::native_whole(10) - token::Amount::from(1);
+        let err =
+            apply_redelegate_tx_with_max_total_delegated(max_total_delegated)
+                .expect_err("The redelegation must be rejected");
+        assert!(err.to_string().contains("max total delegated amount"));
+    }
+
+    #[test]
+    fn test_tx_redelegate_at_max_total_delegated() {
+        apply_redelegate_tx_with_max_total_delegated(
+            token::Amount::native_whole(10),
+        )
+        .unwrap();
+    }
+
+    /// Initialize PoS with the source and destination validators of the
+    /// given redelegation and bond the `initial_stake` from its owner to the
+    /// source validator. Returns the PoS params and the native token address.
+    fn init_delegation(
+        initial_stake: token::Amount,
+        redelegation: &transaction::pos::Redelegation,
+        pos_params: OwnedPosParams,
+    ) -> EnvResult<(PosParams, Address)> {
+        // Remove the validator stake threshold for simplicity
+        let pos_params = OwnedPosParams {
+            validator_stake_threshold: token::Amount::zero(),
+            ..pos_params
+        };
+
+        let consensus_key_1 = key::testing::keypair_1().ref_to();
+        let consensus_key_2 = key::testing::keypair_2().ref_to();
+        let protocol_key = key::testing::keypair_2().ref_to();
+        let eth_cold_key = key::testing::keypair_3().ref_to();
+        let eth_hot_key = key::testing::keypair_4().ref_to();
+        let commission_rate = Dec::new(5, 2).expect("Cannot fail");
+        let max_commission_rate_change = Dec::new(1, 2).expect("Cannot fail");
+
+        let genesis_validators = [
+            GenesisValidator {
+                address: redelegation.src_validator.clone(),
+                tokens: token::Amount::zero(),
+                consensus_key: consensus_key_1,
+                protocol_key: protocol_key.clone(),
+                eth_cold_key: eth_cold_key.clone(),
+                eth_hot_key: eth_hot_key.clone(),
+                commission_rate,
+                max_commission_rate_change,
+                metadata: Default::default(),
+            },
+            GenesisValidator {
+                address: redelegation.dest_validator.clone(),
+                tokens: token::Amount::zero(),
+                consensus_key: consensus_key_2,
+                protocol_key,
+                eth_cold_key,
+                eth_hot_key,
+                commission_rate,
+                max_commission_rate_change,
+                metadata: Default::default(),
+            },
+        ];
+
+        let pos_params =
+            init_pos(&genesis_validators[..], &pos_params, Epoch(0));
+
+        let native_token = tx_host_env::with(|tx_env| {
+            let native_token = tx_env.state.in_mem().native_token.clone();
+            let owner = &redelegation.owner;
+            tx_env.spawn_accounts([owner]);
+
+            // First, credit the delegator with the initial stake,
+            // before we initialize the bond below
+            tx_env.credit_tokens(owner, &native_token, initial_stake);
+            native_token
+        });
+
+        // Create the initial bond.
+        ctx().bond_tokens(
+            Some(&redelegation.owner),
+            &redelegation.src_validator,
+            initial_stake,
+        )?;
+        tx_host_env::commit_tx_and_block();
+
+        Ok((pos_params, native_token))
+    }
+
     /// Generates an initial validator stake and a redelegation, while making
     /// sure that the `initial_stake >= redelegation.amount`.
     fn arb_initial_stake_and_redelegation()
@@ -30,7 +30,7 @@ mod tests {
 
     use namada::core::dec::Dec;
     use namada::ledger::gas::VpGasMeter;
-    use namada::ledger::pos::{OwnedPosParams, PosVP};
+    use namada::ledger::pos::{bond_events, OwnedPosParams, PosVP};
     use namada::proof_of_stake::storage::{
         bond_handle, read_consensus_validator_set_addresses_with_stake,
         read_total_stake, read_validator_stake, unbond_handle,
@@ -197,6 +197,30 @@ mod tests {
         // Apply the unbond tx
         apply_tx(ctx(), signed_tx)?;
 
+        // Check the unbond event emitted by the protocol for the tx
+        let events: Vec<event::BondEvent> = tx_host_env::with(|tx_env| {
+            let keys_changed = tx_env.state.write_log().get_keys();
+            bond_events(&tx_env.state, &keys_changed)
+                .unwrap()
+                .iter()
+                .map(|event| event::BondEvent::try_from(event).unwrap())
+                .collect()
+        });
+        let pipeline_epoch = ctx().get_block_epoch()? + pos_params.pipeline_len;
+        assert_eq!(
+            events,
+            vec![event::BondEvent {
+                kind: event::BondEventKind::Unbond,
+                validator: unbond.validator.clone(),
+                source: unbond
+                    .source
+                    .clone()
+                    .unwrap_or_else(|| unbond.validator.clone()),
+                amount: unbond.amount,
+                epoch: pipeline_epoch,
+            }]
+        );
+
         // Read the data after the unbond tx is executed.
         // The following storage keys should be updated:
         //     - `#{PoS}/validator/#{validator}/deltas`
@@ -23,11 +23,12 @@ use proof_of_stake::storage_key::{
     is_pos_key, is_rewards_counter_key, is_total_consensus_stake_key,
     is_total_deltas_key, is_unbond_key, is_validator_address_raw_hash_key,
     is_validator_addresses_key, is_validator_commission_rate_key,
-    is_validator_deltas_key, is_validator_eth_cold_key_key,
-    is_validator_eth_hot_key_key, is_validator_max_commission_rate_change_key,
-    is_validator_metadata_key, is_validator_redelegations_key,
-    is_validator_set_positions_key, is_validator_state_epoched_meta_key,
-    is_validator_state_key, is_validator_total_bond_or_unbond_key,
+    is_validator_consensus_key_key, is_validator_deltas_key,
+    is_validator_eth_cold_key_key, is_validator_eth_hot_key_key,
+    is_validator_max_commission_rate_change_key, is_validator_metadata_key,
+    is_validator_redelegations_key, is_validator_set_positions_key,
+    is_validator_state_epoched_meta_key, is_validator_state_key,
+    is_validator_total_bond_or_unbond_key,
 };
 
 enum KeyType<'a> {
@@ -185,6 +186,14 @@ fn validate_pos_changes(
         false
     };
 
+    // Consensus key changes must be signed by the validator
+    let is_valid_consensus_key_change = || {
+        if let Some(validator) = is_validator_consensus_key_key(key) {
+            return validator == owner && **valid_sig;
+        }
+        false
+    };
+
     // Metadata changes must be signed by the validator whose
     // metadata is manipulated
     let is_valid_metadata_change = || {
@@ -324,6 +333,7 @@ fn validate_pos_changes(
         || is_valid_reward_claim()
         || is_valid_redelegation()
         || is_valid_commission_rate_change()
+        || is_valid_consensus_key_change()
         || is_valid_metadata_change()
         || is_valid_become_validator()
         || **valid_sig)
@@ -787,6 +797,7 @@ mod tests {
                     Some("discord".to_owned()),
                     Some("avatar".to_owned()),
                     Some(Dec::new(6, 2).unwrap()),
+                    Some(token::Amount::native_whole(1_000)),
                 )
                 .unwrap();
         });
@@ -1073,6 +1084,7 @@ mod tests {
                     Some("discord".to_owned()),
                     Some("avatar".to_owned()),
                     Some(Dec::new(6, 2).unwrap()),
+                    Some(token::Amount::native_whole(1_000)),
                 )
                 .unwrap();
         });